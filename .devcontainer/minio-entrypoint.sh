@@ -18,4 +18,22 @@ mc alias set local $AWS_ENDPOINT_URL $MINIO_ROOT_USER $MINIO_ROOT_PASSWORD
 mc mb local/$AWS_S3_TEST_BUCKET
 mc mb local/${AWS_S3_TEST_BUCKET}2
 
+# create a user, which can only access the second bucket
+cat > /tmp/bucket2-policy.json <<POLICY
+{
+  "Version": "2012-10-17",
+  "Statement": [
+    {
+      "Effect": "Allow",
+      "Action": ["s3:*"],
+      "Resource": ["arn:aws:s3:::${AWS_S3_TEST_BUCKET}2", "arn:aws:s3:::${AWS_S3_TEST_BUCKET}2/*"]
+    }
+  ]
+}
+POLICY
+
+mc admin user add local $AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID $AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY
+mc admin policy create local bucket2-policy /tmp/bucket2-policy.json
+mc admin policy attach local bucket2-policy --user $AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID
+
 wait $minio_pid
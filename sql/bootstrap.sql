@@ -9,8 +9,26 @@ BEGIN
     END IF;
 END $$;
 
--- error if the schema already exists
-CREATE SCHEMA parquet;
-REVOKE ALL ON SCHEMA parquet FROM public;
-GRANT USAGE ON SCHEMA parquet TO public;
-GRANT EXECUTE ON ALL FUNCTIONS IN SCHEMA parquet TO public;
+-- the objects of the extension are created in the schema of CREATE EXTENSION pg_parquet SCHEMA,
+-- e.g. pgparquet, and in schema parquet by default, i.e. when the schema is public
+DO $$
+BEGIN
+    IF '@extschema@' <> 'public' THEN
+        RETURN;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = 'parquet') THEN
+        RAISE EXCEPTION 'schema "parquet" already exists'
+            USING ERRCODE = 'duplicate_schema',
+                  DETAIL = 'pg_parquet creates its functions in schema "parquet" by default.',
+                  HINT = 'Install the extension into another schema, e.g. CREATE EXTENSION pg_parquet SCHEMA pgparquet, or rename the existing schema.';
+    END IF;
+
+    CREATE SCHEMA parquet;
+    REVOKE ALL ON SCHEMA parquet FROM public;
+    GRANT USAGE ON SCHEMA parquet TO public;
+
+    -- the rest of the script creates its objects in the first schema of the search path,
+    -- which is restored after the script
+    PERFORM set_config('search_path', 'parquet, pg_temp', true);
+END $$;
@@ -0,0 +1,284 @@
+-- parquet.metadata() returns bloom filter offset and length of column chunks,
+-- and it skips the files which cannot be read with on_error 'skip'
+DROP FUNCTION parquet."metadata"(TEXT);
+CREATE  FUNCTION parquet."metadata"(
+	"uri" TEXT,
+	"on_error" TEXT DEFAULT 'raise',
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"row_group_id" BIGINT,
+	"row_group_num_rows" BIGINT,
+	"row_group_num_columns" BIGINT,
+	"row_group_bytes" BIGINT,
+	"column_id" BIGINT,
+	"file_offset" BIGINT,
+	"num_values" BIGINT,
+	"path_in_schema" TEXT,
+	"type_name" TEXT,
+	"stats_null_count" BIGINT,
+	"stats_distinct_count" BIGINT,
+	"stats_min" TEXT,
+	"stats_max" TEXT,
+	"compression" TEXT,
+	"encodings" TEXT,
+	"index_page_offset" BIGINT,
+	"dictionary_page_offset" BIGINT,
+	"data_page_offset" BIGINT,
+	"total_compressed_size" BIGINT,
+	"total_uncompressed_size" BIGINT,
+	"bloom_filter_offset" BIGINT,
+	"bloom_filter_length" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'metadata_wrapper';
+
+-- parquet.schema() returns one row per field with its full path and the suggested postgres type,
+-- followed by the previous columns, and it skips the files which cannot be read with on_error 'skip'
+DROP FUNCTION parquet."schema"(TEXT);
+CREATE  FUNCTION parquet."schema"(
+	"uri" TEXT,
+	"on_error" TEXT DEFAULT 'raise',
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"field_path" TEXT[],
+	"parquet_type" TEXT,
+	"logical_type" TEXT,
+	"repetition" TEXT,
+	"postgres_type" TEXT,
+	"nullable" BOOL,
+	"field_id" INT,
+	"name" TEXT,
+	"type_name" TEXT,
+	"type_length" TEXT,
+	"repetition_type" TEXT,
+	"num_children" INT,
+	"converted_type" TEXT,
+	"scale" INT,
+	"precision" INT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'schema_wrapper';
+
+-- parquet.temp() returns the uri of a temporary parquet file of the session
+CREATE  FUNCTION parquet."temp"(
+	"name" TEXT
+) RETURNS TEXT
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'temp_wrapper';
+
+-- parquet.temp_list() lists the temporary parquet files of the session
+CREATE  FUNCTION parquet."temp_list"() RETURNS TABLE (
+	"name" TEXT,
+	"uri" TEXT,
+	"file_size" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'temp_list_wrapper';
+
+-- parquet.last_copy_files() lists the files written by the last COPY TO of the session
+CREATE  FUNCTION parquet."last_copy_files"() RETURNS TABLE (
+	"uri" TEXT,
+	"row_count" BIGINT,
+	"size_bytes" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'last_copy_files_wrapper';
+
+-- parquet.object_store_cache() lists the object stores and http clients cached in the session
+CREATE  FUNCTION parquet."object_store_cache"() RETURNS TABLE (
+	"scheme" TEXT,
+	"bucket" TEXT,
+	"created_at" timestamp with time zone,
+	"expire_at" timestamp with time zone,
+	"hit_count" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'object_store_cache_wrapper';
+
+-- parquet.object_store_cache_clear() removes the object stores and http clients cached in the session
+CREATE  FUNCTION parquet."object_store_cache_clear"() RETURNS void
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'object_store_cache_clear_wrapper';
+
+-- parquet.file_metadata() skips the files which cannot be read with on_error 'skip', and returns
+-- the total byte size and the footer of the file
+DROP FUNCTION parquet."file_metadata"(TEXT);
+CREATE  FUNCTION parquet."file_metadata"(
+	"uri" TEXT,
+	"on_error" TEXT DEFAULT 'raise',
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"created_by" TEXT,
+	"num_rows" BIGINT,
+	"num_row_groups" BIGINT,
+	"format_version" TEXT,
+	"total_byte_size" BIGINT,
+	"footer_size" BIGINT,
+	"footer_encrypted" BOOL
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'file_metadata_wrapper';
+
+-- parquet.async_jobs is the registry of the jobs, which are run by background workers
+CREATE TABLE parquet.async_jobs (
+    job_id bigserial PRIMARY KEY,
+    job_type text NOT NULL,
+    command text NOT NULL,
+    role_name name NOT NULL DEFAULT current_user,
+    status text NOT NULL DEFAULT 'pending',
+    worker_pid int,
+    rows_processed bigint,
+    error text,
+    submitted_at timestamptz NOT NULL DEFAULT now(),
+    started_at timestamptz,
+    finished_at timestamptz
+);
+
+ALTER TABLE parquet.async_jobs ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY async_jobs_of_role ON parquet.async_jobs
+    USING (role_name = current_user)
+    WITH CHECK (role_name = current_user);
+
+-- the jobs are only written by the functions of pg_parquet as the owner of the table
+GRANT SELECT ON parquet.async_jobs TO public;
+
+-- parquet.job_status() returns the status of an async job
+CREATE FUNCTION parquet.job_status(job_id bigint) RETURNS TABLE (
+    job_id bigint,
+    job_type text,
+    status text,
+    rows_processed bigint,
+    error text,
+    submitted_at timestamptz,
+    started_at timestamptz,
+    finished_at timestamptz
+)
+STRICT
+LANGUAGE sql
+AS $$
+    SELECT j.job_id, j.job_type, j.status, j.rows_processed, j.error,
+           j.submitted_at, j.started_at, j.finished_at
+    FROM parquet.async_jobs j
+    WHERE j.job_id = $1;
+$$;
+
+-- parquet.cancel_job() cancels a pending or running async job
+CREATE  FUNCTION parquet."cancel_job"(
+	"job_id" bigint
+) RETURNS bool
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'cancel_job_wrapper';
+
+-- parquet.export_async() runs COPY (query) TO uri in a background worker
+CREATE  FUNCTION parquet."export_async"(
+	"query" TEXT,
+	"uri" TEXT,
+	"options" jsonb DEFAULT '{}'
+) RETURNS bigint
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'export_async_wrapper';
+
+-- parquet.import_async() runs COPY table FROM uri in a background worker
+CREATE  FUNCTION parquet."import_async"(
+	"table_name" regclass,
+	"uri" TEXT,
+	"options" jsonb DEFAULT '{}'
+) RETURNS bigint
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'import_async_wrapper';
+
+-- parquet column chunk metadata function
+CREATE  FUNCTION parquet."column_chunk_metadata"(
+	"uri" TEXT,
+	"on_error" TEXT DEFAULT 'raise',
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"row_group_id" BIGINT,
+	"column_id" BIGINT,
+	"path_in_schema" TEXT,
+	"num_rows" BIGINT,
+	"num_values" BIGINT,
+	"num_data_pages" BIGINT,
+	"max_page_num_rows" BIGINT,
+	"max_page_compressed_size" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'column_chunk_metadata_wrapper';
+
+-- parquet column stats function
+CREATE  FUNCTION parquet."column_stats"(
+	"uri" TEXT,
+	"on_error" TEXT DEFAULT 'raise',
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"column_id" BIGINT,
+	"path_in_schema" TEXT,
+	"type_name" TEXT,
+	"stats_min" TEXT,
+	"stats_max" TEXT,
+	"stats_null_count" BIGINT,
+	"stats_distinct_count" BIGINT,
+	"num_values" BIGINT,
+	"total_compressed_size" BIGINT,
+	"total_uncompressed_size" BIGINT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'column_stats_wrapper';
+
+-- parquet.kv_metadata() accepts the options, which override the configuration of the session
+DROP FUNCTION parquet."kv_metadata"(TEXT);
+CREATE  FUNCTION parquet."kv_metadata"(
+	"uri" TEXT,
+	"options" JSONB DEFAULT '{}'
+) RETURNS TABLE (
+	"uri" TEXT,
+	"key" BYTEA,
+	"value" BYTEA
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'kv_metadata_wrapper';
+
+-- parquet.validate() checks whether the file can be loaded, without raising its error
+CREATE  FUNCTION parquet."validate"(
+	"uri" TEXT,
+	"deep" bool DEFAULT false
+) RETURNS TABLE (
+	"ok" bool,
+	"num_rows" BIGINT,
+	"num_row_groups" INT,
+	"error" TEXT
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'validate_wrapper';
+
+-- parquet.last_copy_stats() sums up the files written by the last COPY TO of the session
+CREATE  FUNCTION parquet."last_copy_stats"() RETURNS TABLE (
+	"files" INT,
+	"rows" BIGINT,
+	"bytes" BIGINT,
+	"duration" interval
+)
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'last_copy_stats_wrapper';
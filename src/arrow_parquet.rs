@@ -1,9 +1,35 @@
+pub(crate) mod abortable_object_writer;
 pub(crate) mod arrow_to_pg;
 pub(crate) mod arrow_utils;
+pub(crate) mod cast_plan_cache;
 pub(crate) mod compression;
+pub(crate) mod copy_files;
+pub(crate) mod duplicate_columns;
+pub(crate) mod extra_columns;
+pub(crate) mod field_ids;
+pub(crate) mod geoparquet_covering;
+pub(crate) mod if_exists;
+pub(crate) mod infinite_timestamps;
+pub(crate) mod list_coercion;
 pub(crate) mod match_by;
+pub(crate) mod missing_columns;
+pub(crate) mod multidim_arrays;
+pub(crate) mod nan_handling;
+pub(crate) mod numeric_overflow;
+pub(crate) mod on_error;
+pub(crate) mod page_pruning;
+pub(crate) mod parallel_decoder;
 pub(crate) mod parquet_reader;
 pub(crate) mod parquet_writer;
 pub(crate) mod pg_to_arrow;
+pub(crate) mod row_group_selection;
 pub(crate) mod schema_parser;
+pub(crate) mod statistics;
+pub(crate) mod stream_writer;
+pub(crate) mod temp_files;
+pub(crate) mod timestamptz_to_timestamp;
+pub(crate) mod unique_file_names;
+pub(crate) mod unsupported_type;
+pub(crate) mod uri_prefixes;
 pub(crate) mod uri_utils;
+pub(crate) mod writer_version;
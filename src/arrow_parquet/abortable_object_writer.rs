@@ -0,0 +1,101 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use bytes::Bytes;
+use futures::{future::BoxFuture, lock::Mutex};
+use object_store::buffered::BufWriter;
+use parquet::{arrow::async_writer::AsyncFileWriter, errors::ParquetError};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use crate::{
+    arrow_parquet::uri_utils::{redact_uri, redact_uri_secrets},
+    PG_BACKEND_TOKIO_RUNTIME,
+};
+
+// AbortableObjectWriter writes the parquet file to the object store like ParquetObjectWriter
+// of the parquet crate. Its buffered writer is shared with an ObjectWriterAbortHandle, which
+// aborts the in-progress multipart upload e.g. when the COPY is canceled.
+pub(crate) struct AbortableObjectWriter {
+    buf_writer: Arc<Mutex<BufWriter>>,
+    completing: Arc<AtomicBool>,
+    // uri of the file, which is reported when the upload cannot be aborted
+    uri: Url,
+}
+
+impl AbortableObjectWriter {
+    pub(crate) fn new(buf_writer: BufWriter, uri: &Url) -> Self {
+        Self {
+            buf_writer: Arc::new(Mutex::new(buf_writer)),
+            completing: Arc::new(AtomicBool::new(false)),
+            uri: uri.clone(),
+        }
+    }
+
+    pub(crate) fn abort_handle(&self) -> ObjectWriterAbortHandle {
+        ObjectWriterAbortHandle {
+            buf_writer: self.buf_writer.clone(),
+            completing: self.completing.clone(),
+            uri: self.uri.clone(),
+        }
+    }
+}
+
+impl AsyncFileWriter for AbortableObjectWriter {
+    fn write(&mut self, bs: Bytes) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        Box::pin(async {
+            self.buf_writer
+                .lock()
+                .await
+                .put(bs)
+                .await
+                .map_err(|e| ParquetError::External(Box::new(e)))
+        })
+    }
+
+    fn complete(&mut self) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        self.completing.store(true, Ordering::Relaxed);
+
+        Box::pin(async {
+            self.buf_writer
+                .lock()
+                .await
+                .shutdown()
+                .await
+                .map_err(|e| ParquetError::External(Box::new(e)))
+        })
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ObjectWriterAbortHandle {
+    buf_writer: Arc<Mutex<BufWriter>>,
+    completing: Arc<AtomicBool>,
+    uri: Url,
+}
+
+impl ObjectWriterAbortHandle {
+    // abort aborts the multipart upload of the file, so that its uploaded parts do not linger
+    // in the object store. Nothing is uploaded yet if the file is smaller than a part. The
+    // upload cannot be aborted once it is completing, hence it returns false if the object
+    // might have been written.
+    pub(crate) fn abort(&self) -> bool {
+        if self.completing.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        PG_BACKEND_TOKIO_RUNTIME
+            .block_on(async { self.buf_writer.lock().await.abort().await })
+            .unwrap_or_else(|e| {
+                pgrx::warning!(
+                    "failed to abort upload of {}: {}",
+                    redact_uri(&self.uri),
+                    redact_uri_secrets(&e.to_string(), &self.uri)
+                )
+            });
+
+        true
+    }
+}
@@ -1,13 +1,18 @@
 use arrow::array::{
-    Array, ArrayData, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float32Array,
-    Float64Array, Int16Array, Int32Array, Int64Array, ListArray, MapArray, StringArray,
-    StructArray, Time64MicrosecondArray, TimestampMicrosecondArray, UInt32Array,
+    make_array, Array, ArrayData, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array,
+    Decimal128Array, Decimal256Array, FixedSizeBinaryArray, Float32Array, Float64Array,
+    GenericListArray, Int16Array, Int32Array, Int64Array, LargeListArray, ListArray, MapArray,
+    OffsetSizeTrait, StringArray, StructArray, Time64MicrosecondArray, TimestampMicrosecondArray,
+    UInt32Array,
 };
 use arrow_schema::{DataType, TimeUnit};
 use context::ArrowToPgAttributeContext;
 use pgrx::{
-    datum::{Date, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone},
-    pg_sys::{Datum, Oid, CHAROID, TEXTOID, TIMEOID},
+    datum::{Date, Interval, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone},
+    pg_sys::{
+        Datum, Oid, CASHOID, CHAROID, CIDROID, DATEOID, INETOID, INTERVALOID, MACADDROID, TEXTOID,
+        TIMEOID, TIMESTAMPOID, TIMESTAMPTZOID, TIMETZOID,
+    },
     prelude::PgHeapTuple,
     AllocatedByRust, AnyNumeric, IntoDatum,
 };
@@ -20,7 +25,12 @@ use crate::{
     type_compat::{
         fallback_to_text::{reset_fallback_to_text_context, FallbackToText},
         geometry::{is_postgis_geometry_type, Geometry},
+        geometry_crs::SridConvertedGeometry,
         map::{is_map_type, reset_map_type_context, Map},
+        money::Money,
+        multidim_array::{reshape_array_datum, ArrayDims},
+        network_address::{Cidr, Inet, MacAddr, MacAddr8},
+        server_encoding::ServerEncodedText,
     },
 };
 
@@ -34,18 +44,47 @@ pub(crate) mod fallback_to_text;
 pub(crate) mod float4;
 pub(crate) mod float8;
 pub(crate) mod geometry;
+pub(crate) mod inet;
 pub(crate) mod int2;
 pub(crate) mod int4;
 pub(crate) mod int8;
+pub(crate) mod interval;
+pub(crate) mod macaddr;
 pub(crate) mod map;
+pub(crate) mod money;
 pub(crate) mod numeric;
 pub(crate) mod oid;
+pub(crate) mod primitive_batch;
 pub(crate) mod text;
 pub(crate) mod time;
 pub(crate) mod timestamp;
 pub(crate) mod timestamptz;
 pub(crate) mod timetz;
 
+// CONVERSION_ROW_NUMBER is the row number in the parquet file of the row being converted,
+// which is reported by the conversion errors.
+static mut CONVERSION_ROW_NUMBER: usize = 0;
+
+pub(crate) fn set_conversion_row_number(row_number: usize) {
+    unsafe { CONVERSION_ROW_NUMBER = row_number };
+}
+
+pub(crate) fn conversion_row_number() -> usize {
+    unsafe { CONVERSION_ROW_NUMBER }
+}
+
+// CONVERSION_ATTRIBUTE_IDX is the index of the attribute being converted, if any, which is
+// reported by the error context of COPY FROM.
+static mut CONVERSION_ATTRIBUTE_IDX: Option<usize> = None;
+
+pub(crate) fn set_conversion_attribute_idx(attribute_idx: Option<usize>) {
+    unsafe { CONVERSION_ATTRIBUTE_IDX = attribute_idx };
+}
+
+pub(crate) fn conversion_attribute_idx() -> Option<usize> {
+    unsafe { CONVERSION_ATTRIBUTE_IDX }
+}
+
 pub(crate) trait ArrowArrayToPgType<T: IntoDatum>: From<ArrayData> {
     fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<T>;
 }
@@ -54,7 +93,10 @@ pub(crate) fn to_pg_datum(
     attribute_array: ArrayData,
     attribute_context: &ArrowToPgAttributeContext,
 ) -> Option<Datum> {
-    if matches!(attribute_array.data_type(), DataType::List(_)) {
+    if matches!(
+        attribute_array.data_type(),
+        DataType::List(_) | DataType::LargeList(_)
+    ) {
         to_pg_array_datum(attribute_array, attribute_context)
     } else {
         to_pg_nonarray_datum(attribute_array, attribute_context)
@@ -75,6 +117,8 @@ fn to_pg_nonarray_datum(
     primitive_array: ArrayData,
     attribute_context: &ArrowToPgAttributeContext,
 ) -> Option<Datum> {
+    let primitive_array = rescale_timestamps_to_microseconds(primitive_array);
+
     match attribute_context.data_type() {
         DataType::Float32 => {
             to_pg_datum!(Float32Array, f32, primitive_array, attribute_context)
@@ -89,7 +133,11 @@ fn to_pg_nonarray_datum(
             to_pg_datum!(Int32Array, i32, primitive_array, attribute_context)
         }
         DataType::Int64 => {
-            to_pg_datum!(Int64Array, i64, primitive_array, attribute_context)
+            if attribute_context.typoid() == CASHOID {
+                to_pg_datum!(Int64Array, Money, primitive_array, attribute_context)
+            } else {
+                to_pg_datum!(Int64Array, i64, primitive_array, attribute_context)
+            }
         }
         DataType::UInt32 => {
             to_pg_datum!(UInt32Array, Oid, primitive_array, attribute_context)
@@ -101,7 +149,14 @@ fn to_pg_nonarray_datum(
             if attribute_context.typoid() == CHAROID {
                 to_pg_datum!(StringArray, i8, primitive_array, attribute_context)
             } else if attribute_context.typoid() == TEXTOID {
-                to_pg_datum!(StringArray, String, primitive_array, attribute_context)
+                to_pg_datum!(
+                    StringArray,
+                    ServerEncodedText,
+                    primitive_array,
+                    attribute_context
+                )
+            } else if attribute_context.typoid() == INTERVALOID {
+                to_pg_datum!(StringArray, Interval, primitive_array, attribute_context)
             } else {
                 reset_fallback_to_text_context(
                     attribute_context.typoid(),
@@ -118,14 +173,33 @@ fn to_pg_nonarray_datum(
         }
         DataType::Binary => {
             if attribute_context.is_geometry() {
-                to_pg_datum!(BinaryArray, Geometry, primitive_array, attribute_context)
+                let geometry_datum =
+                    to_pg_datum!(BinaryArray, Geometry, primitive_array, attribute_context);
+
+                geometry_datum.map(|geometry_datum| {
+                    attribute_context
+                        .geometry_srid_conversion()
+                        .apply(geometry_datum)
+                })
             } else {
                 to_pg_datum!(BinaryArray, Vec<u8>, primitive_array, attribute_context)
             }
         }
         DataType::Decimal128(_, _) => {
+            if attribute_context.typoid() == CASHOID {
+                to_pg_datum!(Decimal128Array, Money, primitive_array, attribute_context)
+            } else {
+                to_pg_datum!(
+                    Decimal128Array,
+                    AnyNumeric,
+                    primitive_array,
+                    attribute_context
+                )
+            }
+        }
+        DataType::Decimal256(_, _) => {
             to_pg_datum!(
-                Decimal128Array,
+                Decimal256Array,
                 AnyNumeric,
                 primitive_array,
                 attribute_context
@@ -151,29 +225,86 @@ fn to_pg_nonarray_datum(
                 )
             }
         }
-        DataType::Timestamp(TimeUnit::Microsecond, None) => {
-            to_pg_datum!(
-                TimestampMicrosecondArray,
-                Timestamp,
-                primitive_array,
-                attribute_context
-            )
+        DataType::Timestamp(_, None) => {
+            // local timestamps are coerced into timestamptz in the session timezone
+            if attribute_context.typoid() == TIMESTAMPTZOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    TimestampWithTimeZone,
+                    primitive_array,
+                    attribute_context
+                )
+            } else {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Timestamp,
+                    primitive_array,
+                    attribute_context
+                )
+            }
         }
-        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => {
-            to_pg_datum!(
-                TimestampMicrosecondArray,
-                TimestampWithTimeZone,
-                primitive_array,
-                attribute_context
-            )
+        DataType::Timestamp(_, Some(_)) => {
+            // UTC timestamps are coerced into timestamp and date in the session timezone
+            if attribute_context.typoid() == TIMESTAMPOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Timestamp,
+                    primitive_array,
+                    attribute_context
+                )
+            } else if attribute_context.typoid() == DATEOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Date,
+                    primitive_array,
+                    attribute_context
+                )
+            } else {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    TimestampWithTimeZone,
+                    primitive_array,
+                    attribute_context
+                )
+            }
         }
         DataType::Struct(_) => {
-            to_pg_datum!(
-                StructArray,
-                PgHeapTuple<AllocatedByRust>,
-                primitive_array,
-                attribute_context
-            )
+            if attribute_context.typoid() == INETOID {
+                to_pg_datum!(StructArray, Inet, primitive_array, attribute_context)
+            } else if attribute_context.typoid() == CIDROID {
+                to_pg_datum!(StructArray, Cidr, primitive_array, attribute_context)
+            } else if attribute_context.typoid() == TIMETZOID {
+                to_pg_datum!(
+                    StructArray,
+                    TimeWithTimeZone,
+                    primitive_array,
+                    attribute_context
+                )
+            } else {
+                to_pg_datum!(
+                    StructArray,
+                    PgHeapTuple<AllocatedByRust>,
+                    primitive_array,
+                    attribute_context
+                )
+            }
+        }
+        DataType::FixedSizeBinary(_) => {
+            if attribute_context.typoid() == MACADDROID {
+                to_pg_datum!(
+                    FixedSizeBinaryArray,
+                    MacAddr,
+                    primitive_array,
+                    attribute_context
+                )
+            } else {
+                to_pg_datum!(
+                    FixedSizeBinaryArray,
+                    MacAddr8,
+                    primitive_array,
+                    attribute_context
+                )
+            }
         }
         DataType::Map(_, _) => {
             reset_map_type_context(attribute_context.typoid());
@@ -190,13 +321,115 @@ fn to_pg_array_datum(
     list_array: ArrayData,
     attribute_context: &ArrowToPgAttributeContext,
 ) -> Option<Datum> {
-    let list_array: ListArray = list_array.into();
+    let list_array = if matches!(list_array.data_type(), DataType::LargeList(_)) {
+        let list_array: LargeListArray = list_array.into();
+
+        if list_array.is_null(0) {
+            return None;
+        }
+
+        // only the list at hand needs to fit into i32 offsets
+        let list_length = list_array.value_length(0);
 
-    if list_array.is_null(0) {
-        return None;
+        if list_length > i32::MAX as i64 {
+            panic!(
+                "list with {} elements is too large to read into an array",
+                list_length
+            );
+        }
+
+        list_array.value(0).to_data()
+    } else {
+        let list_array: ListArray = list_array.into();
+
+        if list_array.is_null(0) {
+            return None;
+        }
+
+        list_array.value(0).to_data()
+    };
+
+    let ndims = attribute_context.array_ndims();
+
+    if ndims == 1 {
+        return to_pg_flat_array_datum(list_array, attribute_context);
     }
 
-    let list_array = list_array.value(0).to_data();
+    let (list_array, dims) = flatten_multidim_list(list_array, ndims, attribute_context.name());
+
+    to_pg_flat_array_datum(list_array, attribute_context)
+        .map(|array_datum| reshape_array_datum(array_datum, &dims))
+}
+
+// flatten_multidim_list returns the elements of the nested lists, which are read into a
+// multidimensional array, and the dimensions of the array. The inner lists of each level
+// must have the same length.
+fn flatten_multidim_list(
+    list_array: ArrayData,
+    ndims: usize,
+    attribute_name: &str,
+) -> (ArrayData, ArrayDims) {
+    let mut dims = vec![list_array.len()];
+
+    let mut list_array = make_array(list_array);
+
+    for _ in 1..ndims {
+        if list_array.null_count() > 0 {
+            panic!(
+                "nested lists of column \"{}\" cannot be read into a multidimensional array, since an inner list is NULL",
+                attribute_name
+            );
+        }
+
+        let (values, lengths) = match list_array.data_type() {
+            DataType::List(_) => list_values_and_lengths(list_array.as_list::<i32>()),
+            DataType::LargeList(_) => list_values_and_lengths(list_array.as_list::<i64>()),
+            _ => unreachable!(),
+        };
+
+        let length = lengths.first().copied().unwrap_or(0);
+
+        if lengths.iter().any(|other_length| *other_length != length) {
+            panic!(
+                "nested lists of column \"{}\" cannot be read into a multidimensional array, since its inner lists have different lengths",
+                attribute_name
+            );
+        }
+
+        dims.push(length);
+
+        list_array = values;
+    }
+
+    (list_array.to_data(), ArrayDims(dims))
+}
+
+fn list_values_and_lengths<O: OffsetSizeTrait>(
+    list_array: &GenericListArray<O>,
+) -> (ArrayRef, Vec<usize>) {
+    let offsets = list_array.value_offsets();
+
+    let first_offset = offsets[0].as_usize();
+    let last_offset = offsets[offsets.len() - 1].as_usize();
+
+    let lengths = offsets
+        .windows(2)
+        .map(|window| (window[1] - window[0]).as_usize())
+        .collect();
+
+    let values = list_array
+        .values()
+        .slice(first_offset, last_offset - first_offset);
+
+    (values, lengths)
+}
+
+// to_pg_flat_array_datum converts the elements of the list into a one-dimensional array.
+fn to_pg_flat_array_datum(
+    list_array: ArrayData,
+    attribute_context: &ArrowToPgAttributeContext,
+) -> Option<Datum> {
+    let list_array = rescale_timestamps_to_microseconds(list_array);
 
     let element_context = attribute_context.element_context();
 
@@ -228,10 +461,18 @@ fn to_pg_array_datum(
             } else if element_context.typoid() == TEXTOID {
                 to_pg_datum!(
                     StringArray,
-                    Vec<Option<String>>,
+                    Vec<Option<ServerEncodedText>>,
                     list_array,
                     element_context
                 )
+            } else if element_context.typoid() == INTERVALOID {
+                // errors name the array column instead of its element field
+                to_pg_datum!(
+                    StringArray,
+                    Vec<Option<Interval>>,
+                    list_array,
+                    attribute_context
+                )
             } else {
                 reset_fallback_to_text_context(element_context.typoid(), element_context.typmod());
 
@@ -247,7 +488,7 @@ fn to_pg_array_datum(
             if element_context.is_geometry() {
                 to_pg_datum!(
                     BinaryArray,
-                    Vec<Option<Geometry>>,
+                    Vec<Option<SridConvertedGeometry>>,
                     list_array,
                     element_context
                 )
@@ -261,8 +502,25 @@ fn to_pg_array_datum(
             }
         }
         DataType::Decimal128(_, _) => {
+            if element_context.typoid() == CASHOID {
+                to_pg_datum!(
+                    Decimal128Array,
+                    Vec<Option<Money>>,
+                    list_array,
+                    element_context
+                )
+            } else {
+                to_pg_datum!(
+                    Decimal128Array,
+                    Vec<Option<AnyNumeric>>,
+                    list_array,
+                    element_context
+                )
+            }
+        }
+        DataType::Decimal256(_, _) => {
             to_pg_datum!(
-                Decimal128Array,
+                Decimal256Array,
                 Vec<Option<AnyNumeric>>,
                 list_array,
                 element_context
@@ -288,29 +546,84 @@ fn to_pg_array_datum(
                 )
             }
         }
-        DataType::Timestamp(TimeUnit::Microsecond, None) => {
-            to_pg_datum!(
-                TimestampMicrosecondArray,
-                Vec<Option<Timestamp>>,
-                list_array,
-                element_context
-            )
+        DataType::Timestamp(_, None) => {
+            if element_context.typoid() == TIMESTAMPTZOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Vec<Option<TimestampWithTimeZone>>,
+                    list_array,
+                    element_context
+                )
+            } else {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Vec<Option<Timestamp>>,
+                    list_array,
+                    element_context
+                )
+            }
         }
-        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => {
-            to_pg_datum!(
-                TimestampMicrosecondArray,
-                Vec<Option<TimestampWithTimeZone>>,
-                list_array,
-                element_context
-            )
+        DataType::Timestamp(_, Some(_)) => {
+            if element_context.typoid() == TIMESTAMPOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Vec<Option<Timestamp>>,
+                    list_array,
+                    element_context
+                )
+            } else if element_context.typoid() == DATEOID {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Vec<Option<Date>>,
+                    list_array,
+                    element_context
+                )
+            } else {
+                to_pg_datum!(
+                    TimestampMicrosecondArray,
+                    Vec<Option<TimestampWithTimeZone>>,
+                    list_array,
+                    element_context
+                )
+            }
         }
         DataType::Struct(_) => {
-            to_pg_datum!(
-                StructArray,
-                Vec<Option<PgHeapTuple<AllocatedByRust>>>,
-                list_array,
-                element_context
-            )
+            if element_context.typoid() == INETOID {
+                to_pg_datum!(StructArray, Vec<Option<Inet>>, list_array, element_context)
+            } else if element_context.typoid() == CIDROID {
+                to_pg_datum!(StructArray, Vec<Option<Cidr>>, list_array, element_context)
+            } else if element_context.typoid() == TIMETZOID {
+                to_pg_datum!(
+                    StructArray,
+                    Vec<Option<TimeWithTimeZone>>,
+                    list_array,
+                    element_context
+                )
+            } else {
+                to_pg_datum!(
+                    StructArray,
+                    Vec<Option<PgHeapTuple<AllocatedByRust>>>,
+                    list_array,
+                    element_context
+                )
+            }
+        }
+        DataType::FixedSizeBinary(_) => {
+            if element_context.typoid() == MACADDROID {
+                to_pg_datum!(
+                    FixedSizeBinaryArray,
+                    Vec<Option<MacAddr>>,
+                    list_array,
+                    element_context
+                )
+            } else {
+                to_pg_datum!(
+                    FixedSizeBinaryArray,
+                    Vec<Option<MacAddr8>>,
+                    list_array,
+                    element_context
+                )
+            }
         }
         DataType::Map(_, _) => {
             reset_map_type_context(element_context.typoid());
@@ -322,3 +635,40 @@ fn to_pg_array_datum(
         }
     }
 }
+
+// rescale_timestamps_to_microseconds rescales the timestamps in seconds, milliseconds or
+// nanoseconds (e.g. INT96 timestamps written by Spark or Hive) to microseconds, which is
+// the precision of Postgres timestamps. Nanoseconds are truncated towards the past.
+fn rescale_timestamps_to_microseconds(timestamp_array: ArrayData) -> ArrayData {
+    let (time_unit, timezone) = match timestamp_array.data_type() {
+        DataType::Timestamp(TimeUnit::Microsecond, _) => return timestamp_array,
+        DataType::Timestamp(time_unit, timezone) => (*time_unit, timezone.clone()),
+        _ => return timestamp_array,
+    };
+
+    let timestamp_array = Int64Array::from(
+        timestamp_array
+            .into_builder()
+            .data_type(DataType::Int64)
+            .build()
+            .unwrap_or_else(|e| panic!("{}", e)),
+    );
+
+    let to_microseconds = |value: i64| {
+        let microseconds = match time_unit {
+            TimeUnit::Second => value.checked_mul(1_000_000),
+            TimeUnit::Millisecond => value.checked_mul(1_000),
+            TimeUnit::Microsecond => Some(value),
+            TimeUnit::Nanosecond => Some(value.div_euclid(1_000)),
+        };
+
+        microseconds.unwrap_or_else(|| panic!("timestamp out of range: {} {:?}", value, time_unit))
+    };
+
+    timestamp_array
+        .iter()
+        .map(|value| value.map(to_microseconds))
+        .collect::<TimestampMicrosecondArray>()
+        .with_timezone_opt(timezone)
+        .into_data()
+}
@@ -16,9 +16,11 @@ impl<'a> ArrowArrayToPgType<PgHeapTuple<'a, AllocatedByRust>> for StructArray {
         let mut datums = vec![];
 
         for attribute_context in context.attribute_contexts() {
-            let column_data = self
-                .column_by_name(attribute_context.name())
-                .unwrap_or_else(|| panic!("column {} not found", &attribute_context.name()));
+            // the fields, which are missing in the struct of the file, are NULL
+            let Some(column_data) = self.column_by_name(attribute_context.name()) else {
+                datums.push(None);
+                continue;
+            };
 
             let datum = to_pg_datum(column_data.into_data(), attribute_context);
 
@@ -6,7 +6,13 @@ use pgrx::{
     PgTupleDesc,
 };
 
-use crate::type_compat::pg_arrow_type_conversions::extract_precision_and_scale_from_numeric_typmod;
+use crate::{
+    arrow_parquet::schema_parser::{decode_dictionary_fields, parse_arrow_schema_from_attributes},
+    type_compat::{
+        geometry_crs::GeometrySridConversion,
+        pg_arrow_type_conversions::extract_precision_and_scale_from_numeric_typmod,
+    },
+};
 
 use super::{
     array_element_typoid, collect_attributes_for, domain_array_base_elem_type, is_array_type,
@@ -84,13 +90,48 @@ impl ArrowToPgAttributeContext {
         &self.data_type
     }
 
-    pub(crate) fn timezone(&self) -> &str {
-        let timezone = match &self.type_context {
-            ArrowToPgAttributeTypeContext::Primitive { timezone, .. } => timezone.as_ref(),
-            _ => None,
-        };
+    // set_geometry_srid_conversions sets the SRID conversions of the geometry column, and of
+    // the geometries nested in the column, which depend on their crs in the parquet file.
+    // The nested geometries are named by their dotted names, e.g. "location.geom", as in
+    // the geoparquet metadata.
+    pub(crate) fn set_geometry_srid_conversions<F>(&mut self, name: &str, conversion: &F)
+    where
+        F: Fn(&str, i32) -> Option<GeometrySridConversion>,
+    {
+        match &mut self.type_context {
+            ArrowToPgAttributeTypeContext::Primitive {
+                is_geometry: true,
+                geometry_srid_conversion,
+                ..
+            } => {
+                if let Some(conversion) = conversion(name, self.typmod) {
+                    *geometry_srid_conversion = conversion;
+                }
+            }
+            ArrowToPgAttributeTypeContext::Array {
+                element_context, ..
+            } => element_context.set_geometry_srid_conversions(name, conversion),
+            ArrowToPgAttributeTypeContext::Composite {
+                attribute_contexts, ..
+            } => {
+                for attribute_context in attribute_contexts {
+                    let field_name = format!("{}.{}", name, attribute_context.name);
+
+                    attribute_context.set_geometry_srid_conversions(&field_name, conversion);
+                }
+            }
+            _ => {}
+        }
+    }
 
-        timezone.unwrap_or_else(|| panic!("missing timezone in context"))
+    pub(crate) fn geometry_srid_conversion(&self) -> GeometrySridConversion {
+        match &self.type_context {
+            ArrowToPgAttributeTypeContext::Primitive {
+                geometry_srid_conversion,
+                ..
+            } => *geometry_srid_conversion,
+            _ => GeometrySridConversion::default(),
+        }
     }
 }
 
@@ -100,12 +141,14 @@ impl ArrowToPgAttributeContext {
 pub(crate) enum ArrowToPgAttributeTypeContext {
     Primitive {
         is_geometry: bool,
+        geometry_srid_conversion: GeometrySridConversion,
         precision: Option<u32>,
         scale: Option<u32>,
-        timezone: Option<String>,
     },
     Array {
         element_context: Box<ArrowToPgAttributeContext>,
+        // number of the nested lists, i.e. the dimensions of the arrays
+        ndims: usize,
     },
     Composite {
         tupledesc: PgTupleDesc<'static>,
@@ -134,7 +177,12 @@ impl ArrowToPgAttributeTypeContext {
         let precision;
         let scale;
         if typoid == NUMERICOID {
-            let (p, s) = extract_precision_and_scale_from_numeric_typmod(typmod);
+            // decimals of the file are read as they are, and the column's typmod is applied
+            // by numeric's input function
+            let (p, s) = match data_type {
+                DataType::Decimal128(p, s) | DataType::Decimal256(p, s) => (*p as _, *s as _),
+                _ => extract_precision_and_scale_from_numeric_typmod(typmod),
+            };
             precision = Some(p);
             scale = Some(s);
         } else {
@@ -144,16 +192,11 @@ impl ArrowToPgAttributeTypeContext {
 
         let is_geometry = is_postgis_geometry_type(typoid);
 
-        let timezone = match &data_type {
-            DataType::Timestamp(_, Some(timezone)) => Some(timezone.to_string()),
-            _ => None,
-        };
-
         Self::Primitive {
             is_geometry,
+            geometry_srid_conversion: GeometrySridConversion::default(),
             precision,
             scale,
-            timezone,
         }
     }
 
@@ -161,11 +204,19 @@ impl ArrowToPgAttributeTypeContext {
         let element_typoid = array_element_typoid(typoid);
         let element_typmod = typmod;
 
-        let element_field = match data_type {
-            DataType::List(field) => field.clone(),
+        let mut element_field = match data_type {
+            DataType::List(field) | DataType::LargeList(field) => field.clone(),
             _ => unreachable!(),
         };
 
+        // nested lists are read into multidimensional arrays
+        let mut ndims = 1;
+
+        while let DataType::List(field) | DataType::LargeList(field) = element_field.data_type() {
+            element_field = field.clone();
+            ndims += 1;
+        }
+
         let element_context = Box::new(ArrowToPgAttributeContext::new(
             element_field.name(),
             element_typoid,
@@ -174,7 +225,10 @@ impl ArrowToPgAttributeTypeContext {
             None,
         ));
 
-        Self::Array { element_context }
+        Self::Array {
+            element_context,
+            ndims,
+        }
     }
 
     fn new_composite(typoid: Oid, typmod: i32, data_type: &DataType) -> Self {
@@ -186,6 +240,25 @@ impl ArrowToPgAttributeTypeContext {
 
         let attributes = collect_attributes_for(CollectAttributesFor::Other, &tupledesc);
 
+        // the fields, which are missing in the struct of the file with match_by 'name', are
+        // converted to NULL. Their contexts are created from the fields of the composite type.
+        let fields = if fields.len() < attributes.len() {
+            let composite_fields =
+                decode_dictionary_fields(&parse_arrow_schema_from_attributes(&attributes).fields);
+
+            composite_fields
+                .iter()
+                .map(
+                    |composite_field| match fields.find(composite_field.name()) {
+                        Some((_, field)) => field.clone(),
+                        None => composite_field.clone(),
+                    },
+                )
+                .collect()
+        } else {
+            fields
+        };
+
         // we only cast the top-level attributes, which already covers the nested attributes
         let cast_to_types = None;
 
@@ -271,6 +344,13 @@ impl ArrowToPgAttributeTypeContext {
         }
     }
 
+    pub(crate) fn array_ndims(&self) -> usize {
+        match self {
+            Self::Array { ndims, .. } => *ndims,
+            _ => panic!("not a context for an array type"),
+        }
+    }
+
     // type checks
     pub(crate) fn is_geometry(&self) -> bool {
         match &self {
@@ -1,7 +1,7 @@
-use arrow::array::{Array, Date32Array};
+use arrow::array::{Array, Date32Array, TimestampMicrosecondArray};
 use pgrx::datum::Date;
 
-use crate::type_compat::pg_arrow_type_conversions::i32_to_date;
+use crate::type_compat::pg_arrow_type_conversions::{i32_to_date, utc_i64_to_local_date};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
@@ -28,3 +28,26 @@ impl ArrowArrayToPgType<Vec<Option<Date>>> for Date32Array {
         Some(vals)
     }
 }
+
+// Date coerced from timestamptz
+impl ArrowArrayToPgType<Date> for TimestampMicrosecondArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Date> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(utc_i64_to_local_date(self.value(0)))
+        }
+    }
+}
+
+// Date[] coerced from timestamptz[]
+impl ArrowArrayToPgType<Vec<Option<Date>>> for TimestampMicrosecondArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Date>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            let val = val.map(utc_i64_to_local_date);
+            vals.push(val);
+        }
+        Some(vals)
+    }
+}
@@ -1,6 +1,6 @@
 use arrow::array::{Array, BinaryArray};
 
-use crate::type_compat::geometry::Geometry;
+use crate::type_compat::{geometry::Geometry, geometry_crs::SridConvertedGeometry};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
@@ -16,12 +16,18 @@ impl ArrowArrayToPgType<Geometry> for BinaryArray {
 }
 
 // Geometry[]
-impl ArrowArrayToPgType<Vec<Option<Geometry>>> for BinaryArray {
-    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Geometry>>> {
+impl ArrowArrayToPgType<Vec<Option<SridConvertedGeometry>>> for BinaryArray {
+    fn to_pg_type(
+        self,
+        context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<SridConvertedGeometry>>> {
+        // the elements get the SRID of the array's crs in the file
+        let conversion = context.geometry_srid_conversion();
+
         let mut vals = vec![];
         for val in self.iter() {
             if let Some(val) = val {
-                vals.push(Some(val.to_vec().into()));
+                vals.push(Some(SridConvertedGeometry(val.to_vec().into(), conversion)));
             } else {
                 vals.push(None);
             }
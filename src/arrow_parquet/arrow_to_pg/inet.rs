@@ -0,0 +1,105 @@
+use arrow::array::{Array, BinaryArray, StructArray, UInt8Array};
+
+use crate::type_compat::network_address::{
+    Cidr, Inet, NetworkAddress, INET_ADDR_FIELD_NAME, INET_BITS_FIELD_NAME, INET_FAMILY_FIELD_NAME,
+};
+
+use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
+
+// Inet
+impl ArrowArrayToPgType<Inet> for StructArray {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Inet> {
+        let is_cidr = false;
+
+        network_addresses(&self, is_cidr, context)
+            .pop()
+            .expect("empty inet array")
+            .map(Inet)
+    }
+}
+
+// Inet[]
+impl ArrowArrayToPgType<Vec<Option<Inet>>> for StructArray {
+    fn to_pg_type(self, element_context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Inet>>> {
+        let is_cidr = false;
+
+        let vals = network_addresses(&self, is_cidr, element_context)
+            .into_iter()
+            .map(|address| address.map(Inet))
+            .collect();
+
+        Some(vals)
+    }
+}
+
+// Cidr
+impl ArrowArrayToPgType<Cidr> for StructArray {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Cidr> {
+        let is_cidr = true;
+
+        network_addresses(&self, is_cidr, context)
+            .pop()
+            .expect("empty cidr array")
+            .map(Cidr)
+    }
+}
+
+// Cidr[]
+impl ArrowArrayToPgType<Vec<Option<Cidr>>> for StructArray {
+    fn to_pg_type(self, element_context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Cidr>>> {
+        let is_cidr = true;
+
+        let vals = network_addresses(&self, is_cidr, element_context)
+            .into_iter()
+            .map(|address| address.map(Cidr))
+            .collect();
+
+        Some(vals)
+    }
+}
+
+// network_addresses validates the addresses in the struct array, since they might be written
+// by other writers. It fails if a cidr address has bits set to the right of its netmask.
+fn network_addresses(
+    struct_array: &StructArray,
+    is_cidr: bool,
+    context: &ArrowToPgAttributeContext,
+) -> Vec<Option<NetworkAddress>> {
+    let families = struct_array_column::<UInt8Array>(struct_array, INET_FAMILY_FIELD_NAME);
+    let bits = struct_array_column::<UInt8Array>(struct_array, INET_BITS_FIELD_NAME);
+    let addrs = struct_array_column::<BinaryArray>(struct_array, INET_ADDR_FIELD_NAME);
+
+    (0..struct_array.len())
+        .map(|idx| {
+            if struct_array.is_null(idx) {
+                return None;
+            }
+
+            let address = NetworkAddress::new(
+                families.value(idx),
+                bits.value(idx),
+                addrs.value(idx).to_vec(),
+                is_cidr,
+            )
+            .unwrap_or_else(|e| {
+                panic!(
+                    "invalid {} value for column \"{}\": {}",
+                    if is_cidr { "cidr" } else { "inet" },
+                    context.name(),
+                    e
+                )
+            });
+
+            Some(address)
+        })
+        .collect()
+}
+
+fn struct_array_column<'a, T: 'static>(struct_array: &'a StructArray, name: &str) -> &'a T {
+    struct_array
+        .column_by_name(name)
+        .unwrap_or_else(|| panic!("inet field {} not found", name))
+        .as_any()
+        .downcast_ref::<T>()
+        .unwrap_or_else(|| panic!("unexpected type of inet field {}", name))
+}
@@ -0,0 +1,279 @@
+use std::panic::AssertUnwindSafe;
+
+use arrow::array::{Array, StringArray};
+use pgrx::{
+    datum::Interval,
+    direct_function_call,
+    pg_sys::{self, panic::CaughtError, AsPgCStr, Datum, InvalidOid, INTSTYLE_POSTGRES},
+    IntoDatum, PgTryBuilder,
+};
+
+use super::{conversion_row_number, ArrowArrayToPgType, ArrowToPgAttributeContext};
+
+// Interval from its text representation
+impl ArrowArrayToPgType<Interval> for StringArray {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Interval> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(text_to_interval(self.value(0), context))
+        }
+    }
+}
+
+// Interval[] from its text representation
+impl ArrowArrayToPgType<Vec<Option<Interval>>> for StringArray {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Interval>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            let val = val.map(|val| text_to_interval(val, context));
+            vals.push(val);
+        }
+        Some(vals)
+    }
+}
+
+// text_to_interval parses the text as an interval in Postgres syntax, e.g. "1 day 02:30:00",
+// or ISO 8601 duration syntax, e.g. "P1DT2H30M". Text that starts with "P" is never valid
+// Postgres syntax, so it is parsed as an ISO 8601 duration. Parsing does not depend on
+// the session's IntervalStyle.
+fn text_to_interval(text: &str, context: &ArrowToPgAttributeContext) -> Interval {
+    let result = if text.trim_start().starts_with(['P', 'p']) {
+        iso8601_duration_to_interval(text.trim())
+    } else {
+        postgres_text_to_interval(text, context.typmod())
+    };
+
+    result.unwrap_or_else(|e| {
+        panic!(
+            "invalid interval value \"{}\" for column \"{}\" at row {}: {}",
+            text,
+            context.name(),
+            conversion_row_number(),
+            e
+        )
+    })
+}
+
+// IntervalStyleGuard sets IntervalStyle to "postgres" until it is dropped, which also
+// happens when interval_in raises an error.
+struct IntervalStyleGuard {
+    interval_style: i32,
+}
+
+impl IntervalStyleGuard {
+    fn new() -> Self {
+        let interval_style = unsafe { pg_sys::IntervalStyle };
+
+        unsafe { pg_sys::IntervalStyle = INTSTYLE_POSTGRES as _ };
+
+        Self { interval_style }
+    }
+}
+
+impl Drop for IntervalStyleGuard {
+    fn drop(&mut self) {
+        unsafe { pg_sys::IntervalStyle = self.interval_style };
+    }
+}
+
+fn postgres_text_to_interval(text: &str, typmod: i32) -> Result<Interval, String> {
+    PgTryBuilder::new(AssertUnwindSafe(|| {
+        let _interval_style_guard = IntervalStyleGuard::new();
+
+        let interval: Interval = unsafe {
+            direct_function_call(
+                pg_sys::interval_in,
+                &[
+                    Some(Datum::from(text.as_pg_cstr())),
+                    InvalidOid.into_datum(),
+                    typmod.into_datum(),
+                ],
+            )
+            .expect("interval_in returned null")
+        };
+
+        Ok(interval)
+    }))
+    .catch_others(|cause| match &cause {
+        CaughtError::PostgresError(report)
+        | CaughtError::ErrorReport(report)
+        | CaughtError::RustPanic {
+            ereport: report, ..
+        } => Err(report.message().to_string()),
+    })
+    .execute()
+}
+
+// IntervalParts accumulates the parts of an ISO 8601 duration. Fractional parts are carried
+// down to the smaller units as Postgres does, e.g. "P1.5M" is 1 month 15 days.
+#[derive(Default)]
+struct IntervalParts {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+impl IntervalParts {
+    fn add_months(&mut self, months: f64) -> Result<(), String> {
+        let whole_months = months.trunc();
+
+        self.months = f64_to_i32(whole_months)
+            .and_then(|whole_months| self.months.checked_add(whole_months))
+            .ok_or("interval out of range")?;
+
+        self.add_days((months - whole_months) * 30.0)
+    }
+
+    fn add_days(&mut self, days: f64) -> Result<(), String> {
+        let whole_days = days.trunc();
+
+        self.days = f64_to_i32(whole_days)
+            .and_then(|whole_days| self.days.checked_add(whole_days))
+            .ok_or("interval out of range")?;
+
+        self.add_micros((days - whole_days) * 86_400_000_000.0)
+    }
+
+    fn add_micros(&mut self, micros: f64) -> Result<(), String> {
+        let micros = micros.round();
+
+        if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+            return Err("interval out of range".into());
+        }
+
+        self.micros = self
+            .micros
+            .checked_add(micros as i64)
+            .ok_or("interval out of range")?;
+
+        Ok(())
+    }
+}
+
+fn f64_to_i32(value: f64) -> Option<i32> {
+    if value.is_finite() && value >= i32::MIN as f64 && value <= i32::MAX as f64 {
+        Some(value as i32)
+    } else {
+        None
+    }
+}
+
+// iso8601_duration_to_interval parses an ISO 8601 duration in the format with designators,
+// e.g. "P1Y2M3W4DT5H6M7.5S", or in the alternative format, e.g. "P0001-02-03T04:05:06".
+fn iso8601_duration_to_interval(text: &str) -> Result<Interval, String> {
+    let duration = &text[1..];
+
+    let (date_part, time_part) = match duration.split_once(['T', 't']) {
+        Some((date_part, time_part)) => (date_part, Some(time_part)),
+        None => (duration, None),
+    };
+
+    if date_part.is_empty() && time_part.unwrap_or_default().is_empty() {
+        return Err("empty ISO 8601 duration".into());
+    }
+
+    let mut parts = IntervalParts::default();
+
+    if date_part.contains('-') && !date_part.ends_with(|c: char| c.is_ascii_alphabetic()) {
+        parse_iso8601_alternative_date(date_part, &mut parts)?;
+    } else {
+        for (value, designator) in iso8601_designated_values(date_part)? {
+            match designator.to_ascii_uppercase() {
+                'Y' => parts.add_months(value * 12.0)?,
+                'M' => parts.add_months(value)?,
+                'W' => parts.add_days(value * 7.0)?,
+                'D' => parts.add_days(value)?,
+                _ => return Err(format!("unexpected designator \"{}\"", designator)),
+            }
+        }
+    }
+
+    if let Some(time_part) = time_part {
+        if time_part.is_empty() {
+            return Err("missing time after \"T\"".into());
+        }
+
+        if time_part.contains(':') {
+            parse_iso8601_alternative_time(time_part, &mut parts)?;
+        } else {
+            for (value, designator) in iso8601_designated_values(time_part)? {
+                match designator.to_ascii_uppercase() {
+                    'H' => parts.add_micros(value * 3_600_000_000.0)?,
+                    'M' => parts.add_micros(value * 60_000_000.0)?,
+                    'S' => parts.add_micros(value * 1_000_000.0)?,
+                    _ => return Err(format!("unexpected designator \"{}\"", designator)),
+                }
+            }
+        }
+    }
+
+    Interval::new(parts.months, parts.days, parts.micros)
+        .map_err(|_| "interval out of range".to_string())
+}
+
+// iso8601_designated_values splits e.g. "1Y2.5M" into [(1.0, 'Y'), (2.5, 'M')]
+fn iso8601_designated_values(part: &str) -> Result<Vec<(f64, char)>, String> {
+    let mut values = vec![];
+    let mut value_start = 0;
+
+    for (idx, c) in part.char_indices() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+
+        let value = &part[value_start..idx];
+
+        let value = value
+            .replace(',', ".")
+            .parse::<f64>()
+            .map_err(|_| format!("invalid number \"{}\" before \"{}\"", value, c))?;
+
+        values.push((value, c));
+
+        value_start = idx + c.len_utf8();
+    }
+
+    if value_start != part.len() {
+        return Err(format!(
+            "missing designator after \"{}\"",
+            &part[value_start..]
+        ));
+    }
+
+    Ok(values)
+}
+
+// parse_iso8601_alternative_date parses the date part of e.g. "P0001-02-03T04:05:06"
+fn parse_iso8601_alternative_date(part: &str, parts: &mut IntervalParts) -> Result<(), String> {
+    let fields = parse_iso8601_alternative_fields(part, '-')?;
+
+    let [years, months, days] = fields[..] else {
+        return Err(format!("invalid date \"{}\"", part));
+    };
+
+    parts.add_months(years * 12.0 + months)?;
+    parts.add_days(days)
+}
+
+// parse_iso8601_alternative_time parses the time part of e.g. "P0001-02-03T04:05:06"
+fn parse_iso8601_alternative_time(part: &str, parts: &mut IntervalParts) -> Result<(), String> {
+    let fields = parse_iso8601_alternative_fields(part, ':')?;
+
+    let [hours, minutes, seconds] = fields[..] else {
+        return Err(format!("invalid time \"{}\"", part));
+    };
+
+    parts.add_micros((hours * 3600.0 + minutes * 60.0 + seconds) * 1_000_000.0)
+}
+
+fn parse_iso8601_alternative_fields(part: &str, separator: char) -> Result<Vec<f64>, String> {
+    part.split(separator)
+        .map(|field| {
+            field
+                .replace(',', ".")
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number \"{}\"", field))
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+use arrow::array::{Array, FixedSizeBinaryArray};
+
+use crate::type_compat::network_address::{MacAddr, MacAddr8};
+
+use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
+
+// MacAddr
+impl ArrowArrayToPgType<MacAddr> for FixedSizeBinaryArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<MacAddr> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(MacAddr(self.value(0).try_into().expect("invalid macaddr")))
+        }
+    }
+}
+
+// MacAddr[]
+impl ArrowArrayToPgType<Vec<Option<MacAddr>>> for FixedSizeBinaryArray {
+    fn to_pg_type(
+        self,
+        _element_context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<MacAddr>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            let val = val.map(|val| MacAddr(val.try_into().expect("invalid macaddr")));
+            vals.push(val);
+        }
+        Some(vals)
+    }
+}
+
+// MacAddr8
+impl ArrowArrayToPgType<MacAddr8> for FixedSizeBinaryArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<MacAddr8> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(MacAddr8(
+                self.value(0).try_into().expect("invalid macaddr8"),
+            ))
+        }
+    }
+}
+
+// MacAddr8[]
+impl ArrowArrayToPgType<Vec<Option<MacAddr8>>> for FixedSizeBinaryArray {
+    fn to_pg_type(
+        self,
+        _element_context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<MacAddr8>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            let val = val.map(|val| MacAddr8(val.try_into().expect("invalid macaddr8")));
+            vals.push(val);
+        }
+        Some(vals)
+    }
+}
@@ -1,4 +1,10 @@
-use arrow::array::{Array, MapArray};
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, AsArray, BooleanArray, MapArray, StructArray},
+    compute::filter,
+    datatypes::Fields,
+};
 use pgrx::{prelude::PgHeapTuple, AllocatedByRust, FromDatum, IntoDatum};
 
 use crate::type_compat::map::Map;
@@ -11,7 +17,7 @@ impl<'a> ArrowArrayToPgType<Map<'a>> for MapArray {
         if self.is_null(0) {
             None
         } else {
-            let entries_array = self.value(0);
+            let entries_array = map_entries(self.value(0), context.entries_context());
 
             let entries: Option<Vec<Option<PgHeapTuple<AllocatedByRust>>>> =
                 entries_array.to_pg_type(context.entries_context());
@@ -46,6 +52,8 @@ impl<'a> ArrowArrayToPgType<Vec<Option<Map<'a>>>> for MapArray {
 
         for entries_array in self.iter() {
             if let Some(entries_array) = entries_array {
+                let entries_array = map_entries(entries_array, element_context.entries_context());
+
                 let entries: Option<Vec<Option<PgHeapTuple<AllocatedByRust>>>> =
                     entries_array.to_pg_type(element_context.entries_context());
 
@@ -73,3 +81,32 @@ impl<'a> ArrowArrayToPgType<Vec<Option<Map<'a>>>> for MapArray {
         Some(maps)
     }
 }
+
+// map_entries returns the entries of the map, whose key and value are renamed by position to
+// the fields of the map type, e.g. "value" to "val". Null entries, which some writers write,
+// are skipped as absent pairs.
+fn map_entries(entries: StructArray, entries_context: &ArrowToPgAttributeContext) -> StructArray {
+    let entries = match entries.nulls() {
+        Some(nulls) if nulls.null_count() > 0 => {
+            let valid_entries = BooleanArray::new(nulls.inner().clone(), None);
+
+            filter(&entries, &valid_entries)
+                .unwrap_or_else(|e| panic!("failed to skip null map entries: {}", e))
+                .as_struct()
+                .clone()
+        }
+        _ => entries,
+    };
+
+    let (fields, columns, _nulls) = entries.into_parts();
+
+    let fields = fields
+        .iter()
+        .zip(entries_context.attribute_contexts())
+        .map(|(field, attribute_context)| {
+            Arc::new(field.as_ref().clone().with_name(attribute_context.name()))
+        })
+        .collect::<Fields>();
+
+    StructArray::new(fields, columns, None)
+}
@@ -0,0 +1,41 @@
+use arrow::array::{Array, Decimal128Array, Int64Array};
+
+use crate::type_compat::money::Money;
+
+use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
+
+// Money, which is cast to Decimal128(19, 2) before the conversion
+impl ArrowArrayToPgType<Money> for Decimal128Array {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Money> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(Money(self.value(0)))
+        }
+    }
+}
+
+// Money from Int64, which holds the amount in cents
+impl ArrowArrayToPgType<Money> for Int64Array {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Money> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(Money(self.value(0) as i128))
+        }
+    }
+}
+
+// Money[]
+impl ArrowArrayToPgType<Vec<Option<Money>>> for Decimal128Array {
+    fn to_pg_type(
+        self,
+        _element_context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<Money>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            vals.push(val.map(Money));
+        }
+        Some(vals)
+    }
+}
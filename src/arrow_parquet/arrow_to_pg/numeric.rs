@@ -1,7 +1,7 @@
-use arrow::array::{Array, Decimal128Array};
+use arrow::array::{Array, Decimal128Array, Decimal256Array};
 use pgrx::AnyNumeric;
 
-use crate::type_compat::pg_arrow_type_conversions::i128_to_numeric;
+use crate::type_compat::pg_arrow_type_conversions::{i128_to_numeric, i256_to_numeric};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
@@ -42,3 +42,41 @@ impl ArrowArrayToPgType<Vec<Option<AnyNumeric>>> for Decimal128Array {
         Some(vals)
     }
 }
+
+// Numeric from Decimal256
+impl ArrowArrayToPgType<AnyNumeric> for Decimal256Array {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<AnyNumeric> {
+        if self.is_null(0) {
+            None
+        } else {
+            Some(i256_to_numeric(
+                self.value(0),
+                context.precision(),
+                context.scale(),
+                context.typmod(),
+            ))
+        }
+    }
+}
+
+// Numeric[] from Decimal256
+impl ArrowArrayToPgType<Vec<Option<AnyNumeric>>> for Decimal256Array {
+    fn to_pg_type(
+        self,
+        element_context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<AnyNumeric>>> {
+        let mut vals = vec![];
+        for val in self.iter() {
+            let val = val.map(|v| {
+                i256_to_numeric(
+                    v,
+                    element_context.precision(),
+                    element_context.scale(),
+                    element_context.typmod(),
+                )
+            });
+            vals.push(val);
+        }
+        Some(vals)
+    }
+}
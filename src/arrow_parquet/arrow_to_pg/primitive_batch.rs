@@ -0,0 +1,119 @@
+use arrow::array::{
+    Array, ArrayRef, AsArray, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, Time64MicrosecondArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, TimeUnit};
+use pgrx::{
+    pg_sys::{
+        Datum, BOOLOID, DATEOID, FLOAT4OID, FLOAT8OID, INT2OID, INT4OID, INT8OID, TIMEOID,
+        TIMESTAMPOID, TIMESTAMPTZOID,
+    },
+    IntoDatum,
+};
+
+use crate::type_compat::pg_arrow_type_conversions::{
+    i32_to_date, i64_to_time, i64_to_timestamp, i64_to_timestamptz,
+};
+
+use super::{context::ArrowToPgAttributeContext, set_conversion_row_number};
+
+// primitive_datums! converts the values of the primitive array into datums in a tight loop over
+// its values buffer and null bitmap. The row number is set for each value, so that a conversion
+// error, e.g. an out of range timestamp, reports its row.
+macro_rules! primitive_datums {
+    ($arrow_array_type:ty, $column_array:expr, $first_row_number:expr, $to_pg_type:expr) => {{
+        let array = $column_array
+            .as_any()
+            .downcast_ref::<$arrow_array_type>()
+            .expect("unexpected primitive array type");
+
+        let to_pg_type = $to_pg_type;
+
+        array
+            .values()
+            .iter()
+            .enumerate()
+            .map(|(row_idx, value)| {
+                if array.is_null(row_idx) {
+                    return None;
+                }
+
+                set_conversion_row_number($first_row_number + row_idx);
+
+                to_pg_type(*value).into_datum()
+            })
+            .collect::<Vec<_>>()
+    }};
+}
+
+// to_pg_primitive_datums converts the whole column of the record batch into datums, when the
+// column is read into a pass-by-value type without a cast, e.g. int4 from an Int32 column.
+// Otherwise, it returns None and the values are converted per row by to_pg_datum.
+pub(crate) fn to_pg_primitive_datums(
+    column_array: &ArrayRef,
+    attribute_context: &ArrowToPgAttributeContext,
+    first_row_number: usize,
+) -> Option<Vec<Option<Datum>>> {
+    if attribute_context.needs_cast() || column_array.data_type() != attribute_context.data_type() {
+        return None;
+    }
+
+    let typoid = attribute_context.typoid();
+
+    let datums = match column_array.data_type() {
+        DataType::Int16 if typoid == INT2OID => {
+            primitive_datums!(Int16Array, column_array, first_row_number, |v: i16| v)
+        }
+        DataType::Int32 if typoid == INT4OID => {
+            primitive_datums!(Int32Array, column_array, first_row_number, |v: i32| v)
+        }
+        DataType::Int64 if typoid == INT8OID => {
+            primitive_datums!(Int64Array, column_array, first_row_number, |v: i64| v)
+        }
+        DataType::Float32 if typoid == FLOAT4OID => {
+            primitive_datums!(Float32Array, column_array, first_row_number, |v: f32| v)
+        }
+        DataType::Float64 if typoid == FLOAT8OID => {
+            primitive_datums!(Float64Array, column_array, first_row_number, |v: f64| v)
+        }
+        DataType::Date32 if typoid == DATEOID => {
+            primitive_datums!(Date32Array, column_array, first_row_number, i32_to_date)
+        }
+        DataType::Time64(TimeUnit::Microsecond) if typoid == TIMEOID => {
+            primitive_datums!(
+                Time64MicrosecondArray,
+                column_array,
+                first_row_number,
+                i64_to_time
+            )
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, None) if typoid == TIMESTAMPOID => {
+            primitive_datums!(
+                TimestampMicrosecondArray,
+                column_array,
+                first_row_number,
+                i64_to_timestamp
+            )
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) if typoid == TIMESTAMPTZOID => {
+            primitive_datums!(
+                TimestampMicrosecondArray,
+                column_array,
+                first_row_number,
+                |v: i64| i64_to_timestamptz(v, "UTC")
+            )
+        }
+        DataType::Boolean if typoid == BOOLOID => {
+            // booleans are bit packed, hence they do not have a values slice
+            let array: &BooleanArray = column_array.as_boolean();
+
+            array
+                .iter()
+                .map(|value| value.and_then(|value| value.into_datum()))
+                .collect()
+        }
+        _ => return None,
+    };
+
+    Some(datums)
+}
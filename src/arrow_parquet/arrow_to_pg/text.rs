@@ -1,25 +1,30 @@
 use arrow::array::{Array, StringArray};
 
+use crate::type_compat::server_encoding::ServerEncodedText;
+
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
 // Text
-impl ArrowArrayToPgType<String> for StringArray {
-    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<String> {
+impl ArrowArrayToPgType<ServerEncodedText> for StringArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<ServerEncodedText> {
         if self.is_null(0) {
             None
         } else {
             let val = self.value(0);
-            Some(val.to_string())
+            Some(ServerEncodedText(val.to_string()))
         }
     }
 }
 
 // Text[]
-impl ArrowArrayToPgType<Vec<Option<String>>> for StringArray {
-    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Vec<Option<String>>> {
+impl ArrowArrayToPgType<Vec<Option<ServerEncodedText>>> for StringArray {
+    fn to_pg_type(
+        self,
+        _context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<ServerEncodedText>>> {
         let mut vals = vec![];
         for val in self.iter() {
-            let val = val.map(|val| val.to_string());
+            let val = val.map(|val| ServerEncodedText(val.to_string()));
             vals.push(val);
         }
         Some(vals)
@@ -1,29 +1,58 @@
 use arrow::array::{Array, TimestampMicrosecondArray};
 use pgrx::datum::Timestamp;
 
-use crate::type_compat::pg_arrow_type_conversions::i64_to_timestamp;
+use crate::{
+    arrow_parquet::timestamptz_to_timestamp::{timestamptz_to_timestamp, TimestamptzToTimestamp},
+    type_compat::pg_arrow_type_conversions::{i64_to_timestamp, utc_i64_to_local_timestamp},
+};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
 // Timestamp
 impl ArrowArrayToPgType<Timestamp> for TimestampMicrosecondArray {
-    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Timestamp> {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Timestamp> {
+        let to_timestamp = timestamp_conversion(&self, context);
+
         if self.is_null(0) {
             None
         } else {
-            Some(i64_to_timestamp(self.value(0)))
+            Some(to_timestamp(self.value(0)))
         }
     }
 }
 
 // Timestamp[]
 impl ArrowArrayToPgType<Vec<Option<Timestamp>>> for TimestampMicrosecondArray {
-    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Timestamp>>> {
+    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<Vec<Option<Timestamp>>> {
+        let to_timestamp = timestamp_conversion(&self, context);
+
         let mut vals = vec![];
         for val in self.iter() {
-            let val = val.map(i64_to_timestamp);
+            let val = val.map(to_timestamp);
             vals.push(val);
         }
         Some(vals)
     }
 }
+
+// timestamp_conversion returns the conversion of the values into timestamps. The values of
+// timestamps with a timezone are UTC instants, whatever the timezone is, which are coerced
+// per timestamptz_to_timestamp option.
+fn timestamp_conversion(
+    array: &TimestampMicrosecondArray,
+    context: &ArrowToPgAttributeContext,
+) -> fn(i64) -> Timestamp {
+    if array.timezone().is_none() {
+        return i64_to_timestamp;
+    }
+
+    match timestamptz_to_timestamp() {
+        TimestamptzToTimestamp::Local => utc_i64_to_local_timestamp,
+        TimestamptzToTimestamp::Utc => i64_to_timestamp,
+        TimestamptzToTimestamp::Error => panic!(
+            "column \"{}\" has timestamps with timezone in the parquet file, which cannot be \
+             read into timestamp with timestamptz_to_timestamp 'error'",
+            context.name()
+        ),
+    }
+}
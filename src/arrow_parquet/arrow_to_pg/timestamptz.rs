@@ -1,17 +1,23 @@
 use arrow::array::{Array, TimestampMicrosecondArray};
 use pgrx::datum::TimestampWithTimeZone;
 
-use crate::type_compat::pg_arrow_type_conversions::i64_to_timestamptz;
+use crate::type_compat::pg_arrow_type_conversions::{i64_to_timestamptz, local_i64_to_timestamptz};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
+// The values of timestamps with a timezone are UTC instants, whatever the timezone is, e.g.
+// "+05:30" or "Europe/Berlin". The timezone is only for display, hence it is not applied again.
+
 // Timestamptz
 impl ArrowArrayToPgType<TimestampWithTimeZone> for TimestampMicrosecondArray {
-    fn to_pg_type(self, context: &ArrowToPgAttributeContext) -> Option<TimestampWithTimeZone> {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<TimestampWithTimeZone> {
         if self.is_null(0) {
             None
+        } else if self.timezone().is_none() {
+            // coerced from timestamp
+            Some(local_i64_to_timestamptz(self.value(0)))
         } else {
-            Some(i64_to_timestamptz(self.value(0), context.timezone()))
+            Some(i64_to_timestamptz(self.value(0), "UTC"))
         }
     }
 }
@@ -20,14 +26,22 @@ impl ArrowArrayToPgType<TimestampWithTimeZone> for TimestampMicrosecondArray {
 impl ArrowArrayToPgType<Vec<Option<TimestampWithTimeZone>>> for TimestampMicrosecondArray {
     fn to_pg_type(
         self,
-        element_context: &ArrowToPgAttributeContext,
+        _element_context: &ArrowToPgAttributeContext,
     ) -> Option<Vec<Option<TimestampWithTimeZone>>> {
         let mut vals = vec![];
 
-        let timezone = element_context.timezone();
+        if self.timezone().is_none() {
+            // coerced from timestamp[]
+            for val in self.iter() {
+                let val = val.map(local_i64_to_timestamptz);
+                vals.push(val);
+            }
+
+            return Some(vals);
+        }
 
         for val in self.iter() {
-            let val = val.map(|v| i64_to_timestamptz(v, timezone));
+            let val = val.map(|v| i64_to_timestamptz(v, "UTC"));
             vals.push(val);
         }
         Some(vals)
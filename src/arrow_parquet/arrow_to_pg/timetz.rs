@@ -1,11 +1,13 @@
-use arrow::array::{Array, Time64MicrosecondArray};
+use arrow::array::{Array, Int32Array, StructArray, Time64MicrosecondArray};
 use pgrx::datum::TimeWithTimeZone;
 
-use crate::type_compat::pg_arrow_type_conversions::i64_to_timetz;
+use crate::type_compat::pg_arrow_type_conversions::{
+    i64_to_timetz, time_and_offset_to_timetz, TIMETZ_OFFSET_FIELD_NAME, TIMETZ_TIME_FIELD_NAME,
+};
 
 use super::{ArrowArrayToPgType, ArrowToPgAttributeContext};
 
-// Timetz
+// Timetz from times, which are adjusted to UTC
 impl ArrowArrayToPgType<TimeWithTimeZone> for Time64MicrosecondArray {
     fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<TimeWithTimeZone> {
         if self.is_null(0) {
@@ -16,7 +18,7 @@ impl ArrowArrayToPgType<TimeWithTimeZone> for Time64MicrosecondArray {
     }
 }
 
-// Timetz[]
+// Timetz[] from times, which are adjusted to UTC
 impl ArrowArrayToPgType<Vec<Option<TimeWithTimeZone>>> for Time64MicrosecondArray {
     fn to_pg_type(
         self,
@@ -30,3 +32,47 @@ impl ArrowArrayToPgType<Vec<Option<TimeWithTimeZone>>> for Time64MicrosecondArra
         Some(vals)
     }
 }
+
+// Timetz
+impl ArrowArrayToPgType<TimeWithTimeZone> for StructArray {
+    fn to_pg_type(self, _context: &ArrowToPgAttributeContext) -> Option<TimeWithTimeZone> {
+        timetzs(&self).pop().expect("empty timetz array")
+    }
+}
+
+// Timetz[]
+impl ArrowArrayToPgType<Vec<Option<TimeWithTimeZone>>> for StructArray {
+    fn to_pg_type(
+        self,
+        _context: &ArrowToPgAttributeContext,
+    ) -> Option<Vec<Option<TimeWithTimeZone>>> {
+        Some(timetzs(&self))
+    }
+}
+
+fn timetzs(struct_array: &StructArray) -> Vec<Option<TimeWithTimeZone>> {
+    let times = struct_array_column::<Time64MicrosecondArray>(struct_array, TIMETZ_TIME_FIELD_NAME);
+    let offsets = struct_array_column::<Int32Array>(struct_array, TIMETZ_OFFSET_FIELD_NAME);
+
+    (0..struct_array.len())
+        .map(|idx| {
+            if struct_array.is_null(idx) {
+                return None;
+            }
+
+            Some(time_and_offset_to_timetz(
+                times.value(idx),
+                offsets.value(idx),
+            ))
+        })
+        .collect()
+}
+
+fn struct_array_column<'a, T: 'static>(struct_array: &'a StructArray, name: &str) -> &'a T {
+    struct_array
+        .column_by_name(name)
+        .unwrap_or_else(|| panic!("timetz field {} not found", name))
+        .as_any()
+        .downcast_ref::<T>()
+        .unwrap_or_else(|| panic!("unexpected type of timetz field {}", name))
+}
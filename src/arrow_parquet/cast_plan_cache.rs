@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int},
+};
+
+use arrow_schema::SchemaRef;
+use pgrx::{
+    pg_guard,
+    pg_sys::{
+        CacheRegisterRelcacheCallback, CacheRegisterSyscacheCallback, Datum, Oid,
+        SysCacheIdentifier::{CASTSOURCETARGET, TYPEOID},
+    },
+    PgTupleDesc,
+};
+
+use super::{
+    extra_columns::ExtraColumns, list_coercion::ListCoercion, match_by::MatchBy,
+    missing_columns::MissingColumns, schema_parser::FileColumnMatch,
+};
+
+// CastPlanKey identifies the schema negotiation of a COPY FROM. Loading many files with the same
+// schema into the same table negotiates the schemas once per session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CastPlanKey {
+    relation_oid: u32,
+    // name, attnum, type, typmod and generated kind of the columns in the COPY's column list
+    attributes: Vec<(String, i16, u32, i32, c_char)>,
+    // the arrow schema in the file's footer
+    file_schema: SchemaRef,
+    match_by: MatchBy,
+    field_ids: Option<Vec<(String, i32)>>,
+    missing_columns: MissingColumns,
+    extra_columns: ExtraColumns,
+    list_coercion: ListCoercion,
+}
+
+impl CastPlanKey {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        relation_oid: Oid,
+        tupledesc: &PgTupleDesc,
+        file_schema: SchemaRef,
+        match_by: MatchBy,
+        field_ids: Option<&HashMap<String, i32>>,
+        missing_columns: MissingColumns,
+        extra_columns: ExtraColumns,
+        list_coercion: ListCoercion,
+    ) -> Self {
+        let attributes = tupledesc
+            .iter()
+            .filter(|attribute| !attribute.is_dropped())
+            .map(|attribute| {
+                (
+                    attribute.name().to_string(),
+                    attribute.attnum,
+                    attribute.atttypid.as_u32(),
+                    attribute.atttypmod,
+                    attribute.attgenerated,
+                )
+            })
+            .collect();
+
+        let field_ids = field_ids.map(|field_ids| {
+            let mut field_ids = field_ids
+                .iter()
+                .map(|(column_name, field_id)| (column_name.clone(), *field_id))
+                .collect::<Vec<_>>();
+
+            field_ids.sort();
+
+            field_ids
+        });
+
+        Self {
+            relation_oid: relation_oid.as_u32(),
+            attributes,
+            file_schema,
+            match_by,
+            field_ids,
+            missing_columns,
+            extra_columns,
+            list_coercion,
+        }
+    }
+}
+
+// CastPlan is the result of the schema negotiation between the file and the table.
+#[derive(Debug, Clone)]
+pub(crate) struct CastPlan {
+    // the file schema without the extra columns, renamed to the table columns with
+    // match_by 'field_id'
+    pub(crate) file_schema: SchemaRef,
+    // the schema of the table columns, as they are read
+    pub(crate) tupledesc_schema: SchemaRef,
+    // the leaves of the file, which are read
+    pub(crate) projection_leaves: Vec<usize>,
+    // the match of each table column with the file columns
+    pub(crate) column_matches: Vec<FileColumnMatch>,
+}
+
+// the cache is dropped when it grows beyond this many plans, e.g. loading files with
+// ever-changing schemas
+const MAX_CAST_PLANS: usize = 64;
+
+static mut CAST_PLANS: Option<HashMap<CastPlanKey, CastPlan>> = None;
+
+// CAST_PLANS_GENERATION is bumped by the invalidation callbacks. We do not drop the plans in
+// the callbacks since they might fire while a plan is being negotiated. The plans of an older
+// generation are dropped at the next lookup.
+static mut CAST_PLANS_GENERATION: u64 = 0;
+
+static mut CAST_PLANS_VALID_GENERATION: u64 = 0;
+
+// CAST_PLAN_NEGOTIATION_COUNT is the number of times a cast plan is negotiated
+static mut CAST_PLAN_NEGOTIATION_COUNT: i64 = 0;
+
+// cached_cast_plan returns the cast plan for the key, negotiating it with "negotiate" if it is
+// not cached. Only successfully negotiated plans are cached.
+pub(crate) fn cached_cast_plan<F>(key: CastPlanKey, negotiate: F) -> CastPlan
+where
+    F: FnOnce() -> CastPlan,
+{
+    #[allow(static_mut_refs)]
+    unsafe {
+        if CAST_PLANS_VALID_GENERATION != CAST_PLANS_GENERATION {
+            CAST_PLANS.take();
+            CAST_PLANS_VALID_GENERATION = CAST_PLANS_GENERATION;
+        }
+
+        if let Some(cast_plan) = CAST_PLANS.as_ref().and_then(|plans| plans.get(&key)) {
+            return cast_plan.clone();
+        }
+
+        // an invalidation during the negotiation might make the plan stale
+        let generation = CAST_PLANS_GENERATION;
+
+        let cast_plan = negotiate();
+
+        CAST_PLAN_NEGOTIATION_COUNT += 1;
+
+        if generation == CAST_PLANS_GENERATION {
+            let cast_plans = CAST_PLANS.get_or_insert_with(HashMap::new);
+
+            if cast_plans.len() >= MAX_CAST_PLANS {
+                cast_plans.clear();
+            }
+
+            cast_plans.insert(key, cast_plan.clone());
+        }
+
+        cast_plan
+    }
+}
+
+// register_cast_plan_invalidation registers the callbacks, which invalidate the cached cast
+// plans. Any relcache invalidation drops all plans, since altering a composite type only
+// invalidates the relcache entry of the composite type, not of the tables using it. Types and
+// casts affect the negotiation as well, e.g. a NOT NULL constraint of a domain.
+pub(crate) fn register_cast_plan_invalidation() {
+    unsafe {
+        CacheRegisterRelcacheCallback(Some(invalidate_cast_plans_by_relation), Datum::from(0));
+
+        CacheRegisterSyscacheCallback(TYPEOID as _, Some(invalidate_cast_plans), Datum::from(0));
+
+        CacheRegisterSyscacheCallback(
+            CASTSOURCETARGET as _,
+            Some(invalidate_cast_plans),
+            Datum::from(0),
+        );
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn invalidate_cast_plans_by_relation(_arg: Datum, _relid: Oid) {
+    CAST_PLANS_GENERATION += 1;
+}
+
+#[pg_guard]
+unsafe extern "C" fn invalidate_cast_plans(_arg: Datum, _cacheid: c_int, _hashvalue: u32) {
+    CAST_PLANS_GENERATION += 1;
+}
+
+// The following udfs are only used for testing purposes.
+#[cfg(feature = "pg_test")]
+#[pgrx::pg_schema]
+mod parquet_test {
+    use pgrx::pg_extern;
+
+    use super::CAST_PLAN_NEGOTIATION_COUNT;
+
+    #[pg_extern]
+    fn cast_plan_negotiation_count() -> i64 {
+        unsafe { CAST_PLAN_NEGOTIATION_COUNT }
+    }
+}
@@ -1,6 +1,18 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    fmt::Display,
+    str::FromStr,
+};
 
 use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use pgrx::{
+    pg_guard,
+    pg_sys::{
+        pstrdup, DefineCustomIntVariable, DefineCustomStringVariable, GUC_check_errdetail_string,
+        GucContext, GucSource,
+    },
+    AsPgCStr,
+};
 use url::Url;
 
 #[repr(C)]
@@ -39,30 +51,155 @@ impl PgParquetCompression {
     }
 
     pub(crate) fn ensure_compression_level(&self, compression_level: i32) {
-        if compression_level != INVALID_COMPRESSION_LEVEL {
-            match self {
-                PgParquetCompression::Zstd => {
-                    ZstdLevel::try_new(compression_level).unwrap_or_else(|e| panic!("{}", e));
-                }
-                PgParquetCompression::Gzip => {
-                    GzipLevel::try_new(compression_level as u32)
-                        .unwrap_or_else(|e| panic!("{}", e));
-                }
-                PgParquetCompression::Brotli => {
-                    BrotliLevel::try_new(compression_level as u32)
-                        .unwrap_or_else(|e| panic!("{}", e));
-                }
-                _ => panic!(
-                    "compression level is not supported for \"{}\" compression",
-                    self
-                ),
-            }
+        self.check_compression_level(compression_level)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub(crate) fn check_compression_level(&self, compression_level: i32) -> Result<(), String> {
+        if compression_level == INVALID_COMPRESSION_LEVEL {
+            return Ok(());
+        }
+
+        match self {
+            PgParquetCompression::Zstd => ZstdLevel::try_new(compression_level)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            PgParquetCompression::Gzip => GzipLevel::try_new(compression_level as u32)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            PgParquetCompression::Brotli => BrotliLevel::try_new(compression_level as u32)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            _ => Err(format!(
+                "compression level is not supported for \"{}\" compression",
+                self
+            )),
         }
     }
 }
 
 pub(crate) const INVALID_COMPRESSION_LEVEL: i32 = -1;
 
+// COMPRESSION_PRECEDENCE explains how COPY TO determines the compression.
+pub(crate) const COMPRESSION_PRECEDENCE: &str = "COPY TO uses the compression option, then the \
+    compression of the file extension, then pg_parquet.default_compression and \
+    pg_parquet.default_compression_level";
+
+// DEFAULT_COMPRESSION and DEFAULT_COMPRESSION_LEVEL are used by COPY TO when neither the
+// compression option nor the file extension specifies a compression, e.g. for ".parquet" files.
+// They are defined without pgrx, which does not support check hooks, so that invalid values
+// are rejected at SET time.
+static mut DEFAULT_COMPRESSION: *mut c_char = std::ptr::null_mut();
+
+static mut DEFAULT_COMPRESSION_LEVEL: c_int = INVALID_COMPRESSION_LEVEL;
+
+pub(crate) fn define_default_compression_gucs() {
+    unsafe {
+        DefineCustomStringVariable(
+            c"pg_parquet.default_compression".as_ptr(),
+            c"Compression used by COPY TO when neither the compression option nor the file extension specifies one".as_ptr(),
+            c"Supported compressions are uncompressed, snappy, gzip, lz4, lz4_raw, brotli and zstd.".as_ptr(),
+            std::ptr::addr_of_mut!(DEFAULT_COMPRESSION),
+            c"snappy".as_ptr(),
+            GucContext::PGC_USERSET,
+            0,
+            Some(check_default_compression),
+            None,
+            None,
+        );
+
+        DefineCustomIntVariable(
+            c"pg_parquet.default_compression_level".as_ptr(),
+            c"Compression level used by COPY TO with pg_parquet.default_compression".as_ptr(),
+            c"Only used when COPY TO uses the default compression, and it supports compression levels. -1 means the compression's own default level.".as_ptr(),
+            std::ptr::addr_of_mut!(DEFAULT_COMPRESSION_LEVEL),
+            INVALID_COMPRESSION_LEVEL,
+            INVALID_COMPRESSION_LEVEL,
+            22,
+            GucContext::PGC_USERSET,
+            0,
+            Some(check_default_compression_level),
+            None,
+            None,
+        );
+    }
+}
+
+pub(crate) fn default_compression() -> PgParquetCompression {
+    let default_compression = unsafe { DEFAULT_COMPRESSION };
+
+    if default_compression.is_null() {
+        return PgParquetCompression::default();
+    }
+
+    let default_compression = unsafe { CStr::from_ptr(default_compression) };
+
+    default_compression
+        .to_str()
+        .ok()
+        .and_then(|compression| PgParquetCompression::from_str(compression).ok())
+        .unwrap_or_default()
+}
+
+// default_compression_level returns pg_parquet.default_compression_level if the default
+// compression supports compression levels, otherwise the level is ignored.
+pub(crate) fn default_compression_level() -> Option<i32> {
+    let compression = default_compression();
+
+    let default_level = compression.default_compression_level()?;
+
+    let level = unsafe { DEFAULT_COMPRESSION_LEVEL };
+
+    if level == INVALID_COMPRESSION_LEVEL {
+        Some(default_level)
+    } else {
+        Some(level)
+    }
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_default_compression(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: GucSource::Type,
+) -> bool {
+    if (*newval).is_null() {
+        return true;
+    }
+
+    let compression = CStr::from_ptr(*newval).to_str().unwrap_or_default();
+
+    if let Err(e) = PgParquetCompression::from_str(compression) {
+        GUC_check_errdetail_string = pstrdup(e.as_pg_cstr());
+        return false;
+    }
+
+    true
+}
+
+// check_default_compression_level checks the level against the current default compression.
+// Levels of compressions, which do not support them, are ignored. Hence, they are not checked,
+// so that the level can be set before the compression.
+#[pg_guard]
+unsafe extern "C" fn check_default_compression_level(
+    newval: *mut c_int,
+    _extra: *mut *mut c_void,
+    _source: GucSource::Type,
+) -> bool {
+    let compression = default_compression();
+
+    if compression.default_compression_level().is_none() {
+        return true;
+    }
+
+    if let Err(e) = compression.check_compression_level(*newval) {
+        GUC_check_errdetail_string = pstrdup(e.as_pg_cstr());
+        return false;
+    }
+
+    true
+}
+
 pub(crate) struct PgParquetCompressionWithLevel {
     pub(crate) compression: PgParquetCompression,
     pub(crate) compression_level: i32,
@@ -147,6 +284,9 @@ impl TryFrom<Url> for PgParquetCompression {
             Ok(PgParquetCompression::Gzip)
         } else if path.ends_with(".parquet.lz4") {
             Ok(PgParquetCompression::Lz4)
+        } else if path.ends_with(".parquet.lz4raw") {
+            // older readers only support LZ4_RAW, not the deprecated LZ4 with hadoop framing
+            Ok(PgParquetCompression::Lz4raw)
         } else if path.ends_with(".parquet.br") {
             Ok(PgParquetCompression::Brotli)
         } else if path.ends_with(".parquet.zst") {
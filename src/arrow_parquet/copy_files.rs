@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+// CopyFile is a parquet file, which is written by the last COPY TO of the session.
+#[derive(Debug, Clone)]
+pub(crate) struct CopyFile {
+    pub(crate) uri: Url,
+    pub(crate) row_count: i64,
+    pub(crate) size_bytes: i64,
+}
+
+// CopyStats sums up the files, which are written by the last COPY TO of the session.
+#[derive(Debug, Clone)]
+pub(crate) struct CopyStats {
+    pub(crate) file_count: i32,
+    pub(crate) row_count: i64,
+    pub(crate) size_bytes: i64,
+    pub(crate) duration: Duration,
+}
+
+// files written by the last COPY TO, in the order they are finished
+static mut LAST_COPY_FILES: Vec<CopyFile> = Vec::new();
+
+// start of the last COPY TO
+static mut LAST_COPY_STARTED_AT: Option<Instant> = None;
+
+// duration of the last COPY TO, which is only set when it completes
+static mut LAST_COPY_DURATION: Option<Duration> = None;
+
+// start_copy_files forgets the files of the previous COPY TO.
+pub(crate) fn start_copy_files() {
+    #[allow(static_mut_refs)]
+    unsafe {
+        LAST_COPY_FILES.clear();
+        LAST_COPY_STARTED_AT = Some(Instant::now());
+        LAST_COPY_DURATION = None;
+    };
+}
+
+// finish_copy_files marks the ongoing COPY TO as completed. It returns the number of rows in
+// its files, which the command tag reports. A failed COPY TO is never completed, even if its
+// partial files are kept.
+pub(crate) fn finish_copy_files() -> u64 {
+    unsafe {
+        LAST_COPY_DURATION = LAST_COPY_STARTED_AT.map(|started_at| started_at.elapsed());
+    };
+
+    last_copy_files()
+        .iter()
+        .map(|file| file.row_count as u64)
+        .sum()
+}
+
+// record_copy_file records the file, which is finished by the ongoing COPY TO.
+pub(crate) fn record_copy_file(uri: &Url, row_count: i64, size_bytes: i64) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        LAST_COPY_FILES.push(CopyFile {
+            uri: uri.clone(),
+            row_count,
+            size_bytes,
+        })
+    };
+}
+
+pub(crate) fn last_copy_files() -> Vec<CopyFile> {
+    #[allow(static_mut_refs)]
+    unsafe {
+        LAST_COPY_FILES.clone()
+    }
+}
+
+// last_copy_stats returns the stats of the last COPY TO, or None if it failed.
+pub(crate) fn last_copy_stats() -> Option<CopyStats> {
+    let duration = unsafe { LAST_COPY_DURATION }?;
+
+    let files = last_copy_files();
+
+    Some(CopyStats {
+        file_count: files.len() as i32,
+        row_count: files.iter().map(|file| file.row_count).sum(),
+        size_bytes: files.iter().map(|file| file.size_bytes).sum(),
+        duration,
+    })
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use pgrx::{
+    pg_sys::{namestrcpy, CreateTupleDescCopy},
+    AsPgCStr, PgTupleDesc,
+};
+
+// PgParquetDuplicateColumns decides what COPY TO does with the columns of its query, which have
+// the same name as an earlier column, e.g. "?column?" for the unnamed columns of
+// "SELECT 1, 2". Many readers cannot read the files with duplicate field names.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetDuplicateColumns {
+    #[default]
+    Rename,
+    Error,
+}
+
+pub(crate) fn all_supported_duplicate_columns() -> Vec<PgParquetDuplicateColumns> {
+    vec![
+        PgParquetDuplicateColumns::Rename,
+        PgParquetDuplicateColumns::Error,
+    ]
+}
+
+impl Display for PgParquetDuplicateColumns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetDuplicateColumns::Rename => write!(f, "rename"),
+            PgParquetDuplicateColumns::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for PgParquetDuplicateColumns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rename" => Ok(PgParquetDuplicateColumns::Rename),
+            "error" => Ok(PgParquetDuplicateColumns::Error),
+            _ => Err(format!("unrecognized duplicate_columns: {}", s)),
+        }
+    }
+}
+
+// rename_duplicate_attributes returns a copy of the tuple descriptor, in which the attributes,
+// whose names are already used by an earlier attribute, are renamed by appending the smallest
+// unused suffix, e.g. "x_1" for the second "x". The renames are reported by a notice. Their
+// order, and hence the field ids, do not change.
+pub(crate) fn rename_duplicate_attributes(tupledesc: &PgTupleDesc) -> PgTupleDesc<'static> {
+    let tupledesc = unsafe { CreateTupleDescCopy(tupledesc.as_ptr()) };
+
+    let natts = unsafe { (*tupledesc).natts } as usize;
+
+    let attributes = unsafe { (*tupledesc).attrs.as_mut_slice(natts) };
+
+    let all_names = attributes
+        .iter()
+        .filter(|attribute| !attribute.is_dropped())
+        .map(|attribute| attribute.name().to_string())
+        .collect::<HashSet<_>>();
+
+    let mut used_names = HashSet::new();
+    let mut renames = vec![];
+
+    for attribute in attributes {
+        if attribute.is_dropped() {
+            continue;
+        }
+
+        let name = attribute.name().to_string();
+
+        if used_names.insert(name.clone()) {
+            continue;
+        }
+
+        let new_name = (1..)
+            .map(|suffix| format!("{}_{}", name, suffix))
+            .find(|new_name| !all_names.contains(new_name) && !used_names.contains(new_name))
+            .expect("unused column name");
+
+        unsafe { namestrcpy(&mut attribute.attname, new_name.as_pg_cstr()) };
+
+        renames.push(format!("\"{}\" to \"{}\"", name, new_name));
+
+        used_names.insert(new_name);
+    }
+
+    if !renames.is_empty() {
+        pgrx::notice!("duplicate columns are renamed: {}", renames.join(", "));
+    }
+
+    unsafe { PgTupleDesc::from_pg_unchecked(tupledesc) }
+}
@@ -0,0 +1,35 @@
+use std::str::FromStr;
+
+use super::match_by::MatchBy;
+
+// ExtraColumns determines how COPY FROM handles the parquet file columns and struct subfields,
+// which are not found in the table or in the composite type of the column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ExtraColumns {
+    // the extra columns are not read from the file at all
+    Ignore,
+    Error,
+}
+
+impl ExtraColumns {
+    // default_for returns the default of extra_columns. Files are matched by name e.g. when
+    // their columns are reordered or added over time, hence their extra columns are ignored.
+    pub(crate) fn default_for(match_by: MatchBy) -> Self {
+        match match_by {
+            MatchBy::Position => ExtraColumns::Error,
+            MatchBy::Name | MatchBy::FieldId => ExtraColumns::Ignore,
+        }
+    }
+}
+
+impl FromStr for ExtraColumns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(ExtraColumns::Ignore),
+            "error" => Ok(ExtraColumns::Error),
+            _ => Err(format!("unrecognized extra_columns option: {}", s)),
+        }
+    }
+}
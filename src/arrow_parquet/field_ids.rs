@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use arrow::array::RecordBatch;
+use arrow_schema::{Field, FieldRef, Fields, Schema};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use pgrx::pg_sys::FormData_pg_attribute;
+
+// attribute_field_id returns the field id of the column, which is its attnum minus one. Dropped
+// columns keep their attnums, hence the ids of the remaining columns do not shift.
+pub(crate) fn attribute_field_id(attribute: &FormData_pg_attribute) -> i32 {
+    attribute.attnum as i32 - 1
+}
+
+// with_field_id returns the field with the given field id in its metadata.
+pub(crate) fn with_field_id(field: FieldRef, field_id: i32) -> FieldRef {
+    let mut metadata = field.metadata().clone();
+    metadata.insert(PARQUET_FIELD_ID_META_KEY.into(), field_id.to_string());
+
+    Arc::new(field.as_ref().clone().with_metadata(metadata))
+}
+
+// field_id returns the field id of the field, which is read from the parquet file.
+fn field_id(field: &Field) -> Option<i32> {
+    field
+        .metadata()
+        .get(PARQUET_FIELD_ID_META_KEY)
+        .and_then(|field_id| field_id.parse().ok())
+}
+
+// parse_field_ids parses the column names and their field ids from the json object,
+// e.g. '{"a": 1, "b": 3}'.
+pub(crate) fn parse_field_ids(field_ids: &str) -> Result<HashMap<String, i32>, String> {
+    let field_ids = match serde_json::from_str::<serde_json::Value>(field_ids) {
+        Ok(serde_json::Value::Object(field_ids)) => field_ids,
+        _ => return Err("field_ids must be a json object".into()),
+    };
+
+    let mut column_field_ids = HashMap::new();
+
+    for (column_name, field_id) in field_ids {
+        let Some(field_id) = field_id.as_i64().and_then(|id| i32::try_from(id).ok()) else {
+            return Err(format!(
+                "field_ids value of column \"{}\" must be an integer",
+                column_name
+            ));
+        };
+
+        if let Some((other_column_name, _)) =
+            column_field_ids.iter().find(|(_, id)| **id == field_id)
+        {
+            return Err(format!(
+                "field_id {} is given to both \"{}\" and \"{}\"",
+                field_id, other_column_name, column_name
+            ));
+        }
+
+        column_field_ids.insert(column_name, field_id);
+    }
+
+    Ok(column_field_ids)
+}
+
+// table_field_ids returns the field ids of the table columns, which are the given field ids, or
+// the field ids, which COPY TO writes for the columns of the table.
+pub(crate) fn table_field_ids(
+    attributes: &[FormData_pg_attribute],
+    field_ids: Option<HashMap<String, i32>>,
+) -> HashMap<String, i32> {
+    let Some(field_ids) = field_ids else {
+        return attributes
+            .iter()
+            .map(|attribute| (attribute.name().to_string(), attribute_field_id(attribute)))
+            .collect();
+    };
+
+    for column_name in field_ids.keys() {
+        if !attributes
+            .iter()
+            .any(|attribute| attribute.name() == column_name)
+        {
+            panic!(
+                "column \"{}\" in field_ids is not found in the table",
+                column_name
+            );
+        }
+    }
+
+    field_ids
+}
+
+// rename_file_fields_by_field_id renames the columns of the parquet file to the table columns
+// with the same field ids, so that match_by 'field_id' matches them by name afterwards. The
+// file columns, whose field ids are not in the table, are renamed only if they have the name
+// of a table column, which must not be matched to them.
+pub(crate) fn rename_file_fields_by_field_id(
+    file_schema: &Schema,
+    table_field_ids: &HashMap<String, i32>,
+) -> Schema {
+    let table_columns = table_field_ids
+        .iter()
+        .map(|(column_name, field_id)| (*field_id, column_name))
+        .collect::<HashMap<_, _>>();
+
+    let mut file_field_ids = HashSet::new();
+
+    let fields = file_schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let Some(field_id) = field_id(field) else {
+                panic!("parquet file column \"{}\" has no field_id", field.name());
+            };
+
+            if !file_field_ids.insert(field_id) {
+                panic!("parquet file has duplicate field_id {}", field_id);
+            }
+
+            let name = match table_columns.get(&field_id) {
+                Some(column_name) => column_name.to_string(),
+                None if table_field_ids.contains_key(field.name()) => {
+                    format!("{} (field_id {})", field.name(), field_id)
+                }
+                None => field.name().to_string(),
+            };
+
+            Arc::new(field.as_ref().clone().with_name(name))
+        })
+        .collect::<Fields>();
+
+    Schema::new_with_metadata(fields, file_schema.metadata().clone())
+}
+
+// rename_record_batch_columns renames the columns of the record batch, which is read from the
+// parquet file, to the columns of the renamed file schema.
+pub(crate) fn rename_record_batch_columns(
+    record_batch: RecordBatch,
+    renamed_schema: &Schema,
+) -> RecordBatch {
+    let fields = record_batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(renamed_schema.fields())
+        .map(|(field, renamed_field)| {
+            Arc::new(field.as_ref().clone().with_name(renamed_field.name()))
+        })
+        .collect::<Fields>();
+
+    let schema = Schema::new_with_metadata(fields, record_batch.schema().metadata().clone());
+
+    RecordBatch::try_new(Arc::new(schema), record_batch.columns().to_vec())
+        .unwrap_or_else(|e| panic!("failed to rename record batch columns: {}", e))
+}
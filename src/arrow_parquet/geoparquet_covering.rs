@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, Float64Array, StructArray},
+    buffer::NullBuffer,
+};
+use arrow_schema::{DataType, Field, FieldRef, Fields, Schema};
+use pgrx::pg_sys::FormData_pg_attribute;
+
+use crate::type_compat::geometry::{geoparquet_bbox_column_name, is_postgis_geometry_type};
+
+// GeoparquetCovering is the bbox column, which is written next to a geometry column with
+// geoparquet_covering option, e.g. "geom_bbox" struct {xmin, ymin, xmax, ymax} for "geom".
+// Readers use it for spatial filtering by row group statistics without decoding the WKBs.
+// See "covering" at https://geoparquet.org/releases/v1.1.0
+#[derive(Debug, Clone)]
+pub(crate) struct GeoparquetCovering {
+    geometry_column_idx: usize,
+    geometry_column_name: String,
+    bbox_field: FieldRef,
+}
+
+impl GeoparquetCovering {
+    pub(crate) fn bbox_field(&self) -> FieldRef {
+        self.bbox_field.clone()
+    }
+
+    // bbox_array returns the bbox of each row's geometry in the record batch. The bbox is
+    // computed from the WKB, which is already built by the conversion, instead of calling
+    // postgis again. It is null for null and empty geometries.
+    pub(crate) fn bbox_array(&self, attribute_arrays: &[ArrayRef]) -> ArrayRef {
+        let wkb_array = attribute_arrays[self.geometry_column_idx]
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .expect("geometry column is not a binary array");
+
+        let mut xmins = Vec::with_capacity(wkb_array.len());
+        let mut ymins = Vec::with_capacity(wkb_array.len());
+        let mut xmaxs = Vec::with_capacity(wkb_array.len());
+        let mut ymaxs = Vec::with_capacity(wkb_array.len());
+        let mut validity = Vec::with_capacity(wkb_array.len());
+
+        for wkb in wkb_array.iter() {
+            let bbox = wkb.and_then(|wkb| {
+                wkb_bounding_box(wkb).unwrap_or_else(|e| {
+                    panic!(
+                        "failed to compute bbox for geometry column \"{}\": {}",
+                        self.geometry_column_name, e
+                    )
+                })
+            });
+
+            let bbox = bbox.unwrap_or_default();
+
+            xmins.push(bbox.xmin);
+            ymins.push(bbox.ymin);
+            xmaxs.push(bbox.xmax);
+            ymaxs.push(bbox.ymax);
+            validity.push(!bbox.is_empty());
+        }
+
+        let DataType::Struct(bbox_fields) = self.bbox_field.data_type() else {
+            unreachable!("bbox field is not a struct");
+        };
+
+        let bbox_array = StructArray::new(
+            bbox_fields.clone(),
+            vec![
+                Arc::new(Float64Array::from(xmins)),
+                Arc::new(Float64Array::from(ymins)),
+                Arc::new(Float64Array::from(xmaxs)),
+                Arc::new(Float64Array::from(ymaxs)),
+            ],
+            Some(NullBuffer::from(validity)),
+        );
+
+        Arc::new(bbox_array)
+    }
+}
+
+// geoparquet_coverings returns the bbox columns for the top level geometry columns.
+// It fails if a bbox column name is already used by another column.
+pub(crate) fn geoparquet_coverings(
+    attributes: &[FormData_pg_attribute],
+    schema: &Schema,
+) -> Vec<GeoparquetCovering> {
+    let mut coverings = vec![];
+
+    for (geometry_column_idx, attribute) in attributes.iter().enumerate() {
+        if !is_postgis_geometry_type(attribute.atttypid) {
+            continue;
+        }
+
+        let geometry_column_name = attribute.name().to_string();
+
+        let bbox_column_name = geoparquet_bbox_column_name(&geometry_column_name);
+
+        if schema.column_with_name(&bbox_column_name).is_some() {
+            panic!(
+                "column \"{}\" conflicts with the geoparquet covering column of \"{}\"",
+                bbox_column_name, geometry_column_name
+            );
+        }
+
+        let bbox_fields = Fields::from(
+            ["xmin", "ymin", "xmax", "ymax"]
+                .into_iter()
+                .map(|name| Field::new(name, DataType::Float64, false))
+                .collect::<Vec<_>>(),
+        );
+
+        let bbox_field = Arc::new(Field::new(
+            bbox_column_name,
+            DataType::Struct(bbox_fields),
+            true,
+        ));
+
+        coverings.push(GeoparquetCovering {
+            geometry_column_idx,
+            geometry_column_name,
+            bbox_field,
+        });
+    }
+
+    coverings
+}
+
+#[derive(Debug, PartialEq)]
+struct BoundingBox {
+    xmin: f64,
+    ymin: f64,
+    xmax: f64,
+    ymax: f64,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self {
+            xmin: f64::INFINITY,
+            ymin: f64::INFINITY,
+            xmax: f64::NEG_INFINITY,
+            ymax: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl BoundingBox {
+    fn is_empty(&self) -> bool {
+        self.xmin > self.xmax || self.ymin > self.ymax
+    }
+
+    fn extend(&mut self, x: f64, y: f64) {
+        // empty points are encoded as NaN coordinates
+        if x.is_nan() || y.is_nan() {
+            return;
+        }
+
+        self.xmin = self.xmin.min(x);
+        self.ymin = self.ymin.min(y);
+        self.xmax = self.xmax.max(x);
+        self.ymax = self.ymax.max(y);
+    }
+}
+
+// WkbReader reads the numbers of a WKB in the byte order of the current geometry
+struct WkbReader<'a> {
+    wkb: &'a [u8],
+    offset: usize,
+    little_endian: bool,
+}
+
+impl WkbReader<'_> {
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .wkb
+            .get(self.offset..self.offset + N)
+            .ok_or("unexpected end of WKB")?;
+
+        self.offset += N;
+
+        Ok(bytes.try_into().expect("invalid WKB slice length"))
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes::<1>()?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.read_bytes::<4>()?;
+
+        if self.little_endian {
+            Ok(u32::from_le_bytes(bytes))
+        } else {
+            Ok(u32::from_be_bytes(bytes))
+        }
+    }
+
+    fn read_f64(&mut self) -> Result<f64, String> {
+        let bytes = self.read_bytes::<8>()?;
+
+        if self.little_endian {
+            Ok(f64::from_le_bytes(bytes))
+        } else {
+            Ok(f64::from_be_bytes(bytes))
+        }
+    }
+}
+
+// wkb_bounding_box returns the 2D bounding box of the WKB, or None for empty geometries
+fn wkb_bounding_box(wkb: &[u8]) -> Result<Option<BoundingBox>, String> {
+    let mut reader = WkbReader {
+        wkb,
+        offset: 0,
+        little_endian: true,
+    };
+
+    let mut bbox = BoundingBox::default();
+
+    read_wkb_geometry(&mut reader, &mut bbox)?;
+
+    if bbox.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(bbox))
+    }
+}
+
+// EWKB flags, which postgis sets on the geometry type instead of ISO WKB type codes
+const EWKB_Z_FLAG: u32 = 0x80000000;
+const EWKB_M_FLAG: u32 = 0x40000000;
+const EWKB_SRID_FLAG: u32 = 0x20000000;
+
+fn read_wkb_geometry(reader: &mut WkbReader, bbox: &mut BoundingBox) -> Result<(), String> {
+    reader.little_endian = match reader.read_u8()? {
+        0 => false,
+        1 => true,
+        byte_order => return Err(format!("invalid WKB byte order {}", byte_order)),
+    };
+
+    let wkb_type = reader.read_u32()?;
+
+    let has_srid = wkb_type & EWKB_SRID_FLAG != 0;
+
+    let mut has_z = wkb_type & EWKB_Z_FLAG != 0;
+    let mut has_m = wkb_type & EWKB_M_FLAG != 0;
+
+    // ISO WKB type codes, e.g. 1001 is Point Z, 2001 is Point M and 3001 is Point ZM
+    let iso_wkb_type = wkb_type & !(EWKB_Z_FLAG | EWKB_M_FLAG | EWKB_SRID_FLAG);
+
+    let geometry_type = iso_wkb_type % 1000;
+
+    match iso_wkb_type / 1000 {
+        0 => {}
+        1 => has_z = true,
+        2 => has_m = true,
+        3 => {
+            has_z = true;
+            has_m = true;
+        }
+        _ => return Err(format!("invalid WKB geometry type {}", wkb_type)),
+    }
+
+    if has_srid {
+        reader.read_u32()?;
+    }
+
+    let num_dims = 2 + has_z as usize + has_m as usize;
+
+    match geometry_type {
+        // Point
+        1 => read_wkb_points(reader, 1, num_dims, bbox),
+        // LineString
+        2 => {
+            let num_points = reader.read_u32()?;
+            read_wkb_points(reader, num_points, num_dims, bbox)
+        }
+        // Polygon
+        3 => {
+            let num_rings = reader.read_u32()?;
+
+            for _ in 0..num_rings {
+                let num_points = reader.read_u32()?;
+                read_wkb_points(reader, num_points, num_dims, bbox)?;
+            }
+
+            Ok(())
+        }
+        // MultiPoint, MultiLineString, MultiPolygon and GeometryCollection
+        4..=7 => {
+            let num_geometries = reader.read_u32()?;
+
+            for _ in 0..num_geometries {
+                read_wkb_geometry(reader, bbox)?;
+            }
+
+            Ok(())
+        }
+        _ => Err(format!("unsupported WKB geometry type {}", wkb_type)),
+    }
+}
+
+fn read_wkb_points(
+    reader: &mut WkbReader,
+    num_points: u32,
+    num_dims: usize,
+    bbox: &mut BoundingBox,
+) -> Result<(), String> {
+    for _ in 0..num_points {
+        let x = reader.read_f64()?;
+        let y = reader.read_f64()?;
+
+        // z and m do not contribute to the 2D bbox
+        for _ in 2..num_dims {
+            reader.read_f64()?;
+        }
+
+        bbox.extend(x, y);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::{fmt::Display, str::FromStr};
+
+// PgParquetIfExists determines what COPY TO does when the file at the uri already exists. With
+// one_file_per_partition or max_rows_per_file, the directory at the uri exists when it has
+// files.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PgParquetIfExists {
+    #[default]
+    Overwrite,
+    Error,
+    // writes to the uri with a unique suffix appended to its file name, e.g.
+    // file-4242-1-9f86d081.parquet, which is made of the backend pid, a counter and a random
+    // number
+    AppendSuffix,
+}
+
+impl Display for PgParquetIfExists {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetIfExists::Overwrite => write!(f, "overwrite"),
+            PgParquetIfExists::Error => write!(f, "error"),
+            PgParquetIfExists::AppendSuffix => write!(f, "append_suffix"),
+        }
+    }
+}
+
+impl FromStr for PgParquetIfExists {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overwrite" => Ok(PgParquetIfExists::Overwrite),
+            "error" => Ok(PgParquetIfExists::Error),
+            "append_suffix" => Ok(PgParquetIfExists::AppendSuffix),
+            _ => Err(format!("unrecognized if_exists option: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::{
+    nan_handling::converted_rows,
+    pg_to_arrow::conversion_progress::{
+        current_conversion_progress, is_converting_nested_elements,
+    },
+};
+
+// PgParquetInfiniteTimestamps decides what COPY TO writes for the infinity and -infinity values
+// of timestamp and timestamptz columns, which parquet timestamps cannot represent. COPY FROM
+// reads the i64::MAX and i64::MIN sentinel values, as written by some systems, as infinities
+// with 'sentinel'.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetInfiniteTimestamps {
+    #[default]
+    Error,
+    Null,
+    Clamp,
+    Sentinel,
+}
+
+pub(crate) fn all_supported_infinite_timestamps() -> Vec<PgParquetInfiniteTimestamps> {
+    vec![
+        PgParquetInfiniteTimestamps::Error,
+        PgParquetInfiniteTimestamps::Null,
+        PgParquetInfiniteTimestamps::Clamp,
+        PgParquetInfiniteTimestamps::Sentinel,
+    ]
+}
+
+impl Display for PgParquetInfiniteTimestamps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetInfiniteTimestamps::Error => write!(f, "error"),
+            PgParquetInfiniteTimestamps::Null => write!(f, "null"),
+            PgParquetInfiniteTimestamps::Clamp => write!(f, "clamp"),
+            PgParquetInfiniteTimestamps::Sentinel => write!(f, "sentinel"),
+        }
+    }
+}
+
+impl FromStr for PgParquetInfiniteTimestamps {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(PgParquetInfiniteTimestamps::Error),
+            "null" => Ok(PgParquetInfiniteTimestamps::Null),
+            "clamp" => Ok(PgParquetInfiniteTimestamps::Clamp),
+            "sentinel" => Ok(PgParquetInfiniteTimestamps::Sentinel),
+            _ => Err(format!("unrecognized infinite_timestamps: {}", s)),
+        }
+    }
+}
+
+// infinities are clamped to the range of timestamps, which most readers accept, in microseconds
+// since Unix epoch
+const MAX_CLAMPED_TIMESTAMP: i64 = 253_402_300_799_999_999; // 9999-12-31 23:59:59.999999
+const MIN_CLAMPED_TIMESTAMP: i64 = -62_135_596_800_000_000; // 0001-01-01 00:00:00
+
+// INFINITE_TIMESTAMPS is the option of the COPY, whose values are being converted. The timestamp
+// conversions do not have access to the copy options, hence the writer and reader contexts keep
+// the option and set it before they convert values, since nested COPYs set their own.
+static mut INFINITE_TIMESTAMPS: PgParquetInfiniteTimestamps = PgParquetInfiniteTimestamps::Error;
+
+pub(crate) fn start_infinite_timestamps(infinite_timestamps: PgParquetInfiniteTimestamps) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        INFINITE_TIMESTAMPS = infinite_timestamps
+    };
+}
+
+fn infinite_timestamps() -> PgParquetInfiniteTimestamps {
+    #[allow(static_mut_refs)]
+    unsafe {
+        INFINITE_TIMESTAMPS
+    }
+}
+
+// infinite_timestamp_to_i64 returns the value, which COPY TO writes for the infinity (or
+// -infinity if "positive" is false) at the row of the record batch, or None if it is written
+// as NULL.
+pub(crate) fn infinite_timestamp_to_i64(positive: bool, row_idx: usize) -> Option<i64> {
+    match infinite_timestamps() {
+        PgParquetInfiniteTimestamps::Error => {
+            let column = current_conversion_progress()
+                .map(|progress| progress.attname)
+                .unwrap_or_default();
+
+            // elements of composite arrays and maps are converted after all of the rows
+            // are collected, hence their row is not known
+            if is_converting_nested_elements() {
+                panic!(
+                    "infinite timestamp in column \"{}\" is not allowed with infinite_timestamps 'error'",
+                    column
+                );
+            }
+
+            panic!(
+                "infinite timestamp in column \"{}\" at row {} is not allowed with infinite_timestamps 'error'",
+                column,
+                converted_rows() + row_idx + 1
+            );
+        }
+        PgParquetInfiniteTimestamps::Null => None,
+        PgParquetInfiniteTimestamps::Clamp if positive => Some(MAX_CLAMPED_TIMESTAMP),
+        PgParquetInfiniteTimestamps::Clamp => Some(MIN_CLAMPED_TIMESTAMP),
+        PgParquetInfiniteTimestamps::Sentinel if positive => Some(i64::MAX),
+        PgParquetInfiniteTimestamps::Sentinel => Some(i64::MIN),
+    }
+}
+
+// sentinel_infinity returns whether the value, which COPY FROM reads, is the sentinel of
+// infinity (true) or -infinity (false) with infinite_timestamps 'sentinel'.
+pub(crate) fn sentinel_infinity(value: i64) -> Option<bool> {
+    if infinite_timestamps() != PgParquetInfiniteTimestamps::Sentinel {
+        return None;
+    }
+
+    match value {
+        i64::MAX => Some(true),
+        i64::MIN => Some(false),
+        _ => None,
+    }
+}
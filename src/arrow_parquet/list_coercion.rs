@@ -0,0 +1,124 @@
+use std::{str::FromStr, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, GenericListArray, ListArray, OffsetSizeTrait, UInt64Array},
+    buffer::OffsetBuffer,
+    compute::take,
+};
+use arrow_schema::{DataType, FieldRef};
+
+// ListCoercion determines how COPY FROM reads the list columns of the parquet file into scalar
+// table columns, and the scalar columns into array table columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) enum ListCoercion {
+    #[default]
+    Error,
+    // reads the single-element lists into scalar columns, and the scalars into array columns
+    // as single-element arrays, e.g. for the files exported from document stores
+    Relaxed,
+}
+
+impl FromStr for ListCoercion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(ListCoercion::Error),
+            "relaxed" => Ok(ListCoercion::Relaxed),
+            _ => Err(format!("unrecognized list_coercion option: {}", s)),
+        }
+    }
+}
+
+pub(crate) fn is_list_type(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::List(_) | DataType::LargeList(_))
+}
+
+// is_scalar_type returns false for lists and maps, e.g. true for primitives and structs.
+pub(crate) fn is_scalar_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(_)
+        | DataType::LargeList(_)
+        | DataType::FixedSizeList(_, _)
+        | DataType::ListView(_)
+        | DataType::LargeListView(_)
+        | DataType::Map(_, _) => false,
+        DataType::Dictionary(_, value_type) => is_scalar_type(value_type),
+        _ => true,
+    }
+}
+
+// innermost_list_element_type returns the element type of the nested lists, e.g. Utf8 for
+// List<List<Utf8>>.
+pub(crate) fn innermost_list_element_type(data_type: &DataType) -> &DataType {
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) => {
+            innermost_list_element_type(field.data_type())
+        }
+        _ => data_type,
+    }
+}
+
+// unwrap_single_element_lists returns the elements of the lists of the column, which is read
+// into a scalar column with list_coercion 'relaxed'. Nested lists are unwrapped until their
+// elements are scalars. NULL and empty lists are read as NULL, and lists with more than one
+// element fail the COPY.
+pub(crate) fn unwrap_single_element_lists(column_array: ArrayRef, column_name: &str) -> ArrayRef {
+    let elements = match column_array.data_type() {
+        DataType::List(_) => {
+            unwrap_single_element_list_array(column_array.as_list::<i32>(), column_name)
+        }
+        DataType::LargeList(_) => {
+            unwrap_single_element_list_array(column_array.as_list::<i64>(), column_name)
+        }
+        _ => return column_array,
+    };
+
+    unwrap_single_element_lists(elements, column_name)
+}
+
+fn unwrap_single_element_list_array<O: OffsetSizeTrait>(
+    list_array: &GenericListArray<O>,
+    column_name: &str,
+) -> ArrayRef {
+    let offsets = list_array.value_offsets();
+
+    let element_indices = (0..list_array.len())
+        .map(|row_idx| {
+            if list_array.is_null(row_idx) {
+                return None;
+            }
+
+            let start = offsets[row_idx].as_usize();
+            let element_count = offsets[row_idx + 1].as_usize() - start;
+
+            match element_count {
+                0 => None,
+                1 => Some(start as u64),
+                element_count => panic!(
+                    "column \"{}\" has a list of {} elements, which cannot be read into a scalar \
+                     column",
+                    column_name, element_count
+                ),
+            }
+        })
+        .collect::<UInt64Array>();
+
+    take(list_array.values().as_ref(), &element_indices, None)
+        .unwrap_or_else(|e| panic!("failed to unwrap lists of column {}: {}", column_name, e))
+}
+
+// wrap_scalars_into_single_element_lists returns the scalars of the column, which are already
+// cast to the element type of the array column, as single-element lists with list_coercion
+// 'relaxed'. Unlike arrow-cast, NULL scalars are read as NULL instead of an array with a NULL
+// element.
+pub(crate) fn wrap_scalars_into_single_element_lists(
+    column_array: ArrayRef,
+    element_field: FieldRef,
+) -> ArrayRef {
+    let nulls = column_array.logical_nulls();
+
+    let offsets = OffsetBuffer::from_lengths(std::iter::repeat(1).take(column_array.len()));
+
+    Arc::new(ListArray::new(element_field, offsets, column_array, nulls))
+}
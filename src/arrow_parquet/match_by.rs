@@ -1,10 +1,13 @@
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub(crate) enum MatchBy {
     #[default]
     Position,
     Name,
+    // the file columns are matched to the table columns with the same field ids, e.g. after
+    // a column is renamed, and their nested fields by name
+    FieldId,
 }
 
 impl FromStr for MatchBy {
@@ -14,6 +17,7 @@ impl FromStr for MatchBy {
         match s {
             "position" => Ok(MatchBy::Position),
             "name" => Ok(MatchBy::Name),
+            "field_id" => Ok(MatchBy::FieldId),
             _ => Err(format!("unrecognized match_by method: {}", s)),
         }
     }
@@ -0,0 +1,23 @@
+use std::str::FromStr;
+
+// MissingColumns determines how COPY FROM handles the table columns, which are not found
+// in the parquet file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) enum MissingColumns {
+    #[default]
+    Error,
+    // fills the missing columns with their default values, or NULL if they have no default
+    Default,
+}
+
+impl FromStr for MissingColumns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(MissingColumns::Error),
+            "default" => Ok(MissingColumns::Default),
+            _ => Err(format!("unrecognized missing_columns option: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fmt::Display, str::FromStr};
+
+// PgParquetMultidimArrays decides how COPY TO writes the array columns, which have
+// multidimensional arrays. Arrays are written as lists, which have a single dimension.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetMultidimArrays {
+    #[default]
+    Error,
+    Nested,
+}
+
+pub(crate) fn all_supported_multidim_arrays() -> Vec<PgParquetMultidimArrays> {
+    vec![
+        PgParquetMultidimArrays::Error,
+        PgParquetMultidimArrays::Nested,
+    ]
+}
+
+impl Display for PgParquetMultidimArrays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetMultidimArrays::Error => write!(f, "error"),
+            PgParquetMultidimArrays::Nested => write!(f, "nested"),
+        }
+    }
+}
+
+impl FromStr for PgParquetMultidimArrays {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(PgParquetMultidimArrays::Error),
+            "nested" => Ok(PgParquetMultidimArrays::Nested),
+            _ => Err(format!("unrecognized multidim_arrays: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::pg_to_arrow::conversion_progress::{
+    current_conversion_progress, is_converting_nested_elements,
+};
+
+// PgParquetNanHandling decides what COPY TO does with NaN, Infinity and -Infinity values of
+// float4 and float8 columns, which some readers reject.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetNanHandling {
+    #[default]
+    Keep,
+    Null,
+    Error,
+}
+
+pub(crate) fn all_supported_nan_handlings() -> Vec<PgParquetNanHandling> {
+    vec![
+        PgParquetNanHandling::Keep,
+        PgParquetNanHandling::Null,
+        PgParquetNanHandling::Error,
+    ]
+}
+
+impl Display for PgParquetNanHandling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetNanHandling::Keep => write!(f, "keep"),
+            PgParquetNanHandling::Null => write!(f, "null"),
+            PgParquetNanHandling::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for PgParquetNanHandling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(PgParquetNanHandling::Keep),
+            "null" => Ok(PgParquetNanHandling::Null),
+            "error" => Ok(PgParquetNanHandling::Error),
+            _ => Err(format!("unrecognized nan_handling: {}", s)),
+        }
+    }
+}
+
+// NanHandlingState is the nan handling of the COPY TO, whose values are being converted. The
+// float conversions do not have access to the copy options, hence the writer context keeps the
+// state and sets it before it converts values, since nested COPYs set their own.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct NanHandlingState {
+    nan_handling: PgParquetNanHandling,
+    // number of rows written by the COPY before the current record batch
+    converted_rows: usize,
+    // number of non-finite values, which are replaced with NULL
+    nulled_values: usize,
+}
+
+impl NanHandlingState {
+    pub(crate) fn new(nan_handling: PgParquetNanHandling) -> Self {
+        Self {
+            nan_handling,
+            converted_rows: 0,
+            nulled_values: 0,
+        }
+    }
+
+    // take_nulled_values returns the number of non-finite values, which are replaced with
+    // NULL since the last call.
+    pub(crate) fn take_nulled_values(&mut self) -> usize {
+        std::mem::take(&mut self.nulled_values)
+    }
+}
+
+static mut NAN_HANDLING_STATE: NanHandlingState = NanHandlingState {
+    nan_handling: PgParquetNanHandling::Keep,
+    converted_rows: 0,
+    nulled_values: 0,
+};
+
+pub(crate) fn start_nan_handling(state: NanHandlingState) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        NAN_HANDLING_STATE = state
+    };
+}
+
+// finish_nan_handling counts the rows of the converted record batch, and returns the state to
+// be set again before the next record batch is converted.
+pub(crate) fn finish_nan_handling(num_rows: usize) -> NanHandlingState {
+    #[allow(static_mut_refs)]
+    unsafe {
+        NAN_HANDLING_STATE.converted_rows += num_rows;
+        NAN_HANDLING_STATE
+    }
+}
+
+// converted_rows returns the number of rows written by the ongoing COPY TO before the current
+// record batch, which is also used to report the row of infinite timestamps.
+pub(crate) fn converted_rows() -> usize {
+    #[allow(static_mut_refs)]
+    unsafe {
+        NAN_HANDLING_STATE.converted_rows
+    }
+}
+
+// handle_non_finite_floats applies the nan handling to the float values of the rows, whose
+// index is the row index in the record batch. Array elements are passed with the index of
+// their row.
+pub(crate) fn handle_non_finite_floats<'a, T: Copy + 'a>(
+    values: impl Iterator<Item = (usize, &'a mut Option<T>)>,
+    is_finite: fn(T) -> bool,
+) {
+    #[allow(static_mut_refs)]
+    let state = unsafe { &mut NAN_HANDLING_STATE };
+
+    if state.nan_handling == PgParquetNanHandling::Keep {
+        return;
+    }
+
+    for (row_idx, value) in values {
+        let Some(float) = *value else {
+            continue;
+        };
+
+        if is_finite(float) {
+            continue;
+        }
+
+        match state.nan_handling {
+            PgParquetNanHandling::Keep => unreachable!("keep does not handle non-finite values"),
+            PgParquetNanHandling::Null => {
+                *value = None;
+                state.nulled_values += 1;
+            }
+            PgParquetNanHandling::Error => {
+                let column = current_conversion_progress()
+                    .map(|progress| progress.attname)
+                    .unwrap_or_default();
+
+                // elements of composite arrays and maps are converted after all of the rows
+                // are collected, hence their row is not known
+                if is_converting_nested_elements() {
+                    panic!(
+                        "NaN or Infinity value in column \"{}\" is not allowed with nan_handling 'error'",
+                        column
+                    );
+                }
+
+                panic!(
+                    "NaN or Infinity value in column \"{}\" at row {} is not allowed with nan_handling 'error'",
+                    column,
+                    state.converted_rows + row_idx + 1
+                );
+            }
+        }
+    }
+}
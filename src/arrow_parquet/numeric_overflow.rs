@@ -0,0 +1,87 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::type_compat::pg_arrow_type_conversions::{
+    DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+};
+
+// PgParquetNumericOverflow decides what COPY TO does with the values of unconstrained numeric
+// columns, which have more decimal digits than the scale of the written decimal. Values with
+// more integral digits than the decimal allows are always rejected.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetNumericOverflow {
+    #[default]
+    Error,
+    Round,
+}
+
+pub(crate) fn all_supported_numeric_overflows() -> Vec<PgParquetNumericOverflow> {
+    vec![
+        PgParquetNumericOverflow::Error,
+        PgParquetNumericOverflow::Round,
+    ]
+}
+
+impl Display for PgParquetNumericOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetNumericOverflow::Error => write!(f, "error"),
+            PgParquetNumericOverflow::Round => write!(f, "round"),
+        }
+    }
+}
+
+impl FromStr for PgParquetNumericOverflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(PgParquetNumericOverflow::Error),
+            "round" => Ok(PgParquetNumericOverflow::Round),
+            _ => Err(format!("unrecognized numeric_overflow: {}", s)),
+        }
+    }
+}
+
+// UnboundedNumericState is the decimal, into which the COPY TO, whose values are being converted,
+// writes the unconstrained numeric columns. The schema and the numeric conversions only know the
+// typmod of the columns, hence the writer context keeps the state and sets it before it converts
+// values, since nested COPYs set their own.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct UnboundedNumericState {
+    pub(crate) precision: u32,
+    pub(crate) scale: u32,
+    pub(crate) overflow: PgParquetNumericOverflow,
+}
+
+impl Default for UnboundedNumericState {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_UNBOUNDED_NUMERIC_PRECISION,
+            scale: DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+            overflow: PgParquetNumericOverflow::Error,
+        }
+    }
+}
+
+static mut UNBOUNDED_NUMERIC_STATE: UnboundedNumericState = UnboundedNumericState {
+    precision: DEFAULT_UNBOUNDED_NUMERIC_PRECISION,
+    scale: DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+    overflow: PgParquetNumericOverflow::Error,
+};
+
+// start_unbounded_numeric sets the decimal of the unconstrained numeric columns. COPY FROM
+// resets it to the default, so that it does not see the options of an earlier COPY TO.
+pub(crate) fn start_unbounded_numeric(state: UnboundedNumericState) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        UNBOUNDED_NUMERIC_STATE = state
+    };
+}
+
+pub(crate) fn unbounded_numeric_state() -> UnboundedNumericState {
+    #[allow(static_mut_refs)]
+    unsafe {
+        UNBOUNDED_NUMERIC_STATE
+    }
+}
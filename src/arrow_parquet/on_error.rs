@@ -0,0 +1,34 @@
+use std::str::FromStr;
+
+use pgrx::PgSqlErrorCode;
+
+// CopyFromOnError determines how COPY FROM handles the rows, whose values cannot be converted
+// to their columns, like the ON_ERROR option of COPY FROM text and csv files.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum CopyFromOnError {
+    #[default]
+    Stop,
+    // skips the rows, whose conversion fails, and reports the number of skipped rows
+    Ignore,
+}
+
+impl FromStr for CopyFromOnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stop" => Ok(CopyFromOnError::Stop),
+            "ignore" => Ok(CopyFromOnError::Ignore),
+            _ => Err(format!("unrecognized on_error option: {}", s)),
+        }
+    }
+}
+
+// is_data_exception returns whether the error is in the data exception class (22), e.g. a numeric
+// overflow or an invalid input syntax, which on_error 'ignore' skips.
+pub(crate) fn is_data_exception(sql_error_code: PgSqlErrorCode) -> bool {
+    // the class is the first 2 characters of the sqlstate, i.e. ERRCODE_TO_CATEGORY
+    let category_mask = (1 << 12) - 1;
+
+    (sql_error_code as i32) & category_mask == PgSqlErrorCode::ERRCODE_DATA_EXCEPTION as i32
+}
@@ -0,0 +1,364 @@
+use std::{ffi::CStr, ops::Range};
+
+use arrow_schema::DataType;
+use parquet::{
+    arrow::arrow_reader::RowSelection,
+    file::{metadata::ParquetMetaData, page_index::index::Index},
+    format::PageLocation,
+};
+use pgrx::{
+    is_a,
+    pg_sys::{
+        get_attname, get_opname, Const, FirstNormalObjectId, List, Node, NodeTag, Oid, OpExpr, Var,
+        DATEOID, INT2OID, INT4OID, INT8OID,
+    },
+    FromDatum, PgList,
+};
+
+// PagePredicateOp is the comparison of a page predicate, e.g. "<" of "id < 100".
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PagePredicateOp {
+    Lt,
+    LtEq,
+    Eq,
+    GtEq,
+    Gt,
+}
+
+impl PagePredicateOp {
+    fn from_opname(opname: &str) -> Option<Self> {
+        match opname {
+            "<" => Some(PagePredicateOp::Lt),
+            "<=" => Some(PagePredicateOp::LtEq),
+            "=" => Some(PagePredicateOp::Eq),
+            ">=" => Some(PagePredicateOp::GtEq),
+            ">" => Some(PagePredicateOp::Gt),
+            _ => None,
+        }
+    }
+
+    // commuted returns the comparison with swapped operands, e.g. "100 > id" is "id < 100"
+    fn commuted(self) -> Self {
+        match self {
+            PagePredicateOp::Lt => PagePredicateOp::Gt,
+            PagePredicateOp::LtEq => PagePredicateOp::GtEq,
+            PagePredicateOp::Eq => PagePredicateOp::Eq,
+            PagePredicateOp::GtEq => PagePredicateOp::LtEq,
+            PagePredicateOp::Gt => PagePredicateOp::Lt,
+        }
+    }
+}
+
+// PagePredicate is a comparison of an integer or date column with a constant in the WHERE
+// clause of COPY FROM, e.g. "id < 100". Pages of the file, whose min and max in the column
+// index cannot satisfy it, are skipped. The constant is in the unit of the file values, i.e.
+// dates are days since the unix epoch.
+#[derive(Debug, Clone)]
+pub(crate) struct PagePredicate {
+    column_name: String,
+    op: PagePredicateOp,
+    value: i64,
+    typoid: Oid,
+}
+
+impl PagePredicate {
+    pub(crate) fn column_name(&self) -> &str {
+        &self.column_name
+    }
+
+    // matches_file_type returns true if the min and max of the file column are comparable
+    // with the constant, e.g. an int4 column is read from an Int64 file column
+    pub(crate) fn matches_file_type(&self, data_type: &DataType) -> bool {
+        match self.typoid {
+            DATEOID => data_type == &DataType::Date32,
+            _ => matches!(
+                data_type,
+                DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64
+            ),
+        }
+    }
+
+    // may_match returns false if none of the values between min and max satisfy the predicate
+    fn may_match(&self, min: i64, max: i64) -> bool {
+        match self.op {
+            PagePredicateOp::Lt => min < self.value,
+            PagePredicateOp::LtEq => min <= self.value,
+            PagePredicateOp::Eq => min <= self.value && self.value <= max,
+            PagePredicateOp::GtEq => max >= self.value,
+            PagePredicateOp::Gt => max > self.value,
+        }
+    }
+}
+
+// page_predicates_from_where_clause returns the comparisons of integer and date columns with
+// constants, which are ANDed in the transformed WHERE clause of COPY FROM. Other conditions,
+// e.g. "a < b" or "a = 1 OR b = 2", do not skip pages. Float comparisons are not used since
+// NaN is not ordered in the column index, nor are text comparisons, which depend on collation.
+pub(crate) fn page_predicates_from_where_clause(
+    where_clause: *mut Node,
+    relation_oid: Oid,
+) -> Vec<PagePredicate> {
+    if where_clause.is_null() {
+        return vec![];
+    }
+
+    let conditions = unsafe { PgList::<Node>::from_pg(where_clause as *mut List) };
+
+    conditions
+        .iter_ptr()
+        .filter_map(|condition| unsafe { page_predicate_from_condition(condition, relation_oid) })
+        .collect()
+}
+
+unsafe fn page_predicate_from_condition(
+    condition: *mut Node,
+    relation_oid: Oid,
+) -> Option<PagePredicate> {
+    if !is_a(condition, NodeTag::T_OpExpr) {
+        return None;
+    }
+
+    let op_expr = &*(condition as *mut OpExpr);
+
+    // user defined operators might not be comparisons, although they are named like one
+    if op_expr.opno.as_u32() >= FirstNormalObjectId {
+        return None;
+    }
+
+    let opname = get_opname(op_expr.opno);
+
+    if opname.is_null() {
+        return None;
+    }
+
+    let op = PagePredicateOp::from_opname(CStr::from_ptr(opname).to_str().ok()?)?;
+
+    let args = PgList::<Node>::from_pg(op_expr.args);
+
+    if args.len() != 2 {
+        return None;
+    }
+
+    let (left, right) = (args.get_ptr(0)?, args.get_ptr(1)?);
+
+    let (var, constant, op) = if is_a(left, NodeTag::T_Var) && is_a(right, NodeTag::T_Const) {
+        (left as *mut Var, right as *mut Const, op)
+    } else if is_a(left, NodeTag::T_Const) && is_a(right, NodeTag::T_Var) {
+        (right as *mut Var, left as *mut Const, op.commuted())
+    } else {
+        return None;
+    };
+
+    let (var, constant) = (&*var, &*constant);
+
+    if var.varlevelsup != 0 || var.varattno <= 0 || constant.constisnull {
+        return None;
+    }
+
+    let value = match (var.vartype, constant.consttype) {
+        (INT2OID | INT4OID | INT8OID, INT2OID) => {
+            i16::from_datum(constant.constvalue, false)? as i64
+        }
+        (INT2OID | INT4OID | INT8OID, INT4OID) => {
+            i32::from_datum(constant.constvalue, false)? as i64
+        }
+        (INT2OID | INT4OID | INT8OID, INT8OID) => i64::from_datum(constant.constvalue, false)?,
+        (DATEOID, DATEOID) => {
+            let pg_epoch_days = i32::from_datum(constant.constvalue, false)?;
+
+            // -infinity and infinity
+            if pg_epoch_days == i32::MIN || pg_epoch_days == i32::MAX {
+                return None;
+            }
+
+            pg_epoch_days as i64 + 10957
+        }
+        _ => return None,
+    };
+
+    let column_name = get_attname(relation_oid, var.varattno, false);
+
+    Some(PagePredicate {
+        column_name: CStr::from_ptr(column_name).to_str().ok()?.to_string(),
+        op,
+        value,
+        typoid: var.vartype,
+    })
+}
+
+// FilePagePredicate is a page predicate on a top level leaf column of the file.
+pub(crate) struct FilePagePredicate {
+    leaf_idx: usize,
+    predicate: PagePredicate,
+}
+
+impl FilePagePredicate {
+    pub(crate) fn new(leaf_idx: usize, predicate: PagePredicate) -> Self {
+        Self {
+            leaf_idx,
+            predicate,
+        }
+    }
+
+    // matching_page_ranges returns the row ranges of the pages of the column chunk, which may
+    // have a value satisfying the predicate. Pages of nulls never satisfy it.
+    fn matching_page_ranges(
+        &self,
+        column_index: &Index,
+        page_locations: &[PageLocation],
+        num_rows: usize,
+    ) -> Vec<Range<usize>> {
+        let page_min_max: Vec<Option<(i64, i64)>> = match column_index {
+            Index::INT32(index) => index
+                .indexes
+                .iter()
+                .map(|page| Some((page.min? as i64, page.max? as i64)))
+                .collect(),
+            Index::INT64(index) => index
+                .indexes
+                .iter()
+                .map(|page| Some((page.min?, page.max?)))
+                .collect(),
+            // none of the pages are skipped without their min and max
+            _ => return vec![0..num_rows],
+        };
+
+        if page_min_max.len() != page_locations.len() {
+            return vec![0..num_rows];
+        }
+
+        page_locations
+            .iter()
+            .enumerate()
+            .filter(|(page_idx, _)| match page_min_max[*page_idx] {
+                Some((min, max)) => self.predicate.may_match(min, max),
+                None => false,
+            })
+            .map(|(page_idx, page_location)| {
+                let end = match page_locations.get(page_idx + 1) {
+                    Some(next_page_location) => next_page_location.first_row_index as usize,
+                    None => num_rows,
+                };
+
+                page_location.first_row_index as usize..end
+            })
+            .collect()
+    }
+}
+
+// PageSelection is the rows of the selected row groups in the pages, which may satisfy all of
+// the page predicates. The other pages are neither fetched nor decoded.
+pub(crate) struct PageSelection {
+    row_group_selections: Vec<RowSelection>,
+    // row ranges of the selection, relative to the first row of the selected row groups
+    selected_ranges: Vec<Range<usize>>,
+    // number of selected rows before each range
+    selected_rows_before: Vec<usize>,
+}
+
+impl PageSelection {
+    // new returns None if the page index of the file does not skip any page.
+    pub(crate) fn new(
+        metadata: &ParquetMetaData,
+        row_groups: Range<usize>,
+        page_predicates: &[FilePagePredicate],
+    ) -> Option<Self> {
+        if page_predicates.is_empty() {
+            return None;
+        }
+
+        let column_index = metadata.column_index()?;
+        let offset_index = metadata.offset_index()?;
+
+        let mut row_group_selections = vec![];
+        let mut selected_ranges = vec![];
+        let mut selected_rows_before = vec![];
+        let mut rows_before_row_group = 0;
+        let mut selected_rows = 0;
+
+        for row_group in row_groups {
+            let num_rows = metadata.row_group(row_group).num_rows() as usize;
+
+            let mut selection =
+                RowSelection::from_consecutive_ranges(std::iter::once(0..num_rows), num_rows);
+
+            for page_predicate in page_predicates {
+                let leaf_idx = page_predicate.leaf_idx;
+
+                let (Some(column_index), Some(offset_index)) = (
+                    column_index.get(row_group).and_then(|c| c.get(leaf_idx)),
+                    offset_index.get(row_group).and_then(|o| o.get(leaf_idx)),
+                ) else {
+                    continue;
+                };
+
+                let page_ranges = page_predicate.matching_page_ranges(
+                    column_index,
+                    offset_index.page_locations(),
+                    num_rows,
+                );
+
+                selection = selection.intersection(&RowSelection::from_consecutive_ranges(
+                    page_ranges.into_iter(),
+                    num_rows,
+                ));
+            }
+
+            let mut row = rows_before_row_group;
+
+            for selector in selection.iter() {
+                if !selector.skip {
+                    selected_ranges.push(row..row + selector.row_count);
+                    selected_rows_before.push(selected_rows);
+                    selected_rows += selector.row_count;
+                }
+
+                row += selector.row_count;
+            }
+
+            rows_before_row_group += num_rows;
+
+            row_group_selections.push(selection);
+        }
+
+        if selected_rows == rows_before_row_group {
+            return None;
+        }
+
+        Some(Self {
+            row_group_selections,
+            selected_ranges,
+            selected_rows_before,
+        })
+    }
+
+    // row_selection returns the selection of the rows of all selected row groups
+    pub(crate) fn row_selection(&self) -> RowSelection {
+        self.row_group_selections
+            .iter()
+            .flat_map(|selection| selection.iter().cloned())
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    // row_group_selections returns the selection of the rows of each selected row group
+    pub(crate) fn row_group_selections(&self) -> Vec<RowSelection> {
+        self.row_group_selections.clone()
+    }
+
+    // selected_row_offset returns the offset of the selected row with the given index from the
+    // first row of the selected row groups, and the number of the selected rows from it up to
+    // the next skipped page.
+    pub(crate) fn selected_row_offset(&self, selected_row_idx: usize) -> (usize, usize) {
+        let range_idx = self
+            .selected_rows_before
+            .partition_point(|rows_before| *rows_before <= selected_row_idx)
+            .saturating_sub(1);
+
+        let range = &self.selected_ranges[range_idx];
+
+        let offset = range.start + selected_row_idx - self.selected_rows_before[range_idx];
+
+        (offset, range.end.saturating_sub(offset))
+    }
+}
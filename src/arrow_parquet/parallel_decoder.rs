@@ -0,0 +1,212 @@
+use std::{collections::VecDeque, ops::Range, sync::Arc, thread::JoinHandle};
+
+use arrow::array::RecordBatch;
+use bytes::{buf::Reader, Buf, Bytes};
+use object_store::{path::Path, ObjectStore};
+use parquet::{
+    arrow::{
+        arrow_reader::{ArrowReaderMetadata, ParquetRecordBatchReaderBuilder, RowSelection},
+        ProjectionMask,
+    },
+    errors::ParquetError,
+    file::reader::{ChunkReader, Length},
+};
+use pgrx::GucSetting;
+
+use crate::pgrx_utils::block_on_interruptible;
+
+// MAX_DECODE_THREADS is the max number of row groups, which are decoded into record batches
+// by separate threads while the backend copies the rows of the current record batch.
+// 1 means row groups are decoded by the backend.
+pub(crate) static MAX_DECODE_THREADS: GucSetting<i32> = GucSetting::<i32>::new(1);
+
+// ParallelRowGroupDecoder reads the row groups of a parquet file in order. The column chunks
+// of the upcoming row groups are fetched by the backend, and then decompressed and decoded by
+// separate threads. Decode threads do not call into Postgres. At most "max_decode_threads"
+// row groups are prefetched at a time, which bounds the memory used by the decoder.
+pub(crate) struct ParallelRowGroupDecoder {
+    object_store: Arc<dyn ObjectStore>,
+    location: Path,
+    file_size: u64,
+    metadata: ArrowReaderMetadata,
+    // column chunks of the columns, which are not projected, are not fetched
+    projection: ProjectionMask,
+    batch_size: usize,
+    max_decode_threads: usize,
+    row_groups: Range<usize>,
+    // rows of the upcoming row groups in the pages, which are not skipped by the WHERE clause
+    row_group_selections: Option<VecDeque<RowSelection>>,
+    decoding_row_groups: VecDeque<JoinHandle<Result<Vec<RecordBatch>, String>>>,
+    decoded_batches: VecDeque<RecordBatch>,
+}
+
+impl ParallelRowGroupDecoder {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        object_store: Arc<dyn ObjectStore>,
+        location: Path,
+        file_size: u64,
+        metadata: ArrowReaderMetadata,
+        projection: ProjectionMask,
+        row_groups: Range<usize>,
+        row_group_selections: Option<Vec<RowSelection>>,
+        batch_size: usize,
+        max_decode_threads: usize,
+    ) -> Self {
+        Self {
+            object_store,
+            location,
+            file_size,
+            metadata,
+            projection,
+            batch_size,
+            max_decode_threads,
+            row_groups,
+            row_group_selections: row_group_selections.map(VecDeque::from),
+            decoding_row_groups: VecDeque::new(),
+            decoded_batches: VecDeque::new(),
+        }
+    }
+
+    // next_batch returns the next record batch of the file, or None if all row groups are read.
+    pub(crate) fn next_batch(&mut self) -> Option<RecordBatch> {
+        loop {
+            if let Some(record_batch) = self.decoded_batches.pop_front() {
+                return Some(record_batch);
+            }
+
+            self.start_decoding_row_groups();
+
+            let decoding_row_group = self.decoding_row_groups.pop_front()?;
+
+            let record_batches = decoding_row_group
+                .join()
+                .unwrap_or_else(|_| panic!("parquet decode thread panicked"))
+                .unwrap_or_else(|e| panic!("failed to read record batch: {}", e));
+
+            self.decoded_batches.extend(record_batches);
+        }
+    }
+
+    // start_decoding_row_groups starts decoding the next row groups in the selected range
+    fn start_decoding_row_groups(&mut self) {
+        while self.decoding_row_groups.len() < self.max_decode_threads {
+            let Some(row_group) = self.row_groups.next() else {
+                break;
+            };
+
+            let row_selection = self
+                .row_group_selections
+                .as_mut()
+                .and_then(|row_group_selections| row_group_selections.pop_front());
+
+            // row groups, whose pages are all skipped, are not fetched
+            if row_selection
+                .as_ref()
+                .is_some_and(|row_selection| !row_selection.selects_any())
+            {
+                continue;
+            }
+
+            let column_chunks = self.fetch_row_group_column_chunks(row_group);
+            let metadata = self.metadata.clone();
+            let projection = self.projection.clone();
+            let batch_size = self.batch_size;
+
+            let decoding_row_group = std::thread::Builder::new()
+                .name("pg_parquet decoder".into())
+                .spawn(move || {
+                    let mut builder =
+                        ParquetRecordBatchReaderBuilder::new_with_metadata(column_chunks, metadata)
+                            .with_projection(projection)
+                            .with_row_groups(vec![row_group])
+                            .with_batch_size(batch_size);
+
+                    if let Some(row_selection) = row_selection {
+                        builder = builder.with_row_selection(row_selection);
+                    }
+
+                    builder
+                        .build()
+                        .map_err(|e| e.to_string())?
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap_or_else(|e| panic!("failed to spawn parquet decode thread: {}", e));
+
+            self.decoding_row_groups.push_back(decoding_row_group);
+        }
+    }
+
+    // fetch_row_group_column_chunks fetches the projected column chunks of the row group on
+    // the backend, since the backend's tokio runtime is single threaded.
+    fn fetch_row_group_column_chunks(&self, row_group: usize) -> RowGroupColumnChunks {
+        let ranges = self
+            .metadata
+            .metadata()
+            .row_group(row_group)
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|(leaf_idx, _)| self.projection.leaf_included(*leaf_idx))
+            .map(|(_, column)| {
+                let (start, length) = column.byte_range();
+                start as usize..(start + length) as usize
+            })
+            .collect::<Vec<Range<usize>>>();
+
+        let chunks = block_on_interruptible(self.object_store.get_ranges(&self.location, &ranges))
+            .unwrap_or_else(|e| panic!("failed to read row group {}: {}", row_group, e));
+
+        RowGroupColumnChunks {
+            file_size: self.file_size,
+            chunks: ranges
+                .into_iter()
+                .map(|range| range.start as u64)
+                .zip(chunks)
+                .collect(),
+        }
+    }
+}
+
+// RowGroupColumnChunks holds the fetched column chunks of a row group at their file offsets.
+struct RowGroupColumnChunks {
+    file_size: u64,
+    chunks: Vec<(u64, Bytes)>,
+}
+
+impl RowGroupColumnChunks {
+    fn chunk_at(&self, start: u64, length: usize) -> Result<Bytes, ParquetError> {
+        self.chunks
+            .iter()
+            .find(|(chunk_start, chunk)| {
+                start >= *chunk_start && start + length as u64 <= chunk_start + chunk.len() as u64
+            })
+            .map(|(chunk_start, chunk)| chunk.slice((start - chunk_start) as usize..))
+            .ok_or_else(|| {
+                ParquetError::General(format!(
+                    "bytes {}..{} are not in the fetched column chunks",
+                    start,
+                    start + length as u64
+                ))
+            })
+    }
+}
+
+impl Length for RowGroupColumnChunks {
+    fn len(&self) -> u64 {
+        self.file_size
+    }
+}
+
+impl ChunkReader for RowGroupColumnChunks {
+    type T = Reader<Bytes>;
+
+    fn get_read(&self, start: u64) -> Result<Self::T, ParquetError> {
+        Ok(self.chunk_at(start, 0)?.reader())
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> Result<Bytes, ParquetError> {
+        Ok(self.chunk_at(start, length)?.slice(..length))
+    }
+}
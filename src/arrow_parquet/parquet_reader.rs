@@ -1,109 +1,553 @@
-use std::sync::Arc;
+use std::{collections::HashMap, panic::AssertUnwindSafe, sync::Arc};
 
-use arrow::array::RecordBatch;
+use arrow::array::{Array, ArrayRef, AsArray, RecordBatch};
 use arrow_cast::{cast_with_options, CastOptions};
+use arrow_schema::{DataType, Schema, SchemaRef};
 use futures::StreamExt;
-use parquet::arrow::async_reader::{ParquetObjectReader, ParquetRecordBatchStream};
+use parquet::arrow::{
+    async_reader::{ParquetObjectReader, ParquetRecordBatchStream},
+    ProjectionMask,
+};
 use pgrx::{
     check_for_interrupts,
     pg_sys::{
-        fmgr_info, getTypeBinaryOutputInfo, varlena, Datum, FmgrInfo, FormData_pg_attribute,
-        InvalidOid, SendFunctionCall,
+        fmgr_info, getTypeBinaryOutputInfo, panic::CaughtError, varlena, Datum, FmgrInfo,
+        FormData_pg_attribute, InvalidOid, Oid, SendFunctionCall, INTERVALOID,
     },
     vardata_any, varsize_any_exhdr, void_mut_ptr, AllocatedByPostgres, PgBox, PgMemoryContexts,
-    PgTupleDesc,
+    PgTryBuilder, PgTupleDesc,
 };
 use url::Url;
 
 use crate::{
     arrow_parquet::{
-        arrow_to_pg::{context::collect_arrow_to_pg_attribute_contexts, to_pg_datum},
+        arrow_to_pg::{
+            context::collect_arrow_to_pg_attribute_contexts, conversion_attribute_idx,
+            conversion_row_number, primitive_batch::to_pg_primitive_datums,
+            set_conversion_attribute_idx, set_conversion_row_number, to_pg_datum,
+        },
         schema_parser::{
-            error_if_copy_from_match_by_position_with_generated_columns,
-            parquet_schema_string_from_attributes,
+            ensure_no_extra_file_fields, file_projection_leaves, generated_column_file_projection,
+            parquet_schema_string_from_attributes, prune_extra_file_fields,
         },
     },
-    pgrx_utils::{collect_attributes_for, CollectAttributesFor},
-    type_compat::{geometry::reset_postgis_context, map::reset_map_context},
-    PG_BACKEND_TOKIO_RUNTIME,
+    pgrx_utils::{block_on_interruptible, collect_attributes_for, CollectAttributesFor},
+    type_compat::{
+        geometry_crs::{geoparquet_column_srids, GeometrySridConversion, GeometrySridMismatch},
+        map::reset_map_context,
+    },
 };
 
 use super::{
     arrow_to_pg::context::ArrowToPgAttributeContext,
+    cast_plan_cache::{cached_cast_plan, CastPlan, CastPlanKey},
+    extra_columns::ExtraColumns,
+    field_ids::{rename_file_fields_by_field_id, rename_record_batch_columns, table_field_ids},
+    infinite_timestamps::{start_infinite_timestamps, PgParquetInfiniteTimestamps},
+    list_coercion::{
+        is_list_type, is_scalar_type, unwrap_single_element_lists,
+        wrap_scalars_into_single_element_lists, ListCoercion,
+    },
     match_by::MatchBy,
+    missing_columns::MissingColumns,
+    numeric_overflow::{start_unbounded_numeric, UnboundedNumericState},
+    on_error::{is_data_exception, CopyFromOnError},
+    page_pruning::{FilePagePredicate, PagePredicate, PageSelection},
+    parallel_decoder::{ParallelRowGroupDecoder, MAX_DECODE_THREADS},
+    row_group_selection::{ResumeToken, RowGroupSelection, SelectedRowGroups},
     schema_parser::{
-        ensure_file_schema_match_tupledesc_schema, parse_arrow_schema_from_attributes,
+        decode_dictionary_fields, ensure_file_schema_match_tupledesc_schema,
+        parse_arrow_schema_from_attributes, unwrap_domain_attributes, FileColumnMatch,
+    },
+    timestamptz_to_timestamp::{start_timestamptz_to_timestamp, TimestamptzToTimestamp},
+    uri_utils::{
+        parallel_row_group_decoder_from_uri, parquet_file_footer_from_uri, parquet_reader_from_uri,
+        redact_uri, ParquetFileFooter,
     },
-    uri_utils::parquet_reader_from_uri,
 };
 
+// RecordBatchSource reads the record batches of the file by the backend, or by decode threads
+// when pg_parquet.max_decode_threads is greater than 1.
+enum RecordBatchSource {
+    Stream(ParquetRecordBatchStream<ParquetObjectReader>),
+    ParallelDecoder(ParallelRowGroupDecoder),
+}
+
+impl RecordBatchSource {
+    fn new(
+        uri: &Url,
+        file_footer: ParquetFileFooter,
+        projection: ProjectionMask,
+        row_group_selection: &RowGroupSelection,
+        page_predicates: &[FilePagePredicate],
+    ) -> (Self, SelectedRowGroups, Option<PageSelection>) {
+        let max_decode_threads = MAX_DECODE_THREADS.get();
+
+        if max_decode_threads > 1 {
+            let (decoder, selected_row_groups, page_selection) =
+                parallel_row_group_decoder_from_uri(
+                    uri,
+                    file_footer,
+                    projection,
+                    row_group_selection,
+                    page_predicates,
+                    max_decode_threads as usize,
+                );
+
+            (
+                RecordBatchSource::ParallelDecoder(decoder),
+                selected_row_groups,
+                page_selection,
+            )
+        } else {
+            let (stream, selected_row_groups, page_selection) = parquet_reader_from_uri(
+                uri,
+                file_footer,
+                projection,
+                row_group_selection,
+                page_predicates,
+            );
+
+            (
+                RecordBatchSource::Stream(stream),
+                selected_row_groups,
+                page_selection,
+            )
+        }
+    }
+
+    fn next_batch(&mut self) -> Option<RecordBatch> {
+        match self {
+            RecordBatchSource::Stream(stream) => {
+                block_on_interruptible(stream.next()).map(|batch_result| {
+                    batch_result.unwrap_or_else(|e| panic!("failed to read record batch: {}", e))
+                })
+            }
+            RecordBatchSource::ParallelDecoder(decoder) => decoder.next_batch(),
+        }
+    }
+}
+
+// COPY_BUFFER_SIZE is the size of the buffer, up to which the rows are converted into PG copy
+// format at a time. COPY reads the data in chunks of the same size (RAW_BUF_SIZE).
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+// CurrentRecordBatch is the record batch, whose rows are being copied, with the datums of its
+// dictionary encoded columns, which live in the per batch memory context, and the datums of its
+// primitive columns, which are converted at once.
+struct CurrentRecordBatch {
+    record_batch: RecordBatch,
+    dictionary_datums: Vec<Option<Vec<Option<Datum>>>>,
+    primitive_datums: Vec<Option<Vec<Option<Datum>>>>,
+    next_row_idx: usize,
+}
+
+// MAX_COPY_BUFFER_SIZE is the largest size of the buffer during the last COPY FROM, which is
+// only tracked by tests.
+#[cfg(feature = "pg_test")]
+static mut MAX_COPY_BUFFER_SIZE: usize = 0;
+
 pub(crate) struct ParquetReaderContext {
     buffer: Vec<u8>,
     offset: usize,
     started: bool,
     finished: bool,
-    parquet_reader: ParquetRecordBatchStream<ParquetObjectReader>,
+    parquet_reader: RecordBatchSource,
+    current_record_batch: Option<CurrentRecordBatch>,
+    // rest of the record batch, whose rows are after a skipped page in the file
+    remaining_record_batch: Option<RecordBatch>,
     attribute_contexts: Vec<ArrowToPgAttributeContext>,
     binary_out_funcs: Vec<PgBox<FmgrInfo>>,
     match_by: MatchBy,
+    // the file schema, whose columns are renamed to the table columns with match_by 'field_id'
+    field_id_file_schema: Option<SchemaRef>,
+    missing_attribute_names: Vec<String>,
+    row_group_selection: RowGroupSelection,
+    selected_row_groups: SelectedRowGroups,
+    uri: Url,
+    // number of rows in the file before the selected row groups
+    rows_before_selection: usize,
+    // rows of the selected row groups, whose pages are read, when the WHERE clause skips pages
+    page_selection: Option<PageSelection>,
+    rows_read: usize,
+    on_error: CopyFromOnError,
+    // options of the value conversions, which are set before the rows are converted, since
+    // a nested COPY, e.g. in a trigger of the table, sets its own options meanwhile
+    timestamptz_to_timestamp: TimestamptzToTimestamp,
+    infinite_timestamps: PgParquetInfiniteTimestamps,
+    // number of rows, which are skipped by on_error 'ignore'
+    skipped_rows: usize,
     per_row_memory_ctx: PgMemoryContexts,
+    per_batch_memory_ctx: PgMemoryContexts,
 }
 
 impl ParquetReaderContext {
-    pub(crate) fn new(uri: Url, match_by: MatchBy, tupledesc: &PgTupleDesc) -> Self {
-        // Postgis and Map contexts are used throughout reading the parquet file.
-        // We need to reset them to avoid reading the stale data. (e.g. extension could be dropped)
-        reset_postgis_context();
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        uri: Url,
+        match_by: MatchBy,
+        field_ids: Option<HashMap<String, i32>>,
+        missing_columns: MissingColumns,
+        extra_columns: ExtraColumns,
+        row_group_selection: RowGroupSelection,
+        page_predicates: Vec<PagePredicate>,
+        geometry_srid_mismatch: GeometrySridMismatch,
+        timestamptz_to_timestamp: TimestamptzToTimestamp,
+        infinite_timestamps: PgParquetInfiniteTimestamps,
+        list_coercion: ListCoercion,
+        on_error: CopyFromOnError,
+        relation_oid: Oid,
+        tupledesc: &PgTupleDesc,
+    ) -> Self {
+        // Map context is used throughout reading the parquet file.
+        // We need to reset it to avoid reading the stale data. (e.g. extension could be dropped)
         reset_map_context();
 
-        error_if_copy_from_match_by_position_with_generated_columns(tupledesc, match_by);
+        Self::start_value_conversions(timestamptz_to_timestamp, infinite_timestamps);
+
+        // the page index is only read when the WHERE clause might skip pages
+        let page_index = match_by == MatchBy::Name && !page_predicates.is_empty();
 
-        let parquet_reader = parquet_reader_from_uri(&uri);
+        let file_footer = parquet_file_footer_from_uri(&uri, page_index);
 
-        let parquet_file_schema = parquet_reader.schema();
+        // a failed COPY might leave the attribute of its failed conversion
+        set_conversion_attribute_idx(None);
 
-        let attributes = collect_attributes_for(CollectAttributesFor::CopyFrom, tupledesc);
+        let file_schema = file_footer.schema().clone();
 
-        pgrx::debug2!(
-            "schema for tuples: {}",
-            parquet_schema_string_from_attributes(&attributes)
+        let cast_plan_key = CastPlanKey::new(
+            relation_oid,
+            tupledesc,
+            file_schema.clone(),
+            match_by,
+            field_ids.as_ref(),
+            missing_columns,
+            extra_columns,
+            list_coercion,
         );
 
-        let tupledesc_schema = parse_arrow_schema_from_attributes(&attributes);
+        // domains over arrays are read as their base arrays
+        let unwrapped_tupledesc =
+            unwrap_domain_attributes(tupledesc, CollectAttributesFor::CopyFrom);
+
+        let attributes =
+            collect_attributes_for(CollectAttributesFor::CopyFrom, &unwrapped_tupledesc);
+
+        // repeated loads of files with the same schema into the same table skip the negotiation
+        let cast_plan = cached_cast_plan(cast_plan_key, || {
+            // with match_by 'field_id', the file columns are renamed to the table columns with
+            // the same field ids, and then matched by name
+            let file_schema = if match_by == MatchBy::FieldId {
+                let attributes = collect_attributes_for(CollectAttributesFor::CopyFrom, tupledesc);
 
-        let tupledesc_schema = Arc::new(tupledesc_schema);
+                let table_field_ids = table_field_ids(&attributes, field_ids);
 
-        // Ensure that the file schema matches the tupledesc schema.
-        // Gets cast_to_types for each attribute if a cast is needed for the attribute's columnar array
-        // to match the expected columnar array for its tupledesc type.
-        let cast_to_types = ensure_file_schema_match_tupledesc_schema(
-            parquet_file_schema.clone(),
-            tupledesc_schema.clone(),
+                Arc::new(rename_file_fields_by_field_id(
+                    &file_schema,
+                    &table_field_ids,
+                ))
+            } else {
+                file_schema
+            };
+
+            let generated_column_projection =
+                generated_column_file_projection(tupledesc, match_by, file_schema.fields().len());
+
+            let parquet_file_schema = match &generated_column_projection {
+                Some(projection) => file_schema
+                    .project(projection)
+                    .unwrap_or_else(|e| panic!("failed to project parquet file schema: {}", e)),
+                None => file_schema.as_ref().clone(),
+            };
+
+            pgrx::debug2!(
+                "schema for tuples: {}",
+                parquet_schema_string_from_attributes(&attributes)
+            );
+
+            let tupledesc_schema = parse_arrow_schema_from_attributes(&attributes);
+
+            // enums are read as text, although COPY TO writes them as dictionaries
+            let tupledesc_schema = Schema::new_with_metadata(
+                decode_dictionary_fields(&tupledesc_schema.fields),
+                tupledesc_schema.metadata,
+            );
+
+            let (pruned_file_schema, extra_field_paths) =
+                prune_extra_file_fields(&parquet_file_schema, &tupledesc_schema, match_by);
+
+            match extra_columns {
+                ExtraColumns::Error => ensure_no_extra_file_fields(
+                    &parquet_file_schema,
+                    &tupledesc_schema,
+                    &extra_field_paths,
+                    match_by,
+                ),
+                ExtraColumns::Ignore if !extra_field_paths.is_empty() => pgrx::debug2!(
+                    "extra columns in parquet file are not read: {}",
+                    extra_field_paths.join(", ")
+                ),
+                ExtraColumns::Ignore => {}
+            }
+
+            // neither the extra columns nor the file columns of generated columns are read
+            let projection_leaves = file_projection_leaves(&file_schema, &pruned_file_schema);
+
+            let parquet_file_schema = Arc::new(pruned_file_schema);
+
+            let tupledesc_schema = Arc::new(tupledesc_schema);
+
+            // Ensure that the file schema matches the tupledesc schema.
+            // Gets cast_to_types for each attribute if a cast is needed for the attribute's
+            // columnar array to match the expected columnar array for its tupledesc type.
+            let column_matches = ensure_file_schema_match_tupledesc_schema(
+                parquet_file_schema.clone(),
+                tupledesc_schema.clone(),
+                &attributes,
+                match_by,
+                missing_columns,
+                list_coercion,
+            );
+
+            CastPlan {
+                file_schema: parquet_file_schema,
+                tupledesc_schema,
+                projection_leaves,
+                column_matches,
+            }
+        });
+
+        let CastPlan {
+            file_schema: parquet_file_schema,
+            tupledesc_schema,
+            projection_leaves,
+            column_matches,
+        } = cast_plan;
+
+        let projection = ProjectionMask::leaves(file_footer.schema_descr(), projection_leaves);
+
+        let page_predicates = Self::file_page_predicates(
+            page_predicates,
+            &file_footer,
             &attributes,
+            &column_matches,
             match_by,
         );
 
-        let attribute_contexts = collect_arrow_to_pg_attribute_contexts(
+        let (parquet_reader, selected_row_groups, page_selection) = RecordBatchSource::new(
+            &uri,
+            file_footer,
+            projection,
+            &row_group_selection,
+            &page_predicates,
+        );
+
+        let rows_before_selection = selected_row_groups.rows_before_selection();
+
+        let field_id_file_schema =
+            (match_by == MatchBy::FieldId).then(|| parquet_file_schema.clone());
+
+        // missing attributes are not read from the file, COPY fills them with their default values
+        let mut missing_attribute_names = vec![];
+        let mut present_attributes = vec![];
+        let mut cast_to_types = vec![];
+
+        for (attribute, column_match) in attributes.into_iter().zip(column_matches) {
+            match column_match {
+                FileColumnMatch::Found(cast_to_type) => {
+                    present_attributes.push(attribute);
+                    cast_to_types.push(cast_to_type);
+                }
+                FileColumnMatch::Missing => {
+                    missing_attribute_names.push(attribute.name().to_string());
+                }
+            }
+        }
+
+        if present_attributes.is_empty() && !missing_attribute_names.is_empty() {
+            panic!("none of the table columns are found in parquet file");
+        }
+
+        let attributes = present_attributes;
+
+        let mut attribute_contexts = collect_arrow_to_pg_attribute_contexts(
             &attributes,
             &tupledesc_schema.fields,
             Some(cast_to_types),
         );
 
+        Self::set_geometry_srid_conversions(
+            &mut attribute_contexts,
+            &parquet_file_schema,
+            match_by,
+            geometry_srid_mismatch,
+        );
+
         let binary_out_funcs = Self::collect_binary_out_funcs(&attributes);
 
         let per_row_memory_ctx = PgMemoryContexts::new("COPY FROM parquet per row memory context");
 
+        let per_batch_memory_ctx =
+            PgMemoryContexts::new("COPY FROM parquet per batch memory context");
+
+        #[cfg(feature = "pg_test")]
+        #[allow(static_mut_refs)]
+        unsafe {
+            MAX_COPY_BUFFER_SIZE = 0
+        };
+
         ParquetReaderContext {
             buffer: Vec::new(),
             offset: 0,
             attribute_contexts,
             parquet_reader,
+            current_record_batch: None,
+            remaining_record_batch: None,
             binary_out_funcs,
             match_by,
+            field_id_file_schema,
+            missing_attribute_names,
+            row_group_selection,
+            selected_row_groups,
             started: false,
             finished: false,
+            uri,
+            rows_before_selection,
+            page_selection,
+            rows_read: 0,
+            on_error,
+            timestamptz_to_timestamp,
+            infinite_timestamps,
+            skipped_rows: 0,
             per_row_memory_ctx,
+            per_batch_memory_ctx,
+        }
+    }
+
+    // start_value_conversions sets the options of the value conversions, which do not have
+    // access to the copy options.
+    fn start_value_conversions(
+        timestamptz_to_timestamp: TimestamptzToTimestamp,
+        infinite_timestamps: PgParquetInfiniteTimestamps,
+    ) {
+        // unconstrained numeric columns are not affected by the options of an earlier COPY TO
+        start_unbounded_numeric(UnboundedNumericState::default());
+
+        start_timestamptz_to_timestamp(timestamptz_to_timestamp);
+
+        start_infinite_timestamps(infinite_timestamps);
+    }
+
+    // next_resume_token returns the token to resume loading the file after the row groups,
+    // which are loaded by a chunked COPY FROM, or None if the last row group is loaded.
+    pub(crate) fn next_resume_token(&self) -> Option<ResumeToken> {
+        if !self.row_group_selection.is_chunked() {
+            return None;
+        }
+
+        self.selected_row_groups.next_resume_token()
+    }
+
+    // retry_resume_token returns the token to retry a chunked COPY FROM, which failed.
+    pub(crate) fn retry_resume_token(&self) -> Option<ResumeToken> {
+        if !self.row_group_selection.is_chunked() {
+            return None;
+        }
+
+        Some(self.selected_row_groups.retry_resume_token())
+    }
+
+    // skipped_rows returns the number of rows, which are skipped by on_error 'ignore'.
+    pub(crate) fn skipped_rows(&self) -> usize {
+        self.skipped_rows
+    }
+
+    pub(crate) fn selected_row_groups(&self) -> &SelectedRowGroups {
+        &self.selected_row_groups
+    }
+
+    // conversion_error_context returns the location of the value, which is being converted,
+    // in the file, e.g. "parquet file s3://bucket/f.parquet, row group 2, row 250001, column "a"".
+    // It returns None if no value is being converted, e.g. while decoding a record batch.
+    pub(crate) fn conversion_error_context(&self) -> Option<String> {
+        let attribute_idx = conversion_attribute_idx()?;
+
+        let attribute_name = self.attribute_contexts[attribute_idx].name();
+
+        let row_number = conversion_row_number();
+
+        Some(format!(
+            "parquet file {}, row group {}, row {}, column \"{}\"",
+            redact_uri(&self.uri),
+            self.selected_row_groups.row_group_of_row(row_number),
+            row_number,
+            attribute_name
+        ))
+    }
+
+    // file_row_number returns the row number in the file of the read row with the given number,
+    // and the number of rows, which are read contiguously from it, i.e. up to a skipped page.
+    fn file_row_number(&self, read_row_number: usize) -> (usize, usize) {
+        match &self.page_selection {
+            Some(page_selection) => {
+                let (offset, contiguous_rows) =
+                    page_selection.selected_row_offset(read_row_number - 1);
+
+                (self.rows_before_selection + offset + 1, contiguous_rows)
+            }
+            None => (self.rows_before_selection + read_row_number, usize::MAX),
+        }
+    }
+
+    // missing_attribute_names returns the names of the attributes, which are not found in the file.
+    pub(crate) fn missing_attribute_names(&self) -> &[String] {
+        &self.missing_attribute_names
+    }
+
+    // file_page_predicates returns the page predicates on the top level leaf columns of the
+    // file, which are read into the columns of the predicates. Only columns matched by name
+    // skip pages, since the predicates are on the table columns.
+    fn file_page_predicates(
+        page_predicates: Vec<PagePredicate>,
+        file_footer: &ParquetFileFooter,
+        attributes: &[FormData_pg_attribute],
+        column_matches: &[FileColumnMatch],
+        match_by: MatchBy,
+    ) -> Vec<FilePagePredicate> {
+        if match_by != MatchBy::Name {
+            return vec![];
         }
+
+        page_predicates
+            .into_iter()
+            .filter(|page_predicate| {
+                attributes
+                    .iter()
+                    .zip(column_matches)
+                    .any(|(attribute, column_match)| {
+                        attribute.name() == page_predicate.column_name()
+                            && matches!(column_match, FileColumnMatch::Found(_))
+                    })
+            })
+            .filter_map(|page_predicate| {
+                let field = file_footer
+                    .schema()
+                    .field_with_name(page_predicate.column_name())
+                    .ok()?;
+
+                if !page_predicate.matches_file_type(field.data_type()) {
+                    return None;
+                }
+
+                let leaf_idx = file_footer
+                    .schema_descr()
+                    .columns()
+                    .iter()
+                    .position(|column| column.path().parts() == [page_predicate.column_name()])?;
+
+                Some(FilePagePredicate::new(leaf_idx, page_predicate))
+            })
+            .collect()
     }
 
     fn collect_binary_out_funcs(
@@ -129,37 +573,234 @@ impl ParquetReaderContext {
         }
     }
 
+    // set_geometry_srid_conversions sets the SRID of the geometry columns from their crs in
+    // the geoparquet metadata of the file. It fails if the SRID differs from the SRID of the
+    // table column's type, unless geometry_srid_mismatch is 'reproject'.
+    fn set_geometry_srid_conversions(
+        attribute_contexts: &mut [ArrowToPgAttributeContext],
+        parquet_file_schema: &SchemaRef,
+        match_by: MatchBy,
+        geometry_srid_mismatch: GeometrySridMismatch,
+    ) {
+        let Some(geo_metadata) = parquet_file_schema.metadata().get("geo") else {
+            return;
+        };
+
+        let column_srids = geoparquet_column_srids(geo_metadata);
+
+        for (attribute_idx, attribute_context) in attribute_contexts.iter_mut().enumerate() {
+            let column_name = match match_by {
+                MatchBy::Position => parquet_file_schema.field(attribute_idx).name().clone(),
+                MatchBy::Name | MatchBy::FieldId => attribute_context.name().to_string(),
+            };
+
+            // the geometries nested in composite types and arrays are looked up by their
+            // dotted names
+            attribute_context.set_geometry_srid_conversions(&column_name, &|name, typmod| {
+                column_srids.get(name).map(|file_srid| {
+                    GeometrySridConversion::new(name, *file_srid, typmod, geometry_srid_mismatch)
+                })
+            });
+        }
+    }
+
+    fn attribute_column_array<'a>(
+        record_batch: &'a RecordBatch,
+        attribute_idx: usize,
+        attribute_context: &ArrowToPgAttributeContext,
+        match_by: MatchBy,
+    ) -> &'a ArrayRef {
+        let name = attribute_context.name();
+
+        match match_by {
+            MatchBy::Position => record_batch
+                .columns()
+                .get(attribute_idx)
+                .unwrap_or_else(|| panic!("column {} not found", name)),
+
+            MatchBy::Name | MatchBy::FieldId => record_batch
+                .column_by_name(name)
+                .unwrap_or_else(|| panic!("column {} not found", name)),
+        }
+    }
+
+    // dictionary_datums_for_batch converts the distinct values of dictionary encoded columns,
+    // which are read into text, char or fallback-to-text (e.g. enum) columns, to datums once per
+    // batch. Rows of these columns are converted by looking up the datum of their dictionary key
+    // instead of converting the same value again for each row. With on_error 'ignore', values are
+    // converted per row, so that a value, which fails to convert, skips only its rows.
+    fn dictionary_datums_for_batch(
+        record_batch: &RecordBatch,
+        attribute_contexts: &[ArrowToPgAttributeContext],
+        match_by: MatchBy,
+        on_error: CopyFromOnError,
+    ) -> Vec<Option<Vec<Option<Datum>>>> {
+        let mut dictionary_datums = vec![];
+
+        if on_error == CopyFromOnError::Ignore {
+            return vec![None; attribute_contexts.len()];
+        }
+
+        for (attribute_idx, attribute_context) in attribute_contexts.iter().enumerate() {
+            let column_array = Self::attribute_column_array(
+                record_batch,
+                attribute_idx,
+                attribute_context,
+                match_by,
+            );
+
+            // interval errors report the row number, so intervals are converted per row
+            if !matches!(column_array.data_type(), DataType::Dictionary(_, _))
+                || attribute_context.data_type() != &DataType::Utf8
+                || attribute_context.typoid() == INTERVALOID
+            {
+                dictionary_datums.push(None);
+                continue;
+            }
+
+            let dictionary_values = column_array.as_any_dictionary().values();
+
+            let dictionary_values =
+                if dictionary_values.data_type() == attribute_context.data_type() {
+                    dictionary_values.clone()
+                } else {
+                    // should fail instead of returning None if the cast fails at runtime
+                    let cast_options = CastOptions {
+                        safe: false,
+                        ..Default::default()
+                    };
+
+                    cast_with_options(
+                        dictionary_values,
+                        attribute_context.data_type(),
+                        &cast_options,
+                    )
+                    .unwrap_or_else(|e| {
+                        panic!("failed to cast column {}: {}", attribute_context.name(), e)
+                    })
+                };
+
+            let value_datums = (0..dictionary_values.len())
+                .map(|value_idx| {
+                    check_for_interrupts!();
+
+                    to_pg_datum(
+                        dictionary_values.slice(value_idx, 1).to_data(),
+                        attribute_context,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            dictionary_datums.push(Some(value_datums));
+        }
+
+        dictionary_datums
+    }
+
+    // primitive_datums_for_batch converts the columns, which are read into pass-by-value types
+    // without a cast, e.g. int8 or timestamptz, to datums at once per batch, instead of slicing
+    // each row out of the arrays. With on_error 'ignore', values are converted per row, so that
+    // a value, which fails to convert, skips only its row.
+    fn primitive_datums_for_batch(
+        record_batch: &RecordBatch,
+        attribute_contexts: &[ArrowToPgAttributeContext],
+        match_by: MatchBy,
+        on_error: CopyFromOnError,
+        first_row_number: usize,
+    ) -> Vec<Option<Vec<Option<Datum>>>> {
+        if on_error == CopyFromOnError::Ignore {
+            return vec![None; attribute_contexts.len()];
+        }
+
+        let mut primitive_datums = vec![];
+
+        for (attribute_idx, attribute_context) in attribute_contexts.iter().enumerate() {
+            check_for_interrupts!();
+
+            set_conversion_attribute_idx(Some(attribute_idx));
+
+            let column_array = Self::attribute_column_array(
+                record_batch,
+                attribute_idx,
+                attribute_context,
+                match_by,
+            );
+
+            primitive_datums.push(to_pg_primitive_datums(
+                column_array,
+                attribute_context,
+                first_row_number,
+            ));
+        }
+
+        set_conversion_attribute_idx(None);
+
+        primitive_datums
+    }
+
     fn record_batch_to_tuple_datums(
         record_batch: RecordBatch,
+        row_idx: usize,
         attribute_contexts: &[ArrowToPgAttributeContext],
+        dictionary_datums: &[Option<Vec<Option<Datum>>>],
+        primitive_datums: &[Option<Vec<Option<Datum>>>],
         match_by: MatchBy,
     ) -> Vec<Option<Datum>> {
         let mut datums = vec![];
 
         for (attribute_idx, attribute_context) in attribute_contexts.iter().enumerate() {
+            set_conversion_attribute_idx(Some(attribute_idx));
+
             let name = attribute_context.name();
 
-            let column_array = match match_by {
-                MatchBy::Position => record_batch
-                    .columns()
-                    .get(attribute_idx)
-                    .unwrap_or_else(|| panic!("column {} not found", name)),
+            let column_array = Self::attribute_column_array(
+                &record_batch,
+                attribute_idx,
+                attribute_context,
+                match_by,
+            );
 
-                MatchBy::Name => record_batch
-                    .column_by_name(name)
-                    .unwrap_or_else(|| panic!("column {} not found", name)),
-            };
+            let datum = if let Some(row_datums) = &primitive_datums[attribute_idx] {
+                row_datums[row_idx]
+            } else if let Some(value_datums) = &dictionary_datums[attribute_idx] {
+                if column_array.is_null(0) {
+                    None
+                } else {
+                    let dictionary_key = column_array.as_any_dictionary().normalized_keys()[0];
 
-            let datum = if attribute_context.needs_cast() {
+                    value_datums[dictionary_key]
+                }
+            } else if attribute_context.needs_cast() {
                 // should fail instead of returning None if the cast fails at runtime
                 let cast_options = CastOptions {
                     safe: false,
                     ..Default::default()
                 };
 
-                let casted_column_array =
-                    cast_with_options(&column_array, attribute_context.data_type(), &cast_options)
-                        .unwrap_or_else(|e| panic!("failed to cast column {}: {}", name, e));
+                let cast = |column_array: &ArrayRef, to_type: &DataType| {
+                    cast_with_options(column_array, to_type, &cast_options)
+                        .unwrap_or_else(|e| panic!("failed to cast column {}: {}", name, e))
+                };
+
+                let from_type = column_array.data_type();
+                let to_type = attribute_context.data_type();
+
+                let casted_column_array = match to_type {
+                    // with list_coercion 'relaxed', the single elements of the lists are read
+                    // into the scalar column
+                    _ if is_list_type(from_type) && !is_list_type(to_type) => cast(
+                        &unwrap_single_element_lists(column_array.clone(), name),
+                        to_type,
+                    ),
+                    // and the scalars are read into the array column as single-element arrays
+                    DataType::List(element_field) if is_scalar_type(from_type) => {
+                        wrap_scalars_into_single_element_lists(
+                            cast(column_array, element_field.data_type()),
+                            element_field.clone(),
+                        )
+                    }
+                    _ => cast(column_array, to_type),
+                };
 
                 to_pg_datum(casted_column_array.to_data(), attribute_context)
             } else {
@@ -169,9 +810,57 @@ impl ParquetReaderContext {
             datums.push(datum);
         }
 
+        set_conversion_attribute_idx(None);
+
         datums
     }
 
+    // try_record_batch_to_tuple_datums converts the row like record_batch_to_tuple_datums, but
+    // returns None if a value fails to convert, e.g. a decimal overflows its numeric column.
+    // Like ON_ERROR of COPY FROM text files, only data errors are ignored, e.g. a query cancel
+    // is not.
+    fn try_record_batch_to_tuple_datums(
+        record_batch: RecordBatch,
+        row_idx: usize,
+        attribute_contexts: &[ArrowToPgAttributeContext],
+        dictionary_datums: &[Option<Vec<Option<Datum>>>],
+        primitive_datums: &[Option<Vec<Option<Datum>>>],
+        match_by: MatchBy,
+    ) -> Option<Vec<Option<Datum>>> {
+        PgTryBuilder::new(AssertUnwindSafe(|| {
+            Some(Self::record_batch_to_tuple_datums(
+                record_batch,
+                row_idx,
+                attribute_contexts,
+                dictionary_datums,
+                primitive_datums,
+                match_by,
+            ))
+        }))
+        .catch_others(|cause| {
+            let is_data_error = match &cause {
+                CaughtError::PostgresError(report) | CaughtError::ErrorReport(report) => {
+                    is_data_exception(report.sql_error_code())
+                }
+                // conversion failures, e.g. a failed cast, are raised as panics
+                CaughtError::RustPanic { .. } => true,
+            };
+
+            if !is_data_error {
+                cause.rethrow()
+            }
+
+            set_conversion_attribute_idx(None);
+
+            None
+        })
+        .execute()
+    }
+
+    // read_parquet converts the next rows of the file into the buffer in PG copy format. Rows
+    // of the current record batch are converted until the buffer has COPY_BUFFER_SIZE bytes,
+    // so that the buffer does not hold a copy of the whole record batch. It returns false once
+    // all of the rows are copied.
     pub(crate) fn read_parquet(&mut self) -> bool {
         if self.finished {
             return false;
@@ -182,46 +871,166 @@ impl ParquetReaderContext {
             self.copy_start();
         }
 
-        // read a record batch from the parquet file. Record batch will contain
-        // DEFAULT_BATCH_SIZE rows as we configured in the parquet reader.
-        let record_batch = PG_BACKEND_TOKIO_RUNTIME.block_on(self.parquet_reader.next());
+        Self::start_value_conversions(self.timestamptz_to_timestamp, self.infinite_timestamps);
 
-        if let Some(batch_result) = record_batch {
-            let record_batch =
-                batch_result.unwrap_or_else(|e| panic!("failed to read record batch: {}", e));
+        // rows, which are skipped by on_error 'ignore', do not fill the buffer
+        while self.buffer.len() < COPY_BUFFER_SIZE && !self.finished {
+            let Some(mut current_record_batch) = self.current_record_batch.take() else {
+                self.read_next_record_batch();
+                continue;
+            };
 
-            let num_rows = record_batch.num_rows();
+            let num_rows = current_record_batch.record_batch.num_rows();
 
-            for i in 0..num_rows {
+            while current_record_batch.next_row_idx < num_rows
+                && self.buffer.len() < COPY_BUFFER_SIZE
+            {
                 check_for_interrupts!();
 
+                self.rows_read += 1;
+                set_conversion_row_number(self.file_row_number(self.rows_read).0);
+
                 // slice the record batch to get the next row
-                let record_batch = record_batch.slice(i, 1);
-                self.copy_row(record_batch);
+                let row_idx = current_record_batch.next_row_idx;
+
+                let record_batch = current_record_batch.record_batch.slice(row_idx, 1);
+
+                current_record_batch.next_row_idx += 1;
+
+                self.copy_row(
+                    record_batch,
+                    row_idx,
+                    &current_record_batch.dictionary_datums,
+                    &current_record_batch.primitive_datums,
+                );
+            }
+
+            if current_record_batch.next_row_idx < num_rows {
+                self.current_record_batch = Some(current_record_batch);
+            } else {
+                // the record batch and its dictionary datums are freed as soon as its rows
+                // are copied
+                drop(current_record_batch);
+                self.per_batch_memory_ctx.reset();
             }
-        } else {
-            // finish PG copy
-            self.copy_finish();
         }
 
+        #[cfg(feature = "pg_test")]
+        #[allow(static_mut_refs)]
+        unsafe {
+            MAX_COPY_BUFFER_SIZE = MAX_COPY_BUFFER_SIZE.max(self.buffer.len())
+        };
+
         true
     }
 
-    fn copy_row(&mut self, record_batch: RecordBatch) {
+    // read_next_record_batch reads the next record batch from the parquet file, or finishes
+    // the PG copy if all of the record batches are read. Record batch will contain
+    // DEFAULT_BATCH_SIZE rows as we configured in the parquet reader.
+    fn read_next_record_batch(&mut self) {
+        let record_batch = match self.remaining_record_batch.take() {
+            Some(record_batch) => record_batch,
+            None => {
+                let Some(record_batch) = self.parquet_reader.next_batch() else {
+                    // finish PG copy
+                    self.copy_finish();
+                    return;
+                };
+
+                match &self.field_id_file_schema {
+                    Some(field_id_file_schema) => {
+                        rename_record_batch_columns(record_batch, field_id_file_schema)
+                    }
+                    None => record_batch,
+                }
+            }
+        };
+
+        let (first_row_number, contiguous_rows) = self.file_row_number(self.rows_read + 1);
+
+        // a record batch, which spans a skipped page, is copied in parts, so that the rows
+        // of each part are contiguous in the file
+        let record_batch = if record_batch.num_rows() > contiguous_rows {
+            self.remaining_record_batch = Some(
+                record_batch.slice(contiguous_rows, record_batch.num_rows() - contiguous_rows),
+            );
+
+            record_batch.slice(0, contiguous_rows)
+        } else {
+            record_batch
+        };
+
+        // dictionary datums should live until all rows of the batch are copied
+        let dictionary_datums = unsafe {
+            self.per_batch_memory_ctx.switch_to(|_context| {
+                Self::dictionary_datums_for_batch(
+                    &record_batch,
+                    &self.attribute_contexts,
+                    self.match_by,
+                    self.on_error,
+                )
+            })
+        };
+
+        let primitive_datums = unsafe {
+            self.per_batch_memory_ctx.switch_to(|_context| {
+                Self::primitive_datums_for_batch(
+                    &record_batch,
+                    &self.attribute_contexts,
+                    self.match_by,
+                    self.on_error,
+                    first_row_number,
+                )
+            })
+        };
+
+        self.current_record_batch = Some(CurrentRecordBatch {
+            record_batch,
+            dictionary_datums,
+            primitive_datums,
+            next_row_idx: 0,
+        });
+    }
+
+    fn copy_row(
+        &mut self,
+        record_batch: RecordBatch,
+        row_idx: usize,
+        dictionary_datums: &[Option<Vec<Option<Datum>>>],
+        primitive_datums: &[Option<Vec<Option<Datum>>>],
+    ) {
         unsafe {
             self.per_row_memory_ctx.switch_to(|_context| {
+                // convert the columnar arrays in record batch to tuple datums
+                let tuple_datums = match self.on_error {
+                    CopyFromOnError::Stop => Some(Self::record_batch_to_tuple_datums(
+                        record_batch,
+                        row_idx,
+                        &self.attribute_contexts,
+                        dictionary_datums,
+                        primitive_datums,
+                        self.match_by,
+                    )),
+                    CopyFromOnError::Ignore => Self::try_record_batch_to_tuple_datums(
+                        record_batch,
+                        row_idx,
+                        &self.attribute_contexts,
+                        dictionary_datums,
+                        primitive_datums,
+                        self.match_by,
+                    ),
+                };
+
+                let Some(tuple_datums) = tuple_datums else {
+                    self.skipped_rows += 1;
+                    return;
+                };
+
                 /* 2 bytes: per-tuple header */
                 let natts = self.attribute_contexts.len() as i16;
                 let attnum_len_bytes = natts.to_be_bytes();
                 self.buffer.extend_from_slice(&attnum_len_bytes);
 
-                // convert the columnar arrays in record batch to tuple datums
-                let tuple_datums = Self::record_batch_to_tuple_datums(
-                    record_batch,
-                    &self.attribute_contexts,
-                    self.match_by,
-                );
-
                 // write the tuple datums to the ParquetReader's internal buffer in PG copy format
                 for (datum, out_func) in tuple_datums.into_iter().zip(self.binary_out_funcs.iter())
                 {
@@ -304,3 +1113,17 @@ impl ParquetReaderContext {
         };
     }
 }
+
+// The following udfs are only used for testing purposes.
+#[cfg(feature = "pg_test")]
+#[pgrx::pg_schema]
+mod parquet_test {
+    use pgrx::pg_extern;
+
+    use super::MAX_COPY_BUFFER_SIZE;
+
+    #[pg_extern]
+    fn copy_from_max_buffer_size() -> i64 {
+        unsafe { MAX_COPY_BUFFER_SIZE as i64 }
+    }
+}
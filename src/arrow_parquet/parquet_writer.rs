@@ -1,147 +1,632 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use arrow::array::RecordBatch;
-use arrow_schema::SchemaRef;
+use arrow_schema::{Schema, SchemaRef};
 use parquet::{
-    arrow::{async_writer::ParquetObjectWriter, AsyncArrowWriter},
-    file::properties::{EnabledStatistics, WriterProperties},
+    arrow::{arrow_to_parquet_schema, async_writer::AsyncFileWriter, AsyncArrowWriter},
+    basic::{Encoding, Type as PhysicalType},
+    errors::ParquetError,
+    file::properties::WriterProperties,
     format::KeyValue,
+    schema::types::ColumnPath,
 };
-use pgrx::{heap_tuple::PgHeapTuple, AllocatedByRust, PgTupleDesc};
+use pgrx::{heap_tuple::PgHeapTuple, pg_sys::FormData_pg_attribute, AllocatedByRust, PgTupleDesc};
+use tokio::task::JoinHandle;
 use url::Url;
 
 use crate::{
     arrow_parquet::{
-        compression::{PgParquetCompression, PgParquetCompressionWithLevel},
+        abortable_object_writer::ObjectWriterAbortHandle,
+        compression::PgParquetCompressionWithLevel,
+        copy_files::record_copy_file,
+        geoparquet_covering::{geoparquet_coverings, GeoparquetCovering},
+        infinite_timestamps::{start_infinite_timestamps, PgParquetInfiniteTimestamps},
+        nan_handling::{finish_nan_handling, start_nan_handling, NanHandlingState},
+        numeric_overflow::{start_unbounded_numeric, UnboundedNumericState},
         pg_to_arrow::context::collect_pg_to_arrow_attribute_contexts,
         schema_parser::{
-            parquet_schema_string_from_attributes, parse_arrow_schema_from_attributes,
+            error_if_copy_to_unsupported_types, nest_multidim_array_fields,
+            parquet_schema_string_from_attributes, parse_arrow_schema_with_field_ids,
         },
-        uri_utils::parquet_writer_from_uri,
+        stream_writer::{CopyToParquetStream, StreamWriter},
+        unsupported_type::PgParquetUnsupportedType,
+        uri_utils::{parquet_writer_from_stream, parquet_writer_from_uri},
     },
-    pgrx_utils::{collect_attributes_for, CollectAttributesFor},
+    object_store::written_objects::mark_object_written,
+    parquet_copy_hook::copy_to_dest_receiver::CopyToParquetOptions,
+    pgrx_utils::{block_on_interruptible, collect_attributes_for, CollectAttributesFor},
     type_compat::{
-        geometry::{geoparquet_metadata_json_from_tupledesc, reset_postgis_context},
+        geometry::{geometry_column_paths, srid_from_typmod, GeometryColumnsMetadata},
+        geometry_crs::{reset_projjson_cache, GeometryColumnSrid},
         map::reset_map_context,
     },
     PG_BACKEND_TOKIO_RUNTIME,
 };
 
-use super::pg_to_arrow::{context::PgToArrowAttributeContext, to_arrow_array};
+use super::pg_to_arrow::{
+    context::PgToArrowAttributeContext,
+    conversion_progress::{finish_conversion, start_attribute_conversion},
+    to_arrow_array,
+};
 
 pub(crate) const DEFAULT_ROW_GROUP_SIZE: i64 = 122880;
 pub(crate) const DEFAULT_ROW_GROUP_SIZE_BYTES: i64 = DEFAULT_ROW_GROUP_SIZE * 1024;
 
+// ParquetWriterDestination is where the parquet file is written, either the object at the uri,
+// or the stream of an extension, which receives the bytes of the file.
+pub(crate) enum ParquetWriterDestination {
+    Uri(Url),
+    Stream(CopyToParquetStream),
+}
+
 pub(crate) struct ParquetWriterContext {
-    parquet_writer: AsyncArrowWriter<ParquetObjectWriter>,
+    destination: ParquetWriterDestination,
+    // taken when the file is finished in the background
+    parquet_writer: Option<AsyncArrowWriter<Box<dyn AsyncFileWriter>>>,
+    // streams cannot be aborted, the caller discards the bytes received so far
+    abort_handle: Option<ObjectWriterAbortHandle>,
+    // aborted writers do not finish the file when dropped
+    aborted: bool,
     schema: SchemaRef,
     attribute_contexts: Vec<PgToArrowAttributeContext>,
+    geoparquet_coverings: Vec<GeoparquetCovering>,
+    geoparquet_metadata: Option<GeometryColumnsMetadata>,
+    geometry_column_srids: Vec<GeometryColumnSrid>,
+    allow_mixed_srids: bool,
+    // options of the value conversions, which are set before the tuples are converted, since
+    // a nested COPY, e.g. in a function called by the query, sets its own options meanwhile
+    infinite_timestamps: PgParquetInfiniteTimestamps,
+    unbounded_numeric: UnboundedNumericState,
+    nan_handling: NanHandlingState,
 }
 
 impl ParquetWriterContext {
     pub(crate) fn new(
-        uri: Url,
-        compression: PgParquetCompression,
-        compression_level: i32,
+        destination: ParquetWriterDestination,
+        options: &CopyToParquetOptions,
         tupledesc: &PgTupleDesc,
+        array_ndims: &HashMap<String, usize>,
     ) -> ParquetWriterContext {
-        // Postgis and Map contexts are used throughout writing the parquet file.
-        // We need to reset them to avoid reading the stale data. (e.g. extension could be dropped)
-        reset_postgis_context();
+        // Map context is used throughout writing the parquet file.
+        // We need to reset it to avoid reading the stale data. (e.g. extension could be dropped)
         reset_map_context();
 
+        // crs of SRIDs are cached only during the COPY, since spatial_ref_sys might change
+        reset_projjson_cache();
+
+        let nan_handling = NanHandlingState::new(options.nan_handling);
+
+        let infinite_timestamps = options.infinite_timestamps;
+
+        let unbounded_numeric = UnboundedNumericState {
+            precision: options.numeric_precision as _,
+            scale: options.numeric_scale as _,
+            overflow: options.numeric_overflow,
+        };
+
+        // the schema of unconstrained numeric columns depends on the numeric options
+        start_infinite_timestamps(infinite_timestamps);
+        start_unbounded_numeric(unbounded_numeric);
+
         let attributes = collect_attributes_for(CollectAttributesFor::CopyTo, tupledesc);
 
+        if options.unsupported_type == PgParquetUnsupportedType::Error {
+            error_if_copy_to_unsupported_types(&attributes);
+        }
+
         pgrx::debug2!(
             "schema for tuples: {}",
             parquet_schema_string_from_attributes(&attributes)
         );
 
-        let schema = parse_arrow_schema_from_attributes(&attributes);
+        let schema = parse_arrow_schema_with_field_ids(&attributes, &options.field_ids());
+
+        // array columns with multidimensional arrays are written as nested lists
+        let schema = nest_multidim_array_fields(schema, array_ndims);
+
+        let geoparquet_coverings = if options.geoparquet_covering {
+            geoparquet_coverings(&attributes, &schema)
+        } else {
+            vec![]
+        };
+
+        // bbox columns of the geometry columns are written after the table columns
+        let schema = Self::schema_with_geoparquet_coverings(schema, &geoparquet_coverings);
         let schema = Arc::new(schema);
 
-        let writer_props = Self::writer_props(tupledesc, compression, compression_level);
+        let writer_props = Self::writer_props(&schema, options);
+
+        let (parquet_writer, abort_handle) = match &destination {
+            ParquetWriterDestination::Uri(uri) => {
+                let (parquet_writer, abort_handle) =
+                    parquet_writer_from_uri(uri, schema.clone(), writer_props);
+
+                (parquet_writer, Some(abort_handle))
+            }
+            ParquetWriterDestination::Stream(stream) => {
+                let stream_writer = StreamWriter::new(*stream);
 
-        let parquet_writer = parquet_writer_from_uri(&uri, schema.clone(), writer_props);
+                let parquet_writer =
+                    parquet_writer_from_stream(stream_writer, schema.clone(), writer_props);
+
+                (parquet_writer, None)
+            }
+        };
 
         let attribute_contexts =
             collect_pg_to_arrow_attribute_contexts(&attributes, &schema.fields);
 
+        let geoparquet_metadata =
+            GeometryColumnsMetadata::from_tupledesc(tupledesc, options.geoparquet_covering);
+
+        let geometry_column_srids = Self::geometry_column_srids(&attributes);
+
         ParquetWriterContext {
-            parquet_writer,
+            destination,
+            parquet_writer: Some(parquet_writer),
+            abort_handle,
+            aborted: false,
             schema,
             attribute_contexts,
+            geoparquet_coverings,
+            geoparquet_metadata,
+            geometry_column_srids,
+            allow_mixed_srids: options.allow_mixed_srids,
+            infinite_timestamps,
+            unbounded_numeric,
+            nan_handling,
         }
     }
 
-    fn writer_props(
-        tupledesc: &PgTupleDesc,
-        compression: PgParquetCompression,
-        compression_level: i32,
-    ) -> WriterProperties {
+    // geometry_column_srids returns the SRID trackers of the geometry columns, and of the
+    // geometries nested in composite types and arrays, whose types do not declare the SRID.
+    // Their crs is known only after all values are written.
+    fn geometry_column_srids(attributes: &[FormData_pg_attribute]) -> Vec<GeometryColumnSrid> {
+        geometry_column_paths(attributes)
+            .into_iter()
+            .filter(|geometry_column_path| srid_from_typmod(geometry_column_path.typmod).is_none())
+            .map(GeometryColumnSrid::new)
+            .collect()
+    }
+
+    fn schema_with_geoparquet_coverings(
+        schema: Schema,
+        geoparquet_coverings: &[GeoparquetCovering],
+    ) -> Schema {
+        if geoparquet_coverings.is_empty() {
+            return schema;
+        }
+
+        let mut fields = schema.fields().iter().cloned().collect::<Vec<_>>();
+
+        for geoparquet_covering in geoparquet_coverings {
+            fields.push(geoparquet_covering.bbox_field());
+        }
+
+        Schema::new_with_metadata(fields, schema.metadata().clone())
+    }
+
+    fn writer_props(schema: &Schema, options: &CopyToParquetOptions) -> WriterProperties {
         let compression = PgParquetCompressionWithLevel {
-            compression,
-            compression_level,
+            compression: options.compression,
+            compression_level: options.compression_level,
         };
 
         let mut writer_props_builder = WriterProperties::builder()
-            .set_statistics_enabled(EnabledStatistics::Page)
+            .set_statistics_enabled(options.statistics.into())
+            // row groups are flushed by the COPY, the limit is only set to prevent the writer
+            // from splitting them at its default limit
+            .set_max_row_group_size(options.row_group_size as usize)
+            .set_dictionary_enabled(options.dictionary_enabled)
+            .set_dictionary_page_size_limit(options.dictionary_page_size_limit as usize)
+            .set_data_page_size_limit(options.data_page_size_bytes as usize)
+            // the row count limit is checked after each batch of write_batch_size values
+            .set_data_page_row_count_limit(options.data_page_row_count_limit as usize)
+            .set_write_batch_size(options.write_batch_size as usize)
             .set_compression(compression.into())
-            .set_created_by("pg_parquet".to_string());
+            .set_writer_version(options.writer_version.into())
+            .set_created_by(format!("pg_parquet version {}", env!("CARGO_PKG_VERSION")));
+
+        let bloom_filter_columns = options.bloom_filter_columns();
+
+        for column_path in
+            Self::leaf_column_paths(schema, &bloom_filter_columns, "bloom_filter_columns")
+        {
+            writer_props_builder = writer_props_builder
+                .set_column_bloom_filter_enabled(column_path.clone(), true)
+                .set_column_bloom_filter_fpp(column_path.clone(), options.bloom_filter_fpp)
+                // a row group cannot have more distinct values than its rows
+                .set_column_bloom_filter_ndv(column_path, options.row_group_size as u64);
+        }
 
-        let geometry_columns_metadata_value = geoparquet_metadata_json_from_tupledesc(tupledesc);
+        let dictionary_columns = options.dictionary_columns();
 
-        if geometry_columns_metadata_value.is_some() {
-            let key_value_metadata = KeyValue::new("geo".into(), geometry_columns_metadata_value);
+        for column_path in
+            Self::leaf_column_paths(schema, &dictionary_columns, "dictionary_columns")
+        {
+            writer_props_builder =
+                writer_props_builder.set_column_dictionary_enabled(column_path, true);
+        }
+
+        let no_dictionary_columns = options.no_dictionary_columns();
 
+        for column_path in
+            Self::leaf_column_paths(schema, &no_dictionary_columns, "no_dictionary_columns")
+        {
             writer_props_builder =
-                writer_props_builder.set_key_value_metadata(Some(vec![key_value_metadata]));
+                writer_props_builder.set_column_dictionary_enabled(column_path, false);
+        }
+
+        // the encoding is used only when the column is not dictionary encoded
+        let byte_stream_split_columns = options.byte_stream_split_columns();
+
+        for column_path in Self::byte_stream_split_column_paths(schema, &byte_stream_split_columns)
+        {
+            writer_props_builder = writer_props_builder
+                .set_column_dictionary_enabled(column_path.clone(), false)
+                .set_column_encoding(column_path, Encoding::BYTE_STREAM_SPLIT);
+        }
+
+        // geoparquet metadata is appended when the writer is finished, since the crs
+        // of geometry columns depends on the SRIDs of the written values
+        let mut key_value_metadata = vec![];
+
+        // custom key/value metadata from kv_metadata option
+        for (key, value) in options.kv_metadata() {
+            key_value_metadata.push(KeyValue::new(key, value));
+        }
+
+        if !key_value_metadata.is_empty() {
+            writer_props_builder =
+                writer_props_builder.set_key_value_metadata(Some(key_value_metadata));
         }
 
         writer_props_builder.build()
     }
 
-    pub(crate) fn write_new_row_group(
+    // leaf_column_paths returns the paths of the leaf columns of the given columns, e.g. for
+    // which bloom filters are written. It errors if any column in the option does not exist.
+    // Nested columns have a path for each of their leaf columns.
+    fn leaf_column_paths(
+        schema: &Schema,
+        columns: &[String],
+        option_name: &str,
+    ) -> Vec<ColumnPath> {
+        if columns.is_empty() {
+            return vec![];
+        }
+
+        let parquet_schema = arrow_to_parquet_schema(schema)
+            .unwrap_or_else(|e| panic!("failed to convert arrow schema to parquet schema: {}", e));
+
+        let mut column_paths = vec![];
+
+        for column_name in columns {
+            if schema.column_with_name(column_name).is_none() {
+                panic!(
+                    "column \"{}\" in {} does not exist",
+                    column_name, option_name
+                );
+            }
+
+            for column in parquet_schema.columns() {
+                if &column.path().parts()[0] == column_name {
+                    column_paths.push(column.path().clone());
+                }
+            }
+        }
+
+        column_paths
+    }
+
+    // byte_stream_split_column_paths returns the paths of the leaf columns of the given columns,
+    // which are written with BYTE_STREAM_SPLIT encoding. It errors if any of them does not have
+    // a fixed width physical type, e.g. float4 and float8 columns.
+    fn byte_stream_split_column_paths(schema: &Schema, columns: &[String]) -> Vec<ColumnPath> {
+        let column_paths = Self::leaf_column_paths(schema, columns, "byte_stream_split_columns");
+
+        if column_paths.is_empty() {
+            return vec![];
+        }
+
+        let parquet_schema = arrow_to_parquet_schema(schema)
+            .unwrap_or_else(|e| panic!("failed to convert arrow schema to parquet schema: {}", e));
+
+        for column in parquet_schema.columns() {
+            if !column_paths.contains(column.path()) {
+                continue;
+            }
+
+            match column.physical_type() {
+                PhysicalType::FLOAT
+                | PhysicalType::DOUBLE
+                | PhysicalType::INT32
+                | PhysicalType::INT64
+                | PhysicalType::FIXED_LEN_BYTE_ARRAY => {}
+                physical_type => panic!(
+                    "column \"{}\" in byte_stream_split_columns has physical type {}, \
+                     byte stream split supports only fixed width types",
+                    column.path().string(),
+                    physical_type
+                ),
+            }
+        }
+
+        column_paths
+    }
+
+    // uri returns the uri of the file, or None if it is written to a stream.
+    pub(crate) fn uri(&self) -> Option<&Url> {
+        match &self.destination {
+            ParquetWriterDestination::Uri(uri) => Some(uri),
+            ParquetWriterDestination::Stream(_) => None,
+        }
+    }
+
+    // bytes_written returns the number of compressed bytes, which are flushed to the file so far.
+    pub(crate) fn bytes_written(&self) -> usize {
+        self.parquet_writer
+            .as_ref()
+            .expect("parquet writer is finished")
+            .bytes_written()
+    }
+
+    // write_tuples writes the tuples to the in progress row group. The row group is flushed
+    // to the file if "finish_row_group" is set. Otherwise, only the encoded column chunks of
+    // the tuples are buffered, so that the tuples and their record batch can be released
+    // before the row group is complete.
+    pub(crate) fn write_tuples(
         &mut self,
         tuples: Vec<Option<PgHeapTuple<AllocatedByRust>>>,
+        finish_row_group: bool,
     ) {
-        let record_batch =
-            Self::pg_tuples_to_record_batch(tuples, &self.attribute_contexts, self.schema.clone());
+        start_infinite_timestamps(self.infinite_timestamps);
+        start_unbounded_numeric(self.unbounded_numeric);
+        start_nan_handling(self.nan_handling);
+
+        for geometry_column_srid in &mut self.geometry_column_srids {
+            geometry_column_srid.track(&tuples, self.allow_mixed_srids);
+        }
+
+        let record_batch = Self::pg_tuples_to_record_batch(
+            tuples,
+            &self.attribute_contexts,
+            &self.geoparquet_coverings,
+            self.schema.clone(),
+        );
 
-        let parquet_writer = &mut self.parquet_writer;
+        self.nan_handling = finish_nan_handling(record_batch.num_rows());
 
-        PG_BACKEND_TOKIO_RUNTIME
-            .block_on(parquet_writer.write(&record_batch))
+        let parquet_writer = self
+            .parquet_writer
+            .as_mut()
+            .expect("parquet writer is finished");
+
+        block_on_interruptible(parquet_writer.write(&record_batch))
             .unwrap_or_else(|e| panic!("failed to write record batch: {}", e));
 
-        PG_BACKEND_TOKIO_RUNTIME
-            .block_on(parquet_writer.flush())
-            .unwrap_or_else(|e| panic!("failed to flush record batch: {}", e));
+        if finish_row_group {
+            block_on_interruptible(parquet_writer.flush())
+                .unwrap_or_else(|e| panic!("failed to flush record batch: {}", e));
+
+            // the bytes of the finished row group are passed to the stream by now
+            if let ParquetWriterDestination::Stream(stream) = &self.destination {
+                stream
+                    .flush()
+                    .unwrap_or_else(|e| panic!("failed to flush stream: {}", e));
+            }
+        }
+    }
+
+    // take_nulled_non_finite_values returns the number of NaN and Infinity values, which are
+    // replaced with NULL since the last call.
+    pub(crate) fn take_nulled_non_finite_values(&mut self) -> usize {
+        self.nan_handling.take_nulled_values()
+    }
+
+    // abort aborts the upload of the file, e.g. when the COPY is canceled, instead of
+    // finishing it when the writer is dropped. It returns false if the upload was already
+    // completing, so that the object might have been written.
+    pub(crate) fn abort(&mut self) -> bool {
+        self.aborted = true;
+
+        match &self.abort_handle {
+            Some(abort_handle) => abort_handle.abort(),
+            None => true,
+        }
+    }
+
+    // finish_in_background finishes the file in a task of the backend's runtime instead of
+    // when the writer is dropped, so that the next file is written while it is uploaded. The
+    // runtime runs on the backend thread, so the task progresses whenever the backend waits
+    // on the runtime, e.g. while it writes the next file.
+    pub(crate) fn finish_in_background(mut self) -> PendingUpload {
+        let ParquetWriterDestination::Uri(uri) = &self.destination else {
+            panic!("stream cannot be finished in the background");
+        };
+
+        let uri = uri.clone();
+
+        self.append_geoparquet_metadata();
+
+        let mut parquet_writer = self
+            .parquet_writer
+            .take()
+            .expect("parquet writer is finished");
+
+        let task = PG_BACKEND_TOKIO_RUNTIME.spawn(async move {
+            let file_metadata = parquet_writer.finish().await?;
+
+            // bytes written include the footer once the file is finished
+            Ok((
+                file_metadata.num_rows,
+                parquet_writer.bytes_written() as i64,
+            ))
+        });
+
+        PendingUpload {
+            uri,
+            task,
+            abort_handle: self.abort_handle.take(),
+        }
+    }
+
+    // fail_in_background is finish_in_background, whose upload fails instead. Tests use it to
+    // check that a failed upload in the background fails the COPY.
+    #[cfg(feature = "pg_test")]
+    pub(crate) fn fail_in_background(mut self) -> PendingUpload {
+        let ParquetWriterDestination::Uri(uri) = &self.destination else {
+            panic!("stream cannot be finished in the background");
+        };
+
+        let uri = uri.clone();
+
+        let parquet_writer = self
+            .parquet_writer
+            .take()
+            .expect("parquet writer is finished");
+
+        let task = PG_BACKEND_TOKIO_RUNTIME.spawn(async move {
+            // the unfinished file is not written
+            drop(parquet_writer);
+
+            Err(ParquetError::General("injected upload failure".into()))
+        });
+
+        PendingUpload {
+            uri,
+            task,
+            abort_handle: self.abort_handle.take(),
+        }
+    }
+
+    fn append_geoparquet_metadata(&mut self) {
+        let Some(mut geoparquet_metadata) = self.geoparquet_metadata.take() else {
+            return;
+        };
+
+        for geometry_column_srid in &self.geometry_column_srids {
+            if let Some(geometry_column) = geoparquet_metadata
+                .columns
+                .get_mut(geometry_column_srid.name())
+            {
+                geometry_column.crs = geometry_column_srid.crs();
+            }
+        }
+
+        if let Some(parquet_writer) = self.parquet_writer.as_mut() {
+            parquet_writer.append_key_value_metadata(KeyValue::new(
+                "geo".into(),
+                geoparquet_metadata.to_json(),
+            ));
+        }
     }
 
     fn pg_tuples_to_record_batch(
         tuples: Vec<Option<PgHeapTuple<AllocatedByRust>>>,
         attribute_contexts: &[PgToArrowAttributeContext],
+        geoparquet_coverings: &[GeoparquetCovering],
         schema: SchemaRef,
     ) -> RecordBatch {
         let mut attribute_arrays = vec![];
 
         for attribute_context in attribute_contexts {
+            start_attribute_conversion(
+                attribute_context.attnum(),
+                attribute_context.field().name(),
+            );
+
             let attribute_array = to_arrow_array(&tuples, attribute_context);
 
             attribute_arrays.push(attribute_array);
         }
 
+        finish_conversion();
+
+        let bbox_arrays = geoparquet_coverings
+            .iter()
+            .map(|geoparquet_covering| geoparquet_covering.bbox_array(&attribute_arrays))
+            .collect::<Vec<_>>();
+
+        attribute_arrays.extend(bbox_arrays);
+
         RecordBatch::try_new(schema, attribute_arrays).expect("Expected record batch")
     }
 }
 
 impl Drop for ParquetWriterContext {
     fn drop(&mut self) {
-        PG_BACKEND_TOKIO_RUNTIME
-            .block_on(self.parquet_writer.finish())
+        if self.aborted {
+            return;
+        }
+
+        self.append_geoparquet_metadata();
+
+        // the file is already finished in the background
+        let Some(parquet_writer) = self.parquet_writer.as_mut() else {
+            return;
+        };
+
+        // finishing the file is not interrupted, since the upload cannot be aborted once
+        // it is completing
+        let file_metadata = PG_BACKEND_TOKIO_RUNTIME
+            .block_on(parquet_writer.finish())
             .unwrap_or_else(|e| {
                 panic!("failed to close parquet writer: {}", e);
             });
+
+        let ParquetWriterDestination::Uri(uri) = &self.destination else {
+            return;
+        };
+
+        mark_object_written(uri);
+
+        // bytes written include the footer once the file is finished
+        record_copy_file(
+            uri,
+            file_metadata.num_rows,
+            parquet_writer.bytes_written() as i64,
+        );
+    }
+}
+
+// PendingUpload is the file of a COPY TO, which is finished in the background. The file is
+// recorded as written by the COPY once its upload is awaited.
+pub(crate) struct PendingUpload {
+    uri: Url,
+    // returns the row count and the size of the finished file
+    task: JoinHandle<Result<(i64, i64), ParquetError>>,
+    abort_handle: Option<ObjectWriterAbortHandle>,
+}
+
+impl PendingUpload {
+    pub(crate) fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    // wait waits until the file is uploaded and records it. It fails the COPY if the upload
+    // failed. The upload is not interrupted, since it cannot be aborted once it is completing.
+    pub(crate) fn wait(self) {
+        let (row_count, size_bytes) = PG_BACKEND_TOKIO_RUNTIME
+            .block_on(self.task)
+            .unwrap_or_else(|e| panic!("failed to finish parquet file: {}", e))
+            .unwrap_or_else(|e| panic!("failed to close parquet writer: {}", e));
+
+        mark_object_written(&self.uri);
+
+        record_copy_file(&self.uri, row_count, size_bytes);
+    }
+
+    // abort cancels the upload of the file, e.g. when the COPY fails. The object might be
+    // written if its upload was already completing, so the caller removes it afterwards.
+    pub(crate) fn abort(self) {
+        self.task.abort();
+
+        // the canceled task releases the writer, which the abort handle shares
+        let _ = PG_BACKEND_TOKIO_RUNTIME.block_on(self.task);
+
+        if let Some(abort_handle) = self.abort_handle {
+            abort_handle.abort();
+        }
     }
 }
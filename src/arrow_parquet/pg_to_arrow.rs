@@ -1,19 +1,26 @@
-use std::num::NonZeroUsize;
+use std::{num::NonZeroUsize, sync::Arc};
 
-use arrow::array::ArrayRef;
+use arrow::{
+    array::{ArrayBuilder, ArrayRef, AsArray, ListArray},
+    buffer::OffsetBuffer,
+};
 use context::PgToArrowAttributeContext;
+use conversion_progress::{check_conversion_memory_limit, track_conversion_row};
 use pgrx::{
     check_for_interrupts,
     datum::{Date, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone, UnboxDatum},
+    ereport,
     heap_tuple::PgHeapTuple,
     pg_sys::{
-        Oid, BOOLOID, BYTEAOID, CHAROID, DATEOID, FLOAT4OID, FLOAT8OID, INT2OID, INT4OID, INT8OID,
-        NUMERICOID, OIDOID, TEXTOID, TIMEOID, TIMESTAMPOID, TIMESTAMPTZOID, TIMETZOID,
+        Oid, BOOLOID, BYTEAOID, CASHOID, CHAROID, CIDROID, DATEOID, FLOAT4OID, FLOAT8OID, INETOID,
+        INT2OID, INT4OID, INT8OID, MACADDR8OID, MACADDROID, NUMERICOID, OIDOID, TEXTOID, TIMEOID,
+        TIMESTAMPOID, TIMESTAMPTZOID, TIMETZOID,
     },
-    AllocatedByRust, AnyNumeric, FromDatum,
+    AllocatedByRust, AnyNumeric, FromDatum, IntoDatum, PgLogLevel, PgSqlErrorCode,
 };
 
 use crate::{
+    arrow_parquet::schema_parser::multidim_list_element_field,
     pgrx_utils::{
         array_element_typoid, collect_attributes_for, domain_array_base_elem_type, is_array_type,
         is_composite_type, tuple_desc, CollectAttributesFor,
@@ -22,9 +29,13 @@ use crate::{
         fallback_to_text::{reset_fallback_to_text_context, FallbackToText},
         geometry::{is_postgis_geometry_type, Geometry},
         map::{is_map_type, reset_map_type_context, Map},
+        money::Money,
+        multidim_array::ArrayDims,
+        network_address::{Cidr, Inet, MacAddr, MacAddr8},
         pg_arrow_type_conversions::{
             extract_precision_and_scale_from_numeric_typmod, should_write_numeric_as_text,
         },
+        server_encoding::ServerEncodedText,
     },
 };
 
@@ -33,15 +44,19 @@ pub(crate) mod bytea;
 pub(crate) mod char;
 pub(crate) mod composite;
 pub(crate) mod context;
+pub(crate) mod conversion_progress;
 pub(crate) mod date;
 pub(crate) mod fallback_to_text;
 pub(crate) mod float4;
 pub(crate) mod float8;
 pub(crate) mod geometry;
+pub(crate) mod inet;
 pub(crate) mod int2;
 pub(crate) mod int4;
 pub(crate) mod int8;
+pub(crate) mod macaddr;
 pub(crate) mod map;
+pub(crate) mod money;
 pub(crate) mod numeric;
 pub(crate) mod oid;
 pub(crate) mod text;
@@ -54,6 +69,16 @@ pub(crate) trait PgTypeToArrowArray<T: FromDatum + UnboxDatum> {
     fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef;
 }
 
+// PgTypeToArrowBuilder appends the values of a pass-by-value type directly into a typed arrow
+// builder, so that their columns are converted without collecting the values of a batch first.
+pub(crate) trait PgTypeToArrowBuilder: FromDatum + IntoDatum + Sized {
+    type Builder: ArrayBuilder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder;
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, row_idx: usize);
+}
+
 pub(crate) fn to_arrow_array(
     tuples: &Vec<Option<PgHeapTuple<AllocatedByRust>>>,
     attribute_context: &PgToArrowAttributeContext,
@@ -69,9 +94,11 @@ macro_rules! to_arrow_primitive_array {
     ($pg_type:ty, $tuples:expr, $attribute_context:expr) => {{
         let mut attribute_vals = vec![];
 
-        for tuple in $tuples {
+        for (row_idx, tuple) in $tuples.iter().enumerate() {
             check_for_interrupts!();
 
+            track_conversion_row(row_idx);
+
             if let Some(tuple) = tuple {
                 let attribute_val: Option<$pg_type> = tuple
                     .get_by_index(
@@ -84,19 +111,53 @@ macro_rules! to_arrow_primitive_array {
             } else {
                 attribute_vals.push(None);
             }
+
+            check_conversion_memory_limit();
         }
 
         return attribute_vals.to_arrow_array($attribute_context);
     }};
 }
 
+// to_arrow_fixed_width_array converts the values of a pass-by-value type, which are appended
+// into the builder of the column as they are read from the tuples.
+fn to_arrow_fixed_width_array<T: PgTypeToArrowBuilder>(
+    tuples: &[Option<PgHeapTuple<AllocatedByRust>>],
+    attribute_context: &PgToArrowAttributeContext,
+) -> ArrayRef {
+    let attnum = NonZeroUsize::new(attribute_context.attnum() as usize).expect("invalid attnum");
+
+    let mut builder = T::new_arrow_builder(tuples.len());
+
+    for (row_idx, tuple) in tuples.iter().enumerate() {
+        check_for_interrupts!();
+
+        track_conversion_row(row_idx);
+
+        let attribute_val: Option<T> = match tuple {
+            Some(tuple) => tuple
+                .get_by_index(attnum)
+                .unwrap_or_else(|e| panic!("failed to get attribute: {}", e)),
+            None => None,
+        };
+
+        T::append_to_arrow_builder(&mut builder, attribute_val, row_idx);
+
+        check_conversion_memory_limit();
+    }
+
+    ArrayBuilder::finish(&mut builder)
+}
+
 macro_rules! to_arrow_list_array {
     ($pg_type:ty, $tuples:expr, $attribute_context:expr) => {{
         let mut attribute_vals = vec![];
 
-        for tuple in $tuples {
+        for (row_idx, tuple) in $tuples.iter().enumerate() {
             check_for_interrupts!();
 
+            track_conversion_row(row_idx);
+
             if let Some(tuple) = tuple {
                 let attribute_val: Option<$pg_type> = tuple
                     .get_by_index(
@@ -109,6 +170,8 @@ macro_rules! to_arrow_list_array {
             } else {
                 attribute_vals.push(None);
             }
+
+            check_conversion_memory_limit();
         }
 
         let attribute_vals = attribute_vals
@@ -125,11 +188,11 @@ fn to_arrow_primitive_array(
     attribute_context: &PgToArrowAttributeContext,
 ) -> ArrayRef {
     match attribute_context.typoid() {
-        FLOAT4OID => to_arrow_primitive_array!(f32, tuples, attribute_context),
-        FLOAT8OID => to_arrow_primitive_array!(f64, tuples, attribute_context),
-        INT2OID => to_arrow_primitive_array!(i16, tuples, attribute_context),
-        INT4OID => to_arrow_primitive_array!(i32, tuples, attribute_context),
-        INT8OID => to_arrow_primitive_array!(i64, tuples, attribute_context),
+        FLOAT4OID => to_arrow_fixed_width_array::<f32>(tuples, attribute_context),
+        FLOAT8OID => to_arrow_fixed_width_array::<f64>(tuples, attribute_context),
+        INT2OID => to_arrow_fixed_width_array::<i16>(tuples, attribute_context),
+        INT4OID => to_arrow_fixed_width_array::<i32>(tuples, attribute_context),
+        INT8OID => to_arrow_fixed_width_array::<i64>(tuples, attribute_context),
         NUMERICOID => {
             let precision = attribute_context.precision();
 
@@ -144,27 +207,34 @@ fn to_arrow_primitive_array(
                 to_arrow_primitive_array!(AnyNumeric, tuples, attribute_context)
             }
         }
-        BOOLOID => to_arrow_primitive_array!(bool, tuples, attribute_context),
-        DATEOID => to_arrow_primitive_array!(Date, tuples, attribute_context),
+        CASHOID => to_arrow_primitive_array!(Money, tuples, attribute_context),
+        BOOLOID => to_arrow_fixed_width_array::<bool>(tuples, attribute_context),
+        DATEOID => to_arrow_fixed_width_array::<Date>(tuples, attribute_context),
         TIMEOID => to_arrow_primitive_array!(Time, tuples, attribute_context),
         TIMETZOID => to_arrow_primitive_array!(TimeWithTimeZone, tuples, attribute_context),
-        TIMESTAMPOID => to_arrow_primitive_array!(Timestamp, tuples, attribute_context),
+        TIMESTAMPOID => to_arrow_fixed_width_array::<Timestamp>(tuples, attribute_context),
         TIMESTAMPTZOID => {
             to_arrow_primitive_array!(TimestampWithTimeZone, tuples, attribute_context)
         }
         CHAROID => to_arrow_primitive_array!(i8, tuples, attribute_context),
-        TEXTOID => to_arrow_primitive_array!(String, tuples, attribute_context),
+        TEXTOID => to_arrow_primitive_array!(ServerEncodedText, tuples, attribute_context),
         BYTEAOID => to_arrow_primitive_array!(&[u8], tuples, attribute_context),
-        OIDOID => to_arrow_primitive_array!(Oid, tuples, attribute_context),
+        OIDOID => to_arrow_fixed_width_array::<Oid>(tuples, attribute_context),
+        INETOID => to_arrow_primitive_array!(Inet, tuples, attribute_context),
+        CIDROID => to_arrow_primitive_array!(Cidr, tuples, attribute_context),
+        MACADDROID => to_arrow_primitive_array!(MacAddr, tuples, attribute_context),
+        MACADDR8OID => to_arrow_primitive_array!(MacAddr8, tuples, attribute_context),
         _ => {
             if attribute_context.is_composite() {
                 let mut attribute_vals = vec![];
 
                 let attribute_tupledesc = attribute_context.tupledesc();
 
-                for tuple in tuples {
+                for (row_idx, tuple) in tuples.iter().enumerate() {
                     check_for_interrupts!();
 
+                    track_conversion_row(row_idx);
+
                     if let Some(tuple) = tuple {
                         let attribute_val: Option<PgHeapTuple<AllocatedByRust>> = tuple
                             .get_by_index(
@@ -189,6 +259,8 @@ fn to_arrow_primitive_array(
                     } else {
                         attribute_vals.push(None);
                     }
+
+                    check_conversion_memory_limit();
                 }
 
                 attribute_vals.to_arrow_array(attribute_context)
@@ -213,6 +285,142 @@ fn to_arrow_primitive_array(
 fn to_arrow_list_array(
     tuples: &Vec<Option<PgHeapTuple<AllocatedByRust>>>,
     attribute_context: &PgToArrowAttributeContext,
+) -> ArrayRef {
+    let array_dims = collect_array_dims(tuples, attribute_context);
+
+    let list_array = to_arrow_flat_list_array(tuples, attribute_context);
+
+    if attribute_context.array_ndims() == 1 {
+        list_array
+    } else {
+        nest_list_array(list_array, &array_dims, attribute_context)
+    }
+}
+
+// collect_array_dims returns the dimensions of the arrays, which should match the number of
+// nested lists of the column. Empty arrays do not have any dimensions.
+fn collect_array_dims(
+    tuples: &[Option<PgHeapTuple<AllocatedByRust>>],
+    attribute_context: &PgToArrowAttributeContext,
+) -> Vec<Option<ArrayDims>> {
+    let ndims = attribute_context.array_ndims();
+    let attribute_field = attribute_context.field();
+
+    tuples
+        .iter()
+        .map(|tuple| {
+            check_for_interrupts!();
+
+            let array_dims: Option<ArrayDims> = tuple.as_ref().and_then(|tuple| {
+                tuple
+                    .get_by_index(
+                        NonZeroUsize::new(attribute_context.attnum() as usize)
+                            .expect("invalid attnum"),
+                    )
+                    .unwrap_or_else(|e| panic!("failed to get attribute: {}", e))
+            });
+
+            let found_ndims = array_dims.as_ref().map(|dims| dims.ndims()).unwrap_or(0);
+
+            if found_ndims > 1 && ndims == 1 {
+                ereport!(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                    format!(
+                        "multidimensional arrays are not supported, found {} dimensions in column \"{}\"",
+                        found_ndims,
+                        attribute_field.name()
+                    ),
+                    "Use multidim_arrays 'nested' to write the array columns as nested lists.",
+                );
+            } else if found_ndims > 0 && found_ndims != ndims {
+                ereport!(
+                    PgLogLevel::ERROR,
+                    PgSqlErrorCode::ERRCODE_ARRAY_SUBSCRIPT_ERROR,
+                    format!(
+                        "found {} dimensions in column \"{}\", which is written as {} nested lists",
+                        found_ndims,
+                        attribute_field.name(),
+                        ndims
+                    ),
+                    "The number of nested lists is decided by the arrays of the first rows.",
+                );
+            }
+
+            array_dims
+        })
+        .collect()
+}
+
+// nest_list_array returns the nested lists of the multidimensional arrays, whose elements are
+// in the given flat list array, e.g. [[1,2],[3,4]] for '{{1,2},{3,4}}'.
+fn nest_list_array(
+    list_array: ArrayRef,
+    array_dims: &[Option<ArrayDims>],
+    attribute_context: &PgToArrowAttributeContext,
+) -> ArrayRef {
+    let list_array = list_array.as_list::<i32>();
+
+    let ndims = attribute_context.array_ndims();
+
+    // offsets of the lists at each level, the outermost level has a list for each row
+    let mut level_offsets = vec![vec![0_i32]; ndims];
+
+    for dims in array_dims {
+        let dims = dims
+            .as_ref()
+            .map(|dims| dims.0.as_slice())
+            .unwrap_or_default();
+
+        // null and empty arrays have an empty outermost list
+        if dims.is_empty() {
+            let last_offset = *level_offsets[0].last().expect("missing offset");
+            level_offsets[0].push(last_offset);
+            continue;
+        }
+
+        let mut list_count = 1;
+
+        for (offsets, dim) in level_offsets.iter_mut().zip(dims) {
+            for _ in 0..list_count {
+                let last_offset = *offsets.last().expect("missing offset");
+                offsets.push(last_offset + *dim as i32);
+            }
+
+            list_count *= dim;
+        }
+    }
+
+    let element_field = attribute_context.element_context().field();
+
+    let mut nested_array = list_array.values().clone();
+
+    for (level, offsets) in level_offsets.into_iter().enumerate().rev() {
+        let list_field = multidim_list_element_field(element_field.clone(), ndims - level);
+
+        // only the outermost lists are null, i.e. null arrays
+        let nulls = if level == 0 {
+            list_array.nulls().cloned()
+        } else {
+            None
+        };
+
+        nested_array = Arc::new(ListArray::new(
+            list_field,
+            OffsetBuffer::new(offsets.into()),
+            nested_array,
+            nulls,
+        ));
+    }
+
+    nested_array
+}
+
+// to_arrow_flat_list_array converts the arrays into lists of their elements, e.g. [1,2,3,4] for
+// '{{1,2},{3,4}}'.
+fn to_arrow_flat_list_array(
+    tuples: &Vec<Option<PgHeapTuple<AllocatedByRust>>>,
+    attribute_context: &PgToArrowAttributeContext,
 ) -> ArrayRef {
     let element_context = attribute_context.element_context();
     let element_typoid = element_context.typoid();
@@ -235,6 +443,7 @@ fn to_arrow_list_array(
                 to_arrow_list_array!(pgrx::Array<AnyNumeric>, tuples, element_context)
             }
         }
+        CASHOID => to_arrow_list_array!(pgrx::Array<Money>, tuples, element_context),
         BOOLOID => to_arrow_list_array!(pgrx::Array<bool>, tuples, element_context),
         DATEOID => to_arrow_list_array!(pgrx::Array<Date>, tuples, element_context),
         TIMEOID => to_arrow_list_array!(pgrx::Array<Time>, tuples, element_context),
@@ -248,18 +457,26 @@ fn to_arrow_list_array(
             to_arrow_list_array!(pgrx::Array<TimestampWithTimeZone>, tuples, element_context)
         }
         CHAROID => to_arrow_list_array!(pgrx::Array<i8>, tuples, element_context),
-        TEXTOID => to_arrow_list_array!(pgrx::Array<String>, tuples, element_context),
+        TEXTOID => {
+            to_arrow_list_array!(pgrx::Array<ServerEncodedText>, tuples, element_context)
+        }
         BYTEAOID => to_arrow_list_array!(pgrx::Array<&[u8]>, tuples, element_context),
         OIDOID => to_arrow_list_array!(pgrx::Array<Oid>, tuples, element_context),
+        INETOID => to_arrow_list_array!(pgrx::Array<Inet>, tuples, element_context),
+        CIDROID => to_arrow_list_array!(pgrx::Array<Cidr>, tuples, element_context),
+        MACADDROID => to_arrow_list_array!(pgrx::Array<MacAddr>, tuples, element_context),
+        MACADDR8OID => to_arrow_list_array!(pgrx::Array<MacAddr8>, tuples, element_context),
         _ => {
             if element_context.is_composite() {
                 let mut attribute_vals = vec![];
 
                 let attribute_tupledesc = element_context.tupledesc();
 
-                for tuple in tuples {
+                for (row_idx, tuple) in tuples.iter().enumerate() {
                     check_for_interrupts!();
 
+                    track_conversion_row(row_idx);
+
                     if let Some(tuple) = tuple {
                         let attribute_val: Option<pgrx::Array<PgHeapTuple<AllocatedByRust>>> =
                             tuple
@@ -295,6 +512,8 @@ fn to_arrow_list_array(
                     } else {
                         attribute_vals.push(None);
                     }
+
+                    check_conversion_memory_limit();
                 }
 
                 attribute_vals.to_arrow_array(element_context)
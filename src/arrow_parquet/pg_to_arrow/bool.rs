@@ -1,16 +1,24 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, BooleanArray, ListArray};
+use arrow::array::{ArrayRef, BooleanArray, BooleanBuilder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Bool
-impl PgTypeToArrowArray<bool> for Vec<Option<bool>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let bool_array = BooleanArray::from(self);
-        Arc::new(bool_array)
+impl PgTypeToArrowBuilder for bool {
+    type Builder = BooleanBuilder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        BooleanBuilder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value);
     }
 }
 
@@ -9,7 +9,9 @@ use pgrx::{heap_tuple::PgHeapTuple, AllocatedByRust};
 
 use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
 
-use super::{to_arrow_array, PgToArrowAttributeContext};
+use super::{
+    conversion_progress::convert_nested_elements, to_arrow_array, PgToArrowAttributeContext,
+};
 
 // PgHeapTuple
 impl PgTypeToArrowArray<PgHeapTuple<'_, AllocatedByRust>>
@@ -54,7 +56,7 @@ impl PgTypeToArrowArray<PgHeapTuple<'_, AllocatedByRust>>
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
         let tuples = self.into_iter().flatten().flatten().collect::<Vec<_>>();
 
-        let struct_array = tuples.to_arrow_array(element_context);
+        let struct_array = convert_nested_elements(|| tuples.to_arrow_array(element_context));
 
         let list_array = ListArray::new(
             element_context.field(),
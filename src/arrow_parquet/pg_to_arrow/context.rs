@@ -82,6 +82,8 @@ pub(crate) enum PgToArrowAttributeTypeContext {
     },
     Array {
         element_context: Box<PgToArrowAttributeContext>,
+        // number of the nested lists, i.e. the dimensions of the arrays
+        ndims: usize,
     },
     Composite {
         tupledesc: PgTupleDesc<'static>,
@@ -131,11 +133,19 @@ impl PgToArrowAttributeTypeContext {
         let element_typoid = array_element_typoid(typoid);
         let element_typmod = typmod;
 
-        let element_field = match field.data_type() {
+        let mut element_field = match field.data_type() {
             arrow::datatypes::DataType::List(field) => field.clone(),
             _ => unreachable!(),
         };
 
+        // multidimensional arrays are written as nested lists
+        let mut ndims = 1;
+
+        while let arrow::datatypes::DataType::List(field) = element_field.data_type() {
+            element_field = field.clone();
+            ndims += 1;
+        }
+
         let element_type_context = PgToArrowAttributeTypeContext::new(
             attnum,
             element_typoid,
@@ -151,7 +161,10 @@ impl PgToArrowAttributeTypeContext {
             type_context: element_type_context,
         });
 
-        Self::Array { element_context }
+        Self::Array {
+            element_context,
+            ndims,
+        }
     }
 
     fn new_composite(typoid: Oid, typmod: i32, field: FieldRef) -> Self {
@@ -251,6 +264,13 @@ impl PgToArrowAttributeTypeContext {
         }
     }
 
+    pub(crate) fn array_ndims(&self) -> usize {
+        match self {
+            Self::Array { ndims, .. } => *ndims,
+            _ => panic!("not a context for an array type"),
+        }
+    }
+
     // type checks
     pub(crate) fn is_array(&self) -> bool {
         matches!(self, PgToArrowAttributeTypeContext::Array { .. })
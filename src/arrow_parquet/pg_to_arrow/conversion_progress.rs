@@ -0,0 +1,121 @@
+#[cfg(feature = "pg_test")]
+use pgrx::{
+    ereport,
+    pg_sys::{CurrentMemoryContext, MemoryContextMemAllocated},
+    GucSetting, PgLogLevel, PgSqlErrorCode,
+};
+
+// TEST_CONVERSION_MEMORY_LIMIT is only used by tests to simulate a tiny conversion memory context.
+// Conversion fails with out of memory when the context allocates more than the limit (in bytes).
+// 0 means no limit.
+#[cfg(feature = "pg_test")]
+pub(crate) static TEST_CONVERSION_MEMORY_LIMIT: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+// ConversionProgress keeps track of the attribute and the row, within the current row group,
+// which are being converted to arrow. It is used to report which value could not be converted
+// when the conversion fails with out of memory.
+#[derive(Debug, Clone)]
+pub(crate) struct ConversionProgress {
+    pub(crate) attnum: i16,
+    pub(crate) attname: String,
+    pub(crate) row_idx: usize,
+    // flattened array elements and map entries do not correspond to the rows of the row group,
+    // so we do not track the row while converting them
+    nested_level: usize,
+}
+
+static mut CONVERSION_PROGRESS: ConversionProgress = ConversionProgress {
+    attnum: 0,
+    attname: String::new(),
+    row_idx: 0,
+    nested_level: 0,
+};
+
+pub(crate) fn start_attribute_conversion(attnum: i16, attname: &str) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        CONVERSION_PROGRESS = ConversionProgress {
+            attnum,
+            attname: attname.to_string(),
+            row_idx: 0,
+            nested_level: 0,
+        };
+    }
+}
+
+pub(crate) fn finish_conversion() {
+    start_attribute_conversion(0, "");
+}
+
+// current_conversion_progress returns the progress of the ongoing conversion, if any.
+pub(crate) fn current_conversion_progress() -> Option<ConversionProgress> {
+    #[allow(static_mut_refs)]
+    let progress = unsafe { CONVERSION_PROGRESS.clone() };
+
+    if progress.attnum == 0 {
+        None
+    } else {
+        Some(progress)
+    }
+}
+
+pub(crate) fn track_conversion_row(row_idx: usize) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        if CONVERSION_PROGRESS.nested_level == 0 {
+            CONVERSION_PROGRESS.row_idx = row_idx;
+        }
+    }
+}
+
+// convert_nested_elements runs the given conversion of flattened array elements or map entries
+// without tracking their indexes as rows.
+pub(crate) fn convert_nested_elements<T>(convert: impl FnOnce() -> T) -> T {
+    #[allow(static_mut_refs)]
+    unsafe {
+        CONVERSION_PROGRESS.nested_level += 1
+    };
+
+    let result = convert();
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        CONVERSION_PROGRESS.nested_level -= 1
+    };
+
+    result
+}
+
+// is_converting_nested_elements returns true while converting flattened array elements or
+// map entries, whose indexes are not rows.
+pub(crate) fn is_converting_nested_elements() -> bool {
+    #[allow(static_mut_refs)]
+    unsafe {
+        CONVERSION_PROGRESS.nested_level > 0
+    }
+}
+
+pub(crate) fn check_conversion_memory_limit() {
+    #[cfg(feature = "pg_test")]
+    {
+        let limit = TEST_CONVERSION_MEMORY_LIMIT.get();
+
+        if limit <= 0 {
+            return;
+        }
+
+        let allocated = unsafe { MemoryContextMemAllocated(CurrentMemoryContext, true) };
+
+        if allocated > limit as _ {
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY,
+                "out of memory",
+                format!(
+                    "Conversion memory context exceeded the limit of {} bytes.",
+                    limit
+                ),
+            );
+        }
+    }
+}
@@ -1,24 +1,28 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Date32Array, ListArray};
+use arrow::array::{ArrayRef, Date32Array, Date32Builder, ListArray};
 use pgrx::datum::Date;
 
 use crate::{
-    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    arrow_parquet::{
+        arrow_utils::arrow_array_offsets,
+        pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+    },
     type_compat::pg_arrow_type_conversions::date_to_i32,
 };
 
 use super::PgToArrowAttributeContext;
 
 // Date
-impl PgTypeToArrowArray<Date> for Vec<Option<Date>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let dates = self
-            .into_iter()
-            .map(|date| date.map(date_to_i32))
-            .collect::<Vec<_>>();
-        let date_array = Date32Array::from(dates);
-        Arc::new(date_array)
+impl PgTypeToArrowBuilder for Date {
+    type Builder = Date32Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Date32Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value.map(date_to_i32));
     }
 }
 
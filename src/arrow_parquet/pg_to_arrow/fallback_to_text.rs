@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, ListArray, StringArray};
+use arrow::{
+    array::{ArrayRef, DictionaryArray, ListArray, StringArray},
+    datatypes::{DataType, Int32Type},
+};
 
 use crate::{
     arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
@@ -11,13 +14,13 @@ use super::PgToArrowAttributeContext;
 
 // Text representation of any type
 impl PgTypeToArrowArray<FallbackToText> for Vec<Option<FallbackToText>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
+    fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef {
         let texts = self
             .into_iter()
             .map(|f| f.map(String::from))
             .collect::<Vec<_>>();
-        let text_array = StringArray::from(texts);
-        Arc::new(text_array)
+
+        to_text_array(texts, context)
     }
 }
 
@@ -34,15 +37,24 @@ impl PgTypeToArrowArray<FallbackToText> for Vec<Option<Vec<Option<FallbackToText
             .map(|f| f.map(String::from))
             .collect::<Vec<_>>();
 
-        let text_array = StringArray::from(pg_array);
+        let text_array = to_text_array(pg_array, element_context);
 
-        let list_array = ListArray::new(
-            element_context.field(),
-            offsets,
-            Arc::new(text_array),
-            Some(nulls),
-        );
+        let list_array = ListArray::new(element_context.field(), offsets, text_array, Some(nulls));
 
         Arc::new(list_array)
     }
 }
+
+// to_text_array returns a dictionary array for enums, whose fields are dictionaries, and a
+// string array otherwise.
+fn to_text_array(texts: Vec<Option<String>>, context: &PgToArrowAttributeContext) -> ArrayRef {
+    match context.field().data_type() {
+        DataType::Dictionary(_, _) => Arc::new(
+            texts
+                .iter()
+                .map(|text| text.as_deref())
+                .collect::<DictionaryArray<Int32Type>>(),
+        ),
+        _ => Arc::new(StringArray::from(texts)),
+    }
+}
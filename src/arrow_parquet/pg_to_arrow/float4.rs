@@ -1,22 +1,44 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Float32Array, ListArray};
+use arrow::array::{ArrayRef, Float32Array, Float32Builder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    nan_handling::handle_non_finite_floats,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Float32
-impl PgTypeToArrowArray<f32> for Vec<Option<f32>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let float_array = Float32Array::from(self);
-        Arc::new(float_array)
+impl PgTypeToArrowBuilder for f32 {
+    type Builder = Float32Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Float32Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(
+        builder: &mut Self::Builder,
+        mut value: Option<Self>,
+        row_idx: usize,
+    ) {
+        handle_non_finite_floats(std::iter::once((row_idx, &mut value)), f32::is_finite);
+
+        builder.append_option(value);
     }
 }
 
 // Float32[]
 impl PgTypeToArrowArray<pgrx::Array<'_, f32>> for Vec<Option<Vec<Option<f32>>>> {
-    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+    fn to_arrow_array(mut self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let elements = self
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(row_idx, array)| array.iter_mut().flatten().map(move |e| (row_idx, e)));
+
+        handle_non_finite_floats(elements, f32::is_finite);
+
         let (offsets, nulls) = arrow_array_offsets(&self);
 
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
@@ -1,22 +1,44 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Float64Array, ListArray};
+use arrow::array::{ArrayRef, Float64Array, Float64Builder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    nan_handling::handle_non_finite_floats,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Float64
-impl PgTypeToArrowArray<f64> for Vec<Option<f64>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let double_array = Float64Array::from(self);
-        Arc::new(double_array)
+impl PgTypeToArrowBuilder for f64 {
+    type Builder = Float64Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Float64Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(
+        builder: &mut Self::Builder,
+        mut value: Option<Self>,
+        row_idx: usize,
+    ) {
+        handle_non_finite_floats(std::iter::once((row_idx, &mut value)), f64::is_finite);
+
+        builder.append_option(value);
     }
 }
 
 // Float64[]
 impl PgTypeToArrowArray<f64> for Vec<Option<Vec<Option<f64>>>> {
-    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+    fn to_arrow_array(mut self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let elements = self
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(row_idx, array)| array.iter_mut().flatten().map(move |e| (row_idx, e)));
+
+        handle_non_finite_floats(elements, f64::is_finite);
+
         let (offsets, nulls) = arrow_array_offsets(&self);
 
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
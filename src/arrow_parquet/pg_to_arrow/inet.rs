@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, BinaryArray, BooleanArray, ListArray, StructArray, UInt8Array},
+    buffer::NullBuffer,
+};
+use arrow_schema::{DataType, FieldRef};
+
+use crate::{
+    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    type_compat::network_address::{Cidr, Inet, NetworkAddress},
+};
+
+use super::PgToArrowAttributeContext;
+
+// Inet
+impl PgTypeToArrowArray<Inet> for Vec<Option<Inet>> {
+    fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef {
+        let addresses = self
+            .into_iter()
+            .map(|inet| inet.map(|inet| inet.0))
+            .collect::<Vec<_>>();
+
+        let is_cidr = false;
+
+        Arc::new(network_address_struct_array(
+            addresses,
+            is_cidr,
+            context.field(),
+        ))
+    }
+}
+
+// Inet[]
+impl PgTypeToArrowArray<Inet> for Vec<Option<Vec<Option<Inet>>>> {
+    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let (offsets, nulls) = arrow_array_offsets(&self);
+
+        // gets rid of the first level of Option, then flattens the inner Vec<Option<Inet>>.
+        let addresses = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|inet| inet.map(|inet| inet.0))
+            .collect::<Vec<_>>();
+
+        let is_cidr = false;
+
+        let struct_array =
+            network_address_struct_array(addresses, is_cidr, element_context.field());
+
+        let list_array = ListArray::new(
+            element_context.field(),
+            offsets,
+            Arc::new(struct_array),
+            Some(nulls),
+        );
+
+        Arc::new(list_array)
+    }
+}
+
+// Cidr
+impl PgTypeToArrowArray<Cidr> for Vec<Option<Cidr>> {
+    fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef {
+        let addresses = self
+            .into_iter()
+            .map(|cidr| cidr.map(|cidr| cidr.0))
+            .collect::<Vec<_>>();
+
+        let is_cidr = true;
+
+        Arc::new(network_address_struct_array(
+            addresses,
+            is_cidr,
+            context.field(),
+        ))
+    }
+}
+
+// Cidr[]
+impl PgTypeToArrowArray<Cidr> for Vec<Option<Vec<Option<Cidr>>>> {
+    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let (offsets, nulls) = arrow_array_offsets(&self);
+
+        // gets rid of the first level of Option, then flattens the inner Vec<Option<Cidr>>.
+        let addresses = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|cidr| cidr.map(|cidr| cidr.0))
+            .collect::<Vec<_>>();
+
+        let is_cidr = true;
+
+        let struct_array =
+            network_address_struct_array(addresses, is_cidr, element_context.field());
+
+        let list_array = ListArray::new(
+            element_context.field(),
+            offsets,
+            Arc::new(struct_array),
+            Some(nulls),
+        );
+
+        Arc::new(list_array)
+    }
+}
+
+fn network_address_struct_array(
+    addresses: Vec<Option<NetworkAddress>>,
+    is_cidr: bool,
+    field: FieldRef,
+) -> StructArray {
+    let DataType::Struct(fields) = field.data_type() else {
+        panic!("inet field is not a struct");
+    };
+
+    let mut families = Vec::with_capacity(addresses.len());
+    let mut bits = Vec::with_capacity(addresses.len());
+    let mut addrs = Vec::with_capacity(addresses.len());
+    let mut validity = Vec::with_capacity(addresses.len());
+
+    for address in addresses {
+        // the struct fields are not nullable, so null addresses have empty fields
+        let is_valid = address.is_some();
+        let address = address.unwrap_or(NetworkAddress {
+            family: 0,
+            bits: 0,
+            addr: vec![],
+        });
+
+        families.push(address.family);
+        bits.push(address.bits);
+        addrs.push(address.addr);
+        validity.push(is_valid);
+    }
+
+    let is_cidrs = vec![is_cidr; families.len()];
+
+    StructArray::new(
+        fields.clone(),
+        vec![
+            Arc::new(UInt8Array::from(families)),
+            Arc::new(UInt8Array::from(bits)),
+            Arc::new(BooleanArray::from(is_cidrs)),
+            Arc::new(BinaryArray::from_iter_values(addrs)),
+        ],
+        Some(NullBuffer::from(validity)),
+    )
+}
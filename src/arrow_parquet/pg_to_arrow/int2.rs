@@ -1,16 +1,24 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Int16Array, ListArray};
+use arrow::array::{ArrayRef, Int16Array, Int16Builder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Int16
-impl PgTypeToArrowArray<i16> for Vec<Option<i16>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let int16_array = Int16Array::from(self);
-        Arc::new(int16_array)
+impl PgTypeToArrowBuilder for i16 {
+    type Builder = Int16Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Int16Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value);
     }
 }
 
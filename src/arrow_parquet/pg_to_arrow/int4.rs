@@ -1,16 +1,24 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Int32Array, ListArray};
+use arrow::array::{ArrayRef, Int32Array, Int32Builder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Int32
-impl PgTypeToArrowArray<i32> for Vec<Option<i32>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let int32_array = Int32Array::from(self);
-        Arc::new(int32_array)
+impl PgTypeToArrowBuilder for i32 {
+    type Builder = Int32Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Int32Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value);
     }
 }
 
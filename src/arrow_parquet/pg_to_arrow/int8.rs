@@ -1,16 +1,24 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Int64Array, ListArray};
+use arrow::array::{ArrayRef, Int64Array, Int64Builder, ListArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Int64
-impl PgTypeToArrowArray<i64> for Vec<Option<i64>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let int64_array = Int64Array::from(self);
-        Arc::new(int64_array)
+impl PgTypeToArrowBuilder for i64 {
+    type Builder = Int64Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        Int64Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value);
     }
 }
 
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, FixedSizeBinaryArray, ListArray};
+
+use crate::{
+    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    type_compat::network_address::{MacAddr, MacAddr8, MACADDR8_LENGTH, MACADDR_LENGTH},
+};
+
+use super::PgToArrowAttributeContext;
+
+// MacAddr
+impl PgTypeToArrowArray<MacAddr> for Vec<Option<MacAddr>> {
+    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
+        let macaddrs = self
+            .into_iter()
+            .map(|macaddr| macaddr.map(|macaddr| macaddr.0));
+
+        Arc::new(fixed_size_binary_array(macaddrs, MACADDR_LENGTH))
+    }
+}
+
+// MacAddr[]
+impl PgTypeToArrowArray<MacAddr> for Vec<Option<Vec<Option<MacAddr>>>> {
+    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let (offsets, nulls) = arrow_array_offsets(&self);
+
+        // gets rid of the first level of Option, then flattens the inner Vec<Option<MacAddr>>.
+        let macaddrs = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|macaddr| macaddr.map(|macaddr| macaddr.0));
+
+        let macaddr_array = fixed_size_binary_array(macaddrs, MACADDR_LENGTH);
+
+        let list_array = ListArray::new(
+            element_context.field(),
+            offsets,
+            Arc::new(macaddr_array),
+            Some(nulls),
+        );
+
+        Arc::new(list_array)
+    }
+}
+
+// MacAddr8
+impl PgTypeToArrowArray<MacAddr8> for Vec<Option<MacAddr8>> {
+    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
+        let macaddrs = self
+            .into_iter()
+            .map(|macaddr| macaddr.map(|macaddr| macaddr.0));
+
+        Arc::new(fixed_size_binary_array(macaddrs, MACADDR8_LENGTH))
+    }
+}
+
+// MacAddr8[]
+impl PgTypeToArrowArray<MacAddr8> for Vec<Option<Vec<Option<MacAddr8>>>> {
+    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let (offsets, nulls) = arrow_array_offsets(&self);
+
+        // gets rid of the first level of Option, then flattens the inner Vec<Option<MacAddr8>>.
+        let macaddrs = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|macaddr| macaddr.map(|macaddr| macaddr.0));
+
+        let macaddr_array = fixed_size_binary_array(macaddrs, MACADDR8_LENGTH);
+
+        let list_array = ListArray::new(
+            element_context.field(),
+            offsets,
+            Arc::new(macaddr_array),
+            Some(nulls),
+        );
+
+        Arc::new(list_array)
+    }
+}
+
+fn fixed_size_binary_array<const N: usize>(
+    values: impl Iterator<Item = Option<[u8; N]>>,
+    size: usize,
+) -> FixedSizeBinaryArray {
+    FixedSizeBinaryArray::try_from_sparse_iter_with_size(values, size as _)
+        .unwrap_or_else(|e| panic!("failed to create FixedSizeBinaryArray: {}", e))
+}
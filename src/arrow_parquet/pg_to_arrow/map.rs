@@ -10,7 +10,7 @@ use crate::{
     type_compat::map::Map,
 };
 
-use super::PgToArrowAttributeContext;
+use super::{conversion_progress::convert_nested_elements, PgToArrowAttributeContext};
 
 // crunchy_map.key_<type1>_val_<type2>
 impl<'b> PgTypeToArrowArray<Map<'b>> for Vec<Option<Map<'b>>> {
@@ -33,7 +33,7 @@ impl<'b> PgTypeToArrowArray<Map<'b>> for Vec<Option<Map<'b>>> {
 
         let entries = entries.into_iter().flatten().flatten().collect::<Vec<_>>();
 
-        let entries_array = entries.to_arrow_array(entries_context);
+        let entries_array = convert_nested_elements(|| entries.to_arrow_array(entries_context));
         let entries_array = entries_array.as_struct().to_owned();
 
         let map_array = MapArray::new(
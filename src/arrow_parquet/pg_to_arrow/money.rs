@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Decimal128Array, ListArray};
+
+use crate::{
+    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    type_compat::money::{Money, MONEY_PRECISION, MONEY_SCALE},
+};
+
+use super::PgToArrowAttributeContext;
+
+// Money
+impl PgTypeToArrowArray<Money> for Vec<Option<Money>> {
+    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
+        let moneys = self
+            .into_iter()
+            .map(|money| money.map(|money| money.0))
+            .collect::<Vec<_>>();
+
+        let money_array = Decimal128Array::from(moneys)
+            .with_precision_and_scale(MONEY_PRECISION, MONEY_SCALE)
+            .unwrap_or_else(|e| panic!("failed to create Decimal128Array: {}", e));
+
+        Arc::new(money_array)
+    }
+}
+
+// Money[]
+impl PgTypeToArrowArray<Money> for Vec<Option<Vec<Option<Money>>>> {
+    fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
+        let (offsets, nulls) = arrow_array_offsets(&self);
+
+        // gets rid of the first level of Option, then flattens the inner Vec<Option<Money>>.
+        let pg_array = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|money| money.map(|money| money.0))
+            .collect::<Vec<_>>();
+
+        let money_array = Decimal128Array::from(pg_array)
+            .with_precision_and_scale(MONEY_PRECISION, MONEY_SCALE)
+            .unwrap_or_else(|e| panic!("failed to create Decimal128Array: {}", e));
+
+        let list_array = ListArray::new(
+            element_context.field(),
+            offsets,
+            Arc::new(money_array),
+            Some(nulls),
+        );
+
+        Arc::new(list_array)
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, Decimal128Array, ListArray};
+use arrow::array::{ArrayRef, Decimal128Array, Decimal256Array, ListArray};
 use pgrx::AnyNumeric;
 
 use crate::{
     arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
-    type_compat::pg_arrow_type_conversions::numeric_to_i128,
+    type_compat::pg_arrow_type_conversions::{
+        numeric_to_i128, numeric_to_i256, should_write_numeric_as_decimal256,
+    },
 };
 
 use super::PgToArrowAttributeContext;
@@ -13,6 +15,10 @@ use super::PgToArrowAttributeContext;
 // Numeric
 impl PgTypeToArrowArray<AnyNumeric> for Vec<Option<AnyNumeric>> {
     fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef {
+        if should_write_numeric_as_decimal256(context.precision()) {
+            return Arc::new(numerics_to_decimal256_array(self, context));
+        }
+
         let numerics = self
             .into_iter()
             .map(|numeric| {
@@ -36,35 +42,55 @@ impl PgTypeToArrowArray<AnyNumeric> for Vec<Option<Vec<Option<AnyNumeric>>>> {
         let (offsets, nulls) = arrow_array_offsets(&self);
 
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
-        let pg_array = self
-            .into_iter()
-            .flatten()
-            .flatten()
-            .map(|numeric| {
-                numeric.map(|numeric| {
-                    numeric_to_i128(
-                        numeric,
-                        element_context.typmod(),
-                        element_context.field().name(),
-                    )
-                })
-            })
-            .collect::<Vec<_>>();
+        let pg_array = self.into_iter().flatten().flatten().collect::<Vec<_>>();
 
         let precision = element_context.precision();
         let scale = element_context.scale();
 
-        let numeric_array = Decimal128Array::from(pg_array)
-            .with_precision_and_scale(precision as _, scale as _)
-            .unwrap_or_else(|e| panic!("failed to create Decimal128Array: {}", e));
+        let numeric_array: ArrayRef = if should_write_numeric_as_decimal256(precision) {
+            Arc::new(numerics_to_decimal256_array(pg_array, element_context))
+        } else {
+            let pg_array = pg_array
+                .into_iter()
+                .map(|numeric| {
+                    numeric.map(|numeric| {
+                        numeric_to_i128(
+                            numeric,
+                            element_context.typmod(),
+                            element_context.field().name(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Arc::new(
+                Decimal128Array::from(pg_array)
+                    .with_precision_and_scale(precision as _, scale as _)
+                    .unwrap_or_else(|e| panic!("failed to create Decimal128Array: {}", e)),
+            )
+        };
 
-        let list_array = ListArray::new(
-            element_context.field(),
-            offsets,
-            Arc::new(numeric_array),
-            Some(nulls),
-        );
+        let list_array =
+            ListArray::new(element_context.field(), offsets, numeric_array, Some(nulls));
 
         Arc::new(list_array)
     }
 }
+
+// numerics_to_decimal256_array converts numerics with precision > 38 to Decimal256Array.
+fn numerics_to_decimal256_array(
+    numerics: Vec<Option<AnyNumeric>>,
+    context: &PgToArrowAttributeContext,
+) -> Decimal256Array {
+    let numerics = numerics
+        .into_iter()
+        .map(|numeric| {
+            numeric
+                .map(|numeric| numeric_to_i256(numeric, context.typmod(), context.field().name()))
+        })
+        .collect::<Vec<_>>();
+
+    Decimal256Array::from(numerics)
+        .with_precision_and_scale(context.precision() as _, context.scale() as _)
+        .unwrap_or_else(|e| panic!("failed to create Decimal256Array: {}", e))
+}
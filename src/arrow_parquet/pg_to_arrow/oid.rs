@@ -1,21 +1,25 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, ListArray, UInt32Array};
+use arrow::array::{ArrayRef, ListArray, UInt32Array, UInt32Builder};
 use pgrx::pg_sys::Oid;
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::arrow_parquet::{
+    arrow_utils::arrow_array_offsets,
+    pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+};
 
 use super::PgToArrowAttributeContext;
 
 // Oid
-impl PgTypeToArrowArray<Oid> for Vec<Option<Oid>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let oids = self
-            .into_iter()
-            .map(|oid| oid.map(|oid| oid.as_u32()))
-            .collect::<Vec<_>>();
-        let oid_array = UInt32Array::from(oids);
-        Arc::new(oid_array)
+impl PgTypeToArrowBuilder for Oid {
+    type Builder = UInt32Builder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        UInt32Builder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, _row_idx: usize) {
+        builder.append_option(value.map(|oid| oid.as_u32()));
     }
 }
 
@@ -2,27 +2,35 @@ use std::sync::Arc;
 
 use arrow::array::{ArrayRef, ListArray, StringArray};
 
-use crate::arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray};
+use crate::{
+    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    type_compat::server_encoding::ServerEncodedText,
+};
 
 use super::PgToArrowAttributeContext;
 
 // Text
-impl PgTypeToArrowArray<String> for Vec<Option<String>> {
+impl PgTypeToArrowArray<ServerEncodedText> for Vec<Option<ServerEncodedText>> {
     fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let text_array = StringArray::from(self);
+        let text_array =
+            StringArray::from_iter(self.into_iter().map(|text| text.map(|text| text.0)));
         Arc::new(text_array)
     }
 }
 
 // Text[]
-impl PgTypeToArrowArray<String> for Vec<Option<Vec<Option<String>>>> {
+impl PgTypeToArrowArray<ServerEncodedText> for Vec<Option<Vec<Option<ServerEncodedText>>>> {
     fn to_arrow_array(self, element_context: &PgToArrowAttributeContext) -> ArrayRef {
         let (offsets, nulls) = arrow_array_offsets(&self);
 
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
-        let pg_array = self.into_iter().flatten().flatten().collect::<Vec<_>>();
+        let pg_array = self
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|text| text.map(|text| text.0));
 
-        let text_array = StringArray::from(pg_array);
+        let text_array = StringArray::from_iter(pg_array);
 
         let list_array = ListArray::new(
             element_context.field(),
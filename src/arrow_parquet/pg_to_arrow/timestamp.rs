@@ -1,24 +1,29 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, ListArray, TimestampMicrosecondArray};
+use arrow::array::{ArrayRef, ListArray, TimestampMicrosecondArray, TimestampMicrosecondBuilder};
 use pgrx::datum::Timestamp;
 
 use crate::{
-    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    arrow_parquet::{
+        arrow_utils::arrow_array_offsets,
+        infinite_timestamps::infinite_timestamp_to_i64,
+        pg_to_arrow::{PgTypeToArrowArray, PgTypeToArrowBuilder},
+    },
     type_compat::pg_arrow_type_conversions::timestamp_to_i64,
 };
 
 use super::PgToArrowAttributeContext;
 
 // Timestamp
-impl PgTypeToArrowArray<Timestamp> for Vec<Option<Timestamp>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let timestamps = self
-            .into_iter()
-            .map(|timestamp| timestamp.map(timestamp_to_i64))
-            .collect::<Vec<_>>();
-        let timestamp_array = TimestampMicrosecondArray::from(timestamps);
-        Arc::new(timestamp_array)
+impl PgTypeToArrowBuilder for Timestamp {
+    type Builder = TimestampMicrosecondBuilder;
+
+    fn new_arrow_builder(capacity: usize) -> Self::Builder {
+        TimestampMicrosecondBuilder::with_capacity(capacity)
+    }
+
+    fn append_to_arrow_builder(builder: &mut Self::Builder, value: Option<Self>, row_idx: usize) {
+        builder.append_option(value.and_then(|timestamp| timestamp_value(timestamp, row_idx)));
     }
 }
 
@@ -30,9 +35,11 @@ impl PgTypeToArrowArray<Timestamp> for Vec<Option<Vec<Option<Timestamp>>>> {
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
         let pg_array = self
             .into_iter()
-            .flatten()
-            .flatten()
-            .map(|timestamp| timestamp.map(timestamp_to_i64))
+            .enumerate()
+            .flat_map(|(row_idx, array)| array.into_iter().flatten().map(move |e| (row_idx, e)))
+            .map(|(row_idx, timestamp)| {
+                timestamp.and_then(|timestamp| timestamp_value(timestamp, row_idx))
+            })
             .collect::<Vec<_>>();
 
         let timestamp_array = TimestampMicrosecondArray::from(pg_array);
@@ -47,3 +54,12 @@ impl PgTypeToArrowArray<Timestamp> for Vec<Option<Vec<Option<Timestamp>>>> {
         Arc::new(list_array)
     }
 }
+
+// timestamp_value returns the value of the timestamp at the row, which is written to parquet.
+fn timestamp_value(timestamp: Timestamp, row_idx: usize) -> Option<i64> {
+    if timestamp.is_infinity() || timestamp.is_neg_infinity() {
+        infinite_timestamp_to_i64(timestamp.is_infinity(), row_idx)
+    } else {
+        Some(timestamp_to_i64(timestamp))
+    }
+}
@@ -4,7 +4,10 @@ use arrow::array::{ArrayRef, ListArray, TimestampMicrosecondArray};
 use pgrx::datum::TimestampWithTimeZone;
 
 use crate::{
-    arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
+    arrow_parquet::{
+        arrow_utils::arrow_array_offsets, infinite_timestamps::infinite_timestamp_to_i64,
+        pg_to_arrow::PgTypeToArrowArray,
+    },
     type_compat::pg_arrow_type_conversions::timestamptz_to_i64,
 };
 
@@ -15,7 +18,10 @@ impl PgTypeToArrowArray<TimestampWithTimeZone> for Vec<Option<TimestampWithTimeZ
     fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
         let timestamptzs = self
             .into_iter()
-            .map(|timestamptz| timestamptz.map(timestamptz_to_i64))
+            .enumerate()
+            .map(|(row_idx, timestamptz)| {
+                timestamptz.and_then(|timestamptz| timestamptz_value(timestamptz, row_idx))
+            })
             .collect::<Vec<_>>();
         let timestamptz_array = TimestampMicrosecondArray::from(timestamptzs).with_timezone_utc();
         Arc::new(timestamptz_array)
@@ -30,9 +36,11 @@ impl PgTypeToArrowArray<TimestampWithTimeZone> for Vec<Option<Vec<Option<Timesta
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
         let pg_array = self
             .into_iter()
-            .flatten()
-            .flatten()
-            .map(|timestamptz| timestamptz.map(timestamptz_to_i64))
+            .enumerate()
+            .flat_map(|(row_idx, array)| array.into_iter().flatten().map(move |e| (row_idx, e)))
+            .map(|(row_idx, timestamptz)| {
+                timestamptz.and_then(|timestamptz| timestamptz_value(timestamptz, row_idx))
+            })
             .collect::<Vec<_>>();
 
         let timestamptz_array = TimestampMicrosecondArray::from(pg_array).with_timezone_utc();
@@ -47,3 +55,12 @@ impl PgTypeToArrowArray<TimestampWithTimeZone> for Vec<Option<Vec<Option<Timesta
         Arc::new(list_array)
     }
 }
+
+// timestamptz_value returns the value of the timestamptz at the row, which is written to parquet.
+fn timestamptz_value(timestamptz: TimestampWithTimeZone, row_idx: usize) -> Option<i64> {
+    if timestamptz.is_infinity() || timestamptz.is_neg_infinity() {
+        infinite_timestamp_to_i64(timestamptz.is_infinity(), row_idx)
+    } else {
+        Some(timestamptz_to_i64(timestamptz))
+    }
+}
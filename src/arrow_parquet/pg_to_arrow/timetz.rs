@@ -1,24 +1,23 @@
 use std::sync::Arc;
 
-use arrow::array::{ArrayRef, ListArray, Time64MicrosecondArray};
+use arrow::{
+    array::{ArrayRef, Int32Array, ListArray, StructArray, Time64MicrosecondArray},
+    buffer::NullBuffer,
+};
+use arrow_schema::{DataType, FieldRef};
 use pgrx::datum::TimeWithTimeZone;
 
 use crate::{
     arrow_parquet::{arrow_utils::arrow_array_offsets, pg_to_arrow::PgTypeToArrowArray},
-    type_compat::pg_arrow_type_conversions::timetz_to_i64,
+    type_compat::pg_arrow_type_conversions::timetz_to_time_and_offset,
 };
 
 use super::PgToArrowAttributeContext;
 
 // TimeTz
 impl PgTypeToArrowArray<TimeWithTimeZone> for Vec<Option<TimeWithTimeZone>> {
-    fn to_arrow_array(self, _context: &PgToArrowAttributeContext) -> ArrayRef {
-        let timetzs = self
-            .into_iter()
-            .map(|timetz| timetz.map(timetz_to_i64))
-            .collect::<Vec<_>>();
-        let timetz_array = Time64MicrosecondArray::from(timetzs);
-        Arc::new(timetz_array)
+    fn to_arrow_array(self, context: &PgToArrowAttributeContext) -> ArrayRef {
+        Arc::new(timetz_struct_array(self, context.field()))
     }
 }
 
@@ -28,22 +27,45 @@ impl PgTypeToArrowArray<TimeWithTimeZone> for Vec<Option<Vec<Option<TimeWithTime
         let (offsets, nulls) = arrow_array_offsets(&self);
 
         // gets rid of the first level of Option, then flattens the inner Vec<Option<bool>>.
-        let pg_array = self
-            .into_iter()
-            .flatten()
-            .flatten()
-            .map(|timetz| timetz.map(timetz_to_i64))
-            .collect::<Vec<_>>();
+        let pg_array = self.into_iter().flatten().flatten().collect::<Vec<_>>();
 
-        let timetz_array = Time64MicrosecondArray::from(pg_array);
+        let struct_array = timetz_struct_array(pg_array, element_context.field());
 
         let list_array = ListArray::new(
             element_context.field(),
             offsets,
-            Arc::new(timetz_array),
+            Arc::new(struct_array),
             Some(nulls),
         );
 
         Arc::new(list_array)
     }
 }
+
+fn timetz_struct_array(timetzs: Vec<Option<TimeWithTimeZone>>, field: FieldRef) -> StructArray {
+    let DataType::Struct(fields) = field.data_type() else {
+        panic!("timetz field is not a struct");
+    };
+
+    let mut times = Vec::with_capacity(timetzs.len());
+    let mut offsets = Vec::with_capacity(timetzs.len());
+    let mut validity = Vec::with_capacity(timetzs.len());
+
+    for timetz in timetzs {
+        // the struct fields are not nullable, so null timetzs have zero fields
+        let (time, offset) = timetz.map(timetz_to_time_and_offset).unwrap_or_default();
+
+        times.push(time);
+        offsets.push(offset);
+        validity.push(timetz.is_some());
+    }
+
+    StructArray::new(
+        fields.clone(),
+        vec![
+            Arc::new(Time64MicrosecondArray::from(times)),
+            Arc::new(Int32Array::from(offsets)),
+        ],
+        Some(NullBuffer::from(validity)),
+    )
+}
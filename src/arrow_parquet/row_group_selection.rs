@@ -0,0 +1,173 @@
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use url::Url;
+
+use super::uri_utils::redact_uri;
+
+// RESUME_EXACTLY_ONCE_NOTE explains when resuming COPY FROM loads each row exactly once.
+pub(crate) const RESUME_EXACTLY_ONCE_NOTE: &str = "Resuming loads each row exactly once only \
+    when each file is loaded into its own empty staging table.";
+
+// ResumeToken identifies the row group of a file, from which a later COPY FROM resumes
+// loading the file, e.g. "3:\"d41d8cd98f00b204\"". It contains the etag of the file,
+// so that the token is rejected when the file is changed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResumeToken {
+    row_group: usize,
+    e_tag: String,
+}
+
+impl FromStr for ResumeToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_token_error = || format!("invalid resume token: {}", s);
+
+        let (row_group, e_tag) = s.split_once(':').ok_or_else(invalid_token_error)?;
+
+        let row_group = row_group
+            .parse::<usize>()
+            .map_err(|_| invalid_token_error())?;
+
+        Ok(ResumeToken {
+            row_group,
+            e_tag: e_tag.to_string(),
+        })
+    }
+}
+
+impl Display for ResumeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.row_group, self.e_tag)
+    }
+}
+
+// RowGroupSelection selects the row groups of the file, which are loaded by COPY FROM.
+// COPY FROM loads whole row groups, from the row group of resume_from, and at most
+// max_row_groups of them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RowGroupSelection {
+    pub(crate) resume_from: Option<ResumeToken>,
+    pub(crate) max_row_groups: Option<usize>,
+}
+
+impl RowGroupSelection {
+    // is_chunked returns true when COPY FROM might load only a part of the file
+    pub(crate) fn is_chunked(&self) -> bool {
+        self.resume_from.is_some() || self.max_row_groups.is_some()
+    }
+
+    // select_row_groups returns the row groups to load from the file with the given etag,
+    // whose row groups have the given number of rows. It fails when the file is changed since
+    // the resume token is issued.
+    pub(crate) fn select_row_groups(
+        &self,
+        uri: &Url,
+        e_tag: Option<&str>,
+        row_group_num_rows: Vec<usize>,
+    ) -> SelectedRowGroups {
+        let e_tag = e_tag.unwrap_or_default().to_string();
+
+        let num_row_groups = row_group_num_rows.len();
+
+        let start = match &self.resume_from {
+            Some(resume_from) => {
+                if resume_from.e_tag != e_tag {
+                    panic!(
+                        "parquet file {} is changed since the resume token is issued",
+                        redact_uri(uri)
+                    );
+                }
+
+                if resume_from.row_group > num_row_groups {
+                    panic!(
+                        "resume token row group {} is out of range, parquet file {} has {} row groups",
+                        resume_from.row_group,
+                        redact_uri(uri),
+                        num_row_groups
+                    );
+                }
+
+                resume_from.row_group
+            }
+            None => 0,
+        };
+
+        let end = match self.max_row_groups {
+            Some(max_row_groups) => {
+                std::cmp::min(start.saturating_add(max_row_groups), num_row_groups)
+            }
+            None => num_row_groups,
+        };
+
+        SelectedRowGroups {
+            row_groups: start..end,
+            row_group_num_rows,
+            e_tag,
+        }
+    }
+}
+
+// SelectedRowGroups are the row groups of the file, which are loaded by COPY FROM.
+#[derive(Debug, Clone)]
+pub(crate) struct SelectedRowGroups {
+    row_groups: Range<usize>,
+    row_group_num_rows: Vec<usize>,
+    e_tag: String,
+}
+
+impl SelectedRowGroups {
+    pub(crate) fn row_groups(&self) -> Range<usize> {
+        self.row_groups.clone()
+    }
+
+    pub(crate) fn num_row_groups(&self) -> usize {
+        self.row_group_num_rows.len()
+    }
+
+    // rows_before_selection returns the number of rows in the file before the selected
+    // row groups.
+    pub(crate) fn rows_before_selection(&self) -> usize {
+        self.row_group_num_rows[..self.row_groups.start]
+            .iter()
+            .sum()
+    }
+
+    // row_group_of_row returns the row group of the row with the given row number in the file,
+    // which starts from 1.
+    pub(crate) fn row_group_of_row(&self, row_number: usize) -> usize {
+        let mut rows = 0;
+
+        for (row_group, num_rows) in self.row_group_num_rows.iter().enumerate() {
+            rows += num_rows;
+
+            if row_number <= rows {
+                return row_group;
+            }
+        }
+
+        self.row_group_num_rows.len().saturating_sub(1)
+    }
+
+    // next_resume_token returns the token to resume loading after the selected row groups,
+    // or None if the selected row groups are the last ones of the file.
+    pub(crate) fn next_resume_token(&self) -> Option<ResumeToken> {
+        if self.row_groups.end >= self.num_row_groups() {
+            return None;
+        }
+
+        Some(ResumeToken {
+            row_group: self.row_groups.end,
+            e_tag: self.e_tag.clone(),
+        })
+    }
+
+    // retry_resume_token returns the token to retry loading the selected row groups, e.g.
+    // after COPY FROM fails, which does not load any of them.
+    pub(crate) fn retry_resume_token(&self) -> ResumeToken {
+        ResumeToken {
+            row_group: self.row_groups.start,
+            e_tag: self.e_tag.clone(),
+        }
+    }
+}
@@ -1,33 +1,75 @@
-use std::{collections::HashMap, ops::Deref, sync::Arc};
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CStr},
+    ops::Deref,
+    sync::Arc,
+};
 
 use arrow::datatypes::{Field, Fields, Schema};
 use arrow_cast::can_cast_types;
 use arrow_schema::{DataType, FieldRef};
 use parquet::arrow::{arrow_to_parquet_schema, PARQUET_FIELD_ID_META_KEY};
 use pg_sys::{
-    can_coerce_type,
+    can_coerce_type, domain_check, format_type_be, get_array_type, get_type_category_preferred,
     CoercionContext::{self, COERCION_EXPLICIT},
-    FormData_pg_attribute, InvalidOid, Oid, BOOLOID, BYTEAOID, CHAROID, DATEOID, FLOAT4OID,
-    FLOAT8OID, INT2OID, INT4OID, INT8OID, NUMERICOID, OIDOID, TEXTOID, TIMEOID, TIMESTAMPOID,
-    TIMESTAMPTZOID, TIMETZOID,
+    CreateTupleDescCopy, Datum, FormData_pg_attribute, InvalidOid, Oid, BOOLOID, BPCHAROID,
+    BYTEAOID, CASHOID, CHAROID, CIDROID, DATEOID, FLOAT4OID, FLOAT8OID, INETOID, INT2OID, INT4OID,
+    INT8OID, JSONBOID, JSONOID, MACADDR8OID, MACADDROID, NUMERICOID, OIDOID, TEXTOID, TIMEOID,
+    TIMESTAMPOID, TIMESTAMPTZOID, TIMETZOID, TYPCATEGORY_ENUM, TYPCATEGORY_STRING, VARHDRSZ,
+};
+use pgrx::{
+    check_for_interrupts, function_name, pg_sys::panic::ErrorReport, prelude::*, PgLogLevel,
+    PgSqlErrorCode, PgTryBuilder, PgTupleDesc,
 };
-use pgrx::{check_for_interrupts, prelude::*, PgTupleDesc};
 
 use crate::{
     pgrx_utils::{
-        array_element_typoid, collect_attributes_for, domain_array_base_elem_type, is_array_type,
-        is_composite_type, is_generated_attribute, tuple_desc, CollectAttributesFor,
+        array_element_typoid, collect_attributes_for, domain_array_base_elem_type,
+        domain_base_type, is_array_type, is_composite_type, is_enum_type, is_generated_attribute,
+        tuple_desc, CollectAttributesFor,
     },
     type_compat::{
-        geometry::is_postgis_geometry_type,
+        geometry::{
+            geoarrow_wkb_extension_metadata, is_postgis_geometry_type, GEOARROW_WKB_EXTENSION_NAME,
+        },
         map::is_map_type,
+        money::{MONEY_PRECISION, MONEY_SCALE},
+        network_address::{
+            is_network_address_type, INET_ADDR_FIELD_NAME, INET_BITS_FIELD_NAME,
+            INET_FAMILY_FIELD_NAME, INET_IS_CIDR_FIELD_NAME, MACADDR8_LENGTH, MACADDR_LENGTH,
+        },
         pg_arrow_type_conversions::{
-            extract_precision_and_scale_from_numeric_typmod, should_write_numeric_as_text,
+            extract_precision_and_scale_from_numeric_typmod, should_write_numeric_as_decimal256,
+            should_write_numeric_as_text, TIMETZ_OFFSET_FIELD_NAME, TIMETZ_TIME_FIELD_NAME,
         },
     },
 };
 
-use super::match_by::MatchBy;
+use super::{
+    field_ids::{attribute_field_id, with_field_id},
+    list_coercion::{innermost_list_element_type, is_list_type, is_scalar_type, ListCoercion},
+    match_by::MatchBy,
+    missing_columns::MissingColumns,
+};
+
+// json and jsonb columns are written as Utf8 fields marked with the canonical "arrow.json"
+// extension type. The extension type is persisted in the arrow schema of the parquet file,
+// and arrow aware readers recognize the column as JSON. The parquet column itself keeps the
+// STRING logical type, since the arrow writer of parquet 53 does not write the JSON logical
+// type for any arrow field, and it cannot be given a parquet schema of our own.
+pub(crate) const ARROW_EXTENSION_NAME_META_KEY: &str = "ARROW:extension:name";
+pub(crate) const ARROW_EXTENSION_METADATA_META_KEY: &str = "ARROW:extension:metadata";
+pub(crate) const ARROW_JSON_EXTENSION_NAME: &str = "arrow.json";
+
+// char(n) columns are written as Utf8 fields without their trailing blanks. The declared length
+// of the column is kept in the field metadata, since Utf8 does not have a length.
+pub(crate) const PG_BPCHAR_LENGTH_META_KEY: &str = "pg_parquet:bpchar_length";
+
+// timetz columns are written as structs of the local time and the offset of its zone, since
+// parquet's TIME is either local or adjusted to UTC, and cannot keep the offset. The field is
+// marked as timetz in its metadata.
+pub(crate) const PG_TYPE_META_KEY: &str = "pg_parquet:type";
+pub(crate) const PG_TIMETZ_TYPE_NAME: &str = "timetz";
 
 pub(crate) fn parquet_schema_string_from_attributes(
     attributes: &[FormData_pg_attribute],
@@ -42,11 +84,35 @@ pub(crate) fn parquet_schema_string_from_attributes(
 }
 
 pub(crate) fn parse_arrow_schema_from_attributes(attributes: &[FormData_pg_attribute]) -> Schema {
-    let mut field_id = 0;
+    parse_arrow_schema_with_field_ids(attributes, &HashMap::new())
+}
+
+// parse_arrow_schema_with_field_ids returns the schema of the attributes, whose columns have the
+// given field ids, or the field ids of their attributes. The fields nested in the columns get
+// the ids after the largest column id, so that the column ids do not depend on the nested fields.
+pub(crate) fn parse_arrow_schema_with_field_ids(
+    attributes: &[FormData_pg_attribute],
+    field_ids: &HashMap<String, i32>,
+) -> Schema {
+    let column_field_ids = attributes
+        .iter()
+        .map(|attribute| {
+            field_ids
+                .get(attribute.name())
+                .copied()
+                .unwrap_or_else(|| attribute_field_id(attribute))
+        })
+        .collect::<Vec<_>>();
+
+    let mut field_id = column_field_ids
+        .iter()
+        .max()
+        .map(|max_field_id| max_field_id + 1)
+        .unwrap_or(0);
 
     let mut struct_attribute_fields = vec![];
 
-    for attribute in attributes {
+    for (attribute, column_field_id) in attributes.iter().zip(column_field_ids) {
         let attribute_name = attribute.name();
         let attribute_typoid = attribute.type_oid().value();
         let attribute_typmod = attribute.type_mod();
@@ -79,22 +145,22 @@ pub(crate) fn parse_arrow_schema_from_attributes(attributes: &[FormData_pg_attri
             )
         };
 
-        struct_attribute_fields.push(field);
+        struct_attribute_fields.push(with_field_id(field, column_field_id));
     }
 
     Schema::new(Fields::from(struct_attribute_fields))
 }
 
+// next_field_id returns the field id of the next nested field.
+fn next_field_id(field_id: &mut i32) -> i32 {
+    let next_field_id = *field_id;
+    *field_id += 1;
+    next_field_id
+}
+
 fn parse_struct_schema(tupledesc: PgTupleDesc, elem_name: &str, field_id: &mut i32) -> Arc<Field> {
     check_for_interrupts!();
 
-    let metadata = HashMap::from_iter(vec![(
-        PARQUET_FIELD_ID_META_KEY.into(),
-        field_id.to_string(),
-    )]);
-
-    *field_id += 1;
-
     let mut child_fields: Vec<Arc<Field>> = vec![];
 
     let attributes = collect_attributes_for(CollectAttributesFor::Other, &tupledesc);
@@ -108,6 +174,8 @@ fn parse_struct_schema(tupledesc: PgTupleDesc, elem_name: &str, field_id: &mut i
         let attribute_oid = attribute.type_oid().value();
         let attribute_typmod = attribute.type_mod();
 
+        let child_field_id = next_field_id(field_id);
+
         let child_field = if is_composite_type(attribute_oid) {
             let attribute_tupledesc = tuple_desc(attribute_oid, attribute_typmod);
             parse_struct_schema(attribute_tupledesc, attribute_name, field_id)
@@ -126,7 +194,7 @@ fn parse_struct_schema(tupledesc: PgTupleDesc, elem_name: &str, field_id: &mut i
             parse_primitive_schema(attribute_oid, attribute_typmod, attribute_name, field_id)
         };
 
-        child_fields.push(child_field);
+        child_fields.push(with_field_id(child_field, child_field_id));
     }
 
     let nullable = true;
@@ -136,22 +204,16 @@ fn parse_struct_schema(tupledesc: PgTupleDesc, elem_name: &str, field_id: &mut i
         arrow::datatypes::DataType::Struct(Fields::from(child_fields)),
         nullable,
     )
-    .with_metadata(metadata)
     .into()
 }
 
 fn parse_list_schema(typoid: Oid, typmod: i32, array_name: &str, field_id: &mut i32) -> Arc<Field> {
     check_for_interrupts!();
 
-    let list_metadata = HashMap::from_iter(vec![(
-        PARQUET_FIELD_ID_META_KEY.into(),
-        field_id.to_string(),
-    )]);
-
-    *field_id += 1;
-
     let element_name = "element";
 
+    let elem_field_id = next_field_id(field_id);
+
     let elem_field = if is_composite_type(typoid) {
         let tupledesc = tuple_desc(typoid, typmod);
         parse_struct_schema(tupledesc, element_name, field_id)
@@ -162,6 +224,8 @@ fn parse_list_schema(typoid: Oid, typmod: i32, array_name: &str, field_id: &mut
         parse_primitive_schema(typoid, typmod, element_name, field_id)
     };
 
+    let elem_field = with_field_id(elem_field, elem_field_id);
+
     let nullable = true;
 
     Field::new(
@@ -169,21 +233,65 @@ fn parse_list_schema(typoid: Oid, typmod: i32, array_name: &str, field_id: &mut
         arrow::datatypes::DataType::List(elem_field),
         nullable,
     )
-    .with_metadata(list_metadata)
     .into()
 }
 
-fn parse_map_schema(typoid: Oid, typmod: i32, map_name: &str, field_id: &mut i32) -> Arc<Field> {
-    let map_metadata = HashMap::from_iter(vec![(
-        PARQUET_FIELD_ID_META_KEY.into(),
-        field_id.to_string(),
-    )]);
+// multidim_list_element_field returns the element field of the nested lists, into which the
+// arrays with the given number of dimensions are written, e.g. List<T> for the 2-dimensional
+// arrays, which are written as List<List<T>>.
+pub(crate) fn multidim_list_element_field(element_field: FieldRef, ndims: usize) -> FieldRef {
+    let nullable = true;
 
-    *field_id += 1;
+    (1..ndims).fold(element_field, |field, _| {
+        Field::new(field.name(), DataType::List(field.clone()), nullable).into()
+    })
+}
 
+// nest_multidim_array_fields returns the schema, whose array columns with multidimensional
+// arrays are written as nested lists with their number of dimensions.
+pub(crate) fn nest_multidim_array_fields(
+    schema: Schema,
+    array_ndims: &HashMap<String, usize>,
+) -> Schema {
+    let fields = schema
+        .fields
+        .iter()
+        .map(
+            |field| match (field.data_type(), array_ndims.get(field.name())) {
+                (DataType::List(element_field), Some(ndims)) => {
+                    let element_field = multidim_list_element_field(element_field.clone(), *ndims);
+
+                    Arc::new(
+                        field
+                            .as_ref()
+                            .clone()
+                            .with_data_type(DataType::List(element_field)),
+                    )
+                }
+                _ => field.clone(),
+            },
+        )
+        .collect::<Vec<_>>();
+
+    Schema::new_with_metadata(Fields::from(fields), schema.metadata)
+}
+
+// is_multidim_list returns true if the list of the file is nested deeper than the list of
+// the array, i.e. the nested lists are read into a multidimensional array.
+fn is_multidim_list(from_element_type: &DataType, to_element_type: &DataType) -> bool {
+    matches!(
+        from_element_type,
+        DataType::List(_) | DataType::LargeList(_)
+    ) && !matches!(to_element_type, DataType::List(_) | DataType::LargeList(_))
+}
+
+fn parse_map_schema(typoid: Oid, typmod: i32, map_name: &str, field_id: &mut i32) -> Arc<Field> {
     let tupledesc = tuple_desc(typoid, typmod);
 
+    let entries_field_id = next_field_id(field_id);
+
     let entries_field = parse_struct_schema(tupledesc, map_name, field_id);
+    let entries_field = with_field_id(entries_field, entries_field_id);
     let entries_field = adjust_map_entries_field(entries_field);
 
     let keys_sorted = false;
@@ -195,7 +303,6 @@ fn parse_map_schema(typoid: Oid, typmod: i32, map_name: &str, field_id: &mut i32
         arrow::datatypes::DataType::Map(entries_field, keys_sorted),
         nullable,
     )
-    .with_metadata(map_metadata)
     .into()
 }
 
@@ -207,13 +314,6 @@ fn parse_primitive_schema(
 ) -> Arc<Field> {
     check_for_interrupts!();
 
-    let primitive_metadata = HashMap::<String, String>::from_iter(vec![(
-        PARQUET_FIELD_ID_META_KEY.into(),
-        field_id.to_string(),
-    )]);
-
-    *field_id += 1;
-
     let nullable = true;
 
     let field = match typoid {
@@ -228,6 +328,12 @@ fn parse_primitive_schema(
 
             if should_write_numeric_as_text(precision) {
                 Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable)
+            } else if should_write_numeric_as_decimal256(precision) {
+                Field::new(
+                    elem_name,
+                    arrow::datatypes::DataType::Decimal256(precision as _, scale as _),
+                    nullable,
+                )
             } else {
                 Field::new(
                     elem_name,
@@ -236,6 +342,11 @@ fn parse_primitive_schema(
                 )
             }
         }
+        CASHOID => Field::new(
+            elem_name,
+            arrow::datatypes::DataType::Decimal128(MONEY_PRECISION, MONEY_SCALE),
+            nullable,
+        ),
         DATEOID => Field::new(elem_name, arrow::datatypes::DataType::Date32, nullable),
         TIMESTAMPOID => Field::new(
             elem_name,
@@ -257,35 +368,176 @@ fn parse_primitive_schema(
         ),
         TIMETZOID => Field::new(
             elem_name,
-            arrow::datatypes::DataType::Time64(arrow::datatypes::TimeUnit::Microsecond),
+            arrow::datatypes::DataType::Struct(timetz_struct_fields(field_id)),
             nullable,
         )
         .with_metadata(HashMap::from_iter(vec![(
-            "adjusted_to_utc".into(),
-            "true".into(),
+            PG_TYPE_META_KEY.into(),
+            PG_TIMETZ_TYPE_NAME.into(),
         )])),
         CHAROID => Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable),
         TEXTOID => Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable),
+        BPCHAROID => {
+            let field = Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable);
+
+            // typmod is -1 for bpchar without a length
+            if typmod >= VARHDRSZ as i32 {
+                field.with_metadata(HashMap::from_iter(vec![(
+                    PG_BPCHAR_LENGTH_META_KEY.into(),
+                    (typmod - VARHDRSZ as i32).to_string(),
+                )]))
+            } else {
+                field
+            }
+        }
         BYTEAOID => Field::new(elem_name, arrow::datatypes::DataType::Binary, nullable),
+        JSONOID | JSONBOID => Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable)
+            .with_metadata(HashMap::from_iter(vec![(
+                ARROW_EXTENSION_NAME_META_KEY.into(),
+                ARROW_JSON_EXTENSION_NAME.into(),
+            )])),
         OIDOID => Field::new(elem_name, arrow::datatypes::DataType::UInt32, nullable),
+        INETOID | CIDROID => Field::new(
+            elem_name,
+            arrow::datatypes::DataType::Struct(inet_struct_fields(field_id)),
+            nullable,
+        ),
+        MACADDROID => Field::new(
+            elem_name,
+            arrow::datatypes::DataType::FixedSizeBinary(MACADDR_LENGTH as _),
+            nullable,
+        ),
+        MACADDR8OID => Field::new(
+            elem_name,
+            arrow::datatypes::DataType::FixedSizeBinary(MACADDR8_LENGTH as _),
+            nullable,
+        ),
         _ => {
             if is_postgis_geometry_type(typoid) {
-                Field::new(elem_name, arrow::datatypes::DataType::Binary, nullable)
+                Field::new(elem_name, arrow::datatypes::DataType::Binary, nullable).with_metadata(
+                    HashMap::from_iter(vec![
+                        (
+                            ARROW_EXTENSION_NAME_META_KEY.into(),
+                            GEOARROW_WKB_EXTENSION_NAME.into(),
+                        ),
+                        (
+                            ARROW_EXTENSION_METADATA_META_KEY.into(),
+                            geoarrow_wkb_extension_metadata(typmod),
+                        ),
+                    ]),
+                )
+            } else if is_enum_type(typoid) {
+                // enums have a few distinct labels, which are written once per dictionary
+                Field::new(
+                    elem_name,
+                    arrow::datatypes::DataType::Dictionary(
+                        Box::new(arrow::datatypes::DataType::Int32),
+                        Box::new(arrow::datatypes::DataType::Utf8),
+                    ),
+                    nullable,
+                )
             } else {
                 Field::new(elem_name, arrow::datatypes::DataType::Utf8, nullable)
             }
         }
     };
 
-    // Combine the field metadata with the field metadata from the schema visitor
-    let primitive_metadata = field
-        .metadata()
-        .iter()
-        .chain(primitive_metadata.iter())
-        .map(|(k, v)| (k.clone(), v.clone()))
-        .collect();
+    field.into()
+}
 
-    field.with_metadata(primitive_metadata).into()
+// inet_struct_fields returns the fields of the struct, which inet and cidr are written as
+fn inet_struct_fields(field_id: &mut i32) -> Fields {
+    let fields = [
+        (INET_FAMILY_FIELD_NAME, DataType::UInt8),
+        (INET_BITS_FIELD_NAME, DataType::UInt8),
+        (INET_IS_CIDR_FIELD_NAME, DataType::Boolean),
+        (INET_ADDR_FIELD_NAME, DataType::Binary),
+    ];
+
+    let nullable = false;
+
+    fields
+        .into_iter()
+        .map(|(name, data_type)| {
+            let metadata = HashMap::from_iter(vec![(
+                PARQUET_FIELD_ID_META_KEY.into(),
+                field_id.to_string(),
+            )]);
+
+            *field_id += 1;
+
+            Field::new(name, data_type, nullable).with_metadata(metadata)
+        })
+        .collect()
+}
+
+// timetz_struct_fields returns the fields of the struct, which timetz is written as. The time
+// is the local time in the zone, hence it is not adjusted to UTC.
+fn timetz_struct_fields(field_id: &mut i32) -> Fields {
+    let fields = [
+        (
+            TIMETZ_TIME_FIELD_NAME,
+            DataType::Time64(arrow::datatypes::TimeUnit::Microsecond),
+        ),
+        (TIMETZ_OFFSET_FIELD_NAME, DataType::Int32),
+    ];
+
+    let nullable = false;
+
+    fields
+        .into_iter()
+        .map(|(name, data_type)| {
+            let metadata = HashMap::from_iter(vec![(
+                PARQUET_FIELD_ID_META_KEY.into(),
+                field_id.to_string(),
+            )]);
+
+            *field_id += 1;
+
+            Field::new(name, data_type, nullable).with_metadata(metadata)
+        })
+        .collect()
+}
+
+// is_timetz_struct_type returns true if the struct has the fields, which timetz is written as.
+fn is_timetz_struct_type(data_type: &DataType) -> bool {
+    let DataType::Struct(fields) = data_type else {
+        return false;
+    };
+
+    let field_type = |name| {
+        fields
+            .find(name)
+            .map(|(_, field)| field.data_type().clone())
+    };
+
+    fields.len() == 2
+        && matches!(
+            field_type(TIMETZ_TIME_FIELD_NAME),
+            Some(DataType::Time64(_))
+        )
+        && field_type(TIMETZ_OFFSET_FIELD_NAME) == Some(DataType::Int32)
+}
+
+// decode_dictionary_fields returns the fields with the value types of their dictionary types.
+// COPY TO writes enums as dictionaries, but COPY FROM reads them as text, like the other
+// dictionary encoded string columns.
+pub(crate) fn decode_dictionary_fields(fields: &Fields) -> Fields {
+    fields.iter().map(decode_dictionary_field).collect()
+}
+
+fn decode_dictionary_field(field: &FieldRef) -> FieldRef {
+    let data_type = match field.data_type() {
+        DataType::Dictionary(_, value_type) => value_type.as_ref().clone(),
+        DataType::Struct(fields) => DataType::Struct(decode_dictionary_fields(fields)),
+        DataType::List(element_field) => DataType::List(decode_dictionary_field(element_field)),
+        DataType::Map(entries_field, sorted) => {
+            DataType::Map(decode_dictionary_field(entries_field), *sorted)
+        }
+        data_type => data_type.clone(),
+    };
+
+    Arc::new(field.as_ref().clone().with_data_type(data_type))
 }
 
 fn adjust_map_entries_field(field: FieldRef) -> FieldRef {
@@ -339,71 +591,561 @@ fn adjust_map_entries_field(field: FieldRef) -> FieldRef {
     Arc::new(entries_field)
 }
 
-pub(crate) fn error_if_copy_from_match_by_position_with_generated_columns(
+// generated_column_file_projection returns the file columns to match with the table columns
+// by position, when the table has generated columns, which are never loaded from the file.
+// A file, which is written by COPY TO from the table, has the generated columns at their
+// positions, hence these file columns are skipped when the file has a column for each table
+// column. Otherwise, the file is expected to have only the non-generated columns. With
+// match_by 'name', the file columns of generated columns are not matched at all.
+pub(crate) fn generated_column_file_projection(
     tupledesc: &PgTupleDesc,
     match_by: MatchBy,
+    file_column_count: usize,
+) -> Option<Vec<usize>> {
+    if match_by != MatchBy::Position {
+        return None;
+    }
+
+    let attributes = collect_attributes_for(CollectAttributesFor::Other, tupledesc);
+
+    if !attributes.iter().any(is_generated_attribute) || file_column_count < attributes.len() {
+        return None;
+    }
+
+    let projection = (0..file_column_count)
+        .filter(|column_idx| {
+            attributes
+                .get(*column_idx)
+                .is_none_or(|attribute| !is_generated_attribute(attribute))
+        })
+        .collect();
+
+    Some(projection)
+}
+
+// prune_extra_file_fields returns the file schema without the extra file columns and struct
+// subfields, which do not have a table column or a composite subfield, and the paths of the
+// extra fields, e.g. "a" or "b.c". With match_by 'position', the trailing file fields are
+// extra. With match_by 'name', the file fields with an unknown name are extra. A struct is not
+// pruned if none of its subfields are found, since it cannot be read without subfields.
+pub(crate) fn prune_extra_file_fields(
+    file_schema: &Schema,
+    tupledesc_schema: &Schema,
+    match_by: MatchBy,
+) -> (Schema, Vec<String>) {
+    let mut extra_field_paths = vec![];
+
+    let fields = prune_extra_subfields(
+        file_schema.fields(),
+        tupledesc_schema.fields(),
+        "",
+        match_by,
+        &mut extra_field_paths,
+    )
+    .unwrap_or_else(|| file_schema.fields().clone());
+
+    let schema = Schema::new_with_metadata(fields, file_schema.metadata().clone());
+
+    (schema, extra_field_paths)
+}
+
+fn prune_extra_subfields(
+    from_fields: &Fields,
+    to_fields: &Fields,
+    parent_path: &str,
+    match_by: MatchBy,
+    extra_field_paths: &mut Vec<String>,
+) -> Option<Fields> {
+    let mut pruned_fields = vec![];
+    let mut pruned_field_paths = vec![];
+
+    for (field_idx, from_field) in from_fields.iter().enumerate() {
+        let field_path = if parent_path.is_empty() {
+            from_field.name().to_string()
+        } else {
+            format!("{}.{}", parent_path, from_field.name())
+        };
+
+        let to_field = match match_by {
+            MatchBy::Position => to_fields.get(field_idx),
+            MatchBy::Name | MatchBy::FieldId => to_fields
+                .find(from_field.name())
+                .map(|(_, to_field)| to_field),
+        };
+
+        let Some(to_field) = to_field else {
+            pruned_field_paths.push(field_path);
+            continue;
+        };
+
+        let data_type = prune_extra_subfields_of_type(
+            from_field.data_type(),
+            to_field.data_type(),
+            &field_path,
+            match_by,
+            extra_field_paths,
+        );
+
+        pruned_fields.push(Arc::new(
+            from_field.as_ref().clone().with_data_type(data_type),
+        ));
+    }
+
+    if pruned_fields.is_empty() {
+        return None;
+    }
+
+    extra_field_paths.extend(pruned_field_paths);
+
+    Some(Fields::from(pruned_fields))
+}
+
+fn prune_extra_subfields_of_type(
+    from_type: &DataType,
+    to_type: &DataType,
+    field_path: &str,
+    match_by: MatchBy,
+    extra_field_paths: &mut Vec<String>,
+) -> DataType {
+    match (from_type, to_type) {
+        (DataType::Struct(from_fields), DataType::Struct(to_fields)) => {
+            match prune_extra_subfields(
+                from_fields,
+                to_fields,
+                field_path,
+                match_by,
+                extra_field_paths,
+            ) {
+                Some(fields) => DataType::Struct(fields),
+                None => from_type.clone(),
+            }
+        }
+        (DataType::List(from_field), DataType::List(to_field))
+        | (DataType::LargeList(from_field), DataType::List(to_field)) => {
+            let element_type = prune_extra_subfields_of_type(
+                from_field.data_type(),
+                to_field.data_type(),
+                &format!("{}.{}", field_path, from_field.name()),
+                match_by,
+                extra_field_paths,
+            );
+
+            let element_field = Arc::new(from_field.as_ref().clone().with_data_type(element_type));
+
+            match from_type {
+                DataType::List(_) => DataType::List(element_field),
+                _ => DataType::LargeList(element_field),
+            }
+        }
+        (DataType::Map(from_entries_field, sorted), DataType::Map(to_entries_field, _)) => {
+            // keys and values are matched by position, since their names differ between writers
+            let (DataType::Struct(from_entries), DataType::Struct(to_entries)) =
+                (from_entries_field.data_type(), to_entries_field.data_type())
+            else {
+                return from_type.clone();
+            };
+
+            let entries = from_entries
+                .iter()
+                .zip(to_entries.iter())
+                .map(|(from_entry, to_entry)| {
+                    let entry_type = prune_extra_subfields_of_type(
+                        from_entry.data_type(),
+                        to_entry.data_type(),
+                        field_path,
+                        match_by,
+                        extra_field_paths,
+                    );
+
+                    Arc::new(from_entry.as_ref().clone().with_data_type(entry_type))
+                })
+                .collect::<Vec<_>>();
+
+            let entries_field = from_entries_field
+                .as_ref()
+                .clone()
+                .with_data_type(DataType::Struct(Fields::from(entries)));
+
+            DataType::Map(Arc::new(entries_field), *sorted)
+        }
+        _ => from_type.clone(),
+    }
+}
+
+// ensure_no_extra_file_fields throws an error, listing the extra file columns and struct
+// subfields, if the file has any of them. It is called with extra_columns 'error'.
+pub(crate) fn ensure_no_extra_file_fields(
+    file_schema: &Schema,
+    tupledesc_schema: &Schema,
+    extra_field_paths: &[String],
+    match_by: MatchBy,
 ) {
-    // match_by 'name' can handle generated columns
-    if let MatchBy::Name = match_by {
+    if extra_field_paths.is_empty() {
         return;
     }
 
-    let attributes = collect_attributes_for(CollectAttributesFor::Other, tupledesc);
+    let extra_field_names = extra_field_paths
+        .iter()
+        .map(|path| format!("\"{}\"", path))
+        .collect::<Vec<_>>()
+        .join(", ");
 
-    for attribute in attributes {
-        if is_generated_attribute(&attribute) {
-            ereport!(
-                PgLogLevel::ERROR,
-                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
-                "COPY FROM parquet with generated columns is not supported",
-                "Try COPY FROM parquet WITH (match_by 'name'). \"
-                 It works only if the column names match with parquet file's.",
+    let file_column_count = file_schema.fields().len();
+    let table_column_count = tupledesc_schema.fields().len();
+
+    if match_by == MatchBy::Position && file_column_count > table_column_count {
+        panic!(
+            "column count mismatch between table and parquet file. \
+             parquet file has {} columns, but table has {} columns\n\n\
+             extra columns in parquet file are {}",
+            file_column_count, table_column_count, extra_field_names
+        );
+    }
+
+    panic!(
+        "parquet file has columns, which are not found in the table: {}",
+        extra_field_names
+    );
+}
+
+// file_projection_leaves returns the indices of the parquet leaf columns of the file, which
+// are read for the projected file schema. The projected schema keeps the order of the file's
+// columns and subfields.
+pub(crate) fn file_projection_leaves(
+    file_schema: &Schema,
+    projected_schema: &Schema,
+) -> Vec<usize> {
+    let mut leaves = vec![];
+
+    collect_projected_leaves(
+        file_schema.fields(),
+        projected_schema.fields(),
+        0,
+        &mut leaves,
+    );
+
+    leaves
+}
+
+fn collect_projected_leaves(
+    file_fields: &Fields,
+    projected_fields: &Fields,
+    first_leaf: usize,
+    leaves: &mut Vec<usize>,
+) {
+    let mut leaf = first_leaf;
+    let mut projected_fields = projected_fields.iter().peekable();
+
+    for file_field in file_fields.iter() {
+        let leaf_count = arrow_type_leaf_count(file_field.data_type());
+
+        if let Some(projected_field) =
+            projected_fields.next_if(|projected_field| projected_field.name() == file_field.name())
+        {
+            collect_projected_type_leaves(
+                file_field.data_type(),
+                projected_field.data_type(),
+                leaf,
+                leaves,
             );
         }
+
+        leaf += leaf_count;
+    }
+}
+
+fn collect_projected_type_leaves(
+    file_type: &DataType,
+    projected_type: &DataType,
+    first_leaf: usize,
+    leaves: &mut Vec<usize>,
+) {
+    match (file_type, projected_type) {
+        _ if file_type == projected_type => {
+            leaves.extend(first_leaf..first_leaf + arrow_type_leaf_count(file_type));
+        }
+        (DataType::Struct(file_fields), DataType::Struct(projected_fields)) => {
+            collect_projected_leaves(file_fields, projected_fields, first_leaf, leaves);
+        }
+        (DataType::List(file_field), DataType::List(projected_field))
+        | (DataType::LargeList(file_field), DataType::LargeList(projected_field))
+        | (DataType::Map(file_field, _), DataType::Map(projected_field, _)) => {
+            collect_projected_type_leaves(
+                file_field.data_type(),
+                projected_field.data_type(),
+                first_leaf,
+                leaves,
+            );
+        }
+        _ => panic!("{} is not a projection of {}", projected_type, file_type),
+    }
+}
+
+// arrow_type_leaf_count returns the number of parquet leaf columns, which the arrow type
+// of the file is read from.
+fn arrow_type_leaf_count(data_type: &DataType) -> usize {
+    match data_type {
+        DataType::Struct(fields) => fields
+            .iter()
+            .map(|field| arrow_type_leaf_count(field.data_type()))
+            .sum(),
+        DataType::List(field)
+        | DataType::LargeList(field)
+        | DataType::FixedSizeList(field, _)
+        | DataType::Map(field, _) => arrow_type_leaf_count(field.data_type()),
+        _ => 1,
+    }
+}
+
+// is_fallback_to_text_type returns true if the type does not have a parquet counterpart.
+// Its values are written as text via the type's output function.
+fn is_fallback_to_text_type(typoid: Oid) -> bool {
+    let has_parquet_counterpart = matches!(
+        typoid,
+        FLOAT4OID
+            | FLOAT8OID
+            | BOOLOID
+            | INT2OID
+            | INT4OID
+            | INT8OID
+            | NUMERICOID
+            | CASHOID
+            | DATEOID
+            | TIMESTAMPOID
+            | TIMESTAMPTZOID
+            | TIMEOID
+            | TIMETZOID
+            | CHAROID
+            | TEXTOID
+            | BYTEAOID
+            | JSONOID
+            | JSONBOID
+            | OIDOID
+            | INETOID
+            | CIDROID
+            | MACADDROID
+            | MACADDR8OID
+    );
+
+    !has_parquet_counterpart && !is_postgis_geometry_type(typoid)
+}
+
+// is_text_like_type returns true for string types, e.g. varchar, and enums, whose natural
+// representation is text.
+fn is_text_like_type(typoid: Oid) -> bool {
+    let mut category: c_char = 0;
+    let mut is_preferred = false;
+
+    unsafe { get_type_category_preferred(typoid, &mut category, &mut is_preferred) };
+
+    category as u8 == TYPCATEGORY_STRING as u8 || category as u8 == TYPCATEGORY_ENUM as u8
+}
+
+// unwrap_domain_attributes returns a copy of the tuple descriptor, in which the attributes of
+// domains have the type, as which their values are converted (see unwrapped_domain_type).
+// A domain has the same datum representation as its base type.
+pub(crate) fn unwrap_domain_attributes(
+    tupledesc: &PgTupleDesc,
+    copy_operation: CollectAttributesFor,
+) -> PgTupleDesc<'static> {
+    let tupledesc = unsafe { CreateTupleDescCopy(tupledesc.as_ptr()) };
+
+    let natts = unsafe { (*tupledesc).natts } as usize;
+
+    let attributes = unsafe { (*tupledesc).attrs.as_mut_slice(natts) };
+
+    for attribute in attributes {
+        if attribute.is_dropped() {
+            continue;
+        }
+
+        let Some((typoid, typmod)) =
+            unwrapped_domain_type(attribute.atttypid, attribute.atttypmod, copy_operation)
+        else {
+            continue;
+        };
+
+        attribute.atttypid = typoid;
+        attribute.atttypmod = typmod;
+    }
+
+    unsafe { PgTupleDesc::from_pg_unchecked(tupledesc) }
+}
+
+// unwrapped_domain_type returns the type, as which the values of the domain are converted, or
+// None if the type is converted as it is. COPY TO writes domains over types with a parquet
+// counterpart, arrays of such domains, and domains over arrays as their base types, instead of
+// falling back to text. COPY FROM only reads domains over arrays as their base arrays, since a
+// list cannot be read as text. It reads the other domains by their input functions, which
+// check the constraints of the domain per row. Domains nested in composite types and maps are
+// not unwrapped.
+fn unwrapped_domain_type(
+    typoid: Oid,
+    typmod: i32,
+    copy_operation: CollectAttributesFor,
+) -> Option<(Oid, i32)> {
+    let copy_to = matches!(copy_operation, CollectAttributesFor::CopyTo);
+
+    if is_map_type(typoid) {
+        return None;
+    }
+
+    if is_array_type(typoid) {
+        if !copy_to {
+            return None;
+        }
+
+        // e.g. positive_int[], whose typmod is the typmod of the base type of its elements
+        let (element_typoid, element_typmod) =
+            unwrapped_domain_type(array_element_typoid(typoid), typmod, copy_operation)?;
+
+        if is_array_type(element_typoid) {
+            return None;
+        }
+
+        let array_typoid = unsafe { get_array_type(element_typoid) };
+
+        if array_typoid == InvalidOid {
+            return None;
+        }
+
+        return Some((array_typoid, element_typmod));
+    }
+
+    let (base_typoid, base_typmod) = domain_base_type(typoid)?;
+
+    if is_array_type(base_typoid) {
+        return Some(
+            unwrapped_domain_type(base_typoid, base_typmod, copy_operation)
+                .unwrap_or((base_typoid, base_typmod)),
+        );
+    }
+
+    if !copy_to || is_composite_type(base_typoid) || is_fallback_to_text_type(base_typoid) {
+        return None;
+    }
+
+    Some((base_typoid, base_typmod))
+}
+
+// error_if_copy_to_unsupported_types throws an error listing the columns, which have a type
+// without a parquet counterpart, or a nested field of such a type. String types and enums are
+// not reported since text is their natural representation.
+pub(crate) fn error_if_copy_to_unsupported_types(attributes: &[FormData_pg_attribute]) {
+    let unsupported_columns = attributes
+        .iter()
+        .filter(|attribute| {
+            has_unsupported_type(attribute.type_oid().value(), attribute.type_mod())
+        })
+        .map(|attribute| {
+            let type_name = unsafe { CStr::from_ptr(format_type_be(attribute.type_oid().value())) };
+
+            format!(
+                "\"{}\" ({})",
+                attribute.name(),
+                type_name
+                    .to_str()
+                    .expect("type name is not a valid C string")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if !unsupported_columns.is_empty() {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            format!(
+                "columns with types not supported by parquet: {}",
+                unsupported_columns.join(", ")
+            ),
+            "Set unsupported_type to 'text' to write them as text, or cast them to a supported type.",
+        );
     }
 }
 
+fn has_unsupported_type(typoid: Oid, typmod: i32) -> bool {
+    if is_composite_type(typoid) {
+        let tupledesc = tuple_desc(typoid, typmod);
+
+        collect_attributes_for(CollectAttributesFor::Other, &tupledesc)
+            .iter()
+            .any(|attribute| {
+                has_unsupported_type(attribute.type_oid().value(), attribute.type_mod())
+            })
+    } else if is_map_type(typoid) {
+        let (entries_typoid, entries_typmod) = domain_array_base_elem_type(typoid);
+        has_unsupported_type(entries_typoid, entries_typmod)
+    } else if is_array_type(typoid) {
+        has_unsupported_type(array_element_typoid(typoid), typmod)
+    } else {
+        is_fallback_to_text_type(typoid) && !is_text_like_type(typoid)
+    }
+}
+
+// FileColumnMatch is the result of matching a table column with the parquet file's columns.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FileColumnMatch {
+    // the column is found in the file. Its array is cast to the type if a cast is needed.
+    Found(Option<DataType>),
+    // the column is not found in the file. COPY fills it with its default value.
+    Missing,
+}
+
 // ensure_file_schema_match_tupledesc_schema throws an error if the file's schema does not match the table schema.
-// If the file's arrow schema is castable to the table's arrow schema, it returns a vector of Option<DataType>
-// to cast to for each field.
+// If the file's arrow schema is castable to the table's arrow schema, it returns a FileColumnMatch
+// with the type to cast to for each field. Fields, which are not found in the file, are marked
+// as missing when missing_columns is 'default'. With match_by 'position', fields are paired
+// by their ordinal regardless of their names. With list_coercion 'relaxed', list columns are
+// read into scalar columns and scalar columns into array columns. The file schema is expected
+// to be pruned from the extra fields by prune_extra_file_fields.
 pub(crate) fn ensure_file_schema_match_tupledesc_schema(
     file_schema: Arc<Schema>,
     tupledesc_schema: Arc<Schema>,
     attributes: &[FormData_pg_attribute],
     match_by: MatchBy,
-) -> Vec<Option<DataType>> {
-    let mut cast_to_types = Vec::new();
+    missing_columns: MissingColumns,
+    list_coercion: ListCoercion,
+) -> Vec<FileColumnMatch> {
+    let mut column_matches = Vec::new();
 
-    if match_by == MatchBy::Position
-        && tupledesc_schema.fields().len() != file_schema.fields().len()
-    {
+    let table_column_count = tupledesc_schema.fields().len();
+    let file_column_count = file_schema.fields().len();
+
+    // with missing_columns 'default', the trailing table columns might be missing in the file
+    let too_few_file_columns =
+        file_column_count < table_column_count && missing_columns == MissingColumns::Error;
+
+    if match_by == MatchBy::Position && too_few_file_columns {
         panic!(
             "column count mismatch between table and parquet file. \
              parquet file has {} columns, but table has {} columns",
-            file_schema.fields().len(),
-            tupledesc_schema.fields().len()
+            file_column_count, table_column_count
         );
     }
 
-    for (tupledesc_schema_field, attribute) in
-        tupledesc_schema.fields().iter().zip(attributes.iter())
+    for (column_idx, (tupledesc_schema_field, attribute)) in tupledesc_schema
+        .fields()
+        .iter()
+        .zip(attributes.iter())
+        .enumerate()
     {
         let field_name = tupledesc_schema_field.name();
 
         let file_schema_field = match match_by {
-            MatchBy::Position => file_schema.field(attribute.attnum as usize - 1),
-
-            MatchBy::Name => {
-                let file_schema_field = file_schema.column_with_name(field_name);
+            // attnum is not the ordinal when the table has dropped columns or
+            // COPY has a column list
+            MatchBy::Position => file_schema.fields().get(column_idx),
 
-                if file_schema_field.is_none() {
-                    panic!("column \"{}\" is not found in parquet file", field_name);
-                }
+            MatchBy::Name | MatchBy::FieldId => file_schema
+                .column_with_name(field_name)
+                .map(|(_, file_schema_field)| file_schema_field),
+        };
 
-                let (_, file_schema_field) = file_schema_field.unwrap();
+        let Some(file_schema_field) = file_schema_field else {
+            ensure_missing_column_can_be_filled(attribute, missing_columns);
 
-                file_schema_field
-            }
+            column_matches.push(FileColumnMatch::Missing);
+            continue;
         };
 
         let file_schema_field = Arc::new(file_schema_field.clone());
@@ -413,17 +1155,50 @@ pub(crate) fn ensure_file_schema_match_tupledesc_schema(
 
         // no cast needed
         if from_type == to_type {
-            cast_to_types.push(None);
+            column_matches.push(FileColumnMatch::Found(None));
             continue;
         }
 
-        if !is_coercible(from_type, to_type, attribute.atttypid, attribute.atttypmod) {
-            panic!(
+        let relaxed_list_cast_type = match list_coercion {
+            ListCoercion::Relaxed => {
+                relaxed_list_cast_type(from_type, to_type, attribute, field_name, match_by)
+            }
+            ListCoercion::Error => None,
+        };
+
+        let cast_to_type = relaxed_list_cast_type.unwrap_or_else(|| {
+            is_coercible(
+                from_type,
+                to_type,
+                attribute.atttypid,
+                attribute.atttypmod,
+                field_name,
+                match_by,
+            )
+            .map(|_| column_cast_type(from_type, to_type, attribute.atttypid, match_by))
+        });
+
+        let cast_to_type = cast_to_type.unwrap_or_else(|incompatible_field_path| match match_by {
+            MatchBy::Position => panic!(
                 "type mismatch for column \"{}\" between table and parquet file.\n\n\
-                 table has \"{}\"\n\nparquet file has \"{}\"",
-                field_name, to_type, from_type
-            );
-        }
+                 column {} is \"{}\" in table and \"{}\" in parquet file\n\n\
+                 table has \"{}\"\n\nparquet file has \"{}\"\n\n\
+                 incompatible field is \"{}\"",
+                field_name,
+                column_idx + 1,
+                field_name,
+                file_schema_field.name(),
+                to_type,
+                from_type,
+                incompatible_field_path
+            ),
+            MatchBy::Name | MatchBy::FieldId => panic!(
+                "type mismatch for column \"{}\" between table and parquet file.\n\n\
+                 table has \"{}\"\n\nparquet file has \"{}\"\n\n\
+                 incompatible field is \"{}\"",
+                field_name, to_type, from_type, incompatible_field_path
+            ),
+        });
 
         pgrx::debug2!(
             "column \"{}\" is being cast from \"{}\" to \"{}\"",
@@ -432,25 +1207,378 @@ pub(crate) fn ensure_file_schema_match_tupledesc_schema(
             to_type
         );
 
-        cast_to_types.push(Some(to_type.clone()));
+        column_matches.push(FileColumnMatch::Found(Some(cast_to_type)));
+    }
+
+    column_matches
+}
+
+// column_cast_type returns the type, to which arrow-cast casts the column of the given type.
+fn column_cast_type(
+    from_type: &DataType,
+    to_type: &DataType,
+    to_typoid: Oid,
+    match_by: MatchBy,
+) -> DataType {
+    if to_typoid == CASHOID {
+        money_cast_type(from_type, to_type, match_by)
+    } else {
+        coercion_cast_type(from_type, to_type, match_by)
+    }
+}
+
+// relaxed_list_cast_type returns the type, to which the column is cast with list_coercion
+// 'relaxed', if a list column is read into a scalar column, or a scalar column into an array
+// column. The single elements of the lists are unwrapped by unwrap_single_element_lists before
+// they are cast to the type of the scalar column, and the scalars are cast to the element type
+// before wrap_scalars_into_single_element_lists wraps them. It returns None for the other
+// columns, which are coerced as usual.
+fn relaxed_list_cast_type(
+    from_type: &DataType,
+    to_type: &DataType,
+    attribute: &FormData_pg_attribute,
+    field_name: &str,
+    match_by: MatchBy,
+) -> Option<Result<DataType, String>> {
+    let to_typoid = attribute.atttypid;
+    let to_typmod = attribute.atttypmod;
+
+    match (from_type, to_type) {
+        (DataType::List(_) | DataType::LargeList(_), _)
+            if !is_list_type(to_type) && !matches!(to_type, DataType::Map(_, _)) =>
+        {
+            let element_type = innermost_list_element_type(from_type);
+
+            let cast_to_type = is_coercible(
+                element_type,
+                to_type,
+                to_typoid,
+                to_typmod,
+                field_name,
+                match_by,
+            )
+            .map(|_| column_cast_type(element_type, to_type, to_typoid, match_by));
+
+            Some(cast_to_type)
+        }
+        (_, DataType::List(to_field)) if is_scalar_type(from_type) => {
+            let element_typoid = array_element_typoid(to_typoid);
+
+            let cast_to_type = is_coercible(
+                from_type,
+                to_field.data_type(),
+                element_typoid,
+                to_typmod,
+                field_name,
+                match_by,
+            )
+            .map(|_| {
+                let element_type =
+                    column_cast_type(from_type, to_field.data_type(), element_typoid, match_by);
+
+                DataType::List(Arc::new(
+                    to_field.as_ref().clone().with_data_type(element_type),
+                ))
+            });
+
+            Some(cast_to_type)
+        }
+        _ => None,
+    }
+}
+
+// coercion_cast_type returns the type, to which arrow-cast casts the column. Some coercions
+// are done by the arrow to pg conversion instead of arrow-cast:
+// - Coercions between timestamp, timestamptz and date depend on the session timezone, which
+//   arrow-cast does not know about. Hence, arrow-cast keeps these timestamps as UTC or local
+//   timestamps in their time unit, and the conversion rescales them to microseconds and coerces
+//   them into the column's type with Postgres' own casts.
+// - Large lists are kept as large lists, since the conversion reads a single list at a time,
+//   while arrow-cast fails when all lists of a record batch exceed i32 offsets.
+// - Structs keep the subfields of the file in their order with match_by 'name', since
+//   arrow-cast casts the subfields by position. Composite fields, which are missing in the
+//   file, are converted to NULL.
+// - Decimals are kept as they are, and numeric's input function applies the column's typmod.
+//   Hence, unconstrained numeric columns read the decimals without losing any digits, and
+//   Decimal256 values, e.g. with precision > 38 by Spark, are read via their text form. Decimals
+//   stored as INT32 or INT64, e.g. by pyarrow and parquet-mr, are read as Decimal128 with their
+//   declared precision and scale by the parquet crate.
+fn coercion_cast_type(from_type: &DataType, to_type: &DataType, match_by: MatchBy) -> DataType {
+    match (from_type, to_type) {
+        (
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _),
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _),
+        ) => from_type.clone(),
+        // only network addresses and timetz are read from text into structs and fixed size
+        // binaries, which is done by their input function
+        (
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View,
+            DataType::Struct(_) | DataType::FixedSizeBinary(_),
+        ) => DataType::Utf8,
+        // only timetz is read from times into structs, which are adjusted to UTC
+        (DataType::Time32(_) | DataType::Time64(_), DataType::Struct(_)) => {
+            DataType::Time64(arrow::datatypes::TimeUnit::Microsecond)
+        }
+        (DataType::Timestamp(from_unit, Some(_)), DataType::Timestamp(_, None))
+        | (DataType::Timestamp(from_unit, Some(_)), DataType::Date32) => {
+            DataType::Timestamp(*from_unit, Some("+00:00".into()))
+        }
+        (DataType::Timestamp(from_unit, None), DataType::Timestamp(_, Some(_))) => {
+            DataType::Timestamp(*from_unit, None)
+        }
+        // e.g. INT96 timestamps, which are read as nanoseconds
+        (DataType::Timestamp(from_unit, _), DataType::Timestamp(_, to_timezone)) => {
+            DataType::Timestamp(*from_unit, to_timezone.clone())
+        }
+        (DataType::List(from_field), DataType::List(to_field)) => {
+            let element_type = if is_multidim_list(from_field.data_type(), to_field.data_type()) {
+                coercion_cast_type(from_field.data_type(), to_type, match_by)
+            } else {
+                coercion_cast_type(from_field.data_type(), to_field.data_type(), match_by)
+            };
+
+            DataType::List(Arc::new(
+                to_field.as_ref().clone().with_data_type(element_type),
+            ))
+        }
+        (DataType::LargeList(from_field), DataType::List(to_field)) => {
+            let element_type = if is_multidim_list(from_field.data_type(), to_field.data_type()) {
+                coercion_cast_type(from_field.data_type(), to_type, match_by)
+            } else {
+                coercion_cast_type(from_field.data_type(), to_field.data_type(), match_by)
+            };
+
+            DataType::LargeList(Arc::new(
+                to_field.as_ref().clone().with_data_type(element_type),
+            ))
+        }
+        (DataType::Struct(from_fields), DataType::Struct(to_fields)) => {
+            let fields = from_fields
+                .iter()
+                .enumerate()
+                .map(|(field_idx, from_field)| {
+                    let to_field = match match_by {
+                        MatchBy::Position => &to_fields[field_idx],
+                        MatchBy::Name | MatchBy::FieldId => {
+                            let (_, to_field) = to_fields
+                                .find(from_field.name())
+                                .expect("subfield is not found in composite type");
+                            to_field
+                        }
+                    };
+
+                    let field_type =
+                        coercion_cast_type(from_field.data_type(), to_field.data_type(), match_by);
+
+                    Arc::new(to_field.as_ref().clone().with_data_type(field_type))
+                })
+                .collect::<Vec<_>>();
+
+            DataType::Struct(Fields::from(fields))
+        }
+        // the key and the value of the map are cast by position, and they are renamed to the
+        // fields of the map type, e.g. "value" to "val"
+        (DataType::Map(from_entries_field, _), DataType::Map(to_entries_field, sorted)) => {
+            let entries_type = match (from_entries_field.data_type(), to_entries_field.data_type())
+            {
+                (DataType::Struct(from_fields), DataType::Struct(to_fields)) => {
+                    let fields = from_fields
+                        .iter()
+                        .zip(to_fields.iter())
+                        .map(|(from_field, to_field)| {
+                            let field_type = coercion_cast_type(
+                                from_field.data_type(),
+                                to_field.data_type(),
+                                match_by,
+                            );
+
+                            Arc::new(to_field.as_ref().clone().with_data_type(field_type))
+                        })
+                        .collect::<Vec<_>>();
+
+                    DataType::Struct(Fields::from(fields))
+                }
+                _ => to_entries_field.data_type().clone(),
+            };
+
+            DataType::Map(
+                Arc::new(
+                    to_entries_field
+                        .as_ref()
+                        .clone()
+                        .with_data_type(entries_type),
+                ),
+                *sorted,
+            )
+        }
+        (DataType::Dictionary(_, from_value_type), _) => {
+            coercion_cast_type(from_value_type, to_type, match_by)
+        }
+        _ => to_type.clone(),
+    }
+}
+
+// money_cast_type returns the type, to which arrow-cast casts the money column. Int64 columns
+// hold cents, and text columns are parsed by money's input function, e.g. "$1,000.00" in the
+// current lc_monetary. Hence, both of them are kept as they are.
+fn money_cast_type(from_type: &DataType, to_type: &DataType, match_by: MatchBy) -> DataType {
+    match from_type {
+        DataType::Int64 => DataType::Int64,
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => to_type.clone(),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => DataType::Utf8,
+        DataType::Dictionary(_, from_value_type) => {
+            money_cast_type(from_value_type, to_type, match_by)
+        }
+        _ => coercion_cast_type(from_type, to_type, match_by),
+    }
+}
+
+// ensure_missing_column_can_be_filled throws an error if the column, which is not found in
+// the parquet file, cannot be filled by its default value.
+fn ensure_missing_column_can_be_filled(
+    attribute: &FormData_pg_attribute,
+    missing_columns: MissingColumns,
+) {
+    let column_name = attribute.name();
+
+    if missing_columns == MissingColumns::Error {
+        panic!("column \"{}\" is not found in parquet file", column_name);
+    }
+
+    // identity columns are filled by their sequence
+    if attribute.attnotnull && !attribute.atthasdef && attribute.attidentity == 0 {
+        panic!(
+            "column \"{}\" is not found in parquet file and it has no default value to fill \
+             the NOT NULL column",
+            column_name
+        );
+    }
+}
+
+// ensure_missing_subfield_can_be_null throws an error if the composite field, which is not
+// found in the struct of the parquet file, cannot be NULL due to a NOT NULL domain.
+fn ensure_missing_subfield_can_be_null(attribute: &FormData_pg_attribute, field_path: &str) {
+    let typoid = attribute.type_oid().value();
+
+    pgrx::debug2!(
+        "field \"{}\" is not found in parquet file, it is read as NULL",
+        field_path
+    );
+
+    if domain_base_type(typoid).is_none() {
+        return;
     }
 
-    cast_to_types
+    // checks the NOT NULL constraints of the domain, and of its base domains
+    PgTryBuilder::new(|| unsafe {
+        domain_check(
+            Datum::null(),
+            true,
+            typoid,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    })
+    .catch_others(|_| {
+        panic!(
+            "field \"{}\" is not found in parquet file and its domain does not allow NULL",
+            field_path
+        )
+    })
+    .execute();
 }
 
 // is_coercible first checks if "from_type" can be cast to "to_type" by arrow-cast.
 // Then, it checks if the cast is meaningful at Postgres by seeing if there is
-// an explicit coercion from "from_typoid" to "to_typoid".
+// an explicit coercion from "from_typoid" to "to_typoid". It returns the path of
+// the innermost field, which cannot be coerced, as error.
 //
 // Additionaly, we need to be careful about struct rules for the cast:
 // Arrow supports casting struct fields by field position instead of field name,
-// which is not the intended behavior for pg_parquet. Hence, we make sure the field names
-// match for structs.
-fn is_coercible(from_type: &DataType, to_type: &DataType, to_typoid: Oid, to_typmod: i32) -> bool {
+// which is only the intended behavior for match_by 'position'. Hence, we make sure
+// the field names match for structs with match_by 'name'.
+//
+// Lists, which are read into scalar columns, and scalars, which are read into array columns,
+// fail with an error that points to the column's type instead of the incompatible field.
+fn is_coercible(
+    from_type: &DataType,
+    to_type: &DataType,
+    to_typoid: Oid,
+    to_typmod: i32,
+    field_path: &str,
+    match_by: MatchBy,
+) -> Result<(), String> {
     match (from_type, to_type) {
+        (DataType::List(_) | DataType::LargeList(_), _)
+            if !is_list_type(to_type) && !matches!(to_type, DataType::Map(_, _)) =>
+        {
+            raise_list_into_scalar_error(field_path, to_typoid)
+        }
+        (_, DataType::List(_)) if is_scalar_type(from_type) => {
+            raise_scalar_into_list_error(field_path, to_typoid)
+        }
+        (_, DataType::Struct(_)) if to_typoid == TIMETZOID => {
+            // timetz is read from its own shape, or from times adjusted to UTC, e.g. in the
+            // files which are written before it kept its offset, or from its text form
+            if is_timetz_struct_type(from_type)
+                || matches!(from_type, DataType::Time32(_) | DataType::Time64(_))
+                || is_string_type(from_type)
+            {
+                Ok(())
+            } else {
+                Err(field_path.into())
+            }
+        }
+        (_, DataType::Struct(_) | DataType::FixedSizeBinary(_))
+            if is_network_address_type(to_typoid) =>
+        {
+            // network addresses are read from their own shape, or from their text form, e.g.
+            // in the files which are written before they had their own shape
+            if from_type == to_type || is_string_type(from_type) {
+                Ok(())
+            } else {
+                Err(field_path.into())
+            }
+        }
+        (DataType::Struct(from_fields), DataType::Struct(to_fields))
+            if match_by != MatchBy::Position =>
+        {
+            // subfields are matched by name regardless of their order. The extra subfields
+            // of the file are already pruned, unless none of the subfields is found.
+            if from_fields
+                .iter()
+                .any(|from_field| to_fields.find(from_field.name()).is_none())
+            {
+                return Err(field_path.into());
+            }
+
+            let tupledesc = tuple_desc(to_typoid, to_typmod);
+
+            let attributes = collect_attributes_for(CollectAttributesFor::Other, &tupledesc);
+
+            for (to_field, to_attribute) in to_fields.iter().zip(attributes.iter()) {
+                let child_field_path = format!("{}.{}", field_path, to_field.name());
+
+                let Some((_, from_field)) = from_fields.find(to_field.name()) else {
+                    ensure_missing_subfield_can_be_null(to_attribute, &child_field_path);
+                    continue;
+                };
+
+                is_coercible(
+                    from_field.data_type(),
+                    to_field.data_type(),
+                    to_attribute.type_oid().value(),
+                    to_attribute.type_mod(),
+                    &child_field_path,
+                    match_by,
+                )?;
+            }
+
+            Ok(())
+        }
         (DataType::Struct(from_fields), DataType::Struct(to_fields)) => {
             if from_fields.len() != to_fields.len() {
-                return false;
+                return Err(field_path.into());
             }
 
             let tupledesc = tuple_desc(to_typoid, to_typmod);
@@ -461,23 +1589,36 @@ fn is_coercible(from_type: &DataType, to_type: &DataType, to_typoid: Oid, to_typ
                 .iter()
                 .zip(to_fields.iter().zip(attributes.iter()))
             {
-                if from_field.name() != to_field.name() {
-                    return false;
-                }
+                let child_field_path = format!("{}.{}", field_path, to_field.name());
 
-                if !is_coercible(
+                is_coercible(
                     from_field.data_type(),
                     to_field.data_type(),
                     to_attribute.type_oid().value(),
                     to_attribute.type_mod(),
-                ) {
-                    return false;
-                }
+                    &child_field_path,
+                    match_by,
+                )?;
             }
 
-            true
+            Ok(())
         }
-        (DataType::List(from_field), DataType::List(to_field)) => {
+        (DataType::List(from_field), DataType::List(to_field))
+        | (DataType::LargeList(from_field), DataType::List(to_field)) => {
+            // the element field's name and nullability are ignored since they differ between
+            // writers (e.g. "array" and non-nullable element for legacy 2-level lists, or "item")
+            if is_multidim_list(from_field.data_type(), to_field.data_type()) {
+                // each nested list is a dimension of the array
+                return is_coercible(
+                    from_field.data_type(),
+                    to_type,
+                    to_typoid,
+                    to_typmod,
+                    &format!("{}.{}", field_path, from_field.name()),
+                    match_by,
+                );
+            }
+
             let element_oid = array_element_typoid(to_typoid);
             let element_typmod = to_typmod;
 
@@ -486,42 +1627,151 @@ fn is_coercible(from_type: &DataType, to_type: &DataType, to_typoid: Oid, to_typ
                 to_field.data_type(),
                 element_oid,
                 element_typmod,
+                &format!("{}.{}", field_path, to_field.name()),
+                match_by,
             )
         }
         (DataType::Map(from_entries_field, _), DataType::Map(to_entries_field, _)) => {
-            // entries field cannot be null
-            if from_entries_field.is_nullable() {
-                return false;
+            // writers name the entries and their fields differently, e.g. "key_value" with
+            // "key" and "value", and some of them write nullable entries, whose null entries
+            // are read as absent pairs. Hence, the key and the value are matched by position.
+            let (DataType::Struct(from_fields), DataType::Struct(to_fields)) =
+                (from_entries_field.data_type(), to_entries_field.data_type())
+            else {
+                return Err(field_path.into());
+            };
+
+            if from_fields.len() != 2 {
+                return Err(field_path.into());
             }
 
             let (entries_typoid, entries_typmod) = domain_array_base_elem_type(to_typoid);
 
+            let tupledesc = tuple_desc(entries_typoid, entries_typmod);
+
+            let attributes = collect_attributes_for(CollectAttributesFor::Other, &tupledesc);
+
+            for (from_field, (to_field, to_attribute)) in from_fields
+                .iter()
+                .zip(to_fields.iter().zip(attributes.iter()))
+            {
+                is_coercible(
+                    from_field.data_type(),
+                    to_field.data_type(),
+                    to_attribute.type_oid().value(),
+                    to_attribute.type_mod(),
+                    &format!("{}.{}", field_path, to_field.name()),
+                    match_by,
+                )?;
+            }
+
+            Ok(())
+        }
+        (DataType::Dictionary(_, from_value_type), _) => {
+            // dictionary encoded columns are read by their value type
+            if !can_cast_types(from_type, to_type) {
+                return Err(field_path.into());
+            }
+
             is_coercible(
-                from_entries_field.data_type(),
-                to_entries_field.data_type(),
-                entries_typoid,
-                entries_typmod,
+                from_value_type,
+                to_type,
+                to_typoid,
+                to_typmod,
+                field_path,
+                match_by,
             )
         }
         _ => {
             // check if arrow-cast can cast the types
             if !can_cast_types(from_type, to_type) {
-                return false;
+                return Err(field_path.into());
             }
 
             let from_typoid = pg_type_for_arrow_primitive_type(from_type);
 
             // pg_parquet could not recognize that arrow type
             if from_typoid == InvalidOid {
-                return false;
+                return Err(field_path.into());
             }
 
             // check if coercion is meaningful at Postgres (it has a coercion path)
-            can_pg_coerce_types(from_typoid, to_typoid, COERCION_EXPLICIT)
+            if !can_pg_coerce_types(from_typoid, to_typoid, COERCION_EXPLICIT) {
+                return Err(field_path.into());
+            }
+
+            Ok(())
         }
     }
 }
 
+fn raise_list_into_scalar_error(field_path: &str, to_typoid: Oid) -> ! {
+    let array_typoid = unsafe { get_array_type(to_typoid) };
+
+    let hint = if array_typoid == InvalidOid {
+        "Change the column to an array type, or use list_coercion 'relaxed' to read the \
+         single-element lists into the column."
+            .to_string()
+    } else {
+        format!(
+            "Change the column to {}, or use list_coercion 'relaxed' to read the single-element \
+             lists into the column.",
+            type_name(array_typoid)
+        )
+    };
+
+    ErrorReport::new(
+        PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+        format!(
+            "column \"{}\" is a list in the parquet file but scalar {} in the table",
+            field_path,
+            type_name(to_typoid)
+        ),
+        function_name!(),
+    )
+    .set_hint(hint)
+    .report(PgLogLevel::ERROR);
+
+    unreachable!("ereport at ERROR level does not return")
+}
+
+fn raise_scalar_into_list_error(field_path: &str, to_typoid: Oid) -> ! {
+    ErrorReport::new(
+        PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+        format!(
+            "column \"{}\" is a scalar in the parquet file but array {} in the table",
+            field_path,
+            type_name(to_typoid)
+        ),
+        function_name!(),
+    )
+    .set_hint(format!(
+        "Change the column to {}, or use list_coercion 'relaxed' to read the scalars into the \
+         column as single-element arrays.",
+        type_name(array_element_typoid(to_typoid))
+    ))
+    .report(PgLogLevel::ERROR);
+
+    unreachable!("ereport at ERROR level does not return")
+}
+
+fn type_name(typoid: Oid) -> String {
+    let type_name = unsafe { CStr::from_ptr(format_type_be(typoid)) };
+
+    type_name
+        .to_str()
+        .expect("type name is not a valid C string")
+        .to_string()
+}
+
+fn is_string_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(_, value_type) => is_string_type(value_type),
+        _ => false,
+    }
+}
+
 fn can_pg_coerce_types(from_typoid: Oid, to_typoid: Oid, ccontext: CoercionContext::Type) -> bool {
     let n_args = 1;
     let input_typeids = [from_typoid];
@@ -537,6 +1787,57 @@ fn can_pg_coerce_types(from_typoid: Oid, to_typoid: Oid, ccontext: CoercionConte
     }
 }
 
+// postgres_type_name_for_arrow_type returns the name of the Postgres type, which COPY FROM
+// reads the given arrow type into without any coercion. Nested lists are read into arrays with
+// multiple dimensions. It returns None for structs and maps, which require a user defined type,
+// except the structs of timetz, and for the arrow types that pg_parquet does not recognize.
+// postgres_type_name_for_arrow_field returns the name of the Postgres type, which the field is
+// read as. The metadata of the field, which is kept in the arrow schema embedded by COPY TO,
+// takes precedence over the type inferred from its arrow type.
+pub(crate) fn postgres_type_name_for_arrow_field(field: &Field) -> Option<String> {
+    if let Some(bpchar_length) = field.metadata().get(PG_BPCHAR_LENGTH_META_KEY) {
+        return Some(format!("character({})", bpchar_length));
+    }
+
+    postgres_type_name_for_arrow_type(field.data_type())
+}
+
+fn postgres_type_name_for_arrow_type(data_type: &DataType) -> Option<String> {
+    match data_type {
+        DataType::Dictionary(_, value_type) => postgres_type_name_for_arrow_type(value_type),
+        DataType::List(element_field) | DataType::LargeList(element_field) => {
+            match element_field.data_type() {
+                DataType::List(_) | DataType::LargeList(_) => {
+                    postgres_type_name_for_arrow_type(element_field.data_type())
+                }
+                DataType::Struct(_) if !is_timetz_struct_type(element_field.data_type()) => None,
+                DataType::Map(_, _) => None,
+                element_type => postgres_type_name_for_arrow_type(element_type)
+                    .map(|element_type_name| format!("{}[]", element_type_name)),
+            }
+        }
+        DataType::Decimal128(precision, scale) | DataType::Decimal256(precision, scale) => {
+            Some(format!("numeric({},{})", precision, scale))
+        }
+        _ => {
+            let typoid = pg_type_for_arrow_primitive_type(data_type);
+
+            if typoid == InvalidOid {
+                return None;
+            }
+
+            let type_name = unsafe { CStr::from_ptr(format_type_be(typoid)) };
+
+            Some(
+                type_name
+                    .to_str()
+                    .expect("type name is not a valid C string")
+                    .to_string(),
+            )
+        }
+    }
+}
+
 // pg_type_for_arrow_primitive_type returns Postgres type for given
 // primitive arrow type. It returns InvalidOid if the arrow type is not recognized.
 fn pg_type_for_arrow_primitive_type(data_type: &DataType) -> Oid {
@@ -546,14 +1847,15 @@ fn pg_type_for_arrow_primitive_type(data_type: &DataType) -> Oid {
         DataType::Int16 | DataType::UInt16 | DataType::Int8 | DataType::UInt8 => INT2OID,
         DataType::Int32 | DataType::UInt32 => INT4OID,
         DataType::Int64 | DataType::UInt64 => INT8OID,
-        DataType::Decimal128(_, _) => NUMERICOID,
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => NUMERICOID,
         DataType::Boolean => BOOLOID,
         DataType::Date32 => DATEOID,
         DataType::Time64(_) => TIMEOID,
+        DataType::Struct(_) if is_timetz_struct_type(data_type) => TIMETZOID,
         DataType::Timestamp(_, None) => TIMESTAMPOID,
         DataType::Timestamp(_, Some(_)) => TIMESTAMPTZOID,
-        DataType::Utf8 | DataType::LargeUtf8 => TEXTOID,
-        DataType::Binary | DataType::LargeBinary => BYTEAOID,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => TEXTOID,
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => BYTEAOID,
         _ => InvalidOid,
     }
 }
@@ -0,0 +1,53 @@
+use std::{fmt::Display, str::FromStr};
+
+use parquet::file::properties::EnabledStatistics;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetStatistics {
+    None,
+    Chunk,
+    #[default]
+    Page,
+}
+
+pub(crate) fn all_supported_statistics() -> Vec<PgParquetStatistics> {
+    vec![
+        PgParquetStatistics::None,
+        PgParquetStatistics::Chunk,
+        PgParquetStatistics::Page,
+    ]
+}
+
+impl From<PgParquetStatistics> for EnabledStatistics {
+    fn from(value: PgParquetStatistics) -> Self {
+        match value {
+            PgParquetStatistics::None => EnabledStatistics::None,
+            PgParquetStatistics::Chunk => EnabledStatistics::Chunk,
+            PgParquetStatistics::Page => EnabledStatistics::Page,
+        }
+    }
+}
+
+impl Display for PgParquetStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetStatistics::None => write!(f, "none"),
+            PgParquetStatistics::Chunk => write!(f, "chunk"),
+            PgParquetStatistics::Page => write!(f, "page"),
+        }
+    }
+}
+
+impl FromStr for PgParquetStatistics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(PgParquetStatistics::None),
+            "chunk" => Ok(PgParquetStatistics::Chunk),
+            "page" => Ok(PgParquetStatistics::Page),
+            _ => Err(format!("unrecognized statistics level: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::ffi::c_void;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use parquet::{arrow::async_writer::AsyncFileWriter, errors::ParquetError};
+use pgrx::pg_sys::ffi::pg_guard_ffi_boundary;
+
+// CopyToParquetStream is the destination of create_copy_to_parquet_dest_receiver_with_stream,
+// which receives the bytes of the parquet file instead of pg_parquet writing them to a uri.
+// "opaque" is passed as is to the callbacks. The callbacks return false on failure, which fails
+// the COPY, or they can raise a Postgres error themselves.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CopyToParquetStream {
+    // write_fn is called with the next chunk of the bytes of the file. It is required.
+    pub write_fn:
+        Option<unsafe extern "C" fn(opaque: *mut c_void, data: *const u8, len: usize) -> bool>,
+    // flush_fn is called after each finished row group, when all bytes of the file written so
+    // far are passed to write_fn. It is optional.
+    pub flush_fn: Option<unsafe extern "C" fn(opaque: *mut c_void) -> bool>,
+    // finish_fn is called once, after the footer is passed to write_fn. It is not called when
+    // the COPY fails. It is optional.
+    pub finish_fn: Option<unsafe extern "C" fn(opaque: *mut c_void) -> bool>,
+    pub opaque: *mut c_void,
+}
+
+impl CopyToParquetStream {
+    // flush calls flush_fn of the stream, if any.
+    pub(crate) fn flush(&self) -> Result<(), ParquetError> {
+        let Some(flush_fn) = self.flush_fn else {
+            return Ok(());
+        };
+
+        let opaque = self.opaque;
+
+        if !unsafe { pg_guard_ffi_boundary(|| flush_fn(opaque)) } {
+            return Err(ParquetError::General(
+                "flush_fn of the stream failed".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, data: &[u8]) -> Result<(), ParquetError> {
+        let write_fn = self.write_fn.expect("write_fn of the stream is not set");
+
+        let opaque = self.opaque;
+
+        if !unsafe { pg_guard_ffi_boundary(|| write_fn(opaque, data.as_ptr(), data.len())) } {
+            return Err(ParquetError::General(
+                "write_fn of the stream failed".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), ParquetError> {
+        let Some(finish_fn) = self.finish_fn else {
+            return Ok(());
+        };
+
+        let opaque = self.opaque;
+
+        if !unsafe { pg_guard_ffi_boundary(|| finish_fn(opaque)) } {
+            return Err(ParquetError::General(
+                "finish_fn of the stream failed".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// StreamWriter passes the bytes, which the parquet writer writes, to the callbacks of the
+// stream. The callbacks are called in the backend, since the writer runs on the current thread
+// runtime of the backend.
+pub(crate) struct StreamWriter {
+    stream: CopyToParquetStream,
+}
+
+// the callbacks are only called from the backend thread
+unsafe impl Send for StreamWriter {}
+
+impl StreamWriter {
+    pub(crate) fn new(stream: CopyToParquetStream) -> Self {
+        if stream.write_fn.is_none() {
+            panic!("write_fn of the stream is not set");
+        }
+
+        Self { stream }
+    }
+}
+
+impl AsyncFileWriter for StreamWriter {
+    fn write(&mut self, bs: Bytes) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        Box::pin(async move { self.stream.write(&bs) })
+    }
+
+    fn complete(&mut self) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        Box::pin(async { self.stream.finish() })
+    }
+}
@@ -0,0 +1,387 @@
+use std::ffi::{c_char, c_int, c_void, CStr};
+
+use pgrx::{
+    pg_guard,
+    pg_sys::{
+        before_shmem_exit, DataDir, Datum, GetCurrentSubTransactionId, GetNextTempTableSpace,
+        InvalidOid, MyDatabaseTableSpace, MyProcPid, PrepareTempTablespaces,
+        RegisterSubXactCallback, SubTransactionId, SubXactEvent, TempTablespacePath, MAXPGPATH,
+    },
+    register_xact_callback, PgXactCallbackEvent,
+};
+use url::Url;
+
+use super::uri_utils::uri_as_string;
+
+// postgres removes the files under temp directories, whose names start with this prefix,
+// at server start, so temp files which are left over after a crash are removed as well
+const PG_TEMP_FILE_PREFIX: &str = "pgsql_tmp";
+
+#[derive(Debug, Clone)]
+pub(crate) struct TempParquetFile {
+    pub(crate) name: String,
+    pub(crate) uri: Url,
+}
+
+// TEMP_PARQUET_FILES is the backend local registry of the temp files created by COPY TO
+// with the temp option. They are removed at the end of the session, or when the transaction
+// which created them aborts.
+static mut TEMP_PARQUET_FILES: Vec<TempParquetFile> = Vec::new();
+
+// XACT_TEMP_PARQUET_FILE_URIS are the uris of the temp files, which are created by the
+// current transaction, along with the subtransaction which created them.
+static mut XACT_TEMP_PARQUET_FILE_URIS: Vec<(Url, SubTransactionId)> = Vec::new();
+
+// REPLACED_TEMP_PARQUET_FILES are the temp files, which are replaced by the current
+// transaction, along with the subtransaction which replaced them. They are removed when the
+// transaction commits, and registered again when the subtransaction or the transaction aborts.
+static mut REPLACED_TEMP_PARQUET_FILES: Vec<(TempParquetFile, SubTransactionId)> = Vec::new();
+
+static mut TEMP_PARQUET_FILE_COUNTER: u64 = 0;
+
+static mut TEMP_PARQUET_FILES_EXIT_CALLBACK_REGISTERED: bool = false;
+
+static mut TEMP_PARQUET_FILES_SUBXACT_CALLBACK_REGISTERED: bool = false;
+
+// create_temp_parquet_file registers a new temp file with the given name and returns its uri.
+// An existing temp file with the same name is replaced. The replaced file is removed when the
+// transaction commits, so that it is still there if the transaction aborts.
+pub(crate) fn create_temp_parquet_file(name: &str) -> Url {
+    if name.is_empty() {
+        panic!("temporary parquet file name cannot be empty");
+    }
+
+    register_temp_parquet_files_exit_callback();
+
+    register_temp_parquet_files_subxact_callback();
+
+    if let Some(replaced_temp_file) = unregister_temp_parquet_file(name) {
+        replace_temp_parquet_file(replaced_temp_file);
+    }
+
+    let uri = next_temp_parquet_file_uri();
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEMP_PARQUET_FILES.push(TempParquetFile {
+            name: name.to_string(),
+            uri: uri.clone(),
+        });
+
+        // the file is not visible after the creating transaction aborts
+        if XACT_TEMP_PARQUET_FILE_URIS.is_empty() {
+            register_xact_callback(PgXactCallbackEvent::Commit, || {
+                end_temp_parquet_files_xact(true)
+            });
+            register_xact_callback(PgXactCallbackEvent::Abort, || {
+                end_temp_parquet_files_xact(false)
+            });
+        }
+
+        XACT_TEMP_PARQUET_FILE_URIS.push((uri.clone(), GetCurrentSubTransactionId()));
+    };
+
+    uri
+}
+
+// replace_temp_parquet_file removes the replaced file right away if the current subtransaction
+// created it, otherwise when the transaction commits.
+fn replace_temp_parquet_file(replaced_temp_file: TempParquetFile) {
+    let subxact_id = unsafe { GetCurrentSubTransactionId() };
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        let created_by_subxact =
+            XACT_TEMP_PARQUET_FILE_URIS.contains(&(replaced_temp_file.uri.clone(), subxact_id));
+
+        if created_by_subxact {
+            XACT_TEMP_PARQUET_FILE_URIS.retain(|(uri, _)| uri != &replaced_temp_file.uri);
+            remove_temp_parquet_file_by_uri(&replaced_temp_file.uri);
+        } else {
+            REPLACED_TEMP_PARQUET_FILES.push((replaced_temp_file, subxact_id));
+        }
+    }
+}
+
+// end_temp_parquet_files_xact removes the replaced temp files when the transaction commits.
+// When it aborts, the temp files created by it are removed, and the replaced files are
+// registered again.
+pub(crate) fn end_temp_parquet_files_xact(committed: bool) {
+    #[allow(static_mut_refs)]
+    let (xact_uris, replaced_temp_files) = unsafe {
+        (
+            std::mem::take(&mut XACT_TEMP_PARQUET_FILE_URIS),
+            std::mem::take(&mut REPLACED_TEMP_PARQUET_FILES),
+        )
+    };
+
+    if committed {
+        for (replaced_temp_file, _) in replaced_temp_files {
+            remove_temp_parquet_file_by_uri(&replaced_temp_file.uri);
+        }
+    } else {
+        for (uri, _) in &xact_uris {
+            remove_temp_parquet_file_by_uri(uri);
+        }
+
+        // the files, which are created by the transaction, are removed above
+        let replaced_temp_files = replaced_temp_files
+            .into_iter()
+            .map(|(replaced_temp_file, _)| replaced_temp_file)
+            .filter(|replaced_temp_file| {
+                !xact_uris
+                    .iter()
+                    .any(|(uri, _)| uri == &replaced_temp_file.uri)
+            });
+
+        #[allow(static_mut_refs)]
+        unsafe {
+            TEMP_PARQUET_FILES.extend(replaced_temp_files)
+        };
+    }
+}
+
+// end_temp_parquet_files_subxact passes the temp files, which are created or replaced by the
+// subtransaction, on to its parent when it commits. When it aborts, the temp files created by
+// it are removed, and the files replaced by it are registered again.
+pub(crate) fn end_temp_parquet_files_subxact(
+    committed: bool,
+    subxact_id: SubTransactionId,
+    parent_subxact_id: SubTransactionId,
+) {
+    #[allow(static_mut_refs)]
+    let (xact_uris, replaced_temp_files) = unsafe {
+        (
+            &mut XACT_TEMP_PARQUET_FILE_URIS,
+            &mut REPLACED_TEMP_PARQUET_FILES,
+        )
+    };
+
+    if committed {
+        for (_, created_by) in xact_uris.iter_mut() {
+            if *created_by == subxact_id {
+                *created_by = parent_subxact_id;
+            }
+        }
+
+        for (_, replaced_by) in replaced_temp_files.iter_mut() {
+            if *replaced_by == subxact_id {
+                *replaced_by = parent_subxact_id;
+            }
+        }
+
+        return;
+    }
+
+    let (subxact_uris, other_uris) = std::mem::take(xact_uris)
+        .into_iter()
+        .partition::<Vec<_>, _>(|(_, created_by)| *created_by == subxact_id);
+
+    *xact_uris = other_uris;
+
+    let (subxact_replaced_temp_files, other_replaced_temp_files) =
+        std::mem::take(replaced_temp_files)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(_, replaced_by)| *replaced_by == subxact_id);
+
+    *replaced_temp_files = other_replaced_temp_files;
+
+    for (uri, _) in &subxact_uris {
+        remove_temp_parquet_file_by_uri(uri);
+    }
+
+    // the files, which are created by the subtransaction, are removed above
+    let subxact_replaced_temp_files = subxact_replaced_temp_files
+        .into_iter()
+        .map(|(replaced_temp_file, _)| replaced_temp_file)
+        .filter(|replaced_temp_file| {
+            !subxact_uris
+                .iter()
+                .any(|(uri, _)| uri == &replaced_temp_file.uri)
+        });
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEMP_PARQUET_FILES.extend(subxact_replaced_temp_files)
+    };
+}
+
+// temp_parquet_file_uri returns the uri of the temp file with the given name.
+pub(crate) fn temp_parquet_file_uri(name: &str) -> Url {
+    #[allow(static_mut_refs)]
+    let temp_file = unsafe { TEMP_PARQUET_FILES.iter().find(|file| file.name == name) };
+
+    match temp_file {
+        Some(temp_file) => temp_file.uri.clone(),
+        None => panic!("temporary parquet file \"{}\" does not exist", name),
+    }
+}
+
+pub(crate) fn is_temp_parquet_file_uri(uri: &Url) -> bool {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEMP_PARQUET_FILES.iter().any(|file| &file.uri == uri)
+    }
+}
+
+pub(crate) fn temp_parquet_files() -> Vec<TempParquetFile> {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEMP_PARQUET_FILES.clone()
+    }
+}
+
+// remove_all_temp_parquet_files removes all temp files of the session, including the ones
+// which are replaced by the current transaction.
+pub(crate) fn remove_all_temp_parquet_files() {
+    #[allow(static_mut_refs)]
+    let replaced_temp_files = unsafe {
+        XACT_TEMP_PARQUET_FILE_URIS.clear();
+        std::mem::take(&mut REPLACED_TEMP_PARQUET_FILES)
+    };
+
+    let replaced_temp_files = replaced_temp_files
+        .into_iter()
+        .map(|(replaced_temp_file, _)| replaced_temp_file);
+
+    for temp_file in temp_parquet_files().into_iter().chain(replaced_temp_files) {
+        remove_temp_parquet_file_by_uri(&temp_file.uri);
+    }
+}
+
+// unregister_temp_parquet_file removes the temp file with the given name from the registry,
+// without removing the file, and returns it.
+fn unregister_temp_parquet_file(name: &str) -> Option<TempParquetFile> {
+    #[allow(static_mut_refs)]
+    unsafe {
+        let temp_file_idx = TEMP_PARQUET_FILES
+            .iter()
+            .position(|file| file.name == name)?;
+
+        Some(TEMP_PARQUET_FILES.remove(temp_file_idx))
+    }
+}
+
+fn remove_temp_parquet_file_by_uri(uri: &Url) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TEMP_PARQUET_FILES.retain(|file| &file.uri != uri)
+    };
+
+    let path = uri_as_string(uri);
+
+    // the file might not be written yet, e.g. COPY TO failed before writing any data
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            pgrx::warning!("failed to remove temporary parquet file {}: {}", path, e);
+        }
+    }
+}
+
+// next_temp_parquet_file_uri returns a new uri under the temp directory of the next temp
+// tablespace, which is configured by temp_tablespaces, or the database's default tablespace.
+pub(crate) fn next_temp_parquet_file_uri() -> Url {
+    let temp_tablespace_oid = unsafe {
+        PrepareTempTablespaces();
+        GetNextTempTableSpace()
+    };
+
+    let temp_tablespace_oid = if temp_tablespace_oid == InvalidOid {
+        unsafe { MyDatabaseTableSpace }
+    } else {
+        temp_tablespace_oid
+    };
+
+    let mut temp_dir = vec![0 as c_char; MAXPGPATH as usize];
+    unsafe { TempTablespacePath(temp_dir.as_mut_ptr(), temp_tablespace_oid) };
+
+    let temp_dir = unsafe {
+        CStr::from_ptr(temp_dir.as_ptr())
+            .to_str()
+            .expect("temp directory is not a valid CString")
+    };
+
+    // temp directory is relative to the data directory
+    let data_dir = unsafe {
+        CStr::from_ptr(DataDir)
+            .to_str()
+            .expect("data directory is not a valid CString")
+    };
+
+    let temp_dir = format!("{}/{}", data_dir, temp_dir);
+
+    std::fs::create_dir_all(&temp_dir).unwrap_or_else(|e| {
+        panic!(
+            "could not create temporary directory \"{}\": {}",
+            temp_dir, e
+        )
+    });
+
+    #[allow(static_mut_refs)]
+    let counter = unsafe {
+        TEMP_PARQUET_FILE_COUNTER += 1;
+        TEMP_PARQUET_FILE_COUNTER
+    };
+
+    let path = format!(
+        "{}/{}{}.pg_parquet.{}.parquet",
+        temp_dir,
+        PG_TEMP_FILE_PREFIX,
+        unsafe { MyProcPid },
+        counter
+    );
+
+    Url::from_file_path(&path).unwrap_or_else(|_| panic!("not a valid file path: {}", path))
+}
+
+fn register_temp_parquet_files_exit_callback() {
+    #[allow(static_mut_refs)]
+    unsafe {
+        if TEMP_PARQUET_FILES_EXIT_CALLBACK_REGISTERED {
+            return;
+        }
+
+        before_shmem_exit(Some(remove_temp_parquet_files_at_exit), Datum::from(0));
+
+        TEMP_PARQUET_FILES_EXIT_CALLBACK_REGISTERED = true;
+    }
+}
+
+#[pg_guard]
+extern "C" fn remove_temp_parquet_files_at_exit(_code: c_int, _arg: Datum) {
+    remove_all_temp_parquet_files();
+}
+
+fn register_temp_parquet_files_subxact_callback() {
+    #[allow(static_mut_refs)]
+    unsafe {
+        if TEMP_PARQUET_FILES_SUBXACT_CALLBACK_REGISTERED {
+            return;
+        }
+
+        RegisterSubXactCallback(
+            Some(end_temp_parquet_files_subxact_callback),
+            std::ptr::null_mut(),
+        );
+
+        TEMP_PARQUET_FILES_SUBXACT_CALLBACK_REGISTERED = true;
+    }
+}
+
+#[pg_guard]
+extern "C" fn end_temp_parquet_files_subxact_callback(
+    event: SubXactEvent::Type,
+    subxact_id: SubTransactionId,
+    parent_subxact_id: SubTransactionId,
+    _arg: *mut c_void,
+) {
+    match event {
+        SubXactEvent::SUBXACT_EVENT_COMMIT_SUB => {
+            end_temp_parquet_files_subxact(true, subxact_id, parent_subxact_id)
+        }
+        SubXactEvent::SUBXACT_EVENT_ABORT_SUB => {
+            end_temp_parquet_files_subxact(false, subxact_id, parent_subxact_id)
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fmt::Display, str::FromStr};
+
+// TimestamptzToTimestamp determines how COPY FROM reads the timestamps with a timezone, i.e.
+// UTC instants, into timestamp columns. They are converted to the local time of the session
+// timezone by default, as Postgres casts timestamptz to timestamp.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum TimestamptzToTimestamp {
+    #[default]
+    Local,
+    Utc,
+    Error,
+}
+
+impl Display for TimestamptzToTimestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimestamptzToTimestamp::Local => write!(f, "local"),
+            TimestamptzToTimestamp::Utc => write!(f, "utc"),
+            TimestamptzToTimestamp::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for TimestamptzToTimestamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(TimestamptzToTimestamp::Local),
+            "utc" => Ok(TimestamptzToTimestamp::Utc),
+            "error" => Ok(TimestamptzToTimestamp::Error),
+            _ => Err(format!(
+                "unrecognized timestamptz_to_timestamp option: {}",
+                s
+            )),
+        }
+    }
+}
+
+// TIMESTAMPTZ_TO_TIMESTAMP is the option of the COPY FROM, whose values are being converted.
+// The timestamp conversions only know the arrow type of the values, hence the reader context
+// keeps the option and sets it before it converts values, since nested COPYs set their own.
+static mut TIMESTAMPTZ_TO_TIMESTAMP: TimestamptzToTimestamp = TimestamptzToTimestamp::Local;
+
+pub(crate) fn start_timestamptz_to_timestamp(timestamptz_to_timestamp: TimestamptzToTimestamp) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TIMESTAMPTZ_TO_TIMESTAMP = timestamptz_to_timestamp
+    };
+}
+
+pub(crate) fn timestamptz_to_timestamp() -> TimestamptzToTimestamp {
+    #[allow(static_mut_refs)]
+    unsafe {
+        TIMESTAMPTZ_TO_TIMESTAMP
+    }
+}
@@ -0,0 +1,47 @@
+use pgrx::pg_sys::{pg_strong_random, MyProcPid};
+use url::Url;
+
+static mut UNIQUE_FILE_SUFFIX_COUNTER: u64 = 0;
+
+// unique_file_suffix returns a suffix, which no other COPY TO generates, e.g. "4242-1-9f86d081".
+// It is made of the pid of the backend, a counter of the backend and a random number, so that
+// the backends of other servers, which write to the same bucket, do not collide either.
+pub(crate) fn unique_file_suffix() -> String {
+    #[allow(static_mut_refs)]
+    let counter = unsafe {
+        UNIQUE_FILE_SUFFIX_COUNTER += 1;
+        UNIQUE_FILE_SUFFIX_COUNTER
+    };
+
+    let mut random = [0u8; 4];
+
+    if !unsafe { pg_strong_random(random.as_mut_ptr() as _, random.len()) } {
+        panic!("could not generate random number");
+    }
+
+    format!(
+        "{}-{}-{:08x}",
+        unsafe { MyProcPid },
+        counter,
+        u32::from_ne_bytes(random)
+    )
+}
+
+// suffixed_file_uri returns the uri with the suffix appended to its file name before the
+// extensions, e.g. "s3://bucket/file-4242-1-9f86d081.parquet" for "s3://bucket/file.parquet", so
+// that the compression is still inferred from the extensions.
+pub(crate) fn suffixed_file_uri(uri: &Url, suffix: &str) -> Url {
+    let path = uri.path().trim_end_matches('/');
+
+    let (dir, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+
+    let file_name = match file_name.split_once('.') {
+        Some((stem, extensions)) => format!("{}-{}.{}", stem, suffix, extensions),
+        None => format!("{}-{}", file_name, suffix),
+    };
+
+    let mut suffixed_uri = uri.clone();
+    suffixed_uri.set_path(&format!("{}/{}", dir, file_name));
+
+    suffixed_uri
+}
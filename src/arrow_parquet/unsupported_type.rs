@@ -0,0 +1,39 @@
+use std::{fmt::Display, str::FromStr};
+
+// PgParquetUnsupportedType decides what COPY TO does with the columns, whose types do not have
+// a parquet counterpart. They are written as text via the type's output function by default.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetUnsupportedType {
+    #[default]
+    Text,
+    Error,
+}
+
+pub(crate) fn all_supported_unsupported_types() -> Vec<PgParquetUnsupportedType> {
+    vec![
+        PgParquetUnsupportedType::Text,
+        PgParquetUnsupportedType::Error,
+    ]
+}
+
+impl Display for PgParquetUnsupportedType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetUnsupportedType::Text => write!(f, "text"),
+            PgParquetUnsupportedType::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for PgParquetUnsupportedType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(PgParquetUnsupportedType::Text),
+            "error" => Ok(PgParquetUnsupportedType::Error),
+            _ => Err(format!("unrecognized unsupported_type: {}", s)),
+        }
+    }
+}
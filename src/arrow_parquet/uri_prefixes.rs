@@ -0,0 +1,270 @@
+use std::{ffi::CStr, path::PathBuf};
+
+use object_store::ObjectStoreScheme;
+use pgrx::{ereport, GucSetting, PgLogLevel, PgSqlErrorCode};
+use url::Url;
+
+use crate::object_store::object_store_cache::resolve_object_store_uri;
+
+use super::uri_utils::redact_uri;
+
+// ALLOWED_URI_PREFIXES is a comma separated list of uri prefixes, e.g. "s3://bucket/dir/*" or
+// "/tmp/exports", which restricts the uris that COPY and the parquet udfs can access. An empty
+// list means no restriction.
+pub(crate) static ALLOWED_URI_PREFIXES: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+// DENIED_URI_PREFIXES is a comma separated list of uri prefixes, which COPY and the parquet
+// udfs cannot access, even if they are allowed by ALLOWED_URI_PREFIXES.
+pub(crate) static DENIED_URI_PREFIXES: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+struct UriPrefix {
+    // prefix as it is given in the list, which is reported in the error message
+    prefix: String,
+    normalized_prefix: String,
+    // a prefix ending with "*" matches any uri starting with it, e.g. "s3://bucket/dir*"
+    // matches "s3://bucket/dir2/file", otherwise only the uri itself and the uris under it
+    wildcard: bool,
+}
+
+// ensure_uri_prefixes_allow_uri throws an error if the uri matches a prefix of
+// "pg_parquet.denied_uri_prefixes", or it does not match any prefix of
+// "pg_parquet.allowed_uri_prefixes". The denied prefixes take precedence.
+pub(crate) fn ensure_uri_prefixes_allow_uri(uri: &Url) {
+    let normalized_uri = normalize_uri(uri);
+
+    let denied_prefixes =
+        parse_uri_prefixes(&DENIED_URI_PREFIXES, "pg_parquet.denied_uri_prefixes");
+
+    if let Some(denied_prefix) = denied_prefixes
+        .iter()
+        .find(|prefix| uri_prefix_matches(prefix, &normalized_uri))
+    {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            format!(
+                "uri {} is denied by pg_parquet.denied_uri_prefixes",
+                redact_uri(uri)
+            ),
+            format!(
+                "The uri matches the denied prefix \"{}\".",
+                denied_prefix.prefix
+            ),
+        );
+    }
+
+    let allowed_prefixes =
+        parse_uri_prefixes(&ALLOWED_URI_PREFIXES, "pg_parquet.allowed_uri_prefixes");
+
+    if allowed_prefixes.is_empty() {
+        return;
+    }
+
+    if !allowed_prefixes
+        .iter()
+        .any(|prefix| uri_prefix_matches(prefix, &normalized_uri))
+    {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            format!(
+                "uri {} is not allowed by pg_parquet.allowed_uri_prefixes",
+                redact_uri(uri)
+            ),
+            "The uri does not match any of the allowed prefixes.",
+        );
+    }
+}
+
+fn parse_uri_prefixes(
+    setting: &GucSetting<Option<&'static CStr>>,
+    guc_name: &str,
+) -> Vec<UriPrefix> {
+    let Some(prefixes) = setting.get() else {
+        return vec![];
+    };
+
+    prefixes
+        .to_str()
+        .unwrap_or_default()
+        .split(',')
+        .map(|prefix| prefix.trim())
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| parse_uri_prefix(prefix, guc_name))
+        .collect()
+}
+
+fn parse_uri_prefix(prefix: &str, guc_name: &str) -> UriPrefix {
+    let (prefix_without_wildcard, wildcard) = match prefix.strip_suffix('*') {
+        Some(prefix_without_wildcard) => (prefix_without_wildcard, true),
+        None => (prefix, false),
+    };
+
+    let prefix_uri = if prefix_without_wildcard.contains("://") {
+        Url::parse(prefix_without_wildcard).ok()
+    } else {
+        // local file path
+        Url::from_file_path(prefix_without_wildcard).ok()
+    };
+
+    let Some(prefix_uri) = prefix_uri else {
+        panic!("{} contains an invalid uri prefix \"{}\"", guc_name, prefix);
+    };
+
+    UriPrefix {
+        prefix: prefix.to_string(),
+        normalized_prefix: normalize_uri(&prefix_uri),
+        wildcard,
+    }
+}
+
+fn uri_prefix_matches(prefix: &UriPrefix, normalized_uri: &str) -> bool {
+    let normalized_prefix = &prefix.normalized_prefix;
+
+    let Some(rest) = normalized_uri.strip_prefix(normalized_prefix.as_str()) else {
+        return false;
+    };
+
+    prefix.wildcard || rest.is_empty() || rest.starts_with('/') || normalized_prefix.ends_with('/')
+}
+
+// normalize_uri returns the scheme, bucket and path of the object, which the uri resolves to,
+// e.g. "s3://bucket/dir/file" for "s3a://bucket//dir/./file?query" and
+// "https://bucket.s3.amazonaws.com/dir/file", so that the uri prefixes match all aliases of
+// the same object and cannot be circumvented by "." or ".." segments.
+fn normalize_uri(uri: &Url) -> String {
+    let mut normalized_uri = uri.clone();
+    normalized_uri.set_query(None);
+    normalized_uri.set_fragment(None);
+
+    // symlinks of local paths are resolved, so that they cannot escape a local prefix
+    if normalized_uri.scheme() == "file" {
+        if let Some(canonical_uri) = normalized_uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| Url::from_file_path(canonicalize_local_path(&path)).ok())
+        {
+            normalized_uri = canonical_uri;
+        }
+    }
+
+    let normalized_path = normalize_uri_path(normalized_uri.path());
+    normalized_uri.set_path(&normalized_path);
+
+    if let Some((scheme, bucket, path)) = resolve_object_store_uri(&normalized_uri) {
+        let scheme = match scheme {
+            ObjectStoreScheme::AmazonS3 => "s3",
+            _ => "az",
+        };
+
+        if path.as_ref().is_empty() {
+            return format!("{}://{}", scheme, bucket);
+        }
+
+        let trailing_slash = if normalized_path.ends_with('/') {
+            "/"
+        } else {
+            ""
+        };
+
+        return format!("{}://{}/{}{}", scheme, bucket, path, trailing_slash);
+    }
+
+    let mut normalized_uri_str = format!("{}://", normalized_uri.scheme());
+
+    // username of abfs(s):// uris is the container, e.g. abfss://{container}@{account}.dfs...
+    if matches!(normalized_uri.scheme(), "abfs" | "abfss") && !normalized_uri.username().is_empty()
+    {
+        normalized_uri_str.push_str(normalized_uri.username());
+        normalized_uri_str.push('@');
+    }
+
+    normalized_uri_str.push_str(normalized_uri.host_str().unwrap_or_default());
+
+    if let Some(port) = normalized_uri.port() {
+        normalized_uri_str.push_str(&format!(":{}", port));
+    }
+
+    if normalized_path != "/" {
+        normalized_uri_str.push_str(&normalized_path);
+    }
+
+    normalized_uri_str
+}
+
+// normalize_uri_path decodes the percent-encoded unreserved characters of the path, e.g.
+// "%70ii" to "pii", and removes the empty and "." segments, and the segments followed by "..".
+fn normalize_uri_path(path: &str) -> String {
+    let mut segments: Vec<String> = vec![];
+
+    for segment in path.split('/') {
+        let segment = decode_unreserved_chars(segment);
+
+        match segment.as_str() {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut normalized_path = String::new();
+
+    for segment in &segments {
+        normalized_path.push('/');
+        normalized_path.push_str(segment);
+    }
+
+    if normalized_path.is_empty() || path.ends_with('/') {
+        normalized_path.push('/');
+    }
+
+    normalized_path
+}
+
+// decode_unreserved_chars decodes the percent-encoded unreserved characters (RFC 3986) of the
+// path segment, which identify the same object as their decoded form. The other
+// percent-encoded characters, e.g. "%2F", are kept as they are.
+fn decode_unreserved_chars(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            let byte = u8::from_str_radix(hex, 16).unwrap_or_default();
+
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| segment.to_string())
+}
+
+// canonicalize_local_path resolves the symlinks of the longest existing ancestor of the path,
+// since the file of an export does not exist yet.
+fn canonicalize_local_path(path: &std::path::Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(canonical_ancestor) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(path);
+            return canonical_ancestor.join(rest);
+        }
+    }
+
+    path.to_path_buf()
+}
@@ -1,38 +1,139 @@
 use std::{panic, sync::Arc};
 
 use arrow::datatypes::SchemaRef;
+use futures::StreamExt;
+use object_store::{buffered::BufWriter, ObjectMeta, ObjectStore, PutMode, PutOptions, PutPayload};
 use parquet::{
     arrow::{
-        arrow_to_parquet_schema,
+        arrow_reader::{ArrowReaderMetadata, ArrowReaderOptions},
+        arrow_writer::ArrowWriterOptions,
         async_reader::{ParquetObjectReader, ParquetRecordBatchStream},
-        async_writer::ParquetObjectWriter,
-        AsyncArrowWriter, ParquetRecordBatchStreamBuilder,
+        async_writer::AsyncFileWriter,
+        AsyncArrowWriter, ParquetRecordBatchStreamBuilder, ProjectionMask,
     },
+    errors::ParquetError,
     file::{metadata::ParquetMetaData, properties::WriterProperties},
     schema::types::SchemaDescriptor,
 };
 use pgrx::{
     ereport,
     pg_sys::{get_role_oid, has_privs_of_role, superuser, AsPgCStr, GetUserId},
+    GucSetting,
 };
 use url::Url;
 
 use crate::{
-    arrow_parquet::parquet_writer::DEFAULT_ROW_GROUP_SIZE,
-    object_store::object_store_cache::get_or_create_object_store, PG_BACKEND_TOKIO_RUNTIME,
+    arrow_parquet::{
+        abortable_object_writer::{AbortableObjectWriter, ObjectWriterAbortHandle},
+        page_pruning::{FilePagePredicate, PageSelection},
+        parallel_decoder::ParallelRowGroupDecoder,
+        parquet_writer::DEFAULT_ROW_GROUP_SIZE,
+        row_group_selection::{RowGroupSelection, SelectedRowGroups},
+        stream_writer::StreamWriter,
+        temp_files::is_temp_parquet_file_uri,
+        uri_prefixes::ensure_uri_prefixes_allow_uri,
+    },
+    object_store::{
+        aws::BucketCredentials,
+        object_store_cache::{create_object_store_with_options, get_or_create_object_store},
+        written_objects::{forget_written_object, head_object},
+    },
+    pgrx_utils::block_on_interruptible,
+    PG_BACKEND_TOKIO_RUNTIME,
 };
 
+// UPLOAD_PART_SIZE is the size (in kB) of the parts, in which COPY TO uploads the file.
+pub(crate) static UPLOAD_PART_SIZE: GucSetting<i32> = GucSetting::<i32>::new(10 * 1024);
+
+// UPLOAD_PART_CONCURRENCY is the max number of parts of a file, which COPY TO uploads
+// concurrently. A new part waits until an upload completes, so at most
+// upload_part_concurrency parts are buffered at a time.
+pub(crate) static UPLOAD_PART_CONCURRENCY: GucSetting<i32> = GucSetting::<i32>::new(8);
+
+// MAX_UPLOAD_BUFFER_SIZE is the max size (in kB) of the parts, which can be in flight at a time,
+// i.e. upload_part_concurrency * upload_part_size, and upload_concurrency times that with
+// max_rows_per_file.
+pub(crate) const MAX_UPLOAD_BUFFER_SIZE: i64 = 5 * 1024 * 1024;
+
 const PARQUET_OBJECT_STORE_READ_ROLE: &str = "parquet_object_store_read";
 const PARQUET_OBJECT_STORE_WRITE_ROLE: &str = "parquet_object_store_write";
 
+// upload_buffer_size returns the max size (in kB) of the parts of a file, which are in flight at
+// a time, i.e. upload_part_concurrency * upload_part_size.
+pub(crate) fn upload_buffer_size() -> i64 {
+    UPLOAD_PART_CONCURRENCY.get() as i64 * UPLOAD_PART_SIZE.get() as i64
+}
+
 pub(crate) fn parse_uri(uri: &str) -> Url {
+    if uri.trim().is_empty() {
+        panic!("uri is empty");
+    }
+
     if !uri.contains("://") {
         // local file
         return Url::from_file_path(uri)
             .unwrap_or_else(|_| panic!("not a valid file path: {}", uri));
     }
 
-    Url::parse(uri).unwrap_or_else(|e| panic!("{}", e))
+    let mut uri = Url::parse(uri).unwrap_or_else(|e| match e {
+        url::ParseError::EmptyHost => panic!("uri {} is missing the host", uri_scheme_prefix(uri)),
+        e => panic!("{}", e),
+    });
+
+    ensure_uri_has_bucket(&uri);
+
+    collapse_duplicate_slashes(&mut uri);
+
+    uri
+}
+
+// ensure_uri_has_bucket throws an error if an object store uri does not have a bucket or
+// container, e.g. "s3://" or "az://", instead of failing later with a confusing error.
+fn ensure_uri_has_bucket(uri: &Url) {
+    let missing = match uri.scheme() {
+        "s3" | "s3a" | "gs" => "bucket",
+        "az" | "azure" | "adl" | "abfs" | "abfss" => "container",
+        _ => return,
+    };
+
+    if uri.host_str().unwrap_or_default().is_empty() {
+        panic!(
+            "uri {} is missing the {} name, e.g. {}://{}/path/to/file.parquet",
+            redact_uri(uri),
+            missing,
+            uri.scheme(),
+            missing
+        );
+    }
+}
+
+// uri_scheme_prefix returns e.g. "https://" for "https:///file", which does not contain secrets
+fn uri_scheme_prefix(uri: &str) -> &str {
+    uri.split_once("://")
+        .map(|(scheme, _)| &uri[..scheme.len() + 3])
+        .unwrap_or(uri)
+}
+
+// collapse_duplicate_slashes removes the empty segments from the path of an object store uri,
+// e.g. "s3://bucket//dir///file" becomes "s3://bucket/dir/file", since object stores would
+// otherwise reject the path or create objects with empty path segments. Uris with a query are
+// kept as is, since the path of a presigned url or SAS token is a part of its signature.
+fn collapse_duplicate_slashes(uri: &mut Url) {
+    if uri.scheme() == "file" || uri.query().is_some() || !uri.path().contains("//") {
+        return;
+    }
+
+    let mut path = String::with_capacity(uri.path().len());
+
+    for c in uri.path().chars() {
+        if c == '/' && path.ends_with('/') {
+            continue;
+        }
+
+        path.push(c);
+    }
+
+    uri.set_path(&path);
 }
 
 pub(crate) fn uri_as_string(uri: &Url) -> String {
@@ -48,80 +149,732 @@ pub(crate) fn uri_as_string(uri: &Url) -> String {
     uri.to_string()
 }
 
-pub(crate) fn parquet_schema_from_uri(uri: &Url) -> SchemaDescriptor {
-    let parquet_reader = parquet_reader_from_uri(uri);
+// SECRET_URI_QUERY_PARAMS are the query parameters of presigned urls and SAS tokens, which
+// carry the signature or the credentials.
+const SECRET_URI_QUERY_PARAMS: &[&str] = &[
+    "signature",
+    "x-amz-signature",
+    "x-amz-credential",
+    "x-amz-security-token",
+    "sig",
+];
 
-    let arrow_schema = parquet_reader.schema();
+// SENSITIVE_URI_QUERY_PARAMS are the query parameters, whose values are redacted from the uris
+// in errors and outputs of the extension. SAS token's expiry, permissions and version are
+// redacted in addition to its signature.
+const SENSITIVE_URI_QUERY_PARAMS: &[&str] = &[
+    "signature",
+    "x-amz-signature",
+    "x-amz-credential",
+    "x-amz-security-token",
+    "sig",
+    "se",
+    "sp",
+    "sv",
+];
+
+const REDACTED_VALUE: &str = "***";
+
+// redact_uri returns the uri as a string, where the userinfo and the values of sensitive
+// query parameters are replaced with "***". All errors, warnings and outputs, which contain
+// a uri, should use it instead of the uri itself.
+pub(crate) fn redact_uri(uri: &Url) -> String {
+    let mut redacted_uri = uri.clone();
+
+    // username of abfs(s):// uris is the container, e.g. abfss://{container}@{account}.dfs...
+    let username_is_container = matches!(uri.scheme(), "abfs" | "abfss");
+
+    if !redacted_uri.username().is_empty() && !username_is_container {
+        redacted_uri
+            .set_username(REDACTED_VALUE)
+            .unwrap_or_else(|_| panic!("failed to redact uri"));
+    }
+
+    if redacted_uri.password().is_some() {
+        redacted_uri
+            .set_password(Some(REDACTED_VALUE))
+            .unwrap_or_else(|_| panic!("failed to redact uri"));
+    }
+
+    if let Some(query) = uri.query() {
+        let redacted_query = query
+            .split('&')
+            .map(|param| match param.split_once('=') {
+                Some((key, _)) if is_sensitive_uri_query_param(key) => {
+                    format!("{}={}", key, REDACTED_VALUE)
+                }
+                _ => param.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+
+        redacted_uri.set_query(Some(&redacted_query));
+    }
+
+    uri_as_string(&redacted_uri)
+}
+
+// redact_uri_secrets replaces the secrets of the uri, which appear in the message, e.g. an
+// error message of the object store, with "***".
+pub(crate) fn redact_uri_secrets(message: &str, uri: &Url) -> String {
+    let mut message = message.replace(uri.as_str(), &redact_uri(uri));
+
+    for secret in uri_secrets(uri) {
+        message = message.replace(&secret, REDACTED_VALUE);
+    }
+
+    message
+}
+
+// uri_secrets returns the password and the values of the secret query parameters of the uri,
+// both percent decoded and as they appear in the uri. Other sensitive values, e.g. the expiry
+// of a SAS token, are too short to be replaced in arbitrary messages.
+fn uri_secrets(uri: &Url) -> Vec<String> {
+    let mut secrets = vec![];
+
+    if let Some(password) = uri.password() {
+        secrets.push(password.to_string());
+    }
+
+    for (key, value) in uri.query_pairs() {
+        if SECRET_URI_QUERY_PARAMS.contains(&key.to_lowercase().as_str()) {
+            secrets.push(value.to_string());
+        }
+    }
+
+    for param in uri.query().unwrap_or_default().split('&') {
+        if let Some((key, value)) = param.split_once('=') {
+            if SECRET_URI_QUERY_PARAMS.contains(&key.to_lowercase().as_str()) {
+                secrets.push(value.to_string());
+            }
+        }
+    }
+
+    secrets.retain(|secret| !secret.is_empty());
+
+    secrets
+}
 
-    arrow_to_parquet_schema(arrow_schema).unwrap_or_else(|e| panic!("{}", e))
+fn is_sensitive_uri_query_param(key: &str) -> bool {
+    SENSITIVE_URI_QUERY_PARAMS.contains(&key.to_lowercase().as_str())
 }
 
-pub(crate) fn parquet_metadata_from_uri(uri: &Url) -> Arc<ParquetMetaData> {
+// parquet_metadata_from_uri returns the metadata of the file. The object store of the
+// session is used unless the options of the parquet function override its configuration.
+pub(crate) fn parquet_metadata_from_uri(
+    uri: &Url,
+    options: Option<&BucketCredentials>,
+) -> Arc<ParquetMetaData> {
+    let page_index = false;
+    load_parquet_metadata(uri, page_index, options)
+}
+
+// parquet_metadata_with_page_index_from_uri returns the metadata of the file with its
+// column and offset indexes, which describe the pages of each column chunk.
+pub(crate) fn parquet_metadata_with_page_index_from_uri(
+    uri: &Url,
+    options: Option<&BucketCredentials>,
+) -> Arc<ParquetMetaData> {
+    let page_index = true;
+    load_parquet_metadata(uri, page_index, options)
+}
+
+fn load_parquet_metadata(
+    uri: &Url,
+    page_index: bool,
+    options: Option<&BucketCredentials>,
+) -> Arc<ParquetMetaData> {
     let copy_from = true;
-    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+    let (parquet_object_store, location) = match options {
+        Some(options) => create_object_store_with_options(uri, options),
+        None => get_or_create_object_store(uri, copy_from),
+    };
 
-    PG_BACKEND_TOKIO_RUNTIME.block_on(async {
-        let object_store_meta = parquet_object_store
-            .head(&location)
+    block_on_interruptible(async {
+        let object_store_meta = head_object(&parquet_object_store, &location, uri)
             .await
             .unwrap_or_else(|e| {
-                panic!("failed to get object store metadata for uri {}: {}", uri, e)
+                panic!(
+                    "failed to get object store metadata for uri {}: {}",
+                    redact_uri(uri),
+                    redact_uri_secrets(&e.to_string(), uri)
+                )
             });
 
         let parquet_object_reader =
             ParquetObjectReader::new(parquet_object_store, object_store_meta);
 
-        let builder = ParquetRecordBatchStreamBuilder::new(parquet_object_reader)
-            .await
-            .unwrap_or_else(|e| panic!("{}", e));
+        let options = ArrowReaderOptions::new().with_page_index(page_index);
+
+        let builder =
+            ParquetRecordBatchStreamBuilder::new_with_options(parquet_object_reader, options)
+                .await
+                .unwrap_or_else(|e| panic!("{}", redact_uri_secrets(&e.to_string(), uri)));
 
         builder.metadata().to_owned()
     })
 }
 
-pub(crate) fn parquet_reader_from_uri(uri: &Url) -> ParquetRecordBatchStream<ParquetObjectReader> {
+// PARQUET_FOOTER_TAIL_SIZE is the size of the tail of the file, i.e. the length of the footer
+// metadata followed by the magic number
+const PARQUET_FOOTER_TAIL_SIZE: usize = 8;
+
+const PARQUET_ENCRYPTED_FOOTER_MAGIC: &[u8] = b"PARE";
+
+// ParquetFooterTail is read from the tail of the file. The footer size includes the footer
+// metadata and the tail itself. The parquet crate cannot read encrypted footers, hence their
+// metadata is not read at all.
+pub(crate) struct ParquetFooterTail {
+    pub(crate) footer_size: i64,
+    pub(crate) encrypted: bool,
+}
+
+// parquet_footer_tail_from_uri reads the last bytes of the file, which tell the length of its
+// footer metadata and whether the footer is encrypted.
+pub(crate) fn parquet_footer_tail_from_uri(
+    uri: &Url,
+    options: Option<&BucketCredentials>,
+) -> ParquetFooterTail {
+    let copy_from = true;
+    let (parquet_object_store, location) = match options {
+        Some(options) => create_object_store_with_options(uri, options),
+        None => get_or_create_object_store(uri, copy_from),
+    };
+
+    let tail = block_on_interruptible(async {
+        let object_store_meta = head_object(&parquet_object_store, &location, uri)
+            .await
+            .unwrap_or_else(|e| {
+                panic!(
+                    "failed to get object store metadata for uri {}: {}",
+                    redact_uri(uri),
+                    redact_uri_secrets(&e.to_string(), uri)
+                )
+            });
+
+        if object_store_meta.size < PARQUET_FOOTER_TAIL_SIZE {
+            panic!(
+                "file at uri {} is too small to be a parquet file",
+                redact_uri(uri)
+            );
+        }
+
+        let range = object_store_meta.size - PARQUET_FOOTER_TAIL_SIZE..object_store_meta.size;
+
+        parquet_object_store
+            .get_range(&location, range)
+            .await
+            .unwrap_or_else(|e| panic!("{}", redact_uri_secrets(&e.to_string(), uri)))
+    });
+
+    let metadata_len = i32::from_le_bytes(tail[0..4].try_into().expect("4 bytes")) as i64;
+    let encrypted = &tail[4..] == PARQUET_ENCRYPTED_FOOTER_MAGIC;
+
+    ParquetFooterTail {
+        footer_size: metadata_len + PARQUET_FOOTER_TAIL_SIZE as i64,
+        encrypted,
+    }
+}
+
+// ParquetFileFooter is the object metadata and the footer of the file, which COPY FROM reads
+// once to decide which columns of the file it reads.
+pub(crate) struct ParquetFileFooter {
+    object_store_meta: ObjectMeta,
+    metadata: ArrowReaderMetadata,
+}
+
+impl ParquetFileFooter {
+    // schema returns the arrow schema of the file with its key/value metadata
+    pub(crate) fn schema(&self) -> &SchemaRef {
+        self.metadata.schema()
+    }
+
+    pub(crate) fn schema_descr(&self) -> &SchemaDescriptor {
+        self.metadata.metadata().file_metadata().schema_descr()
+    }
+
+    fn row_group_row_counts(&self) -> Vec<usize> {
+        self.metadata
+            .metadata()
+            .row_groups()
+            .iter()
+            .map(|row_group| row_group.num_rows() as usize)
+            .collect()
+    }
+}
+
+// parquet_file_footer_from_uri reads the footer of the file, from which its record batch
+// stream or parallel decoder is created. The page index is read with "page_index", when
+// pages might be skipped by the WHERE clause.
+pub(crate) fn parquet_file_footer_from_uri(uri: &Url, page_index: bool) -> ParquetFileFooter {
     let copy_from = true;
     let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
 
-    PG_BACKEND_TOKIO_RUNTIME.block_on(async {
-        let object_store_meta = parquet_object_store
-            .head(&location)
+    block_on_interruptible(async {
+        let object_store_meta = head_object(&parquet_object_store, &location, uri)
             .await
             .unwrap_or_else(|e| {
-                panic!("failed to get object store metadata for uri {}: {}", uri, e)
+                panic!(
+                    "failed to get object store metadata for uri {}: {}",
+                    redact_uri(uri),
+                    redact_uri_secrets(&e.to_string(), uri)
+                )
             });
 
-        let parquet_object_reader =
-            ParquetObjectReader::new(parquet_object_store, object_store_meta);
+        let mut parquet_object_reader =
+            ParquetObjectReader::new(parquet_object_store, object_store_meta.clone());
+
+        // arrow schema inference always applies the backward compatibility rules of the
+        // parquet format for LIST, so legacy 2-level lists (e.g. written by Hive) are read
+        // as list fields whose element is named "array" or "<list>_tuple" instead of "element".
+        // The arrow schema embedded by COPY TO takes precedence over the inferred types and
+        // keeps the field metadata, and files without it are read with the inferred schema.
+        let options = ArrowReaderOptions::new()
+            .with_skip_arrow_metadata(false)
+            .with_page_index(page_index);
 
-        let builder = ParquetRecordBatchStreamBuilder::new(parquet_object_reader)
+        let metadata = ArrowReaderMetadata::load_async(&mut parquet_object_reader, options)
             .await
-            .unwrap_or_else(|e| panic!("{}", e));
+            .unwrap_or_else(|e| panic!("{}", redact_uri_secrets(&e.to_string(), uri)));
 
-        pgrx::debug2!("Converted arrow schema is: {}", builder.schema());
+        pgrx::debug2!("Converted arrow schema is: {}", metadata.schema());
 
-        builder
-            .with_batch_size(DEFAULT_ROW_GROUP_SIZE as usize)
-            .build()
-            .unwrap_or_else(|e| panic!("{}", e))
+        ParquetFileFooter {
+            object_store_meta,
+            metadata,
+        }
     })
 }
 
+// ParquetFileValidation is the result of parquet.validate. The error of the file is returned
+// instead of raised, and the counts are not known when its footer cannot be read.
+pub(crate) struct ParquetFileValidation {
+    pub(crate) num_rows: Option<i64>,
+    pub(crate) num_row_groups: Option<i32>,
+    pub(crate) error: Option<String>,
+}
+
+// validate_parquet_file_from_uri reads the footer of the file, and decodes all of its row groups
+// into record batches with "deep", without converting them to datums.
+pub(crate) fn validate_parquet_file_from_uri(uri: &Url, deep: bool) -> ParquetFileValidation {
+    let copy_from = true;
+    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+
+    let validation = block_on_interruptible(async {
+        let object_store_meta = match head_object(&parquet_object_store, &location, uri).await {
+            Ok(object_store_meta) => object_store_meta,
+            Err(e) => {
+                return ParquetFileValidation {
+                    num_rows: None,
+                    num_row_groups: None,
+                    error: Some(format!("failed to get object store metadata: {}", e)),
+                }
+            }
+        };
+
+        let mut parquet_object_reader =
+            ParquetObjectReader::new(parquet_object_store, object_store_meta);
+
+        let metadata = match ArrowReaderMetadata::load_async(
+            &mut parquet_object_reader,
+            ArrowReaderOptions::new(),
+        )
+        .await
+        {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                return ParquetFileValidation {
+                    num_rows: None,
+                    num_row_groups: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+
+        let error = if deep {
+            decode_parquet_row_groups(&parquet_object_reader, &metadata)
+                .await
+                .err()
+        } else {
+            None
+        };
+
+        ParquetFileValidation {
+            num_rows: Some(metadata.metadata().file_metadata().num_rows()),
+            num_row_groups: Some(metadata.metadata().num_row_groups() as i32),
+            error,
+        }
+    });
+
+    ParquetFileValidation {
+        error: validation
+            .error
+            .map(|error| redact_uri_secrets(&error, uri)),
+        ..validation
+    }
+}
+
+// decode_parquet_row_groups decodes the row groups of the file one by one. The columns of the
+// first row group, which cannot be decoded, are decoded one by one to find the failing column.
+async fn decode_parquet_row_groups(
+    parquet_object_reader: &ParquetObjectReader,
+    metadata: &ArrowReaderMetadata,
+) -> Result<(), String> {
+    let schema_descr = metadata.metadata().file_metadata().schema_descr();
+
+    for (row_group_idx, row_group) in metadata.metadata().row_groups().iter().enumerate() {
+        let error = match decode_parquet_row_group(
+            parquet_object_reader,
+            metadata,
+            row_group_idx,
+            ProjectionMask::all(),
+        )
+        .await
+        {
+            Ok(num_rows) if num_rows == row_group.num_rows() as usize => continue,
+            Ok(num_rows) => {
+                return Err(format!(
+                    "row group {} has {} rows, but {} rows are decoded",
+                    row_group_idx,
+                    row_group.num_rows(),
+                    num_rows
+                ))
+            }
+            Err(e) => e,
+        };
+
+        for column_idx in 0..schema_descr.num_columns() {
+            let projection = ProjectionMask::leaves(schema_descr, [column_idx]);
+
+            if let Err(e) =
+                decode_parquet_row_group(parquet_object_reader, metadata, row_group_idx, projection)
+                    .await
+            {
+                return Err(format!(
+                    "row group {} column \"{}\": {}",
+                    row_group_idx,
+                    schema_descr.column(column_idx).path().string(),
+                    e
+                ));
+            }
+        }
+
+        return Err(format!("row group {}: {}", row_group_idx, error));
+    }
+
+    Ok(())
+}
+
+// decode_parquet_row_group decodes the projected columns of the row group, and returns the
+// number of decoded rows.
+async fn decode_parquet_row_group(
+    parquet_object_reader: &ParquetObjectReader,
+    metadata: &ArrowReaderMetadata,
+    row_group_idx: usize,
+    projection: ProjectionMask,
+) -> Result<usize, ParquetError> {
+    let mut parquet_reader = ParquetRecordBatchStreamBuilder::new_with_metadata(
+        parquet_object_reader.clone(),
+        metadata.clone(),
+    )
+    .with_projection(projection)
+    .with_row_groups(vec![row_group_idx])
+    .build()?;
+
+    let mut num_rows = 0;
+
+    while let Some(record_batch) = parquet_reader.next().await {
+        num_rows += record_batch?.num_rows();
+    }
+
+    Ok(num_rows)
+}
+
+// parquet_reader_from_uri returns the record batch stream of the projected columns of the file.
+pub(crate) fn parquet_reader_from_uri(
+    uri: &Url,
+    file_footer: ParquetFileFooter,
+    projection: ProjectionMask,
+    row_group_selection: &RowGroupSelection,
+    page_predicates: &[FilePagePredicate],
+) -> (
+    ParquetRecordBatchStream<ParquetObjectReader>,
+    SelectedRowGroups,
+    Option<PageSelection>,
+) {
+    let copy_from = true;
+    let (parquet_object_store, _location) = get_or_create_object_store(uri, copy_from);
+
+    let selected_row_groups = row_group_selection.select_row_groups(
+        uri,
+        file_footer.object_store_meta.e_tag.as_deref(),
+        file_footer.row_group_row_counts(),
+    );
+
+    let page_selection = PageSelection::new(
+        file_footer.metadata.metadata(),
+        selected_row_groups.row_groups(),
+        page_predicates,
+    );
+
+    let parquet_object_reader =
+        ParquetObjectReader::new(parquet_object_store, file_footer.object_store_meta);
+
+    let mut parquet_reader_builder = ParquetRecordBatchStreamBuilder::new_with_metadata(
+        parquet_object_reader,
+        file_footer.metadata,
+    )
+    .with_projection(projection)
+    .with_batch_size(DEFAULT_ROW_GROUP_SIZE as usize)
+    .with_row_groups(selected_row_groups.row_groups().collect());
+
+    // only the pages of the selected rows are fetched
+    if let Some(page_selection) = &page_selection {
+        parquet_reader_builder =
+            parquet_reader_builder.with_row_selection(page_selection.row_selection());
+    }
+
+    let parquet_reader = parquet_reader_builder
+        .build()
+        .unwrap_or_else(|e| panic!("{}", redact_uri_secrets(&e.to_string(), uri)));
+
+    (parquet_reader, selected_row_groups, page_selection)
+}
+
+// parallel_row_group_decoder_from_uri returns the decoder of the projected columns of the file.
+pub(crate) fn parallel_row_group_decoder_from_uri(
+    uri: &Url,
+    file_footer: ParquetFileFooter,
+    projection: ProjectionMask,
+    row_group_selection: &RowGroupSelection,
+    page_predicates: &[FilePagePredicate],
+    max_decode_threads: usize,
+) -> (
+    ParallelRowGroupDecoder,
+    SelectedRowGroups,
+    Option<PageSelection>,
+) {
+    let copy_from = true;
+    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+
+    let selected_row_groups = row_group_selection.select_row_groups(
+        uri,
+        file_footer.object_store_meta.e_tag.as_deref(),
+        file_footer.row_group_row_counts(),
+    );
+
+    let page_selection = PageSelection::new(
+        file_footer.metadata.metadata(),
+        selected_row_groups.row_groups(),
+        page_predicates,
+    );
+
+    let decoder = ParallelRowGroupDecoder::new(
+        parquet_object_store,
+        location,
+        file_footer.object_store_meta.size as u64,
+        file_footer.metadata,
+        projection,
+        selected_row_groups.row_groups(),
+        page_selection
+            .as_ref()
+            .map(|page_selection| page_selection.row_group_selections()),
+        DEFAULT_ROW_GROUP_SIZE as usize,
+        max_decode_threads,
+    );
+
+    (decoder, selected_row_groups, page_selection)
+}
+
+// parquet_writer_from_uri returns the parquet writer of the uri, and the handle to abort
+// its upload.
 pub(crate) fn parquet_writer_from_uri(
     uri: &Url,
     arrow_schema: SchemaRef,
     writer_props: WriterProperties,
-) -> AsyncArrowWriter<ParquetObjectWriter> {
+) -> (
+    AsyncArrowWriter<Box<dyn AsyncFileWriter>>,
+    ObjectWriterAbortHandle,
+) {
     let copy_from = false;
     let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
 
-    let parquet_object_writer = ParquetObjectWriter::new(parquet_object_store, location);
+    // the object is uploaded in parts of upload_part_size bytes, which bounds the memory
+    // used for buffering the encoded row groups before they are uploaded
+    let upload_part_size = UPLOAD_PART_SIZE.get() as usize * 1024;
+
+    let upload_part_concurrency = UPLOAD_PART_CONCURRENCY.get();
+
+    if upload_buffer_size() > MAX_UPLOAD_BUFFER_SIZE {
+        ereport!(
+            pgrx::PgLogLevel::ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "pg_parquet.upload_part_concurrency * pg_parquet.upload_part_size must not exceed 5GB",
+            "Lower pg_parquet.upload_part_concurrency or pg_parquet.upload_part_size.",
+        );
+    }
 
-    AsyncArrowWriter::try_new(parquet_object_writer, arrow_schema, Some(writer_props))
-        .unwrap_or_else(|e| panic!("failed to create parquet writer for uri {}: {}", uri, e))
+    let buf_writer = BufWriter::with_capacity(parquet_object_store, location, upload_part_size)
+        .with_max_concurrency(upload_part_concurrency as usize);
+
+    let parquet_object_writer = AbortableObjectWriter::new(buf_writer, uri);
+
+    let abort_handle = parquet_object_writer.abort_handle();
+
+    let parquet_writer = parquet_writer_from_file_writer(
+        Box::new(parquet_object_writer),
+        arrow_schema,
+        writer_props,
+    )
+    .unwrap_or_else(|e| {
+        panic!(
+            "failed to create parquet writer for uri {}: {}",
+            redact_uri(uri),
+            redact_uri_secrets(&e.to_string(), uri)
+        )
+    });
+
+    (parquet_writer, abort_handle)
+}
+
+// parquet_writer_from_stream creates the writer of the parquet file, which passes its bytes to
+// the callbacks of the stream.
+pub(crate) fn parquet_writer_from_stream(
+    stream_writer: StreamWriter,
+    arrow_schema: SchemaRef,
+    writer_props: WriterProperties,
+) -> AsyncArrowWriter<Box<dyn AsyncFileWriter>> {
+    parquet_writer_from_file_writer(Box::new(stream_writer), arrow_schema, writer_props)
+        .unwrap_or_else(|e| panic!("failed to create parquet writer for stream: {}", e))
+}
+
+fn parquet_writer_from_file_writer(
+    file_writer: Box<dyn AsyncFileWriter>,
+    arrow_schema: SchemaRef,
+    writer_props: WriterProperties,
+) -> Result<AsyncArrowWriter<Box<dyn AsyncFileWriter>>, ParquetError> {
+    // the arrow schema is embedded in the footer under the "ARROW:schema" key, since the field
+    // metadata, e.g. the length of char(n) or the extension types, cannot be kept by parquet
+    let writer_options = ArrowWriterOptions::new()
+        .with_properties(writer_props)
+        .with_skip_arrow_metadata(false);
+
+    AsyncArrowWriter::try_new_with_options(file_writer, arrow_schema, writer_options)
+}
+
+// delete_uri removes the object at the given uri. It only warns when the object cannot be removed.
+// An object, which does not exist, e.g. an aborted upload, is not an error.
+pub(crate) fn delete_uri(uri: &Url) {
+    let copy_from = false;
+    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+
+    forget_written_object(uri);
+
+    match PG_BACKEND_TOKIO_RUNTIME.block_on(parquet_object_store.delete(&location)) {
+        Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+        Err(e) => pgrx::warning!(
+            "failed to remove {}: {}",
+            redact_uri(uri),
+            redact_uri_secrets(&e.to_string(), uri)
+        ),
+    }
+}
+
+// create_uri_if_absent creates an empty file at the uri, unless a file exists there, so that
+// no other COPY TO writes to the uri until the file is written. Local files are created with
+// O_EXCL, and objects by a conditional put with If-None-Match: *. It returns false when the file
+// exists.
+pub(crate) fn create_uri_if_absent(uri: &Url) -> bool {
+    if uri.scheme() == "file" {
+        let path = uri
+            .to_file_path()
+            .unwrap_or_else(|_| panic!("not a valid file path: {}", uri));
+
+        // the parent directories are created as when the file is written
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        return match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => false,
+            Err(e) => panic!("failed to create file {}: {}", path.display(), e),
+        };
+    }
+
+    let copy_from = false;
+    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+
+    let put_options = PutOptions {
+        mode: PutMode::Create,
+        ..Default::default()
+    };
+
+    let put_result = block_on_interruptible(parquet_object_store.put_opts(
+        &location,
+        PutPayload::default(),
+        put_options,
+    ));
+
+    match put_result {
+        Ok(_) => true,
+        Err(object_store::Error::AlreadyExists { .. })
+        | Err(object_store::Error::Precondition { .. }) => false,
+        Err(object_store::Error::NotImplemented) => panic!(
+            "object store of uri {} does not support creating a file only if it does not exist",
+            redact_uri(uri)
+        ),
+        Err(e) => panic!(
+            "failed to create object at uri {}: {}",
+            redact_uri(uri),
+            redact_uri_secrets(&e.to_string(), uri)
+        ),
+    }
+}
+
+// uri_prefix_has_files returns whether the directory at the uri has any files, e.g. the files
+// of a former COPY TO with max_rows_per_file. Objects are listed by the prefix of the uri.
+pub(crate) fn uri_prefix_has_files(uri: &Url) -> bool {
+    if uri.scheme() == "file" {
+        let path = uri
+            .to_file_path()
+            .unwrap_or_else(|_| panic!("not a valid file path: {}", uri));
+
+        return std::fs::read_dir(path)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+    }
+
+    let copy_from = false;
+    let (parquet_object_store, location) = get_or_create_object_store(uri, copy_from);
+
+    let first_object =
+        block_on_interruptible(async { parquet_object_store.list(Some(&location)).next().await });
+
+    match first_object {
+        None => false,
+        Some(Ok(_)) => true,
+        Some(Err(e)) => panic!(
+            "failed to list objects for uri {}: {}",
+            redact_uri(uri),
+            redact_uri_secrets(&e.to_string(), uri)
+        ),
+    }
 }
 
 pub(crate) fn ensure_access_privilege_to_uri(uri: &Url, copy_from: bool) {
+    // temp files of the session are accessible without any privilege
+    if is_temp_parquet_file_uri(uri) {
+        return;
+    }
+
+    // uri prefixes restrict superusers, as well
+    ensure_uri_prefixes_allow_uri(uri);
+
     if unsafe { superuser() } {
         return;
     }
@@ -162,3 +915,32 @@ pub(crate) fn ensure_access_privilege_to_uri(uri: &Url, copy_from: bool) {
         );
     }
 }
+
+// ensure_object_store_cache_privilege requires the read role of object stores to inspect or
+// clear the object store cache of the session.
+pub(crate) fn ensure_object_store_cache_privilege() {
+    if unsafe { superuser() } {
+        return;
+    }
+
+    let user_id = unsafe { GetUserId() };
+
+    let required_role_id = unsafe {
+        get_role_oid(
+            PARQUET_OBJECT_STORE_READ_ROLE.to_string().as_pg_cstr(),
+            false,
+        )
+    };
+
+    if !unsafe { has_privs_of_role(user_id, required_role_id) } {
+        ereport!(
+            pgrx::PgLogLevel::ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            "permission denied to access the object store cache",
+            format!(
+                "Only roles with privileges of the \"{}\" role may access the object store cache.",
+                PARQUET_OBJECT_STORE_READ_ROLE
+            ),
+        );
+    }
+}
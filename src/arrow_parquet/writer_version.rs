@@ -0,0 +1,48 @@
+use std::{fmt::Display, str::FromStr};
+
+use parquet::file::properties::WriterVersion;
+
+// PgParquetWriterVersion is the version of the data pages, which COPY TO writes. Version 2.0
+// writes v2 data pages and uses the delta encodings for the columns, which fall back from
+// dictionary encoding.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PgParquetWriterVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+pub(crate) fn all_supported_writer_versions() -> Vec<PgParquetWriterVersion> {
+    vec![PgParquetWriterVersion::V1, PgParquetWriterVersion::V2]
+}
+
+impl From<PgParquetWriterVersion> for WriterVersion {
+    fn from(value: PgParquetWriterVersion) -> Self {
+        match value {
+            PgParquetWriterVersion::V1 => WriterVersion::PARQUET_1_0,
+            PgParquetWriterVersion::V2 => WriterVersion::PARQUET_2_0,
+        }
+    }
+}
+
+impl Display for PgParquetWriterVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgParquetWriterVersion::V1 => write!(f, "1.0"),
+            PgParquetWriterVersion::V2 => write!(f, "2.0"),
+        }
+    }
+}
+
+impl FromStr for PgParquetWriterVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1.0" => Ok(PgParquetWriterVersion::V1),
+            "2.0" => Ok(PgParquetWriterVersion::V2),
+            _ => Err(format!("unrecognized writer version: {}", s)),
+        }
+    }
+}
@@ -1,9 +1,35 @@
 use std::sync::LazyLock;
 
+use crate::object_store::aws::{
+    define_bucket_credentials_guc, AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN,
+    S3_COMPATIBLE_HOSTS,
+};
+use crate::object_store::azure::{
+    AZURE_STORAGE_ACCOUNT, AZURE_STORAGE_CONNECTION_STRING, AZURE_STORAGE_KEY,
+    AZURE_STORAGE_SAS_TOKEN,
+};
+use crate::object_store::client_options::{
+    OBJECT_STORE_MAX_RETRIES, OBJECT_STORE_REQUEST_TIMEOUT_MS, OBJECT_STORE_RETRY_TIMEOUT_MS,
+};
+use crate::object_store::object_store_cache::OBJECT_STORE_CACHE_MAX_ENTRIES;
+use arrow_parquet::cast_plan_cache::register_cast_plan_invalidation;
+use arrow_parquet::compression::define_default_compression_gucs;
+use arrow_parquet::parallel_decoder::MAX_DECODE_THREADS;
+#[cfg(feature = "pg_test")]
+use arrow_parquet::pg_to_arrow::conversion_progress::TEST_CONVERSION_MEMORY_LIMIT;
+use arrow_parquet::uri_prefixes::{ALLOWED_URI_PREFIXES, DENIED_URI_PREFIXES};
+use arrow_parquet::uri_utils::{UPLOAD_PART_CONCURRENCY, UPLOAD_PART_SIZE};
+#[cfg(feature = "pg_test")]
+use parquet_copy_hook::copy_to_dest_receiver::TEST_FAILED_UPLOAD_FILE;
+use parquet_copy_hook::copy_to_dest_receiver::{
+    BATCH_MEMORY_LIMIT, KEEP_PARTIAL_FILES, MAX_EXPORT_BYTES, UPLOAD_CONCURRENCY, WARN_EXPORT_BYTES,
+};
+use parquet_copy_hook::export_allowlist::EXPORT_ALLOWLIST;
 use parquet_copy_hook::hook::{init_parquet_copy_hook, ENABLE_PARQUET_COPY_HOOK};
 use parquet_copy_hook::pg_compat::MarkGUCPrefixReserved;
 use pgrx::{prelude::*, GucContext, GucFlags, GucRegistry};
 use tokio::runtime::Runtime;
+use type_compat::geometry::register_postgis_context_invalidation;
 
 mod arrow_parquet;
 mod object_store;
@@ -18,7 +44,23 @@ mod type_compat;
 #[allow(unused_imports)]
 pub use crate::arrow_parquet::compression::PgParquetCompression;
 #[allow(unused_imports)]
-pub use crate::parquet_copy_hook::copy_to_dest_receiver::create_copy_to_parquet_dest_receiver;
+pub use crate::arrow_parquet::if_exists::PgParquetIfExists;
+#[allow(unused_imports)]
+pub use crate::arrow_parquet::infinite_timestamps::PgParquetInfiniteTimestamps;
+#[allow(unused_imports)]
+pub use crate::arrow_parquet::nan_handling::PgParquetNanHandling;
+#[allow(unused_imports)]
+pub use crate::arrow_parquet::statistics::PgParquetStatistics;
+#[allow(unused_imports)]
+pub use crate::arrow_parquet::stream_writer::CopyToParquetStream;
+#[allow(unused_imports)]
+pub use crate::arrow_parquet::unsupported_type::PgParquetUnsupportedType;
+#[allow(unused_imports)]
+pub use crate::parquet_copy_hook::copy_to_dest_receiver::{
+    create_copy_to_parquet_dest_receiver, create_copy_to_parquet_dest_receiver_with_options,
+    create_copy_to_parquet_dest_receiver_with_stream, CopyToParquetDestReceiverOptions,
+    COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION,
+};
 
 pgrx::pg_module_magic!();
 
@@ -44,8 +86,269 @@ pub extern "C" fn _PG_init() {
         GucFlags::default(),
     );
 
+    GucRegistry::define_int_guc(
+        "pg_parquet.max_export_bytes",
+        "Maximum size of the files written by a single COPY TO",
+        "COPY TO is aborted, and the files written by it are removed, when it writes more compressed bytes than the limit. 0 means unlimited. The limit is at most about 2TB.",
+        &MAX_EXPORT_BYTES,
+        0,
+        i32::MAX,
+        GucContext::Suset,
+        GucFlags::UNIT_KB,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.warn_export_bytes",
+        "Size of the files written by a single COPY TO, after which a warning is emitted",
+        "COPY TO emits a warning, and continues, when it writes more compressed bytes than the limit. 0 means no warning. The limit is at most about 2TB.",
+        &WARN_EXPORT_BYTES,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_KB,
+    );
+
+    GucRegistry::define_bool_guc(
+        "pg_parquet.keep_partial_files",
+        "Keep the partially written files of a failed COPY TO",
+        "A failed COPY TO aborts its upload and removes the files written by it, unless this is set, e.g. for debugging. The kept files are finished with the rows written so far.",
+        &KEEP_PARTIAL_FILES,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.batch_memory_limit",
+        "Size of the rows, which COPY TO holds in memory before it finishes the row group",
+        "COPY TO finishes the row group early, even if it has fewer rows than row_group_size, when its rows exceed the limit. It is the default of the batch_memory_limit option.",
+        &BATCH_MEMORY_LIMIT,
+        1024,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_KB,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.upload_concurrency",
+        "Maximum number of files, which COPY TO with max_rows_per_file uploads at a time",
+        "COPY TO finishes the upload of the previous files in the background while it writes the next file. 1 finishes each file before the next one is written.",
+        &UPLOAD_CONCURRENCY,
+        1,
+        64,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.export_allowlist",
+        "Relations which COPY TO is allowed to export",
+        "Comma separated list of \"schema\" or \"schema.table\" patterns, where \"*\" matches any sequence of characters. COPY TO fails when its table, or any relation referenced by its query, does not match a pattern. Empty means no restriction.",
+        &EXPORT_ALLOWLIST,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.allowed_uri_prefixes",
+        "Uri prefixes which COPY and the parquet functions are allowed to access",
+        "Comma separated list of uri prefixes, e.g. \"s3://bucket/dir/*, /tmp/exports\". COPY and the parquet functions fail when their uri does not match a prefix. Empty means no restriction.",
+        &ALLOWED_URI_PREFIXES,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.denied_uri_prefixes",
+        "Uri prefixes which COPY and the parquet functions are not allowed to access",
+        "Comma separated list of uri prefixes, e.g. \"s3://bucket/private\". COPY and the parquet functions fail when their uri matches a prefix, even if it matches pg_parquet.allowed_uri_prefixes.",
+        &DENIED_URI_PREFIXES,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.s3_compatible_hosts",
+        "Hosts of S3-compatible object stores",
+        "Comma separated list of hosts, e.g. \"s3.wasabisys.com\". http(s) uris of these hosts, either host/bucket/key or bucket.host/key, are accessed via the S3 api.",
+        &S3_COMPATIBLE_HOSTS,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.aws_access_key_id",
+        "AWS access key id of the session",
+        "Takes precedence over AWS_ACCESS_KEY_ID environment variable and the aws config files, but not over pg_parquet.bucket_credentials.",
+        &AWS_ACCESS_KEY_ID,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.aws_secret_access_key",
+        "AWS secret access key of the session",
+        "Takes precedence over AWS_SECRET_ACCESS_KEY environment variable and the aws config files, but not over pg_parquet.bucket_credentials.",
+        &AWS_SECRET_ACCESS_KEY,
+        GucContext::Suset,
+        GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL,
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.aws_session_token",
+        "AWS session token of the session",
+        "Takes precedence over AWS_SESSION_TOKEN environment variable and the aws config files, but not over pg_parquet.bucket_credentials.",
+        &AWS_SESSION_TOKEN,
+        GucContext::Suset,
+        GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL,
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.azure_storage_account",
+        "Azure storage account of the session",
+        "Takes precedence over AZURE_STORAGE_ACCOUNT environment variable and the azure config file.",
+        &AZURE_STORAGE_ACCOUNT,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.azure_storage_key",
+        "Azure storage account key of the session",
+        "Takes precedence over AZURE_STORAGE_KEY environment variable and the azure config file.",
+        &AZURE_STORAGE_KEY,
+        GucContext::Suset,
+        GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL,
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.azure_storage_sas_token",
+        "Azure storage SAS token of the session",
+        "Takes precedence over AZURE_STORAGE_SAS_TOKEN environment variable and the azure config file.",
+        &AZURE_STORAGE_SAS_TOKEN,
+        GucContext::Suset,
+        GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL,
+    );
+
+    GucRegistry::define_string_guc(
+        "pg_parquet.azure_storage_connection_string",
+        "Azure storage connection string of the session",
+        "Takes precedence over AZURE_STORAGE_CONNECTION_STRING environment variable and the azure config file, which are ignored when any other azure credential GUC is set.",
+        &AZURE_STORAGE_CONNECTION_STRING,
+        GucContext::Suset,
+        GucFlags::SUPERUSER_ONLY | GucFlags::NO_SHOW_ALL,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.upload_part_size",
+        "Size of the parts uploaded to object stores by COPY TO",
+        "COPY TO buffers the written bytes up to this size before uploading them as a part of a multipart upload. Smaller files are uploaded in a single request.",
+        &UPLOAD_PART_SIZE,
+        5 * 1024,
+        5 * 1024 * 1024,
+        GucContext::Userset,
+        GucFlags::UNIT_KB,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.upload_part_concurrency",
+        "Maximum number of parts of a file, which COPY TO uploads concurrently",
+        "COPY TO continues writing the file while at most this many parts are uploaded to the object store. A new part waits for an upload to complete, so at most this many parts are buffered at a time.",
+        &UPLOAD_PART_CONCURRENCY,
+        1,
+        64,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.object_store_max_retries",
+        "Maximum number of retries of a failed object store request",
+        "Requests, which fail with a server error, a timeout or a connection error, are retried with exponential backoff. 0 disables retries.",
+        &OBJECT_STORE_MAX_RETRIES,
+        0,
+        1000,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.object_store_retry_timeout_ms",
+        "Time since the first attempt of an object store request, after which it is not retried",
+        "Bounds the time a failing request is retried, regardless of pg_parquet.object_store_max_retries.",
+        &OBJECT_STORE_RETRY_TIMEOUT_MS,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_MS,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.object_store_request_timeout_ms",
+        "Timeout of a single object store request",
+        "A request, which does not complete within the timeout, fails and is retried.",
+        &OBJECT_STORE_REQUEST_TIMEOUT_MS,
+        1,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::UNIT_MS,
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.object_store_cache_max_entries",
+        "Maximum number of object stores cached in the session",
+        "The least recently used object store is evicted when a new one is cached. The http clients of http(s) uris are limited separately by the same number.",
+        &OBJECT_STORE_CACHE_MAX_ENTRIES,
+        1,
+        100000,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        "pg_parquet.max_decode_threads",
+        "Maximum number of threads decoding the row groups read by a single COPY FROM",
+        "COPY FROM decodes the upcoming row groups in separate threads, while the backend inserts the rows of the current row group. At most this many row groups are prefetched at a time. 1 means the backend decodes the row groups.",
+        &MAX_DECODE_THREADS,
+        1,
+        64,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    define_default_compression_gucs();
+
+    define_bucket_credentials_guc();
+
+    #[cfg(feature = "pg_test")]
+    GucRegistry::define_int_guc(
+        "pg_parquet.test_conversion_memory_limit",
+        "Memory limit of the conversion context in bytes (test only)",
+        "Memory limit of the conversion context in bytes (test only)",
+        &TEST_CONVERSION_MEMORY_LIMIT,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    #[cfg(feature = "pg_test")]
+    GucRegistry::define_int_guc(
+        "pg_parquet.test_failed_upload_file",
+        "File of max_rows_per_file, whose upload in the background fails (test only)",
+        "File of max_rows_per_file, whose upload in the background fails (test only)",
+        &TEST_FAILED_UPLOAD_FILE,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
     MarkGUCPrefixReserved("pg_parquet");
 
+    register_postgis_context_invalidation();
+
+    register_cast_plan_invalidation();
+
     init_parquet_copy_hook();
 }
 
@@ -1,13 +1,16 @@
 use crate::{
-    arrow_parquet::uri_utils::uri_as_string,
+    arrow_parquet::uri_utils::{redact_uri, uri_as_string},
     object_store::{
         aws::create_s3_object_store, azure::create_azure_object_store,
-        local_file::create_local_file_object_store,
+        http::create_http_object_store, local_file::create_local_file_object_store,
     },
     PG_BACKEND_TOKIO_RUNTIME,
 };
 
 pub(crate) mod aws;
 pub(crate) mod azure;
+pub(crate) mod client_options;
+pub(crate) mod http;
 pub(crate) mod local_file;
 pub(crate) mod object_store_cache;
+pub(crate) mod written_objects;
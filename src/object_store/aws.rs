@@ -1,36 +1,123 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CStr},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::SystemTime,
+};
 
-use aws_config::BehaviorVersion;
+use aws_config::{profile::ProfileFileCredentialsProvider, BehaviorVersion};
 use aws_credential_types::provider::ProvideCredentials;
-use object_store::aws::AmazonS3Builder;
+use object_store::{
+    aws::{AmazonS3Builder, S3ConditionalPut},
+    path::Path,
+};
+use pgrx::{
+    ereport, pg_guard,
+    pg_sys::{
+        pstrdup, superuser, DefineCustomStringVariable, GUC_check_errdetail_string, GucContext,
+        GucSource, GUC_NO_SHOW_ALL, GUC_SUPERUSER_ONLY,
+    },
+    AsPgCStr, GucSetting, PgLogLevel, PgSqlErrorCode,
+};
+use serde::Deserialize;
 use url::Url;
 
-use super::{object_store_cache::ObjectStoreWithExpiration, PG_BACKEND_TOKIO_RUNTIME};
+use super::{
+    client_options::{object_store_client_options, object_store_retry_config},
+    object_store_cache::ObjectStoreWithExpiration,
+    redact_uri, PG_BACKEND_TOKIO_RUNTIME,
+};
+
+// S3_COMPATIBLE_HOSTS is a comma separated list of the hosts of S3-compatible object stores,
+// e.g. "s3.wasabisys.com". http(s) uris of these hosts are accessed via the S3 api, either
+// path-style (host/bucket/key) or virtual-host style (bucket.host/key).
+pub(crate) static S3_COMPATIBLE_HOSTS: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+// AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN are the s3 credentials of
+// the session. They take precedence over the environment variables and the aws config files,
+// but not over the credentials of the bucket in pg_parquet.bucket_credentials.
+pub(crate) static AWS_ACCESS_KEY_ID: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+pub(crate) static AWS_SECRET_ACCESS_KEY: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+pub(crate) static AWS_SESSION_TOKEN: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+const R2_HOST_SUFFIX: &str = ".r2.cloudflarestorage.com";
+
+// BUCKET_CREDENTIALS is a json object, which maps s3 buckets to their credentials, e.g.
+// {"s3://bucket-a": {"profile": "a"}, "s3://bucket-b": {"access_key_id": "..", ..}}.
+// Buckets, which are not in the map, use the credentials of the session. It is defined
+// without pgrx, which does not support check hooks, so that invalid json is rejected at
+// SET time.
+static mut BUCKET_CREDENTIALS: *mut c_char = std::ptr::null_mut();
+
+// BucketCredentials configures the AmazonS3 object store of a bucket. A profile is read from
+// the aws config files, and static keys are used as is.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BucketCredentials {
+    profile: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+    session_token: Option<String>,
+    region: Option<String>,
+    endpoint_url: Option<String>,
+}
 
 // create_s3_object_store creates an AmazonS3 object store with the given bucket name.
 // It is configured by environment variables and aws config files as fallback method.
 // We need to read the config files to make the fallback method work since object_store
 // does not provide a way to read them. Currently, we only support following environment
 // variables and config parameters:
-// - AWS_ACCESS_KEY_ID
-// - AWS_SECRET_ACCESS_KEY
-// - AWS_SESSION_TOKEN
+// - AWS_ACCESS_KEY_ID (pg_parquet.aws_access_key_id)
+// - AWS_SECRET_ACCESS_KEY (pg_parquet.aws_secret_access_key)
+// - AWS_SESSION_TOKEN (pg_parquet.aws_session_token)
 // - AWS_ENDPOINT_URL
 // - AWS_REGION
 // - AWS_SHARED_CREDENTIALS_FILE (env var only)
 // - AWS_CONFIG_FILE (env var only)
 // - AWS_PROFILE (env var only)
 // - AWS_ALLOW_HTTP (env var only, object_store specific)
+// Credentials of the bucket in pg_parquet.bucket_credentials take precedence over them.
+// Without static keys, credentials are resolved by the default credential chain of aws sdk,
+// i.e. web identity token (IRSA), ECS task role and EC2 instance profile (IMDSv2). Their
+// expiry is the expiry of the cached object store, so they are refreshed before they lapse.
 pub(crate) fn create_s3_object_store(uri: &Url) -> ObjectStoreWithExpiration {
     let bucket_name = parse_s3_bucket(uri).unwrap_or_else(|| {
-        panic!("unsupported s3 uri: {}", uri);
+        panic!("unsupported s3 uri: {}", redact_uri(uri));
+    });
+
+    let credentials = bucket_credentials(&bucket_name);
+
+    create_s3_object_store_with_credentials(uri, credentials)
+}
+
+// create_s3_object_store_with_credentials creates an AmazonS3 object store, whose given
+// credentials take precedence over the environment variables and aws config files.
+pub(crate) fn create_s3_object_store_with_credentials(
+    uri: &Url,
+    credentials: Option<BucketCredentials>,
+) -> ObjectStoreWithExpiration {
+    let bucket_name = parse_s3_bucket(uri).unwrap_or_else(|| {
+        panic!("unsupported s3 uri: {}", redact_uri(uri));
     });
 
     // we do not use builder::from_env() here because not all environment variables have
     // a fallback to the config files
-    let mut aws_s3_builder = AmazonS3Builder::new().with_bucket_name(bucket_name);
+    let mut aws_s3_builder = AmazonS3Builder::new()
+        .with_bucket_name(bucket_name.clone())
+        .with_client_options(object_store_client_options())
+        .with_retry(object_store_retry_config())
+        // COPY TO with if_exists 'error' or 'append_suffix' creates its files only if they do not
+        // exist, which sends If-None-Match: *
+        .with_conditional_put(S3ConditionalPut::ETagMatch);
 
-    let aws_s3_config = AwsS3Config::load();
+    let aws_s3_config = AwsS3Config::load(credentials);
 
     // allow http
     aws_s3_builder = aws_s3_builder.with_allow_http(aws_s3_config.allow_http);
@@ -55,6 +142,11 @@ pub(crate) fn create_s3_object_store(uri: &Url) -> ObjectStoreWithExpiration {
         aws_s3_builder = aws_s3_builder.with_endpoint(endpoint_url);
     }
 
+    // endpoint of S3-compatible uri, e.g. https://{account_id}.r2.cloudflarestorage.com
+    if let Some(endpoint) = parse_s3_compatible_uri(uri).and_then(|uri| uri.endpoint) {
+        aws_s3_builder = aws_s3_builder.with_endpoint(endpoint);
+    }
+
     // region
     if let Some(region) = aws_s3_config.region {
         aws_s3_builder = aws_s3_builder.with_region(region);
@@ -73,6 +165,11 @@ pub(crate) fn create_s3_object_store(uri: &Url) -> ObjectStoreWithExpiration {
 pub(crate) fn parse_s3_bucket(uri: &Url) -> Option<String> {
     let host = uri.host_str()?;
 
+    // http(s)://{bucket}.{s3_compatible_host}/key or http(s)://{s3_compatible_host}/{bucket}/key
+    if let Some(s3_compatible_uri) = parse_s3_compatible_uri(uri) {
+        return Some(s3_compatible_uri.bucket);
+    }
+
     // s3(a)://{bucket}/key
     if uri.scheme() == "s3" {
         return Some(host.to_string());
@@ -98,6 +195,268 @@ pub(crate) fn parse_s3_bucket(uri: &Url) -> Option<String> {
     None
 }
 
+// S3CompatibleUri is an http(s) uri of an S3-compatible object store.
+pub(crate) struct S3CompatibleUri {
+    pub(crate) bucket: String,
+    pub(crate) path: Path,
+    // endpoint is None when the uri is of AWS_ENDPOINT_URL, which is already configured
+    pub(crate) endpoint: Option<String>,
+}
+
+// parse_s3_compatible_uri parses an http(s) uri whose host is the host of AWS_ENDPOINT_URL,
+// one of pg_parquet.s3_compatible_hosts or a Cloudflare R2 account host. Amazon S3 uris are
+// not parsed here.
+pub(crate) fn parse_s3_compatible_uri(uri: &Url) -> Option<S3CompatibleUri> {
+    if uri.scheme() != "https" && uri.scheme() != "http" {
+        return None;
+    }
+
+    let host = uri.host_str()?;
+
+    if host.ends_with("amazonaws.com") {
+        return None;
+    }
+
+    if let Some(endpoint) = std::env::var("AWS_ENDPOINT_URL")
+        .ok()
+        .and_then(|endpoint| Url::parse(&endpoint).ok())
+    {
+        if endpoint.port_or_known_default() == uri.port_or_known_default() {
+            if let Some((bucket, path)) = endpoint
+                .host_str()
+                .and_then(|domain| parse_s3_compatible_host(uri, domain))
+            {
+                return Some(S3CompatibleUri {
+                    bucket,
+                    path,
+                    endpoint: None,
+                });
+            }
+        }
+    }
+
+    let mut domains = s3_compatible_hosts();
+
+    // https://{account_id}.r2.cloudflarestorage.com/{bucket}/key
+    // or https://{bucket}.{account_id}.r2.cloudflarestorage.com/key
+    if let Some(account_id) = host.strip_suffix(R2_HOST_SUFFIX) {
+        let account_id = account_id.rsplit('.').next()?;
+        domains.push(format!("{}{}", account_id, R2_HOST_SUFFIX));
+    }
+
+    domains.into_iter().find_map(|domain| {
+        let (bucket, path) = parse_s3_compatible_host(uri, &domain)?;
+
+        let endpoint = match uri.port() {
+            Some(port) => format!("{}://{}:{}", uri.scheme(), domain, port),
+            None => format!("{}://{}", uri.scheme(), domain),
+        };
+
+        Some(S3CompatibleUri {
+            bucket,
+            path,
+            endpoint: Some(endpoint),
+        })
+    })
+}
+
+// parse_s3_compatible_host returns the bucket and the object path of the uri, when its host is
+// the domain (path-style) or a subdomain of it (virtual-host style).
+fn parse_s3_compatible_host(uri: &Url, domain: &str) -> Option<(String, Path)> {
+    let host = uri.host_str()?;
+
+    let (bucket, path) = if host == domain {
+        uri.path().strip_prefix('/')?.split_once('/')?
+    } else {
+        let bucket = host.strip_suffix(domain)?.strip_suffix('.')?;
+        (bucket, uri.path())
+    };
+
+    if bucket.is_empty() {
+        return None;
+    }
+
+    Some((bucket.to_string(), Path::from_url_path(path).ok()?))
+}
+
+fn s3_compatible_hosts() -> Vec<String> {
+    let Some(s3_compatible_hosts) = S3_COMPATIBLE_HOSTS.get() else {
+        return vec![];
+    };
+
+    s3_compatible_hosts
+        .to_str()
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+pub(crate) fn define_bucket_credentials_guc() {
+    unsafe {
+        DefineCustomStringVariable(
+            c"pg_parquet.bucket_credentials".as_ptr(),
+            c"Credentials of s3 buckets".as_ptr(),
+            c"Json object of bucket uris to their credentials, e.g. {\"s3://bucket\": {\"profile\": \"a\"}}. Supported credential keys are profile, access_key_id, secret_access_key, session_token, region and endpoint_url.".as_ptr(),
+            std::ptr::addr_of_mut!(BUCKET_CREDENTIALS),
+            std::ptr::null(),
+            GucContext::PGC_SUSET,
+            (GUC_SUPERUSER_ONLY | GUC_NO_SHOW_ALL) as _,
+            Some(check_bucket_credentials),
+            None,
+            None,
+        );
+    }
+}
+
+fn bucket_credentials_guc_value() -> Option<&'static str> {
+    let bucket_credentials = unsafe { BUCKET_CREDENTIALS };
+
+    if bucket_credentials.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(bucket_credentials) }.to_str().ok()
+}
+
+// bucket_credentials returns the credentials of the bucket in pg_parquet.bucket_credentials
+fn bucket_credentials(bucket: &str) -> Option<BucketCredentials> {
+    let bucket_credentials = bucket_credentials_guc_value()?;
+
+    // already checked at SET time
+    let mut bucket_credentials = parse_bucket_credentials(bucket_credentials).ok()?;
+
+    bucket_credentials.remove(bucket)
+}
+
+fn aws_credential_guc_value(guc: &GucSetting<Option<&'static CStr>>) -> Option<String> {
+    guc.get()
+        .map(|value| value.to_str().unwrap_or_default().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// s3_credential_gucs_hash returns the hash of pg_parquet.bucket_credentials and the aws
+// credential GUCs, which changes when any of them is changed in the session.
+pub(crate) fn s3_credential_gucs_hash() -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    bucket_credentials_guc_value().hash(&mut hasher);
+
+    for guc in [
+        &AWS_ACCESS_KEY_ID,
+        &AWS_SECRET_ACCESS_KEY,
+        &AWS_SESSION_TOKEN,
+    ] {
+        aws_credential_guc_value(guc).hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+// parse_bucket_credentials parses pg_parquet.bucket_credentials into a map of bucket names
+// to their credentials. Error messages do not contain the credentials.
+fn parse_bucket_credentials(
+    bucket_credentials: &str,
+) -> Result<HashMap<String, BucketCredentials>, String> {
+    if bucket_credentials.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let bucket_credentials: HashMap<String, BucketCredentials> =
+        serde_json::from_str(bucket_credentials).map_err(|e| {
+            format!(
+            "pg_parquet.bucket_credentials must be a json object of bucket uris to credentials: {}",
+            e
+        )
+        })?;
+
+    let mut credentials_by_bucket = HashMap::new();
+
+    for (bucket_uri, credentials) in bucket_credentials {
+        let bucket = Url::parse(&bucket_uri)
+            .ok()
+            .filter(|uri| uri.scheme() == "s3" && matches!(uri.path(), "" | "/"))
+            .and_then(|uri| uri.host_str().map(|bucket| bucket.to_string()))
+            .ok_or_else(|| format!("invalid bucket uri \"{}\", e.g. s3://bucket", bucket_uri))?;
+
+        validate_bucket_credentials(&credentials, &format!("bucket \"{}\"", bucket_uri))?;
+
+        credentials_by_bucket.insert(bucket, credentials);
+    }
+
+    Ok(credentials_by_bucket)
+}
+
+fn validate_bucket_credentials(credentials: &BucketCredentials, owner: &str) -> Result<(), String> {
+    if credentials.profile.is_some()
+        && (credentials.access_key_id.is_some() || credentials.secret_access_key.is_some())
+    {
+        return Err(format!(
+            "profile and access keys of {} cannot be specified together",
+            owner
+        ));
+    }
+
+    if credentials.access_key_id.is_some() != credentials.secret_access_key.is_some() {
+        return Err(format!(
+            "access_key_id and secret_access_key of {} must be specified together",
+            owner
+        ));
+    }
+
+    Ok(())
+}
+
+// parse_s3_options parses the options of a single call of the parquet functions, which have
+// the keys of the credentials in pg_parquet.bucket_credentials. It returns None for an empty
+// object, i.e. the configuration of the session. Profiles are read from the aws config files
+// of the server, hence only superusers may use them like the superuser-only GUC.
+pub(crate) fn parse_s3_options(options: serde_json::Value) -> Option<BucketCredentials> {
+    if options
+        .as_object()
+        .is_some_and(|options| options.is_empty())
+    {
+        return None;
+    }
+
+    let options: BucketCredentials = serde_json::from_value(options)
+        .unwrap_or_else(|e| panic!("invalid options of the parquet function: {}", e));
+
+    validate_bucket_credentials(&options, "options").unwrap_or_else(|e| panic!("{}", e));
+
+    if options.profile.is_some() && !unsafe { superuser() } {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            "permission denied to use the profile option",
+            "Only superusers may read the profiles of the aws config files.",
+        );
+    }
+
+    Some(options)
+}
+
+#[pg_guard]
+unsafe extern "C" fn check_bucket_credentials(
+    newval: *mut *mut c_char,
+    _extra: *mut *mut c_void,
+    _source: GucSource::Type,
+) -> bool {
+    if (*newval).is_null() {
+        return true;
+    }
+
+    let bucket_credentials = CStr::from_ptr(*newval).to_str().unwrap_or_default();
+
+    if let Err(e) = parse_bucket_credentials(bucket_credentials) {
+        GUC_check_errdetail_string = pstrdup(e.as_pg_cstr());
+        return false;
+    }
+
+    true
+}
+
 // AwsS3Config is a struct that holds the configuration that is
 // used to configure the AmazonS3 object store. object_store does
 // not provide a way to read the config files, so we need to read
@@ -114,7 +473,10 @@ struct AwsS3Config {
 
 impl AwsS3Config {
     // load reads the s3 config from the environment variables first and config files as fallback.
-    fn load() -> Self {
+    // Credentials of the bucket, and then the credential GUCs, take precedence over both.
+    fn load(bucket_credentials: Option<BucketCredentials>) -> Self {
+        let bucket_credentials = bucket_credentials.unwrap_or_default();
+
         let allow_http = if let Ok(allow_http) = std::env::var("AWS_ALLOW_HTTP") {
             allow_http.parse().unwrap_or(false)
         } else {
@@ -122,31 +484,59 @@ impl AwsS3Config {
         };
 
         // first tries environment variables and then the config files
-        let sdk_config = PG_BACKEND_TOKIO_RUNTIME.block_on(async {
-            aws_config::defaults(BehaviorVersion::v2024_03_28())
-                .load()
-                .await
-        });
+        let mut config_loader = aws_config::defaults(BehaviorVersion::v2024_03_28());
+
+        // credentials of the profile take precedence over the environment variables
+        if let Some(profile) = &bucket_credentials.profile {
+            let profile_credentials_provider = ProfileFileCredentialsProvider::builder()
+                .profile_name(profile)
+                .build();
+
+            config_loader = config_loader
+                .profile_name(profile)
+                .credentials_provider(profile_credentials_provider);
+        }
+
+        let sdk_config = PG_BACKEND_TOKIO_RUNTIME.block_on(async { config_loader.load().await });
 
         let mut access_key_id = None;
         let mut secret_access_key = None;
         let mut session_token = None;
         let mut expire_at = None;
 
-        if let Some(credential_provider) = sdk_config.credentials_provider() {
-            if let Ok(credentials) = PG_BACKEND_TOKIO_RUNTIME
+        let session_access_key_id = aws_credential_guc_value(&AWS_ACCESS_KEY_ID);
+
+        if bucket_credentials.access_key_id.is_some() {
+            access_key_id = bucket_credentials.access_key_id;
+            secret_access_key = bucket_credentials.secret_access_key;
+            session_token = bucket_credentials.session_token;
+        } else if bucket_credentials.profile.is_none() && session_access_key_id.is_some() {
+            // a profile of the bucket takes precedence over the keys of the session
+            access_key_id = session_access_key_id;
+            secret_access_key = aws_credential_guc_value(&AWS_SECRET_ACCESS_KEY);
+            session_token = aws_credential_guc_value(&AWS_SESSION_TOKEN);
+        } else if let Some(credential_provider) = sdk_config.credentials_provider() {
+            match PG_BACKEND_TOKIO_RUNTIME
                 .block_on(async { credential_provider.provide_credentials().await })
             {
-                access_key_id = Some(credentials.access_key_id().to_string());
-                secret_access_key = Some(credentials.secret_access_key().to_string());
-                session_token = credentials.session_token().map(|t| t.to_string());
-                expire_at = credentials.expiry();
+                Ok(credentials) => {
+                    access_key_id = Some(credentials.access_key_id().to_string());
+                    secret_access_key = Some(credentials.secret_access_key().to_string());
+                    session_token = credentials.session_token().map(|t| t.to_string());
+                    expire_at = credentials.expiry();
+                }
+                // requests are not signed, e.g. for public buckets
+                Err(e) => pgrx::debug2!("no aws credentials are found: {}", e),
             }
         }
 
-        let endpoint_url = sdk_config.endpoint_url().map(|u| u.to_string());
+        let endpoint_url = bucket_credentials
+            .endpoint_url
+            .or_else(|| sdk_config.endpoint_url().map(|u| u.to_string()));
 
-        let region = sdk_config.region().map(|r| r.as_ref().to_string());
+        let region = bucket_credentials
+            .region
+            .or_else(|| sdk_config.region().map(|r| r.as_ref().to_string()));
 
         Self {
             region,
@@ -1,31 +1,64 @@
-use std::sync::Arc;
+use std::{
+    collections::hash_map::DefaultHasher,
+    ffi::CStr,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
 use azure_storage::{ConnectionString, EndpointProtocol};
 use home::home_dir;
 use ini::Ini;
-use object_store::azure::{AzureConfigKey, MicrosoftAzureBuilder};
+use object_store::{
+    azure::{AzureConfigKey, MicrosoftAzureBuilder},
+    path::Path,
+};
+use pgrx::GucSetting;
 use url::Url;
 
-use super::object_store_cache::ObjectStoreWithExpiration;
+use super::{
+    client_options::{object_store_client_options, object_store_retry_config},
+    object_store_cache::ObjectStoreWithExpiration,
+    redact_uri,
+};
+
+// AZURE_STORAGE_ACCOUNT, AZURE_STORAGE_KEY, AZURE_STORAGE_SAS_TOKEN and
+// AZURE_STORAGE_CONNECTION_STRING are the azure credentials of the session. They take
+// precedence over the environment variables and the azure config file.
+pub(crate) static AZURE_STORAGE_ACCOUNT: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+pub(crate) static AZURE_STORAGE_KEY: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+pub(crate) static AZURE_STORAGE_SAS_TOKEN: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+pub(crate) static AZURE_STORAGE_CONNECTION_STRING: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
 
 // create_azure_object_store creates a MicrosoftAzure object store with the given container name.
 // It is configured by environment variables and azure config files as fallback method.
 // We need to read the config files to make the fallback method work since object_store
 // does not provide a way to read them. Currently, we only support following environment
 // variables and config parameters:
-// - AZURE_STORAGE_ACCOUNT
-// - AZURE_STORAGE_KEY
-// - AZURE_STORAGE_CONNECTION_STRING
-// - AZURE_STORAGE_SAS_TOKEN
+// - AZURE_STORAGE_ACCOUNT (pg_parquet.azure_storage_account)
+// - AZURE_STORAGE_KEY (pg_parquet.azure_storage_key)
+// - AZURE_STORAGE_CONNECTION_STRING (pg_parquet.azure_storage_connection_string)
+// - AZURE_STORAGE_SAS_TOKEN (pg_parquet.azure_storage_sas_token)
 // - AZURE_CONFIG_FILE (env var only, object_store specific)
 // - AZURE_STORAGE_ENDPOINT (env var only, object_store specific)
 // - AZURE_ALLOW_HTTP (env var only, object_store specific)
+// Data Lake Storage Gen2 uris (abfss:// and dfs endpoints, including OneLake of Microsoft Fabric)
+// are accessed with the same credentials via the blob api.
 pub(crate) fn create_azure_object_store(uri: &Url) -> ObjectStoreWithExpiration {
-    let container_name = parse_azure_blob_container(uri).unwrap_or_else(|| {
-        panic!("unsupported azure blob storage uri: {}", uri);
+    let azure_uri = parse_azure_uri(uri).unwrap_or_else(|| {
+        panic!("unsupported azure blob storage uri: {}", redact_uri(uri));
     });
 
-    let mut azure_builder = MicrosoftAzureBuilder::new().with_container_name(container_name);
+    let mut azure_builder = MicrosoftAzureBuilder::new()
+        .with_container_name(azure_uri.container)
+        .with_client_options(object_store_client_options())
+        .with_retry(object_store_retry_config());
 
     let azure_blob_config = AzureStorageConfig::load();
 
@@ -47,6 +80,16 @@ pub(crate) fn create_azure_object_store(uri: &Url) -> ObjectStoreWithExpiration
         azure_builder = azure_builder.with_account(account_name);
     }
 
+    // account of the uri, e.g. abfss://{container}@{account}.dfs.core.windows.net/key
+    if let Some(account_name) = azure_uri.account {
+        azure_builder = azure_builder.with_account(account_name);
+    }
+
+    // OneLake endpoint of Microsoft Fabric
+    if azure_uri.use_fabric_endpoint {
+        azure_builder = azure_builder.with_config(AzureConfigKey::UseFabricEndpoint, "true");
+    }
+
     // account key
     if let Some(account_key) = azure_blob_config.account_key {
         azure_builder = azure_builder.with_access_key(account_key);
@@ -79,26 +122,120 @@ pub(crate) fn create_azure_object_store(uri: &Url) -> ObjectStoreWithExpiration
 }
 
 pub(crate) fn parse_azure_blob_container(uri: &Url) -> Option<String> {
+    parse_azure_uri(uri).map(|azure_uri| azure_uri.container)
+}
+
+// AzureUri is the container and the object path of an azure uri, and the storage account
+// if the uri specifies it.
+pub(crate) struct AzureUri {
+    pub(crate) container: String,
+    pub(crate) path: Path,
+    pub(crate) account: Option<String>,
+    // OneLake of Microsoft Fabric
+    pub(crate) use_fabric_endpoint: bool,
+}
+
+pub(crate) fn parse_azure_uri(uri: &Url) -> Option<AzureUri> {
     let host = uri.host_str()?;
 
-    // az(ure)://{container}/key
-    if uri.scheme() == "az" || uri.scheme() == "azure" {
-        return Some(host.to_string());
+    match uri.scheme() {
+        // az(ure)://{container}/key
+        "az" | "azure" | "adl" => Some(AzureUri {
+            container: host.to_string(),
+            path: Path::from_url_path(uri.path()).ok()?,
+            account: None,
+            use_fabric_endpoint: false,
+        }),
+        // abfs(s)://{container}@{account}.dfs.core.windows.net/key
+        // abfs(s)://{workspace}@onelake.dfs.fabric.microsoft.com/key
+        // abfs(s)://{container}/key
+        "abfs" | "abfss" => {
+            let path = Path::from_url_path(uri.path()).ok()?;
+
+            if uri.username().is_empty() {
+                return Some(AzureUri {
+                    container: host.to_string(),
+                    path,
+                    account: None,
+                    use_fabric_endpoint: false,
+                });
+            }
+
+            let (account, use_fabric_endpoint) = parse_azure_dfs_account(host)?;
+
+            Some(AzureUri {
+                container: uri.username().to_string(),
+                path,
+                account: Some(account),
+                use_fabric_endpoint,
+            })
+        }
+        // https://{account}.blob.core.windows.net/{container}
+        "https" if host.ends_with(".blob.core.windows.net") => {
+            let container = uri.path_segments()?.next()?.to_string();
+
+            Some(AzureUri {
+                container,
+                path: Path::from_url_path(uri.path()).ok()?,
+                account: None,
+                use_fabric_endpoint: false,
+            })
+        }
+        // https://{account}.dfs.core.windows.net/{container}/key
+        // https://onelake.dfs.fabric.microsoft.com/{workspace}/key
+        "https" => {
+            let (account, use_fabric_endpoint) = parse_azure_dfs_account(host)?;
+
+            let (container, path) = uri.path().strip_prefix('/')?.split_once('/')?;
+
+            if container.is_empty() {
+                return None;
+            }
+
+            Some(AzureUri {
+                container: container.to_string(),
+                path: Path::from_url_path(path).ok()?,
+                account: Some(account),
+                use_fabric_endpoint,
+            })
+        }
+        _ => None,
     }
-    // https://{account}.blob.core.windows.net/{container}
-    else if host.ends_with(".blob.core.windows.net") {
-        let path_segments: Vec<&str> = uri.path_segments()?.collect();
-
-        // Container name is the first part of the path
-        return Some(
-            path_segments
-                .first()
-                .expect("unexpected error during parsing azure blob uri")
-                .to_string(),
-        );
+}
+
+// parse_azure_dfs_account returns the storage account of a Data Lake Storage Gen2 host, and
+// whether it is a OneLake host of Microsoft Fabric.
+fn parse_azure_dfs_account(host: &str) -> Option<(String, bool)> {
+    if let Some(account) = host.strip_suffix(".dfs.core.windows.net") {
+        Some((account.to_string(), false))
+    } else if let Some(account) = host.strip_suffix(".dfs.fabric.microsoft.com") {
+        Some((account.to_string(), true))
+    } else {
+        None
+    }
+}
+
+fn azure_credential_guc_value(guc: &GucSetting<Option<&'static CStr>>) -> Option<String> {
+    guc.get()
+        .map(|value| value.to_str().unwrap_or_default().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// azure_credential_gucs_hash returns the hash of the azure credential GUCs, which changes
+// when any of them is changed in the session.
+pub(crate) fn azure_credential_gucs_hash() -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for guc in [
+        &AZURE_STORAGE_ACCOUNT,
+        &AZURE_STORAGE_KEY,
+        &AZURE_STORAGE_SAS_TOKEN,
+        &AZURE_STORAGE_CONNECTION_STRING,
+    ] {
+        azure_credential_guc_value(guc).hash(&mut hasher);
     }
 
-    None
+    hasher.finish()
 }
 
 // AzureStorageConfig is a struct that holds the configuration that is
@@ -135,13 +272,23 @@ impl AzureStorageConfig {
 
         let azure_config_content = Ini::load_from_file(&azure_config_file_path).ok();
 
-        let connection_string = match std::env::var("AZURE_STORAGE_CONNECTION_STRING") {
-            Ok(connection_string) => Some(connection_string),
-            Err(_) => azure_config_content
-                .as_ref()
-                .and_then(|ini| ini.section(Some("storage")))
-                .and_then(|section| section.get("connection_string"))
-                .map(|connection_string| connection_string.to_string()),
+        // credentials of the GUCs take precedence over the connection string of the
+        // environment variables and the config file
+        let credential_gucs_set = azure_credential_guc_value(&AZURE_STORAGE_ACCOUNT).is_some()
+            || azure_credential_guc_value(&AZURE_STORAGE_KEY).is_some()
+            || azure_credential_guc_value(&AZURE_STORAGE_SAS_TOKEN).is_some();
+
+        let connection_string = match azure_credential_guc_value(&AZURE_STORAGE_CONNECTION_STRING) {
+            Some(connection_string) => Some(connection_string),
+            None if credential_gucs_set => None,
+            None => match std::env::var("AZURE_STORAGE_CONNECTION_STRING") {
+                Ok(connection_string) => Some(connection_string),
+                Err(_) => azure_config_content
+                    .as_ref()
+                    .and_then(|ini| ini.section(Some("storage")))
+                    .and_then(|section| section.get("connection_string"))
+                    .map(|connection_string| connection_string.to_string()),
+            },
         };
 
         // connection string overrides everything
@@ -151,32 +298,35 @@ impl AzureStorageConfig {
             }
         }
 
-        let account_name = match std::env::var("AZURE_STORAGE_ACCOUNT") {
-            Ok(account) => Some(account),
-            Err(_) => azure_config_content
-                .as_ref()
-                .and_then(|ini| ini.section(Some("storage")))
-                .and_then(|section| section.get("account"))
-                .map(|account| account.to_string()),
-        };
-
-        let account_key = match std::env::var("AZURE_STORAGE_KEY") {
-            Ok(key) => Some(key),
-            Err(_) => azure_config_content
-                .as_ref()
-                .and_then(|ini| ini.section(Some("storage")))
-                .and_then(|section| section.get("key"))
-                .map(|key| key.to_string()),
-        };
-
-        let sas_token = match std::env::var("AZURE_STORAGE_SAS_TOKEN") {
-            Ok(token) => Some(token),
-            Err(_) => azure_config_content
-                .as_ref()
-                .and_then(|ini| ini.section(Some("storage")))
-                .and_then(|section| section.get("sas_token"))
-                .map(|token| token.to_string()),
-        };
+        let account_name = azure_credential_guc_value(&AZURE_STORAGE_ACCOUNT)
+            .or_else(|| std::env::var("AZURE_STORAGE_ACCOUNT").ok())
+            .or_else(|| {
+                azure_config_content
+                    .as_ref()
+                    .and_then(|ini| ini.section(Some("storage")))
+                    .and_then(|section| section.get("account"))
+                    .map(|account| account.to_string())
+            });
+
+        let account_key = azure_credential_guc_value(&AZURE_STORAGE_KEY)
+            .or_else(|| std::env::var("AZURE_STORAGE_KEY").ok())
+            .or_else(|| {
+                azure_config_content
+                    .as_ref()
+                    .and_then(|ini| ini.section(Some("storage")))
+                    .and_then(|section| section.get("key"))
+                    .map(|key| key.to_string())
+            });
+
+        let sas_token = azure_credential_guc_value(&AZURE_STORAGE_SAS_TOKEN)
+            .or_else(|| std::env::var("AZURE_STORAGE_SAS_TOKEN").ok())
+            .or_else(|| {
+                azure_config_content
+                    .as_ref()
+                    .and_then(|ini| ini.section(Some("storage")))
+                    .and_then(|section| section.get("sas_token"))
+                    .map(|token| token.to_string())
+            });
 
         // endpoint, object_store specific
         let endpoint = std::env::var("AZURE_STORAGE_ENDPOINT").ok();
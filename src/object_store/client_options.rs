@@ -0,0 +1,59 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
+
+use object_store::{BackoffConfig, ClientOptions, RetryConfig};
+use pgrx::GucSetting;
+
+// OBJECT_STORE_MAX_RETRIES is the max number of retries of a failed object store request,
+// e.g. with a 5xx status or a connection error.
+pub(crate) static OBJECT_STORE_MAX_RETRIES: GucSetting<i32> = GucSetting::<i32>::new(10);
+
+// OBJECT_STORE_RETRY_TIMEOUT_MS is the time (in ms) since the first attempt of a request,
+// after which it is not retried anymore.
+pub(crate) static OBJECT_STORE_RETRY_TIMEOUT_MS: GucSetting<i32> =
+    GucSetting::<i32>::new(3 * 60 * 1000);
+
+// OBJECT_STORE_REQUEST_TIMEOUT_MS is the timeout (in ms) of a single object store request.
+pub(crate) static OBJECT_STORE_REQUEST_TIMEOUT_MS: GucSetting<i32> =
+    GucSetting::<i32>::new(30 * 1000);
+
+// object_store_retry_config returns the retry config of the object store clients.
+pub(crate) fn object_store_retry_config() -> RetryConfig {
+    RetryConfig {
+        backoff: BackoffConfig::default(),
+        max_retries: OBJECT_STORE_MAX_RETRIES.get() as usize,
+        retry_timeout: object_store_retry_timeout(),
+    }
+}
+
+pub(crate) fn object_store_retry_timeout() -> Duration {
+    Duration::from_millis(OBJECT_STORE_RETRY_TIMEOUT_MS.get() as u64)
+}
+
+pub(crate) fn object_store_request_timeout() -> Duration {
+    Duration::from_millis(OBJECT_STORE_REQUEST_TIMEOUT_MS.get() as u64)
+}
+
+// object_store_client_options returns the http client options of the object store clients.
+// allow_http is set by the builders, hence the options should be set before it.
+pub(crate) fn object_store_client_options() -> ClientOptions {
+    ClientOptions::new().with_timeout(object_store_request_timeout())
+}
+
+// object_store_client_gucs_hash returns the hash of the retry and timeout GUCs, which changes
+// when any of them is changed in the session.
+pub(crate) fn object_store_client_gucs_hash() -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for guc in [
+        &OBJECT_STORE_MAX_RETRIES,
+        &OBJECT_STORE_RETRY_TIMEOUT_MS,
+        &OBJECT_STORE_REQUEST_TIMEOUT_MS,
+    ] {
+        guc.get().hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
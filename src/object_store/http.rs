@@ -0,0 +1,389 @@
+use std::{fmt::Display, sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use object_store::{
+    path::Path, Attributes, GetOptions, GetRange, GetResult, GetResultPayload, ListResult,
+    MultipartUpload, ObjectMeta, ObjectStore, PutMultipartOpts, PutOptions, PutPayload, PutResult,
+    RetryConfig,
+};
+use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_RANGE, ETAG, RANGE},
+    Client, Response, StatusCode,
+};
+use url::Url;
+
+use super::{
+    client_options::{object_store_request_timeout, object_store_retry_config},
+    object_store_cache::ObjectStoreWithExpiration,
+    redact_uri,
+};
+
+const HTTP_STORE: &str = "HTTP";
+
+// PRESIGNED_URI_QUERY_PARAMS are the query parameters, which mark an http(s) uri as a presigned
+// url of an object store. Such uris are read with plain http requests, even when their host is
+// a known object store, since the signature grants the access instead of the credentials.
+const PRESIGNED_URI_QUERY_PARAMS: &[&str] = &["x-amz-signature", "x-goog-signature"];
+
+// is_presigned_uri returns true if the uri is an http(s) uri with the signature of a presigned url.
+pub(crate) fn is_presigned_uri(uri: &Url) -> bool {
+    if uri.scheme() != "https" && uri.scheme() != "http" {
+        return false;
+    }
+
+    uri.query_pairs()
+        .any(|(key, _)| PRESIGNED_URI_QUERY_PARAMS.contains(&key.to_lowercase().as_str()))
+}
+
+// http_host returns the host and the port of the uri, by which the http clients are cached.
+// The query of the uri, which contains the signature of a presigned url, is not a part of it.
+pub(crate) fn http_host(uri: &Url) -> String {
+    let host = uri
+        .host_str()
+        .unwrap_or_else(|| panic!("uri {} is missing the host", redact_uri(uri)));
+
+    match uri.port() {
+        Some(port) => format!("{}://{}:{}", uri.scheme(), host, port),
+        None => format!("{}://{}", uri.scheme(), host),
+    }
+}
+
+// create_http_client creates the http client, which is shared by the http uris of a host.
+pub(crate) fn create_http_client() -> Client {
+    Client::builder()
+        .timeout(object_store_request_timeout())
+        .build()
+        .unwrap_or_else(|e| panic!("failed to create http client: {}", e))
+}
+
+// create_http_object_store creates a read-only object store for the http(s) uri, e.g. a
+// presigned url, which sends the requests to the uri as is.
+pub(crate) fn create_http_object_store(uri: &Url, client: Client) -> ObjectStoreWithExpiration {
+    let object_store = HttpUriObjectStore {
+        uri: uri.clone(),
+        client,
+        retry_config: object_store_retry_config(),
+    };
+
+    // presigned urls expire on their own, the client does not hold any credentials
+    let expire_at = None;
+
+    ObjectStoreWithExpiration {
+        object_store: Arc::new(object_store),
+        expire_at,
+    }
+}
+
+// HttpUriObjectStore reads the object at a single http(s) uri. Presigned urls are signed for
+// GET requests only, hence the size of the object is read from the Content-Range of a ranged
+// GET request instead of a HEAD request.
+struct HttpUriObjectStore {
+    uri: Url,
+    client: Client,
+    retry_config: RetryConfig,
+}
+
+impl std::fmt::Debug for HttpUriObjectStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HttpUriObjectStore({})", redact_uri(&self.uri))
+    }
+}
+
+impl Display for HttpUriObjectStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HttpUriObjectStore({})", redact_uri(&self.uri))
+    }
+}
+
+impl HttpUriObjectStore {
+    // send_get_request sends a GET request to the uri, which is retried on connection errors
+    // and 5xx statuses with backoff like the requests of the other object stores. It returns
+    // the response with the number of retries.
+    async fn send_get_request(
+        &self,
+        range: Option<&GetRange>,
+    ) -> object_store::Result<(Response, usize)> {
+        let started_at = Instant::now();
+        let mut retries = 0;
+        let mut backoff = self.retry_config.backoff.init_backoff;
+
+        loop {
+            let mut request = self.client.get(self.uri.clone());
+
+            if let Some(range) = range {
+                request = request.header(RANGE, range.to_string());
+            }
+
+            let result = request.send().await;
+
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            };
+
+            if !retryable
+                || retries == self.retry_config.max_retries
+                || started_at.elapsed() > self.retry_config.retry_timeout
+            {
+                return match result {
+                    Ok(response) => Ok((response, retries)),
+                    Err(e) => Err(object_store::Error::Generic {
+                        store: HTTP_STORE,
+                        source: format!("error after {} retries: {}", retries, e.without_url())
+                            .into(),
+                    }),
+                };
+            }
+
+            tokio::time::sleep(backoff).await;
+
+            backoff = backoff
+                .mul_f64(self.retry_config.backoff.base)
+                .min(self.retry_config.backoff.max_backoff);
+            retries += 1;
+        }
+    }
+
+    fn error_for_status(
+        &self,
+        location: &Path,
+        status: StatusCode,
+        retries: usize,
+    ) -> Option<object_store::Error> {
+        if status.is_success() {
+            return None;
+        }
+
+        let path = location.to_string();
+        let source = format!(
+            "{} returned {} after {} retries",
+            redact_uri(&self.uri),
+            status,
+            retries
+        )
+        .into();
+
+        let error = match status {
+            StatusCode::NOT_FOUND => object_store::Error::NotFound { path, source },
+            StatusCode::FORBIDDEN => object_store::Error::PermissionDenied { path, source },
+            StatusCode::UNAUTHORIZED => object_store::Error::Unauthenticated { path, source },
+            _ => object_store::Error::Generic {
+                store: HTTP_STORE,
+                source,
+            },
+        };
+
+        Some(error)
+    }
+}
+
+// parse_content_range parses "bytes {start}-{end}/{size}" into the returned range and the
+// size of the object.
+fn parse_content_range(content_range: &str) -> Option<(std::ops::Range<usize>, usize)> {
+    let (range, size) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+    let size = size.parse::<usize>().ok()?;
+
+    // unsatisfiable range, e.g. "bytes */0" for an empty object
+    if range == "*" {
+        return Some((0..0, size));
+    }
+
+    let (start, end) = range.split_once('-')?;
+
+    Some((start.parse().ok()?..end.parse::<usize>().ok()? + 1, size))
+}
+
+// requested_range returns the range of the object with the given size, which is requested by
+// the GetRange, like the object stores which support ranges.
+fn requested_range(range: &GetRange, size: usize) -> std::ops::Range<usize> {
+    match range {
+        GetRange::Bounded(range) => range.start.min(size)..range.end.min(size),
+        GetRange::Offset(offset) => (*offset).min(size)..size,
+        GetRange::Suffix(suffix) => size.saturating_sub(*suffix)..size,
+    }
+}
+
+// slice_payload returns the bytes of the range from the payload of the whole object. The
+// payload is not read further after the end of the range.
+fn slice_payload(
+    payload: impl Stream<Item = object_store::Result<Bytes>>,
+    range: std::ops::Range<usize>,
+) -> impl Stream<Item = object_store::Result<Bytes>> {
+    payload
+        .scan(0, move |offset: &mut usize, bytes| {
+            let bytes = match bytes {
+                Ok(bytes) if *offset < range.end => bytes,
+                Ok(_) => return futures::future::ready(None),
+                Err(e) => return futures::future::ready(Some(Err(e))),
+            };
+
+            let chunk = *offset..*offset + bytes.len();
+            *offset = chunk.end;
+
+            let start = range.start.clamp(chunk.start, chunk.end) - chunk.start;
+            let end = range.end.clamp(chunk.start, chunk.end) - chunk.start;
+
+            futures::future::ready(Some(Ok(bytes.slice(start..end))))
+        })
+        .try_filter(|bytes| futures::future::ready(!bytes.is_empty()))
+}
+
+fn not_supported(operation: &str) -> object_store::Error {
+    object_store::Error::NotSupported {
+        source: format!("{} is not supported for presigned URLs", operation).into(),
+    }
+}
+
+#[async_trait]
+impl ObjectStore for HttpUriObjectStore {
+    async fn put_opts(
+        &self,
+        _location: &Path,
+        _payload: PutPayload,
+        _opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        Err(not_supported("write"))
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        _location: &Path,
+        _opts: PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        Err(not_supported("write"))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        // the first byte is enough to learn the size of the object
+        let range = if options.head {
+            Some(GetRange::Bounded(0..1))
+        } else {
+            options.range
+        };
+
+        let (response, retries) = self.send_get_request(range.as_ref()).await?;
+
+        let status = response.status();
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let content_range = header(CONTENT_RANGE).and_then(|range| parse_content_range(&range));
+
+        // an empty object does not have a first byte
+        if status == StatusCode::RANGE_NOT_SATISFIABLE && options.head {
+            if let Some((range, size)) = content_range.clone().filter(|(_, size)| *size == 0) {
+                return Ok(GetResult {
+                    payload: GetResultPayload::Stream(futures::stream::empty().boxed()),
+                    meta: ObjectMeta {
+                        location: location.clone(),
+                        last_modified: Default::default(),
+                        size,
+                        e_tag: header(ETAG),
+                        version: None,
+                    },
+                    range,
+                    attributes: Attributes::default(),
+                });
+            }
+        }
+
+        if let Some(error) = self.error_for_status(location, status, retries) {
+            return Err(error);
+        }
+
+        let partial_content = status == StatusCode::PARTIAL_CONTENT && content_range.is_some();
+
+        let (returned_range, size) = match content_range {
+            Some(content_range) if partial_content => content_range,
+            _ => {
+                let size = header(CONTENT_LENGTH)
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .ok_or_else(|| object_store::Error::Generic {
+                        store: HTTP_STORE,
+                        source: format!(
+                            "{} did not return the size of the object",
+                            redact_uri(&self.uri)
+                        )
+                        .into(),
+                    })?;
+
+                // servers, which ignore the range, return the whole object, which is sliced
+                // to the range below
+                let returned_range = range
+                    .as_ref()
+                    .map(|range| requested_range(range, size))
+                    .unwrap_or(0..size);
+
+                (returned_range, size)
+            }
+        };
+
+        let meta = ObjectMeta {
+            location: location.clone(),
+            // the modification time is not used by the parquet reader
+            last_modified: Default::default(),
+            size,
+            e_tag: header(ETAG),
+            version: None,
+        };
+
+        let payload = if options.head {
+            futures::stream::empty().boxed()
+        } else {
+            let payload = response
+                .bytes_stream()
+                .map_err(|e| object_store::Error::Generic {
+                    store: HTTP_STORE,
+                    source: e.without_url().into(),
+                });
+
+            if partial_content {
+                payload.boxed()
+            } else {
+                slice_payload(payload, returned_range.clone()).boxed()
+            }
+        };
+
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(payload),
+            meta,
+            range: returned_range,
+            attributes: Attributes::default(),
+        })
+    }
+
+    async fn delete(&self, _location: &Path) -> object_store::Result<()> {
+        Err(not_supported("delete"))
+    }
+
+    fn list(&self, _prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        futures::stream::once(async { Err(not_supported("list")) }).boxed()
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        _prefix: Option<&Path>,
+    ) -> object_store::Result<ListResult> {
+        Err(not_supported("list"))
+    }
+
+    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_supported("copy"))
+    }
+
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
+        Err(not_supported("copy"))
+    }
+}
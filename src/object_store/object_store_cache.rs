@@ -2,22 +2,39 @@ use std::{
     collections::HashMap,
     hash::{Hash, Hasher},
     sync::Arc,
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use object_store::{path::Path, ObjectStore, ObjectStoreScheme};
 use once_cell::sync::Lazy;
-use pgrx::{ereport, PgLogLevel, PgSqlErrorCode};
+use pgrx::{ereport, GucSetting, PgLogLevel, PgSqlErrorCode};
+use reqwest::Client;
 use url::Url;
 
 use super::{
-    aws::parse_s3_bucket, azure::parse_azure_blob_container, create_azure_object_store,
-    create_local_file_object_store, create_s3_object_store,
+    aws::{
+        create_s3_object_store_with_credentials, parse_s3_bucket, parse_s3_compatible_uri,
+        s3_credential_gucs_hash, BucketCredentials,
+    },
+    azure::{azure_credential_gucs_hash, parse_azure_blob_container, parse_azure_uri},
+    client_options::object_store_client_gucs_hash,
+    create_azure_object_store, create_http_object_store, create_local_file_object_store,
+    create_s3_object_store,
+    http::{create_http_client, http_host, is_presigned_uri},
+    redact_uri,
 };
 
+// CREDENTIALS_EXPIRY_MARGIN is the time before the expiry of the credentials, after which
+// a cached object store is considered expired.
+const CREDENTIALS_EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+// OBJECT_STORE_CACHE_MAX_ENTRIES is the max number of cached object stores, and separately of
+// cached http clients. The least recently used one is evicted when a new one is cached.
+pub(crate) static OBJECT_STORE_CACHE_MAX_ENTRIES: GucSetting<i32> = GucSetting::<i32>::new(100);
+
 // OBJECT_STORE_CACHE is a global cache for object stores per Postgres session.
 // It caches object stores based on the scheme and bucket.
-// Local paths are not cached.
+// Local paths are not cached, and http(s) uris only cache the http client of their host.
 static mut OBJECT_STORE_CACHE: Lazy<ObjectStoreCache> = Lazy::new(ObjectStoreCache::new);
 
 pub(crate) fn get_or_create_object_store(
@@ -30,24 +47,158 @@ pub(crate) fn get_or_create_object_store(
     }
 }
 
+// create_object_store_with_options creates a one-off object store for the uri, which is
+// configured by the options of a single call instead of the session. It is not cached, so
+// that the options do not apply to the later calls of the session.
+pub(crate) fn create_object_store_with_options(
+    uri: &Url,
+    options: &BucketCredentials,
+) -> (Arc<dyn ObjectStore>, Path) {
+    let (scheme, path) = parse_object_store_uri(uri);
+
+    if scheme != ObjectStoreScheme::AmazonS3 {
+        panic!(
+            "options are only supported for s3 uris, not {}",
+            redact_uri(uri)
+        );
+    }
+
+    let item = create_s3_object_store_with_credentials(uri, Some(options.clone()));
+
+    (item.object_store, path)
+}
+
+// ObjectStoreCacheItem is an object store, or an http client, in the cache of the session.
+pub(crate) struct ObjectStoreCacheItem {
+    pub(crate) scheme: String,
+    pub(crate) bucket: String,
+    pub(crate) created_at: SystemTime,
+    pub(crate) expire_at: Option<SystemTime>,
+    pub(crate) hit_count: i64,
+}
+
+pub(crate) fn object_store_cache_items() -> Vec<ObjectStoreCacheItem> {
+    #[allow(static_mut_refs)]
+    unsafe {
+        OBJECT_STORE_CACHE.items()
+    }
+}
+
+pub(crate) fn clear_object_store_cache() {
+    #[allow(static_mut_refs)]
+    unsafe {
+        OBJECT_STORE_CACHE.cache.clear();
+        OBJECT_STORE_CACHE.http_clients.clear();
+    }
+}
+
+// parse_object_store_uri returns the scheme and the object path of the uri. http(s) uris of
+// S3-compatible object stores are recognized as s3 uris, and the container of azure dfs uris
+// is not a part of the object path. Presigned urls are read over http regardless of the host.
+fn parse_object_store_uri(uri: &Url) -> (ObjectStoreScheme, Path) {
+    if is_presigned_uri(uri) {
+        let path = Path::from_url_path(uri.path()).unwrap_or_else(|e| panic!("{}", e));
+        return (ObjectStoreScheme::Http, path);
+    }
+
+    if let Some(s3_compatible_uri) = parse_s3_compatible_uri(uri) {
+        return (ObjectStoreScheme::AmazonS3, s3_compatible_uri.path);
+    }
+
+    if let Some(azure_uri) = parse_azure_uri(uri) {
+        return (ObjectStoreScheme::MicrosoftAzure, azure_uri.path);
+    }
+
+    ObjectStoreScheme::parse(uri).unwrap_or_else(|_| {
+        panic!(
+            "unrecognized uri {}. pg_parquet supports local paths, s3:// or azure:// schemes.",
+            redact_uri(uri)
+        )
+    })
+}
+
+// resolve_object_store_uri returns the scheme, the bucket and the object path, which
+// get_or_create_object_store resolves the uri to, e.g. (AmazonS3, "bucket", "dir/key") for
+// "s3a://bucket/dir/key", "https://bucket.s3.amazonaws.com/dir/key" and
+// "https://s3.amazonaws.com/bucket/dir/key". Presigned urls are resolved by their host, too.
+// It returns None for the uris of other object stores.
+pub(crate) fn resolve_object_store_uri(uri: &Url) -> Option<(ObjectStoreScheme, String, Path)> {
+    if let Some(s3_compatible_uri) = parse_s3_compatible_uri(uri) {
+        return Some((
+            ObjectStoreScheme::AmazonS3,
+            s3_compatible_uri.bucket,
+            s3_compatible_uri.path,
+        ));
+    }
+
+    if let Some(azure_uri) = parse_azure_uri(uri) {
+        // the container of https://{account}.blob.core.windows.net/{container}/key is the
+        // first part of the object path
+        let path = if uri.scheme() == "https" && uri.host_str()?.ends_with(".blob.core.windows.net")
+        {
+            azure_uri.path.parts().skip(1).collect()
+        } else {
+            azure_uri.path
+        };
+
+        return Some((ObjectStoreScheme::MicrosoftAzure, azure_uri.container, path));
+    }
+
+    let (scheme, path) = ObjectStoreScheme::parse(uri).ok()?;
+
+    if scheme != ObjectStoreScheme::AmazonS3 {
+        return None;
+    }
+
+    let bucket = match uri.scheme() {
+        "s3" | "s3a" => uri.host_str()?.to_string(),
+        _ => parse_s3_bucket(uri)?,
+    };
+
+    Some((scheme, bucket, path))
+}
+
 struct ObjectStoreCache {
-    cache: HashMap<ObjectStoreCacheKey, ObjectStoreWithExpiration>,
+    cache: HashMap<ObjectStoreCacheKey, ObjectStoreCacheEntry<ObjectStoreWithExpiration>>,
+    // hash of the azure credential GUCs, with which the cached azure object stores are created
+    azure_credential_gucs_hash: u64,
+    // hash of pg_parquet.bucket_credentials and the aws credential GUCs, with which the cached
+    // s3 object stores are created
+    s3_credential_gucs_hash: u64,
+    // hash of the retry and timeout GUCs, with which all cached object stores are created
+    object_store_client_gucs_hash: u64,
+    // http clients by the scheme, host and port of http(s) uris, whose query is not a part
+    // of the key since the signature of presigned urls differs for each url
+    http_clients: HashMap<String, ObjectStoreCacheEntry<Client>>,
+    // incremented by each lookup, so that the entry with the smallest last_used is the least
+    // recently used one
+    lookup_count: u64,
 }
 
 impl ObjectStoreCache {
     fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            azure_credential_gucs_hash: azure_credential_gucs_hash(),
+            s3_credential_gucs_hash: s3_credential_gucs_hash(),
+            object_store_client_gucs_hash: object_store_client_gucs_hash(),
+            http_clients: HashMap::new(),
+            lookup_count: 0,
         }
     }
 
     fn get_or_create(&mut self, uri: &Url, copy_from: bool) -> (Arc<dyn ObjectStore>, Path) {
-        let (scheme, path) = ObjectStoreScheme::parse(uri).unwrap_or_else(|_| {
-            panic!(
-                "unrecognized uri {}. pg_parquet supports local paths, s3:// or azure:// schemes.",
-                uri
-            )
-        });
+        let (scheme, path) = parse_object_store_uri(uri);
+
+        // all object stores and http clients are recreated with the new retry and timeout
+        // config when any of the GUCs is changed in the session
+        let object_store_client_gucs_hash = object_store_client_gucs_hash();
+
+        if object_store_client_gucs_hash != self.object_store_client_gucs_hash {
+            self.cache.clear();
+            self.http_clients.clear();
+            self.object_store_client_gucs_hash = object_store_client_gucs_hash;
+        }
 
         // no need to cache for local files
         if scheme == ObjectStoreScheme::Local {
@@ -55,23 +206,103 @@ impl ObjectStoreCache {
             return (item.object_store, path);
         }
 
+        // http(s) uris are read-only, and each of them gets its own object store, which sends
+        // the requests to the uri with its query
+        if scheme == ObjectStoreScheme::Http {
+            if !copy_from {
+                panic!("cannot write to presigned URL {}", redact_uri(uri));
+            }
+
+            self.lookup_count += 1;
+
+            let host = http_host(uri);
+
+            let client = match self.http_clients.get_mut(&host) {
+                Some(entry) => entry.hit(self.lookup_count).clone(),
+                None => {
+                    let client = create_http_client();
+                    insert_cache_entry(
+                        &mut self.http_clients,
+                        host,
+                        client.clone(),
+                        self.lookup_count,
+                    );
+                    client
+                }
+            };
+
+            let item = create_http_object_store(uri, client);
+            return (item.object_store, path);
+        }
+
+        // azure object stores are recreated with the new credentials when any azure
+        // credential GUC is changed in the session
+        if scheme == ObjectStoreScheme::MicrosoftAzure {
+            let azure_credential_gucs_hash = azure_credential_gucs_hash();
+
+            if azure_credential_gucs_hash != self.azure_credential_gucs_hash {
+                self.cache
+                    .retain(|key, _| key.scheme != ObjectStoreScheme::MicrosoftAzure);
+                self.azure_credential_gucs_hash = azure_credential_gucs_hash;
+            }
+        }
+
+        // s3 object stores are recreated with the new credentials when
+        // pg_parquet.bucket_credentials or any aws credential GUC is changed in the session
+        if scheme == ObjectStoreScheme::AmazonS3 {
+            let s3_credential_gucs_hash = s3_credential_gucs_hash();
+
+            if s3_credential_gucs_hash != self.s3_credential_gucs_hash {
+                self.cache
+                    .retain(|key, _| key.scheme != ObjectStoreScheme::AmazonS3);
+                self.s3_credential_gucs_hash = s3_credential_gucs_hash;
+            }
+        }
+
         let key = ObjectStoreCacheKey::from_uri(uri, scheme.clone());
 
-        if let Some(item) = self.cache.get(&key) {
-            if item.expired(&key.bucket) {
-                self.cache.remove(&key);
-            } else {
+        self.lookup_count += 1;
+
+        // an expired entry is replaced by the new object store below
+        if let Some(entry) = self.cache.get_mut(&key) {
+            if !entry.value.expired(&key.bucket) {
+                let item = entry.hit(self.lookup_count);
                 return (item.object_store.clone(), path);
             }
         }
 
         let item = Self::create(scheme, uri, copy_from);
 
-        self.cache.insert(key, item.clone());
+        insert_cache_entry(&mut self.cache, key, item.clone(), self.lookup_count);
 
         (item.object_store.clone(), path)
     }
 
+    fn items(&self) -> Vec<ObjectStoreCacheItem> {
+        let http_items = self
+            .http_clients
+            .iter()
+            .map(|(host, entry)| ObjectStoreCacheItem {
+                scheme: "Http".to_string(),
+                bucket: host.clone(),
+                created_at: entry.created_at,
+                expire_at: None,
+                hit_count: entry.hit_count,
+            });
+
+        self.cache
+            .iter()
+            .map(|(key, entry)| ObjectStoreCacheItem {
+                scheme: format!("{:?}", key.scheme),
+                bucket: key.bucket.clone(),
+                created_at: entry.created_at,
+                expire_at: entry.value.expire_at,
+                hit_count: entry.hit_count,
+            })
+            .chain(http_items)
+            .collect()
+    }
+
     fn create(scheme: ObjectStoreScheme, uri: &Url, copy_from: bool) -> ObjectStoreWithExpiration {
         // object_store crate can recognize a bunch of different schemes and paths, but we only support
         // local, azure, and s3 schemes with a subset of all supported paths.
@@ -82,12 +313,64 @@ impl ObjectStoreCache {
             _ => panic!(
                     "unsupported scheme {} in uri {}. pg_parquet supports local paths, s3:// or azure:// schemes.",
                     uri.scheme(),
-                    uri
+                    redact_uri(uri)
                 ),
         }
     }
 }
 
+// ObjectStoreCacheEntry is a value for the object store cache maps, which keeps track of
+// its usage for parquet.object_store_cache() and the eviction of the least recently used one.
+struct ObjectStoreCacheEntry<T> {
+    value: T,
+    created_at: SystemTime,
+    hit_count: i64,
+    last_used: u64,
+}
+
+impl<T> ObjectStoreCacheEntry<T> {
+    fn hit(&mut self, lookup_count: u64) -> &T {
+        self.hit_count += 1;
+        self.last_used = lookup_count;
+        &self.value
+    }
+}
+
+// insert_cache_entry caches the value, after evicting the least recently used entries if the
+// cache is full. pg_parquet.object_store_cache_max_entries might have been lowered since the
+// last insert, hence more than one entry might be evicted.
+fn insert_cache_entry<K: Eq + Hash + Clone, T>(
+    entries: &mut HashMap<K, ObjectStoreCacheEntry<T>>,
+    key: K,
+    value: T,
+    lookup_count: u64,
+) {
+    let max_entries = OBJECT_STORE_CACHE_MAX_ENTRIES.get() as usize;
+
+    // an existing entry of the key is replaced
+    entries.remove(&key);
+
+    while entries.len() >= max_entries {
+        let least_recently_used_key = entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())
+            .expect("full cache has an entry");
+
+        entries.remove(&least_recently_used_key);
+    }
+
+    entries.insert(
+        key,
+        ObjectStoreCacheEntry {
+            value,
+            created_at: SystemTime::now(),
+            hit_count: 0,
+            last_used: lookup_count,
+        },
+    );
+}
+
 // ObjectStoreWithExpiration is a value for the object store cache map.
 #[derive(Clone)]
 pub(crate) struct ObjectStoreWithExpiration {
@@ -100,7 +383,8 @@ pub(crate) struct ObjectStoreWithExpiration {
 impl ObjectStoreWithExpiration {
     fn expired(&self, bucket: &str) -> bool {
         if let Some(expire_at) = self.expire_at {
-            let expired = expire_at < SystemTime::now();
+            // refresh credentials before they expire during the requests of the next command
+            let expired = expire_at < SystemTime::now() + CREDENTIALS_EXPIRY_MARGIN;
 
             if expired {
                 ereport!(
@@ -118,28 +402,36 @@ impl ObjectStoreWithExpiration {
 }
 
 // ObjectStoreCacheKey is a key for the object store cache map
-// We cache object stores based on the scheme and bucket.
-// i.e. 1 object store per scheme and bucket.
+// We cache object stores based on the scheme, bucket and endpoint.
+// i.e. 1 object store per scheme and bucket of an endpoint.
 #[derive(Clone, Eq, PartialEq)]
 struct ObjectStoreCacheKey {
     scheme: ObjectStoreScheme,
     bucket: String,
+    // endpoint of S3-compatible uris, since different stores might have buckets with same name
+    endpoint: Option<String>,
 }
 
 impl ObjectStoreCacheKey {
     fn from_uri(uri: &Url, scheme: ObjectStoreScheme) -> Self {
         let bucket = match scheme {
-            ObjectStoreScheme::AmazonS3 => parse_s3_bucket(uri).unwrap_or_else(|| panic!("unsupported s3 uri: {uri}")),
-            ObjectStoreScheme::MicrosoftAzure => parse_azure_blob_container(uri).unwrap_or_else(|| panic!("unsupported azure blob storage uri: {uri}")),
+            ObjectStoreScheme::AmazonS3 => parse_s3_bucket(uri).unwrap_or_else(|| panic!("unsupported s3 uri: {}", redact_uri(uri))),
+            ObjectStoreScheme::MicrosoftAzure => parse_azure_blob_container(uri).unwrap_or_else(|| panic!("unsupported azure blob storage uri: {}", redact_uri(uri))),
             ObjectStoreScheme::Local => panic!("local paths should not be cached"),
             _ => panic!(
                 "unsupported scheme {} in uri {}. pg_parquet supports local paths, s3:// or azure:// schemes.",
                 uri.scheme(),
-                uri
+                redact_uri(uri)
             ),
         };
 
-        ObjectStoreCacheKey { scheme, bucket }
+        let endpoint = parse_s3_compatible_uri(uri).and_then(|uri| uri.endpoint);
+
+        ObjectStoreCacheKey {
+            scheme,
+            bucket,
+            endpoint,
+        }
     }
 }
 
@@ -148,6 +440,7 @@ impl Hash for ObjectStoreCacheKey {
         let schema_tag = self.scheme.clone() as i32;
         schema_tag.hash(state);
         self.bucket.hash(state);
+        self.endpoint.hash(state);
     }
 }
 
@@ -155,19 +448,7 @@ impl Hash for ObjectStoreCacheKey {
 #[cfg(feature = "pg_test")]
 #[pgrx::pg_schema]
 mod parquet_test {
-    use std::time::UNIX_EPOCH;
-
-    use pgrx::{iter::TableIterator, name, pg_extern, pg_sys::Timestamp};
-
-    use super::OBJECT_STORE_CACHE;
-
-    #[pg_extern]
-    fn object_store_cache_clear() {
-        #[allow(static_mut_refs)]
-        unsafe {
-            OBJECT_STORE_CACHE.cache.clear();
-        }
-    }
+    use pgrx::pg_extern;
 
     #[pg_extern]
     fn object_store_cache_expire_bucket(bucket: &str) {
@@ -176,32 +457,4 @@ mod parquet_test {
 
         cache.retain(|key, _| key.bucket != bucket);
     }
-
-    #[pg_extern]
-    fn object_store_cache_items() -> TableIterator<
-        'static,
-        (
-            name!(scheme, String),
-            name!(bucket, String),
-            name!(expire_at, Option<Timestamp>),
-        ),
-    > {
-        #[allow(static_mut_refs)]
-        let cache = unsafe { &super::OBJECT_STORE_CACHE.cache };
-
-        let rows = cache
-            .iter()
-            .map(|(key, value)| {
-                let scheme = format!("{:?}", key.scheme);
-                let bucket = key.bucket.clone();
-                let expire_at = value
-                    .expire_at
-                    .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_micros() as Timestamp);
-
-                (scheme, bucket, expire_at)
-            })
-            .collect::<Vec<_>>();
-
-        TableIterator::new(rows)
-    }
 }
@@ -0,0 +1,91 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use object_store::{path::Path, ObjectMeta, ObjectStore};
+use once_cell::sync::Lazy;
+use url::Url;
+
+// MAX_WRITTEN_OBJECTS is the max number of objects, which are remembered as written by
+// the session. The oldest one is forgotten when a new object is written.
+const MAX_WRITTEN_OBJECTS: usize = 64;
+
+// WRITTEN_OBJECT_READ_RETRY_DELAYS are the delays between the retries of a read, which
+// fails with not found, for an object written by the session (~2 seconds in total).
+const WRITTEN_OBJECT_READ_RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_millis(1000),
+];
+
+// WRITTEN_OBJECTS holds the uris of the objects, which are recently written by
+// the Postgres session.
+static mut WRITTEN_OBJECTS: Lazy<VecDeque<String>> = Lazy::new(VecDeque::new);
+
+// mark_object_written remembers that the object at the given uri is written by the session.
+pub(crate) fn mark_object_written(uri: &Url) {
+    let key = written_object_key(uri);
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        WRITTEN_OBJECTS.retain(|written_key| *written_key != key);
+
+        if WRITTEN_OBJECTS.len() == MAX_WRITTEN_OBJECTS {
+            WRITTEN_OBJECTS.pop_front();
+        }
+
+        WRITTEN_OBJECTS.push_back(key);
+    }
+}
+
+// forget_written_object forgets the object at the given uri, e.g. after it is removed.
+pub(crate) fn forget_written_object(uri: &Url) {
+    let key = written_object_key(uri);
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        WRITTEN_OBJECTS.retain(|written_key| *written_key != key);
+    }
+}
+
+fn is_object_written(uri: &Url) -> bool {
+    let key = written_object_key(uri);
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        WRITTEN_OBJECTS.contains(&key)
+    }
+}
+
+// written_object_key identifies the object by the uri without its query, which might
+// contain credentials that differ between the write and the read.
+fn written_object_key(uri: &Url) -> String {
+    let mut uri = uri.clone();
+    uri.set_query(None);
+    uri.to_string()
+}
+
+// head_object returns the metadata of the object. Object stores with weaker read-after-write
+// consistency might not find an object, which is just written by the session. Hence, a read
+// of such an object is retried with backoff before not found error is returned. Objects,
+// which are not written by the session, fail immediately.
+pub(crate) async fn head_object(
+    object_store: &Arc<dyn ObjectStore>,
+    location: &Path,
+    uri: &Url,
+) -> object_store::Result<ObjectMeta> {
+    let mut retry_delays = WRITTEN_OBJECT_READ_RETRY_DELAYS.iter();
+
+    loop {
+        let result = object_store.head(location).await;
+
+        if !matches!(result, Err(object_store::Error::NotFound { .. })) || !is_object_written(uri) {
+            return result;
+        }
+
+        match retry_delays.next() {
+            Some(retry_delay) => tokio::time::sleep(*retry_delay).await,
+            None => return result,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub(crate) mod copy_from;
+pub(crate) mod copy_program;
 pub(crate) mod copy_to;
 pub(crate) mod copy_to_dest_receiver;
 pub(crate) mod copy_utils;
+pub(crate) mod export_allowlist;
 pub(crate) mod hook;
 pub(crate) mod pg_compat;
@@ -1,27 +1,45 @@
-use std::ffi::CStr;
+use std::ffi::{c_char, CStr};
 
 use pgrx::{
     ereport, pg_guard,
     pg_sys::{
-        addNSItemToQuery, assign_expr_collations, canonicalize_qual, check_enable_rls,
-        coerce_to_boolean, eval_const_expressions, make_ands_implicit, transformExpr, AsPgCStr,
-        BeginCopyFrom, CheckEnableRlsResult, CopyFrom, CopyStmt, EndCopyFrom, InvalidOid, Node,
-        Oid, ParseExprKind, ParseNamespaceItem, ParseState, PlannedStmt, QueryEnvironment,
+        aclcheck_error, addNSItemToQuery, assign_expr_collations, canonicalize_qual,
+        check_enable_rls, coerce_to_boolean, errcontext_msg, error_context_stack,
+        eval_const_expressions, get_namespace_name, get_rel_name, get_rel_namespace,
+        get_rel_relkind, get_relkind_objtype, makeRangeVar, make_ands_implicit,
+        pg_attribute_aclcheck_all, pg_class_aclcheck, transformExpr, AccessExclusiveLock,
+        AclMaskHow, AclResult, AsPgCStr, BeginCopyFrom, CheckEnableRlsResult,
+        CommandCounterIncrement, CopyFrom, CopyStmt, DropBehavior, EndCopyFrom,
+        ErrorContextCallback, ExecuteTruncate, GetUserId, InvalidOid, LockRelationOid, Node,
+        NodeTag, Oid, ParseExprKind, ParseNamespaceItem, ParseState, PlannedStmt, QueryEnvironment,
+        TruncateStmt, ACL_INSERT, ACL_TRUNCATE, RELKIND_VIEW,
     },
-    void_mut_ptr, PgBox, PgLogLevel, PgRelation, PgSqlErrorCode,
+    void_mut_ptr, PgBox, PgList, PgLogLevel, PgRelation, PgSqlErrorCode, PgTupleDesc,
 };
 use url::Url;
 
 use crate::{
-    arrow_parquet::parquet_reader::ParquetReaderContext,
-    parquet_copy_hook::copy_utils::{
-        copy_from_stmt_create_option_list, copy_stmt_lock_mode, copy_stmt_relation_oid,
+    arrow_parquet::{
+        page_pruning::page_predicates_from_where_clause, parquet_reader::ParquetReaderContext,
+        row_group_selection::RESUME_EXACTLY_ONCE_NOTE,
     },
+    parquet_copy_hook::{
+        copy_utils::{
+            copy_from_stmt_create_option_list, copy_stmt_lock_mode, copy_stmt_relation_oid,
+        },
+        pg_compat::check_copy_from_insert_permission,
+    },
+    pgrx_utils::{collect_attributes_for, CollectAttributesFor},
 };
 
 use super::copy_utils::{
-    copy_from_stmt_match_by, copy_stmt_attribute_list, copy_stmt_create_namespace_item,
-    copy_stmt_create_parse_state, create_filtered_tupledesc_for_relation,
+    copy_from_stmt_attribute_list_without, copy_from_stmt_cascade, copy_from_stmt_extra_columns,
+    copy_from_stmt_field_ids, copy_from_stmt_geometry_srid_mismatch, copy_from_stmt_list_coercion,
+    copy_from_stmt_match_by, copy_from_stmt_missing_columns, copy_from_stmt_on_error,
+    copy_from_stmt_restart_identity, copy_from_stmt_row_group_selection,
+    copy_from_stmt_timestamptz_to_timestamp, copy_from_stmt_truncate, copy_stmt_attribute_list,
+    copy_stmt_create_namespace_item, copy_stmt_create_parse_state, copy_stmt_infinite_timestamps,
+    create_filtered_tupledesc_for_relation,
 };
 
 // stack to store parquet reader contexts for COPY FROM.
@@ -63,6 +81,27 @@ pub(crate) fn push_parquet_reader_context(reader_ctx: ParquetReaderContext) {
     };
 }
 
+// COPY_FROM_ERROR_CONTEXT reports the location of the value in the parquet file, when its
+// conversion fails, like COPY reports the line of a CSV file. It is pushed to the error context
+// stack only while converting the rows, so that the errors of inserting the rows do not
+// report it. It is static since it stays in the stack while the error is raised after
+// unwinding the conversion.
+static mut COPY_FROM_ERROR_CONTEXT: ErrorContextCallback = ErrorContextCallback {
+    previous: std::ptr::null_mut(),
+    callback: None,
+    arg: std::ptr::null_mut(),
+};
+
+#[pg_guard]
+extern "C" fn copy_from_parquet_error_callback(_arg: void_mut_ptr) {
+    let context = peek_parquet_reader_context()
+        .and_then(|parquet_reader_context| parquet_reader_context.conversion_error_context());
+
+    if let Some(context) = context {
+        unsafe { errcontext_msg("%s".as_pg_cstr(), context.as_pg_cstr()) };
+    }
+}
+
 // This function is called by the COPY FROM command to read data from the parquet file
 // into output buffer passed by the executor.
 #[pg_guard]
@@ -83,7 +122,21 @@ extern "C" fn copy_parquet_data_to_buffer(
     if bytes_size == 0 {
         current_parquet_reader_context.reset_buffer();
 
-        if !current_parquet_reader_context.read_parquet() {
+        #[allow(static_mut_refs)]
+        unsafe {
+            COPY_FROM_ERROR_CONTEXT.previous = error_context_stack;
+            COPY_FROM_ERROR_CONTEXT.callback = Some(copy_from_parquet_error_callback);
+            error_context_stack = std::ptr::addr_of_mut!(COPY_FROM_ERROR_CONTEXT);
+        }
+
+        let has_more_data = current_parquet_reader_context.read_parquet();
+
+        #[allow(static_mut_refs)]
+        unsafe {
+            error_context_stack = COPY_FROM_ERROR_CONTEXT.previous
+        };
+
+        if !has_more_data {
             return 0;
         }
 
@@ -105,6 +158,7 @@ extern "C" fn copy_parquet_data_to_buffer(
 //    - transforms the WHERE clause to a form that can be used by the executor,
 //    - creates a new tuple descriptor for the COPY FROM operation by removing dropped attributes
 //      and filtering the attributes based on the attribute name list,
+//    - checks the INSERT privilege on the columns and row-level security like COPY FROM does,
 //    - creates a ParquetReaderContext that is used to read data from the parquet file.
 // 2. Registers a callback function, which is called by the executor, to read data from
 //    the output buffer parameter that is written by ParquetReaderContext.
@@ -118,7 +172,17 @@ pub(crate) fn execute_copy_from(
 ) -> u64 {
     let rel_oid = copy_stmt_relation_oid(p_stmt);
 
-    copy_from_stmt_ensure_row_level_security(rel_oid);
+    // TRUNCATE refuses the relations, which are open in the session. Hence, the relation is
+    // truncated before it is opened.
+    if copy_from_stmt_truncate(p_stmt) {
+        copy_from_stmt_ensure_row_level_security(rel_oid);
+
+        truncate_relation(
+            rel_oid,
+            copy_from_stmt_restart_identity(p_stmt),
+            copy_from_stmt_cascade(p_stmt),
+        );
+    }
 
     let lock_mode = copy_stmt_lock_mode(p_stmt);
 
@@ -128,21 +192,71 @@ pub(crate) fn execute_copy_from(
 
     let ns_item = copy_stmt_create_namespace_item(p_stmt, &p_state, &relation);
 
+    let tupledesc = create_filtered_tupledesc_for_relation(p_stmt, &relation);
+
+    copy_from_stmt_ensure_insert_privilege(&p_state, &ns_item, &relation, &tupledesc);
+
+    copy_from_stmt_ensure_row_level_security(rel_oid);
+
     let mut where_clause = copy_from_stmt_where_clause(p_stmt);
 
     if !where_clause.is_null() {
         where_clause = copy_from_stmt_transform_where_clause(&p_state, &ns_item, where_clause);
     }
 
-    let attribute_list = copy_stmt_attribute_list(p_stmt);
+    // comparisons of columns with constants in the WHERE clause skip the pages of the file,
+    // which cannot have a matching row, by its page index
+    let page_predicates = page_predicates_from_where_clause(where_clause, rel_oid);
 
-    let tupledesc = create_filtered_tupledesc_for_relation(p_stmt, &relation);
+    let mut attribute_list = copy_stmt_attribute_list(p_stmt);
 
     let match_by = copy_from_stmt_match_by(p_stmt);
 
+    let field_ids = copy_from_stmt_field_ids(p_stmt);
+
+    let missing_columns = copy_from_stmt_missing_columns(p_stmt);
+
+    let extra_columns = copy_from_stmt_extra_columns(p_stmt, match_by);
+
+    let row_group_selection = copy_from_stmt_row_group_selection(p_stmt);
+
+    let geometry_srid_mismatch = copy_from_stmt_geometry_srid_mismatch(p_stmt);
+
+    let timestamptz_to_timestamp = copy_from_stmt_timestamptz_to_timestamp(p_stmt);
+
+    let list_coercion = copy_from_stmt_list_coercion(p_stmt);
+
+    let infinite_timestamps = copy_stmt_infinite_timestamps(p_stmt);
+
+    let on_error = copy_from_stmt_on_error(p_stmt);
+
     unsafe {
         // parquet reader context is used throughout the COPY FROM operation.
-        let parquet_reader_context = ParquetReaderContext::new(uri, match_by, &tupledesc);
+        let parquet_reader_context = ParquetReaderContext::new(
+            uri,
+            match_by,
+            field_ids,
+            missing_columns,
+            extra_columns,
+            row_group_selection,
+            page_predicates,
+            geometry_srid_mismatch,
+            timestamptz_to_timestamp,
+            infinite_timestamps,
+            list_coercion,
+            on_error,
+            rel_oid,
+            &tupledesc,
+        );
+
+        // COPY fills the attributes, which are not in the attribute list, with their defaults
+        let missing_attribute_names = parquet_reader_context.missing_attribute_names();
+
+        if !missing_attribute_names.is_empty() {
+            attribute_list =
+                copy_from_stmt_attribute_list_without(&tupledesc, missing_attribute_names);
+        }
+
         push_parquet_reader_context(parquet_reader_context);
 
         // makes sure to set binary format
@@ -163,6 +277,10 @@ pub(crate) fn execute_copy_from(
 
         EndCopyFrom(copy_from_state);
 
+        report_skipped_rows();
+
+        report_next_resume_token();
+
         let throw_error = true;
         pop_parquet_reader_context(throw_error);
 
@@ -170,6 +288,139 @@ pub(crate) fn execute_copy_from(
     }
 }
 
+// report_skipped_rows reports the number of rows, which are skipped by on_error 'ignore',
+// with the same notice as COPY FROM text and csv files.
+fn report_skipped_rows() {
+    let parquet_reader_context =
+        peek_parquet_reader_context().expect("parquet reader context is not found");
+
+    let skipped_rows = parquet_reader_context.skipped_rows();
+
+    if skipped_rows == 0 {
+        return;
+    }
+
+    let message = if skipped_rows == 1 {
+        "1 row was skipped due to data type incompatibility".to_string()
+    } else {
+        format!(
+            "{} rows were skipped due to data type incompatibility",
+            skipped_rows
+        )
+    };
+
+    ereport!(
+        PgLogLevel::NOTICE,
+        PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION,
+        message
+    );
+}
+
+// report_next_resume_token reports the token to resume loading the file, when a chunked
+// COPY FROM does not load the last row group of the file.
+fn report_next_resume_token() {
+    let parquet_reader_context =
+        peek_parquet_reader_context().expect("parquet reader context is not found");
+
+    if let Some(next_resume_token) = parquet_reader_context.next_resume_token() {
+        let selected_row_groups = parquet_reader_context.selected_row_groups();
+
+        ereport!(
+            PgLogLevel::NOTICE,
+            PgSqlErrorCode::ERRCODE_SUCCESSFUL_COMPLETION,
+            format!(
+                "loaded row groups {} to {} of {} row groups",
+                selected_row_groups.row_groups().start,
+                selected_row_groups.row_groups().end - 1,
+                selected_row_groups.num_row_groups()
+            ),
+            format!(
+                "Resume with resume_from '{}'. {}",
+                next_resume_token, RESUME_EXACTLY_ONCE_NOTE
+            ),
+        );
+    }
+}
+
+// truncate_relation truncates the relation, as TRUNCATE does, in the transaction of the COPY FROM.
+// Hence, a failed load leaves the old rows in place. TRUNCATE checks the privilege, fires the
+// truncate triggers, and refuses to truncate the tables referenced by foreign keys unless
+// cascade is requested.
+//
+// AccessExclusiveLock is taken up front, which the COPY FROM keeps instead of its
+// RowExclusiveLock. Upgrading the lock later would deadlock concurrent loads into the table.
+// The privileges are checked before, so that a role without them cannot block the readers
+// of the table while it waits for the lock.
+fn truncate_relation(relation_oid: Oid, restart_identity: bool, cascade: bool) {
+    ensure_truncate_privileges(relation_oid);
+
+    unsafe { LockRelationOid(relation_oid, AccessExclusiveLock as _) };
+
+    let relation_name = unsafe { get_rel_name(relation_oid) };
+
+    if unsafe { get_rel_relkind(relation_oid) } == RELKIND_VIEW as c_char {
+        panic!(
+            "truncate option is not supported for view \"{}\"",
+            unsafe { CStr::from_ptr(relation_name) }.to_string_lossy()
+        );
+    }
+
+    // the relation is locked, the name resolves to the same relation
+    let relation_var = unsafe {
+        makeRangeVar(
+            get_namespace_name(get_rel_namespace(relation_oid)),
+            relation_name,
+            -1,
+        )
+    };
+
+    let mut relations = PgList::new();
+    relations.push(relation_var);
+
+    let mut truncate_stmt = unsafe { PgBox::<TruncateStmt>::alloc_node(NodeTag::T_TruncateStmt) };
+
+    truncate_stmt.relations = relations.into_pg();
+    truncate_stmt.restart_seqs = restart_identity;
+    truncate_stmt.behavior = if cascade {
+        DropBehavior::DROP_CASCADE
+    } else {
+        DropBehavior::DROP_RESTRICT
+    };
+
+    unsafe {
+        ExecuteTruncate(truncate_stmt.as_ptr());
+
+        // makes the new storage of the relation visible to the COPY FROM
+        CommandCounterIncrement();
+    }
+}
+
+// ensure_truncate_privileges checks the TRUNCATE privilege on the relation, and the INSERT
+// privilege on any of its columns. The INSERT privilege on the copied columns is checked
+// after the relation is opened.
+fn ensure_truncate_privileges(relation_oid: Oid) {
+    unsafe {
+        let mut acl_result = pg_class_aclcheck(relation_oid, GetUserId(), ACL_TRUNCATE as _);
+
+        if acl_result == AclResult::ACLCHECK_OK {
+            acl_result = pg_attribute_aclcheck_all(
+                relation_oid,
+                GetUserId(),
+                ACL_INSERT as _,
+                AclMaskHow::ACLMASK_ANY,
+            );
+        }
+
+        if acl_result != AclResult::ACLCHECK_OK {
+            aclcheck_error(
+                acl_result,
+                get_relkind_objtype(get_rel_relkind(relation_oid)),
+                get_rel_name(relation_oid),
+            );
+        }
+    }
+}
+
 fn copy_from_stmt_where_clause(p_stmt: &PgBox<PlannedStmt>) -> *mut Node {
     let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
     copy_stmt.whereClause
@@ -205,6 +456,32 @@ fn copy_from_stmt_transform_where_clause(
     where_clause as _
 }
 
+// copy_from_stmt_ensure_insert_privilege ensures that the user has INSERT privilege on the
+// columns, which are loaded, since CopyFrom does not check it.
+// Taken from PG COPY FROM code path.
+fn copy_from_stmt_ensure_insert_privilege(
+    p_state: &PgBox<ParseState>,
+    ns_item: &PgBox<ParseNamespaceItem>,
+    relation: &PgRelation,
+    tupledesc: &PgTupleDesc,
+) {
+    let attribute_names = collect_attributes_for(CollectAttributesFor::CopyFrom, tupledesc)
+        .iter()
+        .map(|attribute| attribute.name().to_string())
+        .collect::<Vec<_>>();
+
+    let attnums = relation
+        .tuple_desc()
+        .iter()
+        .filter(|attribute| {
+            !attribute.is_dropped() && attribute_names.iter().any(|name| name == attribute.name())
+        })
+        .map(|attribute| attribute.attnum)
+        .collect::<Vec<_>>();
+
+    check_copy_from_insert_permission(p_state.as_ptr(), ns_item.as_ptr(), &attnums);
+}
+
 // copy_from_stmt_ensure_row_level_security ensures that the relation does not have row-level
 // security enabled for COPY FROM operation.
 // Taken from PG COPY FROM code path.
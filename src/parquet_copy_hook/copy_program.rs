@@ -0,0 +1,153 @@
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::{Read, Write},
+};
+
+use pgrx::{
+    check_for_interrupts, ereport,
+    pg_sys::{
+        fread, fwrite, get_role_oid, has_privs_of_role, wait_result_to_str, AsPgCStr,
+        ClosePipeStream, GetUserId, OpenPipeStream, FILE,
+    },
+    PgLogLevel, PgSqlErrorCode,
+};
+use url::Url;
+
+use crate::arrow_parquet::uri_utils::uri_as_string;
+
+const COPY_PROGRAM_ROLE: &str = "pg_execute_server_program";
+
+const COPY_PROGRAM_BUFFER_SIZE: usize = 64 * 1024;
+
+// ensure_copy_program_privilege requires the same role as COPY FROM/TO PROGRAM of Postgres.
+pub(crate) fn ensure_copy_program_privilege() {
+    let user_id = unsafe { GetUserId() };
+
+    let required_role_id = unsafe { get_role_oid(COPY_PROGRAM_ROLE.as_pg_cstr(), false) };
+
+    if !unsafe { has_privs_of_role(user_id, required_role_id) } {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            "permission denied to COPY to or from an external program",
+            format!(
+                "Only roles with privileges of the \"{}\" role may COPY to or from an external program.",
+                COPY_PROGRAM_ROLE
+            ),
+        );
+    }
+}
+
+// copy_program_output_to_file runs the program and writes its output to the local file. The
+// parquet reader needs random access to the footer at the end of the file, hence the output
+// is not read as a stream.
+pub(crate) fn copy_program_output_to_file(program: &str, uri: &Url) {
+    let path = uri_as_string(uri);
+
+    let mut file =
+        File::create(&path).unwrap_or_else(|e| panic!("could not create file \"{}\": {}", path, e));
+
+    // the pipe is closed by Postgres if the COPY fails
+    let pipe = open_program_pipe(program, "r");
+
+    let mut buffer = vec![0u8; COPY_PROGRAM_BUFFER_SIZE];
+
+    loop {
+        check_for_interrupts!();
+
+        let nread = unsafe { fread(buffer.as_mut_ptr() as _, 1, buffer.len() as _, pipe) } as usize;
+
+        // the exit status of the program tells whether it failed
+        if nread == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..nread])
+            .unwrap_or_else(|e| panic!("could not write to file \"{}\": {}", path, e));
+    }
+
+    close_program_pipe(program, pipe);
+}
+
+// copy_file_to_program_input runs the program and writes the local file to its input.
+pub(crate) fn copy_file_to_program_input(uri: &Url, program: &str) {
+    let path = uri_as_string(uri);
+
+    let mut file =
+        File::open(&path).unwrap_or_else(|e| panic!("could not open file \"{}\": {}", path, e));
+
+    let pipe = open_program_pipe(program, "w");
+
+    let mut buffer = vec![0u8; COPY_PROGRAM_BUFFER_SIZE];
+
+    loop {
+        check_for_interrupts!();
+
+        let nread = file
+            .read(&mut buffer)
+            .unwrap_or_else(|e| panic!("could not read file \"{}\": {}", path, e));
+
+        if nread == 0 {
+            break;
+        }
+
+        let nwritten = unsafe { fwrite(buffer.as_ptr() as _, 1, nread as _, pipe) } as usize;
+
+        if nwritten != nread {
+            panic!(
+                "could not write to COPY program: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    close_program_pipe(program, pipe);
+}
+
+// remove_copy_program_file removes the local file, which buffers the data of the program.
+pub(crate) fn remove_copy_program_file(uri: &Url) {
+    let path = uri_as_string(uri);
+
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            pgrx::warning!("failed to remove temporary parquet file {}: {}", path, e);
+        }
+    }
+}
+
+fn open_program_pipe(program: &str, mode: &str) -> *mut FILE {
+    let pipe = unsafe { OpenPipeStream(program.as_pg_cstr(), mode.as_pg_cstr()) };
+
+    if pipe.is_null() {
+        panic!(
+            "could not execute command \"{}\": {}",
+            program,
+            std::io::Error::last_os_error()
+        );
+    }
+
+    pipe
+}
+
+fn close_program_pipe(program: &str, pipe: *mut FILE) {
+    let exit_status = unsafe { ClosePipeStream(pipe) };
+
+    if exit_status == -1 {
+        panic!(
+            "could not close pipe to external command: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    if exit_status != 0 {
+        let reason = unsafe { CStr::from_ptr(wait_result_to_str(exit_status)) };
+
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_EXTERNAL_ROUTINE_EXCEPTION,
+            format!("program \"{}\" failed", program),
+            reason.to_string_lossy().to_string(),
+        );
+    }
+}
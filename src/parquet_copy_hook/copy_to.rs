@@ -3,10 +3,11 @@ use std::ffi::{c_char, CStr};
 use pgrx::{
     ereport, is_a,
     pg_sys::{
-        makeRangeVar, pg_plan_query, A_Star, ColumnRef, CommandTag, CopyStmt, CreateNewPortal,
-        DestReceiver, GetActiveSnapshot, Node,
+        find_all_inheritors, get_rel_relkind, makeRangeVar, makeString, pg_plan_query, A_Star,
+        AsPgCStr, ColumnRef, CommandTag, CopyStmt, CreateNewPortal, DestReceiver,
+        GetActiveSnapshot, List, Node,
         NodeTag::{self, T_CopyStmt},
-        ParamListInfoData, PlannedStmt, PortalDefineQuery, PortalDrop, PortalRun, PortalStart,
+        Oid, ParamListInfoData, PlannedStmt, PortalDefineQuery, PortalDrop, PortalRun, PortalStart,
         QueryCompletion, QueryEnvironment, RawStmt, ResTarget, SelectStmt, CURSOR_OPT_PARALLEL_OK,
         RELKIND_FOREIGN_TABLE, RELKIND_MATVIEW, RELKIND_PARTITIONED_TABLE, RELKIND_RELATION,
         RELKIND_SEQUENCE, RELKIND_VIEW,
@@ -14,16 +15,26 @@ use pgrx::{
     AllocatedByRust, PgBox, PgList, PgLogLevel, PgRelation, PgSqlErrorCode,
 };
 
-use crate::parquet_copy_hook::{
-    copy_utils::{copy_stmt_has_relation, copy_stmt_lock_mode, copy_stmt_relation_oid},
-    pg_compat::pg_analyze_and_rewrite,
+use crate::{
+    parquet_copy_hook::{
+        copy_utils::{
+            copy_stmt_has_relation, copy_stmt_lock_mode, copy_stmt_relation_oid,
+            copy_to_stmt_exclude_columns, copy_to_stmt_excludes_columns,
+            copy_to_stmt_include_generated,
+        },
+        export_allowlist::ensure_export_allowed,
+        pg_compat::{pg_analyze_and_rewrite, strVal},
+    },
+    pgrx_utils::is_generated_attribute,
 };
 
 // execute_copy_to_with_dest_receiver executes a COPY TO statement with our custom DestReceiver
 // for writing to Parquet files.
-// - converts the table relation to a SELECT statement if necessary
+// - converts the table relation to a SELECT statement if necessary, or the given leaf partition
+//   of the partitioned table relation with one_file_per_partition
 // - analyzes and rewrites the raw query
 // - plans the rewritten query
+// - ensures that the relations referenced by the plan are allowed to be exported
 // - creates a portal for the planned query by using the custom DestReceiver
 // - executes the query with the portal
 pub(crate) fn execute_copy_to_with_dest_receiver(
@@ -32,6 +43,7 @@ pub(crate) fn execute_copy_to_with_dest_receiver(
     params: &PgBox<ParamListInfoData>,
     query_env: &PgBox<QueryEnvironment>,
     parquet_dest: &PgBox<DestReceiver>,
+    partition_oid: Option<Oid>,
 ) -> u64 {
     unsafe {
         debug_assert!(is_a(p_stmt.utilityStmt, T_CopyStmt));
@@ -40,6 +52,8 @@ pub(crate) fn execute_copy_to_with_dest_receiver(
 
         let mut relation = PgRelation::from_pg(std::ptr::null_mut());
 
+        let mut partition = None;
+
         if copy_stmt_has_relation(p_stmt) {
             let rel_oid = copy_stmt_relation_oid(p_stmt);
 
@@ -48,9 +62,11 @@ pub(crate) fn execute_copy_to_with_dest_receiver(
             relation = PgRelation::with_lock(rel_oid, lock_mode);
 
             copy_to_stmt_ensure_table_kind(&relation);
+
+            partition = partition_oid.map(|oid| PgRelation::with_lock(oid, lock_mode));
         }
 
-        let raw_query = prepare_copy_to_raw_stmt(p_stmt, &copy_stmt, &relation);
+        let raw_query = prepare_copy_to_raw_stmt(p_stmt, &copy_stmt, &relation, partition.as_ref());
 
         let rewritten_queries = pg_analyze_and_rewrite(
             raw_query.as_ptr(),
@@ -69,6 +85,8 @@ pub(crate) fn execute_copy_to_with_dest_receiver(
             params.as_ptr(),
         );
 
+        ensure_export_allowed(&PgBox::from_pg(plan));
+
         let portal = CreateNewPortal();
         let mut portal = PgBox::from_pg(portal);
         portal.visible = false;
@@ -109,11 +127,13 @@ pub(crate) fn execute_copy_to_with_dest_receiver(
 }
 
 // prepare_copy_to_raw_stmt prepares a raw statement for the COPY TO operation.
-// If the relation is not NULL, it converts the relation to a SELECT statement.
+// If the relation is not NULL, it converts the relation, or its given partition,
+// to a SELECT statement.
 fn prepare_copy_to_raw_stmt(
     p_stmt: &PgBox<PlannedStmt>,
     copy_stmt: &PgBox<CopyStmt>,
     relation: &PgRelation,
+    partition: Option<&PgRelation>,
 ) -> PgBox<RawStmt, AllocatedByRust> {
     let mut raw_query = unsafe { PgBox::<RawStmt>::alloc_node(NodeTag::T_RawStmt) };
     raw_query.stmt_location = p_stmt.stmt_location;
@@ -122,7 +142,8 @@ fn prepare_copy_to_raw_stmt(
     if relation.is_null() {
         raw_query.stmt = copy_stmt.query;
     } else {
-        let select_stmt = convert_copy_to_relation_to_select_stmt(copy_stmt, relation);
+        let select_stmt =
+            convert_copy_to_relation_to_select_stmt(p_stmt, copy_stmt, relation, partition);
         raw_query.stmt = select_stmt.into_pg() as _;
     }
 
@@ -130,12 +151,25 @@ fn prepare_copy_to_raw_stmt(
 }
 
 fn convert_copy_to_relation_to_select_stmt(
+    p_stmt: &PgBox<PlannedStmt>,
     copy_stmt: &PgBox<CopyStmt>,
     relation: &PgRelation,
+    partition: Option<&PgRelation>,
 ) -> PgBox<SelectStmt> {
     let mut target_list = PgList::new();
 
-    if copy_stmt.attlist.is_null() {
+    // a partition might have a different column order than its parent, e.g. when it is
+    // attached after dropping a column of the parent, so its columns are selected by the
+    // parent's column names
+    let attlist = if copy_to_stmt_excludes_columns(p_stmt) {
+        included_column_names(p_stmt, copy_stmt, relation)
+    } else if copy_stmt.attlist.is_null() && partition.is_some() {
+        relation_column_names(relation)
+    } else {
+        copy_stmt.attlist
+    };
+
+    if attlist.is_null() {
         // SELECT * FROM relation
         let mut col_ref = unsafe { PgBox::<ColumnRef>::alloc_node(NodeTag::T_ColumnRef) };
         let a_star = unsafe { PgBox::<A_Star>::alloc_node(NodeTag::T_A_Star) };
@@ -155,7 +189,7 @@ fn convert_copy_to_relation_to_select_stmt(
         target_list.push(target.into_pg());
     } else {
         // SELECT a,b,... FROM relation
-        let attribute_name_list = unsafe { PgList::<Node>::from_pg(copy_stmt.attlist) };
+        let attribute_name_list = unsafe { PgList::<Node>::from_pg(attlist) };
         for attribute_name in attribute_name_list.iter_ptr() {
             let mut col_ref = unsafe { PgBox::<ColumnRef>::alloc_node(NodeTag::T_ColumnRef) };
 
@@ -175,15 +209,20 @@ fn convert_copy_to_relation_to_select_stmt(
         }
     }
 
+    let scanned_relation = partition.unwrap_or(relation);
+
     let from = unsafe {
         makeRangeVar(
-            relation.namespace().as_ptr() as _,
-            relation.name().as_ptr() as _,
+            scanned_relation.namespace().as_ptr() as _,
+            scanned_relation.name().as_ptr() as _,
             -1,
         )
     };
     let mut from = unsafe { PgBox::from_pg(from) };
-    from.inh = false;
+
+    // partitioned tables are scanned with their partitions, which lets the planner scan
+    // the partitions in parallel
+    from.inh = is_partitioned_table(scanned_relation);
 
     let mut select_stmt = unsafe { PgBox::<SelectStmt>::alloc_node(NodeTag::T_SelectStmt) };
 
@@ -196,13 +235,113 @@ fn convert_copy_to_relation_to_select_stmt(
     select_stmt.into_pg_boxed()
 }
 
-// copy_to_stmt_ensure_table_kind ensures that the relation is a regular table.
-// Taken from PG COPY TO code path.
+// relation_column_names returns the names of the relation's columns, which are not dropped,
+// as a list of String nodes like the column list of COPY.
+fn relation_column_names(relation: &PgRelation) -> *mut List {
+    let mut column_names = PgList::<Node>::new();
+
+    for attribute in relation.tuple_desc().iter() {
+        if attribute.is_dropped() {
+            continue;
+        }
+
+        let column_name = unsafe { makeString(attribute.name().as_pg_cstr()) };
+        column_names.push(column_name as _);
+    }
+
+    column_names.into_pg()
+}
+
+// included_column_names returns the names of the columns in the column list of COPY, or of the
+// relation's columns, without the columns, which are excluded by exclude_columns, and the
+// generated columns with include_generated false.
+fn included_column_names(
+    p_stmt: &PgBox<PlannedStmt>,
+    copy_stmt: &PgBox<CopyStmt>,
+    relation: &PgRelation,
+) -> *mut List {
+    let attlist = if copy_stmt.attlist.is_null() {
+        relation_column_names(relation)
+    } else {
+        copy_stmt.attlist
+    };
+
+    let exclude_columns = copy_to_stmt_exclude_columns(p_stmt);
+    let include_generated = copy_to_stmt_include_generated(p_stmt);
+
+    let tupledesc = relation.tuple_desc();
+
+    let mut column_names = PgList::<Node>::new();
+
+    for column_name in unsafe { PgList::<Node>::from_pg(attlist) }.iter_ptr() {
+        let name = strVal(column_name);
+
+        if exclude_columns.contains(&name) {
+            continue;
+        }
+
+        let is_generated = tupledesc.iter().any(|attribute| {
+            !attribute.is_dropped() && attribute.name() == name && is_generated_attribute(attribute)
+        });
+
+        if !include_generated && is_generated {
+            continue;
+        }
+
+        column_names.push(column_name);
+    }
+
+    if column_names.is_empty() {
+        panic!(
+            "COPY TO excludes all columns of relation \"{}\"",
+            relation.name()
+        );
+    }
+
+    column_names.into_pg()
+}
+
+fn is_partitioned_table(relation: &PgRelation) -> bool {
+    let relation_pgclass_entry = relation.rd_rel;
+    let relation_kind = (unsafe { *relation_pgclass_entry }).relkind;
+
+    relation_kind == RELKIND_PARTITIONED_TABLE as c_char
+}
+
+// copy_to_stmt_leaf_partitions returns the leaf partitions of the partitioned table, which
+// the COPY TO statement copies from. It fails if the relation is not a partitioned table.
+pub(crate) fn copy_to_stmt_leaf_partitions(p_stmt: &PgBox<PlannedStmt>) -> Vec<Oid> {
+    if !copy_stmt_has_relation(p_stmt) {
+        panic!("one_file_per_partition is only supported for COPY TO from a partitioned table");
+    }
+
+    let rel_oid = copy_stmt_relation_oid(p_stmt);
+
+    if unsafe { get_rel_relkind(rel_oid) } != RELKIND_PARTITIONED_TABLE as c_char {
+        panic!("one_file_per_partition is only supported for COPY TO from a partitioned table");
+    }
+
+    let lock_mode = copy_stmt_lock_mode(p_stmt);
+
+    let inheritors = unsafe { find_all_inheritors(rel_oid, lock_mode, std::ptr::null_mut()) };
+
+    let inheritors = unsafe { PgList::<Oid>::from_pg(inheritors) };
+
+    inheritors
+        .iter_oid()
+        .filter(|oid| unsafe { get_rel_relkind(*oid) } != RELKIND_PARTITIONED_TABLE as c_char)
+        .collect()
+}
+
+// copy_to_stmt_ensure_table_kind ensures that the relation is a regular or partitioned table.
+// Taken from PG COPY TO code path, which does not allow partitioned tables.
 fn copy_to_stmt_ensure_table_kind(relation: &PgRelation) {
     let relation_pgclass_entry = relation.rd_rel;
     let relation_kind = (unsafe { *relation_pgclass_entry }).relkind;
 
-    if relation_kind == RELKIND_RELATION as c_char {
+    if relation_kind == RELKIND_RELATION as c_char
+        || relation_kind == RELKIND_PARTITIONED_TABLE as c_char
+    {
         return;
     }
 
@@ -234,13 +373,6 @@ fn copy_to_stmt_ensure_table_kind(relation: &PgRelation) {
             format!("cannot copy from sequence \"{}\"", relation.name()),
             "Try the COPY (SELECT ...) TO variant.",
         );
-    } else if relation_kind == RELKIND_PARTITIONED_TABLE as c_char {
-        ereport!(
-            PgLogLevel::ERROR,
-            PgSqlErrorCode::ERRCODE_WRONG_OBJECT_TYPE,
-            format!("cannot copy from partitioned table \"{}\"", relation.name()),
-            "Try the COPY (SELECT ...) TO variant.",
-        );
     } else {
         ereport!(
             PgLogLevel::ERROR,
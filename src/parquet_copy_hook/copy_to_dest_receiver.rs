@@ -1,26 +1,318 @@
-use std::ffi::{c_char, CStr, CString};
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::{c_char, CStr, CString},
+    num::NonZeroUsize,
+    panic::AssertUnwindSafe,
+};
 
+use parquet::file::properties::{
+    DEFAULT_BLOOM_FILTER_FPP, DEFAULT_DATA_PAGE_ROW_COUNT_LIMIT, DEFAULT_DICTIONARY_ENABLED,
+    DEFAULT_DICTIONARY_PAGE_SIZE_LIMIT, DEFAULT_PAGE_SIZE, DEFAULT_WRITE_BATCH_SIZE,
+};
 use pg_sys::{
     get_typlenbyval, slot_getallattrs, toast_raw_datum_size, AllocSetContextCreateExtended,
     AsPgCStr, BlessTupleDesc, CommandDest, CurrentMemoryContext, Datum, DestReceiver,
     HeapTupleData, List, MemoryContext, MemoryContextAllocZero, MemoryContextDelete,
-    MemoryContextReset, TupleDesc, TupleTableSlot, ALLOCSET_DEFAULT_INITSIZE,
+    MemoryContextReset, Oid, TupleDesc, TupleTableSlot, ALLOCSET_DEFAULT_INITSIZE,
     ALLOCSET_DEFAULT_MAXSIZE, ALLOCSET_DEFAULT_MINSIZE, VARHDRSZ,
 };
-use pgrx::{prelude::*, FromDatum, PgList, PgMemoryContexts, PgTupleDesc};
-
-use crate::arrow_parquet::{
-    compression::{PgParquetCompression, INVALID_COMPRESSION_LEVEL},
-    parquet_writer::{ParquetWriterContext, DEFAULT_ROW_GROUP_SIZE, DEFAULT_ROW_GROUP_SIZE_BYTES},
-    uri_utils::parse_uri,
+use pgrx::{
+    heap_getattr_raw, prelude::*, FromDatum, GucSetting, PgList, PgMemoryContexts, PgSqlErrorCode,
+    PgTupleDesc,
 };
+use url::Url;
+
+use crate::{
+    arrow_parquet::{
+        compression::{PgParquetCompression, INVALID_COMPRESSION_LEVEL},
+        duplicate_columns::{rename_duplicate_attributes, PgParquetDuplicateColumns},
+        field_ids::parse_field_ids,
+        if_exists::PgParquetIfExists,
+        infinite_timestamps::PgParquetInfiniteTimestamps,
+        multidim_arrays::PgParquetMultidimArrays,
+        nan_handling::PgParquetNanHandling,
+        numeric_overflow::PgParquetNumericOverflow,
+        parquet_writer::{
+            ParquetWriterContext, ParquetWriterDestination, PendingUpload, DEFAULT_ROW_GROUP_SIZE,
+            DEFAULT_ROW_GROUP_SIZE_BYTES,
+        },
+        pg_to_arrow::conversion_progress::{current_conversion_progress, finish_conversion},
+        schema_parser::unwrap_domain_attributes,
+        statistics::PgParquetStatistics,
+        stream_writer::CopyToParquetStream,
+        unique_file_names::{suffixed_file_uri, unique_file_suffix},
+        unsupported_type::PgParquetUnsupportedType,
+        uri_utils::{create_uri_if_absent, delete_uri, parse_uri, redact_uri},
+        writer_version::PgParquetWriterVersion,
+    },
+    pgrx_utils::{is_array_type, CollectAttributesFor},
+    type_compat::{
+        multidim_array::ArrayDims,
+        pg_arrow_type_conversions::{
+            DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+        },
+    },
+};
+
+use super::copy_utils::parse_kv_metadata;
+
+// MAX_EXPORT_BYTES is the maximum number of compressed bytes (in kB), which a single COPY TO
+// can write. 0 means unlimited. Since it is an int GUC in kB, the limit is at most about 2TB.
+pub(crate) static MAX_EXPORT_BYTES: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+// WARN_EXPORT_BYTES is the number of compressed bytes (in kB), after which a single COPY TO
+// emits a warning. 0 means no warning. Like MAX_EXPORT_BYTES, it is at most about 2TB.
+pub(crate) static WARN_EXPORT_BYTES: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+// KEEP_PARTIAL_FILES keeps the partially written files of a failed COPY TO, e.g. for debugging,
+// instead of removing them.
+pub(crate) static KEEP_PARTIAL_FILES: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+// BATCH_MEMORY_LIMIT is the default size of the rows (in kB), which COPY TO holds in memory
+// before it finishes the row group, when the batch_memory_limit option is not specified.
+pub(crate) static BATCH_MEMORY_LIMIT: GucSetting<i32> = GucSetting::<i32>::new(256 * 1024);
+
+// UPLOAD_CONCURRENCY is the max number of files of max_rows_per_file, which COPY TO uploads
+// at a time, including the file in progress. 1 finishes each file before the next one is written.
+pub(crate) static UPLOAD_CONCURRENCY: GucSetting<i32> = GucSetting::<i32>::new(1);
+
+// TEST_FAILED_UPLOAD_FILE is only used by tests to fail the upload of the given file (1-based)
+// of max_rows_per_file, which is uploaded in the background. 0 means no failure.
+#[cfg(feature = "pg_test")]
+pub(crate) static TEST_FAILED_UPLOAD_FILE: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+// MAX_UNIQUE_FILE_SUFFIX_ATTEMPTS is the max number of suffixes, which if_exists 'append_suffix'
+// tries when the files with the former suffixes exist.
+const MAX_UNIQUE_FILE_SUFFIX_ATTEMPTS: usize = 3;
 
 #[repr(C)]
-struct CopyToParquetOptions {
+pub(crate) struct CopyToParquetOptions {
     pub row_group_size: i64,
     pub row_group_size_bytes: i64,
     pub compression: PgParquetCompression,
     pub compression_level: i32,
+    pub statistics: PgParquetStatistics,
+    pub bloom_filter_columns: *const c_char,
+    pub bloom_filter_fpp: f64,
+    pub dictionary_enabled: bool,
+    pub dictionary_columns: *const c_char,
+    pub no_dictionary_columns: *const c_char,
+    pub byte_stream_split_columns: *const c_char,
+    pub writer_version: PgParquetWriterVersion,
+    pub dictionary_page_size_limit: i64,
+    pub data_page_size_bytes: i64,
+    pub data_page_row_count_limit: i64,
+    pub write_batch_size: i64,
+    pub max_buffered_bytes: i64,
+    pub batch_memory_limit: i64,
+    pub kv_metadata: *const c_char,
+    pub geoparquet_covering: bool,
+    pub allow_mixed_srids: bool,
+    pub nan_handling: PgParquetNanHandling,
+    pub infinite_timestamps: PgParquetInfiniteTimestamps,
+    pub unsupported_type: PgParquetUnsupportedType,
+    pub numeric_precision: i32,
+    pub numeric_scale: i32,
+    pub numeric_overflow: PgParquetNumericOverflow,
+    pub multidim_arrays: PgParquetMultidimArrays,
+    pub duplicate_columns: PgParquetDuplicateColumns,
+    pub max_rows_per_file: i64,
+    pub field_ids: *const c_char,
+    pub if_exists: PgParquetIfExists,
+}
+
+impl CopyToParquetOptions {
+    // bloom_filter_columns returns the names of the columns, for which bloom filters are written.
+    pub(crate) fn bloom_filter_columns(&self) -> Vec<String> {
+        column_list(self.bloom_filter_columns)
+    }
+
+    // dictionary_columns returns the names of the columns, which are dictionary encoded.
+    pub(crate) fn dictionary_columns(&self) -> Vec<String> {
+        column_list(self.dictionary_columns)
+    }
+
+    // no_dictionary_columns returns the names of the columns, which are not dictionary encoded.
+    pub(crate) fn no_dictionary_columns(&self) -> Vec<String> {
+        column_list(self.no_dictionary_columns)
+    }
+
+    // byte_stream_split_columns returns the names of the columns, which are written with
+    // BYTE_STREAM_SPLIT encoding.
+    pub(crate) fn byte_stream_split_columns(&self) -> Vec<String> {
+        column_list(self.byte_stream_split_columns)
+    }
+
+    // field_ids returns the field ids of the columns, which are given instead of the field ids
+    // of their attributes, e.g. the attnums of the columns of the COPY TO table.
+    pub(crate) fn field_ids(&self) -> HashMap<String, i32> {
+        if self.field_ids.is_null() {
+            return HashMap::new();
+        }
+
+        let field_ids = unsafe { CStr::from_ptr(self.field_ids) }
+            .to_str()
+            .expect("field_ids is not a valid C string");
+
+        parse_field_ids(field_ids).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // kv_metadata returns the custom key/value pairs, which are written into the footer.
+    pub(crate) fn kv_metadata(&self) -> Vec<(String, String)> {
+        if self.kv_metadata.is_null() {
+            return vec![];
+        }
+
+        let kv_metadata = unsafe { CStr::from_ptr(self.kv_metadata) }
+            .to_str()
+            .expect("kv_metadata is not a valid C string");
+
+        parse_kv_metadata(kv_metadata).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// column_list returns the names of the columns in the comma separated list
+fn column_list(column_list: *const c_char) -> Vec<String> {
+    if column_list.is_null() {
+        return vec![];
+    }
+
+    let column_list = unsafe { CStr::from_ptr(column_list) }
+        .to_str()
+        .expect("column list is not a valid C string");
+
+    column_list
+        .split(',')
+        .map(|column| column.trim().to_string())
+        .collect()
+}
+
+// COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION is the version of
+// CopyToParquetDestReceiverOptions, which is incremented when options are appended to it.
+pub const COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION: u32 = 1;
+
+// CopyToParquetDestReceiverOptions are the copy options of the dest receivers, which other
+// extensions create. Callers zero the struct, set "size" to its size and "version" to
+// COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION, and then set the options they need. A null
+// option has its default value. New options are only appended to the end with a new version,
+// so that the options, which a caller built against an older version does not know of, keep
+// their default values.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CopyToParquetDestReceiverOptions {
+    pub size: usize,
+    pub version: u32,
+    pub row_group_size: *const i64,
+    pub row_group_size_bytes: *const i64,
+    pub compression: *const PgParquetCompression,
+    pub compression_level: *const i32,
+    pub statistics: *const PgParquetStatistics,
+    pub bloom_filter_columns: *const c_char,
+    pub bloom_filter_fpp: *const f64,
+    pub dictionary_enabled: *const bool,
+    pub dictionary_columns: *const c_char,
+    pub no_dictionary_columns: *const c_char,
+    pub byte_stream_split_columns: *const c_char,
+    pub writer_version: *const PgParquetWriterVersion,
+    pub dictionary_page_size_limit: *const i64,
+    pub data_page_size_bytes: *const i64,
+    pub data_page_row_count_limit: *const i64,
+    pub write_batch_size: *const i64,
+    pub max_buffered_bytes: *const i64,
+    pub kv_metadata: *const c_char,
+    pub geoparquet_covering: *const bool,
+    pub allow_mixed_srids: *const bool,
+    pub nan_handling: *const PgParquetNanHandling,
+    pub unsupported_type: *const PgParquetUnsupportedType,
+    pub numeric_precision: *const i32,
+    pub numeric_scale: *const i32,
+    pub numeric_overflow: *const PgParquetNumericOverflow,
+    pub multidim_arrays: *const PgParquetMultidimArrays,
+    pub duplicate_columns: *const PgParquetDuplicateColumns,
+    pub max_rows_per_file: *const i64,
+    pub field_ids: *const c_char,
+    pub batch_memory_limit: *const i64,
+    pub infinite_timestamps: *const PgParquetInfiniteTimestamps,
+    pub if_exists: *const PgParquetIfExists,
+}
+
+impl Default for CopyToParquetDestReceiverOptions {
+    fn default() -> Self {
+        let null = std::ptr::null;
+
+        Self {
+            size: std::mem::size_of::<Self>(),
+            version: COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION,
+            row_group_size: null(),
+            row_group_size_bytes: null(),
+            compression: null(),
+            compression_level: null(),
+            statistics: null(),
+            bloom_filter_columns: null(),
+            bloom_filter_fpp: null(),
+            dictionary_enabled: null(),
+            dictionary_columns: null(),
+            no_dictionary_columns: null(),
+            byte_stream_split_columns: null(),
+            writer_version: null(),
+            dictionary_page_size_limit: null(),
+            data_page_size_bytes: null(),
+            data_page_row_count_limit: null(),
+            write_batch_size: null(),
+            max_buffered_bytes: null(),
+            kv_metadata: null(),
+            geoparquet_covering: null(),
+            allow_mixed_srids: null(),
+            nan_handling: null(),
+            unsupported_type: null(),
+            numeric_precision: null(),
+            numeric_scale: null(),
+            numeric_overflow: null(),
+            multidim_arrays: null(),
+            duplicate_columns: null(),
+            max_rows_per_file: null(),
+            field_ids: null(),
+            batch_memory_limit: null(),
+            infinite_timestamps: null(),
+            if_exists: null(),
+        }
+    }
+}
+
+impl CopyToParquetDestReceiverOptions {
+    // from_ptr copies the options of the caller, which are only "size" bytes long when the
+    // caller is built against an older version. A null pointer means all default values.
+    fn from_ptr(options: *const Self) -> Self {
+        let mut copied_options = Self::default();
+
+        if options.is_null() {
+            return copied_options;
+        }
+
+        let size = unsafe { std::ptr::addr_of!((*options).size).read() };
+        let version = unsafe { std::ptr::addr_of!((*options).version).read() };
+
+        if version == 0 || version > COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION {
+            panic!(
+                "unsupported version {} of the dest receiver options, the latest version is {}",
+                version, COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION
+            );
+        }
+
+        if size < std::mem::offset_of!(Self, row_group_size) || size > std::mem::size_of::<Self>() {
+            panic!("invalid size {} of the dest receiver options", size);
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                options as *const u8,
+                &mut copied_options as *mut Self as *mut u8,
+                size,
+            )
+        };
+
+        copied_options
+    }
 }
 
 #[repr(C)]
@@ -32,10 +324,28 @@ struct CopyToParquetDestReceiver {
     collected_tuple_count: i64,
     collected_tuple_size: i64,
     collected_tuple_column_sizes: *mut i64,
+    row_group_tuple_count: i64,
+    row_group_tuple_size: i64,
     uri: *const c_char,
+    // the file is written to the stream instead of the uri, if set
+    stream: *const CopyToParquetStream,
     copy_options: CopyToParquetOptions,
+    file_tuple_count: i64,
+    finished_file_count: i64,
+    // uris of the finished files, which are removed when the COPY fails
+    finished_file_uris: *mut Vec<Url>,
+    // suffix of the files of max_rows_per_file with if_exists 'append_suffix', which is shared
+    // by the files of the COPY. It is empty until the first file is written.
+    split_file_suffix: *mut String,
+    finished_file_bytes: i64,
+    // finished files, which are still uploaded in the background
+    pending_uploads: *mut VecDeque<PendingUpload>,
     per_copy_context: MemoryContext,
+    per_conversion_context: MemoryContext,
     parquet_writer_context: *mut ParquetWriterContext,
+    export_size_warned: bool,
+    // NaN and Infinity values, which are replaced with NULL in all files of the COPY
+    nulled_non_finite_values: i64,
 }
 
 impl CopyToParquetDestReceiver {
@@ -74,12 +384,35 @@ impl CopyToParquetDestReceiver {
         };
     }
 
+    // the in progress row group consists of the tuples, which are already written to it,
+    // and the collected tuples
     fn collected_tuples_exceeds_row_group_size(&self) -> bool {
-        self.collected_tuple_count >= self.copy_options.row_group_size
+        self.row_group_tuple_count + self.collected_tuple_count >= self.copy_options.row_group_size
     }
 
     fn collected_tuples_exceeds_row_group_size_bytes(&self) -> bool {
-        self.collected_tuple_size >= self.copy_options.row_group_size_bytes
+        self.row_group_tuple_size + self.collected_tuple_size
+            >= self.copy_options.row_group_size_bytes
+    }
+
+    fn collected_tuples_exceeds_max_buffered_bytes(&self) -> bool {
+        self.collected_tuple_size >= self.copy_options.max_buffered_bytes
+    }
+
+    // the rows of the in progress row group are held in memory, either as collected tuples or
+    // as encoded pages, until the row group is finished. "tuple_size" is the size of the tuple,
+    // which is about to be collected.
+    fn collected_tuples_exceeds_batch_memory_limit(&self, tuple_size: i64) -> bool {
+        self.row_group_tuple_size + self.collected_tuple_size + tuple_size
+            >= self.copy_options.batch_memory_limit
+    }
+
+    // the in progress file consists of the tuples, which are already written to it, and the
+    // collected tuples
+    fn collected_tuples_exceeds_max_rows_per_file(&self) -> bool {
+        self.copy_options.max_rows_per_file > 0
+            && self.file_tuple_count + self.collected_tuple_count
+                >= self.copy_options.max_rows_per_file
     }
 
     fn collected_tuples_exceeds_max_col_size(&self, tuple_column_sizes: &[i32]) -> bool {
@@ -95,7 +428,261 @@ impl CopyToParquetDestReceiver {
             .any(|size| size > MAX_ARROW_ARRAY_SIZE)
     }
 
-    fn write_tuples_to_parquet(&mut self) {
+    // start_parquet_writer creates the writer of the parquet file. With multidim_arrays 'nested',
+    // it is created when the first tuples are written, since the nested lists of the array
+    // columns depend on the dimensions of their arrays.
+    fn start_parquet_writer(&mut self, tuples: &[Option<PgHeapTuple<AllocatedByRust>>]) {
+        let tupledesc = unsafe { PgTupleDesc::from_pg_unchecked(self.tupledesc) };
+
+        let array_ndims = match self.copy_options.multidim_arrays {
+            PgParquetMultidimArrays::Error => HashMap::new(),
+            PgParquetMultidimArrays::Nested => multidim_array_ndims(tuples, &tupledesc),
+        };
+
+        let destination = if !self.stream.is_null() {
+            ParquetWriterDestination::Stream(unsafe { *self.stream })
+        } else if self.copy_options.max_rows_per_file > 0 {
+            ParquetWriterDestination::Uri(self.reserve_split_file_uri())
+        } else {
+            ParquetWriterDestination::Uri(self.reserve_file_uri(self.base_uri()))
+        };
+
+        let reserved_uri = match &destination {
+            ParquetWriterDestination::Uri(uri)
+                if self.copy_options.if_exists != PgParquetIfExists::Overwrite =>
+            {
+                Some(uri.clone())
+            }
+            _ => None,
+        };
+
+        // the writer outlives the per copy context, which is reset after each batch of tuples
+        let writer_memory_context = unsafe { (*self.per_copy_context).parent };
+
+        let parquet_writer_context = PgTryBuilder::new(AssertUnwindSafe(|| unsafe {
+            PgMemoryContexts::For(writer_memory_context).switch_to(|_context| {
+                ParquetWriterContext::new(destination, &self.copy_options, &tupledesc, &array_ndims)
+            })
+        }))
+        .catch_others(|cause| {
+            // the reserved file is not written without its writer
+            if let Some(reserved_uri) = &reserved_uri {
+                delete_uri(reserved_uri);
+            }
+
+            cause.rethrow()
+        })
+        .execute();
+
+        // leak the parquet writer context since it will be used during the COPY operation
+        self.parquet_writer_context = Box::into_raw(Box::new(parquet_writer_context));
+    }
+
+    // reserve_file_uri returns the uri, which the next file is written to. With if_exists
+    // 'error' or 'append_suffix', the file is created before it is written, only if it does not
+    // exist, so that a concurrent COPY TO cannot write to the same uri.
+    fn reserve_file_uri(&self, uri: Url) -> Url {
+        match self.copy_options.if_exists {
+            PgParquetIfExists::Overwrite => uri,
+            PgParquetIfExists::Error => {
+                if !create_uri_if_absent(&uri) {
+                    panic!("file {} already exists", redact_uri(&uri));
+                }
+
+                uri
+            }
+            PgParquetIfExists::AppendSuffix => {
+                if create_uri_if_absent(&uri) {
+                    return uri;
+                }
+
+                // a new suffix is only taken if a file with the former one is created meanwhile
+                for _ in 0..MAX_UNIQUE_FILE_SUFFIX_ATTEMPTS {
+                    let suffixed_uri = suffixed_file_uri(&uri, &unique_file_suffix());
+
+                    if create_uri_if_absent(&suffixed_uri) {
+                        pgrx::notice!("COPY TO writes to {}", redact_uri(&suffixed_uri));
+
+                        return suffixed_uri;
+                    }
+                }
+
+                panic!(
+                    "could not create a file with a unique suffix for {}",
+                    redact_uri(&uri)
+                );
+            }
+        }
+    }
+
+    // reserve_split_file_uri returns the uri of the next file of max_rows_per_file. With if_exists
+    // 'append_suffix', the files are written next to the existing files under the uri, with the
+    // suffix of the COPY appended to their names, e.g. <uri>/part-00000-4242-1-9f86d081.parquet.
+    fn reserve_split_file_uri(&mut self) -> Url {
+        let split_uri = split_file_uri(&self.base_uri(), self.finished_file_count);
+
+        if self.copy_options.if_exists != PgParquetIfExists::AppendSuffix {
+            return self.reserve_file_uri(split_uri);
+        }
+
+        for _ in 0..MAX_UNIQUE_FILE_SUFFIX_ATTEMPTS {
+            let split_file_suffix = self.split_file_suffix();
+
+            if split_file_suffix.is_empty() {
+                *split_file_suffix = unique_file_suffix();
+
+                pgrx::notice!(
+                    "COPY TO writes to {} with suffix {}",
+                    redact_uri(&self.base_uri()),
+                    self.split_file_suffix()
+                );
+            }
+
+            let suffixed_uri = suffixed_file_uri(&split_uri, self.split_file_suffix());
+
+            if create_uri_if_absent(&suffixed_uri) {
+                return suffixed_uri;
+            }
+
+            // the following files take a new suffix, if a file with the former one is created
+            // meanwhile
+            self.split_file_suffix().clear();
+        }
+
+        panic!(
+            "could not create a file with a unique suffix for {}",
+            redact_uri(&split_uri)
+        );
+    }
+
+    fn split_file_suffix(&mut self) -> &mut String {
+        unsafe {
+            self.split_file_suffix
+                .as_mut()
+                .expect("split file suffix is not found")
+        }
+    }
+
+    fn base_uri(&self) -> Url {
+        let uri = unsafe { CStr::from_ptr(self.uri) }
+            .to_str()
+            .expect("uri is not a valid C string");
+
+        parse_uri(uri)
+    }
+
+    // finish_parquet_file finishes the in progress file, which has max_rows_per_file rows, so
+    // that the next tuples are written to a new file.
+    fn finish_parquet_file(&mut self) {
+        let parquet_writer_context = unsafe { Box::from_raw(self.parquet_writer_context) };
+
+        self.parquet_writer_context = std::ptr::null_mut();
+
+        self.finished_file_bytes += parquet_writer_context.bytes_written() as i64;
+
+        if let Some(uri) = parquet_writer_context.uri() {
+            self.finished_file_uris().push(uri.clone());
+        }
+
+        let upload_concurrency = UPLOAD_CONCURRENCY.get() as usize;
+
+        #[cfg(feature = "pg_test")]
+        let fail_upload = self.finished_file_count + 1 == TEST_FAILED_UPLOAD_FILE.get() as i64;
+
+        if upload_concurrency > 1 {
+            // the file is uploaded while the next file is written
+            let pending_uploads = self.pending_uploads();
+
+            while pending_uploads.len() >= upload_concurrency - 1 {
+                let pending_upload = pending_uploads.pop_front().expect("no pending upload");
+                pending_upload.wait();
+            }
+
+            #[cfg(feature = "pg_test")]
+            let pending_upload = if fail_upload {
+                parquet_writer_context.fail_in_background()
+            } else {
+                parquet_writer_context.finish_in_background()
+            };
+
+            #[cfg(not(feature = "pg_test"))]
+            let pending_upload = parquet_writer_context.finish_in_background();
+
+            pending_uploads.push_back(pending_upload);
+        } else {
+            // the file is finished when its writer is dropped
+            drop(parquet_writer_context);
+        }
+
+        self.file_tuple_count = 0;
+        self.finished_file_count += 1;
+    }
+
+    fn finished_file_uris(&mut self) -> &mut Vec<Url> {
+        unsafe {
+            self.finished_file_uris
+                .as_mut()
+                .expect("finished file uris are not found")
+        }
+    }
+
+    fn pending_uploads(&mut self) -> &mut VecDeque<PendingUpload> {
+        unsafe {
+            self.pending_uploads
+                .as_mut()
+                .expect("pending uploads are not found")
+        }
+    }
+
+    // wait_finished_uploads records the files, whose uploads are finished in the background,
+    // in their order. It fails the COPY if an upload failed.
+    fn wait_finished_uploads(&mut self) {
+        let pending_uploads = self.pending_uploads();
+
+        while pending_uploads
+            .front()
+            .is_some_and(|pending_upload| pending_upload.is_finished())
+        {
+            let pending_upload = pending_uploads.pop_front().expect("no pending upload");
+            pending_upload.wait();
+        }
+    }
+
+    // wait_pending_uploads waits until all files, which are finished in the background, are
+    // uploaded.
+    fn wait_pending_uploads(&mut self) {
+        while let Some(pending_upload) = self.pending_uploads().pop_front() {
+            pending_upload.wait();
+        }
+    }
+
+    // abort_pending_uploads cancels the uploads of the files, which are finished in the
+    // background. They are removed along with the other finished files.
+    fn abort_pending_uploads(&mut self) {
+        if self.pending_uploads.is_null() {
+            return;
+        }
+
+        while let Some(pending_upload) = self.pending_uploads().pop_front() {
+            pending_upload.abort();
+        }
+    }
+
+    // delete_finished_files removes the files, which are already finished by a COPY TO with
+    // max_rows_per_file.
+    fn delete_finished_files(&self) {
+        let Some(finished_file_uris) = (unsafe { self.finished_file_uris.as_ref() }) else {
+            return;
+        };
+
+        for uri in finished_file_uris {
+            delete_uri(uri);
+        }
+    }
+
+    // write_tuples_to_parquet writes the collected tuples to the in progress row group, and
+    // flushes the row group if "finish_row_group" is set.
+    fn write_tuples_to_parquet(&mut self, finish_row_group: bool) {
         debug_assert!(!self.tupledesc.is_null());
 
         let tupledesc = unsafe { PgTupleDesc::from_pg_unchecked(self.tupledesc) };
@@ -113,14 +700,175 @@ impl CopyToParquetDestReceiver {
             })
             .collect::<Vec<_>>();
 
+        if self.parquet_writer_context.is_null() {
+            self.start_parquet_writer(&tuples);
+        }
+
         let current_parquet_writer_context = unsafe {
             self.parquet_writer_context
                 .as_mut()
                 .expect("parquet writer context is not found")
         };
-        current_parquet_writer_context.write_new_row_group(tuples);
+
+        let per_conversion_context = self.per_conversion_context;
+        let memory_context_before_conversion = unsafe { CurrentMemoryContext };
+
+        // values are converted to arrow in a separate memory context. When it runs out of memory,
+        // we report which value could not be converted instead of the generic out of memory error.
+        PgTryBuilder::new(AssertUnwindSafe(|| unsafe {
+            PgMemoryContexts::For(per_conversion_context).switch_to(|_context| {
+                current_parquet_writer_context.write_tuples(tuples, finish_row_group)
+            })
+        }))
+        .catch_when(PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY, |cause| {
+            unsafe { PgMemoryContexts::For(memory_context_before_conversion).set_as_current() };
+
+            let Some(progress) = current_conversion_progress() else {
+                cause.rethrow()
+            };
+
+            let value_size = self.collected_tuple_value_size(progress.row_idx, progress.attnum);
+
+            // do not retry converting the same tuples at shutdown, and do not report the
+            // column of the failed conversion for the errors after it
+            self.reset_collected_tuples();
+            finish_conversion();
+
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY,
+                format!(
+                    "out of memory while converting column \"{}\" at row {} of the row group to parquet",
+                    progress.attname, progress.row_idx
+                ),
+                format!(
+                    "The value is approximately {} bytes. Try a smaller row_group_size or row_group_size_bytes.",
+                    value_size
+                ),
+            );
+        })
+        .execute();
+
+        self.nulled_non_finite_values +=
+            current_parquet_writer_context.take_nulled_non_finite_values() as i64;
+
+        if finish_row_group {
+            self.row_group_tuple_count = 0;
+            self.row_group_tuple_size = 0;
+        } else {
+            self.row_group_tuple_count += self.collected_tuple_count;
+            self.row_group_tuple_size += self.collected_tuple_size;
+        }
+
+        self.file_tuple_count += self.collected_tuple_count;
 
         self.reset_collected_tuples();
+
+        unsafe { MemoryContextReset(self.per_conversion_context) };
+
+        self.check_export_size();
+    }
+
+    // check_export_size warns when the bytes written by the COPY exceed pg_parquet.warn_export_bytes,
+    // and aborts the COPY when they exceed pg_parquet.max_export_bytes.
+    fn check_export_size(&mut self) {
+        let bytes_written = self.finished_file_bytes
+            + unsafe {
+                self.parquet_writer_context
+                    .as_ref()
+                    .expect("parquet writer context is not found")
+                    .bytes_written() as i64
+            };
+
+        let warn_export_bytes = WARN_EXPORT_BYTES.get() as i64 * 1024;
+
+        if warn_export_bytes > 0 && bytes_written > warn_export_bytes && !self.export_size_warned {
+            self.export_size_warned = true;
+
+            ereport!(
+                PgLogLevel::WARNING,
+                PgSqlErrorCode::ERRCODE_WARNING,
+                format!(
+                    "COPY TO has written {} bytes, which exceeds pg_parquet.warn_export_bytes of {} bytes",
+                    bytes_written, warn_export_bytes
+                ),
+            );
+        }
+
+        let max_export_bytes = MAX_EXPORT_BYTES.get() as i64 * 1024;
+
+        if max_export_bytes > 0 && bytes_written > max_export_bytes {
+            self.abort_export();
+
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_CONFIGURATION_LIMIT_EXCEEDED,
+                format!(
+                    "COPY TO has written {} bytes, which exceeds pg_parquet.max_export_bytes of {} bytes",
+                    bytes_written, max_export_bytes
+                ),
+                "The files written by the COPY are removed.",
+            );
+        }
+    }
+
+    // abort_export closes the parquet file written so far and removes it, along with the
+    // finished files of max_rows_per_file.
+    fn abort_export(&mut self) {
+        self.abort_pending_uploads();
+
+        self.delete_finished_files();
+
+        if self.parquet_writer_context.is_null() {
+            return;
+        }
+
+        let mut parquet_writer_context = unsafe { Box::from_raw(self.parquet_writer_context) };
+
+        self.parquet_writer_context = std::ptr::null_mut();
+
+        let uri = parquet_writer_context.uri().cloned();
+
+        // the upload is aborted instead of finishing the file when the writer is dropped, and
+        // a stream is not finished, its caller discards the bytes received so far
+        let upload_aborted = parquet_writer_context.abort();
+
+        drop(parquet_writer_context);
+
+        // like abort, the file is removed if its upload completed or if_exists reserved it before
+        let file_written =
+            !upload_aborted || self.copy_options.if_exists != PgParquetIfExists::Overwrite;
+
+        if let (Some(uri), true) = (uri, file_written) {
+            delete_uri(&uri);
+        }
+
+        // do not write the remaining tuples at shutdown
+        self.collected_tuple_count = 0;
+    }
+
+    // collected_tuple_value_size returns the approximate size of the value at the given row
+    // and attribute of the collected tuples.
+    fn collected_tuple_value_size(&self, row_idx: usize, attnum: i16) -> i32 {
+        let tuples = unsafe { PgList::<HeapTupleData>::from_pg(self.collected_tuples) };
+
+        let tupledesc = unsafe { PgTupleDesc::from_pg_unchecked(self.tupledesc) };
+
+        let attribute = tupledesc
+            .get(attnum as usize - 1)
+            .expect("cannot get attribute");
+
+        let datum = tuples.get_ptr(row_idx).and_then(|tuple| unsafe {
+            heap_getattr_raw(
+                tuple,
+                NonZeroUsize::new(attnum as usize).expect("invalid attnum"),
+                self.tupledesc,
+            )
+        });
+
+        datum
+            .map(|datum| datum_size(datum, attribute.type_oid().value()))
+            .unwrap_or(0)
     }
 
     fn cleanup(&mut self) {
@@ -130,6 +878,12 @@ impl CopyToParquetDestReceiver {
             self.per_copy_context = std::ptr::null_mut();
         }
 
+        if !self.per_conversion_context.is_null() {
+            unsafe { MemoryContextDelete(self.per_conversion_context) };
+
+            self.per_conversion_context = std::ptr::null_mut();
+        }
+
         if !self.parquet_writer_context.is_null() {
             let parquet_writer_context = unsafe { Box::from_raw(self.parquet_writer_context) };
 
@@ -138,10 +892,77 @@ impl CopyToParquetDestReceiver {
             drop(parquet_writer_context);
         }
 
+        if !self.pending_uploads.is_null() {
+            let pending_uploads = unsafe { Box::from_raw(self.pending_uploads) };
+
+            self.pending_uploads = std::ptr::null_mut();
+
+            drop(pending_uploads);
+        }
+
+        if !self.finished_file_uris.is_null() {
+            let finished_file_uris = unsafe { Box::from_raw(self.finished_file_uris) };
+
+            self.finished_file_uris = std::ptr::null_mut();
+
+            drop(finished_file_uris);
+        }
+
+        if !self.split_file_suffix.is_null() {
+            let split_file_suffix = unsafe { Box::from_raw(self.split_file_suffix) };
+
+            self.split_file_suffix = std::ptr::null_mut();
+
+            drop(split_file_suffix);
+        }
+
         self.collected_tuple_count = 0;
+
+        // the conversion is not finished when it fails, e.g. with an infinite timestamp
+        finish_conversion();
+    }
+
+    // abort aborts the upload of the file without writing the remaining tuples, cleans up
+    // the dest receiver, and removes the partially written file.
+    fn abort(&mut self) {
+        self.abort_pending_uploads();
+
+        self.delete_finished_files();
+
+        if self.parquet_writer_context.is_null() {
+            self.cleanup();
+            return;
+        }
+
+        let uri = unsafe { (*self.parquet_writer_context).uri().cloned() };
+
+        let upload_aborted = unsafe { (*self.parquet_writer_context).abort() };
+
+        self.cleanup();
+
+        // the object is only written when its upload completes, e.g. if the COPY failed
+        // while writing the footer. The file, which is reserved by if_exists, is written before.
+        let file_written =
+            !upload_aborted || self.copy_options.if_exists != PgParquetIfExists::Overwrite;
+
+        if let (Some(uri), true) = (uri, file_written) {
+            delete_uri(&uri);
+        }
     }
 }
 
+// abort_copy_to_parquet_dest_receiver aborts the failed COPY TO instead of shutting down the
+// dest receiver, which would finish the partially written file.
+pub(crate) fn abort_copy_to_parquet_dest_receiver(dest: *mut DestReceiver) {
+    let parquet_dest = unsafe {
+        (dest as *mut CopyToParquetDestReceiver)
+            .as_mut()
+            .expect("invalid parquet dest receiver ptr")
+    };
+
+    parquet_dest.abort();
+}
+
 #[pg_guard]
 extern "C" fn copy_startup(dest: *mut DestReceiver, _operation: i32, tupledesc: TupleDesc) {
     let parquet_dest = unsafe {
@@ -156,6 +977,16 @@ extern "C" fn copy_startup(dest: *mut DestReceiver, _operation: i32, tupledesc:
     // from_pg_unchecked makes sure tupledesc is not dropped since it is an external tupledesc
     let tupledesc = unsafe { PgTupleDesc::from_pg_unchecked(tupledesc) };
 
+    // duplicate columns of the query, e.g. "?column?" of unnamed ones, get unique field names.
+    // Otherwise, collecting the attributes fails for them.
+    let tupledesc = match parquet_dest.copy_options.duplicate_columns {
+        PgParquetDuplicateColumns::Rename => rename_duplicate_attributes(&tupledesc),
+        PgParquetDuplicateColumns::Error => tupledesc,
+    };
+
+    // domains over supported types, and arrays of them, are written as their base types
+    let tupledesc = unwrap_domain_attributes(&tupledesc, CollectAttributesFor::CopyTo);
+
     // update the parquet dest receiver's missing fields
     parquet_dest.tupledesc = tupledesc.as_ptr();
     parquet_dest.collected_tuples = PgList::<HeapTupleData>::new().into_pg();
@@ -167,20 +998,9 @@ extern "C" fn copy_startup(dest: *mut DestReceiver, _operation: i32, tupledesc:
     };
     parquet_dest.natts = tupledesc.len();
 
-    let uri = unsafe { CStr::from_ptr(parquet_dest.uri) }
-        .to_str()
-        .expect("uri is not a valid C string");
-
-    let uri = parse_uri(uri);
-
-    let compression = parquet_dest.copy_options.compression;
-
-    let compression_level = parquet_dest.copy_options.compression_level;
-
-    // leak the parquet writer context since it will be used during the COPY operation
-    let parquet_writer_context =
-        ParquetWriterContext::new(uri, compression, compression_level, &tupledesc);
-    parquet_dest.parquet_writer_context = Box::into_raw(Box::new(parquet_writer_context));
+    if parquet_dest.copy_options.multidim_arrays == PgParquetMultidimArrays::Error {
+        parquet_dest.start_parquet_writer(&[]);
+    }
 }
 
 #[pg_guard]
@@ -191,6 +1011,9 @@ extern "C" fn copy_receive(slot: *mut TupleTableSlot, dest: *mut DestReceiver) -
             .expect("invalid parquet dest receiver ptr")
     };
 
+    // a failed upload of the previous files fails the COPY
+    parquet_dest.wait_finished_uploads();
+
     unsafe {
         let mut per_copy_ctx = PgMemoryContexts::For(parquet_dest.per_copy_context);
 
@@ -214,8 +1037,19 @@ extern "C" fn copy_receive(slot: *mut TupleTableSlot, dest: *mut DestReceiver) -
 
             let column_sizes = tuple_column_sizes(&datums, &tupledesc);
 
-            if parquet_dest.collected_tuples_exceeds_max_col_size(&column_sizes) {
-                parquet_dest.write_tuples_to_parquet();
+            let tuple_size = column_sizes.iter().map(|size| *size as i64).sum::<i64>();
+
+            let has_pending_tuples =
+                parquet_dest.row_group_tuple_count + parquet_dest.collected_tuple_count > 0;
+
+            // a wide tuple, e.g. with large text values, finishes the row group before it is
+            // collected, so that the pending tuples are not held in memory along with it
+            if parquet_dest.collected_tuples_exceeds_max_col_size(&column_sizes)
+                || (has_pending_tuples
+                    && parquet_dest.collected_tuples_exceeds_batch_memory_limit(tuple_size))
+            {
+                let finish_row_group = true;
+                parquet_dest.write_tuples_to_parquet(finish_row_group);
             }
 
             let heap_tuple = PgHeapTuple::from_datums(tupledesc, datums)
@@ -223,10 +1057,20 @@ extern "C" fn copy_receive(slot: *mut TupleTableSlot, dest: *mut DestReceiver) -
 
             parquet_dest.collect_tuple(heap_tuple, column_sizes);
 
-            if parquet_dest.collected_tuples_exceeds_row_group_size()
+            if parquet_dest.collected_tuples_exceeds_max_rows_per_file() {
+                let finish_row_group = true;
+                parquet_dest.write_tuples_to_parquet(finish_row_group);
+                parquet_dest.finish_parquet_file();
+            } else if parquet_dest.collected_tuples_exceeds_row_group_size()
                 || parquet_dest.collected_tuples_exceeds_row_group_size_bytes()
+                || parquet_dest.collected_tuples_exceeds_batch_memory_limit(0)
             {
-                parquet_dest.write_tuples_to_parquet();
+                let finish_row_group = true;
+                parquet_dest.write_tuples_to_parquet(finish_row_group);
+            } else if parquet_dest.collected_tuples_exceeds_max_buffered_bytes() {
+                // the tuples are written to the in progress row group to release their memory
+                let finish_row_group = false;
+                parquet_dest.write_tuples_to_parquet(finish_row_group);
             }
         });
     };
@@ -243,7 +1087,30 @@ extern "C" fn copy_shutdown(dest: *mut DestReceiver) {
     };
 
     if parquet_dest.collected_tuple_count > 0 {
-        parquet_dest.write_tuples_to_parquet();
+        let finish_row_group = true;
+        parquet_dest.write_tuples_to_parquet(finish_row_group);
+    }
+
+    // the files finished in the background are recorded before the last file
+    parquet_dest.wait_pending_uploads();
+
+    // the file has the flat lists of the array columns when no tuples are written. With
+    // max_rows_per_file, no empty file follows the last finished file.
+    if parquet_dest.parquet_writer_context.is_null() && parquet_dest.finished_file_count == 0 {
+        parquet_dest.start_parquet_writer(&[]);
+    }
+
+    let nulled_values = parquet_dest.nulled_non_finite_values;
+
+    if parquet_dest.copy_options.nan_handling == PgParquetNanHandling::Null && nulled_values > 0 {
+        ereport!(
+            PgLogLevel::WARNING,
+            PgSqlErrorCode::ERRCODE_WARNING,
+            format!(
+                "COPY TO replaced {} NaN or Infinity float values with NULL",
+                nulled_values
+            ),
+        );
     }
 
     parquet_dest.cleanup();
@@ -254,7 +1121,9 @@ extern "C" fn copy_destroy(_dest: *mut DestReceiver) {}
 
 // create_copy_to_parquet_dest_receiver creates a new CopyToParquetDestReceiver that can be
 // used as a destination receiver for COPY TO command. All arguments, except "uri", are optional
-// and have default values if not provided.
+// and have default values if not provided. Its arguments are kept as is for the existing
+// callers, and the other copy options are passed to
+// create_copy_to_parquet_dest_receiver_with_options.
 #[pg_guard]
 #[no_mangle]
 pub extern "C" fn create_copy_to_parquet_dest_receiver(
@@ -264,6 +1133,71 @@ pub extern "C" fn create_copy_to_parquet_dest_receiver(
     compression: *const PgParquetCompression,
     compression_level: *const i32,
 ) -> *mut DestReceiver {
+    let options = CopyToParquetDestReceiverOptions {
+        row_group_size,
+        row_group_size_bytes,
+        compression,
+        compression_level,
+        ..Default::default()
+    };
+
+    create_parquet_dest_receiver(uri, std::ptr::null(), &options)
+}
+
+// create_copy_to_parquet_dest_receiver_with_options creates a new CopyToParquetDestReceiver,
+// which writes the file to the uri with the given copy options. The options are optional, too.
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn create_copy_to_parquet_dest_receiver_with_options(
+    uri: *const c_char,
+    options: *const CopyToParquetDestReceiverOptions,
+) -> *mut DestReceiver {
+    let options = CopyToParquetDestReceiverOptions::from_ptr(options);
+
+    create_parquet_dest_receiver(uri, std::ptr::null(), &options)
+}
+
+fn create_parquet_dest_receiver(
+    uri: *const c_char,
+    stream: *const CopyToParquetStream,
+    options: &CopyToParquetDestReceiverOptions,
+) -> *mut DestReceiver {
+    let CopyToParquetDestReceiverOptions {
+        row_group_size,
+        row_group_size_bytes,
+        compression,
+        compression_level,
+        statistics,
+        bloom_filter_columns,
+        bloom_filter_fpp,
+        dictionary_enabled,
+        dictionary_columns,
+        no_dictionary_columns,
+        byte_stream_split_columns,
+        writer_version,
+        dictionary_page_size_limit,
+        data_page_size_bytes,
+        data_page_row_count_limit,
+        write_batch_size,
+        max_buffered_bytes,
+        kv_metadata,
+        geoparquet_covering,
+        allow_mixed_srids,
+        nan_handling,
+        unsupported_type,
+        numeric_precision,
+        numeric_scale,
+        numeric_overflow,
+        multidim_arrays,
+        duplicate_columns,
+        max_rows_per_file,
+        field_ids,
+        batch_memory_limit,
+        infinite_timestamps,
+        if_exists,
+        ..
+    } = *options;
+
     let per_copy_context = unsafe {
         AllocSetContextCreateExtended(
             CurrentMemoryContext as _,
@@ -274,6 +1208,16 @@ pub extern "C" fn create_copy_to_parquet_dest_receiver(
         )
     };
 
+    let per_conversion_context = unsafe {
+        AllocSetContextCreateExtended(
+            CurrentMemoryContext as _,
+            "ParquetCopyConversion".as_pg_cstr(),
+            ALLOCSET_DEFAULT_MINSIZE as _,
+            ALLOCSET_DEFAULT_INITSIZE as _,
+            ALLOCSET_DEFAULT_MAXSIZE as _,
+        )
+    };
+
     let row_group_size = if row_group_size.is_null() {
         DEFAULT_ROW_GROUP_SIZE
     } else {
@@ -300,6 +1244,140 @@ pub extern "C" fn create_copy_to_parquet_dest_receiver(
         unsafe { *compression_level }
     };
 
+    let statistics = if statistics.is_null() {
+        PgParquetStatistics::default()
+    } else {
+        unsafe { *statistics }
+    };
+
+    let bloom_filter_fpp = if bloom_filter_fpp.is_null() {
+        DEFAULT_BLOOM_FILTER_FPP
+    } else {
+        unsafe { *bloom_filter_fpp }
+    };
+
+    let dictionary_enabled = if dictionary_enabled.is_null() {
+        DEFAULT_DICTIONARY_ENABLED
+    } else {
+        unsafe { *dictionary_enabled }
+    };
+
+    let writer_version = if writer_version.is_null() {
+        PgParquetWriterVersion::default()
+    } else {
+        unsafe { *writer_version }
+    };
+
+    let dictionary_page_size_limit = if dictionary_page_size_limit.is_null() {
+        DEFAULT_DICTIONARY_PAGE_SIZE_LIMIT as i64
+    } else {
+        unsafe { *dictionary_page_size_limit }
+    };
+
+    let data_page_size_bytes = if data_page_size_bytes.is_null() {
+        DEFAULT_PAGE_SIZE as i64
+    } else {
+        unsafe { *data_page_size_bytes }
+    };
+
+    let data_page_row_count_limit = if data_page_row_count_limit.is_null() {
+        DEFAULT_DATA_PAGE_ROW_COUNT_LIMIT as i64
+    } else {
+        unsafe { *data_page_row_count_limit }
+    };
+
+    let write_batch_size = if write_batch_size.is_null() {
+        DEFAULT_WRITE_BATCH_SIZE as i64
+    } else {
+        unsafe { *write_batch_size }
+    };
+
+    // by default, tuples are buffered until the row group is complete
+    let max_buffered_bytes = if max_buffered_bytes.is_null() {
+        row_group_size_bytes
+    } else {
+        unsafe { *max_buffered_bytes }
+    };
+
+    let batch_memory_limit = if batch_memory_limit.is_null() {
+        BATCH_MEMORY_LIMIT.get() as i64 * 1024
+    } else {
+        unsafe { *batch_memory_limit }
+    };
+
+    let geoparquet_covering = if geoparquet_covering.is_null() {
+        false
+    } else {
+        unsafe { *geoparquet_covering }
+    };
+
+    let allow_mixed_srids = if allow_mixed_srids.is_null() {
+        false
+    } else {
+        unsafe { *allow_mixed_srids }
+    };
+
+    let nan_handling = if nan_handling.is_null() {
+        PgParquetNanHandling::default()
+    } else {
+        unsafe { *nan_handling }
+    };
+
+    let infinite_timestamps = if infinite_timestamps.is_null() {
+        PgParquetInfiniteTimestamps::default()
+    } else {
+        unsafe { *infinite_timestamps }
+    };
+
+    let if_exists = if if_exists.is_null() {
+        PgParquetIfExists::default()
+    } else {
+        unsafe { *if_exists }
+    };
+
+    let unsupported_type = if unsupported_type.is_null() {
+        PgParquetUnsupportedType::default()
+    } else {
+        unsafe { *unsupported_type }
+    };
+
+    let numeric_precision = if numeric_precision.is_null() {
+        DEFAULT_UNBOUNDED_NUMERIC_PRECISION as i32
+    } else {
+        unsafe { *numeric_precision }
+    };
+
+    let numeric_scale = if numeric_scale.is_null() {
+        DEFAULT_UNBOUNDED_NUMERIC_SCALE as i32
+    } else {
+        unsafe { *numeric_scale }
+    };
+
+    let numeric_overflow = if numeric_overflow.is_null() {
+        PgParquetNumericOverflow::default()
+    } else {
+        unsafe { *numeric_overflow }
+    };
+
+    let multidim_arrays = if multidim_arrays.is_null() {
+        PgParquetMultidimArrays::default()
+    } else {
+        unsafe { *multidim_arrays }
+    };
+
+    let duplicate_columns = if duplicate_columns.is_null() {
+        PgParquetDuplicateColumns::default()
+    } else {
+        unsafe { *duplicate_columns }
+    };
+
+    // by default, all tuples are written to a single file
+    let max_rows_per_file = if max_rows_per_file.is_null() {
+        0
+    } else {
+        unsafe { *max_rows_per_file }
+    };
+
     let mut parquet_dest =
         unsafe { PgBox::<CopyToParquetDestReceiver, AllocatedByPostgres>::alloc0() };
 
@@ -309,21 +1387,134 @@ pub extern "C" fn create_copy_to_parquet_dest_receiver(
     parquet_dest.dest.rDestroy = Some(copy_destroy);
     parquet_dest.dest.mydest = CommandDest::DestCopyOut;
     parquet_dest.uri = uri;
+    parquet_dest.stream = stream;
     parquet_dest.tupledesc = std::ptr::null_mut();
     parquet_dest.parquet_writer_context = std::ptr::null_mut();
     parquet_dest.natts = 0;
     parquet_dest.collected_tuple_count = 0;
     parquet_dest.collected_tuples = std::ptr::null_mut();
     parquet_dest.collected_tuple_column_sizes = std::ptr::null_mut();
+    parquet_dest.row_group_tuple_count = 0;
+    parquet_dest.row_group_tuple_size = 0;
     parquet_dest.copy_options.row_group_size = row_group_size;
     parquet_dest.copy_options.row_group_size_bytes = row_group_size_bytes;
     parquet_dest.copy_options.compression = compression;
     parquet_dest.copy_options.compression_level = compression_level;
+    parquet_dest.copy_options.statistics = statistics;
+    parquet_dest.copy_options.bloom_filter_columns = bloom_filter_columns;
+    parquet_dest.copy_options.bloom_filter_fpp = bloom_filter_fpp;
+    parquet_dest.copy_options.dictionary_enabled = dictionary_enabled;
+    parquet_dest.copy_options.dictionary_columns = dictionary_columns;
+    parquet_dest.copy_options.no_dictionary_columns = no_dictionary_columns;
+    parquet_dest.copy_options.byte_stream_split_columns = byte_stream_split_columns;
+    parquet_dest.copy_options.writer_version = writer_version;
+    parquet_dest.copy_options.dictionary_page_size_limit = dictionary_page_size_limit;
+    parquet_dest.copy_options.data_page_size_bytes = data_page_size_bytes;
+    parquet_dest.copy_options.data_page_row_count_limit = data_page_row_count_limit;
+    parquet_dest.copy_options.write_batch_size = write_batch_size;
+    parquet_dest.copy_options.max_buffered_bytes = max_buffered_bytes;
+    parquet_dest.copy_options.batch_memory_limit = batch_memory_limit;
+    parquet_dest.copy_options.kv_metadata = kv_metadata;
+    parquet_dest.copy_options.geoparquet_covering = geoparquet_covering;
+    parquet_dest.copy_options.allow_mixed_srids = allow_mixed_srids;
+    parquet_dest.copy_options.nan_handling = nan_handling;
+    parquet_dest.copy_options.infinite_timestamps = infinite_timestamps;
+    parquet_dest.copy_options.unsupported_type = unsupported_type;
+    parquet_dest.copy_options.numeric_precision = numeric_precision;
+    parquet_dest.copy_options.numeric_scale = numeric_scale;
+    parquet_dest.copy_options.numeric_overflow = numeric_overflow;
+    parquet_dest.copy_options.multidim_arrays = multidim_arrays;
+    parquet_dest.copy_options.duplicate_columns = duplicate_columns;
+    parquet_dest.copy_options.max_rows_per_file = max_rows_per_file;
+    parquet_dest.copy_options.field_ids = field_ids;
+    parquet_dest.copy_options.if_exists = if_exists;
+    parquet_dest.file_tuple_count = 0;
+    parquet_dest.finished_file_count = 0;
+    parquet_dest.finished_file_uris = Box::into_raw(Box::new(Vec::new()));
+    parquet_dest.split_file_suffix = Box::into_raw(Box::new(String::new()));
+    parquet_dest.finished_file_bytes = 0;
+    parquet_dest.pending_uploads = Box::into_raw(Box::new(VecDeque::new()));
     parquet_dest.per_copy_context = per_copy_context;
+    parquet_dest.per_conversion_context = per_conversion_context;
+    parquet_dest.export_size_warned = false;
+    parquet_dest.nulled_non_finite_values = 0;
 
     unsafe { std::mem::transmute(parquet_dest) }
 }
 
+// create_copy_to_parquet_dest_receiver_with_stream creates a new CopyToParquetDestReceiver,
+// which passes the bytes of the parquet file to the callbacks of the stream, instead of writing
+// the file to a uri, e.g. for an extension which sends the file to its client. The stream must
+// be valid until the dest receiver is shut down. The options are optional as in
+// create_copy_to_parquet_dest_receiver_with_options, except for max_rows_per_file, which
+// requires a uri.
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn create_copy_to_parquet_dest_receiver_with_stream(
+    stream: *const CopyToParquetStream,
+    options: *const CopyToParquetDestReceiverOptions,
+) -> *mut DestReceiver {
+    if stream.is_null() {
+        panic!("stream is not set");
+    }
+
+    let options = CopyToParquetDestReceiverOptions::from_ptr(options);
+
+    if !options.max_rows_per_file.is_null() && unsafe { *options.max_rows_per_file } > 0 {
+        panic!("max_rows_per_file is not supported when writing to a stream");
+    }
+
+    create_parquet_dest_receiver(std::ptr::null(), stream, &options)
+}
+
+// split_file_uri returns the uri of the file with the given index, which the tuples are written
+// to with max_rows_per_file, e.g. <uri>/part-00000.parquet.
+fn split_file_uri(uri: &Url, file_idx: i64) -> Url {
+    let mut file_uri = uri.clone();
+    file_uri.set_path(&format!(
+        "{}/part-{:05}.parquet",
+        uri.path().trim_end_matches('/'),
+        file_idx
+    ));
+
+    file_uri
+}
+
+// multidim_array_ndims returns the number of dimensions of the array columns, which have
+// multidimensional arrays in the given tuples.
+fn multidim_array_ndims(
+    tuples: &[Option<PgHeapTuple<AllocatedByRust>>],
+    tupledesc: &PgTupleDesc,
+) -> HashMap<String, usize> {
+    let mut array_ndims = HashMap::new();
+
+    for (attribute_idx, attribute) in tupledesc.iter().enumerate() {
+        if attribute.is_dropped() || !is_array_type(attribute.type_oid().value()) {
+            continue;
+        }
+
+        let attnum = NonZeroUsize::new(attribute_idx + 1).expect("invalid attnum");
+
+        let ndims = tuples
+            .iter()
+            .flatten()
+            .filter_map(|tuple| {
+                tuple
+                    .get_by_index::<ArrayDims>(attnum)
+                    .unwrap_or_else(|e| panic!("failed to get attribute: {}", e))
+            })
+            .map(|array_dims| array_dims.ndims())
+            .max()
+            .unwrap_or(0);
+
+        if ndims > 1 {
+            array_ndims.insert(attribute.name().to_string(), ndims);
+        }
+    }
+
+    array_ndims
+}
+
 fn tuple_column_sizes(tuple_datums: &[Option<Datum>], tupledesc: &PgTupleDesc) -> Vec<i32> {
     let mut column_sizes = vec![];
 
@@ -337,27 +1528,28 @@ fn tuple_column_sizes(tuple_datums: &[Option<Datum>], tupledesc: &PgTupleDesc) -
 
         let attribute = tupledesc.get(idx).expect("cannot get attribute");
 
-        let typoid = attribute.type_oid();
-
-        let mut typlen = -1_i16;
-        let mut typbyval = false;
-        unsafe { get_typlenbyval(typoid.value(), &mut typlen, &mut typbyval) };
-
-        let column_size = if typlen == -1 {
-            (unsafe { toast_raw_datum_size(*column_datum) }) as i32 - VARHDRSZ as i32
-        } else if typlen == -2 {
-            // cstring
-            let cstring = unsafe {
-                CString::from_datum(*column_datum, false).expect("cannot get cstring from datum")
-            };
-            cstring.as_bytes().len() as i32 + 1
-        } else {
-            // fixed size type
-            typlen as i32
-        };
+        let column_size = datum_size(*column_datum, attribute.type_oid().value());
 
         column_sizes.push(column_size);
     }
 
     column_sizes
 }
+
+fn datum_size(datum: Datum, typoid: Oid) -> i32 {
+    let mut typlen = -1_i16;
+    let mut typbyval = false;
+    unsafe { get_typlenbyval(typoid, &mut typlen, &mut typbyval) };
+
+    if typlen == -1 {
+        (unsafe { toast_raw_datum_size(datum) }) as i32 - VARHDRSZ as i32
+    } else if typlen == -2 {
+        // cstring
+        let cstring =
+            unsafe { CString::from_datum(datum, false).expect("cannot get cstring from datum") };
+        cstring.as_bytes().len() as i32 + 1
+    } else {
+        // fixed size type
+        typlen as i32
+    }
+}
@@ -1,13 +1,18 @@
-use std::{ffi::CStr, str::FromStr};
+use std::{collections::HashMap, ffi::CStr, str::FromStr};
 
+use parquet::file::properties::{
+    DEFAULT_BLOOM_FILTER_FPP, DEFAULT_DATA_PAGE_ROW_COUNT_LIMIT, DEFAULT_DICTIONARY_ENABLED,
+    DEFAULT_DICTIONARY_PAGE_SIZE_LIMIT, DEFAULT_PAGE_SIZE, DEFAULT_WRITE_BATCH_SIZE,
+};
 use pgrx::{
     ereport, is_a,
     pg_sys::{
-        addRangeTableEntryForRelation, defGetInt32, defGetInt64, defGetString, get_namespace_name,
-        get_rel_namespace, makeDefElem, makeString, make_parsestate, quote_qualified_identifier,
-        AccessShareLock, AsPgCStr, CopyStmt, CreateTemplateTupleDesc, DefElem, List, NoLock, Node,
-        NodeTag::T_CopyStmt, Oid, ParseNamespaceItem, ParseState, PlannedStmt, QueryEnvironment,
-        RangeVar, RangeVarGetRelidExtended, RowExclusiveLock, TupleDescInitEntry,
+        addRangeTableEntryForRelation, defGetBoolean, defGetInt32, defGetInt64, defGetNumeric,
+        defGetString, get_namespace_name, get_rel_namespace, makeDefElem, makeString,
+        make_parsestate, quote_qualified_identifier, AccessShareLock, AsPgCStr, CopyStmt,
+        CreateTemplateTupleDesc, DefElem, List, NoLock, Node, NodeTag::T_CopyStmt, Oid,
+        ParseNamespaceItem, ParseState, PlannedStmt, QueryEnvironment, RangeVar,
+        RangeVarGetRelidExtended, RowExclusiveLock, TupleDescInitEntry,
     },
     PgBox, PgList, PgLogLevel, PgRelation, PgSqlErrorCode, PgTupleDesc,
 };
@@ -15,15 +20,47 @@ use url::Url;
 
 use crate::{
     arrow_parquet::{
-        compression::{all_supported_compressions, PgParquetCompression},
+        compression::{
+            all_supported_compressions, default_compression, default_compression_level,
+            PgParquetCompression, COMPRESSION_PRECEDENCE,
+        },
+        duplicate_columns::{all_supported_duplicate_columns, PgParquetDuplicateColumns},
+        extra_columns::ExtraColumns,
+        field_ids::{attribute_field_id, parse_field_ids},
+        if_exists::PgParquetIfExists,
+        infinite_timestamps::{all_supported_infinite_timestamps, PgParquetInfiniteTimestamps},
+        list_coercion::ListCoercion,
         match_by::MatchBy,
+        missing_columns::MissingColumns,
+        multidim_arrays::{all_supported_multidim_arrays, PgParquetMultidimArrays},
+        nan_handling::{all_supported_nan_handlings, PgParquetNanHandling},
+        numeric_overflow::{all_supported_numeric_overflows, PgParquetNumericOverflow},
+        on_error::CopyFromOnError,
         parquet_writer::{DEFAULT_ROW_GROUP_SIZE, DEFAULT_ROW_GROUP_SIZE_BYTES},
-        uri_utils::parse_uri,
+        row_group_selection::{ResumeToken, RowGroupSelection},
+        statistics::{all_supported_statistics, PgParquetStatistics},
+        timestamptz_to_timestamp::TimestamptzToTimestamp,
+        unsupported_type::{all_supported_unsupported_types, PgParquetUnsupportedType},
+        uri_utils::{parse_uri, upload_buffer_size, MAX_UPLOAD_BUFFER_SIZE},
+        writer_version::{all_supported_writer_versions, PgParquetWriterVersion},
+    },
+    pgrx_utils::{
+        collect_attributes_for, extension_exists, is_generated_attribute, CollectAttributesFor,
+    },
+    type_compat::{
+        geometry_crs::GeometrySridMismatch,
+        pg_arrow_type_conversions::{
+            DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+            MAX_NUMERIC_PRECISION,
+        },
     },
-    pgrx_utils::extension_exists,
 };
 
-use super::{hook::ENABLE_PARQUET_COPY_HOOK, pg_compat::strVal};
+use super::{
+    copy_to_dest_receiver::{BATCH_MEMORY_LIMIT, UPLOAD_CONCURRENCY},
+    hook::ENABLE_PARQUET_COPY_HOOK,
+    pg_compat::strVal,
+};
 
 pub(crate) fn validate_copy_to_options(p_stmt: &PgBox<PlannedStmt>, uri: &Url) {
     validate_copy_option_names(
@@ -34,6 +71,37 @@ pub(crate) fn validate_copy_to_options(p_stmt: &PgBox<PlannedStmt>, uri: &Url) {
             "row_group_size_bytes",
             "compression",
             "compression_level",
+            "statistics",
+            "bloom_filter_columns",
+            "bloom_filter_fpp",
+            "dictionary_enabled",
+            "dictionary_columns",
+            "no_dictionary_columns",
+            "byte_stream_split_columns",
+            "writer_version",
+            "dictionary_page_size_limit",
+            "data_page_size_bytes",
+            "data_page_row_count_limit",
+            "write_batch_size",
+            "max_buffered_bytes",
+            "batch_memory_limit",
+            "kv_metadata",
+            "geoparquet_covering",
+            "allow_mixed_srids",
+            "nan_handling",
+            "infinite_timestamps",
+            "unsupported_type",
+            "numeric_precision",
+            "numeric_scale",
+            "numeric_overflow",
+            "multidim_arrays",
+            "duplicate_columns",
+            "one_file_per_partition",
+            "max_rows_per_file",
+            "if_exists",
+            "exclude_columns",
+            "include_generated",
+            "temp",
             "freeze",
         ],
     );
@@ -77,161 +145,1216 @@ pub(crate) fn validate_copy_to_options(p_stmt: &PgBox<PlannedStmt>, uri: &Url) {
         }
     }
 
+    let max_buffered_bytes_option = copy_stmt_get_option(p_stmt, "max_buffered_bytes");
+
+    if !max_buffered_bytes_option.is_null() {
+        let max_buffered_bytes = unsafe { defGetInt64(max_buffered_bytes_option.as_ptr()) };
+
+        if max_buffered_bytes <= 0 {
+            panic!("max_buffered_bytes must be greater than 0");
+        }
+    }
+
+    let batch_memory_limit_option = copy_stmt_get_option(p_stmt, "batch_memory_limit");
+
+    if !batch_memory_limit_option.is_null() {
+        let batch_memory_limit = unsafe { defGetInt64(batch_memory_limit_option.as_ptr()) };
+
+        if batch_memory_limit <= 0 {
+            panic!("batch_memory_limit must be greater than 0");
+        }
+    }
+
+    let max_rows_per_file_option = copy_stmt_get_option(p_stmt, "max_rows_per_file");
+
+    if !max_rows_per_file_option.is_null() {
+        let max_rows_per_file = unsafe { defGetInt64(max_rows_per_file_option.as_ptr()) };
+
+        if max_rows_per_file <= 0 {
+            panic!("max_rows_per_file must be greater than 0");
+        }
+
+        // each of the files, which are uploaded at a time, buffers its parts in flight
+        let upload_concurrency = UPLOAD_CONCURRENCY.get() as i64;
+        let upload_buffer_size = upload_buffer_size();
+
+        if upload_concurrency * upload_buffer_size > MAX_UPLOAD_BUFFER_SIZE {
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!(
+                    "pg_parquet.upload_concurrency ({}) * upload buffer of a file ({}MB) must not exceed 5GB",
+                    upload_concurrency,
+                    upload_buffer_size / 1024
+                ),
+                "The upload buffer of a file is pg_parquet.upload_part_concurrency * pg_parquet.upload_part_size. Lower any of them.",
+            );
+        }
+    }
+
     let compression_option = copy_stmt_get_option(p_stmt, "compression");
 
     if !compression_option.is_null() {
         let compression = unsafe { defGetString(compression_option.as_ptr()) };
 
-        let compression = unsafe {
-            CStr::from_ptr(compression)
-                .to_str()
-                .expect("compression option is not a valid CString")
-        };
+        let compression = unsafe {
+            CStr::from_ptr(compression)
+                .to_str()
+                .expect("compression option is not a valid CString")
+        };
+
+        if PgParquetCompression::from_str(compression).is_err() {
+            panic!(
+                "{} is not a valid compression format. Supported compression formats are {}",
+                compression,
+                all_supported_compressions()
+                    .into_iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let compression = copy_to_stmt_compression(p_stmt, uri.clone());
+
+    if let Some(compression_level) = copy_to_stmt_compression_level(p_stmt, uri.clone()) {
+        if let Err(e) = compression.check_compression_level(compression_level) {
+            panic!("{}. {}.", e, COMPRESSION_PRECEDENCE);
+        }
+    }
+
+    let statistics_option = copy_stmt_get_option(p_stmt, "statistics");
+
+    if !statistics_option.is_null() {
+        let statistics = unsafe { defGetString(statistics_option.as_ptr()) };
+
+        let statistics = unsafe {
+            CStr::from_ptr(statistics)
+                .to_str()
+                .expect("statistics option is not a valid CString")
+        };
+
+        if PgParquetStatistics::from_str(statistics).is_err() {
+            panic!(
+                "{} is not a valid statistics level. Supported statistics levels are {}",
+                statistics,
+                all_supported_statistics()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let nan_handling_option = copy_stmt_get_option(p_stmt, "nan_handling");
+
+    if !nan_handling_option.is_null() {
+        let nan_handling = unsafe { defGetString(nan_handling_option.as_ptr()) };
+
+        let nan_handling = unsafe {
+            CStr::from_ptr(nan_handling)
+                .to_str()
+                .expect("nan_handling option is not a valid CString")
+        };
+
+        if PgParquetNanHandling::from_str(nan_handling).is_err() {
+            panic!(
+                "{} is not a valid nan_handling. Supported nan_handlings are {}",
+                nan_handling,
+                all_supported_nan_handlings()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let infinite_timestamps_option = copy_stmt_get_option(p_stmt, "infinite_timestamps");
+
+    if !infinite_timestamps_option.is_null() {
+        let infinite_timestamps = unsafe { defGetString(infinite_timestamps_option.as_ptr()) };
+
+        let infinite_timestamps = unsafe {
+            CStr::from_ptr(infinite_timestamps)
+                .to_str()
+                .expect("infinite_timestamps option is not a valid CString")
+        };
+
+        if PgParquetInfiniteTimestamps::from_str(infinite_timestamps).is_err() {
+            panic!(
+                "{} is not a valid infinite_timestamps. Supported infinite_timestamps are {}",
+                infinite_timestamps,
+                all_supported_infinite_timestamps()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let unsupported_type_option = copy_stmt_get_option(p_stmt, "unsupported_type");
+
+    if !unsupported_type_option.is_null() {
+        let unsupported_type = unsafe { defGetString(unsupported_type_option.as_ptr()) };
+
+        let unsupported_type = unsafe {
+            CStr::from_ptr(unsupported_type)
+                .to_str()
+                .expect("unsupported_type option is not a valid CString")
+        };
+
+        if PgParquetUnsupportedType::from_str(unsupported_type).is_err() {
+            panic!(
+                "{} is not a valid unsupported_type. Supported values are {}",
+                unsupported_type,
+                all_supported_unsupported_types()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let numeric_precision = copy_to_stmt_numeric_precision(p_stmt);
+
+    if numeric_precision < 1 || numeric_precision > MAX_NUMERIC_PRECISION as i32 {
+        panic!(
+            "numeric_precision must be between 1 and {}",
+            MAX_NUMERIC_PRECISION
+        );
+    }
+
+    let numeric_scale = copy_to_stmt_numeric_scale(p_stmt);
+
+    if numeric_scale < 0 || numeric_scale > numeric_precision {
+        panic!(
+            "numeric_scale must be between 0 and numeric_precision {}",
+            numeric_precision
+        );
+    }
+
+    let numeric_overflow_option = copy_stmt_get_option(p_stmt, "numeric_overflow");
+
+    if !numeric_overflow_option.is_null() {
+        let numeric_overflow = unsafe { defGetString(numeric_overflow_option.as_ptr()) };
+
+        let numeric_overflow = unsafe {
+            CStr::from_ptr(numeric_overflow)
+                .to_str()
+                .expect("numeric_overflow option is not a valid CString")
+        };
+
+        if PgParquetNumericOverflow::from_str(numeric_overflow).is_err() {
+            panic!(
+                "{} is not a valid numeric_overflow. Supported values are {}",
+                numeric_overflow,
+                all_supported_numeric_overflows()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let multidim_arrays_option = copy_stmt_get_option(p_stmt, "multidim_arrays");
+
+    if !multidim_arrays_option.is_null() {
+        let multidim_arrays = unsafe { defGetString(multidim_arrays_option.as_ptr()) };
+
+        let multidim_arrays = unsafe {
+            CStr::from_ptr(multidim_arrays)
+                .to_str()
+                .expect("multidim_arrays option is not a valid CString")
+        };
+
+        if PgParquetMultidimArrays::from_str(multidim_arrays).is_err() {
+            panic!(
+                "{} is not a valid multidim_arrays. Supported values are {}",
+                multidim_arrays,
+                all_supported_multidim_arrays()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let duplicate_columns_option = copy_stmt_get_option(p_stmt, "duplicate_columns");
+
+    if !duplicate_columns_option.is_null() {
+        let duplicate_columns = unsafe { defGetString(duplicate_columns_option.as_ptr()) };
+
+        let duplicate_columns = unsafe {
+            CStr::from_ptr(duplicate_columns)
+                .to_str()
+                .expect("duplicate_columns option is not a valid CString")
+        };
+
+        if PgParquetDuplicateColumns::from_str(duplicate_columns).is_err() {
+            panic!(
+                "{} is not a valid duplicate_columns. Supported values are {}",
+                duplicate_columns,
+                all_supported_duplicate_columns()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let bloom_filter_columns_option = copy_stmt_get_option(p_stmt, "bloom_filter_columns");
+
+    if !bloom_filter_columns_option.is_null() {
+        let bloom_filter_columns = copy_to_stmt_bloom_filter_columns(p_stmt)
+            .expect("bloom_filter_columns option is not found");
+
+        if bloom_filter_columns
+            .split(',')
+            .any(|column| column.trim().is_empty())
+        {
+            panic!("bloom_filter_columns must be a comma separated list of column names");
+        }
+    }
+
+    let bloom_filter_fpp_option = copy_stmt_get_option(p_stmt, "bloom_filter_fpp");
+
+    if !bloom_filter_fpp_option.is_null() {
+        if bloom_filter_columns_option.is_null() {
+            panic!("bloom_filter_fpp requires bloom_filter_columns option");
+        }
+
+        let bloom_filter_fpp = unsafe { defGetNumeric(bloom_filter_fpp_option.as_ptr()) };
+
+        if bloom_filter_fpp <= 0.0 || bloom_filter_fpp >= 1.0 {
+            panic!("bloom_filter_fpp must be between 0 and 1 (exclusive)");
+        }
+    }
+
+    let dictionary_columns = copy_to_stmt_dictionary_columns(p_stmt);
+    let no_dictionary_columns = copy_to_stmt_no_dictionary_columns(p_stmt);
+    let byte_stream_split_columns = copy_to_stmt_byte_stream_split_columns(p_stmt);
+
+    for (option_name, columns) in [
+        ("dictionary_columns", &dictionary_columns),
+        ("no_dictionary_columns", &no_dictionary_columns),
+        ("byte_stream_split_columns", &byte_stream_split_columns),
+    ] {
+        if let Some(columns) = columns {
+            if columns.split(',').any(|column| column.trim().is_empty()) {
+                panic!(
+                    "{} must be a comma separated list of column names",
+                    option_name
+                );
+            }
+        }
+    }
+
+    if let (Some(dictionary_columns), Some(no_dictionary_columns)) =
+        (&dictionary_columns, &no_dictionary_columns)
+    {
+        for column in dictionary_columns.split(',').map(str::trim) {
+            if no_dictionary_columns
+                .split(',')
+                .any(|no_dictionary_column| no_dictionary_column.trim() == column)
+            {
+                panic!(
+                    "column \"{}\" cannot be in both dictionary_columns and no_dictionary_columns",
+                    column
+                );
+            }
+        }
+    }
+
+    // byte stream split columns are not dictionary encoded
+    if let (Some(dictionary_columns), Some(byte_stream_split_columns)) =
+        (&dictionary_columns, &byte_stream_split_columns)
+    {
+        for column in dictionary_columns.split(',').map(str::trim) {
+            if byte_stream_split_columns
+                .split(',')
+                .any(|byte_stream_split_column| byte_stream_split_column.trim() == column)
+            {
+                panic!(
+                    "column \"{}\" cannot be in both dictionary_columns and byte_stream_split_columns",
+                    column
+                );
+            }
+        }
+    }
+
+    let writer_version_option = copy_stmt_get_option(p_stmt, "writer_version");
+
+    if !writer_version_option.is_null() {
+        let writer_version = unsafe { defGetString(writer_version_option.as_ptr()) };
+
+        let writer_version = unsafe {
+            CStr::from_ptr(writer_version)
+                .to_str()
+                .expect("writer_version option is not a valid CString")
+        };
+
+        if PgParquetWriterVersion::from_str(writer_version).is_err() {
+            panic!(
+                "{} is not a valid writer_version. Supported writer versions are {}",
+                writer_version,
+                all_supported_writer_versions()
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    let dictionary_page_size_limit_option =
+        copy_stmt_get_option(p_stmt, "dictionary_page_size_limit");
+
+    if !dictionary_page_size_limit_option.is_null() {
+        let dictionary_page_size_limit =
+            unsafe { defGetInt64(dictionary_page_size_limit_option.as_ptr()) };
+
+        if dictionary_page_size_limit <= 0 {
+            panic!("dictionary_page_size_limit must be greater than 0");
+        }
+    }
+
+    let data_page_size_bytes_option = copy_stmt_get_option(p_stmt, "data_page_size_bytes");
+
+    if !data_page_size_bytes_option.is_null() {
+        let data_page_size_bytes = unsafe { defGetInt64(data_page_size_bytes_option.as_ptr()) };
+
+        if data_page_size_bytes <= 0 {
+            panic!("data_page_size_bytes must be greater than 0");
+        }
+    }
+
+    let data_page_row_count_limit_option =
+        copy_stmt_get_option(p_stmt, "data_page_row_count_limit");
+
+    if !data_page_row_count_limit_option.is_null() {
+        let data_page_row_count_limit =
+            unsafe { defGetInt64(data_page_row_count_limit_option.as_ptr()) };
+
+        if data_page_row_count_limit <= 0 {
+            panic!("data_page_row_count_limit must be greater than 0");
+        }
+    }
+
+    let write_batch_size_option = copy_stmt_get_option(p_stmt, "write_batch_size");
+
+    if !write_batch_size_option.is_null() {
+        let write_batch_size = unsafe { defGetInt64(write_batch_size_option.as_ptr()) };
+
+        if write_batch_size <= 0 {
+            panic!("write_batch_size must be greater than 0");
+        }
+    }
+
+    if let Some(kv_metadata) = copy_to_stmt_kv_metadata(p_stmt) {
+        parse_kv_metadata(&kv_metadata).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    // temp files are addressed by name, which cannot be a directory of partition files
+    if copy_to_stmt_one_file_per_partition(p_stmt) && copy_stmt_is_temp(p_stmt) {
+        panic!("one_file_per_partition is not supported for temp files");
+    }
+
+    if copy_to_stmt_one_file_per_partition(p_stmt) && copy_stmt_program(p_stmt).is_some() {
+        panic!("one_file_per_partition is not supported for COPY TO PROGRAM");
+    }
+
+    // max_rows_per_file writes the files into the directory at the uri
+    if copy_to_stmt_max_rows_per_file(p_stmt).is_some() {
+        if copy_to_stmt_one_file_per_partition(p_stmt) {
+            panic!("max_rows_per_file is not supported with one_file_per_partition");
+        }
+
+        if copy_stmt_is_temp(p_stmt) {
+            panic!("max_rows_per_file is not supported for temp files");
+        }
+
+        if copy_stmt_program(p_stmt).is_some() {
+            panic!("max_rows_per_file is not supported for COPY TO PROGRAM");
+        }
+    }
+
+    if copy_to_stmt_excludes_columns(p_stmt) {
+        ensure_exclude_columns_exist(p_stmt);
+    }
+
+    // temp files and the files of COPY TO PROGRAM are created by the backend
+    if !copy_stmt_get_option(p_stmt, "if_exists").is_null() {
+        if copy_stmt_is_temp(p_stmt) {
+            panic!("if_exists is not supported for temp files");
+        }
+
+        if copy_stmt_program(p_stmt).is_some() {
+            panic!("if_exists is not supported for COPY TO PROGRAM");
+        }
+    }
+
+    ensure_no_temp_option_for_program(p_stmt);
+}
+
+pub(crate) fn validate_copy_from_options(p_stmt: &PgBox<PlannedStmt>) {
+    validate_copy_option_names(
+        p_stmt,
+        &[
+            "format",
+            "match_by",
+            "field_ids",
+            "missing_columns",
+            "allow_extra_columns",
+            "extra_columns",
+            "resume_from",
+            "max_row_groups",
+            "geometry_srid_mismatch",
+            "timestamptz_to_timestamp",
+            "infinite_timestamps",
+            "list_coercion",
+            "on_error",
+            "temp",
+            "freeze",
+            "truncate",
+            "restart_identity",
+            "cascade",
+        ],
+    );
+
+    let format_option = copy_stmt_get_option(p_stmt, "format");
+
+    if !format_option.is_null() {
+        let format = unsafe { defGetString(format_option.as_ptr()) };
+
+        let format = unsafe {
+            CStr::from_ptr(format)
+                .to_str()
+                .expect("format option is not a valid CString")
+        };
+
+        if format != "parquet" {
+            panic!(
+                "{} is not a valid format. Only parquet format is supported.",
+                format
+            );
+        }
+    }
+
+    let max_row_groups_option = copy_stmt_get_option(p_stmt, "max_row_groups");
+
+    if !max_row_groups_option.is_null() {
+        let max_row_groups = unsafe { defGetInt64(max_row_groups_option.as_ptr()) };
+
+        if max_row_groups <= 0 {
+            panic!("max_row_groups must be greater than 0");
+        }
+    }
+
+    if copy_from_stmt_field_ids(p_stmt).is_some()
+        && copy_from_stmt_match_by(p_stmt) != MatchBy::FieldId
+    {
+        panic!("field_ids option requires match_by 'field_id'");
+    }
+
+    // only the sentinel values of infinities are read from the file
+    let infinite_timestamps = copy_stmt_infinite_timestamps(p_stmt);
+
+    if infinite_timestamps != PgParquetInfiniteTimestamps::Error
+        && infinite_timestamps != PgParquetInfiniteTimestamps::Sentinel
+    {
+        panic!(
+            "infinite_timestamps '{}' is not supported by COPY FROM. Supported infinite_timestamps are error, sentinel",
+            infinite_timestamps
+        );
+    }
+
+    // restart_identity and cascade are the options of the truncation
+    if !copy_from_stmt_truncate(p_stmt) {
+        if !copy_stmt_get_option(p_stmt, "restart_identity").is_null() {
+            panic!("restart_identity option requires truncate true");
+        }
+
+        if !copy_stmt_get_option(p_stmt, "cascade").is_null() {
+            panic!("cascade option requires truncate true");
+        }
+    }
+
+    ensure_no_temp_option_for_program(p_stmt);
+}
+
+// ensure_no_temp_option_for_program throws an error if a COPY PROGRAM statement has the temp
+// option, since its program is not the name of a temp file.
+fn ensure_no_temp_option_for_program(p_stmt: &PgBox<PlannedStmt>) {
+    if copy_stmt_program(p_stmt).is_some() && copy_stmt_is_temp(p_stmt) {
+        panic!("temp option is not supported for COPY PROGRAM");
+    }
+}
+
+fn validate_copy_option_names(p_stmt: &PgBox<PlannedStmt>, allowed_options: &[&str]) {
+    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+
+    let copy_options = unsafe { PgList::<DefElem>::from_pg(copy_stmt.options) };
+
+    let copy_from_str = if copy_stmt.is_from { "from" } else { "to" };
+
+    for option in copy_options.iter_ptr() {
+        let option = unsafe { PgBox::<DefElem>::from_pg(option) };
+
+        let option_name = unsafe {
+            CStr::from_ptr(option.defname)
+                .to_str()
+                .expect("option name is not a valid CString")
+        };
+
+        if !allowed_options.contains(&option_name) {
+            panic!(
+                "{} is not a valid option for \"copy {} parquet\". Supported options are {}",
+                option_name,
+                copy_from_str,
+                allowed_options.join(", ")
+            );
+        }
+    }
+}
+
+pub(crate) fn copy_stmt_uri(p_stmt: &PgBox<PlannedStmt>) -> Option<Url> {
+    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+
+    if copy_stmt.is_program {
+        return None;
+    }
+
+    if copy_stmt.filename.is_null() {
+        return None;
+    }
+
+    let uri = unsafe {
+        CStr::from_ptr(copy_stmt.filename)
+            .to_str()
+            .expect("uri option is not a valid CString")
+    };
+
+    if uri.trim().is_empty() {
+        if copy_stmt.is_from {
+            panic!("source uri is empty");
+        } else {
+            panic!("destination uri is empty");
+        }
+    }
+
+    Some(parse_uri(uri))
+}
+
+// copy_stmt_program returns the command of COPY FROM PROGRAM or COPY TO PROGRAM.
+pub(crate) fn copy_stmt_program(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+
+    if !copy_stmt.is_program {
+        return None;
+    }
+
+    let program = unsafe {
+        CStr::from_ptr(copy_stmt.filename)
+            .to_str()
+            .expect("program is not a valid CString")
+    };
+
+    Some(program.to_string())
+}
+
+// copy_stmt_is_temp returns true if the COPY statement addresses a temp file of the session
+// by its name, instead of a uri.
+pub(crate) fn copy_stmt_is_temp(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let temp_option = copy_stmt_get_option(p_stmt, "temp");
+
+    if temp_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(temp_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_stmt_temp_file_name(p_stmt: &PgBox<PlannedStmt>) -> String {
+    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+
+    let temp_file_name = unsafe {
+        CStr::from_ptr(copy_stmt.filename)
+            .to_str()
+            .expect("temp file name is not a valid CString")
+    };
+
+    temp_file_name.to_string()
+}
+
+pub(crate) fn copy_to_stmt_row_group_size(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let row_group_size_option = copy_stmt_get_option(p_stmt, "row_group_size");
+
+    if row_group_size_option.is_null() {
+        DEFAULT_ROW_GROUP_SIZE
+    } else {
+        unsafe { defGetInt64(row_group_size_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_to_stmt_row_group_size_bytes(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let row_group_size_bytes_option = copy_stmt_get_option(p_stmt, "row_group_size_bytes");
+
+    if row_group_size_bytes_option.is_null() {
+        DEFAULT_ROW_GROUP_SIZE_BYTES
+    } else {
+        unsafe { defGetInt64(row_group_size_bytes_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_max_buffered_bytes returns the max size of the tuples, which are buffered before
+// they are written to the in progress row group. Defaults to the row group size in bytes.
+pub(crate) fn copy_to_stmt_max_buffered_bytes(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let max_buffered_bytes_option = copy_stmt_get_option(p_stmt, "max_buffered_bytes");
+
+    if max_buffered_bytes_option.is_null() {
+        copy_to_stmt_row_group_size_bytes(p_stmt)
+    } else {
+        unsafe { defGetInt64(max_buffered_bytes_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_batch_memory_limit returns the size of the rows, which are held in memory before
+// the row group is finished. Defaults to pg_parquet.batch_memory_limit.
+pub(crate) fn copy_to_stmt_batch_memory_limit(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let batch_memory_limit_option = copy_stmt_get_option(p_stmt, "batch_memory_limit");
+
+    if batch_memory_limit_option.is_null() {
+        BATCH_MEMORY_LIMIT.get() as i64 * 1024
+    } else {
+        unsafe { defGetInt64(batch_memory_limit_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_compression returns the compression from the compression option, or from the
+// file extension, or pg_parquet.default_compression, in that order. A plain ".parquet"
+// extension does not specify a compression.
+pub(crate) fn copy_to_stmt_compression(
+    p_stmt: &PgBox<PlannedStmt>,
+    uri: Url,
+) -> PgParquetCompression {
+    let compression_option = copy_stmt_get_option(p_stmt, "compression");
+
+    if compression_option.is_null() {
+        if uri.path().ends_with(".parquet") {
+            return default_compression();
+        }
+
+        PgParquetCompression::try_from(uri).unwrap_or_else(|_| default_compression())
+    } else {
+        let compression = unsafe { defGetString(compression_option.as_ptr()) };
+
+        let compression = unsafe {
+            CStr::from_ptr(compression)
+                .to_str()
+                .expect("compression option is not a valid CString")
+        };
+
+        PgParquetCompression::from_str(compression).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// copy_to_stmt_compression_level returns the compression_level option. Otherwise, it returns
+// pg_parquet.default_compression_level if COPY TO uses the default compression, or the
+// compression's own default level.
+pub(crate) fn copy_to_stmt_compression_level(p_stmt: &PgBox<PlannedStmt>, uri: Url) -> Option<i32> {
+    let compression_level_option = copy_stmt_get_option(p_stmt, "compression_level");
+
+    if compression_level_option.is_null() {
+        let compression = copy_to_stmt_compression(p_stmt, uri);
+
+        if compression == default_compression() {
+            default_compression_level()
+        } else {
+            compression.default_compression_level()
+        }
+    } else {
+        Some(unsafe { defGetInt32(compression_level_option.as_ptr()) as _ })
+    }
+}
+
+pub(crate) fn copy_to_stmt_statistics(p_stmt: &PgBox<PlannedStmt>) -> PgParquetStatistics {
+    let statistics_option = copy_stmt_get_option(p_stmt, "statistics");
+
+    if statistics_option.is_null() {
+        PgParquetStatistics::default()
+    } else {
+        let statistics = unsafe { defGetString(statistics_option.as_ptr()) };
+
+        let statistics = unsafe {
+            CStr::from_ptr(statistics)
+                .to_str()
+                .expect("statistics option is not a valid CString")
+        };
+
+        PgParquetStatistics::from_str(statistics).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// copy_to_stmt_bloom_filter_columns returns the comma separated list of columns,
+// for which bloom filters are written, as is.
+pub(crate) fn copy_to_stmt_bloom_filter_columns(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    let bloom_filter_columns_option = copy_stmt_get_option(p_stmt, "bloom_filter_columns");
+
+    if bloom_filter_columns_option.is_null() {
+        None
+    } else {
+        let bloom_filter_columns = unsafe { defGetString(bloom_filter_columns_option.as_ptr()) };
+
+        let bloom_filter_columns = unsafe {
+            CStr::from_ptr(bloom_filter_columns)
+                .to_str()
+                .expect("bloom_filter_columns option is not a valid CString")
+        };
+
+        Some(bloom_filter_columns.to_string())
+    }
+}
+
+// copy_to_stmt_kv_metadata returns the json object of the custom key/value metadata,
+// which is written into the footer of the file, as is.
+pub(crate) fn copy_to_stmt_dictionary_enabled(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let dictionary_enabled_option = copy_stmt_get_option(p_stmt, "dictionary_enabled");
+
+    if dictionary_enabled_option.is_null() {
+        DEFAULT_DICTIONARY_ENABLED
+    } else {
+        unsafe { defGetBoolean(dictionary_enabled_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_dictionary_columns returns the comma separated list of columns, which are
+// dictionary encoded regardless of dictionary_enabled option.
+pub(crate) fn copy_to_stmt_dictionary_columns(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    copy_to_stmt_column_list(p_stmt, "dictionary_columns")
+}
+
+// copy_to_stmt_no_dictionary_columns returns the comma separated list of columns, which are
+// not dictionary encoded regardless of dictionary_enabled option.
+pub(crate) fn copy_to_stmt_no_dictionary_columns(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    copy_to_stmt_column_list(p_stmt, "no_dictionary_columns")
+}
+
+// copy_to_stmt_byte_stream_split_columns returns the comma separated list of columns, which are
+// written with BYTE_STREAM_SPLIT encoding.
+pub(crate) fn copy_to_stmt_byte_stream_split_columns(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> Option<String> {
+    copy_to_stmt_column_list(p_stmt, "byte_stream_split_columns")
+}
+
+pub(crate) fn copy_to_stmt_writer_version(p_stmt: &PgBox<PlannedStmt>) -> PgParquetWriterVersion {
+    let writer_version_option = copy_stmt_get_option(p_stmt, "writer_version");
+
+    if writer_version_option.is_null() {
+        PgParquetWriterVersion::default()
+    } else {
+        let writer_version = unsafe { defGetString(writer_version_option.as_ptr()) };
+
+        let writer_version = unsafe {
+            CStr::from_ptr(writer_version)
+                .to_str()
+                .expect("writer_version option is not a valid CString")
+        };
+
+        PgParquetWriterVersion::from_str(writer_version).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+fn copy_to_stmt_column_list(p_stmt: &PgBox<PlannedStmt>, option_name: &str) -> Option<String> {
+    let column_list_option = copy_stmt_get_option(p_stmt, option_name);
+
+    if column_list_option.is_null() {
+        None
+    } else {
+        let column_list = unsafe { defGetString(column_list_option.as_ptr()) };
+
+        let column_list = unsafe {
+            CStr::from_ptr(column_list)
+                .to_str()
+                .unwrap_or_else(|_| panic!("{} option is not a valid CString", option_name))
+        };
+
+        Some(column_list.to_string())
+    }
+}
+
+pub(crate) fn copy_to_stmt_dictionary_page_size_limit(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let dictionary_page_size_limit_option =
+        copy_stmt_get_option(p_stmt, "dictionary_page_size_limit");
+
+    if dictionary_page_size_limit_option.is_null() {
+        DEFAULT_DICTIONARY_PAGE_SIZE_LIMIT as i64
+    } else {
+        unsafe { defGetInt64(dictionary_page_size_limit_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_to_stmt_data_page_size_bytes(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let data_page_size_bytes_option = copy_stmt_get_option(p_stmt, "data_page_size_bytes");
+
+    if data_page_size_bytes_option.is_null() {
+        DEFAULT_PAGE_SIZE as i64
+    } else {
+        unsafe { defGetInt64(data_page_size_bytes_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_to_stmt_data_page_row_count_limit(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let data_page_row_count_limit_option =
+        copy_stmt_get_option(p_stmt, "data_page_row_count_limit");
+
+    if data_page_row_count_limit_option.is_null() {
+        DEFAULT_DATA_PAGE_ROW_COUNT_LIMIT as i64
+    } else {
+        unsafe { defGetInt64(data_page_row_count_limit_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_to_stmt_write_batch_size(p_stmt: &PgBox<PlannedStmt>) -> i64 {
+    let write_batch_size_option = copy_stmt_get_option(p_stmt, "write_batch_size");
+
+    if write_batch_size_option.is_null() {
+        DEFAULT_WRITE_BATCH_SIZE as i64
+    } else {
+        unsafe { defGetInt64(write_batch_size_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_to_stmt_kv_metadata(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    let kv_metadata_option = copy_stmt_get_option(p_stmt, "kv_metadata");
+
+    if kv_metadata_option.is_null() {
+        None
+    } else {
+        let kv_metadata = unsafe { defGetString(kv_metadata_option.as_ptr()) };
+
+        let kv_metadata = unsafe {
+            CStr::from_ptr(kv_metadata)
+                .to_str()
+                .expect("kv_metadata option is not a valid CString")
+        };
+
+        Some(kv_metadata.to_string())
+    }
+}
+
+// RESERVED_KV_METADATA_KEYS are the footer keys, which are written by pg_parquet or
+// the arrow writer and cannot be overridden by kv_metadata option.
+const RESERVED_KV_METADATA_KEYS: &[&str] = &["geo", "ARROW:schema"];
+
+// parse_kv_metadata parses kv_metadata option, a json object with string values,
+// into key/value pairs.
+pub(crate) fn parse_kv_metadata(kv_metadata: &str) -> Result<Vec<(String, String)>, String> {
+    let kv_metadata = match serde_json::from_str::<serde_json::Value>(kv_metadata) {
+        Ok(serde_json::Value::Object(kv_metadata)) => kv_metadata,
+        _ => return Err("kv_metadata must be a json object".into()),
+    };
+
+    let mut key_values = vec![];
+
+    for (key, value) in kv_metadata {
+        if RESERVED_KV_METADATA_KEYS.contains(&key.as_str()) {
+            return Err(format!("kv_metadata key \"{}\" is reserved", key));
+        }
+
+        let serde_json::Value::String(value) = value else {
+            return Err(format!(
+                "kv_metadata value of key \"{}\" must be a string",
+                key
+            ));
+        };
+
+        key_values.push((key, value));
+    }
+
+    Ok(key_values)
+}
+
+pub(crate) fn copy_to_stmt_geoparquet_covering(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let geoparquet_covering_option = copy_stmt_get_option(p_stmt, "geoparquet_covering");
+
+    if geoparquet_covering_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(geoparquet_covering_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_exclude_columns returns the columns of the relation, which COPY TO does not write,
+// e.g. 'updated_at, audit_blob'.
+pub(crate) fn copy_to_stmt_exclude_columns(p_stmt: &PgBox<PlannedStmt>) -> Vec<String> {
+    let exclude_columns_option = copy_stmt_get_option(p_stmt, "exclude_columns");
+
+    if exclude_columns_option.is_null() {
+        return vec![];
+    }
+
+    let exclude_columns = unsafe { defGetString(exclude_columns_option.as_ptr()) };
+
+    let exclude_columns = unsafe {
+        CStr::from_ptr(exclude_columns)
+            .to_str()
+            .expect("exclude_columns option is not a valid CString")
+    };
+
+    exclude_columns
+        .split(',')
+        .map(|column| column.trim().to_string())
+        .filter(|column| !column.is_empty())
+        .collect()
+}
+
+pub(crate) fn copy_to_stmt_include_generated(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let include_generated_option = copy_stmt_get_option(p_stmt, "include_generated");
+
+    if include_generated_option.is_null() {
+        true
+    } else {
+        unsafe { defGetBoolean(include_generated_option.as_ptr()) }
+    }
+}
+
+// copy_to_stmt_excludes_columns returns whether COPY TO skips any columns of the relation per
+// exclude_columns or include_generated options.
+pub(crate) fn copy_to_stmt_excludes_columns(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    !copy_to_stmt_exclude_columns(p_stmt).is_empty() || !copy_to_stmt_include_generated(p_stmt)
+}
+
+// ensure_exclude_columns_exist throws an error if COPY TO does not copy from a table, or if
+// exclude_columns has a column, which the table does not have.
+fn ensure_exclude_columns_exist(p_stmt: &PgBox<PlannedStmt>) {
+    if !copy_stmt_has_relation(p_stmt) {
+        panic!("exclude_columns and include_generated are only supported for COPY TO from a table");
+    }
+
+    let relation = unsafe {
+        PgRelation::with_lock(copy_stmt_relation_oid(p_stmt), copy_stmt_lock_mode(p_stmt))
+    };
 
-        if PgParquetCompression::from_str(compression).is_err() {
+    let tupledesc = relation.tuple_desc();
+
+    for exclude_column in copy_to_stmt_exclude_columns(p_stmt) {
+        if !tupledesc
+            .iter()
+            .any(|attribute| !attribute.is_dropped() && attribute.name() == exclude_column)
+        {
             panic!(
-                "{} is not a valid compression format. Supported compression formats are {}",
-                compression,
-                all_supported_compressions()
-                    .into_iter()
-                    .map(|c| c.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                "column \"{}\" in exclude_columns does not exist in relation \"{}\"",
+                exclude_column,
+                relation.name()
             );
         }
     }
+}
 
-    let compression_level_option = copy_stmt_get_option(p_stmt, "compression_level");
+pub(crate) fn copy_to_stmt_allow_mixed_srids(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let allow_mixed_srids_option = copy_stmt_get_option(p_stmt, "allow_mixed_srids");
 
-    if !compression_level_option.is_null() {
-        let compression_level = unsafe { defGetInt32(compression_level_option.as_ptr()) };
+    if allow_mixed_srids_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(allow_mixed_srids_option.as_ptr()) }
+    }
+}
 
-        let compression = copy_to_stmt_compression(p_stmt, uri.clone());
+pub(crate) fn copy_to_stmt_one_file_per_partition(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let one_file_per_partition_option = copy_stmt_get_option(p_stmt, "one_file_per_partition");
 
-        compression.ensure_compression_level(compression_level);
+    if one_file_per_partition_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(one_file_per_partition_option.as_ptr()) }
     }
 }
 
-pub(crate) fn validate_copy_from_options(p_stmt: &PgBox<PlannedStmt>) {
-    validate_copy_option_names(p_stmt, &["format", "match_by", "freeze"]);
+// copy_to_stmt_max_rows_per_file returns the max number of rows of each file, which the COPY TO
+// writes into the directory at the uri. None means a single file at the uri.
+pub(crate) fn copy_to_stmt_max_rows_per_file(p_stmt: &PgBox<PlannedStmt>) -> Option<i64> {
+    let max_rows_per_file_option = copy_stmt_get_option(p_stmt, "max_rows_per_file");
 
-    let format_option = copy_stmt_get_option(p_stmt, "format");
+    if max_rows_per_file_option.is_null() {
+        None
+    } else {
+        Some(unsafe { defGetInt64(max_rows_per_file_option.as_ptr()) })
+    }
+}
 
-    if !format_option.is_null() {
-        let format = unsafe { defGetString(format_option.as_ptr()) };
+pub(crate) fn copy_to_stmt_if_exists(p_stmt: &PgBox<PlannedStmt>) -> PgParquetIfExists {
+    let if_exists_option = copy_stmt_get_option(p_stmt, "if_exists");
 
-        let format = unsafe {
-            CStr::from_ptr(format)
+    if if_exists_option.is_null() {
+        PgParquetIfExists::default()
+    } else {
+        let if_exists = unsafe { defGetString(if_exists_option.as_ptr()) };
+
+        let if_exists = unsafe {
+            CStr::from_ptr(if_exists)
                 .to_str()
-                .expect("format option is not a valid CString")
+                .expect("if_exists option is not a valid CString")
         };
 
-        if format != "parquet" {
-            panic!(
-                "{} is not a valid format. Only parquet format is supported.",
-                format
-            );
-        }
+        PgParquetIfExists::from_str(if_exists).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
-fn validate_copy_option_names(p_stmt: &PgBox<PlannedStmt>, allowed_options: &[&str]) {
-    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+pub(crate) fn copy_to_stmt_nan_handling(p_stmt: &PgBox<PlannedStmt>) -> PgParquetNanHandling {
+    let nan_handling_option = copy_stmt_get_option(p_stmt, "nan_handling");
 
-    let copy_options = unsafe { PgList::<DefElem>::from_pg(copy_stmt.options) };
+    if nan_handling_option.is_null() {
+        PgParquetNanHandling::default()
+    } else {
+        let nan_handling = unsafe { defGetString(nan_handling_option.as_ptr()) };
 
-    let copy_from_str = if copy_stmt.is_from { "from" } else { "to" };
+        let nan_handling = unsafe {
+            CStr::from_ptr(nan_handling)
+                .to_str()
+                .expect("nan_handling option is not a valid CString")
+        };
 
-    for option in copy_options.iter_ptr() {
-        let option = unsafe { PgBox::<DefElem>::from_pg(option) };
+        PgParquetNanHandling::from_str(nan_handling).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
 
-        let option_name = unsafe {
-            CStr::from_ptr(option.defname)
+// copy_stmt_infinite_timestamps returns the infinite_timestamps option of COPY TO and COPY FROM.
+pub(crate) fn copy_stmt_infinite_timestamps(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> PgParquetInfiniteTimestamps {
+    let infinite_timestamps_option = copy_stmt_get_option(p_stmt, "infinite_timestamps");
+
+    if infinite_timestamps_option.is_null() {
+        PgParquetInfiniteTimestamps::default()
+    } else {
+        let infinite_timestamps = unsafe { defGetString(infinite_timestamps_option.as_ptr()) };
+
+        let infinite_timestamps = unsafe {
+            CStr::from_ptr(infinite_timestamps)
                 .to_str()
-                .expect("option name is not a valid CString")
+                .expect("infinite_timestamps option is not a valid CString")
         };
 
-        if !allowed_options.contains(&option_name) {
-            panic!(
-                "{} is not a valid option for \"copy {} parquet\". Supported options are {}",
-                option_name,
-                copy_from_str,
-                allowed_options.join(", ")
-            );
-        }
+        PgParquetInfiniteTimestamps::from_str(infinite_timestamps)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
-pub(crate) fn copy_stmt_uri(p_stmt: &PgBox<PlannedStmt>) -> Option<Url> {
-    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
+pub(crate) fn copy_to_stmt_numeric_precision(p_stmt: &PgBox<PlannedStmt>) -> i32 {
+    let numeric_precision_option = copy_stmt_get_option(p_stmt, "numeric_precision");
 
-    if copy_stmt.is_program {
-        return None;
+    if numeric_precision_option.is_null() {
+        DEFAULT_UNBOUNDED_NUMERIC_PRECISION as i32
+    } else {
+        unsafe { defGetInt32(numeric_precision_option.as_ptr()) }
     }
+}
 
-    if copy_stmt.filename.is_null() {
-        return None;
+pub(crate) fn copy_to_stmt_numeric_scale(p_stmt: &PgBox<PlannedStmt>) -> i32 {
+    let numeric_scale_option = copy_stmt_get_option(p_stmt, "numeric_scale");
+
+    if numeric_scale_option.is_null() {
+        DEFAULT_UNBOUNDED_NUMERIC_SCALE as i32
+    } else {
+        unsafe { defGetInt32(numeric_scale_option.as_ptr()) }
     }
+}
 
-    let uri = unsafe {
-        CStr::from_ptr(copy_stmt.filename)
-            .to_str()
-            .expect("uri option is not a valid CString")
-    };
+pub(crate) fn copy_to_stmt_numeric_overflow(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> PgParquetNumericOverflow {
+    let numeric_overflow_option = copy_stmt_get_option(p_stmt, "numeric_overflow");
 
-    Some(parse_uri(uri))
+    if numeric_overflow_option.is_null() {
+        PgParquetNumericOverflow::default()
+    } else {
+        let numeric_overflow = unsafe { defGetString(numeric_overflow_option.as_ptr()) };
+
+        let numeric_overflow = unsafe {
+            CStr::from_ptr(numeric_overflow)
+                .to_str()
+                .expect("numeric_overflow option is not a valid CString")
+        };
+
+        PgParquetNumericOverflow::from_str(numeric_overflow).unwrap_or_else(|e| panic!("{}", e))
+    }
 }
 
-pub(crate) fn copy_to_stmt_row_group_size(p_stmt: &PgBox<PlannedStmt>) -> i64 {
-    let row_group_size_option = copy_stmt_get_option(p_stmt, "row_group_size");
+pub(crate) fn copy_to_stmt_multidim_arrays(p_stmt: &PgBox<PlannedStmt>) -> PgParquetMultidimArrays {
+    let multidim_arrays_option = copy_stmt_get_option(p_stmt, "multidim_arrays");
 
-    if row_group_size_option.is_null() {
-        DEFAULT_ROW_GROUP_SIZE
+    if multidim_arrays_option.is_null() {
+        PgParquetMultidimArrays::default()
     } else {
-        unsafe { defGetInt64(row_group_size_option.as_ptr()) }
+        let multidim_arrays = unsafe { defGetString(multidim_arrays_option.as_ptr()) };
+
+        let multidim_arrays = unsafe {
+            CStr::from_ptr(multidim_arrays)
+                .to_str()
+                .expect("multidim_arrays option is not a valid CString")
+        };
+
+        PgParquetMultidimArrays::from_str(multidim_arrays).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
-pub(crate) fn copy_to_stmt_row_group_size_bytes(p_stmt: &PgBox<PlannedStmt>) -> i64 {
-    let row_group_size_bytes_option = copy_stmt_get_option(p_stmt, "row_group_size_bytes");
+pub(crate) fn copy_to_stmt_duplicate_columns(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> PgParquetDuplicateColumns {
+    let duplicate_columns_option = copy_stmt_get_option(p_stmt, "duplicate_columns");
 
-    if row_group_size_bytes_option.is_null() {
-        DEFAULT_ROW_GROUP_SIZE_BYTES
+    if duplicate_columns_option.is_null() {
+        PgParquetDuplicateColumns::default()
     } else {
-        unsafe { defGetInt64(row_group_size_bytes_option.as_ptr()) }
+        let duplicate_columns = unsafe { defGetString(duplicate_columns_option.as_ptr()) };
+
+        let duplicate_columns = unsafe {
+            CStr::from_ptr(duplicate_columns)
+                .to_str()
+                .expect("duplicate_columns option is not a valid CString")
+        };
+
+        PgParquetDuplicateColumns::from_str(duplicate_columns).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
-pub(crate) fn copy_to_stmt_compression(
+pub(crate) fn copy_to_stmt_unsupported_type(
     p_stmt: &PgBox<PlannedStmt>,
-    uri: Url,
-) -> PgParquetCompression {
-    let compression_option = copy_stmt_get_option(p_stmt, "compression");
+) -> PgParquetUnsupportedType {
+    let unsupported_type_option = copy_stmt_get_option(p_stmt, "unsupported_type");
 
-    if compression_option.is_null() {
-        PgParquetCompression::try_from(uri).unwrap_or_default()
+    if unsupported_type_option.is_null() {
+        PgParquetUnsupportedType::default()
     } else {
-        let compression = unsafe { defGetString(compression_option.as_ptr()) };
+        let unsupported_type = unsafe { defGetString(unsupported_type_option.as_ptr()) };
 
-        let compression = unsafe {
-            CStr::from_ptr(compression)
+        let unsupported_type = unsafe {
+            CStr::from_ptr(unsupported_type)
                 .to_str()
-                .expect("compression option is not a valid CString")
+                .expect("unsupported_type option is not a valid CString")
         };
 
-        PgParquetCompression::from_str(compression).unwrap_or_else(|e| panic!("{}", e))
+        PgParquetUnsupportedType::from_str(unsupported_type).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
-pub(crate) fn copy_to_stmt_compression_level(p_stmt: &PgBox<PlannedStmt>, uri: Url) -> Option<i32> {
-    let compression_level_option = copy_stmt_get_option(p_stmt, "compression_level");
-
-    if compression_level_option.is_null() {
-        let compression = copy_to_stmt_compression(p_stmt, uri);
+pub(crate) fn copy_to_stmt_bloom_filter_fpp(p_stmt: &PgBox<PlannedStmt>) -> f64 {
+    let bloom_filter_fpp_option = copy_stmt_get_option(p_stmt, "bloom_filter_fpp");
 
-        compression.default_compression_level()
+    if bloom_filter_fpp_option.is_null() {
+        DEFAULT_BLOOM_FILTER_FPP
     } else {
-        Some(unsafe { defGetInt32(compression_level_option.as_ptr()) as _ })
+        unsafe { defGetNumeric(bloom_filter_fpp_option.as_ptr()) }
     }
 }
 
@@ -275,6 +1398,259 @@ pub(crate) fn copy_from_stmt_match_by(p_stmt: &PgBox<PlannedStmt>) -> MatchBy {
     }
 }
 
+// copy_from_stmt_field_ids returns the field ids of the table columns from the field_ids option,
+// e.g. '{"a": 1, "b": 3}', which match_by 'field_id' matches to the file columns.
+pub(crate) fn copy_from_stmt_field_ids(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> Option<HashMap<String, i32>> {
+    let field_ids_option = copy_stmt_get_option(p_stmt, "field_ids");
+
+    if field_ids_option.is_null() {
+        return None;
+    }
+
+    let field_ids = unsafe { defGetString(field_ids_option.as_ptr()) };
+
+    let field_ids = unsafe {
+        CStr::from_ptr(field_ids)
+            .to_str()
+            .expect("field_ids option is not a valid CString")
+    };
+
+    Some(parse_field_ids(field_ids).unwrap_or_else(|e| panic!("{}", e)))
+}
+
+pub(crate) fn copy_from_stmt_missing_columns(p_stmt: &PgBox<PlannedStmt>) -> MissingColumns {
+    let missing_columns_option = copy_stmt_get_option(p_stmt, "missing_columns");
+
+    if missing_columns_option.is_null() {
+        MissingColumns::default()
+    } else {
+        let missing_columns = unsafe { defGetString(missing_columns_option.as_ptr()) };
+
+        let missing_columns = unsafe {
+            CStr::from_ptr(missing_columns)
+                .to_str()
+                .expect("missing_columns option is not a valid CString")
+        };
+
+        MissingColumns::from_str(missing_columns).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+pub(crate) fn copy_from_stmt_geometry_srid_mismatch(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> GeometrySridMismatch {
+    let geometry_srid_mismatch_option = copy_stmt_get_option(p_stmt, "geometry_srid_mismatch");
+
+    if geometry_srid_mismatch_option.is_null() {
+        GeometrySridMismatch::default()
+    } else {
+        let geometry_srid_mismatch =
+            unsafe { defGetString(geometry_srid_mismatch_option.as_ptr()) };
+
+        let geometry_srid_mismatch = unsafe {
+            CStr::from_ptr(geometry_srid_mismatch)
+                .to_str()
+                .expect("geometry_srid_mismatch option is not a valid CString")
+        };
+
+        GeometrySridMismatch::from_str(geometry_srid_mismatch).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+pub(crate) fn copy_from_stmt_timestamptz_to_timestamp(
+    p_stmt: &PgBox<PlannedStmt>,
+) -> TimestamptzToTimestamp {
+    let timestamptz_to_timestamp_option = copy_stmt_get_option(p_stmt, "timestamptz_to_timestamp");
+
+    if timestamptz_to_timestamp_option.is_null() {
+        TimestamptzToTimestamp::default()
+    } else {
+        let timestamptz_to_timestamp =
+            unsafe { defGetString(timestamptz_to_timestamp_option.as_ptr()) };
+
+        let timestamptz_to_timestamp = unsafe {
+            CStr::from_ptr(timestamptz_to_timestamp)
+                .to_str()
+                .expect("timestamptz_to_timestamp option is not a valid CString")
+        };
+
+        TimestamptzToTimestamp::from_str(timestamptz_to_timestamp)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+pub(crate) fn copy_from_stmt_list_coercion(p_stmt: &PgBox<PlannedStmt>) -> ListCoercion {
+    let list_coercion_option = copy_stmt_get_option(p_stmt, "list_coercion");
+
+    if list_coercion_option.is_null() {
+        ListCoercion::default()
+    } else {
+        let list_coercion = unsafe { defGetString(list_coercion_option.as_ptr()) };
+
+        let list_coercion = unsafe {
+            CStr::from_ptr(list_coercion)
+                .to_str()
+                .expect("list_coercion option is not a valid CString")
+        };
+
+        ListCoercion::from_str(list_coercion).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// copy_from_stmt_truncate returns true if COPY FROM truncates the table before loading the file.
+pub(crate) fn copy_from_stmt_truncate(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let truncate_option = copy_stmt_get_option(p_stmt, "truncate");
+
+    if truncate_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(truncate_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_from_stmt_restart_identity(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let restart_identity_option = copy_stmt_get_option(p_stmt, "restart_identity");
+
+    if restart_identity_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(restart_identity_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_from_stmt_cascade(p_stmt: &PgBox<PlannedStmt>) -> bool {
+    let cascade_option = copy_stmt_get_option(p_stmt, "cascade");
+
+    if cascade_option.is_null() {
+        false
+    } else {
+        unsafe { defGetBoolean(cascade_option.as_ptr()) }
+    }
+}
+
+pub(crate) fn copy_from_stmt_on_error(p_stmt: &PgBox<PlannedStmt>) -> CopyFromOnError {
+    let on_error_option = copy_stmt_get_option(p_stmt, "on_error");
+
+    if on_error_option.is_null() {
+        CopyFromOnError::default()
+    } else {
+        let on_error = unsafe { defGetString(on_error_option.as_ptr()) };
+
+        let on_error = unsafe {
+            CStr::from_ptr(on_error)
+                .to_str()
+                .expect("on_error option is not a valid CString")
+        };
+
+        CopyFromOnError::from_str(on_error).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// copy_from_stmt_extra_columns returns the extra_columns option. allow_extra_columns, which
+// predates it, is its alias, i.e. true is extra_columns 'ignore' and false is 'error'.
+pub(crate) fn copy_from_stmt_extra_columns(
+    p_stmt: &PgBox<PlannedStmt>,
+    match_by: MatchBy,
+) -> ExtraColumns {
+    let extra_columns_option = copy_stmt_get_option(p_stmt, "extra_columns");
+
+    let allow_extra_columns_option = copy_stmt_get_option(p_stmt, "allow_extra_columns");
+
+    if !allow_extra_columns_option.is_null() {
+        if !extra_columns_option.is_null() {
+            panic!("allow_extra_columns and extra_columns cannot be specified together");
+        }
+
+        let allow_extra_columns = unsafe { defGetBoolean(allow_extra_columns_option.as_ptr()) };
+
+        return if allow_extra_columns {
+            ExtraColumns::Ignore
+        } else {
+            ExtraColumns::Error
+        };
+    }
+
+    if extra_columns_option.is_null() {
+        return ExtraColumns::default_for(match_by);
+    }
+
+    let extra_columns = unsafe { defGetString(extra_columns_option.as_ptr()) };
+
+    let extra_columns = unsafe {
+        CStr::from_ptr(extra_columns)
+            .to_str()
+            .expect("extra_columns option is not a valid CString")
+    };
+
+    ExtraColumns::from_str(extra_columns).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// copy_from_stmt_row_group_selection returns the row groups to load, which are selected by
+// resume_from and max_row_groups options
+pub(crate) fn copy_from_stmt_row_group_selection(p_stmt: &PgBox<PlannedStmt>) -> RowGroupSelection {
+    let resume_from_option = copy_stmt_get_option(p_stmt, "resume_from");
+
+    let resume_from = if resume_from_option.is_null() {
+        None
+    } else {
+        let resume_from = unsafe { defGetString(resume_from_option.as_ptr()) };
+
+        let resume_from = unsafe {
+            CStr::from_ptr(resume_from)
+                .to_str()
+                .expect("resume_from option is not a valid CString")
+        };
+
+        Some(ResumeToken::from_str(resume_from).unwrap_or_else(|e| panic!("{}", e)))
+    };
+
+    let max_row_groups_option = copy_stmt_get_option(p_stmt, "max_row_groups");
+
+    let max_row_groups = if max_row_groups_option.is_null() {
+        None
+    } else {
+        let max_row_groups = unsafe { defGetInt64(max_row_groups_option.as_ptr()) };
+
+        Some(max_row_groups as usize)
+    };
+
+    RowGroupSelection {
+        resume_from,
+        max_row_groups,
+    }
+}
+
+// copy_from_stmt_attribute_list_without creates the attribute name list for COPY FROM, which
+// consists of the attributes in the tupledesc except the excluded ones. COPY fills the excluded
+// attributes with their default values.
+pub(crate) fn copy_from_stmt_attribute_list_without(
+    tupledesc: &PgTupleDesc,
+    excluded_attribute_names: &[String],
+) -> *mut List {
+    let attributes = collect_attributes_for(CollectAttributesFor::CopyFrom, tupledesc);
+
+    let mut attribute_name_list = PgList::<Node>::new();
+
+    for attribute in attributes {
+        let attribute_name = attribute.name();
+
+        if excluded_attribute_names
+            .iter()
+            .any(|excluded_attribute_name| excluded_attribute_name == attribute_name)
+        {
+            continue;
+        }
+
+        let attribute_name = unsafe { makeString(attribute_name.as_pg_cstr()) };
+
+        attribute_name_list.push(attribute_name as _);
+    }
+
+    attribute_name_list.into_pg()
+}
+
 pub(crate) fn copy_stmt_get_option(
     p_stmt: &PgBox<PlannedStmt>,
     option_name: &str,
@@ -318,18 +1694,26 @@ fn is_copy_parquet_stmt(p_stmt: &PgBox<PlannedStmt>, copy_from: bool) -> bool {
         return false;
     }
 
-    if copy_stmt.is_program {
-        return false;
-    }
-
     if copy_stmt.filename.is_null() {
         return false;
     }
 
-    let uri = copy_stmt_uri(p_stmt).expect("uri is None");
+    if copy_stmt.is_program {
+        // the format cannot be inferred from the command of the program
+        if !is_parquet_format_option(p_stmt) {
+            return false;
+        }
+    } else if copy_stmt_is_temp(p_stmt) {
+        // temp files are addressed by name, so the format cannot be inferred from the uri
+        if !is_parquet_format_option(p_stmt) {
+            return false;
+        }
+    } else {
+        let uri = copy_stmt_uri(p_stmt).expect("uri is None");
 
-    if !is_parquet_format_option(p_stmt) && !is_parquet_uri(uri) {
-        return false;
+        if !is_parquet_format_option(p_stmt) && !is_parquet_uri(uri) {
+            return false;
+        }
     }
 
     // extension checks are done via catalog (not yet searched via cache by postgres till pg18)
@@ -393,6 +1777,34 @@ pub(crate) fn copy_stmt_has_relation(p_stmt: &PgBox<PlannedStmt>) -> bool {
     !copy_stmt.relation.is_null()
 }
 
+// copy_to_stmt_relation_field_ids returns the field ids of the columns of the COPY TO relation
+// as a json object, e.g. '{"a": 0, "c": 2}'. They are based on the attnums of the columns,
+// hence a dropped column does not shift the field ids of the columns after it.
+pub(crate) fn copy_to_stmt_relation_field_ids(p_stmt: &PgBox<PlannedStmt>) -> Option<String> {
+    // the written columns have contiguous field ids, when some columns are excluded
+    if !copy_stmt_has_relation(p_stmt) || copy_to_stmt_excludes_columns(p_stmt) {
+        return None;
+    }
+
+    let relation = unsafe {
+        PgRelation::with_lock(copy_stmt_relation_oid(p_stmt), copy_stmt_lock_mode(p_stmt))
+    };
+
+    let field_ids = relation
+        .tuple_desc()
+        .iter()
+        .filter(|attribute| !attribute.is_dropped())
+        .map(|attribute| {
+            (
+                attribute.name().to_string(),
+                serde_json::Value::from(attribute_field_id(attribute)),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    Some(serde_json::Value::Object(field_ids).to_string())
+}
+
 pub(crate) fn copy_stmt_lock_mode(p_stmt: &PgBox<PlannedStmt>) -> i32 {
     let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(p_stmt.utilityStmt as _) };
 
@@ -493,6 +1905,7 @@ pub(crate) fn copy_stmt_attribute_list(p_stmt: &PgBox<PlannedStmt>) -> *mut List
 
 // create_filtered_tupledesc_for_relation creates a new tuple descriptor for the COPY operation by
 // removing dropped attributes and filtering the attributes based on the attribute name list.
+// Like COPY FROM, it fails if the attribute name list has a generated column.
 pub(crate) fn create_filtered_tupledesc_for_relation<'a>(
     p_stmt: &PgBox<PlannedStmt>,
     relation: &'a PgRelation,
@@ -523,6 +1936,15 @@ pub(crate) fn create_filtered_tupledesc_for_relation<'a>(
             let att_ndims = attribute.attndims;
 
             if attribute.name() == attribute_name {
+                if is_generated_attribute(attribute) {
+                    ereport!(
+                        PgLogLevel::ERROR,
+                        PgSqlErrorCode::ERRCODE_INVALID_COLUMN_REFERENCE,
+                        format!("column \"{}\" is a generated column", attribute_name),
+                        "Generated columns cannot be used in COPY."
+                    );
+                }
+
                 unsafe {
                     TupleDescInitEntry(
                         filtered_tupledesc.as_ptr(),
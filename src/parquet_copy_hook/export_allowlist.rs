@@ -0,0 +1,123 @@
+use std::ffi::CStr;
+
+use pgrx::{
+    ereport,
+    pg_sys::{
+        get_namespace_name, get_rel_name, get_rel_namespace, Oid, PlannedStmt, RTEKind,
+        RangeTblEntry,
+    },
+    GucSetting, PgBox, PgList, PgLogLevel, PgSqlErrorCode,
+};
+
+// EXPORT_ALLOWLIST is a comma separated list of "schema" or "schema.table" patterns, which
+// restricts the relations that COPY TO can export to parquet. "*" in a pattern matches any
+// sequence of characters. An empty list means no restriction.
+pub(crate) static EXPORT_ALLOWLIST: GucSetting<Option<&'static CStr>> =
+    GucSetting::<Option<&'static CStr>>::new(None);
+
+// ensure_export_allowed ensures that every relation referenced by the planned query of
+// COPY TO matches "pg_parquet.export_allowlist". The planner's range table contains the
+// relations referenced by subqueries, views and rules, as well.
+pub(crate) fn ensure_export_allowed(plan: &PgBox<PlannedStmt>) {
+    let allowlist = match EXPORT_ALLOWLIST.get() {
+        Some(allowlist) => allowlist.to_str().unwrap_or_default().to_string(),
+        None => return,
+    };
+
+    let patterns = parse_export_allowlist(&allowlist);
+
+    if patterns.is_empty() {
+        return;
+    }
+
+    let range_table = unsafe { PgList::<RangeTblEntry>::from_pg(plan.rtable) };
+
+    for rte in range_table.iter_ptr() {
+        let rte = unsafe { PgBox::<RangeTblEntry>::from_pg(rte) };
+
+        if rte.rtekind != RTEKind::RTE_RELATION {
+            continue;
+        }
+
+        let (schema_name, table_name) = relation_schema_and_name(rte.relid);
+
+        if !patterns
+            .iter()
+            .any(|pattern| export_allowlist_pattern_matches(pattern, &schema_name, &table_name))
+        {
+            ereport!(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+                format!(
+                    "relation \"{}.{}\" is not allowed to be exported by pg_parquet.export_allowlist",
+                    schema_name, table_name
+                ),
+            );
+        }
+    }
+}
+
+fn parse_export_allowlist(allowlist: &str) -> Vec<String> {
+    allowlist
+        .split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+// export_allowlist_pattern_matches matches a "schema" pattern against the schema of the
+// relation, and a "schema.table" pattern against both its schema and name.
+fn export_allowlist_pattern_matches(pattern: &str, schema_name: &str, table_name: &str) -> bool {
+    match pattern.split_once('.') {
+        Some((schema_pattern, table_pattern)) => {
+            wildcard_matches(schema_pattern, schema_name)
+                && wildcard_matches(table_pattern, table_name)
+        }
+        None => wildcard_matches(pattern, schema_name),
+    }
+}
+
+// wildcard_matches matches the name against the pattern, where "*" matches any sequence of
+// characters.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // pattern without any "*" must match exactly
+    let first_part = parts.next().unwrap_or_default();
+
+    let Some(mut rest) = name.strip_prefix(first_part) else {
+        return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+
+    let Some((last_part, middle_parts)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle_parts {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last_part)
+}
+
+fn relation_schema_and_name(relation_oid: Oid) -> (String, String) {
+    let schema_name = unsafe { get_namespace_name(get_rel_namespace(relation_oid)) };
+    let table_name = unsafe { get_rel_name(relation_oid) };
+
+    if schema_name.is_null() || table_name.is_null() {
+        panic!("cache lookup failed for relation {}", relation_oid.as_u32());
+    }
+
+    let schema_name = unsafe { CStr::from_ptr(schema_name) };
+    let table_name = unsafe { CStr::from_ptr(table_name) };
+
+    (
+        schema_name.to_string_lossy().into_owned(),
+        table_name.to_string_lossy().into_owned(),
+    )
+}
@@ -1,28 +1,59 @@
 use std::ffi::{c_char, CStr};
 
 use pg_sys::{
-    standard_ProcessUtility, AsPgCStr, CommandTag, DestReceiver, ParamListInfoData, PlannedStmt,
-    ProcessUtility_hook, ProcessUtility_hook_type, QueryCompletion, QueryEnvironment,
+    get_rel_name, standard_ProcessUtility, AsPgCStr, CommandTag, DestReceiver, Oid,
+    ParamListInfoData, PlannedStmt, ProcessUtility_hook, ProcessUtility_hook_type, QueryCompletion,
+    QueryEnvironment,
 };
-use pgrx::{prelude::*, GucSetting};
+use pgrx::{pg_sys::panic::CaughtError, prelude::*, GucSetting};
+use url::Url;
 
 use crate::{
     arrow_parquet::{
         compression::INVALID_COMPRESSION_LEVEL,
-        uri_utils::{ensure_access_privilege_to_uri, uri_as_string},
+        copy_files::{finish_copy_files, start_copy_files},
+        if_exists::PgParquetIfExists,
+        row_group_selection::RESUME_EXACTLY_ONCE_NOTE,
+        temp_files::{create_temp_parquet_file, next_temp_parquet_file_uri, temp_parquet_file_uri},
+        uri_utils::{
+            delete_uri, ensure_access_privilege_to_uri, redact_uri, uri_as_string,
+            uri_prefix_has_files,
+        },
     },
     parquet_copy_hook::{
-        copy_to_dest_receiver::create_copy_to_parquet_dest_receiver,
+        copy_program::{
+            copy_file_to_program_input, copy_program_output_to_file, ensure_copy_program_privilege,
+            remove_copy_program_file,
+        },
+        copy_to_dest_receiver::{
+            abort_copy_to_parquet_dest_receiver, create_copy_to_parquet_dest_receiver_with_options,
+            CopyToParquetDestReceiverOptions, KEEP_PARTIAL_FILES,
+        },
         copy_utils::{
-            copy_stmt_uri, copy_to_stmt_compression_level, copy_to_stmt_row_group_size,
-            copy_to_stmt_row_group_size_bytes, is_copy_from_parquet_stmt, is_copy_to_parquet_stmt,
+            copy_stmt_infinite_timestamps, copy_stmt_is_temp, copy_stmt_program,
+            copy_stmt_temp_file_name, copy_stmt_uri, copy_to_stmt_allow_mixed_srids,
+            copy_to_stmt_batch_memory_limit, copy_to_stmt_bloom_filter_columns,
+            copy_to_stmt_bloom_filter_fpp, copy_to_stmt_byte_stream_split_columns,
+            copy_to_stmt_compression_level, copy_to_stmt_data_page_row_count_limit,
+            copy_to_stmt_data_page_size_bytes, copy_to_stmt_dictionary_columns,
+            copy_to_stmt_dictionary_enabled, copy_to_stmt_dictionary_page_size_limit,
+            copy_to_stmt_duplicate_columns, copy_to_stmt_geoparquet_covering,
+            copy_to_stmt_if_exists, copy_to_stmt_kv_metadata, copy_to_stmt_max_buffered_bytes,
+            copy_to_stmt_max_rows_per_file, copy_to_stmt_multidim_arrays,
+            copy_to_stmt_nan_handling, copy_to_stmt_no_dictionary_columns,
+            copy_to_stmt_numeric_overflow, copy_to_stmt_numeric_precision,
+            copy_to_stmt_numeric_scale, copy_to_stmt_one_file_per_partition,
+            copy_to_stmt_relation_field_ids, copy_to_stmt_row_group_size,
+            copy_to_stmt_row_group_size_bytes, copy_to_stmt_statistics,
+            copy_to_stmt_unsupported_type, copy_to_stmt_write_batch_size,
+            copy_to_stmt_writer_version, is_copy_from_parquet_stmt, is_copy_to_parquet_stmt,
         },
     },
 };
 
 use super::{
-    copy_from::{execute_copy_from, pop_parquet_reader_context},
-    copy_to::execute_copy_to_with_dest_receiver,
+    copy_from::{execute_copy_from, peek_parquet_reader_context, pop_parquet_reader_context},
+    copy_to::{copy_to_stmt_leaf_partitions, execute_copy_to_with_dest_receiver},
     copy_utils::{copy_to_stmt_compression, validate_copy_from_options, validate_copy_to_options},
 };
 
@@ -49,32 +80,238 @@ fn process_copy_to_parquet(
     params: &PgBox<ParamListInfoData>,
     query_env: &PgBox<QueryEnvironment>,
 ) -> u64 {
-    let uri = copy_stmt_uri(p_stmt).expect("uri is None");
+    // the program reads the file after it is written, since the footer is written at the end
+    if let Some(program) = copy_stmt_program(p_stmt) {
+        ensure_copy_program_privilege();
+
+        let uri = next_temp_parquet_file_uri();
+
+        return PgTryBuilder::new(|| {
+            let nprocessed =
+                write_copy_to_parquet(p_stmt, query_string, params, query_env, uri.clone());
+
+            copy_file_to_program_input(&uri, &program);
+
+            nprocessed
+        })
+        .finally(|| remove_copy_program_file(&uri))
+        .execute();
+    }
+
+    // temp files are managed by the backend, so they do not require file privileges
+    let uri = if copy_stmt_is_temp(p_stmt) {
+        create_temp_parquet_file(&copy_stmt_temp_file_name(p_stmt))
+    } else {
+        let uri = copy_stmt_uri(p_stmt).expect("uri is None");
+
+        let copy_from = false;
+        ensure_access_privilege_to_uri(&uri, copy_from);
+
+        ensure_copy_to_directory_is_empty(p_stmt, &uri);
 
-    let copy_from = false;
-    ensure_access_privilege_to_uri(&uri, copy_from);
+        uri
+    };
 
+    write_copy_to_parquet(p_stmt, query_string, params, query_env, uri)
+}
+
+// ensure_copy_to_directory_is_empty fails COPY TO with if_exists 'error' when the directory at
+// the uri has files with one_file_per_partition or max_rows_per_file. The files are created by
+// the dest receiver only if they do not exist, which handles if_exists for each file.
+fn ensure_copy_to_directory_is_empty(p_stmt: &PgBox<PlannedStmt>, uri: &Url) {
+    if copy_to_stmt_if_exists(p_stmt) != PgParquetIfExists::Error {
+        return;
+    }
+
+    let is_directory = copy_to_stmt_one_file_per_partition(p_stmt)
+        || copy_to_stmt_max_rows_per_file(p_stmt).is_some();
+
+    if is_directory && uri_prefix_has_files(uri) {
+        panic!("directory {} is not empty", redact_uri(uri));
+    }
+}
+
+fn write_copy_to_parquet(
+    p_stmt: &PgBox<PlannedStmt>,
+    query_string: &CStr,
+    params: &PgBox<ParamListInfoData>,
+    query_env: &PgBox<QueryEnvironment>,
+    uri: Url,
+) -> u64 {
     validate_copy_to_options(p_stmt, &uri);
 
     let row_group_size = copy_to_stmt_row_group_size(p_stmt);
     let row_group_size_bytes = copy_to_stmt_row_group_size_bytes(p_stmt);
     let compression = copy_to_stmt_compression(p_stmt, uri.clone());
     let compression_level = copy_to_stmt_compression_level(p_stmt, uri.clone());
+    let statistics = copy_to_stmt_statistics(p_stmt);
+    let bloom_filter_columns = copy_to_stmt_bloom_filter_columns(p_stmt);
+    let bloom_filter_fpp = copy_to_stmt_bloom_filter_fpp(p_stmt);
+    let dictionary_enabled = copy_to_stmt_dictionary_enabled(p_stmt);
+    let dictionary_columns = copy_to_stmt_dictionary_columns(p_stmt);
+    let no_dictionary_columns = copy_to_stmt_no_dictionary_columns(p_stmt);
+    let byte_stream_split_columns = copy_to_stmt_byte_stream_split_columns(p_stmt);
+    let writer_version = copy_to_stmt_writer_version(p_stmt);
+    let dictionary_page_size_limit = copy_to_stmt_dictionary_page_size_limit(p_stmt);
+    let data_page_size_bytes = copy_to_stmt_data_page_size_bytes(p_stmt);
+    let data_page_row_count_limit = copy_to_stmt_data_page_row_count_limit(p_stmt);
+    let write_batch_size = copy_to_stmt_write_batch_size(p_stmt);
+    let max_buffered_bytes = copy_to_stmt_max_buffered_bytes(p_stmt);
+    let batch_memory_limit = copy_to_stmt_batch_memory_limit(p_stmt);
+    let kv_metadata = copy_to_stmt_kv_metadata(p_stmt);
+    let geoparquet_covering = copy_to_stmt_geoparquet_covering(p_stmt);
+    let allow_mixed_srids = copy_to_stmt_allow_mixed_srids(p_stmt);
+    let nan_handling = copy_to_stmt_nan_handling(p_stmt);
+    let infinite_timestamps = copy_stmt_infinite_timestamps(p_stmt);
+    let unsupported_type = copy_to_stmt_unsupported_type(p_stmt);
+    let numeric_precision = copy_to_stmt_numeric_precision(p_stmt);
+    let numeric_scale = copy_to_stmt_numeric_scale(p_stmt);
+    let numeric_overflow = copy_to_stmt_numeric_overflow(p_stmt);
+    let multidim_arrays = copy_to_stmt_multidim_arrays(p_stmt);
+    let duplicate_columns = copy_to_stmt_duplicate_columns(p_stmt);
+    let max_rows_per_file = copy_to_stmt_max_rows_per_file(p_stmt);
+    let field_ids = copy_to_stmt_relation_field_ids(p_stmt);
+    let if_exists = copy_to_stmt_if_exists(p_stmt);
 
-    let parquet_dest = create_copy_to_parquet_dest_receiver(
-        uri_as_string(&uri).as_pg_cstr(),
-        &row_group_size,
-        &row_group_size_bytes,
-        &compression,
-        &compression_level.unwrap_or(INVALID_COMPRESSION_LEVEL),
-    );
+    // parquet.last_copy_files() lists the files of this COPY
+    start_copy_files();
+
+    let create_parquet_dest = |uri: &Url| {
+        // the options are passed as pointers, where null means the default value
+        let c_string_ptr = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| value.as_pg_cstr() as *const c_char)
+                .unwrap_or(std::ptr::null())
+        };
+
+        let compression_level = compression_level.unwrap_or(INVALID_COMPRESSION_LEVEL);
+
+        let options = CopyToParquetDestReceiverOptions {
+            row_group_size: &row_group_size,
+            row_group_size_bytes: &row_group_size_bytes,
+            compression: &compression,
+            compression_level: &compression_level,
+            statistics: &statistics,
+            bloom_filter_columns: c_string_ptr(&bloom_filter_columns),
+            bloom_filter_fpp: &bloom_filter_fpp,
+            dictionary_enabled: &dictionary_enabled,
+            dictionary_columns: c_string_ptr(&dictionary_columns),
+            no_dictionary_columns: c_string_ptr(&no_dictionary_columns),
+            byte_stream_split_columns: c_string_ptr(&byte_stream_split_columns),
+            writer_version: &writer_version,
+            dictionary_page_size_limit: &dictionary_page_size_limit,
+            data_page_size_bytes: &data_page_size_bytes,
+            data_page_row_count_limit: &data_page_row_count_limit,
+            write_batch_size: &write_batch_size,
+            max_buffered_bytes: &max_buffered_bytes,
+            kv_metadata: c_string_ptr(&kv_metadata),
+            geoparquet_covering: &geoparquet_covering,
+            allow_mixed_srids: &allow_mixed_srids,
+            nan_handling: &nan_handling,
+            unsupported_type: &unsupported_type,
+            numeric_precision: &numeric_precision,
+            numeric_scale: &numeric_scale,
+            numeric_overflow: &numeric_overflow,
+            multidim_arrays: &multidim_arrays,
+            duplicate_columns: &duplicate_columns,
+            max_rows_per_file: max_rows_per_file
+                .as_ref()
+                .map(|max_rows_per_file| max_rows_per_file as *const i64)
+                .unwrap_or(std::ptr::null()),
+            field_ids: c_string_ptr(&field_ids),
+            batch_memory_limit: &batch_memory_limit,
+            infinite_timestamps: &infinite_timestamps,
+            if_exists: &if_exists,
+            ..Default::default()
+        };
+
+        let parquet_dest = create_copy_to_parquet_dest_receiver_with_options(
+            uri_as_string(uri).as_pg_cstr(),
+            &options,
+        );
+
+        unsafe { PgBox::from_pg(parquet_dest) }
+    };
+
+    if !copy_to_stmt_one_file_per_partition(p_stmt) {
+        let parquet_dest = create_parquet_dest(&uri);
+
+        let partition_oid = None;
+
+        return execute_copy_to_parquet(
+            p_stmt,
+            query_string,
+            params,
+            query_env,
+            &parquet_dest,
+            partition_oid,
+        );
+    }
+
+    // each leaf partition is written to <uri>/<partition name>.parquet
+    let mut nprocessed = 0;
+    let mut written_partition_uris = vec![];
+
+    for partition_oid in copy_to_stmt_leaf_partitions(p_stmt) {
+        let partition_uri = partition_file_uri(&uri, partition_oid);
+
+        let parquet_dest = create_parquet_dest(&partition_uri);
+
+        nprocessed += PgTryBuilder::new(|| {
+            execute_copy_to_parquet(
+                p_stmt,
+                query_string,
+                params,
+                query_env,
+                &parquet_dest,
+                Some(partition_oid),
+            )
+        })
+        .catch_others(|cause| {
+            // the files of the preceding partitions are removed as well
+            if !KEEP_PARTIAL_FILES.get() {
+                for written_partition_uri in &written_partition_uris {
+                    delete_uri(written_partition_uri);
+                }
+            }
+
+            cause.rethrow()
+        })
+        .execute();
+
+        written_partition_uris.push(partition_uri);
+    }
 
-    let parquet_dest = unsafe { PgBox::from_pg(parquet_dest) };
+    nprocessed
+}
 
+fn execute_copy_to_parquet(
+    p_stmt: &PgBox<PlannedStmt>,
+    query_string: &CStr,
+    params: &PgBox<ParamListInfoData>,
+    query_env: &PgBox<QueryEnvironment>,
+    parquet_dest: &PgBox<DestReceiver>,
+    partition_oid: Option<Oid>,
+) -> u64 {
     PgTryBuilder::new(|| {
-        execute_copy_to_with_dest_receiver(p_stmt, query_string, params, query_env, &parquet_dest)
+        execute_copy_to_with_dest_receiver(
+            p_stmt,
+            query_string,
+            params,
+            query_env,
+            parquet_dest,
+            partition_oid,
+        )
     })
     .catch_others(|cause| {
+        // a failed COPY aborts the upload of the file and removes it, unless the partial
+        // file is kept, e.g. for debugging
+        if !KEEP_PARTIAL_FILES.get() {
+            abort_copy_to_parquet_dest_receiver(parquet_dest.as_ptr());
+            cause.rethrow()
+        }
+
         // make sure to cleanup parquet dest receiver
         if let Some(shutdown_callback) = parquet_dest.rShutdown {
             unsafe {
@@ -86,30 +323,119 @@ fn process_copy_to_parquet(
     .execute()
 }
 
+// partition_file_uri returns the uri of the file, which the leaf partition is written to
+// with one_file_per_partition.
+fn partition_file_uri(uri: &Url, partition_oid: Oid) -> Url {
+    let partition_name = unsafe { get_rel_name(partition_oid) };
+
+    let partition_name = unsafe {
+        CStr::from_ptr(partition_name)
+            .to_str()
+            .expect("partition name is not a valid CString")
+    };
+
+    let mut partition_uri = uri.clone();
+    partition_uri.set_path(&format!(
+        "{}/{}.parquet",
+        uri.path().trim_end_matches('/'),
+        partition_name
+    ));
+
+    partition_uri
+}
+
 fn process_copy_from_parquet(
     p_stmt: &PgBox<PlannedStmt>,
     query_string: &CStr,
     query_env: &PgBox<QueryEnvironment>,
 ) -> u64 {
-    let uri = copy_stmt_uri(p_stmt).expect("uri is None");
+    // the program's output is buffered in a file, since the footer is read first
+    if let Some(program) = copy_stmt_program(p_stmt) {
+        ensure_copy_program_privilege();
+
+        let uri = next_temp_parquet_file_uri();
+
+        return PgTryBuilder::new(|| {
+            copy_program_output_to_file(&program, &uri);
+
+            read_copy_from_parquet(p_stmt, query_string, query_env, uri.clone())
+        })
+        .finally(|| remove_copy_program_file(&uri))
+        .execute();
+    }
 
-    let copy_from = true;
-    ensure_access_privilege_to_uri(&uri, copy_from);
+    let uri = if copy_stmt_is_temp(p_stmt) {
+        temp_parquet_file_uri(&copy_stmt_temp_file_name(p_stmt))
+    } else {
+        let uri = copy_stmt_uri(p_stmt).expect("uri is None");
 
+        let copy_from = true;
+        ensure_access_privilege_to_uri(&uri, copy_from);
+
+        uri
+    };
+
+    read_copy_from_parquet(p_stmt, query_string, query_env, uri)
+}
+
+fn read_copy_from_parquet(
+    p_stmt: &PgBox<PlannedStmt>,
+    query_string: &CStr,
+    query_env: &PgBox<QueryEnvironment>,
+    uri: Url,
+) -> u64 {
     validate_copy_from_options(p_stmt);
 
     PgTryBuilder::new(|| execute_copy_from(p_stmt, query_string, query_env, uri))
         .catch_others(|cause| {
+            let retry_resume_token =
+                peek_parquet_reader_context().and_then(|context| context.retry_resume_token());
+
             // make sure to pop the parquet reader context
             // In case we did not push the context, we should not throw an error while popping
             let throw_error = false;
             pop_parquet_reader_context(throw_error);
 
-            cause.rethrow()
+            match retry_resume_token {
+                Some(retry_resume_token) => {
+                    report_chunked_copy_from_error(cause, &retry_resume_token.to_string())
+                }
+                None => cause.rethrow(),
+            }
         })
         .execute()
 }
 
+// report_chunked_copy_from_error rethrows the error of a chunked COPY FROM with the token
+// to retry it, since none of its rows are loaded.
+fn report_chunked_copy_from_error(cause: CaughtError, retry_resume_token: &str) -> ! {
+    let report = match &cause {
+        CaughtError::PostgresError(report)
+        | CaughtError::ErrorReport(report)
+        | CaughtError::RustPanic {
+            ereport: report, ..
+        } => report,
+    };
+
+    let mut detail = format!(
+        "No rows of this COPY FROM are loaded. Retry with resume_from '{}'. {}",
+        retry_resume_token, RESUME_EXACTLY_ONCE_NOTE
+    );
+
+    if let Some(original_detail) = report.detail() {
+        detail = format!("{}\n{}", original_detail, detail);
+    }
+
+    ereport!(
+        PgLogLevel::ERROR,
+        report.sql_error_code(),
+        report.message(),
+        detail
+    );
+
+    unreachable!("error is reported");
+}
+
 #[pg_guard]
 #[allow(clippy::too_many_arguments)]
 extern "C" fn parquet_copy_hook(
@@ -131,6 +457,12 @@ extern "C" fn parquet_copy_hook(
     if is_copy_to_parquet_stmt(&p_stmt) {
         let nprocessed = process_copy_to_parquet(&p_stmt, query_string, &params, &query_env);
 
+        // the command tag reports the rows, which are written to the finished files
+        let rows_written = finish_copy_files();
+        debug_assert_eq!(nprocessed, rows_written);
+
+        let nprocessed = rows_written;
+
         if !completion_tag.is_null() {
             completion_tag.nprocessed = nprocessed;
             completion_tag.commandTag = CommandTag::CMDTAG_COPY;
@@ -1,6 +1,8 @@
 use std::ffi::{c_char, CStr};
 
-use pgrx::pg_sys::{AsPgCStr, List, Node, QueryEnvironment, RawStmt};
+use pgrx::pg_sys::{
+    AsPgCStr, List, Node, ParseNamespaceItem, ParseState, QueryEnvironment, RawStmt,
+};
 
 pub(crate) fn pg_analyze_and_rewrite(
     raw_stmt: *mut RawStmt,
@@ -65,3 +67,43 @@ pub(crate) fn MarkGUCPrefixReserved(guc_prefix: &str) {
         pgrx::pg_sys::MarkGUCPrefixReserved(guc_prefix.as_pg_cstr())
     }
 }
+
+// check_copy_from_insert_permission checks the INSERT privilege on the relation of the namespace
+// item for the given columns, like DoCopy does for COPY FROM.
+pub(crate) fn check_copy_from_insert_permission(
+    p_state: *mut ParseState,
+    ns_item: *mut ParseNamespaceItem,
+    attnums: &[i16],
+) {
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    unsafe {
+        let rte = (*ns_item).p_rte;
+
+        (*rte).requiredPerms = pgrx::pg_sys::ACL_INSERT as _;
+
+        for attnum in attnums {
+            let attno = *attnum as i32 - pgrx::pg_sys::FirstLowInvalidHeapAttributeNumber as i32;
+            (*rte).insertedCols = pgrx::pg_sys::bms_add_member((*rte).insertedCols, attno);
+        }
+
+        pgrx::pg_sys::ExecCheckRTPerms((*p_state).p_rtable, true);
+    }
+
+    #[cfg(any(feature = "pg16", feature = "pg17"))]
+    unsafe {
+        let perminfo = (*ns_item).p_perminfo;
+
+        (*perminfo).requiredPerms = pgrx::pg_sys::ACL_INSERT as _;
+
+        for attnum in attnums {
+            let attno = *attnum as i32 - pgrx::pg_sys::FirstLowInvalidHeapAttributeNumber as i32;
+            (*perminfo).insertedCols =
+                pgrx::pg_sys::bms_add_member((*perminfo).insertedCols, attno);
+        }
+
+        let mut perminfos = pgrx::PgList::<pgrx::pg_sys::RTEPermissionInfo>::new();
+        perminfos.push(perminfo);
+
+        pgrx::pg_sys::ExecCheckPermissions((*p_state).p_rtable, perminfos.into_pg(), true);
+    }
+}
@@ -1,2 +1,10 @@
+// the functions of the "parquet" modules are created in the schema of the extension, which is
+// schema parquet by default, instead of a hard-coded schema (see sql/bootstrap.sql)
+pub(crate) mod async_job;
+pub(crate) mod copy_files;
 pub(crate) mod metadata;
+pub(crate) mod object_store_cache;
+pub(crate) mod on_error;
 pub(crate) mod schema;
+pub(crate) mod temp;
+pub(crate) mod validate;
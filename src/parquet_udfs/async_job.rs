@@ -0,0 +1,604 @@
+use std::{
+    ffi::{c_char, CStr},
+    panic::AssertUnwindSafe,
+    ptr,
+};
+
+use pgrx::{
+    default, ereport, extension_sql, is_a, pg_extern, pg_guard,
+    pg_sys::{
+        self, aclcheck_error, get_namespace_name, get_rel_name, get_rel_namespace, get_rel_relkind,
+        get_relkind_objtype, panic::CaughtError, pg_class_aclcheck, pg_parse_query,
+        quote_literal_cstr, quote_qualified_identifier, AbortCurrentTransaction, AclResult,
+        AsPgCStr, BackgroundWorkerInitializeConnectionByOid, BackgroundWorkerUnblockSignals,
+        BgWorkerStartTime, CommitTransactionCommand, CopyStmt, GetTopTransactionId,
+        GetTransactionSnapshot, GetUserId, GetUserIdAndSecContext, GetUserNameFromId, InvalidOid,
+        MyBgworkerEntry, MyDatabaseId, NoLock, Node, NodeTag, Oid, PopActiveSnapshot,
+        PushActiveSnapshot, RangeVarGetRelidExtended, RawStmt, RegisterDynamicBackgroundWorker,
+        SelectStmt, SetCurrentStatementStartTimestamp, SetUserIdAndSecContext,
+        StartTransactionCommand, TransactionId, XLTW_Oper, XactLockTableWait, ACL_INSERT,
+        BGWORKER_BACKEND_DATABASE_CONNECTION, BGWORKER_SHMEM_ACCESS, BGW_NEVER_RESTART,
+        SECURITY_LOCAL_USERID_CHANGE,
+    },
+    JsonB, PgBox, PgList, PgLogLevel, PgSqlErrorCode, PgTryBuilder, Spi,
+};
+
+use crate::{
+    arrow_parquet::uri_utils::{ensure_access_privilege_to_uri, parse_uri},
+    pgrx_utils::extension_schema_name,
+};
+
+// parquet.async_jobs is the registry of the COPY commands, which are run by background workers.
+// Each role only sees its own jobs. The roles cannot write the registry, the jobs are only
+// submitted, cancelled and updated by the functions below as the owner of the registry, like
+// SECURITY DEFINER functions. A job is started by its worker after the transaction, which
+// submits it, commits.
+extension_sql!(
+    r#"
+CREATE TABLE async_jobs (
+    job_id bigserial PRIMARY KEY,
+    job_type text NOT NULL,
+    command text NOT NULL,
+    role_name name NOT NULL DEFAULT current_user,
+    status text NOT NULL DEFAULT 'pending',
+    worker_pid int,
+    rows_processed bigint,
+    error text,
+    submitted_at timestamptz NOT NULL DEFAULT now(),
+    started_at timestamptz,
+    finished_at timestamptz
+);
+
+ALTER TABLE async_jobs ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY async_jobs_of_role ON async_jobs
+    USING (role_name = current_user)
+    WITH CHECK (role_name = current_user);
+
+GRANT SELECT ON async_jobs TO public;
+
+-- parquet.job_status() returns the status of an async job
+CREATE FUNCTION job_status(job_id bigint) RETURNS TABLE (
+    job_id bigint,
+    job_type text,
+    status text,
+    rows_processed bigint,
+    error text,
+    submitted_at timestamptz,
+    started_at timestamptz,
+    finished_at timestamptz
+)
+STRICT
+LANGUAGE sql
+SET search_path FROM CURRENT
+AS $$
+    SELECT j.job_id, j.job_type, j.status, j.rows_processed, j.error,
+           j.submitted_at, j.started_at, j.finished_at
+    FROM async_jobs j
+    WHERE j.job_id = $1;
+$$;
+"#,
+    name = "async_jobs"
+);
+
+mod parquet {
+    use super::*;
+
+    // export_async submits a job, which runs COPY (query) TO uri with the options
+    // in a background worker, and returns its id.
+    #[pg_extern]
+    fn export_async(query: &str, uri: &str, options: default!(JsonB, "'{}'")) -> i64 {
+        let command = format!(
+            "COPY ({}) TO {}{}",
+            select_statement_text(query),
+            quote_literal(uri),
+            copy_options_clause(&options)
+        );
+
+        validate_async_job_command("export", &command);
+
+        submit_async_job("export", &command)
+    }
+
+    // import_async submits a job, which runs COPY table FROM uri with the options
+    // in a background worker, and returns its id. The sql declares the table as regclass,
+    // since pgrx declares an Oid argument as oid.
+    #[pg_extern(sql = r#"
+CREATE FUNCTION "import_async"(
+	"table_name" regclass,
+	"uri" TEXT,
+	"options" jsonb DEFAULT '{}'
+) RETURNS bigint
+STRICT
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'import_async_wrapper';
+"#)]
+    fn import_async(table_name: Oid, uri: &str, options: default!(JsonB, "'{}'")) -> i64 {
+        // regclass accepts the oid of a dropped table
+        if unsafe { get_rel_name(table_name) }.is_null() {
+            panic!("relation with OID {} does not exist", table_name.as_u32());
+        }
+
+        let command = format!(
+            "COPY {} FROM {}{}",
+            qualified_relation_name(table_name),
+            quote_literal(uri),
+            copy_options_clause(&options)
+        );
+
+        validate_async_job_command("import", &command);
+
+        submit_async_job("import", &command)
+    }
+
+    // cancel_job cancels a pending or running async job of the current role
+    #[pg_extern]
+    fn cancel_job(job_id: i64) -> bool {
+        let role_name = current_role_name();
+
+        let async_jobs_table = async_jobs_table_name();
+
+        let cancelled = run_as_async_jobs_owner(|| {
+            Spi::connect(|mut client| {
+                client
+                    .update(
+                        &format!(
+                            "UPDATE {}
+                             SET status = 'cancelled', finished_at = now()
+                             WHERE job_id = {} AND role_name = {} AND status = 'pending'",
+                            async_jobs_table,
+                            job_id,
+                            quote_literal(&role_name)
+                        ),
+                        None,
+                        None,
+                    )
+                    .map(|result| !result.is_empty())
+            })
+        })
+        .unwrap_or_else(|e| panic!("failed to cancel parquet job {}: {}", job_id, e));
+
+        if cancelled {
+            return true;
+        }
+
+        // the worker runs as the role of the job, which the role can cancel
+        Spi::get_one::<bool>(&format!(
+            "SELECT pg_cancel_backend(worker_pid) FROM {}
+             WHERE job_id = {} AND status = 'running'",
+            async_jobs_table, job_id
+        ))
+        .unwrap_or_else(|e| panic!("failed to cancel parquet job {}: {}", job_id, e))
+        .unwrap_or(false)
+    }
+}
+
+// submit_async_job registers the job and the background worker, which runs it as the current
+// role in the current database.
+fn submit_async_job(job_type: &str, command: &str) -> i64 {
+    let role_name = current_role_name();
+
+    let async_jobs_table = async_jobs_table_name();
+
+    let job_id = run_as_async_jobs_owner(|| {
+        Spi::connect(|mut client| {
+            client
+                .update(
+                    &format!(
+                        "INSERT INTO {} (job_type, command, role_name)
+                         VALUES ({}, {}, {})
+                         RETURNING job_id",
+                        async_jobs_table,
+                        quote_literal(job_type),
+                        quote_literal(command),
+                        quote_literal(&role_name)
+                    ),
+                    None,
+                    None,
+                )?
+                .first()
+                .get_one::<i64>()
+        })
+    })
+    .unwrap_or_else(|e| panic!("failed to submit parquet job: {}", e))
+    .expect("job_id is null");
+
+    // the worker waits for the submitting transaction before it starts the job
+    let worker_extra = format!(
+        "{} {} {}",
+        unsafe { MyDatabaseId }.as_u32(),
+        unsafe { GetUserId() }.as_u32(),
+        unsafe { GetTopTransactionId() }.into_inner()
+    );
+
+    let mut worker: pg_sys::BackgroundWorker = unsafe { std::mem::zeroed() };
+
+    worker.bgw_flags = (BGWORKER_SHMEM_ACCESS | BGWORKER_BACKEND_DATABASE_CONNECTION) as _;
+    worker.bgw_start_time = BgWorkerStartTime::BgWorkerStart_RecoveryFinished;
+    worker.bgw_restart_time = BGW_NEVER_RESTART as _;
+    worker.bgw_main_arg = pg_sys::Datum::from(job_id);
+
+    copy_to_c_chars(&mut worker.bgw_name, &format!("pg_parquet job {}", job_id));
+    copy_to_c_chars(&mut worker.bgw_type, "pg_parquet job");
+    copy_to_c_chars(&mut worker.bgw_library_name, "pg_parquet");
+    copy_to_c_chars(&mut worker.bgw_function_name, "parquet_async_job_main");
+    copy_to_c_chars(&mut worker.bgw_extra, &worker_extra);
+
+    let registered = unsafe { RegisterDynamicBackgroundWorker(&mut worker, std::ptr::null_mut()) };
+
+    if !registered {
+        ereport!(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_CONFIGURATION_LIMIT_EXCEEDED,
+            "could not register background worker for parquet job",
+            "You might need to increase max_worker_processes.",
+        );
+    }
+
+    job_id
+}
+
+// parquet_async_job_main is the entry point of the background worker, which runs a job.
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn parquet_async_job_main(arg: pg_sys::Datum) {
+    let job_id = arg.value() as i64;
+
+    let worker_extra = unsafe { CStr::from_ptr((*MyBgworkerEntry).bgw_extra.as_ptr()) }
+        .to_str()
+        .expect("invalid parquet job worker extra");
+
+    let worker_extra = worker_extra
+        .split(' ')
+        .map(|value| {
+            value
+                .parse::<u32>()
+                .expect("invalid parquet job worker extra")
+        })
+        .collect::<Vec<_>>();
+
+    let [database_id, role_id, submitting_xid] = worker_extra[..] else {
+        panic!("invalid parquet job worker extra");
+    };
+
+    unsafe {
+        BackgroundWorkerUnblockSignals();
+        BackgroundWorkerInitializeConnectionByOid(database_id.into(), role_id.into(), 0);
+    }
+
+    run_in_transaction(|| unsafe {
+        XactLockTableWait(
+            TransactionId::from_inner(submitting_xid),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            XLTW_Oper::XLTW_None,
+        )
+    });
+
+    // the job is not found if the submitting transaction is rolled back, and it is not pending
+    // if it is cancelled before the worker starts
+    let Some((job_type, command)) = run_in_transaction(|| claim_async_job(job_id)) else {
+        return;
+    };
+
+    let result = PgTryBuilder::new(AssertUnwindSafe(|| {
+        Ok(run_in_transaction(|| {
+            // the command is checked again as the role of the job, whose privileges might have
+            // changed since the submission
+            validate_async_job_command(&job_type, &command);
+
+            execute_async_job_command(&command)
+        }))
+    }))
+    .catch_others(|cause| {
+        unsafe { AbortCurrentTransaction() };
+
+        let report = match &cause {
+            CaughtError::PostgresError(report)
+            | CaughtError::ErrorReport(report)
+            | CaughtError::RustPanic {
+                ereport: report, ..
+            } => report,
+        };
+
+        Err((report.sql_error_code(), report.message().to_string()))
+    })
+    .execute();
+
+    let (status, rows_processed, error) = match result {
+        Ok(rows_processed) => ("succeeded", rows_processed.to_string(), "NULL".into()),
+        Err((PgSqlErrorCode::ERRCODE_QUERY_CANCELED, message)) => {
+            ("cancelled", "NULL".into(), quote_literal(&message))
+        }
+        Err((_, message)) => ("failed", "NULL".into(), quote_literal(&message)),
+    };
+
+    run_in_transaction(|| {
+        let async_jobs_table = async_jobs_table_name();
+
+        run_as_async_jobs_owner(|| {
+            Spi::run(&format!(
+                "UPDATE {}
+                 SET status = {}, rows_processed = {}, error = {}, finished_at = now()
+                 WHERE job_id = {}",
+                async_jobs_table,
+                quote_literal(status),
+                rows_processed,
+                error,
+                job_id
+            ))
+        })
+        .unwrap_or_else(|e| panic!("failed to finish parquet job {}: {}", job_id, e))
+    });
+}
+
+// claim_async_job marks the pending job as running and returns its type and command
+fn claim_async_job(job_id: i64) -> Option<(String, String)> {
+    let async_jobs_table = async_jobs_table_name();
+
+    run_as_async_jobs_owner(|| {
+        Spi::connect(|mut client| {
+            let claimed_job = client.update(
+                &format!(
+                    "UPDATE {}
+                     SET status = 'running', worker_pid = pg_backend_pid(), started_at = now()
+                     WHERE job_id = {} AND status = 'pending'
+                     RETURNING job_type, command",
+                    async_jobs_table, job_id
+                ),
+                None,
+                None,
+            )?;
+
+            if claimed_job.is_empty() {
+                return Ok(None);
+            }
+
+            let (job_type, command) = claimed_job.first().get_two::<String, String>()?;
+
+            Ok(job_type.zip(command))
+        })
+    })
+    .unwrap_or_else(|e| panic!("failed to start parquet job {}: {}", job_id, e))
+}
+
+// async_jobs_table_name returns the qualified name of the async_jobs table in the schema of
+// the extension.
+fn async_jobs_table_name() -> String {
+    format!("{}.async_jobs", extension_schema_name())
+}
+
+// run_as_async_jobs_owner runs the function as the owner of parquet.async_jobs, as a
+// SECURITY DEFINER function does. The owner bypasses the row level security of the registry,
+// hence the statements filter the jobs themselves.
+fn run_as_async_jobs_owner<R>(f: impl FnOnce() -> R) -> R {
+    let owner_id = Spi::get_one::<Oid>(&format!(
+        "SELECT relowner FROM pg_class WHERE oid = {}::regclass",
+        quote_literal(&async_jobs_table_name())
+    ))
+    .unwrap_or_else(|e| panic!("failed to find the owner of parquet.async_jobs: {}", e))
+    .expect("owner of parquet.async_jobs is null");
+
+    let mut user_id = InvalidOid;
+    let mut sec_context = 0;
+
+    unsafe {
+        GetUserIdAndSecContext(&mut user_id, &mut sec_context);
+        SetUserIdAndSecContext(owner_id, sec_context | SECURITY_LOCAL_USERID_CHANGE as i32);
+    }
+
+    // an error aborts the (sub)transaction, which restores the user as well
+    let result = f();
+
+    unsafe { SetUserIdAndSecContext(user_id, sec_context) };
+
+    result
+}
+
+fn current_role_name() -> String {
+    let role_name = unsafe { GetUserNameFromId(GetUserId(), false) };
+
+    unsafe { CStr::from_ptr(role_name) }
+        .to_str()
+        .expect("role name is not a valid CString")
+        .to_string()
+}
+
+// select_statement_text returns the text of the query, which must be a single SELECT
+// statement, without the trailing semicolon. The query is embedded into COPY (query) TO.
+fn select_statement_text(query: &str) -> String {
+    let raw_stmts = parse_raw_statements(query);
+
+    let [raw_stmt] = raw_stmts.as_slice() else {
+        panic!("query must be a single SELECT statement");
+    };
+
+    ensure_select_statement(raw_stmt.stmt);
+
+    // stmt_len is 0 for the last statement without a semicolon
+    let start = raw_stmt.stmt_location as usize;
+    let end = if raw_stmt.stmt_len == 0 {
+        query.len()
+    } else {
+        start + raw_stmt.stmt_len as usize
+    };
+
+    query[start..end].trim().to_string()
+}
+
+// validate_async_job_command checks that the command is a single COPY statement of the job's
+// type, and checks the privileges of the current role to run it.
+fn validate_async_job_command(job_type: &str, command: &str) {
+    let raw_stmts = parse_raw_statements(command);
+
+    let [raw_stmt] = raw_stmts.as_slice() else {
+        panic!("parquet job command must be a single COPY statement");
+    };
+
+    if !unsafe { is_a(raw_stmt.stmt, NodeTag::T_CopyStmt) } {
+        panic!("parquet job command must be a single COPY statement");
+    }
+
+    let copy_stmt = unsafe { PgBox::<CopyStmt>::from_pg(raw_stmt.stmt as _) };
+
+    let copy_from = job_type == "import";
+
+    if copy_stmt.is_from != copy_from || copy_stmt.is_program || copy_stmt.filename.is_null() {
+        panic!("parquet job command is not a COPY {} a uri", job_type);
+    }
+
+    if copy_from {
+        let relation_oid = unsafe {
+            RangeVarGetRelidExtended(copy_stmt.relation, NoLock as _, 0, None, ptr::null_mut())
+        };
+
+        ensure_insert_privilege(relation_oid);
+    } else {
+        ensure_select_statement(copy_stmt.query);
+    }
+
+    let uri = unsafe { CStr::from_ptr(copy_stmt.filename) }
+        .to_str()
+        .expect("uri is not a valid CString");
+
+    ensure_access_privilege_to_uri(&parse_uri(uri), copy_from);
+}
+
+fn parse_raw_statements(query: &str) -> Vec<PgBox<RawStmt>> {
+    let raw_stmts = unsafe { PgList::<RawStmt>::from_pg(pg_parse_query(query.as_pg_cstr())) };
+
+    raw_stmts
+        .iter_ptr()
+        .map(|raw_stmt| unsafe { PgBox::from_pg(raw_stmt) })
+        .collect()
+}
+
+fn ensure_select_statement(stmt: *mut Node) {
+    let is_select = unsafe { is_a(stmt, NodeTag::T_SelectStmt) }
+        && unsafe { (*(stmt as *mut SelectStmt)).intoClause.is_null() };
+
+    if !is_select {
+        panic!("query must be a single SELECT statement");
+    }
+}
+
+// execute_async_job_command runs the COPY command of the job and returns the number of rows
+// it processed
+fn execute_async_job_command(command: &str) -> i64 {
+    unsafe {
+        pg_sys::pgstat_report_activity(pg_sys::BackendState::STATE_RUNNING, command.as_pg_cstr())
+    };
+
+    let rows_processed = Spi::connect(|mut client| {
+        client
+            .update(command, None, None)
+            .map(|result| result.len() as i64)
+    })
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    unsafe { pg_sys::pgstat_report_activity(pg_sys::BackendState::STATE_IDLE, std::ptr::null()) };
+
+    rows_processed
+}
+
+fn run_in_transaction<R>(f: impl FnOnce() -> R) -> R {
+    unsafe {
+        SetCurrentStatementStartTimestamp();
+        StartTransactionCommand();
+        PushActiveSnapshot(GetTransactionSnapshot());
+    }
+
+    let result = f();
+
+    unsafe {
+        PopActiveSnapshot();
+        CommitTransactionCommand();
+    }
+
+    result
+}
+
+// copy_options_clause converts the options, e.g. {"format": "parquet", "row_group_size": 100},
+// to the WITH clause of COPY
+fn copy_options_clause(options: &JsonB) -> String {
+    let Some(options) = options.0.as_object() else {
+        panic!("options must be a json object");
+    };
+
+    if options.is_empty() {
+        return String::new();
+    }
+
+    let options = options
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => quote_literal(value),
+                serde_json::Value::Number(value) => value.to_string(),
+                serde_json::Value::Bool(value) => value.to_string(),
+                _ => panic!("option \"{}\" must be a string, number or boolean", name),
+            };
+
+            format!("{} {}", quote_identifier(name), value)
+        })
+        .collect::<Vec<_>>();
+
+    format!(" WITH ({})", options.join(", "))
+}
+
+fn ensure_insert_privilege(relation_oid: Oid) {
+    unsafe {
+        let acl_result = pg_class_aclcheck(relation_oid, GetUserId(), ACL_INSERT as _);
+
+        if acl_result != AclResult::ACLCHECK_OK {
+            aclcheck_error(
+                acl_result,
+                get_relkind_objtype(get_rel_relkind(relation_oid)),
+                get_rel_name(relation_oid),
+            );
+        }
+    }
+}
+
+fn qualified_relation_name(relation_oid: Oid) -> String {
+    let qualified_name = unsafe {
+        quote_qualified_identifier(
+            get_namespace_name(get_rel_namespace(relation_oid)),
+            get_rel_name(relation_oid),
+        )
+    };
+
+    unsafe { CStr::from_ptr(qualified_name) }
+        .to_str()
+        .expect("relation name is not a valid CString")
+        .to_string()
+}
+
+fn quote_literal(value: &str) -> String {
+    let quoted_value = unsafe { quote_literal_cstr(value.as_pg_cstr()) };
+
+    unsafe { CStr::from_ptr(quoted_value) }
+        .to_str()
+        .expect("quoted literal is not a valid CString")
+        .to_string()
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    let quoted_identifier = unsafe { pg_sys::quote_identifier(identifier.as_pg_cstr()) };
+
+    unsafe { CStr::from_ptr(quoted_identifier) }
+        .to_str()
+        .expect("quoted identifier is not a valid CString")
+        .to_string()
+}
+
+fn copy_to_c_chars(dest: &mut [c_char], value: &str) {
+    // leaves room for the null terminator
+    let len = value.len().min(dest.len() - 1);
+
+    for (dest_char, byte) in dest.iter_mut().zip(value.bytes().take(len)) {
+        *dest_char = byte as c_char;
+    }
+
+    dest[len] = 0;
+}
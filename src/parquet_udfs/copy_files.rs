@@ -0,0 +1,59 @@
+use pgrx::{datum::Interval, iter::TableIterator, name, pg_extern};
+
+use crate::arrow_parquet::{
+    copy_files::{last_copy_files, last_copy_stats},
+    uri_utils::redact_uri,
+};
+
+mod parquet {
+    use super::*;
+
+    // last_copy_files lists the files written by the last COPY TO of the session, in the order
+    // they are written, e.g. the files of max_rows_per_file.
+    #[pg_extern]
+    fn last_copy_files() -> TableIterator<
+        'static,
+        (
+            name!(uri, String),
+            name!(row_count, i64),
+            name!(size_bytes, i64),
+        ),
+    > {
+        let rows = last_copy_files()
+            .into_iter()
+            .map(|file| (redact_uri(&file.uri), file.row_count, file.size_bytes))
+            .collect::<Vec<_>>();
+
+        TableIterator::new(rows)
+    }
+
+    // last_copy_stats sums up the files written by the last COPY TO of the session. It returns
+    // no rows when the last COPY TO failed.
+    #[pg_extern]
+    fn last_copy_stats() -> TableIterator<
+        'static,
+        (
+            name!(files, i32),
+            name!(rows, i64),
+            name!(bytes, i64),
+            name!(duration, Interval),
+        ),
+    > {
+        let rows = last_copy_stats()
+            .into_iter()
+            .map(|stats| {
+                let duration = Interval::new(0, 0, stats.duration.as_micros() as i64)
+                    .expect("duration is out of range for interval");
+
+                (
+                    stats.file_count,
+                    stats.row_count,
+                    stats.size_bytes,
+                    duration,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        TableIterator::new(rows)
+    }
+}
@@ -1,11 +1,24 @@
-use ::parquet::file::statistics::Statistics;
-use pgrx::{iter::TableIterator, name, pg_extern, pg_schema};
-
-use crate::arrow_parquet::uri_utils::{
-    ensure_access_privilege_to_uri, parquet_metadata_from_uri, parse_uri, uri_as_string,
+use ::parquet::{
+    arrow::{arrow_reader::statistics::StatisticsConverter, parquet_to_arrow_schema},
+    basic::SortOrder,
+    file::statistics::{Statistics, ValueStatistics},
+};
+use arrow::{
+    array::ArrayRef,
+    compute::{sort_to_indices, SortOptions},
+};
+use arrow_cast::display::array_value_to_string;
+use pgrx::{default, iter::TableIterator, name, pg_extern, JsonB};
+
+use crate::{
+    arrow_parquet::uri_utils::{
+        ensure_access_privilege_to_uri, parquet_footer_tail_from_uri, parquet_metadata_from_uri,
+        parquet_metadata_with_page_index_from_uri, parse_uri, redact_uri,
+    },
+    object_store::aws::parse_s3_options,
+    parquet_udfs::on_error::{collect_rows_on_error, parse_on_error},
 };
 
-#[pg_schema]
 mod parquet {
     use super::*;
 
@@ -13,6 +26,8 @@ mod parquet {
     #[allow(clippy::type_complexity)]
     fn metadata(
         uri: String,
+        on_error: default!(String, "'raise'"),
+        options: default!(JsonB, "'{}'"),
     ) -> TableIterator<
         'static,
         (
@@ -37,137 +52,431 @@ mod parquet {
             name!(data_page_offset, i64),
             name!(total_compressed_size, i64),
             name!(total_uncompressed_size, i64),
+            name!(bloom_filter_offset, Option<i64>),
+            name!(bloom_filter_length, Option<i64>),
         ),
     > {
         let uri = parse_uri(&uri);
 
         ensure_access_privilege_to_uri(&uri, true);
-        let parquet_metadata = parquet_metadata_from_uri(&uri);
 
-        let mut rows = vec![];
+        let options = parse_s3_options(options.0);
+
+        let rows = collect_rows_on_error(&uri, parse_on_error(&on_error), || {
+            let parquet_metadata = parquet_metadata_from_uri(&uri, options.as_ref());
+
+            let mut rows = vec![];
+
+            for (row_group_id, row_group) in parquet_metadata.row_groups().iter().enumerate() {
+                let row_group_num_rows = row_group.num_rows();
+                let row_group_num_columns = row_group.num_columns() as i64;
+                let row_group_bytes = row_group.total_byte_size();
+
+                for (column_id, column) in row_group.columns().iter().enumerate() {
+                    let file_offset = column.file_offset();
+
+                    let num_values = column.num_values();
+
+                    let path_in_schema = column.column_path().string();
+
+                    let type_name = column.column_type().to_string();
+
+                    let mut stats_min = None;
+                    let mut stats_max = None;
+                    let mut stats_null_count = None;
+                    let mut stats_distinct_count = None;
+
+                    if let Some(statistics) = column.statistics() {
+                        stats_min = stats_min_value_to_str(statistics);
+
+                        stats_max = stats_max_value_to_str(statistics);
+
+                        stats_null_count = statistics.null_count_opt().map(|v| v as i64);
+
+                        stats_distinct_count = statistics.distinct_count_opt().map(|v| v as i64);
+                    }
+
+                    let compression = column.compression().to_string();
 
-        for (row_group_id, row_group) in parquet_metadata.row_groups().iter().enumerate() {
-            let row_group_num_rows = row_group.num_rows();
-            let row_group_num_columns = row_group.num_columns() as i64;
-            let row_group_bytes = row_group.total_byte_size();
+                    let encodings = column
+                        .encodings()
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",");
 
-            for (column_id, column) in row_group.columns().iter().enumerate() {
-                let file_offset = column.file_offset();
+                    let index_page_offset = column.index_page_offset();
 
-                let num_values = column.num_values();
+                    let dictionary_page_offset = column.dictionary_page_offset();
 
-                let path_in_schema = column.column_path().string();
+                    let data_page_offset = column.data_page_offset();
 
-                let type_name = column.column_type().to_string();
+                    let total_compressed_size = column.compressed_size();
 
-                let mut stats_min = None;
-                let mut stats_max = None;
-                let mut stats_null_count = None;
-                let mut stats_distinct_count = None;
+                    let total_uncompressed_size = column.uncompressed_size();
 
-                if let Some(statistics) = column.statistics() {
-                    stats_min = stats_min_value_to_str(statistics);
+                    let bloom_filter_offset = column.bloom_filter_offset();
 
-                    stats_max = stats_max_value_to_str(statistics);
+                    let bloom_filter_length = column.bloom_filter_length().map(|v| v as i64);
+
+                    let row = (
+                        redact_uri(&uri),
+                        row_group_id as i64,
+                        row_group_num_rows,
+                        row_group_num_columns,
+                        row_group_bytes,
+                        column_id as i64,
+                        file_offset,
+                        num_values,
+                        path_in_schema,
+                        type_name,
+                        stats_null_count,
+                        stats_distinct_count,
+                        stats_min,
+                        stats_max,
+                        compression,
+                        encodings,
+                        index_page_offset,
+                        dictionary_page_offset,
+                        data_page_offset,
+                        total_compressed_size,
+                        total_uncompressed_size,
+                        bloom_filter_offset,
+                        bloom_filter_length,
+                    );
+
+                    rows.push(row);
+                }
+            }
+
+            rows
+        });
+
+        TableIterator::new(rows)
+    }
+
+    // column_chunk_metadata returns the page layout of each column chunk from the offset
+    // index of the file. The page columns are NULL when the file does not have an offset index.
+    #[pg_extern]
+    #[allow(clippy::type_complexity)]
+    fn column_chunk_metadata(
+        uri: String,
+        on_error: default!(String, "'raise'"),
+        options: default!(JsonB, "'{}'"),
+    ) -> TableIterator<
+        'static,
+        (
+            name!(uri, String),
+            name!(row_group_id, i64),
+            name!(column_id, i64),
+            name!(path_in_schema, String),
+            name!(num_rows, i64),
+            name!(num_values, i64),
+            name!(num_data_pages, Option<i64>),
+            name!(max_page_num_rows, Option<i64>),
+            name!(max_page_compressed_size, Option<i64>),
+        ),
+    > {
+        let uri = parse_uri(&uri);
 
-                    stats_null_count = statistics.null_count_opt().map(|v| v as i64);
+        ensure_access_privilege_to_uri(&uri, true);
 
-                    stats_distinct_count = statistics.distinct_count_opt().map(|v| v as i64);
+        let options = parse_s3_options(options.0);
+
+        let rows = collect_rows_on_error(&uri, parse_on_error(&on_error), || {
+            let parquet_metadata =
+                parquet_metadata_with_page_index_from_uri(&uri, options.as_ref());
+
+            let offset_index = parquet_metadata.offset_index();
+
+            let mut rows = vec![];
+
+            for (row_group_id, row_group) in parquet_metadata.row_groups().iter().enumerate() {
+                let num_rows = row_group.num_rows();
+
+                for (column_id, column) in row_group.columns().iter().enumerate() {
+                    let page_locations = offset_index
+                        .and_then(|offset_index| offset_index.get(row_group_id))
+                        .and_then(|column_indexes| column_indexes.get(column_id))
+                        .map(|column_index| column_index.page_locations());
+
+                    let num_data_pages =
+                        page_locations.map(|page_locations| page_locations.len() as i64);
+
+                    // a page ends where the next page, or the row group, starts
+                    let max_page_num_rows = page_locations.and_then(|page_locations| {
+                        page_locations
+                            .iter()
+                            .map(|page| page.first_row_index)
+                            .chain(std::iter::once(num_rows))
+                            .collect::<Vec<_>>()
+                            .windows(2)
+                            .map(|rows| rows[1] - rows[0])
+                            .max()
+                    });
+
+                    let max_page_compressed_size = page_locations.and_then(|page_locations| {
+                        page_locations
+                            .iter()
+                            .map(|page| page.compressed_page_size as i64)
+                            .max()
+                    });
+
+                    let row = (
+                        redact_uri(&uri),
+                        row_group_id as i64,
+                        column_id as i64,
+                        column.column_path().string(),
+                        num_rows,
+                        column.num_values(),
+                        num_data_pages,
+                        max_page_num_rows,
+                        max_page_compressed_size,
+                    );
+
+                    rows.push(row);
                 }
+            }
+
+            rows
+        });
 
-                let compression = column.compression().to_string();
+        TableIterator::new(rows)
+    }
 
-                let encodings = column
-                    .encodings()
+    // column_stats rolls up the row group statistics of each leaf column of the file. min and
+    // max are NULL when a row group with values does not have them. distinct_count is the sum
+    // over the row groups, hence an upper bound, and NULL unless all row groups have it.
+    #[pg_extern]
+    #[allow(clippy::type_complexity)]
+    fn column_stats(
+        uri: String,
+        on_error: default!(String, "'raise'"),
+        options: default!(JsonB, "'{}'"),
+    ) -> TableIterator<
+        'static,
+        (
+            name!(uri, String),
+            name!(column_id, i64),
+            name!(path_in_schema, String),
+            name!(type_name, String),
+            name!(stats_min, Option<String>),
+            name!(stats_max, Option<String>),
+            name!(stats_null_count, Option<i64>),
+            name!(stats_distinct_count, Option<i64>),
+            name!(num_values, i64),
+            name!(total_compressed_size, i64),
+            name!(total_uncompressed_size, i64),
+        ),
+    > {
+        let uri = parse_uri(&uri);
+
+        ensure_access_privilege_to_uri(&uri, true);
+
+        let options = parse_s3_options(options.0);
+
+        let rows = collect_rows_on_error(&uri, parse_on_error(&on_error), || {
+            let parquet_metadata = parquet_metadata_from_uri(&uri, options.as_ref());
+            let file_metadata = parquet_metadata.file_metadata();
+            let schema_descr = file_metadata.schema_descr();
+            let row_groups = parquet_metadata.row_groups();
+
+            let arrow_schema =
+                parquet_to_arrow_schema(schema_descr, file_metadata.key_value_metadata())
+                    .unwrap_or_else(|e| {
+                        panic!("failed to convert parquet schema to arrow schema: {}", e)
+                    });
+
+            let mut rows = vec![];
+
+            for (column_id, column_descr) in schema_descr.columns().iter().enumerate() {
+                let columns = row_groups
                     .iter()
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",");
+                    .map(|row_group| row_group.column(column_id))
+                    .collect::<Vec<_>>();
 
-                let index_page_offset = column.index_page_offset();
+                let num_values = columns
+                    .iter()
+                    .map(|column| column.num_values())
+                    .sum::<i64>();
 
-                let dictionary_page_offset = column.dictionary_page_offset();
+                let total_compressed_size = columns
+                    .iter()
+                    .map(|column| column.compressed_size())
+                    .sum::<i64>();
 
-                let data_page_offset = column.data_page_offset();
+                let total_uncompressed_size = columns
+                    .iter()
+                    .map(|column| column.uncompressed_size())
+                    .sum::<i64>();
 
-                let total_compressed_size = column.compressed_size();
+                let stats_null_count = columns
+                    .iter()
+                    .map(|column| column.statistics().and_then(|s| s.null_count_opt()))
+                    .sum::<Option<u64>>()
+                    .map(|v| v as i64);
 
-                let total_uncompressed_size = column.uncompressed_size();
+                let stats_distinct_count = columns
+                    .iter()
+                    .map(|column| column.statistics().and_then(|s| s.distinct_count_opt()))
+                    .sum::<Option<u64>>()
+                    .map(|v| v as i64);
+
+                // a row group without min and max is skipped only if all of its values are null
+                let has_min_max = columns.iter().all(|column| match column.statistics() {
+                    Some(statistics) if statistics.min_bytes_opt().is_some() => {
+                        statistics.max_bytes_opt().is_some()
+                    }
+                    Some(statistics) => {
+                        statistics.null_count_opt() == Some(column.num_values() as u64)
+                    }
+                    None => column.num_values() == 0,
+                });
+
+                let (stats_min, stats_max) = if !has_min_max {
+                    (None, None)
+                } else {
+                    // top level columns are rendered via their arrow type, nested leaves via
+                    // their physical type
+                    let root_field = arrow_schema
+                        .field(schema_descr.get_column_root_idx(column_id))
+                        .name();
+
+                    match StatisticsConverter::try_new(root_field, &arrow_schema, schema_descr) {
+                        Ok(converter) if converter.parquet_column_index() == Some(column_id) => (
+                            converter
+                                .row_group_mins(row_groups)
+                                .ok()
+                                .and_then(|mins| merged_arrow_stat(&mins, false)),
+                            converter
+                                .row_group_maxes(row_groups)
+                                .ok()
+                                .and_then(|maxes| merged_arrow_stat(&maxes, true)),
+                        ),
+                        _ => {
+                            let statistics = columns
+                                .iter()
+                                .filter_map(|column| column.statistics())
+                                .filter(|statistics| statistics.min_bytes_opt().is_some())
+                                .collect::<Vec<_>>();
+
+                            let sort_order = column_descr.sort_order();
+
+                            (
+                                merged_physical_stat(&statistics, sort_order, false),
+                                merged_physical_stat(&statistics, sort_order, true),
+                            )
+                        }
+                    }
+                };
 
                 let row = (
-                    uri_as_string(&uri),
-                    row_group_id as i64,
-                    row_group_num_rows,
-                    row_group_num_columns,
-                    row_group_bytes,
+                    redact_uri(&uri),
                     column_id as i64,
-                    file_offset,
-                    num_values,
-                    path_in_schema,
-                    type_name,
-                    stats_null_count,
-                    stats_distinct_count,
+                    column_descr.path().string(),
+                    column_descr.physical_type().to_string(),
                     stats_min,
                     stats_max,
-                    compression,
-                    encodings,
-                    index_page_offset,
-                    dictionary_page_offset,
-                    data_page_offset,
+                    stats_null_count,
+                    stats_distinct_count,
+                    num_values,
                     total_compressed_size,
                     total_uncompressed_size,
                 );
 
                 rows.push(row);
             }
-        }
+
+            rows
+        });
 
         TableIterator::new(rows)
     }
 
     #[pg_extern]
+    #[allow(clippy::type_complexity)]
     fn file_metadata(
         uri: String,
+        on_error: default!(String, "'raise'"),
+        options: default!(JsonB, "'{}'"),
     ) -> TableIterator<
         'static,
         (
             name!(uri, String),
             name!(created_by, Option<String>),
-            name!(num_rows, i64),
-            name!(num_row_groups, i64),
-            name!(format_version, String),
+            name!(num_rows, Option<i64>),
+            name!(num_row_groups, Option<i64>),
+            name!(format_version, Option<String>),
+            name!(total_byte_size, Option<i64>),
+            name!(footer_size, i64),
+            name!(footer_encrypted, bool),
         ),
     > {
         let uri = parse_uri(&uri);
 
         ensure_access_privilege_to_uri(&uri, true);
-        let parquet_metadata = parquet_metadata_from_uri(&uri);
 
-        let created_by = parquet_metadata
-            .file_metadata()
-            .created_by()
-            .map(|c| c.to_string());
+        let options = parse_s3_options(options.0);
+
+        let rows = collect_rows_on_error(&uri, parse_on_error(&on_error), || {
+            let footer_tail = parquet_footer_tail_from_uri(&uri, options.as_ref());
+
+            // encrypted footers cannot be decoded, hence their fields are null
+            if footer_tail.encrypted {
+                let row = (
+                    redact_uri(&uri),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    footer_tail.footer_size,
+                    footer_tail.encrypted,
+                );
+
+                return vec![row];
+            }
+
+            let parquet_metadata = parquet_metadata_from_uri(&uri, options.as_ref());
 
-        let num_rows = parquet_metadata.file_metadata().num_rows();
+            let created_by = parquet_metadata
+                .file_metadata()
+                .created_by()
+                .map(|c| c.to_string());
 
-        let num_row_groups = parquet_metadata.num_row_groups() as i64;
+            let num_rows = parquet_metadata.file_metadata().num_rows();
 
-        let format_version = parquet_metadata.file_metadata().version().to_string();
+            let num_row_groups = parquet_metadata.num_row_groups() as i64;
 
-        let row = (
-            uri_as_string(&uri),
-            created_by,
-            num_rows,
-            num_row_groups,
-            format_version,
-        );
+            let format_version = parquet_metadata.file_metadata().version().to_string();
 
-        TableIterator::new(vec![row])
+            let total_byte_size = parquet_metadata
+                .row_groups()
+                .iter()
+                .map(|row_group| row_group.compressed_size())
+                .sum::<i64>();
+
+            let row = (
+                redact_uri(&uri),
+                created_by,
+                Some(num_rows),
+                Some(num_row_groups),
+                Some(format_version),
+                Some(total_byte_size),
+                footer_tail.footer_size,
+                footer_tail.encrypted,
+            );
+
+            vec![row]
+        });
+
+        TableIterator::new(rows)
     }
 
     #[pg_extern]
     fn kv_metadata(
         uri: String,
+        options: default!(JsonB, "'{}'"),
     ) -> TableIterator<
         'static,
         (
@@ -179,7 +488,9 @@ mod parquet {
         let uri = parse_uri(&uri);
 
         ensure_access_privilege_to_uri(&uri, true);
-        let parquet_metadata = parquet_metadata_from_uri(&uri);
+
+        let options = parse_s3_options(options.0);
+        let parquet_metadata = parquet_metadata_from_uri(&uri, options.as_ref());
         let kv_metadata = parquet_metadata.file_metadata().key_value_metadata();
 
         if kv_metadata.is_none() {
@@ -194,7 +505,7 @@ mod parquet {
             let key = kv.key.as_bytes().to_owned();
             let value = kv.value.as_ref().map(|v| v.as_bytes().to_owned());
 
-            let row = (uri_as_string(&uri), key, value);
+            let row = (redact_uri(&uri), key, value);
 
             rows.push(row);
         }
@@ -203,6 +514,111 @@ mod parquet {
     }
 }
 
+// merged_arrow_stat returns the smallest, or the largest if descending, of the row group
+// statistics of a column, which StatisticsConverter read as the arrow type of the column.
+fn merged_arrow_stat(row_group_stats: &ArrayRef, descending: bool) -> Option<String> {
+    let options = SortOptions {
+        descending,
+        nulls_first: false,
+    };
+
+    let indices = sort_to_indices(row_group_stats, Some(options), Some(1)).ok()?;
+
+    let idx = indices.iter().next().flatten()? as usize;
+
+    if row_group_stats.is_null(idx) {
+        return None;
+    }
+
+    array_value_to_string(row_group_stats, idx).ok()
+}
+
+// StatSortKey is the physical min or max value of a row group, by which the statistics of
+// a nested leaf are merged.
+#[derive(PartialEq, PartialOrd)]
+enum StatSortKey<'a> {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Bytes(&'a [u8]),
+}
+
+fn stat_value<T>(statistics: &ValueStatistics<T>, max: bool) -> Option<&T> {
+    if max {
+        statistics.max_opt()
+    } else {
+        statistics.min_opt()
+    }
+}
+
+// stat_sort_key returns None for the values, whose sort order is not known, e.g. INT96
+// timestamps and legacy decimals.
+fn stat_sort_key(statistics: &Statistics, sort_order: SortOrder, max: bool) -> Option<StatSortKey> {
+    match (statistics, sort_order) {
+        (Statistics::Boolean(s), SortOrder::UNSIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Unsigned(*v as u64))
+        }
+        (Statistics::Int32(s), SortOrder::SIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Signed(*v as i64))
+        }
+        (Statistics::Int32(s), SortOrder::UNSIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Unsigned(*v as u32 as u64))
+        }
+        (Statistics::Int64(s), SortOrder::SIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Signed(*v))
+        }
+        (Statistics::Int64(s), SortOrder::UNSIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Unsigned(*v as u64))
+        }
+        (Statistics::Float(s), SortOrder::SIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Float(*v as f64))
+        }
+        (Statistics::Double(s), SortOrder::SIGNED) => {
+            stat_value(s, max).map(|v| StatSortKey::Float(*v))
+        }
+        (Statistics::ByteArray(_) | Statistics::FixedLenByteArray(_), SortOrder::UNSIGNED) => {
+            if max {
+                statistics.max_bytes_opt().map(StatSortKey::Bytes)
+            } else {
+                statistics.min_bytes_opt().map(StatSortKey::Bytes)
+            }
+        }
+        _ => None,
+    }
+}
+
+// merged_physical_stat returns the smallest min, or the largest max if max, of the row group
+// statistics of a nested leaf.
+fn merged_physical_stat(
+    row_group_stats: &[&Statistics],
+    sort_order: SortOrder,
+    max: bool,
+) -> Option<String> {
+    let mut merged: Option<(&Statistics, StatSortKey)> = None;
+
+    for statistics in row_group_stats.iter().copied() {
+        let key = stat_sort_key(statistics, sort_order, max)?;
+
+        let replace = match &merged {
+            None => true,
+            Some((_, merged_key)) if max => key > *merged_key,
+            Some((_, merged_key)) => key < *merged_key,
+        };
+
+        if replace {
+            merged = Some((statistics, key));
+        }
+    }
+
+    let (statistics, _) = merged?;
+
+    if max {
+        stats_max_value_to_str(statistics)
+    } else {
+        stats_min_value_to_str(statistics)
+    }
+}
+
 fn stats_min_value_to_str(statistics: &Statistics) -> Option<String> {
     match &statistics {
         Statistics::Boolean(val_stats) => val_stats.min_opt().map(|v| v.to_string()),
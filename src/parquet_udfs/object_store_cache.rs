@@ -0,0 +1,62 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pgrx::{datum::TimestampWithTimeZone, iter::TableIterator, name, pg_extern};
+
+use crate::{
+    arrow_parquet::uri_utils::ensure_object_store_cache_privilege,
+    object_store::object_store_cache::{clear_object_store_cache, object_store_cache_items},
+    type_compat::pg_arrow_type_conversions::i64_to_timestamptz,
+};
+
+mod parquet {
+    use super::*;
+
+    // object_store_cache lists the object stores, and the http clients of http(s) uris, which
+    // are cached in the session.
+    #[pg_extern]
+    fn object_store_cache() -> TableIterator<
+        'static,
+        (
+            name!(scheme, String),
+            name!(bucket, String),
+            name!(created_at, TimestampWithTimeZone),
+            name!(expire_at, Option<TimestampWithTimeZone>),
+            name!(hit_count, i64),
+        ),
+    > {
+        ensure_object_store_cache_privilege();
+
+        let rows = object_store_cache_items()
+            .into_iter()
+            .map(|item| {
+                (
+                    item.scheme,
+                    item.bucket,
+                    system_time_to_timestamptz(item.created_at),
+                    item.expire_at.map(system_time_to_timestamptz),
+                    item.hit_count,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        TableIterator::new(rows)
+    }
+
+    // object_store_cache_clear removes the cached object stores and http clients, so that
+    // they are recreated with the current credentials by the next command.
+    #[pg_extern]
+    fn object_store_cache_clear() {
+        ensure_object_store_cache_privilege();
+
+        clear_object_store_cache();
+    }
+}
+
+fn system_time_to_timestamptz(time: SystemTime) -> TimestampWithTimeZone {
+    let micros_since_unix_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .as_micros() as i64;
+
+    i64_to_timestamptz(micros_since_unix_epoch, "UTC")
+}
@@ -0,0 +1,103 @@
+use std::{panic::AssertUnwindSafe, str::FromStr};
+
+use pgrx::{
+    ereport,
+    pg_sys::{
+        panic::CaughtError, BeginInternalSubTransaction, CurrentMemoryContext,
+        CurrentResourceOwner, MemoryContextSwitchTo, ReleaseCurrentSubTransaction,
+        RollbackAndReleaseCurrentSubTransaction,
+    },
+    PgLogLevel, PgSqlErrorCode, PgTryBuilder,
+};
+use url::Url;
+
+use crate::arrow_parquet::uri_utils::{redact_uri, redact_uri_secrets};
+
+// OnError determines how the parquet functions handle the errors while reading a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum OnError {
+    #[default]
+    Raise,
+    // reports the error as a warning and returns no rows for the file
+    Skip,
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "raise" => Ok(OnError::Raise),
+            "skip" => Ok(OnError::Skip),
+            _ => Err(format!("unrecognized on_error option: {}", s)),
+        }
+    }
+}
+
+pub(crate) fn parse_on_error(on_error: &str) -> OnError {
+    OnError::from_str(on_error).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// collect_rows_on_error collects the rows of a parquet function for the file at the given uri.
+// With on_error 'skip', an error while collecting the rows is reported as a warning and no rows
+// are returned, so that a query, e.g. a lateral join over many files, continues with the
+// remaining files.
+pub(crate) fn collect_rows_on_error<T>(
+    uri: &Url,
+    on_error: OnError,
+    collect_rows: impl FnOnce() -> Vec<T>,
+) -> Vec<T> {
+    if on_error == OnError::Raise {
+        return collect_rows();
+    }
+
+    // rows are collected in a subtransaction, which is rolled back on error, to release
+    // the resources acquired until the error as plpgsql exception blocks do
+    let memory_context = unsafe { CurrentMemoryContext };
+    let resource_owner = unsafe { CurrentResourceOwner };
+
+    unsafe {
+        BeginInternalSubTransaction(std::ptr::null());
+        MemoryContextSwitchTo(memory_context);
+    }
+
+    PgTryBuilder::new(AssertUnwindSafe(|| {
+        let rows = collect_rows();
+
+        unsafe {
+            ReleaseCurrentSubTransaction();
+            MemoryContextSwitchTo(memory_context);
+            CurrentResourceOwner = resource_owner;
+        }
+
+        rows
+    }))
+    .catch_others(|cause| {
+        unsafe {
+            MemoryContextSwitchTo(memory_context);
+            RollbackAndReleaseCurrentSubTransaction();
+            CurrentResourceOwner = resource_owner;
+        }
+
+        let message = match &cause {
+            CaughtError::PostgresError(report)
+            | CaughtError::ErrorReport(report)
+            | CaughtError::RustPanic {
+                ereport: report, ..
+            } => report.message().to_string(),
+        };
+
+        ereport!(
+            PgLogLevel::WARNING,
+            PgSqlErrorCode::ERRCODE_WARNING,
+            format!(
+                "skipping {}: {}",
+                redact_uri(uri),
+                redact_uri_secrets(&message, uri)
+            ),
+        );
+
+        vec![]
+    })
+    .execute()
+}
@@ -1,14 +1,25 @@
-use crate::arrow_parquet::uri_utils::{
-    ensure_access_privilege_to_uri, parquet_schema_from_uri, parse_uri, uri_as_string,
+use std::collections::HashSet;
+
+use crate::{
+    arrow_parquet::{
+        schema_parser::{postgres_type_name_for_arrow_field, ARROW_EXTENSION_NAME_META_KEY},
+        uri_utils::{
+            ensure_access_privilege_to_uri, parquet_metadata_from_uri, parse_uri, redact_uri,
+        },
+    },
+    object_store::aws::parse_s3_options,
+    parquet_udfs::on_error::{collect_rows_on_error, parse_on_error},
+    type_compat::geometry::GEOARROW_WKB_EXTENSION_NAME,
 };
 
 use ::parquet::{
-    format::{ConvertedType, FieldRepetitionType, LogicalType, Type},
+    arrow::parquet_to_arrow_schema,
+    format::{ConvertedType, FieldRepetitionType, LogicalType, SchemaElement, Type},
     schema::types::to_thrift,
 };
-use pgrx::{iter::TableIterator, name, pg_extern, pg_schema};
+use arrow_schema::{DataType, Field};
+use pgrx::{default, iter::TableIterator, name, pg_extern, JsonB};
 
-#[pg_schema]
 mod parquet {
     use super::*;
 
@@ -16,10 +27,19 @@ mod parquet {
     #[allow(clippy::type_complexity)]
     fn schema(
         uri: String,
+        on_error: default!(String, "'raise'"),
+        options: default!(JsonB, "'{}'"),
     ) -> TableIterator<
         'static,
         (
             name!(uri, String),
+            name!(field_path, Vec<String>),
+            name!(parquet_type, Option<String>),
+            name!(logical_type, Option<String>),
+            name!(repetition, Option<String>),
+            name!(postgres_type, Option<String>),
+            name!(nullable, bool),
+            name!(field_id, Option<i32>),
             name!(name, String),
             name!(type_name, Option<String>),
             name!(type_length, Option<String>),
@@ -28,66 +48,272 @@ mod parquet {
             name!(converted_type, Option<String>),
             name!(scale, Option<i32>),
             name!(precision, Option<i32>),
-            name!(field_id, Option<i32>),
-            name!(logical_type, Option<String>),
         ),
     > {
         let uri = parse_uri(&uri);
 
         ensure_access_privilege_to_uri(&uri, true);
-        let parquet_schema = parquet_schema_from_uri(&uri);
 
-        let root_type = parquet_schema.root_schema();
-        let thrift_schema_elements = to_thrift(root_type).unwrap_or_else(|e| {
-            panic!("Failed to convert schema to thrift: {}", e);
+        let options = parse_s3_options(options.0);
+
+        let rows = collect_rows_on_error(&uri, parse_on_error(&on_error), || {
+            let parquet_metadata = parquet_metadata_from_uri(&uri, options.as_ref());
+            let file_metadata = parquet_metadata.file_metadata();
+
+            // postgres types are suggested from the arrow schema,
+            // which COPY FROM reads the file with
+            let arrow_schema = parquet_to_arrow_schema(
+                file_metadata.schema_descr(),
+                file_metadata.key_value_metadata(),
+            )
+            .unwrap_or_else(|e| panic!("failed to convert parquet schema to arrow schema: {}", e));
+
+            let geometry_columns =
+                geometry_columns_from_kv_metadata(file_metadata.key_value_metadata());
+
+            let root_type = file_metadata.schema_descr().root_schema();
+            let thrift_schema_elements = to_thrift(root_type).unwrap_or_else(|e| {
+                panic!("Failed to convert schema to thrift: {}", e);
+            });
+
+            let mut schema_fields = vec![];
+
+            // the first element is the root of the schema, whose children are the top level fields
+            let mut elem_idx = 1;
+
+            for field in arrow_schema.fields() {
+                let arrow_type =
+                    if geometry_columns.contains(field.name()) || is_geoarrow_wkb_field(field) {
+                        ArrowSchemaNode::Geometry
+                    } else {
+                        ArrowSchemaNode::Field(field)
+                    };
+
+                collect_schema_fields(
+                    &thrift_schema_elements,
+                    &mut elem_idx,
+                    &[],
+                    arrow_type,
+                    &mut schema_fields,
+                );
+            }
+
+            schema_fields
+                .into_iter()
+                .map(|schema_field| {
+                    (
+                        redact_uri(&uri),
+                        schema_field.field_path,
+                        schema_field.parquet_type,
+                        schema_field.logical_type,
+                        schema_field.repetition,
+                        schema_field.postgres_type,
+                        schema_field.nullable,
+                        schema_field.field_id,
+                        schema_field.name,
+                        schema_field.type_name,
+                        schema_field.type_length,
+                        schema_field.repetition_type,
+                        schema_field.num_children,
+                        schema_field.converted_type,
+                        schema_field.scale,
+                        schema_field.precision,
+                    )
+                })
+                .collect::<Vec<_>>()
         });
 
-        let mut rows = vec![];
+        TableIterator::new(rows)
+    }
+}
+
+struct SchemaField {
+    field_path: Vec<String>,
+    parquet_type: Option<String>,
+    logical_type: Option<String>,
+    repetition: Option<String>,
+    postgres_type: Option<String>,
+    nullable: bool,
+    field_id: Option<i32>,
 
-        for schema_elem in thrift_schema_elements {
-            let name = schema_elem.name;
+    // the columns of the previous parquet.schema(), which are kept for existing callers
+    name: String,
+    type_name: Option<String>,
+    type_length: Option<String>,
+    repetition_type: Option<String>,
+    num_children: Option<i32>,
+    converted_type: Option<String>,
+    scale: Option<i32>,
+    precision: Option<i32>,
+}
 
-            let type_name = schema_elem.type_.map(thrift_type_to_str);
+// ArrowSchemaNode is the arrow counterpart of a parquet schema element.
+#[derive(Clone, Copy)]
+enum ArrowSchemaNode<'a> {
+    Field(&'a Field),
+    // repeated group between a list and its element, which has no arrow counterpart
+    ListWrapper(&'a Field),
+    Geometry,
+    Unknown,
+}
 
-            let type_length = schema_elem.type_length.map(|t| t.to_string());
+// collect_schema_fields collects the schema element at "elem_idx" and all of its descendants
+// into "schema_fields". The elements are in depth-first order as in the parquet file footer.
+fn collect_schema_fields(
+    schema_elements: &[SchemaElement],
+    elem_idx: &mut usize,
+    parent_path: &[String],
+    arrow_node: ArrowSchemaNode,
+    schema_fields: &mut Vec<SchemaField>,
+) {
+    let schema_elem = &schema_elements[*elem_idx];
+    *elem_idx += 1;
 
-            let repetition_type = schema_elem
-                .repetition_type
-                .map(thrift_repetition_type_to_str);
+    let mut field_path = parent_path.to_vec();
+    field_path.push(schema_elem.name.clone());
 
-            let num_children = schema_elem.num_children;
+    let parquet_type = schema_elem
+        .type_
+        .map(|type_| match schema_elem.type_length {
+            Some(type_length) if type_ == Type::FIXED_LEN_BYTE_ARRAY => {
+                format!("{}({})", thrift_type_to_str(type_), type_length)
+            }
+            _ => thrift_type_to_str(type_),
+        });
 
-            let converted_type = schema_elem.converted_type.map(thrift_converted_type_to_str);
+    // legacy files might only have the converted type
+    let logical_type = schema_elem
+        .logical_type
+        .clone()
+        .map(thrift_logical_type_to_str)
+        .or_else(|| schema_elem.converted_type.map(thrift_converted_type_to_str));
 
-            let scale = schema_elem.scale;
+    let repetition = schema_elem
+        .repetition_type
+        .map(thrift_repetition_type_to_str);
 
-            let precision = schema_elem.precision;
+    let nullable = schema_elem.repetition_type == Some(FieldRepetitionType::OPTIONAL);
 
-            let field_id = schema_elem.field_id;
+    let postgres_type = match arrow_node {
+        ArrowSchemaNode::Field(field) => postgres_type_name_for_arrow_field(field),
+        ArrowSchemaNode::Geometry => Some("geometry".into()),
+        ArrowSchemaNode::ListWrapper(_) | ArrowSchemaNode::Unknown => None,
+    };
 
-            let logical_type = schema_elem.logical_type.map(thrift_logical_type_to_str);
+    schema_fields.push(SchemaField {
+        field_path: field_path.clone(),
+        parquet_type,
+        logical_type,
+        repetition: repetition.clone(),
+        postgres_type,
+        nullable,
+        field_id: schema_elem.field_id,
+        name: schema_elem.name.clone(),
+        type_name: schema_elem.type_.map(thrift_type_to_str),
+        type_length: schema_elem.type_length.map(|t| t.to_string()),
+        repetition_type: repetition,
+        num_children: schema_elem.num_children,
+        converted_type: schema_elem.converted_type.map(thrift_converted_type_to_str),
+        scale: schema_elem.scale,
+        precision: schema_elem.precision,
+    });
 
-            let row = (
-                uri_as_string(&uri),
-                name,
-                type_name,
-                type_length,
-                repetition_type,
-                num_children,
-                converted_type,
-                scale,
-                precision,
-                field_id,
-                logical_type,
-            );
+    let num_children = schema_elem.num_children.unwrap_or(0) as usize;
 
-            rows.push(row);
+    for child_idx in 0..num_children {
+        let child_arrow_node = child_arrow_schema_node(
+            schema_elements,
+            *elem_idx,
+            &schema_elem.name,
+            arrow_node,
+            child_idx,
+            num_children,
+        );
+
+        collect_schema_fields(
+            schema_elements,
+            elem_idx,
+            &field_path,
+            child_arrow_node,
+            schema_fields,
+        );
+    }
+}
+
+// child_arrow_schema_node returns the arrow counterpart of the child element at "child_elem_idx".
+fn child_arrow_schema_node<'a>(
+    schema_elements: &[SchemaElement],
+    child_elem_idx: usize,
+    parent_name: &str,
+    parent_arrow_node: ArrowSchemaNode<'a>,
+    child_idx: usize,
+    num_children: usize,
+) -> ArrowSchemaNode<'a> {
+    let parent_field = match parent_arrow_node {
+        ArrowSchemaNode::Field(parent_field) => parent_field,
+        ArrowSchemaNode::ListWrapper(element_field) if num_children == 1 => {
+            return ArrowSchemaNode::Field(element_field);
         }
+        _ => return ArrowSchemaNode::Unknown,
+    };
 
-        TableIterator::new(rows)
+    match parent_field.data_type() {
+        DataType::Struct(fields) if fields.len() == num_children => {
+            ArrowSchemaNode::Field(fields[child_idx].as_ref())
+        }
+        DataType::Map(entries_field, _) if num_children == 1 => {
+            ArrowSchemaNode::Field(entries_field)
+        }
+        DataType::List(element_field) | DataType::LargeList(element_field) if num_children == 1 => {
+            let child_elem = &schema_elements[child_elem_idx];
+
+            // see backward compatibility rules for lists in the parquet format spec
+            let is_list_wrapper = child_elem.num_children == Some(1)
+                && child_elem.name != "array"
+                && child_elem.name != format!("{}_tuple", parent_name);
+
+            if is_list_wrapper {
+                ArrowSchemaNode::ListWrapper(element_field)
+            } else {
+                ArrowSchemaNode::Field(element_field)
+            }
+        }
+        _ => ArrowSchemaNode::Unknown,
     }
 }
 
+// geometry_columns_from_kv_metadata returns the names of the geometry columns, which are
+// specified in the geoparquet metadata of the file.
+fn geometry_columns_from_kv_metadata(
+    kv_metadata: Option<&Vec<::parquet::format::KeyValue>>,
+) -> HashSet<String> {
+    let geoparquet_metadata = kv_metadata.and_then(|kv_metadata| {
+        kv_metadata
+            .iter()
+            .find(|kv| kv.key == "geo")
+            .and_then(|kv| kv.value.as_ref())
+    });
+
+    geoparquet_metadata
+        .and_then(|metadata| serde_json::from_str::<serde_json::Value>(metadata).ok())
+        .and_then(|metadata| {
+            metadata
+                .get("columns")
+                .and_then(|columns| columns.as_object())
+                .map(|columns| columns.keys().cloned().collect())
+        })
+        .unwrap_or_default()
+}
+
+// is_geoarrow_wkb_field returns true if the field is marked with the "geoarrow.wkb" extension
+// type, which arrow native writers use instead of, or in addition to, the geoparquet metadata.
+fn is_geoarrow_wkb_field(field: &Field) -> bool {
+    field
+        .metadata()
+        .get(ARROW_EXTENSION_NAME_META_KEY)
+        .is_some_and(|extension_name| extension_name == GEOARROW_WKB_EXTENSION_NAME)
+}
+
 fn thrift_type_to_str(thrift_type: Type) -> String {
     match thrift_type {
         Type::BOOLEAN => "BOOLEAN",
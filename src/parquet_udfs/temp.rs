@@ -0,0 +1,41 @@
+use pgrx::{iter::TableIterator, name, pg_extern};
+
+use crate::arrow_parquet::{
+    temp_files::{temp_parquet_file_uri, temp_parquet_files},
+    uri_utils::uri_as_string,
+};
+
+mod parquet {
+    use super::*;
+
+    // temp returns the uri of the temp file, which is created by COPY TO with the temp option,
+    // so that it can be passed to other parquet functions.
+    #[pg_extern]
+    fn temp(name: String) -> String {
+        uri_as_string(&temp_parquet_file_uri(&name))
+    }
+
+    #[pg_extern]
+    fn temp_list() -> TableIterator<
+        'static,
+        (
+            name!(name, String),
+            name!(uri, String),
+            name!(file_size, Option<i64>),
+        ),
+    > {
+        let mut rows = vec![];
+
+        for temp_file in temp_parquet_files() {
+            let uri = uri_as_string(&temp_file.uri);
+
+            let file_size = std::fs::metadata(&uri)
+                .ok()
+                .map(|metadata| metadata.len() as i64);
+
+            rows.push((temp_file.name, uri, file_size));
+        }
+
+        TableIterator::new(rows)
+    }
+}
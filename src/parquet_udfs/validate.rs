@@ -0,0 +1,41 @@
+use pgrx::{default, iter::TableIterator, name, pg_extern};
+
+use crate::arrow_parquet::uri_utils::{
+    ensure_access_privilege_to_uri, parse_uri, validate_parquet_file_from_uri,
+};
+
+mod parquet {
+    use super::*;
+
+    // validate checks whether the file can be loaded, e.g. before a big COPY FROM. It reads the
+    // footer of the file, and with "deep", decodes all of its row groups. The error of the file
+    // is returned instead of raised.
+    #[pg_extern]
+    fn validate(
+        uri: String,
+        deep: default!(bool, false),
+    ) -> TableIterator<
+        'static,
+        (
+            name!(ok, bool),
+            name!(num_rows, Option<i64>),
+            name!(num_row_groups, Option<i32>),
+            name!(error, Option<String>),
+        ),
+    > {
+        let uri = parse_uri(&uri);
+
+        ensure_access_privilege_to_uri(&uri, true);
+
+        let validation = validate_parquet_file_from_uri(&uri, deep);
+
+        let row = (
+            validation.error.is_none(),
+            validation.num_rows,
+            validation.num_row_groups,
+            validation.error,
+        );
+
+        TableIterator::new(vec![row])
+    }
+}
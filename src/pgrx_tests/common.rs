@@ -1,5 +1,7 @@
+use std::ffi::CStr;
 use std::fs::File;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::{collections::HashMap, fmt::Debug};
 
 use crate::type_compat::map::Map;
@@ -7,10 +9,14 @@ use crate::type_compat::map::Map;
 use arrow::array::RecordBatch;
 use arrow_schema::SchemaRef;
 use parquet::arrow::ArrowWriter;
-use pgrx::{
-    datum::{Time, TimeWithTimeZone},
-    FromDatum, IntoDatum, Spi,
-};
+use parquet::basic::Encoding;
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type, Int96, Int96Type};
+use parquet::file::properties::{WriterProperties, WriterVersion};
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use pgrx::pg_guard;
+use pgrx::pg_sys::{emit_log_hook, emit_log_hook_type, ErrorData, WARNING};
+use pgrx::{FromDatum, IntoDatum, Spi};
 
 pub(crate) enum CopyOptionValue {
     StringOption(String),
@@ -282,21 +288,6 @@ pub(crate) fn assert_double(expected_result: Vec<Option<f64>>, result: Vec<Optio
     }
 }
 
-pub(crate) fn timetz_to_utc_time(timetz: TimeWithTimeZone) -> Option<Time> {
-    Some(timetz.to_utc())
-}
-
-pub(crate) fn timetz_array_to_utc_time_array(
-    timetz_array: Vec<Option<TimeWithTimeZone>>,
-) -> Option<Vec<Option<Time>>> {
-    Some(
-        timetz_array
-            .into_iter()
-            .map(|timetz| timetz.map(|timetz| timetz.to_utc()))
-            .collect(),
-    )
-}
-
 pub(crate) fn extension_exists(extension_name: &str) -> bool {
     let query = format!(
         "select count(*) = 1 from pg_available_extensions where name = '{}'",
@@ -313,3 +304,271 @@ pub(crate) fn write_record_batch_to_parquet(schema: SchemaRef, record_batch: Rec
     writer.write(&record_batch).unwrap();
     writer.close().unwrap();
 }
+
+// write_record_batch_with_encodings_to_parquet writes the columns with the given encodings
+// instead of dictionary encoding, in small pages, as written by other writers with format
+// version 2.0, e.g. pyarrow with use_dictionary=False and column_encoding.
+pub(crate) fn write_record_batch_with_encodings_to_parquet(
+    schema: SchemaRef,
+    record_batch: RecordBatch,
+    column_encodings: &[(&str, Encoding)],
+) {
+    let mut writer_props = WriterProperties::builder()
+        .set_writer_version(WriterVersion::PARQUET_2_0)
+        .set_dictionary_enabled(false)
+        .set_data_page_row_count_limit(100)
+        .set_write_batch_size(100);
+
+    for (column, encoding) in column_encodings {
+        writer_props = writer_props.set_column_encoding((*column).into(), *encoding);
+    }
+
+    let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+    let mut writer = ArrowWriter::try_new(file, schema, Some(writer_props.build())).unwrap();
+
+    writer.write(&record_batch).unwrap();
+    writer.close().unwrap();
+}
+
+// write_legacy_two_level_list_parquet writes lists with the legacy 2-level encoding,
+// as written by Hive, i.e. without the middle repeated group.
+// rows: ([1,2,3], ["a","b","c"]), ([], []), (NULL, NULL), ([4], ["d"])
+pub(crate) fn write_legacy_two_level_list_parquet() {
+    let schema = parse_message_type(
+        "message hive_schema {
+            optional group ints (LIST) {
+                repeated int32 array;
+            }
+            optional group texts (LIST) {
+                repeated binary array (UTF8);
+            }
+        }",
+    )
+    .unwrap();
+
+    let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+    let mut writer = SerializedFileWriter::new(
+        file,
+        Arc::new(schema),
+        Arc::new(WriterProperties::default()),
+    )
+    .unwrap();
+
+    let def_levels = [2, 2, 2, 1, 0, 2];
+    let rep_levels = [0, 1, 1, 0, 0, 0];
+
+    let mut row_group_writer = writer.next_row_group().unwrap();
+
+    let mut ints_writer = row_group_writer.next_column().unwrap().unwrap();
+    ints_writer
+        .typed::<Int32Type>()
+        .write_batch(&[1, 2, 3, 4], Some(&def_levels), Some(&rep_levels))
+        .unwrap();
+    ints_writer.close().unwrap();
+
+    let texts = ["a", "b", "c", "d"].map(ByteArray::from);
+
+    let mut texts_writer = row_group_writer.next_column().unwrap().unwrap();
+    texts_writer
+        .typed::<ByteArrayType>()
+        .write_batch(&texts, Some(&def_levels), Some(&rep_levels))
+        .unwrap();
+    texts_writer.close().unwrap();
+
+    row_group_writer.close().unwrap();
+    writer.close().unwrap();
+}
+
+// write_map_parquet writes the maps {aa: 1, bb: 2} and NULL with the given name of the entries
+// group and of its value field, e.g. "map (MAP_KEY_VALUE)" and "value" as written by older Spark
+// versions, instead of the "key_value" group with "key" and "val" fields of COPY TO.
+pub(crate) fn write_map_parquet(entries_group: &str, value_field: &str) {
+    let schema = parse_message_type(&format!(
+        "message map_schema {{
+            optional group x (MAP) {{
+                repeated group {} {{
+                    required binary key (UTF8);
+                    optional int64 {};
+                }}
+            }}
+        }}",
+        entries_group, value_field
+    ))
+    .unwrap();
+
+    let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+    let mut writer = SerializedFileWriter::new(
+        file,
+        Arc::new(schema),
+        Arc::new(WriterProperties::default()),
+    )
+    .unwrap();
+
+    let rep_levels = [0, 1, 0];
+
+    let mut row_group_writer = writer.next_row_group().unwrap();
+
+    let keys = ["aa", "bb"].map(ByteArray::from);
+
+    let mut keys_writer = row_group_writer.next_column().unwrap().unwrap();
+    keys_writer
+        .typed::<ByteArrayType>()
+        .write_batch(&keys, Some(&[2, 2, 0]), Some(&rep_levels))
+        .unwrap();
+    keys_writer.close().unwrap();
+
+    let mut values_writer = row_group_writer.next_column().unwrap().unwrap();
+    values_writer
+        .typed::<Int64Type>()
+        .write_batch(&[1, 2], Some(&[3, 3, 0]), Some(&rep_levels))
+        .unwrap();
+    values_writer.close().unwrap();
+
+    row_group_writer.close().unwrap();
+    writer.close().unwrap();
+}
+
+// write_int96_timestamps_parquet writes the timestamps, given as nanoseconds since Unix epoch,
+// as INT96 values, as written by older Spark and Hive versions.
+pub(crate) fn write_int96_timestamps_parquet(timestamps: &[Option<i64>]) {
+    const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
+    const NANOSECONDS_PER_DAY: i64 = 86_400_000_000_000;
+
+    let schema = parse_message_type(
+        "message spark_schema {
+            optional int96 ts;
+        }",
+    )
+    .unwrap();
+
+    let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+    let mut writer = SerializedFileWriter::new(
+        file,
+        Arc::new(schema),
+        Arc::new(WriterProperties::default()),
+    )
+    .unwrap();
+
+    let def_levels = timestamps
+        .iter()
+        .map(|timestamp| timestamp.is_some() as i16)
+        .collect::<Vec<_>>();
+
+    let values = timestamps
+        .iter()
+        .flatten()
+        .map(|nanoseconds| {
+            let julian_day = nanoseconds.div_euclid(NANOSECONDS_PER_DAY) + JULIAN_DAY_OF_EPOCH;
+            let nanoseconds_of_day = nanoseconds.rem_euclid(NANOSECONDS_PER_DAY);
+
+            let mut value = Int96::new();
+            value.set_data(
+                nanoseconds_of_day as u32,
+                (nanoseconds_of_day >> 32) as u32,
+                julian_day as u32,
+            );
+            value
+        })
+        .collect::<Vec<_>>();
+
+    let mut row_group_writer = writer.next_row_group().unwrap();
+
+    let mut ts_writer = row_group_writer.next_column().unwrap().unwrap();
+    ts_writer
+        .typed::<Int96Type>()
+        .write_batch(&values, Some(&def_levels), None)
+        .unwrap();
+    ts_writer.close().unwrap();
+
+    row_group_writer.close().unwrap();
+    writer.close().unwrap();
+}
+
+// write_int_decimals_parquet writes the same unscaled decimals with DECIMAL(9,2) as INT32 and
+// as INT64 values, as written by pyarrow and parquet-mr, into the columns "d32" and "d64".
+pub(crate) fn write_int_decimals_parquet(unscaled: &[Option<i32>]) {
+    let schema = parse_message_type(
+        "message decimal_schema {
+            optional int32 d32 (DECIMAL(9,2));
+            optional int64 d64 (DECIMAL(9,2));
+        }",
+    )
+    .unwrap();
+
+    let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+    let mut writer = SerializedFileWriter::new(
+        file,
+        Arc::new(schema),
+        Arc::new(WriterProperties::default()),
+    )
+    .unwrap();
+
+    let def_levels = unscaled
+        .iter()
+        .map(|value| value.is_some() as i16)
+        .collect::<Vec<_>>();
+
+    let values = unscaled.iter().flatten().copied().collect::<Vec<_>>();
+
+    let mut row_group_writer = writer.next_row_group().unwrap();
+
+    let mut d32_writer = row_group_writer.next_column().unwrap().unwrap();
+    d32_writer
+        .typed::<Int32Type>()
+        .write_batch(&values, Some(&def_levels), None)
+        .unwrap();
+    d32_writer.close().unwrap();
+
+    let values = values.iter().map(|value| *value as i64).collect::<Vec<_>>();
+
+    let mut d64_writer = row_group_writer.next_column().unwrap().unwrap();
+    d64_writer
+        .typed::<Int64Type>()
+        .write_batch(&values, Some(&def_levels), None)
+        .unwrap();
+    d64_writer.close().unwrap();
+
+    row_group_writer.close().unwrap();
+    writer.close().unwrap();
+}
+
+// WARNINGS are the messages of the warnings, which are emitted while the hook is installed
+static mut WARNINGS: Vec<String> = vec![];
+
+static mut PREV_EMIT_LOG_HOOK: emit_log_hook_type = None;
+
+#[pg_guard]
+extern "C" fn record_warning(edata: *mut ErrorData) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        if (*edata).elevel == WARNING as i32 && !(*edata).message.is_null() {
+            let message = CStr::from_ptr((*edata).message).to_string_lossy();
+            WARNINGS.push(message.into_owned());
+        }
+
+        if let Some(prev_emit_log_hook) = PREV_EMIT_LOG_HOOK {
+            prev_emit_log_hook(edata);
+        }
+    }
+}
+
+// record_warnings returns the messages of the warnings, which are emitted while f runs.
+pub(crate) fn record_warnings(f: impl FnOnce()) -> Vec<String> {
+    // the hook is only called for the messages, which are written to the server log
+    Spi::run("SET log_min_messages TO warning;").unwrap();
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        WARNINGS.clear();
+        PREV_EMIT_LOG_HOOK = emit_log_hook;
+        emit_log_hook = Some(record_warning);
+    }
+
+    f();
+
+    #[allow(static_mut_refs)]
+    unsafe {
+        emit_log_hook = PREV_EMIT_LOG_HOOK;
+        std::mem::take(&mut WARNINGS)
+    }
+}
@@ -5,20 +5,27 @@ mod tests {
     use std::vec;
 
     use crate::pgrx_tests::common::{
-        extension_exists, write_record_batch_to_parquet, LOCAL_TEST_FILE_PATH,
+        extension_exists, write_int96_timestamps_parquet, write_int_decimals_parquet,
+        write_legacy_two_level_list_parquet, write_map_parquet, write_record_batch_to_parquet,
+        write_record_batch_with_encodings_to_parquet, LOCAL_TEST_FILE_PATH,
     };
     use crate::type_compat::pg_arrow_type_conversions::{
-        date_to_i32, time_to_i64, timestamp_to_i64, timestamptz_to_i64, timetz_to_i64,
+        date_to_i32, time_to_i64, timestamp_to_i64, timestamptz_to_i64,
     };
     use arrow::array::{
-        ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float32Array,
-        Float64Array, Int16Array, Int32Array, Int8Array, LargeBinaryArray, LargeStringArray,
-        ListArray, MapArray, RecordBatch, StringArray, StructArray, Time64MicrosecondArray,
-        TimestampMicrosecondArray, UInt16Array, UInt32Array, UInt64Array,
+        ArrayRef, BinaryArray, BinaryViewArray, BooleanArray, Date32Array, Decimal128Array,
+        Decimal256Array, DictionaryArray, FixedSizeBinaryArray, Float32Array, Float64Array,
+        Int16Array, Int32Array, Int64Array, Int8Array, LargeBinaryArray, LargeListArray,
+        LargeStringArray, ListArray, MapArray, RecordBatch, StringArray, StringViewArray,
+        StructArray, Time64MicrosecondArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+        TimestampSecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
     };
     use arrow::buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
-    use arrow::datatypes::UInt16Type;
-    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use arrow::datatypes::{i256, Int32Type, UInt16Type};
+    use arrow_schema::{DataType, Field, Fields, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Encoding;
+    use parquet::file::properties::WriterProperties;
     use pgrx::pg_test;
     use pgrx::{
         datum::{Date, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone},
@@ -251,9 +258,7 @@ mod tests {
             "true".into(),
         )]))]));
 
-        let timetz = TimeWithTimeZone::with_timezone(13, 0, 0.0, "UTC").unwrap();
-
-        let x = Arc::new(Time64MicrosecondArray::from(vec![timetz_to_i64(timetz)]));
+        let x = Arc::new(Time64MicrosecondArray::from(vec![time_to_i64(time)]));
 
         let batch = RecordBatch::try_new(schema.clone(), vec![x]).unwrap();
         write_record_batch_to_parquet(schema, batch);
@@ -267,7 +272,7 @@ mod tests {
         let value = Spi::get_one::<Time>("SELECT x FROM test_table LIMIT 1")
             .unwrap()
             .unwrap();
-        assert_eq!(value, timetz.into());
+        assert_eq!(value, time);
 
         let drop_table = "DROP TABLE test_table";
         Spi::run(drop_table).unwrap();
@@ -553,6 +558,86 @@ mod tests {
         Spi::run(drop_table).unwrap();
     }
 
+    #[pg_test]
+    fn test_coerce_view_and_large_list_types() {
+        // UTF8VIEW => text, BINARYVIEW => bytea
+        // LARGELIST<INT32> => int[], LARGELIST<UTF8VIEW> => text[]
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("x", DataType::Utf8View, true),
+            Field::new("y", DataType::BinaryView, true),
+            Field::new(
+                "z",
+                DataType::LargeList(Field::new("item", DataType::Int32, true).into()),
+                true,
+            ),
+            Field::new(
+                "w",
+                DataType::LargeList(Field::new("item", DataType::Utf8View, true).into()),
+                true,
+            ),
+        ]));
+
+        let x = Arc::new(StringViewArray::from(vec![
+            Some("a string longer than twelve bytes"),
+            None,
+        ]));
+
+        let y = Arc::new(BinaryViewArray::from(vec![Some(b"abc".as_slice()), None]));
+
+        let z = Arc::new(LargeListArray::from_iter_primitive::<Int32Type, _, _>(
+            vec![Some(vec![Some(1), None, Some(3)]), None],
+        ));
+
+        let w = Arc::new(LargeListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8View, true)),
+            OffsetBuffer::new(ScalarBuffer::from(vec![0_i64, 2, 2])),
+            Arc::new(StringViewArray::from(vec![Some("a"), None])),
+            Some(NullBuffer::from(vec![true, false])),
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![x, y, z, w]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (x text, y bytea, z int[], w text[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let value =
+            Spi::get_two::<String, Vec<u8>>("SELECT x, y FROM test_table WHERE x IS NOT NULL")
+                .unwrap();
+        assert_eq!(
+            value,
+            (
+                Some("a string longer than twelve bytes".into()),
+                Some(b"abc".to_vec())
+            )
+        );
+
+        let value = Spi::get_two::<Vec<Option<i32>>, Vec<Option<String>>>(
+            "SELECT z, w FROM test_table WHERE x IS NOT NULL",
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            (
+                Some(vec![Some(1), None, Some(3)]),
+                Some(vec![Some("a".into()), None])
+            )
+        );
+
+        let nulls = Spi::get_one::<bool>(
+            "SELECT y IS NULL AND z IS NULL AND w IS NULL FROM test_table WHERE x IS NULL",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(nulls);
+
+        let drop_table = "DROP TABLE test_table";
+        Spi::run(drop_table).unwrap();
+    }
+
     #[pg_test]
     fn test_coerce_struct_types() {
         // STRUCT {a: UINT16, b: UINT16} => test_type {a: int, b: bigint}
@@ -754,7 +839,11 @@ mod tests {
         let create_table = "CREATE TABLE test_table (x test_type)";
         Spi::run(create_table).unwrap();
 
-        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        // structs are paired by field name with match_by 'name'
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
         Spi::run(&copy_from).unwrap();
     }
 
@@ -891,6 +980,55 @@ mod tests {
         assert!(value);
     }
 
+    #[pg_test]
+    fn test_coerce_map_layouts() {
+        // Skip the test if crunchy_map extension is not available
+        if !extension_exists("crunchy_map") {
+            return;
+        }
+
+        Spi::run("DROP EXTENSION IF EXISTS crunchy_map; CREATE EXTENSION crunchy_map;").unwrap();
+
+        Spi::run("SELECT crunchy_map.create('text','bigint');").unwrap();
+
+        let create_table = "CREATE TABLE test_table (x crunchy_map.key_text_val_bigint)";
+        Spi::run(create_table).unwrap();
+
+        // the key and the value are read by position, whatever their names are
+        let layouts = [
+            ("key_value", "value"),
+            ("map (MAP_KEY_VALUE)", "value"),
+            ("entries", "val"),
+        ];
+
+        for (entries_group, value_field) in layouts {
+            write_map_parquet(entries_group, value_field);
+
+            Spi::run("TRUNCATE test_table").unwrap();
+
+            for match_by in ["position", "name"] {
+                let copy_from = format!(
+                    "COPY test_table FROM '{}' WITH (match_by '{}')",
+                    LOCAL_TEST_FILE_PATH, match_by
+                );
+                Spi::run(&copy_from).unwrap();
+            }
+
+            let maps = Spi::get_one::<i64>(
+                "select count(*) from test_table \
+                 where x = array[('aa',1),('bb',2)]::crunchy_map.key_text_val_bigint;",
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(maps, 2, "entries group {}", entries_group);
+
+            let null_maps = Spi::get_one::<i64>("select count(*) from test_table where x is null;")
+                .unwrap()
+                .unwrap();
+            assert_eq!(null_maps, 2, "entries group {}", entries_group);
+        }
+    }
+
     #[pg_test]
     fn test_coerce_list_of_map() {
         // Skip the test if crunchy_map extension is not available
@@ -1014,6 +1152,199 @@ mod tests {
         Spi::run(copy_from).unwrap();
     }
 
+    #[pg_test]
+    fn test_delta_encodings() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int64, true),
+            Field::new("c", DataType::Utf8, true),
+            Field::new("d", DataType::Utf8, true),
+            Field::new("e", DataType::Binary, true),
+        ]));
+
+        let row_count = 1000;
+
+        let a = Int32Array::from_iter((0..row_count).map(|i| (i % 7 != 0).then_some(i * 3 - 500)));
+        let b = Int64Array::from_iter(
+            (0..row_count).map(|i| (i % 11 != 0).then_some(i as i64 * 1_000_000_007)),
+        );
+        let c = StringArray::from_iter(
+            (0..row_count).map(|i| (i % 13 != 0).then(|| format!("value {}", i))),
+        );
+        // values with common prefixes, for which DELTA_BYTE_ARRAY is made
+        let d = StringArray::from_iter(
+            (0..row_count).map(|i| (i % 17 != 0).then(|| format!("https://example.com/{}", i))),
+        );
+        let e = BinaryArray::from_iter(
+            (0..row_count).map(|i| (i % 19 != 0).then(|| format!("bytes {}", i).into_bytes())),
+        );
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(a),
+                Arc::new(b),
+                Arc::new(c),
+                Arc::new(d),
+                Arc::new(e),
+            ],
+        )
+        .unwrap();
+
+        write_record_batch_with_encodings_to_parquet(
+            schema,
+            batch,
+            &[
+                ("a", Encoding::DELTA_BINARY_PACKED),
+                ("b", Encoding::DELTA_BINARY_PACKED),
+                ("c", Encoding::DELTA_LENGTH_BYTE_ARRAY),
+                ("d", Encoding::DELTA_BYTE_ARRAY),
+                ("e", Encoding::DELTA_BYTE_ARRAY),
+            ],
+        );
+
+        let encodings = Spi::get_one::<String>(&format!(
+            "SELECT string_agg(encodings, ';' ORDER BY path_in_schema)
+             FROM parquet.column_chunk_metadata('{}')",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(encodings.contains("DELTA_BINARY_PACKED"));
+        assert!(encodings.contains("DELTA_LENGTH_BYTE_ARRAY"));
+        assert!(encodings.contains("DELTA_BYTE_ARRAY"));
+
+        let create_table = "CREATE TABLE test_table (a int, b bigint, c text, d text, e bytea)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let different_rows = Spi::get_one::<i64>(
+            "SELECT count(*) FROM (
+                TABLE test_table
+                EXCEPT ALL
+                SELECT CASE WHEN i % 7 <> 0 THEN i * 3 - 500 END,
+                       CASE WHEN i % 11 <> 0 THEN i::bigint * 1000000007 END,
+                       CASE WHEN i % 13 <> 0 THEN 'value ' || i END,
+                       CASE WHEN i % 17 <> 0 THEN 'https://example.com/' || i END,
+                       CASE WHEN i % 19 <> 0 THEN convert_to('bytes ' || i, 'UTF8') END
+                FROM generate_series(0, 999) i
+            ) diff",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(different_rows, 0);
+
+        let result_rows = Spi::get_one::<i64>("SELECT count(*) FROM test_table")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result_rows, 1000);
+    }
+
+    #[pg_test]
+    fn test_match_by_field_id_after_rename_column() {
+        let create_table = "CREATE TABLE test_table (a int, b text, c int)";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("INSERT INTO test_table VALUES (1, 'hello', 2)").unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("TRUNCATE test_table").unwrap();
+        Spi::run("ALTER TABLE test_table RENAME COLUMN a TO c_old").unwrap();
+        Spi::run("ALTER TABLE test_table RENAME COLUMN c TO a").unwrap();
+        Spi::run("ALTER TABLE test_table RENAME COLUMN b TO d").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'field_id')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result =
+            Spi::get_three::<i32, &str, i32>("SELECT c_old, d, a FROM test_table").unwrap();
+        assert_eq!(result, (Some(1), Some("hello"), Some(2)));
+    }
+
+    #[pg_test]
+    fn test_field_ids_with_dropped_column() {
+        let create_table = "CREATE TABLE test_table (a int, b int, c text)";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("ALTER TABLE test_table DROP COLUMN b").unwrap();
+        Spi::run("INSERT INTO test_table VALUES (1, 'hello')").unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        // the field ids of the columns after the dropped column do not shift
+        let field_ids = Spi::get_one::<Vec<i32>>(&format!(
+            "SELECT array_agg(field_id ORDER BY field_id) FROM parquet.schema('{}') \
+             WHERE field_id IS NOT NULL",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(field_ids, vec![0, 2]);
+
+        Spi::run("CREATE TABLE test_table2 (c text, a int)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table2 FROM '{}' WITH (match_by 'field_id', field_ids '{{\"a\": 0, \"c\": 2}}')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_two::<&str, i32>("SELECT c, a FROM test_table2").unwrap();
+        assert_eq!(result, (Some("hello"), Some(1)));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column \"c\" is not found in parquet file")]
+    fn test_match_by_field_id_unknown_field_id() {
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // "c" gets field id 2, which is not in the parquet file
+        Spi::run("CREATE TABLE test_table (a int, b_dropped int, c text)").unwrap();
+        Spi::run("ALTER TABLE test_table DROP COLUMN b_dropped").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'field_id')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "field_ids option requires match_by 'field_id'")]
+    fn test_field_ids_without_match_by_field_id() {
+        Spi::run("CREATE TABLE test_table (a int)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name', field_ids '{{\"a\": 0}}')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "field_id 0 is given to both")]
+    fn test_duplicate_field_ids() {
+        Spi::run("CREATE TABLE test_table (a int, b int)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'field_id', field_ids '{{\"a\": 0, \"b\": 0}}')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
     #[pg_test]
     #[should_panic(expected = "column \"name\" is not found in parquet file")]
     fn test_missing_column_in_parquet() {
@@ -1030,6 +1361,114 @@ mod tests {
         Spi::run(&copy_from).unwrap();
     }
 
+    #[pg_test]
+    fn test_missing_columns_default_match_by_name() {
+        let create_table = "create table test_table(name text default 'unknown', id int,
+                                                    created_at timestamptz default now(), note text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1, 3) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' with (match_by 'name', missing_columns 'default')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "SELECT id, name, note FROM test_table ORDER BY id",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            for row in tup_table {
+                let id = row["id"].value::<i32>().unwrap();
+                let name = row["name"].value::<String>().unwrap();
+                let note = row["note"].value::<String>().unwrap();
+                results.push((id, name, note));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            result,
+            vec![
+                (Some(1), Some("unknown".into()), None),
+                (Some(2), Some("unknown".into()), None),
+                (Some(3), Some("unknown".into()), None),
+            ]
+        );
+
+        // default is evaluated as in regular COPY, now() returns the same value for all rows
+        let created_at_matches = Spi::get_one::<bool>(
+            "SELECT count(DISTINCT created_at) = 1 AND bool_and(created_at = now()) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(created_at_matches);
+    }
+
+    #[pg_test]
+    fn test_missing_columns_default_match_by_position() {
+        let create_table = "create table test_table(id int, name text default 'unknown');";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 100 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' with (missing_columns 'default')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_two::<i32, &str>("SELECT id, name FROM test_table").unwrap();
+        assert_eq!(result, (Some(100), Some("unknown")));
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"name\" is not found in parquet file and it has no default value to fill the NOT NULL column"
+    )]
+    fn test_missing_columns_default_not_null_without_default() {
+        let create_table = "create table test_table(id int, name text not null);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 100 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' with (match_by 'name', missing_columns 'default')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized missing_columns option: invalid")]
+    fn test_invalid_missing_columns() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 100 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' with (missing_columns 'invalid')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
     #[pg_test]
     #[should_panic(expected = "type mismatch for column \"x\" between table and parquet file.")]
     fn test_coerce_custom_cast_fail() {
@@ -1052,4 +1491,1805 @@ mod tests {
         let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
         Spi::run(&copy_from).unwrap();
     }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid input syntax for type json")]
+    fn test_coerce_invalid_json_text_into_jsonb() {
+        let copy_to = format!(
+            "COPY (SELECT '{{\"a\": 1}}' as x UNION ALL SELECT '{{\"a\": ' as x) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_table = "CREATE TABLE test_table (x jsonb)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_dictionary_encoded_text() {
+        let dictionary_type =
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("x", dictionary_type.clone(), true),
+            Field::new("y", dictionary_type.clone(), true),
+            Field::new("z", dictionary_type, true),
+        ]));
+
+        let values = vec![Some("active"), None, Some("inactive"), Some("active"), None];
+
+        let x: DictionaryArray<Int32Type> = values.clone().into_iter().collect();
+        let y: DictionaryArray<Int32Type> = values.into_iter().collect();
+        let z: DictionaryArray<Int32Type> = vec![None::<&str>; 5].into_iter().collect();
+
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(x), Arc::new(y), Arc::new(z)])
+                .unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TYPE status AS ENUM ('active', 'inactive');
+                            CREATE TABLE test_table (x text, y status, z text)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let rows = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select("SELECT x, y::text, z FROM test_table", None, None)
+                .unwrap();
+
+            for row in tup_table {
+                let x = row["x"].value::<String>().unwrap();
+                let y = row["y"].value::<String>().unwrap();
+                let z = row["z"].value::<String>().unwrap();
+                results.push((x, y, z));
+            }
+
+            results
+        });
+
+        let expected = vec![
+            (Some("active".into()), Some("active".into()), None),
+            (None, None, None),
+            (Some("inactive".into()), Some("inactive".into()), None),
+            (Some("active".into()), Some("active".into()), None),
+            (None, None, None),
+        ];
+
+        assert_eq!(rows, expected);
+    }
+
+    #[pg_test]
+    fn test_coerce_legacy_two_level_list() {
+        write_legacy_two_level_list_parquet();
+
+        let create_table = "create table test_table (ints int[], texts text[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let value = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select("SELECT ints, texts FROM test_table", None, None)
+                .unwrap();
+
+            for row in tup_table {
+                let ints = row["ints"].value::<Vec<Option<i32>>>().unwrap();
+                let texts = row["texts"].value::<Vec<Option<String>>>().unwrap();
+                results.push((ints, texts));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            value,
+            vec![
+                (
+                    Some(vec![Some(1), Some(2), Some(3)]),
+                    Some(vec![
+                        Some("a".to_string()),
+                        Some("b".to_string()),
+                        Some("c".to_string())
+                    ])
+                ),
+                (Some(vec![]), Some(vec![])),
+                (None, None),
+                (Some(vec![Some(4)]), Some(vec![Some("d".to_string())])),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "incompatible field is \"texts.element\"")]
+    fn test_coerce_legacy_two_level_list_incompatible_element() {
+        write_legacy_two_level_list_parquet();
+
+        let create_table = "create table test_table (ints int[], texts date[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_match_by_position_with_different_names() {
+        let create_type = "CREATE TYPE file_type AS (p int, q text)";
+        Spi::run(create_type).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b, row(2, 'world')::file_type as c) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_type = "CREATE TYPE test_type AS (y bigint, z text)";
+        Spi::run(create_type).unwrap();
+
+        let create_table = "CREATE TABLE test_table (x bigint, y varchar, z test_type)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'position')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result =
+            Spi::get_three::<i64, &str, &str>("SELECT x, y, z::text FROM test_table LIMIT 1")
+                .unwrap();
+        assert_eq!(result, (Some(1), Some("hello"), Some("(2,world)")));
+    }
+
+    #[pg_test]
+    fn test_match_by_position_with_dropped_column() {
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_table = "CREATE TABLE test_table (w int, x bigint, y text)";
+        Spi::run(create_table).unwrap();
+
+        let drop_column = "ALTER TABLE test_table DROP COLUMN w";
+        Spi::run(drop_column).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_two::<i64, &str>("SELECT x, y FROM test_table LIMIT 1").unwrap();
+        assert_eq!(result, (Some(1), Some("hello")));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column count mismatch between table and parquet file")]
+    fn test_match_by_position_extra_columns_fail() {
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_table = "CREATE TABLE test_table (x bigint)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_match_by_position_allow_extra_columns() {
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_table = "CREATE TABLE test_table (x bigint)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (allow_extra_columns true)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_one::<i64>("SELECT x FROM test_table LIMIT 1").unwrap();
+        assert_eq!(result, Some(1));
+    }
+
+    // write_extra_columns_file writes a file, which has an extra column "extra", and whose
+    // composite columns have an extra subfield "color", to LOCAL_TEST_FILE_PATH
+    fn write_extra_columns_file() {
+        let create_type = "CREATE TYPE dog_v2 AS (name text, age int, color text)";
+        Spi::run(create_type).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT 1 AS id,
+                          ROW('rex', 3, 'brown')::dog_v2 AS dog,
+                          ARRAY[ROW('fido', 5, 'black')::dog_v2] AS dogs,
+                          'extra' AS extra)
+             TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_type = "CREATE TYPE dog AS (name text, age int)";
+        Spi::run(create_type).unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int, dog dog, dogs dog[])";
+        Spi::run(create_table).unwrap();
+    }
+
+    #[pg_test]
+    fn test_extra_columns_ignore_by_name() {
+        write_extra_columns_file();
+
+        // extra columns are ignored by default with match_by 'name'
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_three::<i32, &str, i32>(
+            "SELECT id, (dog).name, (dogs[1]).age FROM test_table LIMIT 1",
+        )
+        .unwrap();
+        assert_eq!(result, (Some(1), Some("rex"), Some(5)));
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "parquet file has columns, which are not found in the table: \"dog.color\", \"dogs.element.color\", \"extra\""
+    )]
+    fn test_extra_columns_error_by_name() {
+        write_extra_columns_file();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name', extra_columns 'error')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_extra_columns_ignore_by_position() {
+        write_extra_columns_file();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (extra_columns 'ignore')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_three::<i32, &str, i32>(
+            "SELECT id, (dog).name, (dogs[1]).age FROM test_table LIMIT 1",
+        )
+        .unwrap();
+        assert_eq!(result, (Some(1), Some("rex"), Some(5)));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "extra columns in parquet file are \"extra\"")]
+    fn test_extra_columns_error_by_position() {
+        write_extra_columns_file();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (extra_columns 'error')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "parquet file has columns, which are not found in the table: \"dog.color\", \"dogs.element.color\""
+    )]
+    fn test_extra_columns_error_by_position_nested() {
+        write_extra_columns_file();
+
+        Spi::run("ALTER TABLE test_table ADD COLUMN extra text").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    // write_nested_composites_file writes a file with 3-level nested composites, whose fields
+    // are later reordered, added and removed, to LOCAL_TEST_FILE_PATH
+    fn write_nested_composites_file() {
+        Spi::run("CREATE TYPE leaf_v1 AS (x int, y text)").unwrap();
+        Spi::run("CREATE TYPE mid_v1 AS (a int, leaf leaf_v1, b text)").unwrap();
+        Spi::run("CREATE TYPE top_v1 AS (id int, mid mid_v1)").unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ROW(1, ROW(2, ROW(3, 'three')::leaf_v1, 'b')::mid_v1)::top_v1 AS t,
+                          ARRAY[ROW(4, 'four')::leaf_v1, NULL] AS leaves)
+             TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+    }
+
+    #[pg_test]
+    fn test_match_by_name_nested_composite_fields() {
+        write_nested_composites_file();
+
+        // fields are reordered, "z", "c" and "note" are added, and "b" is removed
+        Spi::run("CREATE TYPE leaf AS (y text, z int, x int)").unwrap();
+        Spi::run("CREATE TYPE mid AS (leaf leaf, a int, c text)").unwrap();
+        Spi::run("CREATE TYPE top AS (mid mid, note text, id int)").unwrap();
+
+        let create_table = "CREATE TABLE test_table (leaves leaf[], t top)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_three::<i32, i32, i32>(
+            "SELECT (t).id, ((t).mid).a, (((t).mid).leaf).x FROM test_table",
+        )
+        .unwrap();
+        assert_eq!(result, (Some(1), Some(2), Some(3)));
+
+        let result = Spi::get_three::<&str, i32, &str>(
+            "SELECT (((t).mid).leaf).y, (((t).mid).leaf).z, ((t).mid).c FROM test_table",
+        )
+        .unwrap();
+        assert_eq!(result, (Some("three"), None, None));
+
+        let result = Spi::get_three::<bool, i32, bool>(
+            "SELECT (t).note IS NULL, (leaves[1]).x, leaves[2] IS NULL FROM test_table",
+        )
+        .unwrap();
+        assert_eq!(result, (Some(true), Some(4), Some(true)));
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "field \"t.mid.leaf.z\" is not found in parquet file and its domain does not allow NULL"
+    )]
+    fn test_match_by_name_missing_not_null_domain_field() {
+        write_nested_composites_file();
+
+        Spi::run("CREATE DOMAIN not_null_int AS int NOT NULL").unwrap();
+        Spi::run("CREATE TYPE leaf AS (x int, y text, z not_null_int)").unwrap();
+        Spi::run("CREATE TYPE mid AS (a int, leaf leaf)").unwrap();
+        Spi::run("CREATE TYPE top AS (id int, mid mid)").unwrap();
+
+        let create_table = "CREATE TABLE test_table (t top)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "parquet file has columns, which are not found in the table")]
+    fn test_allow_extra_columns_false_with_match_by_name() {
+        write_extra_columns_file();
+
+        // false is extra_columns 'error', although match_by 'name' ignores them by default
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name', allow_extra_columns false)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "allow_extra_columns and extra_columns cannot be specified together")]
+    fn test_extra_columns_with_allow_extra_columns() {
+        write_extra_columns_file();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (allow_extra_columns true, extra_columns 'ignore')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column 2 is \"y\" in table and \"b\" in parquet file")]
+    fn test_match_by_position_type_mismatch_cites_both_names() {
+        let copy_to = format!(
+            "COPY (SELECT 1 as a, 'hello' as b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let create_table = "CREATE TABLE test_table (x bigint, y int[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_timestamps_at_dst_transitions() {
+        // local times, which do not exist (spring forward) or are ambiguous (fall back),
+        // and a local midnight for date coercion
+        let timezones = [
+            (
+                "America/New_York",
+                [
+                    "2024-03-10 02:30:00",
+                    "2024-11-03 01:30:00",
+                    "2024-11-03 00:00:00",
+                ],
+            ),
+            (
+                "Europe/Berlin",
+                [
+                    "2024-03-31 02:30:00",
+                    "2024-10-27 02:30:00",
+                    "2024-10-27 00:00:00",
+                ],
+            ),
+        ];
+
+        // each value should be coerced as Postgres' own cast in the session timezone does
+        let assert_coerced_as_postgres = |expected_query: &str, table: &str| {
+            let different_rows = Spi::get_one::<i64>(&format!(
+                "SELECT count(*) FROM (
+                    (SELECT * FROM ({expected_query}) e EXCEPT ALL SELECT * FROM {table})
+                    UNION ALL
+                    (SELECT * FROM {table} EXCEPT ALL SELECT * FROM ({expected_query}) e)
+                 ) d;"
+            ))
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(
+                different_rows, 0,
+                "{} differs from {}",
+                table, expected_query
+            );
+        };
+
+        for (timezone, local_times) in timezones {
+            Spi::run(&format!("SET TimeZone TO '{}';", timezone)).unwrap();
+
+            // every 15 minutes within an hour of the local times
+            let local_times_query = local_times
+                .iter()
+                .map(|local_time| {
+                    format!(
+                        "SELECT '{}'::timestamp + i * interval '15 minutes' AS ts \
+                         FROM generate_series(-4, 4) i",
+                        local_time
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" UNION ALL ");
+
+            // TIMESTAMP => {timestamptz}
+            let copy_to = format!("COPY ({}) TO '{}'", local_times_query, LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_to).unwrap();
+
+            Spi::run("CREATE TABLE test_timestamptz (ts timestamptz)").unwrap();
+
+            let copy_from = format!("COPY test_timestamptz FROM '{}'", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from).unwrap();
+
+            assert_coerced_as_postgres(
+                &format!("SELECT ts::timestamptz FROM ({}) q", local_times_query),
+                "test_timestamptz",
+            );
+
+            // TIMESTAMPTZ => {timestamp, date}
+            let copy_to = format!(
+                "COPY (SELECT ts::timestamptz AS ts FROM ({}) q) TO '{}'",
+                local_times_query, LOCAL_TEST_FILE_PATH
+            );
+            Spi::run(&copy_to).unwrap();
+
+            Spi::run("CREATE TABLE test_timestamp (ts timestamp)").unwrap();
+
+            let copy_from = format!("COPY test_timestamp FROM '{}'", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from).unwrap();
+
+            assert_coerced_as_postgres(
+                &format!(
+                    "SELECT ts::timestamptz::timestamp FROM ({}) q",
+                    local_times_query
+                ),
+                "test_timestamp",
+            );
+
+            Spi::run("CREATE TABLE test_date (ts date)").unwrap();
+
+            let copy_from = format!("COPY test_date FROM '{}'", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from).unwrap();
+
+            assert_coerced_as_postgres(
+                &format!(
+                    "SELECT ts::timestamptz::date FROM ({}) q",
+                    local_times_query
+                ),
+                "test_date",
+            );
+
+            Spi::run("DROP TABLE test_timestamptz, test_timestamp, test_date").unwrap();
+        }
+    }
+
+    #[pg_test]
+    fn test_coerce_int_decimals() {
+        let unscaled = [
+            Some(12_345_678),
+            None,
+            Some(-1),
+            Some(0),
+            Some(999_999_999),
+            Some(-999_999_999),
+        ];
+
+        write_int_decimals_parquet(&unscaled);
+
+        let schema_command = format!(
+            "SELECT array_agg(parquet_type || ' ' || postgres_type ORDER BY field_path)
+             FROM parquet.schema('{}') WHERE field_path IN (ARRAY['d32'], ARRAY['d64']);",
+            LOCAL_TEST_FILE_PATH
+        );
+        let physical_types = Spi::get_one::<Vec<String>>(&schema_command)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            physical_types,
+            vec!["INT32 numeric(9,2)", "INT64 numeric(9,2)"]
+        );
+
+        let expected_values = vec![
+            Some("123456.78".to_string()),
+            None,
+            Some("-0.01".into()),
+            Some("0.00".into()),
+            Some("9999999.99".into()),
+            Some("-9999999.99".into()),
+        ];
+
+        // both physical types are read as the same decimal, into unconstrained and constrained
+        // numeric columns
+        for column_type in ["numeric", "numeric(9,2)", "numeric(12,4)"] {
+            let create_table = format!("CREATE TABLE test_table (d32 {0}, d64 {0})", column_type);
+            Spi::run(&create_table).unwrap();
+
+            let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from).unwrap();
+
+            let values = Spi::connect(|client| {
+                client
+                    .select(
+                        "SELECT round(d32, 2)::text AS d32, round(d64, 2)::text AS d64,
+                                d32 IS NOT DISTINCT FROM d64 AS same
+                         FROM test_table",
+                        None,
+                        None,
+                    )
+                    .unwrap()
+                    .map(|row| {
+                        assert!(row["same"].value::<bool>().unwrap().unwrap());
+                        assert_eq!(
+                            row["d32"].value::<String>().unwrap(),
+                            row["d64"].value::<String>().unwrap()
+                        );
+                        row["d32"].value::<String>().unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            assert_eq!(values, expected_values);
+
+            Spi::run("DROP TABLE test_table").unwrap();
+        }
+    }
+
+    #[pg_test]
+    fn test_coerce_int96_timestamps() {
+        // 2022-05-05 12:34:56.123456789 and 1969-12-31 23:59:59.999999999
+        let timestamps = [Some(1_651_754_096_123_456_789), None, Some(-1)];
+
+        write_int96_timestamps_parquet(&timestamps);
+
+        let schema_command = format!(
+            "SELECT parquet_type FROM parquet.schema('{}') WHERE field_path = ARRAY['ts'];",
+            LOCAL_TEST_FILE_PATH
+        );
+        let physical_type = Spi::get_one::<String>(&schema_command).unwrap().unwrap();
+        assert_eq!(physical_type, "INT96");
+
+        // nanoseconds are truncated to microseconds
+        let create_table = "CREATE TABLE test_table (ts timestamp)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::connect(|client| {
+            client
+                .select("SELECT ts::text FROM test_table", None, None)
+                .unwrap()
+                .map(|row| row["ts"].value::<String>().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(
+            values,
+            vec![
+                Some("2022-05-05 12:34:56.123456".into()),
+                None,
+                Some("1969-12-31 23:59:59.999999".into()),
+            ]
+        );
+
+        let drop_table = "DROP TABLE test_table";
+        Spi::run(drop_table).unwrap();
+
+        Spi::run("SET TimeZone TO 'UTC'").unwrap();
+
+        let create_table = "CREATE TABLE test_table (ts timestamptz)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let value = Spi::get_one::<String>("SELECT ts::text FROM test_table LIMIT 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, "2022-05-05 12:34:56.123456+00");
+    }
+
+    #[pg_test]
+    fn test_coerce_second_and_millisecond_timestamps() {
+        let timestamp = Timestamp::new(2022, 5, 5, 12, 34, 56.0).unwrap();
+        let seconds = timestamp_to_i64(timestamp) / 1_000_000;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Timestamp(TimeUnit::Second, None), true),
+            Field::new(
+                "ms",
+                DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+                true,
+            ),
+        ]));
+
+        let s = Arc::new(TimestampSecondArray::from(vec![seconds]));
+        let ms = Arc::new(
+            TimestampMillisecondArray::from(vec![seconds * 1_000 + 123]).with_timezone("UTC"),
+        );
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![s, ms]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        Spi::run("SET TimeZone TO 'UTC'").unwrap();
+
+        let create_table = "CREATE TABLE test_table (s timestamp, ms timestamptz)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let (s, ms) =
+            Spi::get_two::<String, String>("SELECT s::text, ms::text FROM test_table").unwrap();
+        assert_eq!(s, Some("2022-05-05 12:34:56".into()));
+        assert_eq!(ms, Some("2022-05-05 12:34:56.123+00".into()));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "timestamp out of range")]
+    fn test_coerce_second_timestamp_out_of_range() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "s",
+            DataType::Timestamp(TimeUnit::Second, None),
+            true,
+        )]));
+
+        let s = Arc::new(TimestampSecondArray::from(vec![i64::MAX / 1_000]));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![s]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (s timestamp)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_text_to_interval() {
+        // ISO 8601 and Postgres syntax are parsed regardless of IntervalStyle
+        Spi::run("SET intervalstyle TO 'sql_standard';").unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("i", DataType::Utf8, true),
+            Field::new(
+                "ia",
+                DataType::List(Field::new("item", DataType::Utf8, true).into()),
+                true,
+            ),
+        ]));
+
+        let i = Arc::new(StringArray::from(vec![
+            Some("P1DT2H30M"),
+            Some("1 day 02:30:00"),
+            Some("P1Y2M3W"),
+            Some("P0001-02-03T04:05:06.5"),
+            Some("-1 02:00:00"),
+            Some("PT1.5S"),
+            None,
+        ]));
+
+        let ia_values = Arc::new(StringArray::from(vec![Some("P1D"), Some("2 hours"), None]));
+        let offsets = OffsetBuffer::new(ScalarBuffer::from(vec![0, 3, 3, 3, 3, 3, 3, 3]));
+        let ia = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+            offsets,
+            ia_values,
+            None,
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![i, ia]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (i interval, ia interval[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        Spi::run("SET intervalstyle TO 'postgres';").unwrap();
+
+        let intervals = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select("SELECT i::text FROM test_table", None, None)
+                .unwrap();
+
+            for row in tup_table {
+                results.push(row["i"].value::<String>().unwrap());
+            }
+
+            results
+        });
+
+        assert_eq!(
+            intervals,
+            vec![
+                Some("1 day 02:30:00".into()),
+                Some("1 day 02:30:00".into()),
+                Some("1 year 2 mons 21 days".into()),
+                Some("1 year 2 mons 3 days 04:05:06.5".into()),
+                Some("-1 days +02:00:00".into()),
+                Some("00:00:01.5".into()),
+                None,
+            ]
+        );
+
+        let interval_array = Spi::get_one::<String>("SELECT ia::text FROM test_table LIMIT 1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(interval_array, "{\"1 day\",02:00:00,NULL}");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid interval value \"P1X\" for column \"i\" at row 2")]
+    fn test_coerce_invalid_iso8601_text_to_interval() {
+        let schema = Arc::new(Schema::new(vec![Field::new("i", DataType::Utf8, true)]));
+
+        let i = Arc::new(StringArray::from(vec![Some("P1D"), Some("P1X")]));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![i]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (i interval)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid interval value \"1 fortnight\" for column \"ia\" at row 1")]
+    fn test_coerce_invalid_text_array_to_interval_array() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "ia",
+            DataType::List(Field::new("item", DataType::Utf8, true).into()),
+            true,
+        )]));
+
+        let ia_values = Arc::new(StringArray::from(vec![Some("1 day"), Some("1 fortnight")]));
+        let offsets = OffsetBuffer::new(ScalarBuffer::from(vec![0, 2]));
+        let ia = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+            offsets,
+            ia_values,
+            None,
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![ia]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (ia interval[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_to_money() {
+        // cents in INT64, text and decimals with another scale are read into money
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("cents", DataType::Int64, true),
+            Field::new("text", DataType::Utf8, true),
+            Field::new("decimal", DataType::Decimal128(10, 3), true),
+        ]));
+
+        let cents = Arc::new(Int64Array::from(vec![Some(123456), Some(-1), None]));
+        let text = Arc::new(StringArray::from(vec![
+            Some("$1,234.56"),
+            Some("-0.01"),
+            None,
+        ]));
+        let decimal = Arc::new(
+            Decimal128Array::from(vec![Some(1234560), Some(-10), None])
+                .with_precision_and_scale(10, 3)
+                .unwrap(),
+        );
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![cents, text, decimal]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        Spi::run("SET lc_monetary TO 'C';").unwrap();
+
+        let create_table = "CREATE TABLE test_table (cents money, text money, decimal money)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let result = Spi::get_one::<Vec<Option<bool>>>(
+            "SELECT array_agg(cents = text AND text = decimal ORDER BY cents) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(result, vec![Some(true), Some(true), None]);
+
+        let result = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(cents::numeric::text ORDER BY cents) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![Some("-0.01".into()), Some("1234.56".into()), None]
+        );
+    }
+
+    #[pg_test]
+    fn test_coerce_text_to_network_addresses() {
+        // network addresses were written as text before they had their own shape
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("i", DataType::Utf8, true),
+            Field::new("c", DataType::Utf8, true),
+            Field::new("m", DataType::Utf8, true),
+            Field::new("m8", DataType::Utf8, true),
+            Field::new(
+                "ia",
+                DataType::List(Field::new("item", DataType::Utf8, true).into()),
+                true,
+            ),
+        ]));
+
+        let i = Arc::new(StringArray::from(vec![Some("192.168.0.1/24"), None]));
+        let c = Arc::new(StringArray::from(vec![Some("2001:db8::/32"), None]));
+        let m = Arc::new(StringArray::from(vec![Some("08:00:2b:01:02:03"), None]));
+        let m8 = Arc::new(StringArray::from(vec![
+            Some("08:00:2b:01:02:03:04:05"),
+            None,
+        ]));
+
+        let ia_values = Arc::new(StringArray::from(vec![Some("::1"), None]));
+        let offsets = OffsetBuffer::new(ScalarBuffer::from(vec![0, 2, 2]));
+        let ia = Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, true)),
+            offsets,
+            ia_values,
+            None,
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![i, c, m, m8, ia]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table =
+            "CREATE TABLE test_table (i inet, c cidr, m macaddr, m8 macaddr8, ia inet[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let value = Spi::get_one::<String>(
+            "SELECT concat_ws(' ', i, c, m, m8, ia) FROM test_table WHERE i IS NOT NULL",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            value,
+            "192.168.0.1/24 2001:db8::/32 08:00:2b:01:02:03 08:00:2b:01:02:03:04:05 {::1,NULL}"
+        );
+
+        let null_count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM test_table WHERE i IS NULL AND c IS NULL AND m IS NULL AND m8 IS NULL AND ia = '{}'",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(null_count, 1);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "invalid cidr value for column \"c\": cidr value has bits set to right of mask"
+    )]
+    fn test_coerce_invalid_cidr_struct() {
+        let fields = Fields::from(vec![
+            Field::new("family", DataType::UInt8, false),
+            Field::new("bits", DataType::UInt8, false),
+            Field::new("is_cidr", DataType::Boolean, false),
+            Field::new("addr", DataType::Binary, false),
+        ]);
+
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "c",
+            DataType::Struct(fields.clone()),
+            true,
+        )]));
+
+        // 192.168.0.1/24
+        let c = Arc::new(StructArray::new(
+            fields,
+            vec![
+                Arc::new(UInt8Array::from(vec![4])),
+                Arc::new(UInt8Array::from(vec![24])),
+                Arc::new(BooleanArray::from(vec![true])),
+                Arc::new(BinaryArray::from_iter_values(vec![[192, 168, 0, 1]])),
+            ],
+            None,
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![c]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (c cidr)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_fixed_size_binary_to_macaddr() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "m",
+            DataType::FixedSizeBinary(6),
+            true,
+        )]));
+
+        let m = Arc::new(
+            FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                vec![Some([8, 0, 0x2b, 1, 2, 3]), None].into_iter(),
+                6,
+            )
+            .unwrap(),
+        );
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![m]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (m macaddr)";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let value =
+            Spi::get_one::<Vec<Option<String>>>("SELECT array_agg(m::text) FROM test_table")
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(value, vec![Some("08:00:2b:01:02:03".into()), None]);
+    }
+
+    #[pg_test]
+    fn test_coerce_error_context_reports_file_location() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("a", DataType::UInt32, true),
+        ]));
+
+        // 2 row groups with 2 rows each, the 3rd row overflows int
+        let file = std::fs::File::create(LOCAL_TEST_FILE_PATH).unwrap();
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), None).unwrap();
+
+        for values in [vec![1, 2], vec![u32::MAX, 4]] {
+            let ids = Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef;
+            let values = Arc::new(UInt32Array::from(values)) as ArrayRef;
+
+            let batch = RecordBatch::try_new(schema.clone(), vec![ids, values]).unwrap();
+
+            writer.write(&batch).unwrap();
+            writer.flush().unwrap();
+        }
+
+        writer.close().unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int, a int)";
+        Spi::run(create_table).unwrap();
+
+        Spi::run(&format!(
+            "DO $$
+             DECLARE
+                context text;
+             BEGIN
+                COPY test_table FROM '{}';
+             EXCEPTION WHEN OTHERS THEN
+                GET STACKED DIAGNOSTICS context = PG_EXCEPTION_CONTEXT;
+                PERFORM set_config('pg_parquet_test.error_context', context, false);
+             END $$;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+
+        let error_context = Spi::get_one::<String>(
+            "SELECT current_setting('pg_parquet_test.error_context', true);",
+        )
+        .unwrap()
+        .unwrap_or_default();
+
+        assert!(
+            error_context.contains(&format!(
+                "parquet file {}, row group 1, row 3, column \"a\"",
+                LOCAL_TEST_FILE_PATH
+            )),
+            "unexpected error context: {}",
+            error_context
+        );
+    }
+
+    #[pg_test]
+    fn test_coerce_skips_pages_by_where_clause() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("a", DataType::UInt32, true),
+        ]));
+
+        // 10 pages of 100 rows, the 50th and 555th rows overflow int
+        let ids = Arc::new(Int32Array::from_iter_values(1..=1000)) as ArrayRef;
+        let values = Arc::new(UInt32Array::from_iter_values((1..=1000).map(|id| {
+            if id == 50 || id == 555 {
+                u32::MAX
+            } else {
+                id
+            }
+        }))) as ArrayRef;
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![ids, values]).unwrap();
+
+        let writer_props = WriterProperties::builder()
+            .set_data_page_row_count_limit(100)
+            .set_write_batch_size(100)
+            .build();
+
+        let file = std::fs::File::create(LOCAL_TEST_FILE_PATH).unwrap();
+        let mut writer = ArrowWriter::try_new(file, schema, Some(writer_props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int, a int)";
+        Spi::run(create_table).unwrap();
+
+        // the rows of the skipped first page are not converted
+        Spi::run(&format!(
+            "COPY test_table FROM '{}' WHERE id > 100 AND id < 500",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+
+        let result = Spi::get_one::<i64>("SELECT count(*) FROM test_table WHERE id = a")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, 399);
+
+        // the error context reports the row number in the file, not among the read rows
+        Spi::run(&format!(
+            "DO $$
+             DECLARE
+                context text;
+             BEGIN
+                COPY test_table FROM '{}' WHERE id > 500;
+             EXCEPTION WHEN OTHERS THEN
+                GET STACKED DIAGNOSTICS context = PG_EXCEPTION_CONTEXT;
+                PERFORM set_config('pg_parquet_test.error_context', context, false);
+             END $$;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+
+        let error_context = Spi::get_one::<String>(
+            "SELECT current_setting('pg_parquet_test.error_context', true);",
+        )
+        .unwrap()
+        .unwrap_or_default();
+
+        assert!(
+            error_context.contains(&format!(
+                "parquet file {}, row group 0, row 555, column \"a\"",
+                LOCAL_TEST_FILE_PATH
+            )),
+            "unexpected error context: {}",
+            error_context
+        );
+    }
+
+    #[pg_test]
+    fn test_coerce_decimals_into_unconstrained_numeric() {
+        // a Decimal128 with more integral digits than Decimal128(38,9), and a Decimal256 with
+        // precision > 38, e.g. written by Spark
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Decimal128(38, 0), true),
+            Field::new("b", DataType::Decimal256(50, 10), true),
+            Field::new(
+                "c",
+                DataType::List(Field::new("item", DataType::Decimal256(50, 10), true).into()),
+                true,
+            ),
+        ]));
+
+        let a = Arc::new(
+            Decimal128Array::from(vec![Some(10_i128.pow(36) + 1), Some(-42), None])
+                .with_precision_and_scale(38, 0)
+                .unwrap(),
+        );
+
+        let b_values = [
+            Some("1234567890123456789012345678901234567890123456789"),
+            Some("-12345"),
+            None,
+        ]
+        .into_iter()
+        .map(|value| value.map(|value| i256::from_string(value).unwrap()))
+        .collect::<Vec<_>>();
+
+        let b = Arc::new(
+            Decimal256Array::from(b_values.clone())
+                .with_precision_and_scale(50, 10)
+                .unwrap(),
+        );
+
+        let c_values = Decimal256Array::from(b_values)
+            .with_precision_and_scale(50, 10)
+            .unwrap();
+
+        let c = Arc::new(ListArray::new(
+            Field::new("item", DataType::Decimal256(50, 10), true).into(),
+            OffsetBuffer::new(ScalarBuffer::from(vec![0, 3, 3, 3])),
+            Arc::new(c_values),
+            Some(NullBuffer::from(vec![true, true, false])),
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![a, b, c]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (a numeric, b numeric, c numeric[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(a::text ORDER BY a NULLS LAST) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some("-42".into()),
+                Some("1000000000000000000000000000000000001".into()),
+                None
+            ]
+        );
+
+        let values = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(b::text ORDER BY b NULLS LAST) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some("-0.0000012345".into()),
+                Some("123456789012345678901234567890123456789.0123456789".into()),
+                None
+            ]
+        );
+
+        let value = Spi::get_one::<String>("SELECT c::text FROM test_table WHERE a > 0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            value,
+            "{123456789012345678901234567890123456789.0123456789,-0.0000012345,NULL}"
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "numeric field overflow")]
+    fn test_coerce_decimal256_into_too_small_numeric() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "a",
+            DataType::Decimal256(50, 10),
+            true,
+        )]));
+
+        let a = Arc::new(
+            Decimal256Array::from(vec![i256::from_string("123456789012345").unwrap()])
+                .with_precision_and_scale(50, 10)
+                .unwrap(),
+        );
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![a]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TABLE test_table (a numeric(5,2))";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    fn write_nested_int_lists(inner_offsets: Vec<i32>, inner_nulls: Option<NullBuffer>) {
+        let element_field = Arc::new(Field::new("element", DataType::Int32, true));
+        let inner_list_field = Arc::new(Field::new(
+            "element",
+            DataType::List(element_field.clone()),
+            true,
+        ));
+
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "a",
+            DataType::List(inner_list_field.clone()),
+            true,
+        )]));
+
+        let value_count = *inner_offsets.last().unwrap();
+        let values = Arc::new(Int32Array::from_iter_values(1..=value_count));
+
+        let inner_list_count = inner_offsets.len() as i32 - 1;
+        let inner_lists = Arc::new(ListArray::new(
+            element_field,
+            OffsetBuffer::new(ScalarBuffer::from(inner_offsets)),
+            values,
+            inner_nulls,
+        ));
+
+        // rows: [inner lists], NULL, []
+        let a = Arc::new(ListArray::new(
+            inner_list_field,
+            OffsetBuffer::new(ScalarBuffer::from(vec![
+                0,
+                inner_list_count,
+                inner_list_count,
+                inner_list_count,
+            ])),
+            inner_lists,
+            Some(NullBuffer::from(vec![true, false, true])),
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![a]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+    }
+
+    #[pg_test]
+    fn test_coerce_nested_lists_into_multidim_array() {
+        // [[1,2,3],[4,5,6]], NULL, []
+        write_nested_int_lists(vec![0, 3, 6], None);
+
+        let create_table = "CREATE TABLE test_table (a bigint[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let value = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(a::text, 'null'), ' ' ORDER BY a) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(value, "{} {{1,2,3},{4,5,6}} null");
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "nested lists of column \"a\" cannot be read into a multidimensional array, since its inner lists have different lengths"
+    )]
+    fn test_coerce_ragged_nested_lists_into_multidim_array() {
+        // [[1,2],[3,4,5]], NULL, []
+        write_nested_int_lists(vec![0, 2, 5], None);
+
+        let create_table = "CREATE TABLE test_table (a int[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "nested lists of column \"a\" cannot be read into a multidimensional array, since an inner list is NULL"
+    )]
+    fn test_coerce_nested_lists_with_null_inner_list_into_multidim_array() {
+        // [[1,2],NULL], NULL, []
+        write_nested_int_lists(vec![0, 2, 2], Some(NullBuffer::from(vec![true, false])));
+
+        let create_table = "CREATE TABLE test_table (a int[])";
+        Spi::run(create_table).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    // utc instants around the DST transitions of Europe/Berlin in 2024
+    const DST_TRANSITION_TIMESTAMPS: [i64; 4] = [
+        1711845000000000, // 2024-03-31 00:30:00+00
+        1711848600000000, // 2024-03-31 01:30:00+00
+        1729989000000000, // 2024-10-27 00:30:00+00
+        1729992600000000, // 2024-10-27 01:30:00+00
+    ];
+
+    #[pg_test]
+    fn test_coerce_timestamptz_with_non_utc_timezones() {
+        let berlin_field = Field::new(
+            "element",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("Europe/Berlin".into())),
+            true,
+        );
+
+        let struct_fields = Fields::from(vec![Field::new(
+            "ts",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("+05:30".into())),
+            true,
+        )]);
+
+        let schema = Arc::new(Schema::new(vec![
+            berlin_field.clone().with_name("named_zone"),
+            Field::new(
+                "offset_zone",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("+05:30".into())),
+                true,
+            ),
+            Field::new("list", DataType::List(Arc::new(berlin_field.clone())), true),
+            Field::new("s", DataType::Struct(struct_fields.clone()), true),
+        ]));
+
+        let berlin_timestamps = || {
+            TimestampMicrosecondArray::from(DST_TRANSITION_TIMESTAMPS.to_vec())
+                .with_timezone("Europe/Berlin")
+        };
+
+        let offset_timestamps = || {
+            TimestampMicrosecondArray::from(DST_TRANSITION_TIMESTAMPS.to_vec())
+                .with_timezone("+05:30")
+        };
+
+        let named_zone = Arc::new(berlin_timestamps());
+        let offset_zone = Arc::new(offset_timestamps());
+
+        let list = Arc::new(ListArray::new(
+            Arc::new(berlin_field),
+            OffsetBuffer::from_lengths(vec![1; DST_TRANSITION_TIMESTAMPS.len()]),
+            Arc::new(berlin_timestamps()),
+            None,
+        ));
+
+        let s = Arc::new(StructArray::new(
+            struct_fields,
+            vec![Arc::new(offset_timestamps()) as ArrayRef],
+            None,
+        ));
+
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![named_zone, offset_zone, list, s]).unwrap();
+
+        write_record_batch_to_parquet(schema, batch);
+
+        let create_table = "CREATE TYPE ts_holder AS (ts timestamptz);
+                            CREATE TABLE test_table (named_zone timestamptz,
+                                                     offset_zone timestamptz,
+                                                     list timestamptz[],
+                                                     s ts_holder);";
+        Spi::run(create_table).unwrap();
+
+        // the timezone of the values does not depend on the session timezone
+        Spi::run("SET LOCAL timezone TO 'America/New_York'").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let all_equal = Spi::get_one::<bool>(
+            "SELECT bool_and(named_zone = offset_zone AND named_zone = list[1]
+                             AND named_zone = (s).ts)
+             FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(all_equal);
+
+        let values = Spi::get_one::<String>(
+            "SELECT array_agg(named_zone AT TIME ZONE 'UTC' ORDER BY named_zone)::text
+             FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            values,
+            "{\"2024-03-31 00:30:00\",\"2024-03-31 01:30:00\",\
+              \"2024-10-27 00:30:00\",\"2024-10-27 01:30:00\"}"
+        );
+    }
+
+    #[pg_test]
+    fn test_coerce_timestamptz_into_timestamp_options() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "x",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("Europe/Berlin".into())),
+            true,
+        )]));
+
+        let x = Arc::new(
+            TimestampMicrosecondArray::from(DST_TRANSITION_TIMESTAMPS.to_vec())
+                .with_timezone("Europe/Berlin"),
+        );
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![x]).unwrap();
+
+        write_record_batch_to_parquet(schema, batch);
+
+        Spi::run("CREATE TABLE test_table (x timestamp)").unwrap();
+
+        Spi::run("SET LOCAL timezone TO 'Europe/Berlin'").unwrap();
+
+        let select_values = "SELECT array_agg(x ORDER BY x)::text FROM test_table";
+
+        // local times of the session timezone by default
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<String>(select_values).unwrap().unwrap();
+        assert_eq!(
+            values,
+            "{\"2024-03-31 01:30:00\",\"2024-03-31 03:30:00\",\
+              \"2024-10-27 02:30:00\",\"2024-10-27 02:30:00\"}"
+        );
+
+        Spi::run("TRUNCATE test_table").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (timestamptz_to_timestamp 'utc')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<String>(select_values).unwrap().unwrap();
+        assert_eq!(
+            values,
+            "{\"2024-03-31 00:30:00\",\"2024-03-31 01:30:00\",\
+              \"2024-10-27 00:30:00\",\"2024-10-27 01:30:00\"}"
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "cannot be read into timestamp with timestamptz_to_timestamp 'error'"
+    )]
+    fn test_coerce_timestamptz_into_timestamp_error() {
+        let copy_to = format!("COPY (SELECT now() AS x) TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (x timestamp)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (timestamptz_to_timestamp 'error')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"tags\" is a list in the parquet file but scalar text in the table"
+    )]
+    fn test_coerce_list_into_scalar_error() {
+        let copy_to = format!(
+            "COPY (SELECT ARRAY['a']::text[] AS tags) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (tags text)").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"tags\" is a scalar in the parquet file but array text[] in the table"
+    )]
+    fn test_coerce_scalar_into_list_error() {
+        let copy_to = format!(
+            "COPY (SELECT 'a'::text AS tags) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (tags text[])").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_list_into_scalar_relaxed() {
+        let copy_to = format!(
+            "COPY (SELECT * FROM (VALUES (1, ARRAY[10]), (2, NULL), (3, ARRAY[]::int[]), \
+                                         (4, ARRAY[NULL]::int[])) v(id, score)) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (id int, score bigint)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (list_coercion 'relaxed')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(score::text, 'null'), ' ' ORDER BY id) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "10 null null null");
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"tags\" has a list of 2 elements, which cannot be read into a scalar column"
+    )]
+    fn test_coerce_list_with_multiple_elements_into_scalar_relaxed() {
+        let copy_to = format!(
+            "COPY (SELECT ARRAY['a', 'b']::text[] AS tags) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (tags text)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (list_coercion 'relaxed')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_coerce_scalar_into_list_relaxed() {
+        let copy_to = format!(
+            "COPY (SELECT * FROM (VALUES (1, 'a'), (2, NULL)) v(id, tags)) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (id int, tags text[])").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (list_coercion 'relaxed')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(tags::text, 'null'), ' ' ORDER BY id) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "{a} null");
+    }
+
+    #[pg_test]
+    fn test_coerce_nested_lists_into_scalar_relaxed() {
+        // [[1]], NULL, []
+        write_nested_int_lists(vec![0, 1], None);
+
+        Spi::run("CREATE TABLE test_table (a bigint)").unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (list_coercion 'relaxed')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let values = Spi::get_one::<String>(
+            "SELECT string_agg(coalesce(a::text, 'null'), ' ' ORDER BY a NULLS LAST) FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "1 null null");
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"a\" is a list in the parquet file but scalar bigint in the table"
+    )]
+    fn test_coerce_nested_lists_into_scalar_error() {
+        // [[1]], NULL, []
+        write_nested_int_lists(vec![0, 1], None);
+
+        Spi::run("CREATE TABLE test_table (a bigint)").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    fn cast_plan_negotiation_count() -> i64 {
+        Spi::get_one::<i64>("SELECT parquet_test.cast_plan_negotiation_count()")
+            .unwrap()
+            .unwrap()
+    }
+
+    #[pg_test]
+    fn test_cast_plan_reused_across_copies() {
+        let copy_to = format!(
+            "COPY (SELECT 1::bigint AS a, 'x' AS b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (a int, b text)").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let negotiation_count_before = cast_plan_negotiation_count();
+
+        for _ in 0..10 {
+            Spi::run(&copy_from).unwrap();
+        }
+
+        assert_eq!(cast_plan_negotiation_count(), negotiation_count_before);
+
+        // a different column list negotiates its own plan
+        let copy_from_column_list = format!(
+            "COPY test_table (a) FROM '{}' WITH (extra_columns 'ignore')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_column_list).unwrap();
+
+        assert_eq!(cast_plan_negotiation_count(), negotiation_count_before + 1);
+
+        let values =
+            Spi::get_two::<i64, i64>("SELECT count(*), count(b) FROM test_table WHERE a = 1")
+                .unwrap();
+        assert_eq!(values, (Some(12), Some(11)));
+    }
+
+    #[pg_test]
+    fn test_cast_plan_invalidated_by_alter_table() {
+        let copy_to = format!(
+            "COPY (SELECT 1::bigint AS a, 'x' AS b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (a int, b text)").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let negotiation_count_before = cast_plan_negotiation_count();
+
+        // the cached plan casts the file's bigints to int
+        Spi::run("ALTER TABLE test_table ALTER COLUMN a TYPE numeric(10,2)").unwrap();
+
+        Spi::run(&copy_from).unwrap();
+
+        assert_eq!(cast_plan_negotiation_count(), negotiation_count_before + 1);
+
+        let values = Spi::get_one::<String>(
+            "SELECT string_agg(a::text || ' ' || pg_typeof(a)::text, ', ') FROM test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "1.00 numeric, 1.00 numeric");
+    }
+
+    #[pg_test]
+    fn test_cast_plan_invalidated_by_alter_type() {
+        Spi::run("CREATE TYPE test_type AS (x int)").unwrap();
+
+        Spi::run("CREATE TABLE test_table (a int, c test_type)").unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT 1 AS a, row(2)::test_type AS c) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let negotiation_count_before = cast_plan_negotiation_count();
+
+        // the columns of the table do not change, but the fields of its composite type do
+        Spi::run("ALTER TYPE test_type ADD ATTRIBUTE y int").unwrap();
+
+        Spi::run(&copy_from).unwrap();
+
+        assert_eq!(cast_plan_negotiation_count(), negotiation_count_before + 1);
+
+        let values = Spi::get_one::<String>("SELECT string_agg(c::text, ', ') FROM test_table")
+            .unwrap()
+            .unwrap();
+        assert_eq!(values, "(2,), (2,)");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "parquet file has columns, which are not found in the table: \"b\"")]
+    fn test_cast_plan_invalidated_by_drop_column() {
+        let copy_to = format!(
+            "COPY (SELECT 1::bigint AS a, 'x' AS b) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_table (a int, b text)").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        // the second COPY renegotiates and finds the extra column
+        Spi::run("ALTER TABLE test_table DROP COLUMN b").unwrap();
+
+        Spi::run(&copy_from).unwrap();
+    }
 }
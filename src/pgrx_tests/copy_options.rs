@@ -2,11 +2,12 @@
 mod tests {
     use std::collections::HashMap;
 
+    use object_store::{local::LocalFileSystem, path::Path, ObjectStore};
     use pgrx::{pg_test, Spi};
 
     use crate::{
-        pgrx_tests::common::{CopyOptionValue, TestTable, LOCAL_TEST_FILE_PATH},
-        PgParquetCompression,
+        pgrx_tests::common::{extension_exists, CopyOptionValue, TestTable, LOCAL_TEST_FILE_PATH},
+        PgParquetCompression, PG_BACKEND_TOKIO_RUNTIME,
     };
 
     #[pg_test]
@@ -75,6 +76,7 @@ mod tests {
             format!("{}.gz", LOCAL_TEST_FILE_PATH),
             format!("{}.br", LOCAL_TEST_FILE_PATH),
             format!("{}.lz4", LOCAL_TEST_FILE_PATH),
+            format!("{}.lz4raw", LOCAL_TEST_FILE_PATH),
             format!("{}.zst", LOCAL_TEST_FILE_PATH),
         ];
 
@@ -84,6 +86,7 @@ mod tests {
             "GZIP(GzipLevel(6))",
             "BROTLI(BrotliLevel(1))",
             "LZ4",
+            "LZ4_RAW",
             "ZSTD(ZstdLevel(1))",
         ];
 
@@ -254,6 +257,130 @@ mod tests {
         assert!(result_compression.starts_with("GZIP"));
     }
 
+    #[pg_test]
+    fn test_valid_zstd_compression_levels() {
+        for compression_level in [1, 3, 22] {
+            let mut copy_options = HashMap::new();
+            copy_options.insert(
+                "compression".to_string(),
+                CopyOptionValue::StringOption("zstd".to_string()),
+            );
+            copy_options.insert(
+                "compression_level".to_string(),
+                CopyOptionValue::IntOption(compression_level),
+            );
+
+            let test_table =
+                TestTable::<i32>::new("int4".into()).with_copy_to_options(copy_options);
+            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+            test_table.assert_expected_and_result_rows();
+
+            let parquet_metadata_command = format!(
+                "select compression from parquet.metadata('{}');",
+                LOCAL_TEST_FILE_PATH
+            );
+
+            let result_compression = Spi::get_one::<String>(&parquet_metadata_command)
+                .unwrap()
+                .unwrap();
+
+            // compression level is not read properly by parquet-rs (bug)
+            assert!(result_compression.starts_with("ZSTD"));
+        }
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "valid compression range 1..=22 exceeded")]
+    fn test_invalid_zstd_compression_level() {
+        let mut copy_options = HashMap::new();
+        copy_options.insert(
+            "compression".to_string(),
+            CopyOptionValue::StringOption("zstd".to_string()),
+        );
+        copy_options.insert(
+            "compression_level".to_string(),
+            CopyOptionValue::IntOption(23),
+        );
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_copy_to_options(copy_options);
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_default_compression_guc() {
+        Spi::run("SET pg_parquet.default_compression TO 'zstd';").unwrap();
+        Spi::run("SET pg_parquet.default_compression_level TO 7;").unwrap();
+
+        // option > file extension > pg_parquet.default_compression
+        let mut gzip_option = HashMap::new();
+        gzip_option.insert(
+            "compression".to_string(),
+            CopyOptionValue::StringOption("gzip".to_string()),
+        );
+
+        let copy_cases = vec![
+            (LOCAL_TEST_FILE_PATH.to_string(), HashMap::new(), "ZSTD"),
+            (
+                format!("{}.br", LOCAL_TEST_FILE_PATH),
+                HashMap::new(),
+                "BROTLI",
+            ),
+            (LOCAL_TEST_FILE_PATH.to_string(), gzip_option, "GZIP"),
+        ];
+
+        for (uri, copy_options, expected_compression) in copy_cases {
+            let test_table = TestTable::<i32>::new("int4".into())
+                .with_uri(uri.clone())
+                .with_copy_to_options(copy_options);
+            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+            test_table.assert_expected_and_result_rows();
+
+            let parquet_metadata_command =
+                format!("select compression from parquet.metadata('{}');", uri);
+
+            let result_compression = Spi::get_one::<String>(&parquet_metadata_command)
+                .unwrap()
+                .unwrap();
+
+            // compression level is not read properly by parquet-rs (bug)
+            assert!(result_compression.starts_with(expected_compression));
+        }
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid value for parameter \"pg_parquet.default_compression\"")]
+    fn test_invalid_default_compression_guc() {
+        Spi::run("SET pg_parquet.default_compression TO 'invalid_compression';").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "invalid value for parameter \"pg_parquet.default_compression_level\""
+    )]
+    fn test_invalid_default_compression_level_guc() {
+        Spi::run("SET pg_parquet.default_compression TO 'gzip';").unwrap();
+        Spi::run("SET pg_parquet.default_compression_level TO 20;").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "compression level is not supported for \"snappy\" compression. COPY TO uses the compression option"
+    )]
+    fn test_unsupported_compression_level_with_default_compression() {
+        Spi::run("SET pg_parquet.default_compression TO 'snappy';").unwrap();
+
+        let mut copy_options = HashMap::new();
+        copy_options.insert(
+            "compression_level".to_string(),
+            CopyOptionValue::IntOption(1),
+        );
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_copy_to_options(copy_options);
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
     #[pg_test]
     #[should_panic(expected = "row_group_size must be greater than 0")]
     fn test_invalid_row_group_size() {
@@ -281,12 +408,16 @@ mod tests {
 
     #[pg_test]
     fn test_large_arrow_array_limit() {
-        // disable row group size bytes limit
+        // disable row group size bytes and batch memory limits
         let mut copy_options = HashMap::new();
         copy_options.insert(
             "row_group_size_bytes".to_string(),
             CopyOptionValue::IntOption(10_000_000_000),
         );
+        copy_options.insert(
+            "batch_memory_limit".to_string(),
+            CopyOptionValue::IntOption(10_000_000_000),
+        );
 
         let test_table = TestTable::<String>::new("text".into()).with_copy_to_options(copy_options);
         test_table.insert(
@@ -393,6 +524,149 @@ mod tests {
         assert_eq!(result_metadata, vec![10]);
     }
 
+    #[pg_test]
+    fn test_max_buffered_bytes() {
+        let total_rows = 300_000;
+        let row_group_size = 100_000;
+
+        Spi::run("set pg_parquet.upload_part_size to '5MB';").unwrap();
+
+        // ~300MB of tuples are written in 3 row groups, while buffering only 1MB of tuples
+        let copy_to_parquet = format!(
+            "copy (select i as id, repeat('a', 1000) as name from generate_series(1, {}) i) to '{}' \
+             with (row_group_size {}, max_buffered_bytes 1048576);",
+            total_rows, LOCAL_TEST_FILE_PATH, row_group_size
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let parquet_file_metadata_command = format!(
+            "select num_rows, num_row_groups from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        );
+        let result_metadata = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(&parquet_file_metadata_command, None, None)
+                .unwrap();
+
+            for row in tup_table {
+                let num_rows = row["num_rows"].value::<i64>().unwrap().unwrap();
+                let num_row_groups = row["num_row_groups"].value::<i64>().unwrap().unwrap();
+                results.push((num_rows, num_row_groups));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            result_metadata,
+            vec![(total_rows, total_rows / row_group_size)]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "max_buffered_bytes must be greater than 0")]
+    fn test_invalid_max_buffered_bytes() {
+        let mut copy_options = HashMap::new();
+        copy_options.insert(
+            "max_buffered_bytes".to_string(),
+            CopyOptionValue::IntOption(0),
+        );
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_copy_to_options(copy_options);
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_batch_memory_limit() {
+        // each 100MB value exceeds the 64MB limit, hence it is written into its own row group
+        let copy_to_parquet = format!(
+            "copy (select i as id, repeat('a', 100 * 1024 * 1024) as name from generate_series(1, 5) i) \
+             to '{}' with (row_group_size_bytes 10000000000, batch_memory_limit 67108864);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let num_row_groups = Spi::get_one::<i64>(&format!(
+            "select num_row_groups from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_row_groups, 5);
+
+        Spi::run("create table test_table (id int, name text);").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let loaded_rows = Spi::get_one::<i64>(
+            "select count(*) from test_table where length(name) = 100 * 1024 * 1024;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(loaded_rows, 5);
+    }
+
+    #[pg_test]
+    fn test_batch_memory_limit_guc() {
+        Spi::run("set pg_parquet.batch_memory_limit to '1MB';").unwrap();
+
+        // the row group is finished before the 11th row of ~100kB exceeds the limit
+        let copy_to_parquet = format!(
+            "copy (select i as id, repeat('a', 100000) as name from generate_series(1, 100) i) \
+             to '{}' with (row_group_size_bytes 10000000000);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let num_row_groups = Spi::get_one::<i64>(&format!(
+            "select num_row_groups from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_row_groups, 10);
+    }
+
+    #[pg_test]
+    fn test_batch_memory_limit_with_max_rows_per_file() {
+        let uri = "/tmp/pg_parquet_test_batch_memory_limit";
+
+        // row groups of 3 rows of ~300kB, and files of 4 rows
+        let copy_to_parquet = format!(
+            "copy (select i as id, repeat('a', 300000) as name from generate_series(1, 10) i) \
+             to '{}' with (format parquet, batch_memory_limit 1048576, max_rows_per_file 4);",
+            uri
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let num_row_groups = Spi::get_one::<Vec<i64>>(
+            "select array_agg(m.num_row_groups order by f.uri)
+             from parquet.last_copy_files() f, parquet.file_metadata(f.uri) m;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_row_groups, vec![2, 2, 1]);
+
+        std::fs::remove_dir_all(uri).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "batch_memory_limit must be greater than 0")]
+    fn test_invalid_batch_memory_limit() {
+        let mut copy_options = HashMap::new();
+        copy_options.insert(
+            "batch_memory_limit".to_string(),
+            CopyOptionValue::IntOption(0),
+        );
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_copy_to_options(copy_options);
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
     #[pg_test]
     #[should_panic(expected = "unrecognized match_by method: invalid_match_by")]
     fn test_invalid_match_by() {
@@ -407,4 +681,2125 @@ mod tests {
         test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
         test_table.assert_expected_and_result_rows();
     }
+
+    #[pg_test]
+    fn test_statistics() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table select i from generate_series(1, 10) i;";
+        Spi::run(insert_data).unwrap();
+
+        for statistics in ["none", "chunk", "page"] {
+            let copy_to_parquet = format!(
+                "copy test_table to '{}' with (statistics '{}');",
+                LOCAL_TEST_FILE_PATH, statistics
+            );
+            Spi::run(&copy_to_parquet).unwrap();
+
+            let stats_min_command = format!(
+                "select stats_min from parquet.metadata('{}');",
+                LOCAL_TEST_FILE_PATH
+            );
+            let stats_min = Spi::get_one::<String>(&stats_min_command).unwrap();
+
+            if statistics == "none" {
+                assert_eq!(stats_min, None);
+            } else {
+                assert_eq!(stats_min, Some("1".into()));
+            }
+        }
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid is not a valid statistics level")]
+    fn test_invalid_statistics() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (statistics 'invalid');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn create_non_finite_float_table() {
+        let create_type = "create type float_pair as (x float8, y float4);";
+        Spi::run(create_type).unwrap();
+
+        let create_table =
+            "create table test_table(id int, f float8, r float4, fa float8[], c float_pair);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table values
+                            (1, 1.5, 2.5, array[1.5, null], row(1.5, 2.5)),
+                            (2, 'NaN', 'Infinity', array[1.5, '-Infinity'], row('NaN', 2.5)),
+                            (3, '-Infinity', 2.5, array['NaN'::float8], row(1.5, 'Infinity'));";
+        Spi::run(insert_data).unwrap();
+
+        let create_result_table = "create table test_result (like test_table);";
+        Spi::run(create_result_table).unwrap();
+    }
+
+    #[pg_test]
+    fn test_nan_handling_keep() {
+        create_non_finite_float_table();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (nan_handling 'keep');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let different_rows = Spi::get_one::<i64>(
+            "select count(*) from (table test_table except table test_result) diff;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(different_rows, 0);
+
+        let nulls = Spi::get_one::<i64>(
+            "select count(*) from test_result where f is null or r is null or (c).x is null;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(nulls, 0);
+    }
+
+    #[pg_test]
+    fn test_nan_handling_null() {
+        create_non_finite_float_table();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (nan_handling 'null');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let result_rows = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "select f, r, fa, (c).x, (c).y from test_result order by id;",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            for row in tup_table {
+                let f = row[1].value::<f64>().unwrap();
+                let r = row[2].value::<f32>().unwrap();
+                let fa = row[3].value::<Vec<Option<f64>>>().unwrap();
+                let x = row[4].value::<f64>().unwrap();
+                let y = row[5].value::<f32>().unwrap();
+                results.push((f, r, fa, x, y));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            result_rows,
+            vec![
+                (
+                    Some(1.5),
+                    Some(2.5),
+                    Some(vec![Some(1.5), None]),
+                    Some(1.5),
+                    Some(2.5)
+                ),
+                (None, None, Some(vec![Some(1.5), None]), None, Some(2.5)),
+                (None, Some(2.5), Some(vec![None]), Some(1.5), None),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "NaN or Infinity value in column \"f\" at row 2 is not allowed with nan_handling 'error'"
+    )]
+    fn test_nan_handling_error() {
+        create_non_finite_float_table();
+
+        let copy_to_parquet = format!(
+            "copy (select id, f from test_table) to '{}' with (nan_handling 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "NaN or Infinity value in column \"fa\" at row 2")]
+    fn test_nan_handling_error_array() {
+        create_non_finite_float_table();
+
+        let copy_to_parquet = format!(
+            "copy (select id, fa from test_table) to '{}' with (nan_handling 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "NaN or Infinity value in column \"c\" at row 2")]
+    fn test_nan_handling_error_composite() {
+        create_non_finite_float_table();
+
+        // rows are written in separate record batches, so the row number counts the rows of
+        // the previous ones
+        let copy_to_parquet = format!(
+            "copy (select id, c from test_table where id != 2 order by id) to '{}'
+             with (nan_handling 'error', row_group_size 1);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn create_nested_nan_handling_copy(nan_handling: &str) -> &'static str {
+        let nested_file_path = "/tmp/pg_parquet_test_nested.parquet";
+
+        let create_function = format!(
+            "create function nested_copy() returns int language plpgsql as $$
+             begin
+                 copy (select 1 as a) to '{}' with (nan_handling '{}');
+                 return 1;
+             end $$;",
+            nested_file_path, nan_handling
+        );
+        Spi::run(&create_function).unwrap();
+
+        nested_file_path
+    }
+
+    #[pg_test]
+    fn test_nan_handling_with_nested_copy() {
+        let nested_file_path = create_nested_nan_handling_copy("keep");
+
+        // the nested COPY TO of each row does not change the option of the outer COPY TO
+        let copy_to_parquet = format!(
+            "copy (select nested_copy() as n, 'NaN'::float8 as f from generate_series(1, 2))
+             to '{}' with (nan_handling 'null');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("create table test_table(n int, f float8);").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let result =
+            Spi::get_two::<i64, i64>("select count(*), count(f) from test_table;").unwrap();
+        assert_eq!(result, (Some(2), Some(0)));
+
+        std::fs::remove_file(nested_file_path).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "NaN or Infinity value in column \"f\" at row 3")]
+    fn test_nan_handling_error_row_with_nested_copy() {
+        create_nested_nan_handling_copy("error");
+
+        // the nested COPY TO of the third row runs after the first record batch is written,
+        // and does not restart the row numbers of the outer COPY TO
+        let copy_to_parquet = format!(
+            "copy (select nested_copy() as n, case when i = 3 then 'NaN'::float8 else i end as f
+                   from generate_series(1, 3) i)
+             to '{}' with (nan_handling 'error', row_group_size 2);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid is not a valid nan_handling")]
+    fn test_invalid_nan_handling() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (nan_handling 'invalid');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn create_infinite_timestamp_table() {
+        let create_table = "create table test_table(id int, ts timestamp, tstz timestamptz[]);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table values
+                            (1, '2024-01-01 12:00:00', array['2024-01-01 12:00:00+00']::timestamptz[]),
+                            (2, 'infinity', array['-infinity', null]::timestamptz[]),
+                            (3, '-infinity', array['infinity']::timestamptz[]),
+                            (4, null, null);";
+        Spi::run(insert_data).unwrap();
+    }
+
+    fn infinite_timestamp_table_values() -> Vec<(Option<String>, Option<String>)> {
+        Spi::connect(|client| {
+            client
+                .select(
+                    "select ts::text, tstz::text from test_table order by id;",
+                    None,
+                    None,
+                )
+                .unwrap()
+                .map(|row| {
+                    (
+                        row["ts"].value::<String>().unwrap(),
+                        row["tstz"].value::<String>().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "infinite timestamp in column \"ts\" at row 2")]
+    fn test_infinite_timestamps_error() {
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select id, ts from test_table order by id) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "infinite timestamp in column \"tstz\" at row 2")]
+    fn test_infinite_timestamps_error_array() {
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select id, tstz from test_table order by id) to '{}' with (infinite_timestamps 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_infinite_timestamps_null() {
+        Spi::run("set timezone to 'UTC';").unwrap();
+
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select * from test_table order by id) to '{}' with (infinite_timestamps 'null');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("truncate test_table;").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        assert_eq!(
+            infinite_timestamp_table_values(),
+            vec![
+                (
+                    Some("2024-01-01 12:00:00".into()),
+                    Some("{\"2024-01-01 12:00:00+00\"}".into())
+                ),
+                (None, Some("{NULL,NULL}".into())),
+                (None, Some("{NULL}".into())),
+                (None, None),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_infinite_timestamps_clamp() {
+        Spi::run("set timezone to 'UTC';").unwrap();
+
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select * from test_table order by id) to '{}' with (infinite_timestamps 'clamp');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("truncate test_table;").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        assert_eq!(
+            infinite_timestamp_table_values(),
+            vec![
+                (
+                    Some("2024-01-01 12:00:00".into()),
+                    Some("{\"2024-01-01 12:00:00+00\"}".into())
+                ),
+                (
+                    Some("9999-12-31 23:59:59.999999".into()),
+                    Some("{\"0001-01-01 00:00:00+00\",NULL}".into())
+                ),
+                (
+                    Some("0001-01-01 00:00:00".into()),
+                    Some("{\"9999-12-31 23:59:59.999999+00\"}".into())
+                ),
+                (None, None),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_infinite_timestamps_sentinel() {
+        Spi::run("set timezone to 'UTC';").unwrap();
+
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select * from test_table order by id) to '{}' with (infinite_timestamps 'sentinel');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("truncate test_table;").unwrap();
+
+        // the sentinels are read back as infinities
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (infinite_timestamps 'sentinel');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        assert_eq!(
+            infinite_timestamp_table_values(),
+            vec![
+                (
+                    Some("2024-01-01 12:00:00".into()),
+                    Some("{\"2024-01-01 12:00:00+00\"}".into())
+                ),
+                (Some("infinity".into()), Some("{-infinity,NULL}".into())),
+                (Some("-infinity".into()), Some("{infinity}".into())),
+                (None, None),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "use infinite_timestamps 'sentinel' to read it as infinity")]
+    fn test_infinite_timestamps_sentinel_without_read_option() {
+        create_infinite_timestamp_table();
+
+        let copy_to_parquet = format!(
+            "copy (select id, ts from test_table where id = 3) to '{}' with (infinite_timestamps 'sentinel');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("create table test_read(id int, ts timestamp);").unwrap();
+
+        let copy_from_parquet = format!("copy test_read from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "infinite_timestamps 'clamp' is not supported by COPY FROM")]
+    fn test_infinite_timestamps_clamp_copy_from() {
+        Spi::run("create table test_table(ts timestamp);").unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (infinite_timestamps 'clamp');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_infinite_timestamps_with_nested_copy() {
+        let nested_file_path = "/tmp/pg_parquet_test_nested.parquet";
+
+        let create_function = format!(
+            "create function nested_copy() returns int language plpgsql as $$
+             begin
+                 copy (select 1 as a) to '{}' with (infinite_timestamps 'error');
+                 return 1;
+             end $$;",
+            nested_file_path
+        );
+        Spi::run(&create_function).unwrap();
+
+        // the nested COPY TO of each row does not change the option of the outer COPY TO
+        let copy_to_parquet = format!(
+            "copy (select nested_copy() as n, 'infinity'::timestamp as ts
+                   from generate_series(1, 2))
+             to '{}' with (infinite_timestamps 'null');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("create table test_table(n int, ts timestamp);").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let result =
+            Spi::get_two::<i64, i64>("select count(*), count(ts) from test_table;").unwrap();
+        assert_eq!(result, (Some(2), Some(0)));
+
+        std::fs::remove_file(nested_file_path).unwrap();
+    }
+
+    #[pg_test]
+    fn test_far_future_timestamps() {
+        Spi::run("set timezone to 'UTC';").unwrap();
+
+        let create_table = "create table test_table(ts timestamp, tstz timestamptz);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table values
+                            ('294000-06-15 12:34:56.789', '294000-06-15 12:34:56.789+00');";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("truncate test_table;").unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let values =
+            Spi::get_two::<String, String>("select ts::text, tstz::text from test_table;").unwrap();
+        assert_eq!(
+            values,
+            (
+                Some("294000-06-15 12:34:56.789".into()),
+                Some("294000-06-15 12:34:56.789+00".into())
+            )
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "is out of range of parquet timestamps")]
+    fn test_out_of_range_timestamp() {
+        // the latest timestamps of Postgres do not fit into parquet timestamps
+        let copy_to_parquet = format!(
+            "copy (select '294270-01-01 00:00:00'::timestamp as ts) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "columns with types not supported by parquet: \"a\" (")]
+    fn test_unsupported_type_error() {
+        // hstore is an extension type without a parquet counterpart. tsvector is used instead
+        // when the extension is not available.
+        let type_name = if extension_exists("hstore") {
+            Spi::run("DROP EXTENSION IF EXISTS hstore; CREATE EXTENSION hstore;").unwrap();
+            "hstore"
+        } else {
+            "tsvector"
+        };
+
+        Spi::run("CREATE TYPE color AS ENUM ('red', 'green');").unwrap();
+
+        // string types and enums are not reported
+        let create_table = format!(
+            "create table test_table(id int, a {}, b varchar(10), c color);",
+            type_name
+        );
+        Spi::run(&create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (unsupported_type 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "columns with types not supported by parquet: \"x\" (document), \"y\" (tsvector[])"
+    )]
+    fn test_unsupported_type_error_nested() {
+        Spi::run("create type document as (id int, body tsvector);").unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select row(1, 'a b')::document as x, array['a b'::tsvector] as y)
+             to '{}' with (unsupported_type 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid is not a valid unsupported_type")]
+    fn test_invalid_unsupported_type() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (unsupported_type 'invalid');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_bloom_filter_columns() {
+        let create_table = "create table test_table(id int, name text, info int[], nobloom text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data =
+            "insert into test_table select i, 'name' || i, array[i], 'x' from generate_series(1, 10) i;";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (bloom_filter_columns 'id, name,info', bloom_filter_fpp 0.01);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let bloom_filter_command = format!(
+            "select path_in_schema, bloom_filter_offset is not null and bloom_filter_length > 0
+             from parquet.metadata('{}') order by column_id;",
+            LOCAL_TEST_FILE_PATH
+        );
+        let result_bloom_filters = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&bloom_filter_command, None, None).unwrap();
+
+            for row in tup_table {
+                let path_in_schema = row[1].value::<String>().unwrap().unwrap();
+                let has_bloom_filter = row[2].value::<bool>().unwrap().unwrap_or(false);
+                results.push((path_in_schema, has_bloom_filter));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            result_bloom_filters,
+            vec![
+                ("id".into(), true),
+                ("name".into(), true),
+                ("info.list.element".into(), true),
+                ("nobloom".into(), false),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column \"nonexistent\" in bloom_filter_columns does not exist")]
+    fn test_bloom_filter_nonexistent_column() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (bloom_filter_columns 'id, nonexistent');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "bloom_filter_fpp must be between 0 and 1 (exclusive)")]
+    fn test_invalid_bloom_filter_fpp() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (bloom_filter_columns 'id', bloom_filter_fpp 1.5);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "bloom_filter_fpp requires bloom_filter_columns option")]
+    fn test_bloom_filter_fpp_without_columns() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (bloom_filter_fpp 0.1);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    // dictionary_encoded_columns returns whether the column chunks of the file are dictionary
+    // encoded, by their encodings in parquet.metadata
+    fn dictionary_encoded_columns() -> Vec<(String, bool)> {
+        let encodings_command = format!(
+            "select path_in_schema, encodings like '%DICTIONARY%'
+             from parquet.metadata('{}') order by column_id;",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&encodings_command, None, None).unwrap();
+
+            for row in tup_table {
+                let path_in_schema = row[1].value::<String>().unwrap().unwrap();
+                let is_dictionary_encoded = row[2].value::<bool>().unwrap().unwrap();
+                results.push((path_in_schema, is_dictionary_encoded));
+            }
+
+            results
+        })
+    }
+
+    #[pg_test]
+    fn test_dictionary_columns() {
+        let create_table = "create table test_table(id int, status text, tags text[], note text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table
+                           select i, 'status' || i % 2, array['tag' || i % 3], 'note' || i
+                           from generate_series(1, 100) i;";
+        Spi::run(insert_data).unwrap();
+
+        // all columns are dictionary encoded by default
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(
+            dictionary_encoded_columns(),
+            vec![
+                ("id".into(), true),
+                ("status".into(), true),
+                ("tags.list.element".into(), true),
+                ("note".into(), true),
+            ]
+        );
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (no_dictionary_columns 'id, note');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(
+            dictionary_encoded_columns(),
+            vec![
+                ("id".into(), false),
+                ("status".into(), true),
+                ("tags.list.element".into(), true),
+                ("note".into(), false),
+            ]
+        );
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (dictionary_enabled false, dictionary_columns 'status,tags',
+                                           dictionary_page_size_limit 4096);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(
+            dictionary_encoded_columns(),
+            vec![
+                ("id".into(), false),
+                ("status".into(), true),
+                ("tags.list.element".into(), true),
+                ("note".into(), false),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column \"nonexistent\" in no_dictionary_columns does not exist")]
+    fn test_dictionary_nonexistent_column() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (no_dictionary_columns 'id, nonexistent');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"id\" cannot be in both dictionary_columns and no_dictionary_columns"
+    )]
+    fn test_dictionary_column_in_both_lists() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (dictionary_columns 'id', no_dictionary_columns 'id');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "dictionary_page_size_limit must be greater than 0")]
+    fn test_invalid_dictionary_page_size_limit() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (dictionary_page_size_limit 0);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_byte_stream_split_columns() {
+        let create_table =
+            "create table test_table(id int, measurement float8, samples float4[], note text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table
+                           select i, i / 7.0, array[i / 3.0, null], 'note' || i
+                           from generate_series(1, 100) i;";
+        Spi::run(insert_data).unwrap();
+
+        for writer_version in ["1.0", "2.0"] {
+            let copy_to_parquet = format!(
+                "copy test_table to '{}' with (byte_stream_split_columns 'measurement, samples',
+                                               writer_version '{}');",
+                LOCAL_TEST_FILE_PATH, writer_version
+            );
+            Spi::run(&copy_to_parquet).unwrap();
+
+            let byte_stream_split_columns = Spi::get_one::<String>(&format!(
+                "select string_agg(path_in_schema, ',' order by column_id)
+                 from parquet.metadata('{}') where encodings like '%BYTE_STREAM_SPLIT%';",
+                LOCAL_TEST_FILE_PATH
+            ))
+            .unwrap()
+            .unwrap();
+            assert_eq!(
+                byte_stream_split_columns,
+                "measurement,samples.list.element"
+            );
+
+            Spi::run("create table test_result (like test_table);").unwrap();
+
+            let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from_parquet).unwrap();
+
+            let differing_rows = Spi::get_one::<i64>(
+                "select count(*) from (table test_table except all table test_result) diff;",
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(differing_rows, 0);
+
+            Spi::run("drop table test_result;").unwrap();
+        }
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"note\" in byte_stream_split_columns has physical type BYTE_ARRAY"
+    )]
+    fn test_byte_stream_split_variable_width_column() {
+        let copy_to_parquet = format!(
+            "copy (select 'a' as note) to '{}' with (byte_stream_split_columns 'note');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "3.0 is not a valid writer_version")]
+    fn test_invalid_writer_version() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (writer_version '3.0');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn num_row_groups() -> i64 {
+        Spi::get_one::<i64>(&format!(
+            "select num_row_groups from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_row_group_size_and_row_group_size_bytes() {
+        let create_table = "create table test_table(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table select i, 'a' from generate_series(1, 1000) i;";
+        Spi::run(insert_data).unwrap();
+
+        // row_group_size is reached first
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (row_group_size 100, row_group_size_bytes 1000000000);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(num_row_groups(), 10);
+
+        // row_group_size_bytes is reached first, each row is 5 bytes
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (row_group_size 1000000, row_group_size_bytes 500);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(num_row_groups(), 10);
+    }
+
+    fn last_copy_files() -> Vec<(String, i64, i64)> {
+        Spi::connect(|client| {
+            let tup_table = client
+                .select(
+                    "select uri, row_count, size_bytes from parquet.last_copy_files();",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            tup_table
+                .map(|row| {
+                    (
+                        row["uri"].value::<String>().unwrap().unwrap(),
+                        row["row_count"].value::<i64>().unwrap().unwrap(),
+                        row["size_bytes"].value::<i64>().unwrap().unwrap(),
+                    )
+                })
+                .collect()
+        })
+    }
+
+    #[pg_test]
+    fn test_max_rows_per_file() {
+        let create_table = "create table test_table(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table select i, 'a' from generate_series(1, 1050) i;";
+        Spi::run(insert_data).unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_max_rows_per_file";
+
+        // the files are split in the middle of the row groups as well
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (format parquet, max_rows_per_file 250, row_group_size 100);",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_files = last_copy_files();
+
+        let uris = copy_files
+            .iter()
+            .map(|(uri, _, _)| uri.clone())
+            .collect::<Vec<_>>();
+        let expected_uris = (0..5)
+            .map(|file_idx| format!("{}/part-{:05}.parquet", uri_dir, file_idx))
+            .collect::<Vec<_>>();
+        assert_eq!(uris, expected_uris);
+
+        let row_counts = copy_files
+            .iter()
+            .map(|(_, row_count, _)| *row_count)
+            .collect::<Vec<_>>();
+        assert_eq!(row_counts, vec![250, 250, 250, 250, 50]);
+        assert_eq!(row_counts.iter().sum::<i64>(), 1050);
+
+        for (uri, row_count, size_bytes) in &copy_files {
+            let file_size = std::fs::metadata(uri).unwrap().len() as i64;
+            assert_eq!(*size_bytes, file_size);
+
+            let num_rows = Spi::get_one::<i64>(&format!(
+                "select num_rows from parquet.file_metadata('{}');",
+                uri
+            ))
+            .unwrap()
+            .unwrap();
+            assert_eq!(num_rows, *row_count);
+        }
+
+        // the files have the rows in order
+        let create_table = "create table test_result(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        for uri in &uris {
+            Spi::run(&format!("copy test_result from '{}';", uri)).unwrap();
+        }
+
+        let ids_in_order = Spi::get_one::<bool>(
+            "select array_agg(id) = (select array_agg(id order by id) from test_table) from test_result;",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(ids_in_order);
+
+        // the next COPY TO replaces the listed files
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_files = last_copy_files();
+        assert_eq!(copy_files.len(), 1);
+        assert_eq!(copy_files[0].0, LOCAL_TEST_FILE_PATH);
+        assert_eq!(copy_files[0].1, 1050);
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+    }
+
+    fn last_copy_stats() -> Option<(i32, i64, i64)> {
+        Spi::connect(|client| {
+            let tup_table = client
+                .select(
+                    "select files, rows, bytes from parquet.last_copy_stats();",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            tup_table
+                .map(|row| {
+                    (
+                        row["files"].value::<i32>().unwrap().unwrap(),
+                        row["rows"].value::<i64>().unwrap().unwrap(),
+                        row["bytes"].value::<i64>().unwrap().unwrap(),
+                    )
+                })
+                .next()
+        })
+    }
+
+    // copy_to_command_tag_rows returns the number of rows in the command tag of the COPY TO.
+    fn copy_to_command_tag_rows(copy_to_parquet: &str) -> usize {
+        Spi::connect(|mut client| client.update(copy_to_parquet, None, None).unwrap().len())
+    }
+
+    #[pg_test]
+    fn test_last_copy_stats() {
+        let create_table = "create table test_table(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table select i, 'a' from generate_series(1, 1050) i;";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        assert_eq!(copy_to_command_tag_rows(&copy_to_parquet), 1050);
+
+        let file_size = std::fs::metadata(LOCAL_TEST_FILE_PATH).unwrap().len() as i64;
+        assert_eq!(last_copy_stats(), Some((1, 1050, file_size)));
+
+        let has_duration =
+            Spi::get_one::<bool>("select duration >= interval '0' from parquet.last_copy_stats();")
+                .unwrap()
+                .unwrap();
+        assert!(has_duration);
+    }
+
+    #[pg_test]
+    fn test_last_copy_stats_with_max_rows_per_file() {
+        let create_table = "create table test_table(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table select i, 'a' from generate_series(1, 1050) i;";
+        Spi::run(insert_data).unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_last_copy_stats";
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (format parquet, max_rows_per_file 250);",
+            uri_dir
+        );
+        assert_eq!(copy_to_command_tag_rows(&copy_to_parquet), 1050);
+
+        let total_size = last_copy_files()
+            .iter()
+            .map(|(uri, _, _)| std::fs::metadata(uri).unwrap().len() as i64)
+            .sum::<i64>();
+        assert_eq!(last_copy_stats(), Some((5, 1050, total_size)));
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+    }
+
+    #[pg_test]
+    fn test_last_copy_stats_after_failed_copy() {
+        let copy_to_parquet = format!(
+            "copy (select i from generate_series(1, 10) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert!(last_copy_stats().is_some());
+
+        // the COPY TO fails after it finishes the first files
+        let uri_dir = "/tmp/pg_parquet_test_last_copy_stats_failed";
+
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT 1 / (2000 - i) FROM generate_series(1, 3000) i) TO '{}'
+                WITH (format parquet, max_rows_per_file 500);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(last_copy_stats(), None);
+
+        let _ = std::fs::remove_dir_all(uri_dir);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "max_rows_per_file must be greater than 0")]
+    fn test_invalid_max_rows_per_file() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (max_rows_per_file 0);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "max_rows_per_file is not supported for temp files")]
+    fn test_max_rows_per_file_with_temp() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet =
+            "copy test_table to 'scratch' with (format parquet, temp true, max_rows_per_file 10);";
+        Spi::run(copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_if_exists_overwrite() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("insert into test_table values (1);").unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (if_exists 'overwrite');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("insert into test_table values (2);").unwrap();
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "select num_rows from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 2);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "file /tmp/pg_parquet_test.parquet already exists")]
+    fn test_if_exists_error() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (if_exists 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "directory /tmp/pg_parquet_test_if_exists_error is not empty")]
+    fn test_if_exists_error_with_directory() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("insert into test_table select generate_series(1, 10);").unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_if_exists_error";
+        let _ = std::fs::remove_dir_all(uri_dir);
+
+        // the directory does not have files yet
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (max_rows_per_file 5, if_exists 'error');",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(last_copy_files().len(), 2);
+
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_if_exists_append_suffix() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let _ = std::fs::remove_file(LOCAL_TEST_FILE_PATH);
+
+        Spi::run("insert into test_table values (1);").unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (if_exists 'append_suffix');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the file does not exist, so it is written as is
+        let copy_files = last_copy_files();
+        assert_eq!(copy_files[0].0, LOCAL_TEST_FILE_PATH);
+
+        Spi::run("insert into test_table values (2);").unwrap();
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_files = last_copy_files();
+        let suffixed_uri = &copy_files[0].0;
+
+        let suffix = suffixed_uri
+            .strip_prefix("/tmp/pg_parquet_test-")
+            .and_then(|suffix| suffix.strip_suffix(".parquet"))
+            .unwrap_or_else(|| panic!("unexpected suffixed uri {}", suffixed_uri));
+
+        // the suffix is made of the backend pid, a counter and a random number
+        let suffix_parts = suffix.split('-').collect::<Vec<_>>();
+        assert_eq!(suffix_parts.len(), 3);
+        assert!(suffix_parts[0].parse::<i32>().is_ok());
+        assert!(suffix_parts[1].parse::<u64>().is_ok());
+        assert!(u32::from_str_radix(suffix_parts[2], 16).is_ok());
+
+        // the existing file is kept
+        let num_rows = |uri: &str| {
+            Spi::get_one::<i64>(&format!(
+                "select num_rows from parquet.file_metadata('{}');",
+                uri
+            ))
+            .unwrap()
+            .unwrap()
+        };
+        assert_eq!(num_rows(LOCAL_TEST_FILE_PATH), 1);
+        assert_eq!(num_rows(suffixed_uri), 2);
+
+        std::fs::remove_file(suffixed_uri).unwrap();
+    }
+
+    #[pg_test]
+    fn test_if_exists_append_suffix_with_max_rows_per_file() {
+        let uri_dir = "/tmp/pg_parquet_test_if_exists_append_suffix";
+        let _ = std::fs::remove_dir_all(uri_dir);
+
+        let copy_to_parquet = |if_exists: &str| {
+            format!(
+                "copy (select i from generate_series(1, 10) i) to '{}'
+                 with (max_rows_per_file 5, if_exists '{}');",
+                uri_dir, if_exists
+            )
+        };
+
+        Spi::run(&copy_to_parquet("overwrite")).unwrap();
+        Spi::run(&copy_to_parquet("append_suffix")).unwrap();
+
+        // the files are written next to the existing files, with the same suffix
+        let copy_files = last_copy_files();
+        assert_eq!(copy_files.len(), 2);
+
+        let suffixes = copy_files
+            .iter()
+            .enumerate()
+            .map(|(file_idx, (uri, row_count, _))| {
+                assert_eq!(*row_count, 5);
+
+                uri.strip_prefix(&format!("{}/part-{:05}-", uri_dir, file_idx))
+                    .and_then(|suffix| suffix.strip_suffix(".parquet"))
+                    .unwrap_or_else(|| panic!("unexpected suffixed uri {}", uri))
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(suffixes[0], suffixes[1]);
+
+        let mut file_names = std::fs::read_dir(uri_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        file_names.sort();
+
+        let mut expected_file_names = vec![
+            "part-00000.parquet".to_string(),
+            format!("part-00000-{}.parquet", suffixes[0]),
+            "part-00001.parquet".to_string(),
+            format!("part-00001-{}.parquet", suffixes[0]),
+        ];
+        expected_file_names.sort();
+        assert_eq!(file_names, expected_file_names);
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+    }
+
+    #[pg_test]
+    fn test_if_exists_failed_copy_removes_created_file() {
+        let _ = std::fs::remove_file(LOCAL_TEST_FILE_PATH);
+
+        let suffixed_files = || {
+            std::fs::read_dir("/tmp")
+                .unwrap()
+                .filter_map(|entry| entry.unwrap().file_name().into_string().ok())
+                .filter(|file_name| file_name.starts_with("pg_parquet_test-"))
+                .count()
+        };
+
+        let failing_copy_to_parquet = |if_exists: &str| {
+            format!(
+                "DO $$
+                 BEGIN
+                    COPY (SELECT CASE WHEN i > 1500 THEN i / (i - i) ELSE i END AS i
+                          FROM generate_series(1, 2000) i)
+                    TO '{}' WITH (row_group_size 1000, if_exists '{}');
+                 EXCEPTION WHEN division_by_zero THEN
+                    NULL;
+                 END $$;",
+                LOCAL_TEST_FILE_PATH, if_exists
+            )
+        };
+
+        // the file, which is created before it is written, is removed
+        Spi::run(&failing_copy_to_parquet("error")).unwrap();
+        assert!(!std::path::Path::new(LOCAL_TEST_FILE_PATH).exists());
+
+        // the suffixed file is removed, and the existing file is kept
+        Spi::run(&format!(
+            "copy (select 1 as i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+
+        let suffixed_files_before = suffixed_files();
+
+        Spi::run(&failing_copy_to_parquet("append_suffix")).unwrap();
+        assert_eq!(suffixed_files(), suffixed_files_before);
+
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "select num_rows from parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 1);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized if_exists option: replace")]
+    fn test_invalid_if_exists() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (if_exists 'replace');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn written_columns() -> Vec<(String, i32)> {
+        Spi::connect(|client| {
+            let tup_table = client
+                .select(
+                    &format!(
+                        "select field_path[1] as name, field_id from parquet.schema('{}')
+                         where field_id is not null order by field_id;",
+                        LOCAL_TEST_FILE_PATH
+                    ),
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            tup_table
+                .map(|row| {
+                    (
+                        row["name"].value::<String>().unwrap().unwrap(),
+                        row["field_id"].value::<i32>().unwrap().unwrap(),
+                    )
+                })
+                .collect()
+        })
+    }
+
+    #[pg_test]
+    fn test_exclude_columns() {
+        let create_table = "create table test_table(id int, updated_at timestamptz, name text,
+                                                    audit_blob bytea);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data =
+            "insert into test_table values (1, now(), 'a', 'blob'), (2, now(), 'b', null);";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (exclude_columns 'updated_at, audit_blob');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the field ids of the written columns are contiguous
+        assert_eq!(
+            written_columns(),
+            vec![("id".to_string(), 0), ("name".to_string(), 1)]
+        );
+
+        let create_table = "create table test_result(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let result = Spi::get_one::<String>(
+            "select string_agg(id || ':' || name, ',' order by id) from test_result;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result, "1:a,2:b");
+    }
+
+    #[pg_test]
+    fn test_include_generated() {
+        let create_table =
+            "create table test_table(a int, b int generated always as (a * 2) stored,
+                                                    c text);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("insert into test_table (a, c) values (1, 'x');").unwrap();
+
+        let copy_to_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(
+            written_columns(),
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 1),
+                ("c".to_string(), 2)
+            ]
+        );
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (include_generated false);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert_eq!(
+            written_columns(),
+            vec![("a".to_string(), 0), ("c".to_string(), 1)]
+        );
+
+        let create_table = "create table test_result(a int, c text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let result = Spi::get_two::<i32, &str>("select a, c from test_result;").unwrap();
+        assert_eq!(result, (Some(1), Some("x")));
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "column \"audit\" in exclude_columns does not exist in relation \"test_table\""
+    )]
+    fn test_exclude_columns_unknown_column() {
+        let create_table = "create table test_table(id int, audit_blob bytea);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (exclude_columns 'audit');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "exclude_columns and include_generated are only supported for COPY TO from a table"
+    )]
+    fn test_exclude_columns_with_query() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id, 2 as audit) to '{}' with (exclude_columns 'audit');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn page_layout(path_in_schema: &str) -> (i64, i64, i64) {
+        let page_layout_command = format!(
+            "select num_data_pages, max_page_num_rows, max_page_compressed_size
+             from parquet.column_chunk_metadata('{}') where path_in_schema = '{}';",
+            LOCAL_TEST_FILE_PATH, path_in_schema
+        );
+
+        let (num_data_pages, max_page_num_rows, max_page_compressed_size) =
+            Spi::get_three::<i64, i64, i64>(&page_layout_command).unwrap();
+
+        (
+            num_data_pages.unwrap(),
+            max_page_num_rows.unwrap(),
+            max_page_compressed_size.unwrap(),
+        )
+    }
+
+    #[pg_test]
+    fn test_data_page_limits() {
+        let create_table = "create table test_table(id int, note text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data =
+            "insert into test_table select i, 'note' || i from generate_series(1, 1000) i;";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (compression 'uncompressed');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let (num_data_pages, max_page_num_rows, _) = page_layout("id");
+        assert_eq!((num_data_pages, max_page_num_rows), (1, 1000));
+
+        // the row count limit is checked after each write batch
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (compression 'uncompressed',
+                                           data_page_row_count_limit 100,
+                                           write_batch_size 100);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let (num_data_pages, max_page_num_rows, _) = page_layout("id");
+        assert_eq!((num_data_pages, max_page_num_rows), (10, 100));
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (compression 'uncompressed',
+                                           dictionary_enabled false,
+                                           data_page_size_bytes 1024,
+                                           write_batch_size 10);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let (num_data_pages, _, max_page_compressed_size) = page_layout("note");
+        assert!(num_data_pages >= 5);
+        assert!(max_page_compressed_size < 2048);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "data_page_size_bytes must be greater than 0")]
+    fn test_invalid_data_page_size_bytes() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (data_page_size_bytes 0);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "data_page_row_count_limit must be greater than 0")]
+    fn test_invalid_data_page_row_count_limit() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (data_page_row_count_limit -1);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "write_batch_size must be greater than 0")]
+    fn test_invalid_write_batch_size() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (write_batch_size 0);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_kv_metadata() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (kv_metadata '{{\"source\":\"orders\",\"lsn\":\"0/1A2B3C\"}}');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let kv_metadata_command = format!(
+            "select encode(key, 'escape'), encode(value, 'escape')
+             from parquet.kv_metadata('{}')
+             where encode(key, 'escape') <> 'ARROW:schema' order by 1;",
+            LOCAL_TEST_FILE_PATH
+        );
+        let result_kv_metadata = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&kv_metadata_command, None, None).unwrap();
+
+            for row in tup_table {
+                let key = row[1].value::<String>().unwrap().unwrap();
+                let value = row[2].value::<String>().unwrap().unwrap();
+                results.push((key, value));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            result_kv_metadata,
+            vec![
+                ("lsn".into(), "0/1A2B3C".into()),
+                ("source".into(), "orders".into()),
+            ]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "kv_metadata key \"geo\" is reserved")]
+    fn test_kv_metadata_reserved_key() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (kv_metadata '{{\"geo\":\"{{}}\"}}');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "kv_metadata must be a json object")]
+    fn test_invalid_kv_metadata() {
+        let copy_to_parquet = format!(
+            "copy (select 1 as id) to '{}' with (kv_metadata 'source=orders');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    fn local_test_file_e_tag() -> String {
+        PG_BACKEND_TOKIO_RUNTIME.block_on(async {
+            LocalFileSystem::new()
+                .head(&Path::from_filesystem_path(LOCAL_TEST_FILE_PATH).unwrap())
+                .await
+                .unwrap()
+                .e_tag
+                .unwrap()
+        })
+    }
+
+    #[pg_test]
+    fn test_max_row_groups_and_resume_from() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        // 10 row groups with 10 rows each
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1,100) i) to '{}' with (row_group_size 10);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let e_tag = local_test_file_e_tag();
+
+        // loads row groups 0 to 2
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (max_row_groups 3);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let max_id = Spi::get_one::<i32>("select max(id) from test_table;").unwrap();
+        assert_eq!(max_id, Some(30));
+
+        // resumes from row group 3 and loads the rest
+        for resume_row_group in [3, 6, 9] {
+            let copy_from_parquet = format!(
+                "copy test_table from '{}' with (resume_from '{}:{}', max_row_groups 3);",
+                LOCAL_TEST_FILE_PATH, resume_row_group, e_tag
+            );
+            Spi::run(&copy_from_parquet).unwrap();
+        }
+
+        let result =
+            Spi::get_two::<i64, i64>("select count(*), count(distinct id) from test_table;")
+                .unwrap();
+        assert_eq!(result, (Some(100), Some(100)));
+
+        // resuming after the last row group loads nothing
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (resume_from '10:{}');",
+            LOCAL_TEST_FILE_PATH, e_tag
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let count = Spi::get_one::<i64>("select count(*) from test_table;").unwrap();
+        assert_eq!(count, Some(100));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "is changed since the resume token is issued")]
+    fn test_resume_from_changed_file() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1,100) i) to '{}' with (row_group_size 10);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let e_tag = local_test_file_e_tag();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1,200) i) to '{}' with (row_group_size 10);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (resume_from '3:{}');",
+            LOCAL_TEST_FILE_PATH, e_tag
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "resume token row group 11 is out of range")]
+    fn test_resume_from_out_of_range_row_group() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1,100) i) to '{}' with (row_group_size 10);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (resume_from '11:{}');",
+            LOCAL_TEST_FILE_PATH,
+            local_test_file_e_tag()
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid resume token: 3")]
+    fn test_invalid_resume_from() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1,100) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (resume_from '3');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "max_row_groups must be greater than 0")]
+    fn test_invalid_max_row_groups() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (max_row_groups 0);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    // create_decimal_overflow_file writes numeric(12,2) values, 2 of which overflow numeric(5,2)
+    fn create_decimal_overflow_file() {
+        let copy_to_parquet = format!(
+            "copy (select id, (case when id % 3 = 0 then 123456.78 else id + 0.5 end)::numeric(12,2) as a
+                   from generate_series(1, 6) id) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a numeric(5,2));";
+        Spi::run(create_table).unwrap();
+    }
+
+    #[pg_test]
+    fn test_on_error_ignore() {
+        create_decimal_overflow_file();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (on_error 'ignore');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let ids = Spi::get_one::<Vec<i32>>("select array_agg(id order by id) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids, vec![1, 2, 4, 5]);
+
+        let sum = Spi::get_one::<f64>("select sum(a)::float8 from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum, 14.0);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "numeric field overflow")]
+    fn test_on_error_stop() {
+        create_decimal_overflow_file();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (on_error 'stop');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized on_error option: skip")]
+    fn test_invalid_on_error() {
+        create_decimal_overflow_file();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (on_error 'skip');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_freeze() {
+        // the table is created in the current transaction of the test
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1, 2000) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (freeze);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let total_rows = Spi::get_one::<i64>("select count(*) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(total_rows, 2000);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "cannot perform COPY FREEZE because the table was not created or truncated in the current subtransaction"
+    )]
+    fn test_freeze_table_not_created_in_subtransaction() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the exception block runs the COPY in a subtransaction
+        let copy_from_parquet = format!(
+            "do $$
+             begin
+                copy test_table from '{}' with (freeze);
+             exception when division_by_zero then
+                null;
+             end $$;",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_truncate() {
+        let create_table = "create table test_table(id int, a text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table values (1, 'old'), (2, 'old'), (3, 'old');";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id, 'new' as a from generate_series(1, 2) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let values = Spi::get_one::<String>(
+            "select string_agg(id || ' ' || a, ', ' order by id) from test_table;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "1 new, 2 new");
+
+        // the lock of the truncation is kept until the end of the transaction
+        let lock_mode = Spi::get_one::<String>(
+            "select string_agg(mode, ', ' order by mode) from pg_locks
+             where relation = 'test_table'::regclass and pid = pg_backend_pid();",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(lock_mode.contains("AccessExclusiveLock"));
+
+        // a second load into the same table in the transaction works as well
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let count = Spi::get_one::<i64>("select count(*) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[pg_test]
+    fn test_truncate_restart_identity() {
+        let create_table =
+            "create table test_table(id int generated by default as identity, a text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table (a) values ('old'), ('old'), ('old');";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!("copy (select 'new' as a) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table (a) from '{}' with (truncate true, restart_identity true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let id = Spi::get_one::<i32>("select id from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(id, 1);
+
+        // the identity continues without restart_identity
+        let copy_from_parquet = format!(
+            "copy test_table (a) from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let id = Spi::get_one::<i32>("select id from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(id, 2);
+    }
+
+    #[pg_test]
+    fn test_truncate_rollback_on_error() {
+        let create_table = "create table test_table(id int, a numeric(5,2));";
+        Spi::run(create_table).unwrap();
+
+        let insert_data = "insert into test_table values (1, 1.5), (2, 2.5);";
+        Spi::run(insert_data).unwrap();
+
+        // the last row overflows its numeric column
+        let copy_to_parquet = format!(
+            "copy (select i as id, case when i = 1000 then 123456.78 else 1.5 end::numeric(12,2) as a
+                   from generate_series(1, 1000) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the exception block rolls back the failed COPY, including its truncation
+        let copy_from_parquet = format!(
+            "do $$
+             begin
+                copy test_table from '{}' with (truncate true);
+             exception when others then
+                null;
+             end $$;",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let values = Spi::get_one::<String>(
+            "select string_agg(id || ' ' || a, ', ' order by id) from test_table;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, "1 1.50, 2 2.50");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "cannot truncate a table referenced in a foreign key constraint")]
+    fn test_truncate_referenced_by_foreign_key() {
+        let create_table = "create table test_table(id int primary key);";
+        Spi::run(create_table).unwrap();
+
+        let create_referencing_table =
+            "create table test_referencing_table(id int references test_table(id));";
+        Spi::run(create_referencing_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_truncate_cascade() {
+        let create_table = "create table test_table(id int primary key);";
+        Spi::run(create_table).unwrap();
+
+        let create_referencing_table =
+            "create table test_referencing_table(id int references test_table(id));";
+        Spi::run(create_referencing_table).unwrap();
+
+        let insert_data = "insert into test_table values (1);
+                           insert into test_referencing_table values (1);";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!("copy (select 2 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true, cascade true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let id = Spi::get_one::<i32>("select id from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(id, 2);
+
+        let referencing_rows = Spi::get_one::<i64>("select count(*) from test_referencing_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(referencing_rows, 0);
+    }
+
+    #[pg_test]
+    fn test_truncate_fires_triggers() {
+        let create_table = "create table test_table(id int);
+                            create table test_audit(event text);";
+        Spi::run(create_table).unwrap();
+
+        let create_trigger = "create function test_audit() returns trigger language plpgsql as $$
+                              begin
+                                 insert into test_audit values (tg_op || ' ' || tg_level);
+                                 return null;
+                              end $$;
+                              create trigger test_truncate_trigger after truncate on test_table
+                                 for each statement execute function test_audit();
+                              create trigger test_insert_trigger after insert on test_table
+                                 for each row execute function test_audit();";
+        Spi::run(create_trigger).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id from generate_series(1, 2) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let events = Spi::get_one::<String>("select string_agg(event, ', ') from test_audit;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(events, "TRUNCATE STATEMENT, INSERT ROW, INSERT ROW");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "truncate option is not supported for view \"test_view\"")]
+    fn test_truncate_view() {
+        let create_view = "create table test_table(id int);
+                           create view test_view as select * from test_table;";
+        Spi::run(create_view).unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_view from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "restart_identity option requires truncate true")]
+    fn test_restart_identity_without_truncate() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (restart_identity true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_on_error_ignore_all_rows() {
+        let copy_to_parquet = format!(
+            "copy (select id, 123456.78::numeric(12,2) as a from generate_series(1, 10) id) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a numeric(5,2));";
+        Spi::run(create_table).unwrap();
+
+        // none of the rows of the record batch end up in the copy buffer
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (on_error 'ignore');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let total_rows = Spi::get_one::<i64>("select count(*) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(total_rows, 0);
+    }
+
+    #[pg_test]
+    fn test_copy_from_long_text_values() {
+        let copy_to_parquet = format!(
+            "copy (select i as id, repeat('x', 100000) as a from generate_series(1, 200) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let total_length = Spi::get_one::<i64>("select sum(length(a)) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(total_length, 200 * 100000);
+
+        // the record batch of 20MB is not converted into the copy buffer at once
+        let max_buffer_size =
+            Spi::get_one::<i64>("select parquet_test.copy_from_max_buffer_size();")
+                .unwrap()
+                .unwrap();
+        assert!(max_buffer_size < 1024 * 1024);
+    }
 }
@@ -1,6 +1,11 @@
 #[pgrx::pg_schema]
 mod tests {
-    use pgrx::{pg_test, Spi};
+    use pgrx::{
+        pg_sys::{
+            AccessExclusiveLock, CheckRelationLockedByMe, Oid, RelationClose, RelationIdGetRelation,
+        },
+        pg_test, PgTryBuilder, Spi,
+    };
 
     use crate::pgrx_tests::common::LOCAL_TEST_FILE_PATH;
 
@@ -102,15 +107,106 @@ mod tests {
     }
 
     #[pg_test]
-    #[should_panic(expected = "COPY FROM parquet with generated columns is not supported")]
-    fn test_copy_from_by_position_with_generated_columns_not_supported() {
+    fn test_copy_from_by_position_with_generated_columns() {
+        Spi::run("DROP TABLE IF EXISTS test_table;").unwrap();
+
+        Spi::run(
+            "CREATE TABLE test_table (a int, b int generated always as (a * 10) stored, c text);",
+        )
+        .unwrap();
+
+        // the file has the generated column at its position, which is skipped
+        Spi::run("INSERT INTO test_table (a, c) VALUES (1, 'one');").unwrap();
+
+        let copy_to_query = format!(
+            "COPY (SELECT * FROM test_table) TO '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_to_query.as_str()).unwrap();
+
+        Spi::run("TRUNCATE test_table;").unwrap();
+
+        let copy_from_query = format!(
+            "COPY test_table FROM '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_from_query.as_str()).unwrap();
+
+        // the file has only the non-generated columns
+        let copy_to_query = format!(
+            "COPY (SELECT 2 as a, 'two' as c) TO '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_to_query.as_str()).unwrap();
+
+        Spi::run(copy_from_query.as_str()).unwrap();
+
+        let result = Spi::get_one::<String>(
+            "SELECT string_agg(format('%s,%s,%s', a, b, c), ' ' ORDER BY a) FROM test_table;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result, "1,10,one 2,20,two");
+    }
+
+    #[pg_test]
+    fn test_copy_from_with_dropped_identity_and_generated_columns() {
+        Spi::run("DROP TABLE IF EXISTS test_table;").unwrap();
+
+        Spi::run(
+            "CREATE TABLE test_table (id int generated always as identity, x int, d int,
+                                      y int generated always as (x * 2) stored, z text);",
+        )
+        .unwrap();
+
+        // the dropped column sits between the live columns
+        Spi::run("ALTER TABLE test_table DROP COLUMN d;").unwrap();
+
+        let copy_to_query = format!(
+            "COPY (SELECT 10 as id, 1 as x, 2 as y, 'a' as z
+                   UNION ALL SELECT 20, 2, 4, 'b') TO '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_to_query.as_str()).unwrap();
+
+        // identity values are loaded from the file like COPY does without OVERRIDING
+        let copy_from_query = format!(
+            "COPY test_table FROM '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_from_query.as_str()).unwrap();
+
+        // missing identity values are generated by the identity sequence
+        let copy_to_query = format!(
+            "COPY (SELECT 'c' as z, 3 as x) TO '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_to_query.as_str()).unwrap();
+
+        let copy_from_query = format!(
+            "COPY test_table FROM '{}' WITH (format parquet, match_by 'name', missing_columns 'default');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(copy_from_query.as_str()).unwrap();
+
+        let result = Spi::get_one::<String>(
+            "SELECT string_agg(format('%s,%s,%s,%s', id, x, y, z), ' ' ORDER BY x) FROM test_table;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(result, "10,1,2,a 20,2,4,b 1,3,6,c");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "column \"b\" is a generated column")]
+    fn test_copy_from_with_generated_column_in_column_list() {
         Spi::run("DROP TABLE IF EXISTS test_table;").unwrap();
 
         Spi::run("CREATE TABLE test_table (a int, b int generated always as (10) stored, c text);")
             .unwrap();
 
         let copy_from_query = format!(
-            "COPY test_table FROM '{}' WITH (format parquet);",
+            "COPY test_table (a, b) FROM '{}' WITH (format parquet);",
             LOCAL_TEST_FILE_PATH
         );
         Spi::run(copy_from_query.as_str()).unwrap();
@@ -238,12 +334,110 @@ mod tests {
         assert_eq!(result, vec![3, 4, 5]);
     }
 
+    #[pg_test]
+    fn test_with_where_clause_skipping_pages() {
+        let create_table = "create table test_table(id int, d date);";
+        Spi::run(create_table).unwrap();
+
+        // 10 pages of 100 rows
+        let copy_to_parquet = format!(
+            "copy (select i as id, date '2024-01-01' + i as d from generate_series(1,1000) i)
+             to '{}' with (data_page_row_count_limit 100, write_batch_size 100);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        for max_decode_threads in [1, 2] {
+            Spi::run("truncate test_table;").unwrap();
+
+            Spi::run(&format!(
+                "set pg_parquet.max_decode_threads to {};",
+                max_decode_threads
+            ))
+            .unwrap();
+
+            let copy_from_parquet = format!(
+                "copy test_table from '{}' where 250 < id and d <= date '2025-02-24';",
+                LOCAL_TEST_FILE_PATH
+            );
+            Spi::run(&copy_from_parquet).unwrap();
+
+            let result = Spi::get_three::<i64, i32, i32>(
+                "select count(*), min(id), max(id) from test_table;",
+            )
+            .unwrap();
+            assert_eq!(result, (Some(170), Some(251), Some(420)));
+        }
+    }
+
     #[pg_test]
     #[should_panic(expected = "duplicate attribute \"a\" is not allowed in parquet schema")]
     fn test_with_duplicate_column_name() {
         Spi::run(
             format!(
-                "copy (select 1 as a, 2 as a) to '{}';",
+                "copy (select 1 as a, 2 as a) to '{}' with (duplicate_columns 'error');",
+                LOCAL_TEST_FILE_PATH
+            )
+            .as_str(),
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_with_renamed_duplicate_column_names() {
+        Spi::run(
+            format!(
+                "copy (select 1 as a, 2 as a, 3 as a_1, 4 as a) to '{}';",
+                LOCAL_TEST_FILE_PATH
+            )
+            .as_str(),
+        )
+        .unwrap();
+
+        let field_names = Spi::get_one::<String>(&format!(
+            "select string_agg(field_path[1], ',') from parquet.schema('{}') \
+             where cardinality(field_path) = 1;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(field_names, "a,a_2,a_1,a_3");
+
+        Spi::run("create table test_table (a int, a_2 int, a_1 int, a_3 int);").unwrap();
+
+        Spi::run(&format!(
+            "copy test_table from '{}' with (match_by 'name');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+
+        let result =
+            Spi::get_one::<String>("select concat_ws(',', a, a_1, a_2, a_3) from test_table;")
+                .unwrap()
+                .unwrap();
+        assert_eq!(result, "1,3,2,4");
+    }
+
+    #[pg_test]
+    fn test_with_unnamed_columns() {
+        Spi::run(format!("copy (select 1, 2) to '{}';", LOCAL_TEST_FILE_PATH).as_str()).unwrap();
+
+        let field_names = Spi::get_one::<String>(&format!(
+            "select string_agg(field_path[1], ',') from parquet.schema('{}') \
+             where cardinality(field_path) = 1;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(field_names, "?column?,?column?_1");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "ignore is not a valid duplicate_columns")]
+    fn test_invalid_duplicate_columns() {
+        Spi::run(
+            format!(
+                "copy (select 1 as a) to '{}' with (duplicate_columns 'ignore');",
                 LOCAL_TEST_FILE_PATH
             )
             .as_str(),
@@ -304,33 +498,283 @@ mod tests {
     }
 
     #[pg_test]
-    #[should_panic(expected = "cannot copy from partitioned table \"partitioned_table\"")]
+    fn test_copy_with_program() {
+        let create_table = "create table test_table(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let insert_data =
+            "insert into test_table select i, 'name_' || i from generate_series(1, 10) i;";
+        Spi::run(insert_data).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to program 'cat > {}' with (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the file is written by the program as a regular parquet file
+        let create_table = "create table test_file_result(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_file_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let create_table = "create table test_result(id int, name text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_result from program 'cat {}' with (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let mismatches = Spi::get_one::<i64>(
+            "select count(*) from (
+                (table test_table except all table test_result)
+                union all
+                (table test_result except all table test_table)
+                union all
+                (table test_file_result except all table test_table)
+             ) t;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatches, 0);
+
+        let result_count = Spi::get_one::<i64>("select count(*) from test_result;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result_count, 10);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "program \"exit 3\" failed")]
+    fn test_copy_from_failed_program() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = "copy test_table from program 'exit 3' with (format parquet);";
+        Spi::run(copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "program \"exit 3\" failed")]
+    fn test_copy_to_failed_program() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = "copy test_table to program 'exit 3' with (format parquet);";
+        Spi::run(copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "temp option is not supported for COPY PROGRAM")]
+    fn test_copy_with_program_and_temp() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet =
+            "copy test_table to program 'cat > /dev/null' with (format parquet, temp);";
+        Spi::run(copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied to COPY to or from an external program")]
+    fn test_copy_with_program_no_execute_privilege() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let create_role = "create role test_role;";
+        Spi::run(create_role).unwrap();
+
+        let grant_role = "grant ALL ON ALL TABLES IN SCHEMA public TO test_role;
+                          grant pg_write_server_files TO test_role;";
+        Spi::run(grant_role).unwrap();
+
+        let set_role = "set role test_role;";
+        Spi::run(set_role).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to program 'cat > {}' with (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
     fn test_copy_to_partitioned_table() {
-        let create_table = "create table partitioned_table(id int) partition by range (id);";
+        let create_table =
+            "create table partitioned_table(id int, a text, b int) partition by range (id);";
         Spi::run(create_table).unwrap();
 
         let create_partition =
             "create table partitioned_table_1 partition of partitioned_table for values from (1) to (11);";
         Spi::run(create_partition).unwrap();
 
-        let insert_data = "insert into partitioned_table select i from generate_series(1, 10) i;";
+        // the attached partition has a dropped column and a different column order
+        let create_partition = "create table partitioned_table_2(x int, b int, a text, id int);";
+        Spi::run(create_partition).unwrap();
+
+        Spi::run("alter table partitioned_table_2 drop column x;").unwrap();
+
+        let attach_partition =
+            "alter table partitioned_table attach partition partitioned_table_2 for values from (11) to (21);";
+        Spi::run(attach_partition).unwrap();
+
+        let insert_data =
+            "insert into partitioned_table select i, 'a' || i, i * 10 from generate_series(1, 20) i;";
         Spi::run(insert_data).unwrap();
 
-        // success via query form
+        let copy_to_parquet = format!("copy partitioned_table to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a text, b int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let mismatched_rows = Spi::get_one::<i64>(
+            "select count(*) from (select * from partitioned_table
+                                   except
+                                   select * from test_table) t;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatched_rows, 0);
+
+        let total_rows = Spi::get_one::<i64>("select count(*) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(total_rows, 20);
+
+        // column list
         let copy_to_parquet = format!(
-            "copy (select * from partitioned_table) to '{}';",
+            "copy partitioned_table (b, id) to '{}';",
             LOCAL_TEST_FILE_PATH
         );
         Spi::run(&copy_to_parquet).unwrap();
 
-        let total_rows = Spi::get_one::<i64>("select count(*) from partitioned_table;")
-            .unwrap()
-            .unwrap();
-        assert_eq!(total_rows, 10);
+        let columns = Spi::get_one::<Vec<String>>(&format!(
+            "select array_agg(path_in_schema order by column_id) from parquet.metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(columns, vec!["b", "id"]);
+    }
+
+    #[pg_test]
+    fn test_copy_to_hash_partitioned_table() {
+        let create_table = "create table partitioned_table(id int, a text) partition by hash (id);";
+        Spi::run(create_table).unwrap();
+
+        for remainder in 0..4 {
+            let create_partition = format!(
+                "create table partitioned_table_{remainder} partition of partitioned_table
+                 for values with (modulus 4, remainder {remainder});"
+            );
+            Spi::run(&create_partition).unwrap();
+        }
+
+        let insert_data =
+            "insert into partitioned_table select i, 'a' || i from generate_series(1, 1000) i;";
+        Spi::run(insert_data).unwrap();
 
-        // should fail with partitioned table
         let copy_to_parquet = format!("copy partitioned_table to '{}';", LOCAL_TEST_FILE_PATH);
         Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let (total_rows, id_sum) = Spi::get_two::<i64, i64>(
+            "select count(*), sum(id) from test_table where a = 'a' || id;",
+        )
+        .unwrap();
+        assert_eq!(total_rows, Some(1000));
+        assert_eq!(id_sum, Some(500500));
+    }
+
+    #[pg_test]
+    fn test_copy_to_partitioned_table_one_file_per_partition() {
+        let create_table =
+            "create table partitioned_table(id int, a text) partition by range (id);";
+        Spi::run(create_table).unwrap();
+
+        let create_partition =
+            "create table partitioned_table_1 partition of partitioned_table for values from (1) to (11);";
+        Spi::run(create_partition).unwrap();
+
+        // sub-partitions are written to the files of their leaf partitions
+        let create_partition =
+            "create table partitioned_table_2 partition of partitioned_table for values from (11) to (31)
+             partition by range (id);";
+        Spi::run(create_partition).unwrap();
+
+        let create_partition =
+            "create table partitioned_table_2_1 partition of partitioned_table_2 for values from (11) to (21);";
+        Spi::run(create_partition).unwrap();
+
+        let create_partition =
+            "create table partitioned_table_2_2 partition of partitioned_table_2 for values from (21) to (31);";
+        Spi::run(create_partition).unwrap();
+
+        let insert_data =
+            "insert into partitioned_table select i, 'a' || i from generate_series(1, 30) i;";
+        Spi::run(insert_data).unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_partitions";
+
+        let copy_to_parquet = format!(
+            "copy partitioned_table to '{}' with (format parquet, one_file_per_partition true);",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_table = "create table test_table(id int, a text);";
+        Spi::run(create_table).unwrap();
+
+        for (partition_name, min_id, max_id) in [
+            ("partitioned_table_1", 1, 10),
+            ("partitioned_table_2_1", 11, 20),
+            ("partitioned_table_2_2", 21, 30),
+        ] {
+            Spi::run("truncate test_table;").unwrap();
+
+            let copy_from_parquet = format!(
+                "copy test_table from '{}/{}.parquet';",
+                uri_dir, partition_name
+            );
+            Spi::run(&copy_from_parquet).unwrap();
+
+            let (min, max) =
+                Spi::get_two::<i32, i32>("select min(id), max(id) from test_table;").unwrap();
+            assert_eq!((min, max), (Some(min_id), Some(max_id)));
+        }
+
+        let partition_file_exists =
+            std::path::Path::new(&format!("{}/partitioned_table_2.parquet", uri_dir)).exists();
+        assert!(!partition_file_exists);
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "one_file_per_partition is only supported for COPY TO from a partitioned table"
+    )]
+    fn test_copy_to_one_file_per_partition_with_regular_table() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (one_file_per_partition true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
     }
 
     #[pg_test]
@@ -499,4 +943,187 @@ mod tests {
         let copy_from_parquet = format!("copy test_table to '{}';", LOCAL_TEST_FILE_PATH);
         Spi::run(&copy_from_parquet).unwrap();
     }
+
+    #[pg_test]
+    fn test_copy_from_with_triggers_and_transition_tables() {
+        let create_tables = "create table test_table(id int, name text);
+                             create table trigger_log(event text, row_count int);";
+        Spi::run(create_tables).unwrap();
+
+        let create_trigger_functions = "
+            create function log_before_statement() returns trigger language plpgsql as $$
+            begin
+                insert into trigger_log values ('before statement', null);
+                return null;
+            end $$;
+
+            create function upper_name() returns trigger language plpgsql as $$
+            begin
+                new.name := upper(new.name);
+                return new;
+            end $$;
+
+            create function log_after_statement() returns trigger language plpgsql as $$
+            begin
+                insert into trigger_log select 'after statement', count(*) from new_rows;
+                return null;
+            end $$;";
+        Spi::run(create_trigger_functions).unwrap();
+
+        let create_triggers = "
+            create trigger before_statement before insert on test_table
+                for each statement execute function log_before_statement();
+            create trigger before_row before insert on test_table
+                for each row execute function upper_name();
+            create trigger after_statement after insert on test_table
+                referencing new table as new_rows
+                for each statement execute function log_after_statement();";
+        Spi::run(create_triggers).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select i as id, 'name_' || i as name from generate_series(1, 3) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let names =
+            Spi::get_one::<String>("select string_agg(name, ',' order by id) from test_table;")
+                .unwrap()
+                .unwrap();
+        assert_eq!(names, "NAME_1,NAME_2,NAME_3");
+
+        let trigger_log = Spi::get_one::<String>(
+            "select string_agg(event || ':' || coalesce(row_count::text, ''), ',' order by event desc)
+             from trigger_log;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(trigger_log, "before statement:,after statement:3");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "COPY FROM not supported with row-level security")]
+    fn test_copy_from_with_forced_row_level_security() {
+        let create_table = "create table test_table(username text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!(
+            "copy (select 'dummy' as username) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the policy would reject the row, but COPY FROM rejects the table, as it does for csv
+        let create_username_policy =
+            "create policy test_policy on test_table with check (username = current_user);";
+        Spi::run(create_username_policy).unwrap();
+
+        let enable_rls = "alter table test_table enable row level security;
+                          alter table test_table force row level security;";
+        Spi::run(enable_rls).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied for table test_table")]
+    fn test_copy_from_with_no_insert_privilege() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_role = "create role test_role;";
+        Spi::run(create_role).unwrap();
+
+        let grant_role = "grant pg_read_server_files to test_role;
+                          grant select on test_table to test_role;";
+        Spi::run(grant_role).unwrap();
+
+        let set_role = "set role test_role;";
+        Spi::run(set_role).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied for table test_table")]
+    fn test_truncate_with_no_truncate_privilege() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_role = "create role test_role;";
+        Spi::run(create_role).unwrap();
+
+        let grant_server_file_read = "grant pg_read_server_files to test_role;";
+        Spi::run(grant_server_file_read).unwrap();
+
+        let grant_table = "grant select, insert on test_table to test_role;";
+        Spi::run(grant_table).unwrap();
+
+        let set_role = "set role test_role;";
+        Spi::run(set_role).unwrap();
+
+        // insert privilege is enough to load, but not to truncate
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied for table test_table")]
+    fn test_truncate_with_no_privilege_does_not_lock() {
+        let create_table = "create table test_table(id int);";
+        Spi::run(create_table).unwrap();
+
+        let relation_oid = Spi::get_one::<Oid>("select 'test_table'::regclass::oid;")
+            .unwrap()
+            .unwrap();
+
+        let copy_to_parquet = format!("copy (select 1 as id) to '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let create_role = "create role test_role;";
+        Spi::run(create_role).unwrap();
+
+        let grant_server_file_read = "grant pg_read_server_files to test_role;";
+        Spi::run(grant_server_file_read).unwrap();
+
+        let set_role = "set role test_role;";
+        Spi::run(set_role).unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_table from '{}' with (truncate true);",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        // the error is caught without aborting the transaction, which would release the locks
+        // of the failed COPY. An AccessExclusiveLock would block the readers of the table in
+        // the other sessions until the transaction ends.
+        PgTryBuilder::new(|| Spi::run(&copy_from_parquet).unwrap())
+            .catch_others(|cause| {
+                let locked = unsafe {
+                    let relation = RelationIdGetRelation(relation_oid);
+                    let locked = CheckRelationLockedByMe(relation, AccessExclusiveLock as _, true);
+                    RelationClose(relation);
+                    locked
+                };
+
+                assert!(!locked, "failed COPY locked the table");
+
+                cause.rethrow()
+            })
+            .execute();
+    }
 }
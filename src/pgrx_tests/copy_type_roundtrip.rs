@@ -1,21 +1,33 @@
 #[pgrx::pg_schema]
 mod tests {
+    use std::fs::File;
     use std::vec;
 
+    use crate::arrow_parquet::schema_parser::{
+        ARROW_EXTENSION_METADATA_META_KEY, ARROW_EXTENSION_NAME_META_KEY,
+        ARROW_JSON_EXTENSION_NAME, PG_TIMETZ_TYPE_NAME, PG_TYPE_META_KEY,
+    };
+
     use crate::pgrx_tests::common::{
-        assert_double, assert_float, assert_int_text_map, extension_exists,
-        timetz_array_to_utc_time_array, timetz_to_utc_time, TestResult, TestTable,
+        assert_double, assert_float, assert_int_text_map, extension_exists, TestResult, TestTable,
         LOCAL_TEST_FILE_PATH,
     };
     use crate::type_compat::fallback_to_text::FallbackToText;
     use crate::type_compat::geometry::{
-        Geometry, GeometryColumnsMetadata, GeometryEncoding, GeometryType,
+        BboxCovering, Geometry, GeometryColumnsMetadata, GeometryCovering, GeometryEncoding,
+        GeometryType, GEOARROW_WKB_EXTENSION_NAME,
     };
     use crate::type_compat::map::Map;
+    use crate::type_compat::money::Money;
+    use crate::type_compat::network_address::{Cidr, Inet, MacAddr, MacAddr8};
     use crate::type_compat::pg_arrow_type_conversions::{
-        DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE,
+        DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE, MAX_NUMERIC_PRECISION,
     };
-    use pgrx::pg_sys::Oid;
+    use crate::type_compat::server_encoding::{encoding_to_utf8, utf8_to_encoding};
+    use arrow_schema::{DataType, Field, TimeUnit};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::arrow::arrow_writer::{ArrowWriter, ArrowWriterOptions};
+    use pgrx::pg_sys::{pg_enc, Oid};
     use pgrx::{
         composite_type,
         datum::{Date, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone},
@@ -54,6 +66,80 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    #[should_panic(
+        expected = "multidimensional arrays are not supported, found 2 dimensions in column \"a\""
+    )]
+    fn test_multidim_array_default_error() {
+        let copy_to_command = format!(
+            "copy (select '{{{{1,2}},{{3,4}}}}'::int4[] as a) to '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    fn test_multidim_array_nested() {
+        Spi::run("create table test_expected (id int, a int4[], b text[]);").unwrap();
+        Spi::run(
+            "insert into test_expected values
+                (1, '{{1,2,3},{4,null,6}}', '{{{a},{b}},{{c},{d}}}'),
+                (2, null, null),
+                (3, '{}', '{}'),
+                (4, '{{7,8,9}}', '{{{e},{f}}}');",
+        )
+        .unwrap();
+
+        let copy_to_command = format!(
+            "copy test_expected to '{}' with (multidim_arrays 'nested')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let element_type = Spi::get_one::<String>(&format!(
+            "select parquet_type from parquet.schema('{}') \
+             where field_path = ARRAY['a', 'list', 'element', 'list', 'element'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(element_type, "INT32");
+
+        let postgres_type = Spi::get_one::<String>(&format!(
+            "select postgres_type from parquet.schema('{}') where field_path = ARRAY['b'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(postgres_type, "text[]");
+
+        Spi::run("create table test_result (like test_expected);").unwrap();
+
+        let copy_from_command = format!("copy test_result from '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let mismatched_rows = Spi::get_one::<i64>(
+            "select count(*) from test_expected e full join test_result r using (id)
+             where e.a is distinct from r.a or e.b is distinct from r.b;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatched_rows, 0);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "found 1 dimensions in column \"a\", which is written as 2 nested lists"
+    )]
+    fn test_multidim_array_nested_different_dims() {
+        let copy_to_command = format!(
+            "copy (select '{{{{1,2}},{{3,4}}}}'::int4[] as a union all select '{{5,6}}'::int4[]) \
+             to '{}' with (multidim_arrays 'nested')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
     #[pg_test]
     fn test_int8() {
         let test_table = TestTable::<i64>::new("int8".into());
@@ -157,6 +243,48 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_bool_schema() {
+        Spi::run("CREATE TYPE flags AS (flag bool, flags bool[]);").unwrap();
+        Spi::run("CREATE TABLE test_bool (id int, a bool, b bool[], c flags);").unwrap();
+        Spi::run(
+            "INSERT INTO test_bool VALUES (1, true, array[true,null,false], ROW(false, array[null,true])),
+                                          (2, null, null, ROW(null, null)),
+                                          (3, false, array[]::bool[], null);",
+        )
+        .unwrap();
+
+        let copy_to_command = format!("COPY test_bool TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        let parquet_schema_command = format!(
+            "select array_agg(distinct parquet_type) from parquet.schema('{}')
+             WHERE parquet_type IS NOT NULL AND field_path[1] <> 'id';",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        let parquet_types = Spi::get_one::<Vec<String>>(&parquet_schema_command)
+            .unwrap()
+            .unwrap();
+        assert_eq!(parquet_types, vec!["BOOLEAN"]);
+
+        Spi::run("CREATE TABLE test_bool_result (LIKE test_bool);").unwrap();
+
+        let copy_from_command = format!("COPY test_bool_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let rows_query =
+            |table: &str| format!("select array_agg(t::text order by id) from {} t;", table);
+
+        let expected_rows = Spi::get_one::<Vec<String>>(&rows_query("test_bool"))
+            .unwrap()
+            .unwrap();
+        let result_rows = Spi::get_one::<Vec<String>>(&rows_query("test_bool_result"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected_rows, result_rows);
+    }
+
     #[pg_test]
     fn test_text() {
         let test_table = TestTable::<String>::new("text".into());
@@ -173,6 +301,43 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_non_ascii_text() {
+        let test_table = TestTable::<String>::new("text".into());
+        test_table
+            .insert("INSERT INTO test_expected (a) VALUES ('café'), ('日本語'), ('😀'), (null);");
+        test_table.assert_expected_and_result_rows();
+
+        let test_table = TestTable::<FallbackToText>::new("varchar(10)".into());
+        test_table.insert("INSERT INTO test_expected (a) VALUES ('café'), ('日本語'), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_text_encoding_conversion() {
+        let latin1 = pg_enc::PG_LATIN1 as i32;
+
+        assert_eq!(encoding_to_utf8(b"caf\xe9", latin1), "café");
+        assert_eq!(utf8_to_encoding("café", latin1), b"caf\xe9");
+
+        let sql_ascii = pg_enc::PG_SQL_ASCII as i32;
+
+        assert_eq!(encoding_to_utf8(b"cafe", sql_ascii), "cafe");
+        assert_eq!(utf8_to_encoding("café", sql_ascii), "café".as_bytes());
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "has no equivalent in encoding \"LATIN1\"")]
+    fn test_text_without_equivalent_in_server_encoding() {
+        utf8_to_encoding("日本", pg_enc::PG_LATIN1 as i32);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "cannot convert the value to UTF-8")]
+    fn test_text_invalid_in_server_encoding() {
+        encoding_to_utf8(b"caf\xe9", pg_enc::PG_SQL_ASCII as i32);
+    }
+
     #[pg_test]
     fn test_varchar() {
         let test_table = TestTable::<FallbackToText>::new("varchar".into());
@@ -205,6 +370,52 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_bpchar_with_length() {
+        let test_table = TestTable::<FallbackToText>::new("char(5)".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES ('ab   '), ('ab'), ('öç€'), ('abcde'), (''), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+
+        // trailing blanks are not written to the file
+        Spi::run("CREATE TABLE test_text (a text);").unwrap();
+
+        let copy_from_command = format!("COPY test_text FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let values = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(a ORDER BY a) FROM test_text WHERE a IS NOT NULL;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(values, vec!["", "ab", "ab", "abcde", "öç€"]);
+    }
+
+    #[pg_test]
+    fn test_bpchar_array_with_length() {
+        let test_table = TestTable::<Vec<Option<FallbackToText>>>::new("char(3)[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array['a  ','öç€',null]), (array['ab']), (null), (array[]::char(3)[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "value too long for type character(5)")]
+    fn test_bpchar_value_too_long() {
+        let copy_to_command = format!(
+            "COPY (SELECT 'abcdef'::text AS a) TO '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("CREATE TABLE test_bpchar (a char(5));").unwrap();
+
+        let copy_from_command = format!("COPY test_bpchar FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+    }
+
     #[pg_test]
     fn test_name() {
         let test_table = TestTable::<FallbackToText>::new("name".into());
@@ -248,6 +459,70 @@ mod tests {
         Spi::run(drop_enum_query).unwrap();
     }
 
+    #[pg_test]
+    fn test_enums_in_composite() {
+        Spi::run("CREATE TYPE color AS ENUM ('red', 'green', 'blue');").unwrap();
+        Spi::run("CREATE TYPE size AS ENUM ('small', 'large');").unwrap();
+        Spi::run("CREATE TYPE item AS (color color, size size, sizes size[]);").unwrap();
+        Spi::run("CREATE TABLE test_enums (id int, a color, b size[], c item, d item[]);").unwrap();
+        Spi::run(
+            "INSERT INTO test_enums
+             SELECT i,
+                    (array['red','green','blue'])[i % 3 + 1]::color,
+                    array['small', null, 'large']::size[],
+                    ROW('blue', 'small', array['large'])::item,
+                    array[ROW('green', null, null)::item, null]
+             FROM generate_series(1, 100) i
+             UNION ALL
+             SELECT 101, null, null, null, null;",
+        )
+        .unwrap();
+
+        let copy_to_command = format!("COPY test_enums TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        // the labels are dictionary encoded
+        let metadata_command = format!(
+            "SELECT bool_and(dictionary_page_offset IS NOT NULL) FROM parquet.metadata('{}')
+             WHERE path_in_schema <> 'id';",
+            LOCAL_TEST_FILE_PATH
+        );
+        let dictionary_encoded = Spi::get_one::<bool>(&metadata_command).unwrap().unwrap();
+        assert!(dictionary_encoded);
+
+        Spi::run("CREATE TABLE test_enums_result (LIKE test_enums);").unwrap();
+
+        let copy_from_command = format!("COPY test_enums_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let rows_query =
+            |table: &str| format!("select array_agg(t::text order by id) from {} t;", table);
+
+        let expected_rows = Spi::get_one::<Vec<String>>(&rows_query("test_enums"))
+            .unwrap()
+            .unwrap();
+        let result_rows = Spi::get_one::<Vec<String>>(&rows_query("test_enums_result"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected_rows, result_rows);
+
+        // the dictionary encoded labels are also read into text columns
+        Spi::run("CREATE TABLE test_enums_text (id int, a text, b text[]);").unwrap();
+
+        let copy_from_command = format!(
+            "COPY test_enums_text FROM '{}' WITH (extra_columns 'ignore');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_command).unwrap();
+
+        let colors = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(DISTINCT a ORDER BY a) FROM test_enums_text WHERE a IS NOT NULL;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(colors, vec!["blue", "green", "red"]);
+    }
+
     #[pg_test]
     #[should_panic(expected = "invalid input value for enum color: \"red\"")]
     fn test_enum_invalid_value() {
@@ -262,6 +537,201 @@ mod tests {
         Spi::run(drop_enum_query).unwrap();
     }
 
+    fn domain_column_schema() -> (Option<String>, Option<String>) {
+        let parquet_schema_command = format!(
+            "select parquet_type, logical_type from parquet.schema('{}') WHERE field_path = ARRAY['a'];",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        Spi::get_two::<String, String>(&parquet_schema_command).unwrap()
+    }
+
+    #[pg_test]
+    fn test_domain_over_int() {
+        Spi::run("CREATE DOMAIN positive_int AS int CHECK (VALUE > 0);").unwrap();
+        Spi::run("CREATE TABLE test_expected (a positive_int);").unwrap();
+        Spi::run("CREATE TABLE test_result (a positive_int);").unwrap();
+        Spi::run("INSERT INTO test_expected VALUES (1), (2), (null);").unwrap();
+
+        // domains over supported types are not reported as unsupported types
+        let copy_to_command = format!(
+            "COPY test_expected TO '{}' WITH (unsupported_type 'error');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        assert_eq!(
+            domain_column_schema(),
+            (Some("INT32".into()), Some("INTEGER".into()))
+        );
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let result = Spi::get_one::<Vec<Option<i32>>>(
+            "SELECT array_agg(a::int ORDER BY a) FROM test_result;",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(result, vec![Some(1), Some(2), None]);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "violates check constraint \"positive_int_check\"")]
+    fn test_domain_over_int_check_on_copy_from() {
+        Spi::run("CREATE DOMAIN positive_int AS int CHECK (VALUE > 0);").unwrap();
+        Spi::run("CREATE TABLE test_result (a positive_int);").unwrap();
+
+        let copy_to_command = format!(
+            "COPY (SELECT -1 AS a) TO '{}' WITH (format parquet);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+    }
+
+    #[pg_test]
+    fn test_domain_over_numeric_with_typmod() {
+        Spi::run("CREATE DOMAIN price AS numeric(10,2);").unwrap();
+        Spi::run("CREATE TABLE test_expected (a price);").unwrap();
+        Spi::run("CREATE TABLE test_result (a price);").unwrap();
+        Spi::run("INSERT INTO test_expected VALUES (12.5), (-0.01), (null);").unwrap();
+
+        let copy_to_command = format!("COPY test_expected TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        assert_eq!(
+            domain_column_schema(),
+            (Some("INT64".into()), Some("DECIMAL".into()))
+        );
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let result = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(a::text ORDER BY a) FROM test_result;",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![Some("-0.01".into()), Some("12.50".into()), None]
+        );
+    }
+
+    #[pg_test]
+    fn test_domain_over_timestamptz() {
+        Spi::run("CREATE DOMAIN event_time AS timestamptz NOT NULL;").unwrap();
+        Spi::run("CREATE TABLE test_expected (a event_time);").unwrap();
+        Spi::run("CREATE TABLE test_result (a event_time);").unwrap();
+        Spi::run(
+            "INSERT INTO test_expected VALUES ('2024-01-01 10:00:00+03'), ('1999-12-31 23:59:59+00');",
+        )
+        .unwrap();
+
+        let copy_to_command = format!("COPY test_expected TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        assert_eq!(
+            domain_column_schema(),
+            (Some("INT64".into()), Some("TIMESTAMP".into()))
+        );
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let mismatched_rows = Spi::get_one::<i64>(
+            "SELECT count(*) FROM (TABLE test_expected EXCEPT TABLE test_result) t;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatched_rows, 0);
+    }
+
+    #[pg_test]
+    fn test_array_of_domain_and_domain_over_array() {
+        Spi::run("CREATE DOMAIN price AS numeric(10,2) CHECK (VALUE >= 0);").unwrap();
+        Spi::run("CREATE DOMAIN prices AS price[];").unwrap();
+        Spi::run("CREATE DOMAIN int_list AS int[] CHECK (cardinality(VALUE) > 0);").unwrap();
+        Spi::run("CREATE TABLE test_expected (a price[], b prices, c int_list);").unwrap();
+        Spi::run("CREATE TABLE test_result (a price[], b prices, c int_list);").unwrap();
+        Spi::run(
+            "INSERT INTO test_expected VALUES (array[1.5, null], array[2.25], array[1, 2]),
+                                              (null, null, null);",
+        )
+        .unwrap();
+
+        let copy_to_command = format!("COPY test_expected TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        let parquet_schema_command = format!(
+            "SELECT array_agg(parquet_type || ':' || coalesce(logical_type, '') ORDER BY field_path)
+             FROM parquet.schema('{}') WHERE parquet_type IS NOT NULL;",
+            LOCAL_TEST_FILE_PATH
+        );
+        let schema = Spi::get_one::<Vec<String>>(&parquet_schema_command)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            schema,
+            vec!["INT64:DECIMAL", "INT64:DECIMAL", "INT32:INTEGER"]
+        );
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let rows_query = |table: &str| {
+            format!(
+                "SELECT array_agg(t::text ORDER BY t::text) FROM {} t;",
+                table
+            )
+        };
+
+        let expected_rows = Spi::get_one::<Vec<String>>(&rows_query("test_expected"))
+            .unwrap()
+            .unwrap();
+        let result_rows = Spi::get_one::<Vec<String>>(&rows_query("test_result"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected_rows, result_rows);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "violates check constraint \"price_check\"")]
+    fn test_array_of_domain_check_on_copy_from() {
+        Spi::run("CREATE DOMAIN price AS numeric(10,2) CHECK (VALUE >= 0);").unwrap();
+        Spi::run("CREATE TABLE test_result (a price[]);").unwrap();
+
+        let copy_to_command = format!(
+            "COPY (SELECT array[1.5, -1]::numeric(10,2)[] AS a) TO '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "violates check constraint \"int_list_check\"")]
+    fn test_domain_over_array_check_on_copy_from() {
+        Spi::run("CREATE DOMAIN int_list AS int[] CHECK (cardinality(VALUE) > 0);").unwrap();
+        Spi::run("CREATE TABLE test_result (a int_list);").unwrap();
+
+        let copy_to_command = format!(
+            "COPY (SELECT array[]::int[] AS a) TO '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+    }
+
     #[pg_test]
     fn test_bit() {
         let test_table = TestTable::<FallbackToText>::new("bit".into());
@@ -598,50 +1068,78 @@ mod tests {
     fn test_timetz() {
         let test_table = TestTable::<TimeWithTimeZone>::new("timetz".into());
         test_table.insert(
-            "INSERT INTO test_expected (a) VALUES ('15:00:00+03'), ('15:30:12-03'), (null);",
+            "INSERT INTO test_expected (a) VALUES ('15:00:00+03'), ('15:30:12-03'), ('10:00:00+05:30'), ('00:00:00+00'), ('23:59:59.999999-09:30'), ('01:00:00+14'), (null);",
         );
-        let TestResult { expected, result } = test_table.select_expected_and_result_rows();
-
-        // timetz is converted to utc timetz after copying to parquet,
-        // so we need to the results to utc before comparing them
-        let expected = expected
-            .into_iter()
-            .map(|(timetz,)| (timetz.and_then(timetz_to_utc_time),))
-            .collect::<Vec<_>>();
-
-        let result = result
-            .into_iter()
-            .map(|(timetz,)| (timetz.and_then(timetz_to_utc_time),))
-            .collect::<Vec<_>>();
-
-        let normalized_test_result = TestResult { expected, result };
-
-        normalized_test_result.assert();
+        test_table.assert_expected_and_result_rows();
     }
 
     #[pg_test]
     fn test_timetz_array() {
         let test_table = TestTable::<Vec<Option<TimeWithTimeZone>>>::new("timetz[]".into());
         test_table.insert(
-            "INSERT INTO test_expected (a) VALUES (array['15:00:00+03','15:30:12-03',null]::timetz[]), (null), (array[]::timetz[]);",
+            "INSERT INTO test_expected (a) VALUES (array['15:00:00+03','15:30:12-03','10:00:00+05:30','04:30:00-09:30',null]::timetz[]), (null), (array[]::timetz[]);",
         );
-        let TestResult { expected, result } = test_table.select_expected_and_result_rows();
+        test_table.assert_expected_and_result_rows();
+    }
 
-        // timetz is converted to utc timetz after copying to parquet,
-        // so we need to the results to utc before comparing them
-        let expected = expected
-            .into_iter()
-            .map(|(timetz,)| (timetz.and_then(timetz_array_to_utc_time_array),))
-            .collect::<Vec<_>>();
+    #[pg_test]
+    fn test_timetz_keeps_offset() {
+        let create_table = "CREATE TABLE test_expected (a timetz, b timetz[]);
+                            CREATE TABLE test_result (a timetz, b timetz[]);";
+        Spi::run(create_table).unwrap();
 
-        let result = result
-            .into_iter()
-            .map(|(timetz,)| (timetz.and_then(timetz_array_to_utc_time_array),))
-            .collect::<Vec<_>>();
+        let insert =
+            "INSERT INTO test_expected VALUES ('10:00:00+05:30', array['10:00:00-09:30']), \
+                                                          ('04:30:00+00', array['04:30:00+00']);";
+        Spi::run(insert).unwrap();
+
+        let copy_to = format!("COPY test_expected TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
 
-        let normalized_test_result = TestResult { expected, result };
+        // the same instant in another zone is not equal to the original timetz
+        let mismatches = Spi::get_one::<i64>(
+            "SELECT count(*) FROM test_expected e FULL JOIN test_result r
+             ON e.a = r.a AND e.b = r.b AND e.a::text = r.a::text
+             WHERE e.a IS NULL OR r.a IS NULL",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatches, 0);
+
+        let offset = Spi::get_one::<String>(
+            "SELECT a::text FROM test_result WHERE a = '10:00:00+05:30'::timetz",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(offset, "10:00:00+05:30");
+
+        let file = File::open(LOCAL_TEST_FILE_PATH).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let schema = reader.schema();
+
+        let timetz_field = schema.field_with_name("a").unwrap();
+        assert_eq!(
+            timetz_field.metadata().get(PG_TYPE_META_KEY).cloned(),
+            Some(PG_TIMETZ_TYPE_NAME.to_string())
+        );
 
-        normalized_test_result.assert();
+        let DataType::Struct(timetz_fields) = timetz_field.data_type() else {
+            panic!("expected struct field");
+        };
+        let timetz_fields = timetz_fields
+            .iter()
+            .map(|field| (field.name().clone(), field.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timetz_fields,
+            vec![
+                ("time".into(), DataType::Time64(TimeUnit::Microsecond)),
+                ("offset_seconds".into(), DataType::Int32),
+            ]
+        );
     }
 
     #[pg_test]
@@ -704,21 +1202,168 @@ mod tests {
     }
 
     #[pg_test]
-    fn test_json() {
-        let test_table =
-            TestTable::<FallbackToText>::new("json".into()).with_order_by_col("a->>'a'".into());
-        test_table.insert("INSERT INTO test_expected (a) VALUES ('{\"a\":\"test_json_1\"}'), ('{\"a\":\"test_json_2\"}'), (null);");
-        let TestResult { expected, result } = test_table.select_expected_and_result_rows();
+    fn test_inet() {
+        let test_table = TestTable::<Inet>::new("inet".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES ('192.168.0.1'), ('192.168.0.1/24'), ('10.0.0.0/8'),
+                                                  ('::1'), ('2001:db8::1/64'), ('::ffff:1.2.3.4'), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
 
-        for ((expected,), (actual,)) in expected.into_iter().zip(result.into_iter()) {
-            assert_eq!(expected, actual);
-        }
+        // host and network addresses keep their text form
+        let result = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(a::text ORDER BY a) FROM test_result",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                Some("10.0.0.0/8".into()),
+                Some("192.168.0.1/24".into()),
+                Some("192.168.0.1".into()),
+                Some("::1".into()),
+                Some("::ffff:1.2.3.4".into()),
+                Some("2001:db8::1/64".into()),
+                None
+            ]
+        );
     }
 
     #[pg_test]
-    fn test_json_array() {
-        let test_table = TestTable::<Vec<Option<FallbackToText>>>::new("json[]".into())
-            .with_order_by_col("a::text[]".into());
+    fn test_inet_array() {
+        let test_table = TestTable::<Vec<Option<Inet>>>::new("inet[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array['192.168.0.1','2001:db8::/32',null]::inet[]), (null), (array[]::inet[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_cidr() {
+        let test_table = TestTable::<Cidr>::new("cidr".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES ('192.168.0.0/24'), ('192.168.0.1'), ('0.0.0.0/0'),
+                                                  ('2001:db8::/32'), ('::1/128'), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_cidr_array() {
+        let test_table = TestTable::<Vec<Option<Cidr>>>::new("cidr[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array['10.0.0.0/8','2001:db8::/32',null]::cidr[]), (null), (array[]::cidr[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_inet_schema() {
+        Spi::run("CREATE TABLE test_inet (a inet, b cidr, c macaddr, d macaddr8);").unwrap();
+
+        let copy_to_command = format!("COPY test_inet TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        let parquet_schema_command = format!(
+            "select array_to_string(field_path, '.') AS field_path, parquet_type, logical_type
+             from parquet.schema('{}') WHERE parquet_type IS NOT NULL ORDER BY field_path;",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        let schema = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&parquet_schema_command, None, None).unwrap();
+
+            for row in tup_table {
+                let field_path = row["field_path"].value::<String>().unwrap().unwrap();
+                let parquet_type = row["parquet_type"].value::<String>().unwrap().unwrap();
+                let logical_type = row["logical_type"].value::<String>().unwrap();
+
+                results.push((field_path, parquet_type, logical_type));
+            }
+
+            results
+        });
+
+        let inet_schema = |name: &str| {
+            vec![
+                (format!("{}.addr", name), "BYTE_ARRAY".into(), None),
+                (
+                    format!("{}.bits", name),
+                    "INT32".into(),
+                    Some("INTEGER".into()),
+                ),
+                (
+                    format!("{}.family", name),
+                    "INT32".into(),
+                    Some("INTEGER".into()),
+                ),
+                (format!("{}.is_cidr", name), "BOOLEAN".into(), None),
+            ]
+        };
+
+        let mut expected_schema = inet_schema("a");
+        expected_schema.extend(inet_schema("b"));
+        expected_schema.push(("c".into(), "FIXED_LEN_BYTE_ARRAY(6)".into(), None));
+        expected_schema.push(("d".into(), "FIXED_LEN_BYTE_ARRAY(8)".into(), None));
+
+        assert_eq!(schema, expected_schema);
+    }
+
+    #[pg_test]
+    fn test_macaddr() {
+        let test_table = TestTable::<MacAddr>::new("macaddr".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES ('08:00:2b:01:02:03'), ('ff:ff:ff:ff:ff:ff'), ('00:00:00:00:00:00'), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_macaddr_array() {
+        let test_table = TestTable::<Vec<Option<MacAddr>>>::new("macaddr[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array['08:00:2b:01:02:03',null]::macaddr[]), (null), (array[]::macaddr[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_macaddr8() {
+        let test_table = TestTable::<MacAddr8>::new("macaddr8".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES ('08:00:2b:01:02:03:04:05'), ('08:00:2b:01:02:03'), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_macaddr8_array() {
+        let test_table = TestTable::<Vec<Option<MacAddr8>>>::new("macaddr8[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array['08:00:2b:01:02:03:04:05',null]::macaddr8[]), (null), (array[]::macaddr8[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_json() {
+        let test_table =
+            TestTable::<FallbackToText>::new("json".into()).with_order_by_col("a->>'a'".into());
+        test_table.insert("INSERT INTO test_expected (a) VALUES ('{\"a\":\"test_json_1\"}'), ('{\"a\":\"test_json_2\"}'), (null);");
+        let TestResult { expected, result } = test_table.select_expected_and_result_rows();
+
+        for ((expected,), (actual,)) in expected.into_iter().zip(result.into_iter()) {
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[pg_test]
+    fn test_json_array() {
+        let test_table = TestTable::<Vec<Option<FallbackToText>>>::new("json[]".into())
+            .with_order_by_col("a::text[]".into());
         test_table.insert("INSERT INTO test_expected (a) VALUES (array['{\"a\":\"test_json_1\"}','{\"a\":\"test_json_2\"}',null]::json[]), (null), (array[]::json[]);");
         let TestResult { expected, result } = test_table.select_expected_and_result_rows();
 
@@ -750,6 +1395,74 @@ mod tests {
         }
     }
 
+    #[pg_test]
+    fn test_json_arrow_extension_type() {
+        let create_table = "CREATE TABLE test_table (a json, b jsonb, c jsonb[], d text);";
+        Spi::run(create_table).unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let file = File::open(LOCAL_TEST_FILE_PATH).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let schema = reader.schema();
+
+        let extension_name =
+            |field: &Field| field.metadata().get(ARROW_EXTENSION_NAME_META_KEY).cloned();
+
+        let json_field = schema.field_with_name("a").unwrap();
+        assert_eq!(json_field.data_type(), &DataType::Utf8);
+        assert_eq!(
+            extension_name(json_field),
+            Some(ARROW_JSON_EXTENSION_NAME.to_string())
+        );
+
+        let jsonb_field = schema.field_with_name("b").unwrap();
+        assert_eq!(jsonb_field.data_type(), &DataType::Utf8);
+        assert_eq!(
+            extension_name(jsonb_field),
+            Some(ARROW_JSON_EXTENSION_NAME.to_string())
+        );
+
+        let jsonb_array_field = schema.field_with_name("c").unwrap();
+        let DataType::List(jsonb_element_field) = jsonb_array_field.data_type() else {
+            panic!("expected list field");
+        };
+        assert_eq!(
+            extension_name(jsonb_element_field),
+            Some(ARROW_JSON_EXTENSION_NAME.to_string())
+        );
+
+        let text_field = schema.field_with_name("d").unwrap();
+        assert_eq!(extension_name(text_field), None);
+    }
+
+    #[pg_test]
+    fn test_jsonb_semantic_equality() {
+        let create_table = "CREATE TABLE test_expected (a jsonb);
+                            CREATE TABLE test_result (a jsonb);";
+        Spi::run(create_table).unwrap();
+
+        let insert = "INSERT INTO test_expected VALUES ('{\"b\": [1, 2.50, null], \"a\": {\"c\": true}}'), (null);";
+        Spi::run(insert).unwrap();
+
+        let copy_to = format!("COPY test_expected TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        // jsonb equality does not depend on the key order
+        let equal = Spi::get_one::<bool>(
+            "SELECT (SELECT array_agg(a ORDER BY a) FROM test_expected) =
+                    (SELECT array_agg(a ORDER BY a) FROM test_result);",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(equal);
+    }
+
     #[pg_test]
     fn test_small_numeric() {
         let attribute_schema_getter = || -> Vec<(Option<i32>, Option<i32>, String, String)> {
@@ -831,9 +1544,75 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_decimal256_numeric() {
+        // (38 < P <= 76) => FIXED_LEN_BYTE_ARRAY(17-32)
+        let test_table = TestTable::<AnyNumeric>::new("numeric(50,10)".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (0.0), (1.), (-2.12313), (null),
+                    ((repeat('9', 40) || '.' || repeat('9', 10))::numeric),
+                    (('-' || repeat('9', 40) || '.' || repeat('9', 10))::numeric);",
+        );
+        test_table.assert_expected_and_result_rows();
+
+        let postgres_type = Spi::get_one::<String>(&format!(
+            "select postgres_type || ' ' || parquet_type from parquet.schema('{}') WHERE field_path = ARRAY['a'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(postgres_type, "numeric(50,10) FIXED_LEN_BYTE_ARRAY(21)");
+
+        let test_table = TestTable::<AnyNumeric>::new("numeric(76,0)".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (0), (-1), (null),
+                    (repeat('9', 76)::numeric), (('-' || repeat('9', 76))::numeric);",
+        );
+        test_table.assert_expected_and_result_rows();
+
+        let postgres_type = Spi::get_one::<String>(&format!(
+            "select postgres_type || ' ' || parquet_type from parquet.schema('{}') WHERE field_path = ARRAY['a'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(postgres_type, "numeric(76,0) FIXED_LEN_BYTE_ARRAY(32)");
+    }
+
+    #[pg_test]
+    fn test_decimal256_numeric_array() {
+        let test_table = TestTable::<Vec<Option<AnyNumeric>>>::new("numeric(50,10)[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES
+                    (array[0.0, -2.12313, null, (repeat('9', 40) || '.' || repeat('9', 10))::numeric, ('-' || repeat('9', 40) || '.1')::numeric]),
+                    (null), (array[]::numeric(50,10)[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_unbounded_numeric_decimal256_options() {
+        let copy_to_command = format!(
+            "copy (select (repeat('9', 60) || '.12345')::numeric as a) to '{}' \
+             with (numeric_precision 70, numeric_scale 5)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("create table test_table(a numeric);").unwrap();
+
+        let copy_from_command = format!("copy test_table from '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let value = Spi::get_one::<String>("select a::text from test_table")
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, format!("{}.12345", "9".repeat(60)));
+    }
+
     #[pg_test]
     fn test_large_numeric() {
-        let large_precision = DEFAULT_UNBOUNDED_NUMERIC_PRECISION + 1;
+        let large_precision = MAX_NUMERIC_PRECISION + 1;
 
         let test_table =
             TestTable::<FallbackToText>::new(format!("numeric({},4)", large_precision));
@@ -845,7 +1624,7 @@ mod tests {
 
     #[pg_test]
     fn test_large_numeric_array() {
-        let large_precision = DEFAULT_UNBOUNDED_NUMERIC_PRECISION + 1;
+        let large_precision = MAX_NUMERIC_PRECISION + 1;
 
         let test_table = TestTable::<Vec<Option<FallbackToText>>>::new(format!(
             "numeric({},4)[]",
@@ -966,6 +1745,139 @@ mod tests {
         Spi::run(&copy_to_command).unwrap();
     }
 
+    #[pg_test]
+    fn test_unbounded_numeric_precision_and_scale_options() {
+        let copy_to_command = format!(
+            "copy (select a::numeric from (values (1.25), (-123456.5), (null)) as t(a)) to '{}' \
+             with (numeric_precision 10, numeric_scale 3)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let postgres_type = Spi::get_one::<String>(&format!(
+            "select postgres_type from parquet.schema('{}') WHERE field_path = ARRAY['a'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(postgres_type, "numeric(10,3)");
+
+        Spi::run("create table test_table(a numeric);").unwrap();
+
+        let copy_from_command = format!("copy test_table from '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let values = Spi::get_one::<Vec<Option<String>>>(
+            "select array_agg(a::text order by a nulls last) from test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![Some("-123456.500".into()), Some("1.250".into()), None]
+        );
+    }
+
+    #[pg_test]
+    fn test_unbounded_numeric_overflow_round() {
+        let copy_to_command = format!(
+            "copy (select a::numeric from (values (1.2345), (-9.999), (0.001)) as t(a)) to '{}' \
+             with (numeric_precision 4, numeric_scale 2, numeric_overflow 'round')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("create table test_table(a numeric);").unwrap();
+
+        let copy_from_command = format!("copy test_table from '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let values = Spi::get_one::<Vec<Option<String>>>(
+            "select array_agg(a::text order by a) from test_table",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some("-10.00".into()),
+                Some("0.00".into()),
+                Some("1.23".into())
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_unbounded_numeric_overflow_round_matches_numeric_cast() {
+        // values with more fractional digits than numeric_scale, including exact halves,
+        // are rounded the same way as the cast to numeric(20, 4)
+        Spi::run(
+            "select setseed(0.5);
+             create table test_expected as
+                select i as id,
+                       round(((random() - 0.5) * 10 ^ (i % 12))::numeric, i % 15) as a
+                from generate_series(1, 10000) i
+                union all
+                select 10000 + i, (i::numeric / 20000) * (case when i % 2 = 0 then 1 else -1 end)
+                from generate_series(1, 1000) i;",
+        )
+        .unwrap();
+
+        let copy_to_command = format!(
+            "copy test_expected to '{}' \
+             with (numeric_precision 20, numeric_scale 4, numeric_overflow 'round')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("create table test_result(id int, a numeric);").unwrap();
+
+        let copy_from_command = format!("copy test_result from '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let mismatches = Spi::get_one::<i64>(
+            "select count(*) from test_expected e full join test_result r using (id)
+             where r.a is distinct from e.a::numeric(20, 4)",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatches, 0);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "numeric value contains 3 digits before decimal point, which exceeds max allowed integral digits 2 during copy to parquet"
+    )]
+    fn test_unbounded_numeric_overflow_round_integral_digits() {
+        // rounding adds an integral digit
+        let copy_to_command = format!(
+            "copy (select 99.996::numeric as a) to '{}' \
+             with (numeric_precision 4, numeric_scale 2, numeric_overflow 'round')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "numeric_scale must be between 0 and numeric_precision 10")]
+    fn test_invalid_numeric_scale_option() {
+        let copy_to_command = format!(
+            "copy (select 1::numeric as a) to '{}' with (numeric_precision 10, numeric_scale 11)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "numeric_precision must be between 1 and 76")]
+    fn test_invalid_numeric_precision_option() {
+        let copy_to_command = format!(
+            "copy (select 1::numeric as a) to '{}' with (numeric_precision 77)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
     #[cfg(feature = "pg14")]
     #[pg_test]
     #[should_panic = "NUMERIC scale -2 must be between 0 and precision 5"]
@@ -1008,6 +1920,25 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_numeric_at_precision() {
+        let test_table = TestTable::<AnyNumeric>::new("numeric(5, 2)".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (999.99), (-999.99), (0.01), (-0.01), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[cfg(not(feature = "pg14"))]
+    #[pg_test]
+    fn test_numeric_negative_scale_at_precision() {
+        let test_table = TestTable::<AnyNumeric>::new("numeric(7, -2)".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (999999949), (-999999949), (12345678.9), (50), (null);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
     #[pg_test]
     #[should_panic = "Special numeric values like NaN, Inf, -Inf are not allowed"]
     fn test_numeric_nan() {
@@ -1024,6 +1955,80 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_money() {
+        let test_table = TestTable::<Money>::new("money".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (0), (12.34), (-12.34), (1000000), (null),
+                                                  ('-92233720368547758.08'), ('92233720368547758.07');",
+        );
+        test_table.assert_expected_and_result_rows();
+
+        // money is written as decimal, which does not depend on lc_monetary
+        let parquet_schema_command = format!(
+            "select parquet_type, logical_type from parquet.schema('{}') WHERE field_path = ARRAY['a'];",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        let (parquet_type, logical_type) =
+            Spi::get_two::<String, String>(&parquet_schema_command).unwrap();
+
+        assert_eq!(parquet_type, Some("FIXED_LEN_BYTE_ARRAY(9)".into()));
+        assert_eq!(logical_type, Some("DECIMAL".into()));
+    }
+
+    #[pg_test]
+    fn test_money_array() {
+        let test_table = TestTable::<Vec<Option<Money>>>::new("money[]".into());
+        test_table.insert(
+            "INSERT INTO test_expected (a) VALUES (array[0,12.34,-12.34,null]::money[]), (null),
+                                                  (array['-92233720368547758.08','92233720368547758.07']::money[]),
+                                                  (array[]::money[]);",
+        );
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    fn test_money_lc_monetary() {
+        let de_locale = Spi::get_one::<String>(
+            "SELECT min(collcollate) FROM pg_collation WHERE collname IN ('de_DE.utf8', 'de_DE')",
+        )
+        .unwrap();
+
+        // de_DE locale is not available
+        let Some(de_locale) = de_locale else {
+            return;
+        };
+
+        Spi::run("CREATE TABLE test_money (a money);").unwrap();
+
+        // written as "1.234,56 €" in text
+        Spi::run(&format!("SET lc_monetary TO '{}';", de_locale)).unwrap();
+
+        Spi::run("INSERT INTO test_money VALUES (1234.56), (-0.01), (null);").unwrap();
+
+        let copy_to_command = format!("COPY test_money TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("SET lc_monetary TO 'C';").unwrap();
+
+        Spi::run("TRUNCATE test_money;").unwrap();
+
+        let copy_from_command = format!("COPY test_money FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        let result = Spi::get_one::<Vec<Option<String>>>(
+            "SELECT array_agg(a::numeric::text ORDER BY a) FROM test_money;",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![Some("-0.01".into()), Some("1234.56".into()), None]
+        );
+    }
+
     #[pg_test]
     fn test_geometry() {
         // Skip the test if postgis extension is not available
@@ -1169,8 +2174,788 @@ mod tests {
     }
 
     #[pg_test]
-    fn test_complex_composite() {
-        Spi::run("CREATE TYPE dog AS (name text, age int);").unwrap();
+    fn test_postgis_context_not_initialized_without_geometry() {
+        let postgis_context_init_count_query = "SELECT parquet_test.postgis_context_init_count();";
+
+        let init_count_before = Spi::get_one::<i64>(postgis_context_init_count_query)
+            .unwrap()
+            .unwrap();
+
+        Spi::run("CREATE TABLE test_table (a int, b text);").unwrap();
+
+        for _ in 0..1000 {
+            let copy_to = format!(
+                "COPY (SELECT 1 as a, 'a' as b) TO '{}'",
+                LOCAL_TEST_FILE_PATH
+            );
+            Spi::run(&copy_to).unwrap();
+
+            let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from).unwrap();
+        }
+
+        let init_count_after = Spi::get_one::<i64>(postgis_context_init_count_query)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(init_count_before, init_count_after);
+    }
+
+    #[pg_test]
+    fn test_postgis_context_cached_across_copies() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let postgis_context_init_count_query = "SELECT parquet_test.postgis_context_init_count();";
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 1)') as a) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+
+        Spi::run(&copy_to).unwrap();
+
+        let init_count_before = Spi::get_one::<i64>(postgis_context_init_count_query)
+            .unwrap()
+            .unwrap();
+
+        for _ in 0..100 {
+            Spi::run(&copy_to).unwrap();
+        }
+
+        let init_count_after = Spi::get_one::<i64>(postgis_context_init_count_query)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(init_count_before, init_count_after);
+
+        // recreating the extension invalidates the cached context
+        Spi::run(query).unwrap();
+
+        Spi::run(&copy_to).unwrap();
+
+        let init_count_after_recreate = Spi::get_one::<i64>(postgis_context_init_count_query)
+            .unwrap()
+            .unwrap();
+
+        assert!(init_count_after_recreate > init_count_after);
+
+        Spi::run("CREATE TABLE test_table (a geometry);").unwrap();
+
+        let copy_from = format!("COPY test_table FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let geometry_wkt = Spi::get_one::<String>("SELECT ST_AsText(a) FROM test_table;");
+        assert_eq!(geometry_wkt, Ok(Some("POINT(1 1)".into())));
+    }
+
+    #[pg_test]
+    fn test_geometry_geoarrow_extension_type() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let create_table = "CREATE TABLE test_table (a geometry(point, 4326), b geometry);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run("INSERT INTO test_table VALUES (ST_GeomFromText('POINT(1 2)', 4326), ST_GeomFromText('LINESTRING(0 0, 1 1)'));").unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let file = File::open(LOCAL_TEST_FILE_PATH).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let schema = reader.schema();
+
+        let extension = |field: &Field| {
+            (
+                field.metadata().get(ARROW_EXTENSION_NAME_META_KEY).cloned(),
+                field
+                    .metadata()
+                    .get(ARROW_EXTENSION_METADATA_META_KEY)
+                    .cloned(),
+            )
+        };
+
+        let point_field = schema.field_with_name("a").unwrap();
+        assert_eq!(point_field.data_type(), &DataType::Binary);
+        assert_eq!(
+            extension(point_field),
+            (
+                Some(GEOARROW_WKB_EXTENSION_NAME.to_string()),
+                Some(r#"{"crs":"EPSG:4326","crs_type":"authority_code"}"#.to_string())
+            )
+        );
+
+        let geometry_field = schema.field_with_name("b").unwrap();
+        assert_eq!(
+            extension(geometry_field),
+            (
+                Some(GEOARROW_WKB_EXTENSION_NAME.to_string()),
+                Some("{}".to_string())
+            )
+        );
+
+        // geoparquet metadata is still written
+        let geoparquet_metadata_exists = Spi::get_one::<bool>(&format!(
+            "select count(*) = 1 from parquet.kv_metadata('{LOCAL_TEST_FILE_PATH}')
+             where encode(key, 'escape') = 'geo';"
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(geoparquet_metadata_exists);
+
+        Spi::run("CREATE TABLE test_result (LIKE test_table);").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let geometries_equal = Spi::get_one::<bool>(
+            "SELECT ST_Equals(r.a, t.a) AND ST_SRID(r.a) = 4326 AND ST_Equals(r.b, t.b)
+             FROM test_result r, test_table t;",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(geometries_equal);
+    }
+
+    // create_annotated_types_table creates test_expected with the columns, whose Postgres
+    // types are kept in the field metadata of the embedded arrow schema
+    fn create_annotated_types_table() {
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        Spi::run("CREATE TYPE color AS ENUM ('red', 'green', 'blue');").unwrap();
+
+        Spi::run("CREATE TABLE test_expected (a timetz, b color, c char(5), d geometry);").unwrap();
+
+        Spi::run(
+            "INSERT INTO test_expected VALUES
+                ('10:30:00+05:30', 'green', 'ab', ST_GeomFromText('POINT(1 1)')),
+                ('23:59:59.999999-08', 'blue', 'abcde', ST_GeomFromText('LINESTRING(0 0, 1 1)')),
+                (null, null, null, null);",
+        )
+        .unwrap();
+
+        Spi::run("CREATE TABLE test_result (LIKE test_expected);").unwrap();
+    }
+
+    fn assert_annotated_types_table_roundtrip() {
+        let mismatch_count = Spi::get_one::<i64>(
+            "WITH expected AS (SELECT a::text, b, c, ST_AsEWKT(d) FROM test_expected),
+                  result AS (SELECT a::text, b, c, ST_AsEWKT(d) FROM test_result)
+             SELECT count(*) FROM ((TABLE expected EXCEPT ALL TABLE result)
+                                   UNION ALL
+                                   (TABLE result EXCEPT ALL TABLE expected)) mismatches;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatch_count, 0);
+    }
+
+    #[pg_test]
+    fn test_arrow_schema_roundtrip() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        create_annotated_types_table();
+
+        let copy_to_command = format!("COPY test_expected TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        // the arrow schema is embedded in the footer
+        let has_arrow_schema = Spi::get_one::<bool>(&format!(
+            "SELECT count(*) = 1 FROM parquet.kv_metadata('{}') WHERE key = 'ARROW:schema'::bytea;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(has_arrow_schema);
+
+        // the length of char(n) is only kept by the embedded arrow schema
+        let postgres_type = Spi::get_one::<String>(&format!(
+            "SELECT postgres_type FROM parquet.schema('{}') WHERE field_path = ARRAY['c'];",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(postgres_type, "character(5)");
+
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        assert_annotated_types_table_roundtrip();
+    }
+
+    #[pg_test]
+    fn test_roundtrip_without_arrow_schema() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        create_annotated_types_table();
+
+        let copy_to_command = format!("COPY test_expected TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_command).unwrap();
+
+        // rewrite the file without the embedded arrow schema, as written by other writers
+        let file = File::open(LOCAL_TEST_FILE_PATH).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let record_batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+
+        let file = File::create(LOCAL_TEST_FILE_PATH).unwrap();
+        let options = ArrowWriterOptions::new().with_skip_arrow_metadata(true);
+        let mut writer =
+            ArrowWriter::try_new_with_options(file, record_batches[0].schema(), options).unwrap();
+
+        for record_batch in record_batches {
+            writer.write(&record_batch).unwrap();
+        }
+
+        writer.close().unwrap();
+
+        let has_arrow_schema = Spi::get_one::<bool>(&format!(
+            "SELECT count(*) = 1 FROM parquet.kv_metadata('{}') WHERE key = 'ARROW:schema'::bytea;",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(!has_arrow_schema);
+
+        // the columns are read with the schema inferred from the parquet types
+        let copy_from_command = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_command).unwrap();
+
+        assert_annotated_types_table_roundtrip();
+    }
+
+    #[pg_test]
+    fn test_geometry_geoparquet_covering() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int, a geometry);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run(
+            "INSERT INTO test_table VALUES (1, ST_GeomFromText('POINT(1 2)')),
+                                           (2, ST_GeomFromText('LINESTRING(0 0, 3 -1)')),
+                                           (3, ST_GeomFromText('POLYGON((0 0, 0 4, 5 4, 5 0, 0 0))')),
+                                           (4, ST_GeomFromText('MULTIPOINT((0 0), (2 3))')),
+                                           (5, ST_GeomFromText('POINT Z (1 2 3)')),
+                                           (6, ST_GeomFromText('POINT EMPTY')),
+                                           (7, null);",
+        )
+        .unwrap();
+
+        let copy_to = format!(
+            "COPY test_table TO '{}' WITH (geoparquet_covering true)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // Check geoparquet metadata
+        let geoparquet_metadata_query = format!(
+            "select encode(value, 'escape')::jsonb
+            from parquet.kv_metadata('{LOCAL_TEST_FILE_PATH}')
+            where encode(key, 'escape') = 'geo';",
+        );
+        let geoparquet_metadata_json = Spi::get_one::<JsonB>(geoparquet_metadata_query.as_str())
+            .unwrap()
+            .unwrap();
+
+        let geoparquet_metadata: GeometryColumnsMetadata =
+            serde_json::from_value(geoparquet_metadata_json.0).unwrap();
+
+        let path = |field_name: &str| vec!["a_bbox".to_string(), field_name.to_string()];
+
+        assert_eq!(
+            geoparquet_metadata.columns.get("a").unwrap().covering,
+            Some(GeometryCovering {
+                bbox: BboxCovering {
+                    xmin: path("xmin"),
+                    ymin: path("ymin"),
+                    xmax: path("xmax"),
+                    ymax: path("ymax"),
+                }
+            })
+        );
+
+        // bbox column is written after the table columns
+        let file = File::open(LOCAL_TEST_FILE_PATH).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let schema = reader.schema();
+
+        let field_names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(field_names, vec!["id", "a", "a_bbox"]);
+
+        // Check bbox values
+        Spi::run("CREATE TYPE bbox AS (xmin float8, ymin float8, xmax float8, ymax float8);")
+            .unwrap();
+
+        Spi::run("CREATE TABLE test_result (id int, a geometry, a_bbox bbox);").unwrap();
+
+        let copy_from = format!(
+            "COPY test_result FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let bboxes = Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "SELECT (a_bbox).xmin, (a_bbox).ymin, (a_bbox).xmax, (a_bbox).ymax
+                     FROM test_result ORDER BY id;",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            for row in tup_table {
+                let xmin = row[1].value::<f64>().unwrap();
+                let ymin = row[2].value::<f64>().unwrap();
+                let xmax = row[3].value::<f64>().unwrap();
+                let ymax = row[4].value::<f64>().unwrap();
+
+                results.push((xmin, ymin, xmax, ymax));
+            }
+
+            results
+        });
+
+        assert_eq!(
+            bboxes,
+            vec![
+                (Some(1.0), Some(2.0), Some(1.0), Some(2.0)),
+                (Some(0.0), Some(-1.0), Some(3.0), Some(0.0)),
+                (Some(0.0), Some(0.0), Some(5.0), Some(4.0)),
+                (Some(0.0), Some(0.0), Some(2.0), Some(3.0)),
+                (Some(1.0), Some(2.0), Some(1.0), Some(2.0)),
+                (None, None, None, None),
+                (None, None, None, None),
+            ]
+        );
+
+        // bbox column is ignored when the table does not have it
+        Spi::run("CREATE TABLE test_result_without_bbox (id int, a geometry);").unwrap();
+
+        let copy_from = format!(
+            "COPY test_result_without_bbox FROM '{}' WITH (match_by 'name')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let geometries_equal = Spi::get_one::<bool>(
+            "SELECT bool_and(r.a IS NOT DISTINCT FROM t.a)
+             FROM test_result_without_bbox r JOIN test_table t USING (id);",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(geometries_equal);
+    }
+
+    #[pg_test]
+    fn test_geometry_geoparquet_crs() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int,
+                                                     a geometry(point, 3857),
+                                                     b geometry,
+                                                     c geometry);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run(
+            "INSERT INTO test_table VALUES (1, ST_GeomFromText('POINT(1 2)', 3857),
+                                               ST_GeomFromText('POINT(1 2)', 3857),
+                                               ST_GeomFromText('POINT(1 2)')),
+                                           (2, null,
+                                               ST_GeomFromText('LINESTRING(0 0, 3 -1)', 3857),
+                                               ST_GeomFromText('POINT(3 4)'));",
+        )
+        .unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let crs_query = |column_name: &str| {
+            format!(
+                "select (encode(value, 'escape')::jsonb)->'columns'->'{}'->'crs'
+                 from parquet.kv_metadata('{}')
+                 where encode(key, 'escape') = 'geo';",
+                column_name, LOCAL_TEST_FILE_PATH
+            )
+        };
+
+        // crs from the SRID of the column's type
+        let crs = Spi::get_one::<JsonB>(&crs_query("a")).unwrap().unwrap().0;
+        assert_eq!(crs["type"], "ProjectedCRS");
+        assert_eq!(crs["name"], "WGS 84 / Pseudo-Mercator");
+        assert_eq!(
+            crs["id"],
+            serde_json::json!({"authority": "EPSG", "code": 3857})
+        );
+
+        // crs from the SRID of the column's values
+        let crs = Spi::get_one::<JsonB>(&crs_query("b")).unwrap().unwrap().0;
+        assert_eq!(
+            crs["id"],
+            serde_json::json!({"authority": "EPSG", "code": 3857})
+        );
+
+        // crs is omitted for the unknown SRID
+        let crs = Spi::get_one::<JsonB>(&crs_query("c")).unwrap();
+        assert!(crs.is_none());
+
+        // re-read the file
+        Spi::run("CREATE TABLE test_result (LIKE test_table);").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let geometries_equal = Spi::get_one::<bool>(
+            "SELECT bool_and(ST_AsText(r.a) IS NOT DISTINCT FROM ST_AsText(t.a) AND
+                             ST_AsText(r.b) IS NOT DISTINCT FROM ST_AsText(t.b) AND
+                             ST_AsText(r.c) IS NOT DISTINCT FROM ST_AsText(t.c))
+             FROM test_result r JOIN test_table t USING (id);",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(geometries_equal);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "geometry column \"a\" has mixed SRIDs 4326 and 3857")]
+    fn test_geometry_mixed_srids() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            // let the test pass
+            panic!("geometry column \"a\" has mixed SRIDs 4326 and 3857");
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 2)', 4326) as a
+                   UNION ALL
+                   SELECT ST_GeomFromText('POINT(1 2)', 3857) as a)
+             TO '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+    }
+
+    #[pg_test]
+    fn test_geometry_allow_mixed_srids() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 2)', 4326) as a
+                   UNION ALL
+                   SELECT ST_GeomFromText('POINT(1 2)', 3857) as a)
+             TO '{}' WITH (format parquet, allow_mixed_srids true)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // crs is null, which means unknown
+        let crs_is_null = Spi::get_one::<bool>(&format!(
+            "select (encode(value, 'escape')::jsonb)->'columns'->'a'->'crs' = 'null'::jsonb
+             from parquet.kv_metadata('{}')
+             where encode(key, 'escape') = 'geo';",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(crs_is_null);
+    }
+
+    #[pg_test]
+    fn test_geometry_copy_from_crs() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 2)', 4326) as a,
+                          ST_GeomFromText('POINT(1 2)') as b)
+             TO '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // SRID is set from the crs of the column
+        Spi::run("CREATE TABLE test_result (a geometry, b geometry);").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let srids =
+            Spi::get_two::<i32, i32>("SELECT ST_SRID(a), ST_SRID(b) FROM test_result;").unwrap();
+        assert_eq!(srids, (Some(4326), Some(0)));
+
+        // SRID of the column's type is used when the crs is unknown
+        Spi::run(
+            "CREATE TABLE test_result_typmod (a geometry(point, 4326), b geometry(point, 4326));",
+        )
+        .unwrap();
+
+        let copy_from = format!("COPY test_result_typmod FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let srids =
+            Spi::get_two::<i32, i32>("SELECT ST_SRID(a), ST_SRID(b) FROM test_result_typmod;")
+                .unwrap();
+        assert_eq!(srids, (Some(4326), Some(4326)));
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "geometry column \"a\" has SRID 4326 in the parquet file, but SRID 3857 in the table"
+    )]
+    fn test_geometry_copy_from_srid_mismatch() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            // let the test pass
+            panic!("geometry column \"a\" has SRID 4326 in the parquet file, but SRID 3857 in the table");
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 2)', 4326) as a) TO '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_result (a geometry(point, 3857));").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_geometry_copy_from_srid_mismatch_reproject() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT ST_GeomFromText('POINT(1 2)', 4326) as a) TO '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_result (a geometry(point, 3857));").unwrap();
+
+        let copy_from = format!(
+            "COPY test_result FROM '{}' WITH (geometry_srid_mismatch 'reproject')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+
+        let reprojected = Spi::get_one::<bool>(
+            "SELECT ST_SRID(a) = 3857 AND
+                    ST_Equals(a, ST_Transform(ST_GeomFromText('POINT(1 2)', 4326), 3857))
+             FROM test_result;",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(reprojected);
+    }
+
+    #[pg_test]
+    fn test_nested_geometry_geoparquet_metadata() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        Spi::run("CREATE TYPE located AS (id int, geom geometry);").unwrap();
+
+        let create_table = "CREATE TABLE test_table (id int,
+                                                     c located,
+                                                     geoms geometry(point, 3857)[]);";
+        Spi::run(create_table).unwrap();
+
+        Spi::run(
+            "INSERT INTO test_table VALUES (1, row(1, ST_GeomFromText('POINT(1 2)', 4326)),
+                                               array[ST_GeomFromText('POINT(1 2)', 3857),
+                                                     ST_GeomFromText('POINT(3 4)', 3857)]),
+                                           (2, row(2, ST_GeomFromText('LINESTRING(0 0, 3 -1)', 4326)),
+                                               array[null, ST_GeomFromText('POINT(5 6)', 3857)]),
+                                           (3, row(3, null), null),
+                                           (4, null, '{}');",
+        )
+        .unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let geo_metadata = Spi::get_one::<JsonB>(&format!(
+            "select encode(value, 'escape')::jsonb
+             from parquet.kv_metadata('{}')
+             where encode(key, 'escape') = 'geo';",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap()
+        .0;
+
+        // nested geometries are listed by their dotted names
+        let mut columns = geo_metadata["columns"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        columns.sort();
+        assert_eq!(columns, vec!["c.geom", "geoms"]);
+
+        assert_eq!(geo_metadata["primary_column"], "c.geom");
+        assert_eq!(geo_metadata["columns"]["c.geom"]["encoding"], "WKB");
+        assert_eq!(geo_metadata["columns"]["geoms"]["encoding"], "WKB");
+        assert_eq!(
+            geo_metadata["columns"]["geoms"]["geometry_types"],
+            serde_json::json!(["Point"])
+        );
+
+        // crs from the SRID of the nested values
+        assert_eq!(
+            geo_metadata["columns"]["c.geom"]["crs"]["id"],
+            serde_json::json!({"authority": "EPSG", "code": 4326})
+        );
+
+        // crs from the SRID of the array's element type
+        assert_eq!(
+            geo_metadata["columns"]["geoms"]["crs"]["id"],
+            serde_json::json!({"authority": "EPSG", "code": 3857})
+        );
+
+        // nested geometries are written as WKB
+        let schema_command = format!(
+            "SELECT parquet_type FROM parquet.schema('{}') WHERE field_path = ARRAY['c', 'geom'];",
+            LOCAL_TEST_FILE_PATH
+        );
+        let physical_type = Spi::get_one::<String>(&schema_command).unwrap().unwrap();
+        assert_eq!(physical_type, "BYTE_ARRAY");
+
+        // re-read the file into the nested structure, SRIDs are set from the crs
+        Spi::run("CREATE TABLE test_result (id int, c located, geoms geometry[]);").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+
+        let geometries_equal = Spi::get_one::<bool>(
+            "SELECT bool_and(ST_AsEWKT((r.c).geom) IS NOT DISTINCT FROM ST_AsEWKT((t.c).geom) AND
+                             (r.c).id IS NOT DISTINCT FROM (t.c).id AND
+                             (SELECT array_agg(ST_AsEWKT(g)) FROM unnest(r.geoms) g) IS NOT DISTINCT FROM
+                             (SELECT array_agg(ST_AsEWKT(g)) FROM unnest(t.geoms) g))
+             FROM test_result r JOIN test_table t USING (id);",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(geometries_equal);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "geometry column \"c.geom\" has SRID 4326 in the parquet file, but SRID 3857 in the table"
+    )]
+    fn test_nested_geometry_copy_from_srid_mismatch() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            // let the test pass
+            panic!("geometry column \"c.geom\" has SRID 4326 in the parquet file, but SRID 3857 in the table");
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        Spi::run("CREATE TYPE located AS (id int, geom geometry);").unwrap();
+        Spi::run("CREATE TYPE located_3857 AS (id int, geom geometry(point, 3857));").unwrap();
+
+        let copy_to = format!(
+            "COPY (SELECT row(1, ST_GeomFromText('POINT(1 2)', 4326))::located as c)
+             TO '{}' WITH (format parquet)",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        Spi::run("CREATE TABLE test_result (c located_3857);").unwrap();
+
+        let copy_from = format!("COPY test_result FROM '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized geometry_srid_mismatch option: transform")]
+    fn test_invalid_geometry_srid_mismatch() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        let copy_to = format!("COPY test_table TO '{}'", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to).unwrap();
+
+        let copy_from = format!(
+            "COPY test_table FROM '{}' WITH (geometry_srid_mismatch 'transform')",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from).unwrap();
+    }
+
+    #[pg_test]
+    fn test_complex_composite() {
+        Spi::run("CREATE TYPE dog AS (name text, age int);").unwrap();
         Spi::run("CREATE TYPE dog_owner AS (name text, dogs dog[], lucky_numbers int[]);").unwrap();
         Spi::run("CREATE TABLE dog_owners (owner dog_owner);").unwrap();
 
@@ -1303,4 +3088,130 @@ mod tests {
         Spi::run("DROP TYPE dog_owner;").unwrap();
         Spi::run("DROP TYPE dog;").unwrap();
     }
+
+    #[pg_test]
+    fn test_wide_fixed_width_table() {
+        let column_types = [
+            ("int2", "(i % 100)::int2"),
+            ("int4", "i * {c}"),
+            ("int8", "i::int8 * 1000000000 + {c}"),
+            ("float4", "(i / 3.0 + {c})::float4"),
+            ("float8", "(i / 7.0 + {c})::float8"),
+            ("bool", "(i + {c}) % 2 = 0"),
+            ("date", "'2020-01-01'::date + i + {c}"),
+            (
+                "timestamp",
+                "'2020-01-01'::timestamp + (i + {c}) * interval '1 minute'",
+            ),
+            ("oid", "(i + {c})::oid"),
+            ("text", "'text_' || (i + {c})"),
+        ];
+
+        let columns = (0..100)
+            .map(|c| (c, column_types[c % column_types.len()]))
+            .collect::<Vec<_>>();
+
+        let create_table = format!(
+            "create table test_table ({});",
+            columns
+                .iter()
+                .map(|(c, (typ, _))| format!("c{} {}", c, typ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Spi::run(&create_table).unwrap();
+
+        // each column has its own nulls
+        let insert_data = format!(
+            "insert into test_table select {} from generate_series(1, 1000) i;",
+            columns
+                .iter()
+                .map(|(c, (_, value))| format!(
+                    "case when (i + {c}) % 10 = 0 then null else {} end",
+                    value.replace("{c}", &c.to_string())
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Spi::run(&insert_data).unwrap();
+
+        Spi::run("create table test_result (like test_table);").unwrap();
+
+        let copy_to_parquet = format!(
+            "copy test_table to '{}' with (row_group_size 300);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!("copy test_result from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let different_rows = Spi::get_one::<i64>(
+            "select count(*) from ((table test_table except all table test_result)
+                                   union all
+                                   (table test_result except all table test_table)) diff;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(different_rows, 0);
+
+        let result_rows = Spi::get_one::<i64>("select count(*) from test_result;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result_rows, 1000);
+    }
+
+    #[pg_test]
+    fn test_primitive_batch_conversion_matches_per_row_conversion() {
+        let create_table = "create table test_table (a int2, b int4, c int8, d float4, e float8,
+                                                     f bool, g date, h timestamp, i timestamptz,
+                                                     j time);";
+        Spi::run(create_table).unwrap();
+
+        // each column has its own nulls, e.g. at the batch boundaries
+        let copy_to_parquet = format!(
+            "copy (select case when i % 7 = 0 then null else (i % 30000)::int2 end,
+                          case when i % 11 = 0 then null else i end,
+                          case when i % 13 = 0 then null else i::int8 * 1000003 end,
+                          case when i % 17 = 0 then null else i::float4 / 3 end,
+                          case when i % 19 = 0 then null else i::float8 / 7 end,
+                          case when i % 23 = 0 then null else i % 2 = 0 end,
+                          case when i % 29 = 0 then null else '2000-01-01'::date + i % 100000 end,
+                          case when i % 31 = 0 then null
+                               else '1970-01-01'::timestamp + i * interval '1 minute' end,
+                          case when i % 37 = 0 then null
+                               else '1970-01-01'::timestamptz + i * interval '1 second' end,
+                          case when i % 41 = 0 then null
+                               else '00:00:00'::time + (i % 86400) * interval '1 second' end
+                   from generate_series(1, 5000000) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!("copy test_table from '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        // on_error 'ignore' converts each row separately
+        Spi::run("create table test_result (like test_table);").unwrap();
+
+        let copy_from_parquet = format!(
+            "copy test_result from '{}' with (on_error 'ignore');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let different_rows = Spi::get_one::<i64>(
+            "select count(*) from ((table test_table except all table test_result)
+                                   union all
+                                   (table test_result except all table test_table)) diff;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(different_rows, 0);
+
+        let result_rows = Spi::get_one::<i64>("select count(*) from test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(result_rows, 5000000);
+    }
 }
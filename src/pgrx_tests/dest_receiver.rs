@@ -0,0 +1,331 @@
+#[pgrx::pg_schema]
+mod tests {
+    use std::ffi::c_void;
+
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use pgrx::pg_sys::{
+        AsPgCStr, CommandDest, DestReceiver, SPIExecuteOptions, SPI_execute_extended, TupleDesc,
+        TupleTableSlot, SPI_OK_SELECT,
+    };
+    use pgrx::{pg_test, Spi};
+
+    use crate::arrow_parquet::if_exists::PgParquetIfExists;
+    use crate::arrow_parquet::stream_writer::CopyToParquetStream;
+    use crate::parquet_copy_hook::copy_to_dest_receiver::{
+        create_copy_to_parquet_dest_receiver_with_options,
+        create_copy_to_parquet_dest_receiver_with_stream, CopyToParquetDestReceiverOptions,
+        COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION,
+    };
+
+    #[derive(Default)]
+    struct StreamCapture {
+        bytes: Vec<u8>,
+        flush_count: usize,
+        finished: bool,
+    }
+
+    unsafe extern "C" fn capture_write(opaque: *mut c_void, data: *const u8, len: usize) -> bool {
+        let capture = &mut *(opaque as *mut StreamCapture);
+        capture
+            .bytes
+            .extend_from_slice(std::slice::from_raw_parts(data, len));
+        true
+    }
+
+    unsafe extern "C" fn capture_flush(opaque: *mut c_void) -> bool {
+        let capture = &mut *(opaque as *mut StreamCapture);
+        capture.flush_count += 1;
+        true
+    }
+
+    unsafe extern "C" fn capture_finish(opaque: *mut c_void) -> bool {
+        let capture = &mut *(opaque as *mut StreamCapture);
+        capture.finished = true;
+        true
+    }
+
+    unsafe extern "C" fn failing_write(
+        _opaque: *mut c_void,
+        _data: *const u8,
+        _len: usize,
+    ) -> bool {
+        false
+    }
+
+    fn create_stream_dest_receiver(
+        stream: &CopyToParquetStream,
+        row_group_size: Option<&i64>,
+        max_rows_per_file: Option<&i64>,
+    ) -> *mut DestReceiver {
+        let options = CopyToParquetDestReceiverOptions {
+            row_group_size: row_group_size.map_or(std::ptr::null(), |size| size as *const i64),
+            max_rows_per_file: max_rows_per_file
+                .map_or(std::ptr::null(), |rows| rows as *const i64),
+            ..Default::default()
+        };
+
+        create_copy_to_parquet_dest_receiver_with_stream(stream, &options)
+    }
+
+    // run_query_into_dest_receiver runs the query and sends its rows to the dest receiver.
+    fn run_query_into_dest_receiver(query: &str, dest: *mut DestReceiver) {
+        Spi::connect(|_client| {
+            let mut options: SPIExecuteOptions = unsafe { std::mem::zeroed() };
+            options.dest = dest;
+
+            let result = unsafe { SPI_execute_extended(query.as_pg_cstr(), &options) };
+            assert_eq!(result, SPI_OK_SELECT as i32);
+        });
+    }
+
+    #[pg_test]
+    fn test_dest_receiver_with_stream() {
+        let mut capture = StreamCapture::default();
+
+        let stream = CopyToParquetStream {
+            write_fn: Some(capture_write),
+            flush_fn: Some(capture_flush),
+            finish_fn: Some(capture_finish),
+            opaque: &mut capture as *mut StreamCapture as *mut c_void,
+        };
+
+        let row_group_size = 5;
+
+        let dest = create_stream_dest_receiver(&stream, Some(&row_group_size), None);
+
+        run_query_into_dest_receiver(
+            "SELECT i AS id, 'name_' || i AS name FROM generate_series(1, 12) i",
+            dest,
+        );
+
+        assert!(capture.finished);
+        assert_eq!(capture.flush_count, 3);
+
+        let reader_builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(capture.bytes))
+            .unwrap_or_else(|e| panic!("stream is not a valid parquet file: {}", e));
+
+        let metadata = reader_builder.metadata().clone();
+        assert_eq!(metadata.num_row_groups(), 3);
+        assert_eq!(metadata.file_metadata().num_rows(), 12);
+
+        let schema = reader_builder.schema().clone();
+        let column_names = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(column_names, vec!["id", "name"]);
+
+        let num_rows = reader_builder
+            .build()
+            .unwrap()
+            .map(|record_batch| record_batch.unwrap().num_rows())
+            .sum::<usize>();
+        assert_eq!(num_rows, 12);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "write_fn of the stream failed")]
+    fn test_dest_receiver_with_failing_stream() {
+        let stream = CopyToParquetStream {
+            write_fn: Some(failing_write),
+            flush_fn: None,
+            finish_fn: None,
+            opaque: std::ptr::null_mut(),
+        };
+
+        let dest = create_stream_dest_receiver(&stream, None, None);
+
+        run_query_into_dest_receiver("SELECT i FROM generate_series(1, 10) i", dest);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "max_rows_per_file is not supported when writing to a stream")]
+    fn test_dest_receiver_with_stream_max_rows_per_file() {
+        let mut capture = StreamCapture::default();
+
+        let stream = CopyToParquetStream {
+            write_fn: Some(capture_write),
+            flush_fn: None,
+            finish_fn: None,
+            opaque: &mut capture as *mut StreamCapture as *mut c_void,
+        };
+
+        let max_rows_per_file = 5;
+
+        create_stream_dest_receiver(&stream, None, Some(&max_rows_per_file));
+    }
+
+    #[pg_test]
+    fn test_dest_receiver_options_of_older_caller() {
+        let mut capture = StreamCapture::default();
+
+        let stream = CopyToParquetStream {
+            write_fn: Some(capture_write),
+            flush_fn: None,
+            finish_fn: Some(capture_finish),
+            opaque: &mut capture as *mut StreamCapture as *mut c_void,
+        };
+
+        let row_group_size = 5;
+        let max_rows_per_file = 5;
+
+        // the options after row_group_size are beyond the size of the caller's struct, so
+        // max_rows_per_file is not read
+        let options = CopyToParquetDestReceiverOptions {
+            size: std::mem::offset_of!(CopyToParquetDestReceiverOptions, row_group_size_bytes),
+            row_group_size: &row_group_size,
+            max_rows_per_file: &max_rows_per_file,
+            ..Default::default()
+        };
+
+        let dest = create_copy_to_parquet_dest_receiver_with_stream(&stream, &options);
+
+        run_query_into_dest_receiver("SELECT i FROM generate_series(1, 12) i", dest);
+
+        assert!(capture.finished);
+
+        let reader_builder =
+            ParquetRecordBatchReaderBuilder::try_new(Bytes::from(capture.bytes)).unwrap();
+        assert_eq!(reader_builder.metadata().num_row_groups(), 3);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unsupported version 2 of the dest receiver options")]
+    fn test_dest_receiver_options_of_newer_caller() {
+        let mut capture = StreamCapture::default();
+
+        let stream = CopyToParquetStream {
+            write_fn: Some(capture_write),
+            flush_fn: None,
+            finish_fn: None,
+            opaque: &mut capture as *mut StreamCapture as *mut c_void,
+        };
+
+        let options = CopyToParquetDestReceiverOptions {
+            version: COPY_TO_PARQUET_DEST_RECEIVER_OPTIONS_VERSION + 1,
+            ..Default::default()
+        };
+
+        create_copy_to_parquet_dest_receiver_with_stream(&stream, &options);
+    }
+
+    // RoundRobinDestReceiver sends the rows to its dest receivers in turns, so that their
+    // exports run concurrently, as if they were run by different backends.
+    #[repr(C)]
+    struct RoundRobinDestReceiver {
+        dest: DestReceiver,
+        targets: [*mut DestReceiver; 2],
+        received: usize,
+    }
+
+    unsafe extern "C" fn round_robin_startup(
+        dest: *mut DestReceiver,
+        operation: i32,
+        tupledesc: TupleDesc,
+    ) {
+        let round_robin = &mut *(dest as *mut RoundRobinDestReceiver);
+
+        for target in round_robin.targets {
+            (*target).rStartup.unwrap()(target, operation, tupledesc);
+        }
+    }
+
+    unsafe extern "C" fn round_robin_receive(
+        slot: *mut TupleTableSlot,
+        dest: *mut DestReceiver,
+    ) -> bool {
+        let round_robin = &mut *(dest as *mut RoundRobinDestReceiver);
+
+        let target = round_robin.targets[round_robin.received % 2];
+        round_robin.received += 1;
+
+        (*target).receiveSlot.unwrap()(slot, target)
+    }
+
+    unsafe extern "C" fn round_robin_shutdown(dest: *mut DestReceiver) {
+        let round_robin = &mut *(dest as *mut RoundRobinDestReceiver);
+
+        for target in round_robin.targets {
+            (*target).rShutdown.unwrap()(target);
+        }
+    }
+
+    unsafe extern "C" fn round_robin_destroy(_dest: *mut DestReceiver) {}
+
+    #[pg_test]
+    fn test_dest_receiver_concurrent_exports_with_append_suffix() {
+        let uri_dir = "/tmp/pg_parquet_test_concurrent_exports";
+        let _ = std::fs::remove_dir_all(uri_dir);
+
+        // the files of a former export are kept
+        Spi::run(&format!(
+            "COPY (SELECT i FROM generate_series(101, 110) i) TO '{}' WITH (max_rows_per_file 2);",
+            uri_dir
+        ))
+        .unwrap();
+
+        let max_rows_per_file = 2;
+        let if_exists = PgParquetIfExists::AppendSuffix;
+
+        let options = CopyToParquetDestReceiverOptions {
+            max_rows_per_file: &max_rows_per_file,
+            if_exists: &if_exists,
+            ..Default::default()
+        };
+
+        let mut round_robin: RoundRobinDestReceiver = unsafe { std::mem::zeroed() };
+        round_robin.dest.receiveSlot = Some(round_robin_receive);
+        round_robin.dest.rStartup = Some(round_robin_startup);
+        round_robin.dest.rShutdown = Some(round_robin_shutdown);
+        round_robin.dest.rDestroy = Some(round_robin_destroy);
+        round_robin.dest.mydest = CommandDest::DestNone;
+
+        for target in round_robin.targets.iter_mut() {
+            *target =
+                create_copy_to_parquet_dest_receiver_with_options(uri_dir.as_pg_cstr(), &options);
+        }
+
+        // both exports write part-00000 to part-00004 to the same directory
+        run_query_into_dest_receiver(
+            "SELECT i FROM generate_series(1, 20) i",
+            &mut round_robin as *mut RoundRobinDestReceiver as *mut DestReceiver,
+        );
+
+        let file_names = std::fs::read_dir(uri_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(file_names.len(), 15);
+
+        // each export has its own suffix, e.g. part-00000-4242-1-9f86d081.parquet
+        let mut suffixes = file_names
+            .iter()
+            .filter_map(|file_name| file_name.strip_suffix(".parquet"))
+            .filter_map(|file_name| file_name.split_once('-').map(|(_, rest)| rest))
+            .filter_map(|rest| rest.split_once('-').map(|(_, suffix)| suffix.to_string()))
+            .collect::<Vec<_>>();
+        suffixes.sort();
+        suffixes.dedup();
+        assert_eq!(suffixes.len(), 2);
+
+        // no file is lost or overwritten
+        Spi::run("CREATE TABLE test_table (i int);").unwrap();
+
+        for file_name in &file_names {
+            Spi::run(&format!(
+                "COPY test_table FROM '{}/{}';",
+                uri_dir, file_name
+            ))
+            .unwrap();
+        }
+
+        let values = Spi::get_one::<Vec<i32>>("SELECT array_agg(i ORDER BY i) FROM test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(values, (1..=20).chain(101..=110).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+    }
+}
@@ -2,7 +2,10 @@
 mod tests {
     use pgrx::{pg_test, Spi};
 
-    use crate::pgrx_tests::common::LOCAL_TEST_FILE_PATH;
+    use crate::{
+        arrow_parquet::pg_to_arrow::conversion_progress::current_conversion_progress,
+        pgrx_tests::common::{record_warnings, LOCAL_TEST_FILE_PATH},
+    };
 
     #[pg_test]
     #[should_panic(expected = "file size of 2 is less than footer")]
@@ -16,4 +19,599 @@ mod tests {
         );
         Spi::run(&parquet_metadata_command).unwrap();
     }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "out of memory while converting column \"b\" at row 2 of the row group to parquet"
+    )]
+    fn test_conversion_out_of_memory_reports_value() {
+        Spi::run("CREATE TABLE test_oom (a int, b jsonb);").unwrap();
+
+        Spi::run(
+            "INSERT INTO test_oom
+             SELECT i, jsonb_build_object('key', repeat('x', CASE WHEN i < 2 THEN 10 ELSE 1000000 END))
+             FROM generate_series(0, 3) i;",
+        )
+        .unwrap();
+
+        Spi::run("SET pg_parquet.test_conversion_memory_limit TO 500000;").unwrap();
+
+        Spi::run(format!("COPY test_oom TO '{}'", LOCAL_TEST_FILE_PATH).as_str()).unwrap();
+    }
+
+    #[pg_test]
+    fn test_failed_conversion_resets_progress() {
+        Spi::run("CREATE TABLE test_oom (a int, b jsonb, c timestamp);").unwrap();
+
+        Spi::run(
+            "INSERT INTO test_oom
+             SELECT i,
+                    jsonb_build_object('key', repeat('x', CASE WHEN i < 2 THEN 10 ELSE 1000000 END)),
+                    'infinity'
+             FROM generate_series(0, 3) i;",
+        )
+        .unwrap();
+
+        // fails with out of memory while converting column b
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                SET LOCAL pg_parquet.test_conversion_memory_limit TO 500000;
+                COPY (SELECT a, b FROM test_oom) TO '{}';
+             EXCEPTION WHEN out_of_memory THEN
+                NULL;
+             END $$;",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert!(current_conversion_progress().is_none());
+
+        // fails with an infinite timestamp while converting column c
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT a, c FROM test_oom) TO '{}' WITH (infinite_timestamps 'error');
+             EXCEPTION WHEN OTHERS THEN
+                NULL;
+             END $$;",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert!(current_conversion_progress().is_none());
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "which exceeds pg_parquet.max_export_bytes of 1024 bytes")]
+    fn test_max_export_bytes() {
+        Spi::run("SET pg_parquet.max_export_bytes TO '1kB';").unwrap();
+
+        let copy_to_parquet = format!(
+            "COPY (SELECT i FROM generate_series(1, 100000) i) TO '{}' WITH (row_group_size 1000);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_max_export_bytes_removes_file() {
+        Spi::run("SET pg_parquet.max_export_bytes TO '1kB';").unwrap();
+
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT i FROM generate_series(1, 100000) i) TO '{}' WITH (row_group_size 1000);
+             EXCEPTION WHEN configuration_limit_exceeded THEN
+                NULL;
+             END $$;",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert!(!std::path::Path::new(LOCAL_TEST_FILE_PATH).exists());
+    }
+
+    // FAILING_COPY_TO fails with division by zero after the first row group is flushed
+    const FAILING_COPY_TO_QUERY: &str =
+        "SELECT CASE WHEN i > 1500 THEN i / (i - i) ELSE i END AS i FROM generate_series(1, 2000) i";
+
+    #[pg_test]
+    fn test_failed_copy_to_removes_partial_file() {
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY ({}) TO '{}' WITH (row_group_size 1000);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            FAILING_COPY_TO_QUERY, LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        assert!(!std::path::Path::new(LOCAL_TEST_FILE_PATH).exists());
+    }
+
+    #[pg_test]
+    fn test_keep_partial_files() {
+        Spi::run("SET pg_parquet.keep_partial_files TO on;").unwrap();
+
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY ({}) TO '{}' WITH (row_group_size 1000);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            FAILING_COPY_TO_QUERY, LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the partial file is finished with the rows written so far
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT sum(num_rows)::bigint FROM parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        assert!(num_rows >= 1000);
+    }
+
+    #[pg_test]
+    fn test_upload_concurrency() {
+        Spi::run("SET pg_parquet.upload_concurrency TO 3;").unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_upload_concurrency";
+
+        let copy_to_parquet = format!(
+            "COPY (SELECT i FROM generate_series(1, 2000) i) TO '{}' WITH (max_rows_per_file 300);",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let file_rows = Spi::get_one::<Vec<i64>>(
+            "SELECT array_agg(m.num_rows ORDER BY f.uri)
+             FROM parquet.last_copy_files() f, parquet.file_metadata(f.uri) m;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(file_rows, vec![300, 300, 300, 300, 300, 300, 200]);
+
+        std::fs::remove_dir_all(uri_dir).unwrap();
+
+        // the files, which are uploaded in the background, are removed when the COPY fails
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY ({}) TO '{}' WITH (max_rows_per_file 300);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            FAILING_COPY_TO_QUERY, uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let written_files = std::fs::read_dir(uri_dir)
+            .map(|files| files.count())
+            .unwrap_or(0);
+        assert_eq!(written_files, 0);
+    }
+
+    #[pg_test]
+    fn test_upload_concurrency_failed_upload() {
+        Spi::run("SET pg_parquet.upload_concurrency TO 2;").unwrap();
+
+        // the upload of the third file fails in the background while the next file is written
+        Spi::run("SET pg_parquet.test_failed_upload_file TO 3;").unwrap();
+
+        let uri_dir = "/tmp/pg_parquet_test_upload_concurrency_failed_upload";
+
+        let copy_to_parquet = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT i FROM generate_series(1, 2000) i) TO '{}' WITH (max_rows_per_file 300);
+                RAISE EXCEPTION 'COPY TO did not fail';
+             EXCEPTION WHEN internal_error THEN
+                IF SQLERRM NOT LIKE '%injected upload failure%' THEN
+                    RAISE;
+                END IF;
+             END $$;",
+            uri_dir
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // the files, which are already uploaded, are removed as well
+        let written_files = std::fs::read_dir(uri_dir)
+            .map(|files| files.count())
+            .unwrap_or(0);
+        assert_eq!(written_files, 0);
+    }
+
+    #[pg_test]
+    fn test_warn_export_bytes() {
+        Spi::run("SET pg_parquet.warn_export_bytes TO '1kB';").unwrap();
+
+        // emits a warning but does not abort
+        let copy_to_parquet = format!(
+            "COPY (SELECT i FROM generate_series(1, 100000) i) TO '{}' WITH (row_group_size 1000);",
+            LOCAL_TEST_FILE_PATH
+        );
+        let warnings = record_warnings(|| Spi::run(&copy_to_parquet).unwrap());
+
+        // the warning is emitted once
+        let export_size_warnings = warnings
+            .iter()
+            .filter(|warning| {
+                warning.contains("exceeds pg_parquet.warn_export_bytes of 1024 bytes")
+            })
+            .count();
+        assert_eq!(
+            export_size_warnings, 1,
+            "unexpected warnings: {:?}",
+            warnings
+        );
+
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT num_rows FROM parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(num_rows, 100000);
+    }
+
+    #[pg_test]
+    fn test_max_decode_threads() {
+        Spi::run("CREATE TABLE test_decode (a int, b text, c int[]);").unwrap();
+
+        Spi::run(
+            "INSERT INTO test_decode
+             SELECT i, CASE WHEN i % 7 = 0 THEN NULL ELSE 'row ' || i END, ARRAY[i, i + 1]
+             FROM generate_series(1, 100000) i;",
+        )
+        .unwrap();
+
+        let copy_to_parquet = format!(
+            "COPY test_decode TO '{}' WITH (row_group_size 1000);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("CREATE TABLE test_decode_result (LIKE test_decode);").unwrap();
+
+        for max_decode_threads in [1, 4] {
+            Spi::run(&format!(
+                "SET pg_parquet.max_decode_threads TO {};",
+                max_decode_threads
+            ))
+            .unwrap();
+
+            Spi::run("TRUNCATE test_decode_result;").unwrap();
+
+            let copy_from_parquet =
+                format!("COPY test_decode_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+            Spi::run(&copy_from_parquet).unwrap();
+
+            let different_rows = Spi::get_one::<i64>(
+                "SELECT count(*) FROM (
+                    (SELECT * FROM test_decode EXCEPT ALL SELECT * FROM test_decode_result)
+                    UNION ALL
+                    (SELECT * FROM test_decode_result EXCEPT ALL SELECT * FROM test_decode)
+                 ) d;",
+            )
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(different_rows, 0);
+
+            // row groups are inserted in file order
+            let rows_in_order = Spi::get_one::<bool>(
+                "SELECT bool_and(a = rn) FROM (SELECT a, row_number() OVER () AS rn FROM test_decode_result) r;",
+            )
+            .unwrap()
+            .unwrap();
+
+            assert!(rows_in_order);
+        }
+    }
+
+    #[pg_test]
+    fn test_export_allowlist_allowed_table() {
+        Spi::run("CREATE SCHEMA exported; CREATE TABLE exported.test_allowed (a int);").unwrap();
+        Spi::run("INSERT INTO exported.test_allowed VALUES (1), (2);").unwrap();
+
+        Spi::run("SET pg_parquet.export_allowlist TO 'exported, public.test_*';").unwrap();
+
+        let copy_to_parquet = format!("COPY exported.test_allowed TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        // COPY FROM is not restricted
+        Spi::run("CREATE TABLE not_exported (a int);").unwrap();
+
+        let copy_from_parquet = format!("COPY not_exported FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let num_rows = Spi::get_one::<i64>("SELECT count(*) FROM not_exported;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(num_rows, 2);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "relation \"public.test_blocked\" is not allowed to be exported by pg_parquet.export_allowlist"
+    )]
+    fn test_export_allowlist_blocked_table() {
+        Spi::run("CREATE SCHEMA exported; CREATE TABLE public.test_blocked (a int);").unwrap();
+
+        Spi::run("SET pg_parquet.export_allowlist TO 'exported';").unwrap();
+
+        let copy_to_parquet = format!("COPY test_blocked TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "relation \"public.test_blocked\" is not allowed to be exported by pg_parquet.export_allowlist"
+    )]
+    fn test_export_allowlist_query_joining_blocked_table() {
+        Spi::run("CREATE SCHEMA exported; CREATE TABLE exported.test_allowed (a int);").unwrap();
+        Spi::run("CREATE TABLE public.test_blocked (a int, secret text);").unwrap();
+
+        Spi::run("SET pg_parquet.export_allowlist TO 'exported.*';").unwrap();
+
+        let copy_to_parquet = format!(
+            "COPY (SELECT t.a, b.secret FROM exported.test_allowed t JOIN test_blocked b USING (a)) TO '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    fn test_allowed_uri_prefixes() {
+        Spi::run("SET pg_parquet.allowed_uri_prefixes TO 's3://analytics-export/*, /tmp/pg_parquet_test*';")
+            .unwrap();
+
+        let copy_to_parquet = format!("COPY (SELECT 1 AS a) TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT num_rows FROM parquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 1);
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri s3://analytics-import/data.parquet is not allowed by pg_parquet.allowed_uri_prefixes"
+    )]
+    fn test_allowed_uri_prefixes_blocked_remote_uri() {
+        Spi::run("SET pg_parquet.allowed_uri_prefixes TO 's3://analytics-export/*';").unwrap();
+
+        Spi::run("COPY (SELECT 1 AS a) TO 's3://analytics-import/data.parquet';").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri file:///tmp/exports2/data.parquet is not allowed by pg_parquet.allowed_uri_prefixes"
+    )]
+    fn test_allowed_uri_prefixes_blocked_file() {
+        // a prefix without "*" only matches the uris under it
+        Spi::run("SET pg_parquet.allowed_uri_prefixes TO '/tmp/exports';").unwrap();
+
+        Spi::run("COPY (SELECT 1 AS a) TO '/tmp/exports2/data.parquet';").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "/etc/data.parquet is not allowed by pg_parquet.allowed_uri_prefixes"
+    )]
+    fn test_allowed_uri_prefixes_parent_directory() {
+        Spi::run("SET pg_parquet.allowed_uri_prefixes TO '/tmp/exports';").unwrap();
+
+        Spi::run("SELECT * FROM parquet.schema('/tmp/exports/../../etc/data.parquet');").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri s3a://analytics-export/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run("SELECT * FROM parquet.metadata('s3a://analytics-export//pii/users.parquet');")
+            .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri https://analytics-export.s3.amazonaws.com/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_virtual_hosted_s3_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('https://analytics-export.s3.amazonaws.com/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri https://s3.amazonaws.com/analytics-export/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_path_style_s3_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('https://s3.amazonaws.com/analytics-export/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri https://analytics-export.s3.wasabisys.com/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_s3_compatible_uri() {
+        Spi::run("SET pg_parquet.s3_compatible_hosts TO 's3.wasabisys.com';").unwrap();
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('https://analytics-export.s3.wasabisys.com/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri https://s3.wasabisys.com/analytics-export/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_path_style_s3_compatible_uri() {
+        Spi::run("SET pg_parquet.s3_compatible_hosts TO 's3.wasabisys.com';").unwrap();
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('https://s3.wasabisys.com/analytics-export/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri s3://analytics-export/%70ii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_percent_encoded_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii';").unwrap();
+
+        Spi::run("SELECT * FROM parquet.metadata('s3://analytics-export/%70ii/users.parquet');")
+            .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri s3://analytics-export/privat%65/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_percent_encoded_prefix() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/%70rivate/';")
+            .unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('s3://analytics-export/privat%65/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri https://account.blob.core.windows.net/analytics-export/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_azure_blob_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 'az://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('https://account.blob.core.windows.net/analytics-export/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri abfss://analytics-export@account.dfs.core.windows.net/pii/users.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_azure_dfs_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 'azure://analytics-export/pii';").unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.metadata('abfss://analytics-export@account.dfs.core.windows.net/pii/users.parquet');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri file:///tmp/pg_parquet_public/data.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_symlink() {
+        std::fs::create_dir_all("/tmp/pg_parquet_secret").unwrap();
+        std::fs::remove_file("/tmp/pg_parquet_public").ok();
+        std::os::unix::fs::symlink("/tmp/pg_parquet_secret", "/tmp/pg_parquet_public").unwrap();
+
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO '/tmp/pg_parquet_secret';").unwrap();
+
+        Spi::run("COPY (SELECT 1 AS a) TO '/tmp/pg_parquet_public/data.parquet';").unwrap();
+    }
+
+    #[pg_test]
+    fn test_denied_uri_prefixes_other_uri() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 's3://analytics-export/pii, /tmp/secret';")
+            .unwrap();
+
+        let copy_to_parquet = format!("COPY (SELECT 1 AS a) TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        let copy_from_parquet = format!(
+            "CREATE TABLE test_denied (a int); COPY test_denied FROM '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_from_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "uri file:///tmp/secret/data.parquet is denied by pg_parquet.denied_uri_prefixes"
+    )]
+    fn test_denied_uri_prefixes_take_precedence() {
+        Spi::run("SET pg_parquet.allowed_uri_prefixes TO '/tmp/*';").unwrap();
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO '/tmp/secret';").unwrap();
+
+        Spi::run("CREATE TABLE test_denied (a int);").unwrap();
+        Spi::run("COPY test_denied FROM '/tmp/secret/data.parquet';").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "pg_parquet.denied_uri_prefixes contains an invalid uri prefix \"tmp/secret\""
+    )]
+    fn test_denied_uri_prefixes_invalid_prefix() {
+        Spi::run("SET pg_parquet.denied_uri_prefixes TO 'tmp/secret';").unwrap();
+
+        Spi::run("COPY (SELECT 1 AS a) TO '/tmp/data.parquet';").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "pg_parquet.upload_part_concurrency * pg_parquet.upload_part_size must not exceed"
+    )]
+    fn test_upload_part_concurrency_exceeds_upload_buffer_limit() {
+        Spi::run("SET pg_parquet.upload_part_size TO '1GB';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_concurrency TO 6;").unwrap();
+
+        let copy_to_parquet = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(
+        expected = "pg_parquet.upload_concurrency (4) * upload buffer of a file (2048MB) must not exceed 5GB"
+    )]
+    fn test_upload_concurrency_exceeds_upload_buffer_limit() {
+        Spi::run("SET pg_parquet.upload_part_size TO '1GB';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_concurrency TO 2;").unwrap();
+        Spi::run("SET pg_parquet.upload_concurrency TO 4;").unwrap();
+
+        let copy_to_parquet = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}' WITH (max_rows_per_file 5);",
+            "/tmp/pg_parquet_test_upload_concurrency_exceeds_upload_buffer_limit"
+        );
+        Spi::run(&copy_to_parquet).unwrap();
+    }
 }
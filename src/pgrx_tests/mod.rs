@@ -3,6 +3,8 @@ mod copy_from_coerce;
 mod copy_options;
 mod copy_pg_rules;
 mod copy_type_roundtrip;
+mod dest_receiver;
 mod gucs;
 mod object_store;
+mod temp_files;
 mod udfs;
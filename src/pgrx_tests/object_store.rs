@@ -1,71 +1,999 @@
 #[pgrx::pg_schema]
 mod tests {
-    use std::io::Write;
-
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use aws_credential_types::provider::ProvideCredentials;
+    use object_store::{aws::AmazonS3Builder, path::Path, signer::Signer, ObjectStore};
     use pgrx::{pg_sys::Timestamp, pg_test, Spi};
+    use reqwest::Method;
+    use url::Url;
+
+    use crate::{
+        object_store::{aws::parse_s3_compatible_uri, azure::parse_azure_uri, http::http_host},
+        pgrx_tests::common::{record_warnings, TestTable},
+        PG_BACKEND_TOKIO_RUNTIME,
+    };
+
+    fn object_store_cache_clear() {
+        Spi::run("SELECT parquet.object_store_cache_clear();").unwrap();
+    }
+
+    fn object_store_cache_items() -> Vec<(&'static str, &'static str, Option<Timestamp>)> {
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client
+                .select(
+                    "SELECT scheme, bucket, (extract(epoch FROM expire_at) * 1000000)::bigint AS expire_at
+                     FROM parquet.object_store_cache() ORDER BY 1,2,3;",
+                    None,
+                    None,
+                )
+                .unwrap();
+
+            for row in tup_table {
+                let scheme = row.get_by_name("scheme").unwrap().unwrap();
+                let bucket = row.get_by_name("bucket").unwrap().unwrap();
+                let expire_at = row.get_by_name("expire_at").unwrap();
+
+                results.push((scheme, bucket, expire_at));
+            }
+
+            results
+        })
+    }
+
+    fn object_store_cache_hit_count(bucket: &str) -> i64 {
+        Spi::get_one::<i64>(&format!(
+            "SELECT hit_count FROM parquet.object_store_cache() WHERE bucket = '{}';",
+            bucket
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    fn object_store_expire_item(bucket: &str) {
+        Spi::run(&format!(
+            "SELECT parquet_test.object_store_cache_expire_bucket('{}');",
+            bucket
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_from_env() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uris = [
+            format!("s3://{}/pg_parquet_test.parquet", test_bucket_name),
+            format!(
+                "https://s3.amazonaws.com/{}/pg_parquet_test.parquet",
+                test_bucket_name
+            ),
+            format!(
+                "https://{}.s3.amazonaws.com/pg_parquet_test.parquet",
+                test_bucket_name
+            ),
+        ];
+
+        for s3_uri in s3_uris {
+            let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+
+            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+            test_table.assert_expected_and_result_rows();
+        }
+    }
+
+    #[pg_test]
+    fn test_s3_bucket_credentials() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let bucket2_access_key_id = std::env::var("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID")
+            .expect("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID not found");
+        let bucket2_secret_access_key = std::env::var("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY")
+            .expect("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY not found");
+
+        // the second bucket uses its own access keys, the first one the env credentials
+        let bucket_credentials = serde_json::json!({
+            format!("s3://{}2", test_bucket_name): {
+                "access_key_id": bucket2_access_key_id,
+                "secret_access_key": bucket2_secret_access_key,
+            }
+        });
+
+        Spi::run(&format!(
+            "SET pg_parquet.bucket_credentials TO '{}';",
+            bucket_credentials
+        ))
+        .unwrap();
+
+        let s3_uris = [
+            format!("s3://{}/pg_parquet_test.parquet", test_bucket_name),
+            format!("s3://{}2/pg_parquet_test.parquet", test_bucket_name),
+        ];
+
+        for s3_uri in s3_uris {
+            let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+
+            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+            test_table.assert_expected_and_result_rows();
+        }
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "403")]
+    fn test_s3_bucket_credentials_change_invalidates_cache() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let bucket2_access_key_id = std::env::var("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID")
+            .expect("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID not found");
+        let bucket2_secret_access_key = std::env::var("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY")
+            .expect("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY not found");
+
+        let s3_uri = format!("s3://{}/pg_parquet_test.parquet", test_bucket_name);
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1,10) i) TO '{}' WITH (format parquet);",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        // access keys of the second bucket cannot access the first bucket
+        let bucket_credentials = serde_json::json!({
+            format!("s3://{}", test_bucket_name): {
+                "access_key_id": bucket2_access_key_id,
+                "secret_access_key": bucket2_secret_access_key,
+            }
+        });
+
+        Spi::run(&format!(
+            "SET pg_parquet.bucket_credentials TO '{}';",
+            bucket_credentials
+        ))
+        .unwrap();
+
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_guc_credentials() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let bucket2_access_key_id = std::env::var("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID")
+            .expect("AWS_S3_TEST_BUCKET2_ACCESS_KEY_ID not found");
+        let bucket2_secret_access_key = std::env::var("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY")
+            .expect("AWS_S3_TEST_BUCKET2_SECRET_ACCESS_KEY not found");
+
+        // the keys of the session take precedence over the env credentials
+        Spi::run(&format!(
+            "SET pg_parquet.aws_access_key_id TO '{}';",
+            bucket2_access_key_id
+        ))
+        .unwrap();
+        Spi::run(&format!(
+            "SET pg_parquet.aws_secret_access_key TO '{}';",
+            bucket2_secret_access_key
+        ))
+        .unwrap();
+
+        let s3_uri = format!("s3://{}2/pg_parquet_test.parquet", test_bucket_name);
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "403")]
+    fn test_s3_guc_credentials_change_invalidates_cache() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!("s3://{}/pg_parquet_test.parquet", test_bucket_name);
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1,10) i) TO '{}' WITH (format parquet);",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        // the cached object store is not used after the keys are changed
+        Spi::run("SET pg_parquet.aws_access_key_id TO 'wrong_access_key_id';").unwrap();
+        Spi::run("SET pg_parquet.aws_secret_access_key TO 'wrong_secret_access_key';").unwrap();
+
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid value for parameter \"pg_parquet.bucket_credentials\"")]
+    fn test_s3_invalid_bucket_credentials() {
+        Spi::run("SET pg_parquet.bucket_credentials TO '{\"s3://bucket\": {\"secret\": 1}}';")
+            .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "invalid value for parameter \"pg_parquet.bucket_credentials\"")]
+    fn test_s3_bucket_credentials_with_invalid_bucket_uri() {
+        Spi::run("SET pg_parquet.bucket_credentials TO '{\"bucket\": {\"profile\": \"a\"}}';")
+            .unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_udf_options_endpoint() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!("s3://{}/pg_parquet_test_options.parquet", test_bucket_name);
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}';",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        // a proxy in front of the object store is the second endpoint of the same path
+        let port = start_flaky_s3_proxy(0);
+
+        let endpoints_options = [
+            serde_json::json!({}),
+            serde_json::json!({ "endpoint_url": format!("http://127.0.0.1:{}", port) }),
+        ];
+
+        for options in endpoints_options {
+            let num_rows = Spi::get_one::<i64>(&format!(
+                "SELECT num_rows FROM parquet.file_metadata('{}', options => '{}');",
+                s3_uri, options
+            ))
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(num_rows, 10);
+        }
+
+        // the object store of the options is not cached
+        assert_eq!(object_store_cache_items().len(), 1);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "403 Forbidden")]
+    fn test_s3_udf_options_override_session_credentials() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!("s3://{}/pg_parquet_test_options.parquet", test_bucket_name);
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}';",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let options = serde_json::json!({
+            "access_key_id": "wrong_access_key_id",
+            "secret_access_key": "wrong_secret_access_key",
+        });
+
+        Spi::run(&format!(
+            "SELECT * FROM parquet.schema('{}', options => '{}');",
+            s3_uri, options
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "already exists")]
+    fn test_s3_if_exists_error() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_if_exists.parquet",
+            test_bucket_name
+        );
+
+        Spi::run(&format!("COPY (SELECT 1 AS a) TO '{}';", s3_uri)).unwrap();
+
+        Spi::run(&format!(
+            "COPY (SELECT 2 AS a) TO '{}' WITH (if_exists 'error');",
+            s3_uri
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "is not empty")]
+    fn test_s3_if_exists_error_with_directory() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!("s3://{}/pg_parquet_test_if_exists_dir", test_bucket_name);
+
+        let copy_to = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}'
+             WITH (max_rows_per_file 5, if_exists 'error');",
+            s3_uri
+        );
+
+        // the objects of a former run are replaced first
+        Spi::run(&format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}' WITH (max_rows_per_file 5);",
+            s3_uri
+        ))
+        .unwrap();
+
+        Spi::run(&copy_to).unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_upload_concurrency() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_concurrent_uploads",
+            test_bucket_name
+        );
+
+        Spi::run("SET pg_parquet.upload_concurrency TO 2;").unwrap();
+
+        Spi::run(&format!(
+            "COPY (SELECT i, md5(i::text) AS h FROM generate_series(1, 6000) i) TO '{}'
+             WITH (max_rows_per_file 1000, row_group_size 100);",
+            s3_uri
+        ))
+        .unwrap();
+
+        // the files are recorded in their order, although they are uploaded in the background
+        let copy_files = Spi::connect(|client| {
+            client
+                .select(
+                    "SELECT uri, row_count FROM parquet.last_copy_files();",
+                    None,
+                    None,
+                )
+                .unwrap()
+                .map(|row| {
+                    (
+                        row["uri"].value::<String>().unwrap().unwrap(),
+                        row["row_count"].value::<i64>().unwrap().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let expected_copy_files = (0..6)
+            .map(|file_idx| (format!("{}/part-{:05}.parquet", s3_uri, file_idx), 1000))
+            .collect::<Vec<_>>();
+        assert_eq!(copy_files, expected_copy_files);
+
+        Spi::run("CREATE TABLE test_table (i int, h text);").unwrap();
+
+        for (uri, _) in &copy_files {
+            Spi::run(&format!("COPY test_table FROM '{}';", uri)).unwrap();
+        }
+
+        let mismatched_rows = Spi::get_one::<i64>(
+            "SELECT count(*) FROM test_table t
+             FULL JOIN generate_series(1, 6000) i ON t.i = i AND t.h = md5(i::text)
+             WHERE t.i IS NULL OR i IS NULL;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(mismatched_rows, 0);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "404 Not Found")]
+    fn test_s3_upload_concurrency_failed_upload() {
+        object_store_cache_clear();
+
+        // the upload of the first file fails in the background
+        let s3_uri = "s3://randombucketwhichdoesnotexist/pg_parquet_test_concurrent_uploads";
+
+        Spi::run("SET pg_parquet.upload_concurrency TO 2;").unwrap();
+
+        Spi::run(&format!(
+            "COPY (SELECT i FROM generate_series(1, 6000) i) TO '{}' WITH (max_rows_per_file 1000);",
+            s3_uri
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_if_exists_append_suffix() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_if_exists.parquet",
+            test_bucket_name
+        );
+
+        Spi::run(&format!("COPY (SELECT 1 AS a) TO '{}';", s3_uri)).unwrap();
+
+        Spi::run(&format!(
+            "COPY (SELECT a FROM generate_series(1, 2) a) TO '{}' WITH (if_exists 'append_suffix');",
+            s3_uri
+        ))
+        .unwrap();
+
+        let suffixed_uri = Spi::get_one::<String>("SELECT uri FROM parquet.last_copy_files();")
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(suffixed_uri, s3_uri);
+        assert!(suffixed_uri.starts_with(&format!(
+            "s3://{}/pg_parquet_test_if_exists-",
+            test_bucket_name
+        )));
+
+        let num_rows = |uri: &str| {
+            Spi::get_one::<i64>(&format!(
+                "SELECT num_rows FROM parquet.file_metadata('{}');",
+                uri
+            ))
+            .unwrap()
+            .unwrap()
+        };
+        assert_eq!(num_rows(&s3_uri), 1);
+        assert_eq!(num_rows(&suffixed_uri), 2);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unknown field `aws_region`, expected one of `profile`")]
+    fn test_s3_udf_options_unknown_key() {
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        Spi::run(&format!(
+            "SELECT * FROM parquet.metadata('s3://{}/pg_parquet_test.parquet',
+                                            options => '{{\"aws_region\": \"eu-west-1\"}}');",
+            test_bucket_name
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "options are only supported for s3 uris")]
+    fn test_udf_options_local_file() {
+        Spi::run(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '/tmp/pg_parquet_test_options.parquet';",
+        )
+        .unwrap();
+
+        Spi::run(
+            "SELECT * FROM parquet.kv_metadata('/tmp/pg_parquet_test_options.parquet',
+                                               options => '{\"region\": \"eu-west-1\"}');",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_compatible_endpoint_uri() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let endpoint = std::env::var("AWS_ENDPOINT_URL").expect("AWS_ENDPOINT_URL not found");
+
+        // path-style uri of the endpoint, e.g. http://localhost:9000/{bucket}/key
+        let s3_uri = format!(
+            "{}/{}/pg_parquet_test.parquet",
+            endpoint.trim_end_matches('/'),
+            test_bucket_name
+        );
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    // presigned_s3_get_url returns a presigned url to read the object at the key of the test bucket
+    fn presigned_s3_get_url(key: &str) -> String {
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3 = AmazonS3Builder::from_env()
+            .with_bucket_name(test_bucket_name)
+            .with_allow_http(true)
+            .build()
+            .unwrap();
+
+        let url = PG_BACKEND_TOKIO_RUNTIME
+            .block_on(s3.signed_url(Method::GET, &Path::from(key), Duration::from_secs(600)))
+            .unwrap();
+
+        url.to_string()
+    }
+
+    #[pg_test]
+    fn test_s3_presigned_url() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let key = "pg_parquet_test_presigned.parquet";
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO 's3://{}/{}';",
+            test_bucket_name, key
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        object_store_cache_clear();
+
+        let presigned_url = presigned_s3_get_url(key);
+
+        // metadata udfs read the footer with ranged GET requests of the presigned url
+        let metadata_query = format!(
+            "SELECT sum(num_rows) FROM parquet.file_metadata('{}');",
+            presigned_url
+        );
+        let num_rows = Spi::get_one::<i64>(&metadata_query).unwrap().unwrap();
+        assert_eq!(num_rows, 10);
+
+        Spi::run("CREATE TABLE test_presigned (i int);").unwrap();
+
+        let copy_from_command = format!("COPY test_presigned FROM '{}';", presigned_url);
+        Spi::run(&copy_from_command).unwrap();
+
+        let sum = Spi::get_one::<i64>("SELECT sum(i) FROM test_presigned;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum, 55);
+
+        // a new presigned url of the same host reuses the cached http client
+        let presigned_url = presigned_s3_get_url(key);
+        let metadata_query = format!("SELECT parquet.file_metadata('{}');", presigned_url);
+        Spi::run(&metadata_query).unwrap();
+
+        let host = http_host(&Url::parse(&presigned_url).unwrap());
+
+        assert_eq!(
+            object_store_cache_items()
+                .into_iter()
+                .map(|(scheme, bucket, _)| (scheme.to_string(), bucket.to_string()))
+                .collect::<Vec<_>>(),
+            vec![("Http".to_string(), host)]
+        );
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "cannot write to presigned URL")]
+    fn test_s3_presigned_url_write() {
+        object_store_cache_clear();
+
+        let presigned_url = presigned_s3_get_url("pg_parquet_test_presigned_write.parquet");
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}';",
+            presigned_url
+        );
+        Spi::run(&copy_to_command).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "403 Forbidden")]
+    fn test_s3_presigned_url_with_invalid_signature() {
+        object_store_cache_clear();
 
-    use crate::pgrx_tests::common::TestTable;
+        let mut presigned_url =
+            Url::parse(&presigned_s3_get_url("pg_parquet_test.parquet")).unwrap();
 
-    fn object_store_cache_clear() {
-        Spi::run("SELECT parquet_test.object_store_cache_clear();").unwrap();
-    }
+        let query = presigned_url
+            .query_pairs()
+            .map(|(key, value)| match key.as_ref() {
+                "X-Amz-Signature" => (key.to_string(), "0".repeat(value.len())),
+                _ => (key.to_string(), value.to_string()),
+            })
+            .collect::<Vec<_>>();
 
-    fn object_store_cache_items() -> Vec<(&'static str, &'static str, Option<Timestamp>)> {
-        Spi::connect(|client| {
-            let mut results = Vec::new();
-            let tup_table = client
-                .select(
-                    "SELECT * FROM parquet_test.object_store_cache_items() ORDER BY 1,2,3;",
-                    None,
-                    None,
-                )
-                .unwrap();
+        presigned_url.query_pairs_mut().clear().extend_pairs(query);
 
-            for row in tup_table {
-                let scheme = row.get_by_name("scheme").unwrap().unwrap();
-                let bucket = row.get_by_name("bucket").unwrap().unwrap();
-                let expire_at = row.get_by_name("expire_at").unwrap();
+        let metadata_query = format!("SELECT parquet.file_metadata('{}');", presigned_url);
+        Spi::run(&metadata_query).unwrap();
+    }
 
-                results.push((scheme, bucket, expire_at));
+    // start_range_ignoring_server starts an http server, which responds to all requests with
+    // the whole file and 200, as the servers which do not support range requests. It returns
+    // the port of the server.
+    fn start_range_ignoring_server(file: Vec<u8>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for client in listener.incoming() {
+                let Ok(mut client) = client else {
+                    continue;
+                };
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match client.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                let _ = client.write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        file.len()
+                    )
+                    .as_bytes(),
+                );
+                let _ = client.write_all(&file);
             }
+        });
 
-            results
-        })
+        port
     }
 
-    fn object_store_expire_item(bucket: &str) {
+    #[pg_test]
+    fn test_presigned_url_of_range_ignoring_server() {
+        object_store_cache_clear();
+
+        let local_path = "/tmp/pg_parquet_test_range_ignoring.parquet";
+
         Spi::run(&format!(
-            "SELECT parquet_test.object_store_cache_expire_bucket('{}');",
-            bucket
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}';",
+            local_path
+        ))
+        .unwrap();
+
+        let port = start_range_ignoring_server(std::fs::read(local_path).unwrap());
+
+        // the signature marks the uri as a presigned url, which is read over plain http
+        let presigned_url = format!(
+            "http://127.0.0.1:{}/pg_parquet_test_range_ignoring.parquet?X-Amz-Signature=0",
+            port
+        );
+
+        // the footer is read from the end of the whole object
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT sum(num_rows)::bigint FROM parquet.file_metadata('{}');",
+            presigned_url
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 10);
+
+        Spi::run("CREATE TABLE test_range_ignoring (i int);").unwrap();
+
+        Spi::run(&format!(
+            "COPY test_range_ignoring FROM '{}';",
+            presigned_url
         ))
         .unwrap();
+
+        let sum = Spi::get_one::<i64>("SELECT sum(i) FROM test_range_ignoring;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum, 55);
+
+        std::fs::remove_file(local_path).unwrap();
+    }
+
+    // start_flaky_s3_proxy starts a proxy in front of AWS_ENDPOINT_URL, which responds with
+    // 503 to the first failing_connections connections and forwards the others. It returns
+    // the port of the proxy.
+    fn start_flaky_s3_proxy(failing_connections: usize) -> u16 {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL").expect("AWS_ENDPOINT_URL not found");
+        let endpoint = Url::parse(&endpoint).unwrap();
+        let endpoint_addr = format!(
+            "{}:{}",
+            endpoint.host_str().unwrap(),
+            endpoint.port_or_known_default().unwrap()
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for (connection_idx, client) in listener.incoming().enumerate() {
+                let Ok(mut client) = client else {
+                    continue;
+                };
+
+                if connection_idx < failing_connections {
+                    let mut request = [0; 8192];
+                    let _ = client.read(&mut request);
+                    let _ = client.write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    );
+                    continue;
+                }
+
+                let Ok(server) = TcpStream::connect(&endpoint_addr) else {
+                    continue;
+                };
+
+                let mut client_reader = client.try_clone().unwrap();
+                let mut server_writer = server.try_clone().unwrap();
+                std::thread::spawn(move || std::io::copy(&mut client_reader, &mut server_writer));
+
+                let (mut server_reader, mut client_writer) = (server, client);
+                std::thread::spawn(move || std::io::copy(&mut server_reader, &mut client_writer));
+            }
+        });
+
+        port
+    }
+
+    // copy_from_flaky_s3_proxy writes a file to the test bucket, and reads it back via a flaky
+    // proxy, which fails the first failing_connections connections.
+    fn copy_from_flaky_s3_proxy(failing_connections: usize) {
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let key = "pg_parquet_test_retries.parquet";
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO 's3://{}/{}';",
+            test_bucket_name, key
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let port = start_flaky_s3_proxy(failing_connections);
+
+        Spi::run("SET pg_parquet.s3_compatible_hosts TO '127.0.0.1';").unwrap();
+
+        Spi::run("CREATE TABLE test_retries (i int);").unwrap();
+
+        let copy_from_command = format!(
+            "COPY test_retries FROM 'http://127.0.0.1:{}/{}/{}';",
+            port, test_bucket_name, key
+        );
+        Spi::run(&copy_from_command).unwrap();
+
+        let sum = Spi::get_one::<i64>("SELECT sum(i) FROM test_retries;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum, 55);
     }
 
     #[pg_test]
-    fn test_s3_from_env() {
+    fn test_s3_object_store_retries() {
+        object_store_cache_clear();
+
+        Spi::run("SET pg_parquet.object_store_max_retries TO 5;").unwrap();
+        Spi::run("SET pg_parquet.object_store_retry_timeout_ms TO '1min';").unwrap();
+
+        copy_from_flaky_s3_proxy(3);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "Error after 0 retries")]
+    fn test_s3_object_store_retries_disabled() {
+        object_store_cache_clear();
+
+        Spi::run("SET pg_parquet.object_store_max_retries TO 0;").unwrap();
+
+        copy_from_flaky_s3_proxy(1);
+    }
+
+    // start_canceling_s3_proxy starts a proxy in front of AWS_ENDPOINT_URL, which sends a cancel
+    // request (SIGINT) to the backend after it forwards cancel_after_bytes bytes of requests,
+    // and stalls the upload for a while. It returns the port of the proxy, and whether an
+    // upload is aborted via the proxy.
+    fn start_canceling_s3_proxy(cancel_after_bytes: usize) -> (u16, Arc<AtomicBool>) {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL").expect("AWS_ENDPOINT_URL not found");
+        let endpoint = Url::parse(&endpoint).unwrap();
+        let endpoint_addr = format!(
+            "{}:{}",
+            endpoint.host_str().unwrap(),
+            endpoint.port_or_known_default().unwrap()
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let backend_pid = std::process::id();
+        let forwarded_bytes = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let upload_aborted = Arc::new(AtomicBool::new(false));
+        let proxy_upload_aborted = upload_aborted.clone();
+
+        std::thread::spawn(move || {
+            for client in listener.incoming() {
+                let Ok(client) = client else {
+                    continue;
+                };
+
+                let Ok(server) = TcpStream::connect(&endpoint_addr) else {
+                    continue;
+                };
+
+                let mut client_reader = client.try_clone().unwrap();
+                let mut server_writer = server.try_clone().unwrap();
+                let forwarded_bytes = forwarded_bytes.clone();
+                let upload_aborted = proxy_upload_aborted.clone();
+
+                std::thread::spawn(move || {
+                    let mut buf = [0; 64 * 1024];
+
+                    while let Ok(n) = client_reader.read(&mut buf) {
+                        if n == 0 || server_writer.write_all(&buf[..n]).is_err() {
+                            break;
+                        }
+
+                        // AbortMultipartUpload request, i.e. DELETE /{bucket}/{key}?uploadId=
+                        if buf[..n].starts_with(b"DELETE ")
+                            && String::from_utf8_lossy(&buf[..n]).contains("uploadId=")
+                        {
+                            upload_aborted.store(true, Ordering::Relaxed);
+                        }
+
+                        let total = forwarded_bytes.fetch_add(n, Ordering::Relaxed) + n;
+
+                        if total - n < cancel_after_bytes && total >= cancel_after_bytes {
+                            std::process::Command::new("kill")
+                                .arg("-INT")
+                                .arg(backend_pid.to_string())
+                                .status()
+                                .unwrap();
+
+                            std::thread::sleep(Duration::from_secs(5));
+                        }
+                    }
+                });
+
+                let (mut server_reader, mut client_writer) = (server, client);
+                std::thread::spawn(move || std::io::copy(&mut server_reader, &mut client_writer));
+            }
+        });
+
+        (port, upload_aborted)
+    }
+
+    #[pg_test]
+    fn test_s3_cancel_aborts_multipart_upload() {
         object_store_cache_clear();
 
         let test_bucket_name: String =
             std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
 
-        let s3_uris = [
-            format!("s3://{}/pg_parquet_test.parquet", test_bucket_name),
-            format!(
-                "https://s3.amazonaws.com/{}/pg_parquet_test.parquet",
-                test_bucket_name
-            ),
-            format!(
-                "https://{}.s3.amazonaws.com/pg_parquet_test.parquet",
-                test_bucket_name
-            ),
-        ];
+        let key = "pg_parquet_test_cancel.parquet";
 
-        for s3_uri in s3_uris {
-            let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+        // cancel after the first part is uploaded
+        let (port, upload_aborted) = start_canceling_s3_proxy(6 * 1024 * 1024);
 
-            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
-            test_table.assert_expected_and_result_rows();
-        }
+        Spi::run("SET pg_parquet.s3_compatible_hosts TO '127.0.0.1';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_size TO '5MB';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_concurrency TO 1;").unwrap();
+
+        let copy_to_command = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT i, md5(i::text) FROM generate_series(1, 2000000) i)
+                TO 'http://127.0.0.1:{}/{}/{}' WITH (row_group_size 100000);
+                RAISE EXCEPTION 'COPY TO is not canceled';
+             EXCEPTION WHEN query_canceled THEN
+                NULL;
+             END $$;",
+            port, test_bucket_name, key
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        assert!(upload_aborted.load(Ordering::Relaxed));
+
+        // the partial file is not completed
+        let s3 = AmazonS3Builder::from_env()
+            .with_bucket_name(test_bucket_name)
+            .with_allow_http(true)
+            .build()
+            .unwrap();
+
+        let result = PG_BACKEND_TOKIO_RUNTIME.block_on(s3.head(&Path::from(key)));
+        assert!(matches!(result, Err(object_store::Error::NotFound { .. })));
+    }
+
+    #[pg_test]
+    fn test_parse_s3_compatible_uri() {
+        Spi::run(
+            "SET pg_parquet.s3_compatible_hosts TO 's3.wasabisys.com, s3.gra.io.cloud.ovh.net';",
+        )
+        .unwrap();
+
+        let s3_compatible_uri = |uri: &str| {
+            parse_s3_compatible_uri(&Url::parse(uri).unwrap())
+                .map(|uri| (uri.bucket, uri.path.to_string(), uri.endpoint))
+        };
+
+        // cloudflare r2
+        assert_eq!(
+            s3_compatible_uri("https://acc.r2.cloudflarestorage.com/bucket/dir/file.parquet"),
+            Some((
+                "bucket".into(),
+                "dir/file.parquet".into(),
+                Some("https://acc.r2.cloudflarestorage.com".into())
+            ))
+        );
+        assert_eq!(
+            s3_compatible_uri("https://bucket.acc.r2.cloudflarestorage.com/file.parquet"),
+            Some((
+                "bucket".into(),
+                "file.parquet".into(),
+                Some("https://acc.r2.cloudflarestorage.com".into())
+            ))
+        );
+
+        // wasabi
+        assert_eq!(
+            s3_compatible_uri("https://s3.wasabisys.com/bucket/file.parquet"),
+            Some((
+                "bucket".into(),
+                "file.parquet".into(),
+                Some("https://s3.wasabisys.com".into())
+            ))
+        );
+        assert_eq!(
+            s3_compatible_uri("https://bucket.s3.wasabisys.com/file.parquet"),
+            Some((
+                "bucket".into(),
+                "file.parquet".into(),
+                Some("https://s3.wasabisys.com".into())
+            ))
+        );
+
+        // ovh
+        assert_eq!(
+            s3_compatible_uri("https://bucket.s3.gra.io.cloud.ovh.net/dir/file.parquet"),
+            Some((
+                "bucket".into(),
+                "dir/file.parquet".into(),
+                Some("https://s3.gra.io.cloud.ovh.net".into())
+            ))
+        );
+
+        // not listed hosts and amazon s3 uris
+        assert_eq!(
+            s3_compatible_uri("https://example.com/bucket/file.parquet"),
+            None
+        );
+        assert_eq!(
+            s3_compatible_uri("https://bucket.s3.amazonaws.com/file.parquet"),
+            None
+        );
+        assert_eq!(s3_compatible_uri("s3://bucket/file.parquet"), None);
     }
 
     #[pg_test]
@@ -242,6 +1170,134 @@ mod tests {
         Spi::run(copy_to_command.as_str()).unwrap();
     }
 
+    #[pg_test]
+    #[should_panic(expected = "404 Not Found")]
+    fn test_s3_max_export_bytes_removes_object() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_max_export.parquet",
+            test_bucket_name
+        );
+
+        Spi::run("SET pg_parquet.max_export_bytes TO '1kB';").unwrap();
+
+        let copy_to_command = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT i FROM generate_series(1, 100000) i) TO '{}' WITH (row_group_size 1000);
+             EXCEPTION WHEN configuration_limit_exceeded THEN
+                NULL;
+             END $$;",
+            s3_uri
+        );
+        Spi::run(copy_to_command.as_str()).unwrap();
+
+        // the object is removed
+        let metadata_query = format!("SELECT parquet.metadata('{}');", s3_uri);
+        Spi::run(&metadata_query).unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "404 Not Found")]
+    fn test_s3_failed_copy_to_removes_object() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_failed_copy.parquet",
+            test_bucket_name
+        );
+
+        // fails after the first row group is flushed
+        let copy_to_command = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT CASE WHEN i > 1500 THEN i / (i - i) ELSE i END AS i
+                      FROM generate_series(1, 2000) i)
+                TO '{}' WITH (row_group_size 1000);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        let metadata_query = format!("SELECT parquet.metadata('{}');", s3_uri);
+        Spi::run(&metadata_query).unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_kv_metadata() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_kv_metadata.parquet",
+            test_bucket_name
+        );
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1,10) i) TO '{}' WITH (kv_metadata '{{\"source\":\"orders\"}}');",
+            s3_uri
+        );
+        Spi::run(copy_to_command.as_str()).unwrap();
+
+        let kv_metadata_query = format!(
+            "SELECT encode(value, 'escape') FROM parquet.kv_metadata('{}')
+             WHERE encode(key, 'escape') = 'source';",
+            s3_uri
+        );
+        let kv_metadata_value = Spi::get_one::<String>(&kv_metadata_query).unwrap().unwrap();
+
+        assert_eq!(kv_metadata_value, "orders");
+    }
+
+    #[pg_test]
+    fn test_s3_upload_part_concurrency() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let s3_uri = format!(
+            "s3://{}/pg_parquet_test_upload_part_concurrency.parquet",
+            test_bucket_name
+        );
+
+        Spi::run("SET pg_parquet.upload_part_size TO '5MB';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_concurrency TO 3;").unwrap();
+
+        // ~50MB file is uploaded in 5MB parts, 3 of them concurrently
+        let copy_to_command = format!(
+            "COPY (SELECT i, md5(i::text) || md5((i + 1)::text) AS t FROM generate_series(1, 500000) i)
+             TO '{}' WITH (compression 'uncompressed');",
+            s3_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        Spi::run("CREATE TABLE test_upload_part_concurrency (i int, t text);").unwrap();
+
+        let copy_from_command = format!("COPY test_upload_part_concurrency FROM '{}';", s3_uri);
+        Spi::run(&copy_from_command).unwrap();
+
+        let all_rows_intact = Spi::get_one::<bool>(
+            "SELECT count(*) = 500000 AND bool_and(t = md5(i::text) || md5((i + 1)::text))
+             FROM test_upload_part_concurrency;",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(all_rows_intact);
+    }
+
     #[pg_test]
     #[should_panic(expected = "404 Not Found")]
     fn test_s3_write_wrong_bucket() {
@@ -319,25 +1375,135 @@ mod tests {
 
         let s3_uri = format!("s3://{}/pg_parquet_test.parquet", test_bucket_name);
 
-        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri.clone());
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri.clone());
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+
+        assert!(matches!(
+            object_store_cache_items()[..],
+            [("AmazonS3", "testbucket" , Some(expire_at) )] if expire_at > 0
+        ));
+
+        // expire the token for the bucket
+        object_store_expire_item("testbucket");
+
+        // next run will create a new token
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+
+        // remove the config file
+        std::fs::remove_file(aws_config_file_path).unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_container_credentials() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        // remove static keys to make sure the credentials endpoint is used
+        let access_key_id = std::env::var("AWS_ACCESS_KEY_ID").unwrap();
+        std::env::remove_var("AWS_ACCESS_KEY_ID");
+        let secret_access_key = std::env::var("AWS_SECRET_ACCESS_KEY").unwrap();
+        std::env::remove_var("AWS_SECRET_ACCESS_KEY");
+
+        // temporary credentials, which are vended by the credentials endpoint below like
+        // ECS task roles or IMDS, are obtained by assuming a role
+        let profile = "pg_parquet_test";
+
+        let aws_config_file_content = format!(
+            "[profile {profile}-source]\n\
+            aws_access_key_id={access_key_id}\n\
+            aws_secret_access_key={secret_access_key}\n\
+            \n\
+            [profile {profile}]\n\
+            source_profile={profile}-source\n\
+            role_arn=arn:aws:iam::123456789012:dummy\n"
+        );
+
+        let aws_config_file_path = "/tmp/pg_parquet_aws_config";
+        std::fs::write(aws_config_file_path, aws_config_file_content).unwrap();
+        std::env::set_var("AWS_CONFIG_FILE", aws_config_file_path);
+
+        let credentials = PG_BACKEND_TOKIO_RUNTIME.block_on(async {
+            aws_config::defaults(aws_config::BehaviorVersion::v2024_03_28())
+                .profile_name(profile)
+                .load()
+                .await
+                .credentials_provider()
+                .unwrap()
+                .provide_credentials()
+                .await
+                .unwrap()
+        });
+
+        std::env::remove_var("AWS_CONFIG_FILE");
+        std::fs::remove_file(aws_config_file_path).unwrap();
+
+        let expiration = Spi::get_one::<String>(
+            "SELECT to_char(now() AT TIME ZONE 'UTC' + interval '1 hour', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"');",
+        )
+        .unwrap()
+        .unwrap();
+
+        let credentials_response_body = format!(
+            "{{\"AccessKeyId\":\"{}\",\"SecretAccessKey\":\"{}\",\"Token\":\"{}\",\"Expiration\":\"{}\"}}",
+            credentials.access_key_id(),
+            credentials.secret_access_key(),
+            credentials.session_token().unwrap(),
+            expiration
+        );
+
+        // local credentials endpoint, as the one of ECS task roles
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+
+                let mut request = [0; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut request).unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    credentials_response_body.len(),
+                    credentials_response_body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        std::env::set_var(
+            "AWS_CONTAINER_CREDENTIALS_FULL_URI",
+            format!("http://127.0.0.1:{}/credentials", port),
+        );
+
+        let s3_uri = format!("s3://{}/pg_parquet_test.parquet", test_bucket_name);
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
         test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
         test_table.assert_expected_and_result_rows();
 
+        // expiry of the vended credentials is the expiry of the cached object store
         assert!(matches!(
             object_store_cache_items()[..],
             [("AmazonS3", "testbucket" , Some(expire_at) )] if expire_at > 0
         ));
 
-        // expire the token for the bucket
-        object_store_expire_item("testbucket");
-
-        // next run will create a new token
-        let test_table = TestTable::<i32>::new("int4".into()).with_uri(s3_uri);
-        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
-        test_table.assert_expected_and_result_rows();
+        let expire_at_matches = Spi::get_one::<bool>(&format!(
+            "SELECT expire_at = '{}'::timestamptz FROM parquet.object_store_cache();",
+            expiration
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(expire_at_matches);
 
-        // remove the config file
-        std::fs::remove_file(aws_config_file_path).unwrap();
+        std::env::remove_var("AWS_CONTAINER_CREDENTIALS_FULL_URI");
+        std::env::set_var("AWS_ACCESS_KEY_ID", access_key_id);
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", secret_access_key);
     }
 
     #[pg_test]
@@ -383,6 +1549,110 @@ mod tests {
         }
     }
 
+    #[pg_test]
+    fn test_azure_data_lake_uri() {
+        object_store_cache_clear();
+
+        let test_container_name: String = std::env::var("AZURE_TEST_CONTAINER_NAME")
+            .expect("AZURE_TEST_CONTAINER_NAME not found");
+
+        let test_account_name: String =
+            std::env::var("AZURE_STORAGE_ACCOUNT").expect("AZURE_STORAGE_ACCOUNT not found");
+
+        let azure_dfs_uris = [
+            format!(
+                "abfss://{}@{}.dfs.core.windows.net/pg_parquet_test.parquet",
+                test_container_name, test_account_name
+            ),
+            format!(
+                "https://{}.dfs.core.windows.net/{}/pg_parquet_test.parquet",
+                test_account_name, test_container_name
+            ),
+        ];
+
+        for azure_dfs_uri in azure_dfs_uris {
+            let test_table = TestTable::<i32>::new("int4".into()).with_uri(azure_dfs_uri);
+
+            test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+            test_table.assert_expected_and_result_rows();
+        }
+    }
+
+    #[pg_test]
+    fn test_parse_azure_uri() {
+        let azure_uri = |uri: &str| {
+            parse_azure_uri(&Url::parse(uri).unwrap()).map(|uri| {
+                (
+                    uri.container,
+                    uri.path.to_string(),
+                    uri.account,
+                    uri.use_fabric_endpoint,
+                )
+            })
+        };
+
+        assert_eq!(
+            azure_uri("az://container/dir/file.parquet"),
+            Some(("container".into(), "dir/file.parquet".into(), None, false))
+        );
+        assert_eq!(
+            azure_uri("azure://container/file.parquet"),
+            Some(("container".into(), "file.parquet".into(), None, false))
+        );
+        assert_eq!(
+            azure_uri("abfss://container/file.parquet"),
+            Some(("container".into(), "file.parquet".into(), None, false))
+        );
+        assert_eq!(
+            azure_uri("abfss://container@account.dfs.core.windows.net/dir/file.parquet"),
+            Some((
+                "container".into(),
+                "dir/file.parquet".into(),
+                Some("account".into()),
+                false
+            ))
+        );
+        assert_eq!(
+            azure_uri("abfss://workspace@onelake.dfs.fabric.microsoft.com/item/Files/file.parquet"),
+            Some((
+                "workspace".into(),
+                "item/Files/file.parquet".into(),
+                Some("onelake".into()),
+                true
+            ))
+        );
+        assert_eq!(
+            azure_uri("https://account.dfs.core.windows.net/container/dir/file.parquet"),
+            Some((
+                "container".into(),
+                "dir/file.parquet".into(),
+                Some("account".into()),
+                false
+            ))
+        );
+        assert_eq!(
+            azure_uri("https://onelake.dfs.fabric.microsoft.com/workspace/item/Files/file.parquet"),
+            Some((
+                "workspace".into(),
+                "item/Files/file.parquet".into(),
+                Some("onelake".into()),
+                true
+            ))
+        );
+        assert_eq!(
+            azure_uri("https://account.blob.core.windows.net/container"),
+            Some(("container".into(), "container".into(), None, false))
+        );
+        assert_eq!(
+            azure_uri("abfss://container@account.example.com/file.parquet"),
+            None
+        );
+        assert_eq!(
+            azure_uri("https://example.com/container/file.parquet"),
+            None
+        );
+    }
+
     #[pg_test]
     fn test_azure_from_config_file() {
         object_store_cache_clear();
@@ -448,6 +1718,61 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    fn test_azure_from_guc_credentials() {
+        object_store_cache_clear();
+
+        let test_container_name: String = std::env::var("AZURE_TEST_CONTAINER_NAME")
+            .expect("AZURE_TEST_CONTAINER_NAME not found");
+
+        // remove these to make sure the GUCs are used
+        let account_name = std::env::var("AZURE_STORAGE_ACCOUNT").unwrap();
+        std::env::remove_var("AZURE_STORAGE_ACCOUNT");
+        let account_key = std::env::var("AZURE_STORAGE_KEY").unwrap();
+        std::env::remove_var("AZURE_STORAGE_KEY");
+        std::env::remove_var("AZURE_STORAGE_CONNECTION_STRING");
+
+        Spi::run(&format!(
+            "SET pg_parquet.azure_storage_account TO '{}';",
+            account_name
+        ))
+        .unwrap();
+        Spi::run(&format!(
+            "SET pg_parquet.azure_storage_key TO '{}';",
+            account_key
+        ))
+        .unwrap();
+
+        let azure_blob_uri = format!("az://{}/pg_parquet_test.parquet", test_container_name);
+
+        let test_table = TestTable::<i32>::new("int4".into()).with_uri(azure_blob_uri);
+
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "403 Forbidden")]
+    fn test_azure_guc_credentials_change_invalidates_cache() {
+        object_store_cache_clear();
+
+        let test_container_name: String = std::env::var("AZURE_TEST_CONTAINER_NAME")
+            .expect("AZURE_TEST_CONTAINER_NAME not found");
+
+        let azure_blob_uri = format!("az://{}/pg_parquet_test.parquet", test_container_name);
+
+        let copy_to_command = format!(
+            "COPY (SELECT i FROM generate_series(1,10) i) TO '{}' WITH (format parquet);",
+            azure_blob_uri
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        // the cached object store is not used after the key is changed
+        Spi::run("SET pg_parquet.azure_storage_key TO 'd3Jvbmcga2V5';").unwrap();
+
+        Spi::run(&copy_to_command).unwrap();
+    }
+
     #[pg_test]
     fn test_azure_from_config_via_connection_string() {
         object_store_cache_clear();
@@ -655,6 +1980,293 @@ mod tests {
         test_table.assert_expected_and_result_rows();
     }
 
+    #[pg_test]
+    #[should_panic(expected = "destination uri is empty")]
+    fn test_copy_to_empty_uri() {
+        Spi::run("COPY (SELECT 1 AS a) TO '' WITH (format parquet);").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "source uri is empty")]
+    fn test_copy_from_whitespace_uri() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run("COPY test_table FROM '   ' WITH (format parquet);").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "uri is empty")]
+    fn test_udf_empty_uri() {
+        Spi::run("SELECT * FROM parquet.schema(' ');").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "uri s3:// is missing the bucket name")]
+    fn test_s3_uri_without_bucket() {
+        Spi::run("COPY (SELECT 1 AS a) TO 's3://' WITH (format parquet);").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "uri az:// is missing the container name")]
+    fn test_azure_uri_without_container() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run("COPY test_table FROM 'az://' WITH (format parquet);").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "uri https:// is missing the host")]
+    fn test_https_uri_without_host() {
+        Spi::run("COPY (SELECT 1 AS a) TO 'https://' WITH (format parquet);").unwrap();
+    }
+
+    #[pg_test]
+    fn test_s3_uri_with_duplicate_slashes() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let copy_to_command = format!(
+            "COPY (SELECT i AS a FROM generate_series(1,10) i)
+             TO 's3://{}//dir///pg_parquet_test.parquet';",
+            test_bucket_name
+        );
+        Spi::run(&copy_to_command).unwrap();
+
+        // the object is written without empty path segments
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT num_rows FROM parquet.file_metadata('s3://{}/dir/pg_parquet_test.parquet');",
+            test_bucket_name
+        ))
+        .unwrap();
+        assert_eq!(num_rows, Some(10));
+    }
+
+    #[pg_test]
+    fn test_read_after_write_retries_not_found() {
+        let uri = "/tmp/pg_parquet_test_read_after_write.parquet";
+        let hidden_path = "/tmp/pg_parquet_test_read_after_write.parquet.hidden";
+
+        let copy_to_command = format!("COPY (SELECT i FROM generate_series(1,10) i) TO '{}';", uri);
+        Spi::run(&copy_to_command).unwrap();
+
+        // the written object becomes visible only after a while
+        std::fs::rename(uri, hidden_path).unwrap();
+
+        let make_visible = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            std::fs::rename(hidden_path, uri).unwrap();
+        });
+
+        let num_rows_query = format!(
+            "SELECT sum(row_group_num_rows)::bigint FROM (
+                SELECT DISTINCT row_group_id, row_group_num_rows FROM parquet.metadata('{}')
+             ) t;",
+            uri
+        );
+        let num_rows = Spi::get_one::<i64>(&num_rows_query).unwrap().unwrap();
+
+        make_visible.join().unwrap();
+
+        assert_eq!(num_rows, 10);
+    }
+
+    #[pg_test]
+    fn test_read_not_written_object_fails_immediately() {
+        let uri = "/tmp/pg_parquet_test_never_written.parquet";
+
+        let started_at = std::time::Instant::now();
+
+        let error_message =
+            copy_error_message(&format!("PERFORM * FROM parquet.metadata('{}')", uri));
+
+        assert!(error_message.contains("failed to get object store metadata"));
+        assert!(started_at.elapsed() < std::time::Duration::from_millis(500));
+    }
+
+    // copy_error_message returns the error message of the COPY command.
+    fn copy_error_message(copy_command: &str) -> String {
+        Spi::run("SELECT set_config('pg_parquet_test.error_message', '', false);").unwrap();
+
+        Spi::run(&format!(
+            "DO $$
+             DECLARE
+                message text;
+             BEGIN
+                {};
+             EXCEPTION WHEN OTHERS THEN
+                GET STACKED DIAGNOSTICS message = MESSAGE_TEXT;
+                PERFORM set_config('pg_parquet_test.error_message', message, false);
+             END $$;",
+            copy_command
+        ))
+        .unwrap();
+
+        let error_message = Spi::get_one::<String>(
+            "SELECT current_setting('pg_parquet_test.error_message', true);",
+        )
+        .unwrap()
+        .unwrap_or_default();
+
+        assert!(!error_message.is_empty(), "expected command to fail");
+
+        error_message
+    }
+
+    #[pg_test]
+    fn test_uri_secrets_are_redacted_in_errors() {
+        object_store_cache_clear();
+
+        let uris_and_secrets = vec![
+            (
+                "gs://user:secretpassword@testbucket/file.parquet",
+                vec!["user:", "secretpassword"],
+            ),
+            (
+                "https://testbucket.example.com/file.parquet?X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=secretcredential&X-Amz-Signature=secretsignature",
+                vec!["secretcredential", "secretsignature"],
+            ),
+            (
+                "https://testbucket.example.com/file.parquet?Expires=1700000000&Signature=secretsignature",
+                vec!["secretsignature"],
+            ),
+            (
+                "gs://testbucket/file.parquet?sv=2022-11-02&sp=rw&se=2030-01-01T00%3A00%3A00Z&sig=secret%2Bsig",
+                vec!["2022-11-02", "sp=rw", "2030-01-01", "secret%2Bsig", "secret+sig"],
+            ),
+        ];
+
+        Spi::run("CREATE TABLE redact_test (a int);").unwrap();
+
+        for (uri, secrets) in uris_and_secrets {
+            let copy_commands = vec![
+                format!("COPY (SELECT 1 AS a) TO '{}'", uri),
+                format!("COPY redact_test FROM '{}'", uri),
+                format!("PERFORM * FROM parquet.schema('{}')", uri),
+            ];
+
+            for copy_command in copy_commands {
+                let error_message = copy_error_message(&copy_command);
+
+                assert!(
+                    error_message.contains("***"),
+                    "uri is not redacted in \"{}\"",
+                    error_message
+                );
+
+                for secret in &secrets {
+                    assert!(
+                        !error_message.contains(secret),
+                        "\"{}\" is leaked in \"{}\"",
+                        secret,
+                        error_message
+                    );
+                }
+            }
+        }
+    }
+
+    // start_failing_abort_s3_proxy starts a proxy in front of AWS_ENDPOINT_URL, which drops the
+    // connection of an AbortMultipartUpload request instead of forwarding it. It returns the
+    // port of the proxy.
+    fn start_failing_abort_s3_proxy() -> u16 {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL").expect("AWS_ENDPOINT_URL not found");
+        let endpoint = Url::parse(&endpoint).unwrap();
+        let endpoint_addr = format!(
+            "{}:{}",
+            endpoint.host_str().unwrap(),
+            endpoint.port_or_known_default().unwrap()
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for client in listener.incoming() {
+                let Ok(client) = client else {
+                    continue;
+                };
+
+                let Ok(server) = TcpStream::connect(&endpoint_addr) else {
+                    continue;
+                };
+
+                let mut client_reader = client.try_clone().unwrap();
+                let mut server_writer = server.try_clone().unwrap();
+
+                std::thread::spawn(move || {
+                    let mut buf = [0; 64 * 1024];
+
+                    while let Ok(n) = client_reader.read(&mut buf) {
+                        // AbortMultipartUpload request, i.e. DELETE /{bucket}/{key}?uploadId=
+                        let is_abort_request = buf[..n].starts_with(b"DELETE ")
+                            && String::from_utf8_lossy(&buf[..n]).contains("uploadId=");
+
+                        if n == 0 || is_abort_request || server_writer.write_all(&buf[..n]).is_err()
+                        {
+                            break;
+                        }
+                    }
+
+                    let _ = client_reader.shutdown(std::net::Shutdown::Both);
+                    let _ = server_writer.shutdown(std::net::Shutdown::Both);
+                });
+
+                let (mut server_reader, mut client_writer) = (server, client);
+                std::thread::spawn(move || std::io::copy(&mut server_reader, &mut client_writer));
+            }
+        });
+
+        port
+    }
+
+    #[pg_test]
+    fn test_uri_secrets_are_redacted_in_failed_abort() {
+        object_store_cache_clear();
+
+        let test_bucket_name: String =
+            std::env::var("AWS_S3_TEST_BUCKET").expect("AWS_S3_TEST_BUCKET not found");
+
+        let port = start_failing_abort_s3_proxy();
+
+        Spi::run("SET pg_parquet.s3_compatible_hosts TO '127.0.0.1';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_size TO '5MB';").unwrap();
+        Spi::run("SET pg_parquet.upload_part_concurrency TO 1;").unwrap();
+        Spi::run("SET pg_parquet.object_store_max_retries TO 0;").unwrap();
+
+        // fails after the first part is uploaded, so that the multipart upload is aborted
+        let copy_to_command = format!(
+            "DO $$
+             BEGIN
+                COPY (SELECT CASE WHEN i > 300000 THEN i / (i - i) ELSE i END AS i, md5(i::text)
+                      FROM generate_series(1, 400000) i)
+                TO 'http://127.0.0.1:{}/{}/pg_parquet_test_failed_abort.parquet?sig=secretsig'
+                WITH (row_group_size 100000);
+             EXCEPTION WHEN division_by_zero THEN
+                NULL;
+             END $$;",
+            port, test_bucket_name
+        );
+        let warnings = record_warnings(|| Spi::run(&copy_to_command).unwrap());
+
+        let abort_warning = warnings
+            .iter()
+            .find(|warning| warning.starts_with("failed to abort upload of "))
+            .unwrap_or_else(|| panic!("no failed abort in warnings: {:?}", warnings));
+
+        assert!(
+            abort_warning.contains("sig=***"),
+            "uri is not redacted in \"{}\"",
+            abort_warning
+        );
+        assert!(
+            !abort_warning.contains("secretsig"),
+            "\"secretsig\" is leaked in \"{}\"",
+            abort_warning
+        );
+    }
+
     #[pg_test]
     fn test_object_store_cache() {
         object_store_cache_clear();
@@ -741,4 +2353,83 @@ mod tests {
             ]
         );
     }
+
+    #[pg_test]
+    fn test_object_store_cache_eviction() {
+        object_store_cache_clear();
+
+        Spi::run("SET pg_parquet.object_store_cache_max_entries TO 2;").unwrap();
+
+        let copy_to = |uri: &str| {
+            Spi::run(&format!("COPY (SELECT 1 AS a) TO '{}';", uri)).unwrap();
+        };
+
+        copy_to("s3://testbucket/test_cache_eviction.parquet");
+        copy_to("az://testcontainer/test_cache_eviction.parquet");
+
+        let testbucket_hit_count = object_store_cache_hit_count("testbucket");
+
+        // testbucket is used more recently than testcontainer
+        copy_to("s3://testbucket/test_cache_eviction.parquet");
+
+        assert!(object_store_cache_hit_count("testbucket") > testbucket_hit_count);
+
+        // the least recently used testcontainer is evicted
+        copy_to("s3://testbucket2/test_cache_eviction.parquet");
+
+        assert_eq!(
+            object_store_cache_items(),
+            vec![
+                ("AmazonS3", "testbucket", None),
+                ("AmazonS3", "testbucket2", None)
+            ]
+        );
+
+        // lowering the limit evicts the entries above it by the next insert
+        Spi::run("SET pg_parquet.object_store_cache_max_entries TO 1;").unwrap();
+
+        copy_to("az://testcontainer/test_cache_eviction.parquet");
+
+        assert_eq!(
+            object_store_cache_items(),
+            vec![("MicrosoftAzure", "testcontainer", None)]
+        );
+
+        // the evicted object stores are recreated
+        copy_to("s3://testbucket/test_cache_eviction.parquet");
+
+        assert_eq!(
+            object_store_cache_items(),
+            vec![("AmazonS3", "testbucket", None)]
+        );
+
+        Spi::run("RESET pg_parquet.object_store_cache_max_entries;").unwrap();
+    }
+
+    #[pg_test]
+    fn test_object_store_cache_clear() {
+        let test_table =
+            TestTable::<i32>::new("int4".into()).with_uri("s3://testbucket/test1.parquet".into());
+        test_table.insert("INSERT INTO test_expected (a) VALUES (1), (2), (null);");
+        test_table.assert_expected_and_result_rows();
+
+        assert!(!object_store_cache_items().is_empty());
+
+        object_store_cache_clear();
+
+        assert!(object_store_cache_items().is_empty());
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied to access the object store cache")]
+    fn test_object_store_cache_no_read_access() {
+        Spi::run("CREATE USER regular_user;").unwrap();
+
+        // write access does not grant access to the cache
+        Spi::run("GRANT parquet_object_store_write TO regular_user;").unwrap();
+
+        Spi::run("SET SESSION AUTHORIZATION regular_user;").unwrap();
+
+        Spi::run("SELECT * FROM parquet.object_store_cache();").unwrap();
+    }
 }
@@ -0,0 +1,257 @@
+#[pgrx::pg_schema]
+mod tests {
+    use pgrx::{pg_test, Spi};
+
+    use crate::arrow_parquet::temp_files::{
+        end_temp_parquet_files_xact, remove_all_temp_parquet_files,
+    };
+
+    #[pg_test]
+    fn test_temp_file() {
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let create_table = "CREATE TABLE test_table (i int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_temp = "COPY test_table FROM 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_from_temp).unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 10);
+
+        // temp file can be passed to other parquet functions
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT num_rows FROM parquet.file_metadata(parquet.temp('scratch1'));",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 10);
+
+        let temp_file_names = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(name) FROM parquet.temp_list() WHERE file_size > 0;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(temp_file_names, vec!["scratch1".to_string()]);
+    }
+
+    #[pg_test]
+    fn test_temp_file_replace() {
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let old_path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 5) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        assert!(!std::path::Path::new(&old_path).exists());
+
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT num_rows FROM parquet.file_metadata(parquet.temp('scratch1'));",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 5);
+
+        let temp_file_count = Spi::get_one::<i64>("SELECT count(*) FROM parquet.temp_list();")
+            .unwrap()
+            .unwrap();
+        assert_eq!(temp_file_count, 1);
+    }
+
+    #[pg_test]
+    fn test_temp_file_replace_in_later_transaction() {
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let old_path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+
+        // same as the commit callback of the transaction, which created the file
+        end_temp_parquet_files_xact(true);
+
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 5) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let new_path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+
+        // the replaced file is kept until the transaction commits
+        assert!(std::path::Path::new(&old_path).exists());
+
+        // same as the abort callback of the transaction, which replaced the file
+        end_temp_parquet_files_xact(false);
+
+        assert!(!std::path::Path::new(&new_path).exists());
+
+        let path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, old_path);
+
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT num_rows FROM parquet.file_metadata(parquet.temp('scratch1'));",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 10);
+
+        Spi::run(copy_to_temp).unwrap();
+
+        // same as the commit callback of the transaction, which replaced the file
+        end_temp_parquet_files_xact(true);
+
+        assert!(!std::path::Path::new(&old_path).exists());
+
+        let temp_file_count = Spi::get_one::<i64>("SELECT count(*) FROM parquet.temp_list();")
+            .unwrap()
+            .unwrap();
+        assert_eq!(temp_file_count, 1);
+    }
+
+    #[pg_test]
+    fn test_temp_file_rollback_to_savepoint() {
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let old_path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+
+        // an exception block runs in a subtransaction, which commits when the block succeeds
+        Spi::run(
+            "DO $$
+             BEGIN
+                COPY (SELECT i FROM generate_series(1, 3) i) TO 'scratch2' WITH (format parquet, temp true);
+             EXCEPTION WHEN raise_exception THEN
+                NULL;
+             END $$;",
+        )
+        .unwrap();
+
+        // and rolls back to its savepoint when the block fails, the error passes on the paths
+        Spi::run(
+            "DO $$
+             DECLARE
+                rolled_back_paths text;
+             BEGIN
+                COPY (SELECT i FROM generate_series(1, 5) i) TO 'scratch1' WITH (format parquet, temp true);
+                COPY (SELECT i FROM generate_series(1, 5) i) TO 'scratch3' WITH (format parquet, temp true);
+                RAISE EXCEPTION '%', parquet.temp('scratch1') || ',' || parquet.temp('scratch3');
+             EXCEPTION WHEN raise_exception THEN
+                GET STACKED DIAGNOSTICS rolled_back_paths = MESSAGE_TEXT;
+                PERFORM set_config('pg_parquet_test.rolled_back_paths', rolled_back_paths, false);
+             END $$;",
+        )
+        .unwrap();
+
+        // the files created by the rolled back subtransaction are removed
+        let rolled_back_paths =
+            Spi::get_one::<String>("SELECT current_setting('pg_parquet_test.rolled_back_paths');")
+                .unwrap()
+                .unwrap();
+
+        for rolled_back_path in rolled_back_paths.split(',') {
+            assert!(!std::path::Path::new(rolled_back_path).exists());
+        }
+
+        // the file replaced by the rolled back subtransaction is registered again
+        let path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, old_path);
+
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT num_rows FROM parquet.file_metadata(parquet.temp('scratch1'));",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 10);
+
+        // the file created by the committed subtransaction is kept
+        let temp_file_names = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(name ORDER BY name) FROM parquet.temp_list();",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            temp_file_names,
+            vec!["scratch1".to_string(), "scratch2".to_string()]
+        );
+
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT num_rows FROM parquet.file_metadata(parquet.temp('scratch2'));",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 3);
+    }
+
+    #[pg_test]
+    fn test_temp_file_removed_at_session_end() {
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let path = Spi::get_one::<String>("SELECT parquet.temp('scratch1');")
+            .unwrap()
+            .unwrap();
+        assert!(std::path::Path::new(&path).exists());
+
+        // same as the exit callback of the backend
+        remove_all_temp_parquet_files();
+
+        assert!(!std::path::Path::new(&path).exists());
+
+        let temp_file_count = Spi::get_one::<i64>("SELECT count(*) FROM parquet.temp_list();")
+            .unwrap()
+            .unwrap();
+        assert_eq!(temp_file_count, 0);
+    }
+
+    #[pg_test]
+    fn test_temp_file_without_file_privileges() {
+        let create_role = "create role test_role;";
+        Spi::run(create_role).unwrap();
+
+        let create_table = "CREATE TABLE test_table (i int);";
+        Spi::run(create_table).unwrap();
+
+        let grant_table = "grant all on test_table to test_role;";
+        Spi::run(grant_table).unwrap();
+
+        let set_role = "set role test_role;";
+        Spi::run(set_role).unwrap();
+
+        let copy_to_temp = "COPY (SELECT i FROM generate_series(1, 10) i) TO 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_to_temp).unwrap();
+
+        let copy_from_temp = "COPY test_table FROM 'scratch1' WITH (format parquet, temp true);";
+        Spi::run(copy_from_temp).unwrap();
+
+        let reset_role = "reset role;";
+        Spi::run(reset_role).unwrap();
+
+        let count = Spi::get_one::<i64>("SELECT count(*) FROM test_table;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 10);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "temporary parquet file \"nonexistent\" does not exist")]
+    fn test_nonexistent_temp_file() {
+        let create_table = "CREATE TABLE test_table (i int);";
+        Spi::run(create_table).unwrap();
+
+        let copy_from_temp = "COPY test_table FROM 'nonexistent' WITH (format parquet, temp true);";
+        Spi::run(copy_from_temp).unwrap();
+    }
+}
@@ -1,224 +1,445 @@
 #[pgrx::pg_schema]
 mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use arrow::{
+        array::{
+            ArrayRef, BinaryArray, Int32Array, ListArray, MapArray, RecordBatch, StringArray,
+            StructArray,
+        },
+        buffer::{OffsetBuffer, ScalarBuffer},
+        datatypes::{DataType, Field, Schema},
+    };
     use pgrx::{pg_test, Spi};
 
-    use crate::pgrx_tests::common::LOCAL_TEST_FILE_PATH;
+    use crate::pgrx_tests::common::{
+        extension_exists, write_record_batch_to_parquet, LOCAL_TEST_FILE_PATH,
+    };
 
-    #[pg_test]
-    fn test_parquet_schema() {
-        let ddls = format!(
-            "
-            create type person AS (id int, name text);
-            create type worker AS (p person[], monthly_salary decimal(15,6));
-            create table workers (id int, workers worker[], company text);
-            copy workers to '{}';
-        ",
-            LOCAL_TEST_FILE_PATH
-        );
-        Spi::run(&ddls).unwrap();
+    type SchemaRow = (
+        Vec<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        bool,
+        Option<i32>,
+    );
 
-        let parquet_schema_command = format!(
-            "select * from parquet.schema('{}') ORDER BY name, converted_type;",
-            LOCAL_TEST_FILE_PATH
-        );
+    fn parquet_schema_rows(uri: &str) -> Vec<SchemaRow> {
+        let parquet_schema_command = format!("select * from parquet.schema('{}');", uri);
 
-        let result_schema = Spi::connect(|client| {
+        Spi::connect(|client| {
             let mut results = Vec::new();
             let tup_table = client.select(&parquet_schema_command, None, None).unwrap();
 
             for row in tup_table {
-                let uri = row["uri"].value::<String>().unwrap().unwrap();
-                let name = row["name"].value::<String>().unwrap().unwrap();
-                let type_name = row["type_name"].value::<String>().unwrap();
-                let type_length = row["type_length"].value::<String>().unwrap();
-                let repetition_type = row["repetition_type"].value::<String>().unwrap();
-                let num_children = row["num_children"].value::<i32>().unwrap();
-                let converted_type = row["converted_type"].value::<String>().unwrap();
-                let scale = row["scale"].value::<i32>().unwrap();
-                let precision = row["precision"].value::<i32>().unwrap();
-                let field_id = row["field_id"].value::<i32>().unwrap();
+                let result_uri = row["uri"].value::<String>().unwrap().unwrap();
+                assert_eq!(result_uri, uri);
+
+                let field_path = row["field_path"].value::<Vec<String>>().unwrap().unwrap();
+                let parquet_type = row["parquet_type"].value::<String>().unwrap();
                 let logical_type = row["logical_type"].value::<String>().unwrap();
+                let repetition = row["repetition"].value::<String>().unwrap();
+                let postgres_type = row["postgres_type"].value::<String>().unwrap();
+                let nullable = row["nullable"].value::<bool>().unwrap().unwrap();
+                let field_id = row["field_id"].value::<i32>().unwrap();
 
                 results.push((
-                    uri,
-                    name,
-                    type_name,
-                    type_length,
-                    repetition_type,
-                    num_children,
-                    converted_type,
-                    scale,
-                    precision,
-                    field_id,
+                    field_path,
+                    parquet_type,
                     logical_type,
+                    repetition,
+                    postgres_type,
+                    nullable,
+                    field_id,
                 ));
             }
 
             results
-        });
+        })
+    }
+
+    fn field_path(path: &[&str]) -> Vec<String> {
+        path.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[pg_test]
+    fn test_parquet_schema() {
+        let ddls = format!(
+            "
+            create type person AS (id int, name text);
+            create type worker AS (p person[], monthly_salary decimal(15,6));
+            create table workers (id int, workers worker[], company text);
+            copy workers to '{}';
+        ",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&ddls).unwrap();
+
+        let result_schema = parquet_schema_rows(LOCAL_TEST_FILE_PATH);
 
         let expected_schema = vec![
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "arrow_schema".into(),
-                None,
-                None,
-                None,
-                Some(3),
-                None,
-                None,
-                None,
-                None,
+                field_path(&["id"]),
+                Some("INT32".into()),
                 None,
+                Some("OPTIONAL".into()),
+                Some("integer".into()),
+                true,
+                Some(0),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "company".into(),
-                Some("BYTE_ARRAY".into()),
+                field_path(&["workers"]),
                 None,
+                Some("LIST".into()),
                 Some("OPTIONAL".into()),
                 None,
-                Some("UTF8".into()),
-                None,
-                None,
-                Some(8),
-                Some("STRING".into()),
+                true,
+                Some(1),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "element".into(),
+                field_path(&["workers", "list"]),
                 None,
                 None,
-                Some("OPTIONAL".into()),
-                Some(2),
-                None,
+                Some("REPEATED".into()),
                 None,
-                None,
-                Some(2),
+                false,
                 None,
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "element".into(),
+                field_path(&["workers", "list", "element"]),
                 None,
                 None,
                 Some("OPTIONAL".into()),
-                Some(2),
-                None,
-                None,
-                None,
-                Some(4),
                 None,
+                true,
+                Some(3),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "id".into(),
-                Some("INT32".into()),
+                field_path(&["workers", "list", "element", "p"]),
                 None,
+                Some("LIST".into()),
                 Some("OPTIONAL".into()),
                 None,
+                true,
+                Some(4),
+            ),
+            (
+                field_path(&["workers", "list", "element", "p", "list"]),
                 None,
                 None,
+                Some("REPEATED".into()),
                 None,
-                Some(0),
+                false,
                 None,
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "id".into(),
-                Some("INT32".into()),
-                None,
-                Some("OPTIONAL".into()),
-                None,
+                field_path(&["workers", "list", "element", "p", "list", "element"]),
                 None,
                 None,
+                Some("OPTIONAL".into()),
                 None,
+                true,
                 Some(5),
+            ),
+            (
+                field_path(&["workers", "list", "element", "p", "list", "element", "id"]),
+                Some("INT32".into()),
                 None,
+                Some("OPTIONAL".into()),
+                Some("integer".into()),
+                true,
+                Some(6),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "list".into(),
+                field_path(&["workers", "list", "element", "p", "list", "element", "name"]),
+                Some("BYTE_ARRAY".into()),
+                Some("STRING".into()),
+                Some("OPTIONAL".into()),
+                Some("text".into()),
+                true,
+                Some(7),
+            ),
+            (
+                field_path(&["workers", "list", "element", "monthly_salary"]),
+                Some("INT64".into()),
+                Some("DECIMAL".into()),
+                Some("OPTIONAL".into()),
+                Some("numeric(15,6)".into()),
+                true,
+                Some(8),
+            ),
+            (
+                field_path(&["company"]),
+                Some("BYTE_ARRAY".into()),
+                Some("STRING".into()),
+                Some("OPTIONAL".into()),
+                Some("text".into()),
+                true,
+                Some(2),
+            ),
+        ];
+
+        assert_eq!(result_schema, expected_schema);
+
+        Spi::run("DROP TABLE workers; DROP TYPE worker, person;").unwrap();
+    }
+
+    #[pg_test]
+    fn test_parquet_schema_map() {
+        let entries_field = Arc::new(Field::new(
+            "key_value",
+            DataType::Struct(
+                vec![
+                    Field::new("key", DataType::Utf8, false),
+                    Field::new("value", DataType::Int32, true),
+                ]
+                .into(),
+            ),
+            false,
+        ));
+
+        let tags_field = Field::new(
+            "tags",
+            DataType::List(Arc::new(Field::new("element", DataType::Utf8, true))),
+            true,
+        );
+
+        let details_field = Field::new(
+            "details",
+            DataType::Struct(
+                vec![
+                    Field::new(
+                        "attributes",
+                        DataType::Map(entries_field.clone(), false),
+                        true,
+                    ),
+                    tags_field.clone(),
+                ]
+                .into(),
+            ),
+            true,
+        );
+
+        let schema = Arc::new(Schema::new(vec![details_field.clone()]));
+
+        let keys: ArrayRef = Arc::new(StringArray::from(vec![Some("aa"), Some("bb")]));
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None]));
+        let entries = StructArray::try_from(vec![("key", keys), ("value", values)]).unwrap();
+
+        let attributes: ArrayRef = Arc::new(MapArray::new(
+            entries_field,
+            OffsetBuffer::new(ScalarBuffer::from(vec![0, 2])),
+            entries,
+            None,
+            false,
+        ));
+
+        let tags: ArrayRef = Arc::new(ListArray::new(
+            Arc::new(Field::new("element", DataType::Utf8, true)),
+            OffsetBuffer::new(ScalarBuffer::from(vec![0, 1])),
+            Arc::new(StringArray::from(vec![Some("tag")])),
+            None,
+        ));
+
+        let DataType::Struct(details_fields) = details_field.data_type() else {
+            panic!("expected struct type");
+        };
+
+        let details = Arc::new(StructArray::new(
+            details_fields.clone(),
+            vec![attributes, tags],
+            None,
+        ));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![details]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let result_schema = parquet_schema_rows(LOCAL_TEST_FILE_PATH);
+
+        let expected_schema = vec![
+            (
+                field_path(&["details"]),
                 None,
                 None,
-                Some("REPEATED".into()),
-                Some(1),
+                Some("OPTIONAL".into()),
                 None,
+                true,
                 None,
+            ),
+            (
+                field_path(&["details", "attributes"]),
                 None,
+                Some("MAP".into()),
+                Some("OPTIONAL".into()),
                 None,
+                true,
                 None,
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "list".into(),
+                field_path(&["details", "attributes", "key_value"]),
                 None,
                 None,
                 Some("REPEATED".into()),
-                Some(1),
-                None,
-                None,
                 None,
+                false,
                 None,
+            ),
+            (
+                field_path(&["details", "attributes", "key_value", "key"]),
+                Some("BYTE_ARRAY".into()),
+                Some("STRING".into()),
+                Some("REQUIRED".into()),
+                Some("text".into()),
+                false,
                 None,
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "monthly_salary".into(),
-                Some("INT64".into()),
+                field_path(&["details", "attributes", "key_value", "value"]),
+                Some("INT32".into()),
                 None,
                 Some("OPTIONAL".into()),
+                Some("integer".into()),
+                true,
                 None,
-                Some("DECIMAL".into()),
-                Some(6),
-                Some(15),
-                Some(7),
-                Some("DECIMAL".into()),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "name".into(),
-                Some("BYTE_ARRAY".into()),
+                field_path(&["details", "tags"]),
                 None,
+                Some("LIST".into()),
                 Some("OPTIONAL".into()),
+                Some("text[]".into()),
+                true,
                 None,
-                Some("UTF8".into()),
-                None,
-                None,
-                Some(6),
-                Some("STRING".into()),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "p".into(),
+                field_path(&["details", "tags", "list"]),
                 None,
                 None,
-                Some("OPTIONAL".into()),
-                Some(1),
-                Some("LIST".into()),
+                Some("REPEATED".into()),
                 None,
+                false,
                 None,
-                Some(3),
-                Some("LIST".into()),
             ),
             (
-                LOCAL_TEST_FILE_PATH.into(),
-                "workers".into(),
+                field_path(&["details", "tags", "list", "element"]),
+                Some("BYTE_ARRAY".into()),
+                Some("STRING".into()),
+                Some("OPTIONAL".into()),
+                Some("text".into()),
+                true,
                 None,
+            ),
+        ];
+
+        assert_eq!(result_schema, expected_schema);
+    }
+
+    #[pg_test]
+    fn test_parquet_schema_geometry() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        let ddls = format!(
+            "
+            create table geoms (id int, geom geometry(point));
+            copy geoms to '{}';
+        ",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&ddls).unwrap();
+
+        let result_schema = parquet_schema_rows(LOCAL_TEST_FILE_PATH);
+
+        let expected_schema = vec![
+            (
+                field_path(&["id"]),
+                Some("INT32".into()),
                 None,
                 Some("OPTIONAL".into()),
-                Some(1),
-                Some("LIST".into()),
-                None,
+                Some("integer".into()),
+                true,
+                Some(0),
+            ),
+            (
+                field_path(&["geom"]),
+                Some("BYTE_ARRAY".into()),
                 None,
+                Some("OPTIONAL".into()),
+                Some("geometry".into()),
+                true,
                 Some(1),
-                Some("LIST".into()),
             ),
         ];
 
         assert_eq!(result_schema, expected_schema);
+    }
 
-        Spi::run("DROP TABLE workers; DROP TYPE worker, person;").unwrap();
+    #[pg_test]
+    fn test_parquet_schema_geoarrow_extension_type() {
+        // Skip the test if postgis extension is not available
+        if !extension_exists("postgis") {
+            return;
+        }
+
+        let query = "DROP EXTENSION IF EXISTS postgis; CREATE EXTENSION postgis;";
+        Spi::run(query).unwrap();
+
+        // arrow native writers might mark the column only with the extension type,
+        // without geoparquet metadata
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "geom",
+            DataType::Binary,
+            true,
+        )
+        .with_metadata(HashMap::from_iter(vec![(
+            "ARROW:extension:name".to_string(),
+            "geoarrow.wkb".to_string(),
+        )]))]));
+
+        // WKB of POINT(1 2)
+        let point_wkb = hex_to_bytes("0101000000000000000000F03F0000000000000040");
+
+        let geom = Arc::new(BinaryArray::from(vec![Some(point_wkb.as_slice()), None]));
+
+        let batch = RecordBatch::try_new(schema.clone(), vec![geom]).unwrap();
+        write_record_batch_to_parquet(schema, batch);
+
+        let result_schema = parquet_schema_rows(LOCAL_TEST_FILE_PATH);
+
+        let expected_schema = vec![(
+            field_path(&["geom"]),
+            Some("BYTE_ARRAY".into()),
+            None,
+            Some("OPTIONAL".into()),
+            Some("geometry".into()),
+            true,
+            None,
+        )];
+
+        assert_eq!(result_schema, expected_schema);
+
+        Spi::run("create table geoms (geom geometry);").unwrap();
+        Spi::run(&format!("copy geoms from '{}';", LOCAL_TEST_FILE_PATH)).unwrap();
+
+        let point =
+            Spi::get_one::<String>("select ST_AsText(geom) from geoms where geom is not null;")
+                .unwrap()
+                .unwrap();
+        assert_eq!(point, "POINT(1 2)");
+    }
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
     }
 
     #[pg_test]
@@ -613,8 +834,16 @@ mod tests {
                 let num_rows = row["num_rows"].value::<i64>().unwrap().unwrap();
                 let num_row_groups = row["num_row_groups"].value::<i64>().unwrap().unwrap();
                 let format_version = row["format_version"].value::<String>().unwrap().unwrap();
+                let footer_encrypted = row["footer_encrypted"].value::<bool>().unwrap().unwrap();
 
-                results.push((uri, created_by, num_rows, num_row_groups, format_version));
+                results.push((
+                    uri,
+                    created_by,
+                    num_rows,
+                    num_row_groups,
+                    format_version,
+                    footer_encrypted,
+                ));
             }
 
             results
@@ -622,14 +851,26 @@ mod tests {
 
         let expected_file_metadata = vec![(
             LOCAL_TEST_FILE_PATH.into(),
-            Some("pg_parquet".into()),
+            Some(format!("pg_parquet version {}", env!("CARGO_PKG_VERSION"))),
             total_rows,
             total_row_groups,
             "1".into(),
+            false,
         )];
 
         assert_eq!(result_file_metadata, expected_file_metadata);
 
+        // the row groups and the footer fit in the file with its leading magic number
+        let sizes_fit_in_file = Spi::get_one::<bool>(&format!(
+            "select total_byte_size > 0 and footer_size > 8 and
+                    total_byte_size + footer_size + 4 <= (pg_stat_file('{0}')).size
+             from parquet.file_metadata('{0}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(sizes_fit_in_file);
+
         Spi::run("DROP TABLE workers; DROP TYPE worker, person;").unwrap();
     }
 
@@ -747,4 +988,622 @@ mod tests {
 
         Spi::run("DROP TABLE workers; DROP TYPE worker, person;").unwrap();
     }
+
+    type ColumnStatsRow = (
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<i64>,
+        Option<i64>,
+    );
+
+    fn column_stats(uri: &str) -> Vec<ColumnStatsRow> {
+        let column_stats_command = format!(
+            "select path_in_schema, stats_min, stats_max, stats_null_count, num_values
+             from parquet.column_stats('{}') order by column_id;",
+            uri
+        );
+
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&column_stats_command, None, None).unwrap();
+
+            for row in tup_table {
+                results.push((
+                    row["path_in_schema"].value().unwrap(),
+                    row["stats_min"].value().unwrap(),
+                    row["stats_max"].value().unwrap(),
+                    row["stats_null_count"].value().unwrap(),
+                    row["num_values"].value().unwrap(),
+                ));
+            }
+
+            results
+        })
+    }
+
+    #[pg_test]
+    fn test_parquet_column_stats() {
+        let copy_to = format!(
+            "copy (select i as id,
+                          case when i % 4 = 0 then null else 'name_' || i end as name,
+                          array[i, i * 10] as tags
+                   from generate_series(1, 10) i) to '{}' with (row_group_size 3);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let row = |path: &str, min: &str, max: &str, null_count: i64, num_values: i64| {
+            (
+                Some(path.to_string()),
+                Some(min.to_string()),
+                Some(max.to_string()),
+                Some(null_count),
+                Some(num_values),
+            )
+        };
+
+        // min and max are merged across the 4 row groups, nested leaves have dotted paths
+        assert_eq!(
+            column_stats(LOCAL_TEST_FILE_PATH),
+            vec![
+                row("id", "1", "10", 0, 10),
+                row("name", "name_1", "name_9", 2, 10),
+                row("tags.list.element", "1", "100", 0, 20),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_parquet_column_stats_without_statistics() {
+        let copy_to = format!(
+            "copy (select i as id, 'name_' || i as name from generate_series(1, 10) i)
+             to '{}' with (row_group_size 3, statistics 'none');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        assert_eq!(
+            column_stats(LOCAL_TEST_FILE_PATH),
+            vec![
+                (Some("id".to_string()), None, None, None, Some(10)),
+                (Some("name".to_string()), None, None, None, Some(10)),
+            ]
+        );
+    }
+
+    fn create_files_with_missing_and_corrupt_uris() {
+        let copy_to = format!(
+            "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}'",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        std::fs::write("/tmp/pg_parquet_corrupt.parquet", "not a parquet file").unwrap();
+
+        let _ = std::fs::remove_file("/tmp/pg_parquet_missing.parquet");
+
+        let create_table = format!(
+            "CREATE TABLE files (uri text);
+             INSERT INTO files VALUES ('{}'), ('/tmp/pg_parquet_missing.parquet'),
+                                      ('/tmp/pg_parquet_corrupt.parquet');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&create_table).unwrap();
+    }
+
+    #[pg_test]
+    fn test_parquet_udfs_on_error_skip() {
+        create_files_with_missing_and_corrupt_uris();
+
+        let metadata_uris = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(DISTINCT m.uri)
+             FROM files f, LATERAL parquet.metadata(f.uri, on_error => 'skip') m;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(metadata_uris, vec![LOCAL_TEST_FILE_PATH.to_string()]);
+
+        let schema_uris = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(DISTINCT s.uri)
+             FROM files f, LATERAL parquet.schema(f.uri, on_error => 'skip') s;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(schema_uris, vec![LOCAL_TEST_FILE_PATH.to_string()]);
+
+        let num_rows = Spi::get_one::<i64>(
+            "SELECT sum(m.num_rows)::bigint
+             FROM files f, LATERAL parquet.file_metadata(f.uri, on_error => 'skip') m;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 10);
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "failed to get object store metadata")]
+    fn test_parquet_udfs_on_error_raise_missing() {
+        create_files_with_missing_and_corrupt_uris();
+
+        Spi::run(
+            "SELECT * FROM files f, LATERAL parquet.file_metadata(f.uri, on_error => 'raise') m;",
+        )
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "Invalid Parquet file. Corrupt footer")]
+    fn test_parquet_udfs_on_error_raise_corrupt() {
+        create_files_with_missing_and_corrupt_uris();
+
+        Spi::run("DELETE FROM files WHERE uri = '/tmp/pg_parquet_missing.parquet';").unwrap();
+
+        Spi::run("SELECT * FROM files f, LATERAL parquet.schema(f.uri) s;").unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "unrecognized on_error option: ignore")]
+    fn test_parquet_udfs_invalid_on_error() {
+        Spi::run(&format!(
+            "SELECT * FROM parquet.file_metadata('{}', on_error => 'ignore');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "schema \"parquet\" already exists")]
+    fn test_install_with_existing_parquet_schema() {
+        // move the schema of the extension away and create a schema parquet of the user
+        Spi::run("ALTER SCHEMA parquet RENAME TO parquet_ext;").unwrap();
+        Spi::run("CREATE SCHEMA parquet;").unwrap();
+
+        let extension_owns_schema = Spi::get_one::<bool>(
+            "SELECT EXISTS (
+                SELECT 1 FROM pg_depend
+                WHERE classid = 'pg_namespace'::regclass
+                AND objid = 'parquet'::regnamespace
+                AND refclassid = 'pg_extension'::regclass
+                AND deptype = 'e'
+            );",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!extension_owns_schema);
+
+        // CREATE EXTENSION pg_parquet without a schema installs into public
+        let bootstrap = include_str!("../../sql/bootstrap.sql").replace("@extschema@", "public");
+        Spi::run(&bootstrap).unwrap();
+    }
+
+    #[pg_test]
+    fn test_install_into_custom_schema() {
+        Spi::run("DROP EXTENSION pg_parquet;").unwrap();
+        Spi::run("CREATE SCHEMA pgparquet;").unwrap();
+        Spi::run("CREATE EXTENSION pg_parquet SCHEMA pgparquet;").unwrap();
+
+        // schema parquet is not created
+        let parquet_schema_exists = Spi::get_one::<bool>(
+            "SELECT EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = 'parquet');",
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!parquet_schema_exists);
+
+        Spi::run("CREATE TABLE test_table (a int, b text);").unwrap();
+        Spi::run("INSERT INTO test_table VALUES (1, 'one'), (2, 'two');").unwrap();
+
+        let copy_to_parquet = format!("COPY test_table TO '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_to_parquet).unwrap();
+
+        Spi::run("CREATE TABLE test_result (LIKE test_table);").unwrap();
+
+        let copy_from_parquet = format!("COPY test_result FROM '{}';", LOCAL_TEST_FILE_PATH);
+        Spi::run(&copy_from_parquet).unwrap();
+
+        let missing_rows = Spi::get_one::<i64>(
+            "SELECT count(*) FROM (TABLE test_table EXCEPT TABLE test_result) missing;",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(missing_rows, 0);
+
+        let num_rows = Spi::get_one::<i64>(&format!(
+            "SELECT num_rows FROM pgparquet.file_metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_rows, 2);
+
+        let column_chunks = Spi::get_one::<i64>(&format!(
+            "SELECT count(*) FROM pgparquet.metadata('{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(column_chunks, 2);
+
+        let column_names = Spi::get_one::<Vec<String>>(&format!(
+            "SELECT array_agg(name ORDER BY name) FROM pgparquet.schema('{}')
+             WHERE name IN ('a', 'b');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(column_names, vec!["a".to_string(), "b".to_string()]);
+
+        // the registry of the async jobs is found in the schema of the extension
+        let job_id = Spi::get_one::<i64>(&format!(
+            "SELECT pgparquet.export_async('SELECT 1 AS a', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        let status = Spi::get_one::<String>(&format!(
+            "SELECT status FROM pgparquet.job_status({});",
+            job_id
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(status, "pending");
+
+        let cancelled = Spi::get_one::<bool>(&format!("SELECT pgparquet.cancel_job({});", job_id))
+            .unwrap()
+            .unwrap();
+        assert!(cancelled);
+    }
+
+    // the worker of an async job starts after the submitting transaction commits, which never
+    // happens in a test transaction, hence the tests cover the submission and cancellation
+    #[pg_test]
+    fn test_export_async_job_cancel() {
+        let job_id = Spi::get_one::<i64>(&format!(
+            "SELECT parquet.export_async('SELECT i FROM generate_series(1, 10) i;', '{}',
+                                         '{{\"row_group_size\": 5}}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        let (job_type, status) = Spi::get_two::<String, String>(&format!(
+            "SELECT job_type, status FROM parquet.job_status({});",
+            job_id
+        ))
+        .unwrap();
+        assert_eq!(job_type, Some("export".into()));
+        assert_eq!(status, Some("pending".into()));
+
+        let command = Spi::get_one::<String>(&format!(
+            "SELECT command FROM parquet.async_jobs WHERE job_id = {};",
+            job_id
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            command,
+            format!(
+                "COPY (SELECT i FROM generate_series(1, 10) i) TO '{}' WITH (row_group_size 5)",
+                LOCAL_TEST_FILE_PATH
+            )
+        );
+
+        let cancelled = Spi::get_one::<bool>(&format!("SELECT parquet.cancel_job({});", job_id))
+            .unwrap()
+            .unwrap();
+        assert!(cancelled);
+
+        let status = Spi::get_one::<String>(&format!(
+            "SELECT status FROM parquet.job_status({});",
+            job_id
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(status, "cancelled");
+
+        // already cancelled
+        let cancelled = Spi::get_one::<bool>(&format!("SELECT parquet.cancel_job({});", job_id))
+            .unwrap()
+            .unwrap();
+        assert!(!cancelled);
+    }
+
+    #[pg_test]
+    fn test_import_async_job_of_role() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run("CREATE ROLE test_role;").unwrap();
+        Spi::run("GRANT pg_read_server_files TO test_role;").unwrap();
+        Spi::run("GRANT INSERT ON test_table TO test_role;").unwrap();
+
+        Spi::run("SET ROLE test_role;").unwrap();
+
+        let job_id = Spi::get_one::<i64>(&format!(
+            "SELECT parquet.import_async('test_table', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        let command = Spi::get_one::<String>(&format!(
+            "SELECT command FROM parquet.async_jobs WHERE job_id = {};",
+            job_id
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            command,
+            format!("COPY public.test_table FROM '{}'", LOCAL_TEST_FILE_PATH)
+        );
+
+        Spi::run("RESET ROLE;").unwrap();
+
+        // jobs are only visible to their roles
+        Spi::run("CREATE ROLE other_role;").unwrap();
+        Spi::run("SET ROLE other_role;").unwrap();
+
+        let visible_jobs = Spi::get_one::<i64>("SELECT count(*) FROM parquet.async_jobs;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(visible_jobs, 0);
+
+        // and can only be cancelled by their roles
+        let cancelled = Spi::get_one::<bool>(&format!("SELECT parquet.cancel_job({});", job_id))
+            .unwrap()
+            .unwrap();
+        assert!(!cancelled);
+
+        Spi::run("RESET ROLE;").unwrap();
+
+        let status = Spi::get_one::<String>(&format!(
+            "SELECT status FROM parquet.job_status({});",
+            job_id
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(status, "pending");
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied for table async_jobs")]
+    fn test_async_jobs_not_writable_by_roles() {
+        Spi::run("CREATE ROLE test_role;").unwrap();
+        Spi::run("GRANT pg_write_server_files TO test_role;").unwrap();
+        Spi::run("SET ROLE test_role;").unwrap();
+
+        let job_id = Spi::get_one::<i64>(&format!(
+            "SELECT parquet.export_async('SELECT 1', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        // the command of a submitted job cannot be changed
+        Spi::run(&format!(
+            "UPDATE parquet.async_jobs SET command = 'COPY (SELECT 2) TO ''/tmp/other.parquet'''
+             WHERE job_id = {};",
+            job_id
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "query must be a single SELECT statement")]
+    fn test_export_async_multiple_statements() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run(&format!(
+            "SELECT parquet.export_async('SELECT 1; DROP TABLE test_table', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "query must be a single SELECT statement")]
+    fn test_export_async_not_select() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run(&format!(
+            "SELECT parquet.export_async('DELETE FROM test_table RETURNING a', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "syntax error")]
+    fn test_export_async_query_escaping_copy() {
+        Spi::run(&format!(
+            "SELECT parquet.export_async('SELECT 1) TO ''/tmp/other.parquet'' --', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied to COPY to a file")]
+    fn test_export_async_without_privilege() {
+        Spi::run("CREATE ROLE test_role;").unwrap();
+        Spi::run("SET ROLE test_role;").unwrap();
+
+        Spi::run(&format!(
+            "SELECT parquet.export_async('SELECT 1', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied for table test_table")]
+    fn test_import_async_without_insert_privilege() {
+        Spi::run("CREATE TABLE test_table (a int);").unwrap();
+
+        Spi::run("CREATE ROLE test_role;").unwrap();
+        Spi::run("GRANT pg_read_server_files TO test_role;").unwrap();
+        Spi::run("SET ROLE test_role;").unwrap();
+
+        Spi::run(&format!(
+            "SELECT parquet.import_async('test_table', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "relation \"missing_table\" does not exist")]
+    fn test_import_async_missing_table() {
+        Spi::run(&format!(
+            "SELECT parquet.import_async('missing_table', '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "relation with OID 4294967295 does not exist")]
+    fn test_import_async_missing_table_oid() {
+        Spi::run(&format!(
+            "SELECT parquet.import_async(4294967295::oid::regclass, '{}');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "options must be a json object")]
+    fn test_export_async_invalid_options() {
+        Spi::run(&format!(
+            "SELECT parquet.export_async('SELECT 1', '{}', '[1]');",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap();
+    }
+
+    type ValidateRow = (Option<bool>, Option<i64>, Option<i32>, Option<String>);
+
+    fn validate(uri: &str, deep: bool) -> ValidateRow {
+        let validate_command = format!(
+            "select ok, num_rows, num_row_groups, error from parquet.validate('{}', deep => {});",
+            uri, deep
+        );
+
+        Spi::connect(|client| {
+            let mut results = Vec::new();
+            let tup_table = client.select(&validate_command, None, None).unwrap();
+
+            for row in tup_table {
+                results.push((
+                    row["ok"].value().unwrap(),
+                    row["num_rows"].value().unwrap(),
+                    row["num_row_groups"].value().unwrap(),
+                    row["error"].value().unwrap(),
+                ));
+            }
+
+            assert_eq!(results.len(), 1);
+
+            results.remove(0)
+        })
+    }
+
+    #[pg_test]
+    fn test_validate() {
+        let copy_to = format!(
+            "copy (select i as id, 'name_' || i as name from generate_series(1, 10) i)
+             to '{}' with (row_group_size 3);",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        let expected = (Some(true), Some(10), Some(4), None);
+
+        assert_eq!(validate(LOCAL_TEST_FILE_PATH, false), expected);
+        assert_eq!(validate(LOCAL_TEST_FILE_PATH, true), expected);
+    }
+
+    #[pg_test]
+    fn test_validate_truncated_file() {
+        let copy_to = format!(
+            "copy (select i as id from generate_series(1, 10) i) to '{}';",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // the footer is lost, hence both modes fail
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(LOCAL_TEST_FILE_PATH)
+            .unwrap();
+        let file_size = file.metadata().unwrap().len();
+        file.set_len(file_size - 100).unwrap();
+
+        for deep in [false, true] {
+            let (ok, num_rows, num_row_groups, error) = validate(LOCAL_TEST_FILE_PATH, deep);
+
+            assert_eq!((ok, num_rows, num_row_groups), (Some(false), None, None));
+            assert!(error.unwrap().contains("Corrupt footer"));
+        }
+    }
+
+    #[pg_test]
+    fn test_validate_corrupt_page() {
+        let copy_to = format!(
+            "copy (select i as id, 'name_' || i as name from generate_series(1, 10) i)
+             to '{}' with (row_group_size 5, compression 'uncompressed');",
+            LOCAL_TEST_FILE_PATH
+        );
+        Spi::run(&copy_to).unwrap();
+
+        // overwrites the first page header of the column chunk of "name" in the second row group
+        let page_offset = Spi::get_one::<i64>(&format!(
+            "select coalesce(dictionary_page_offset, data_page_offset)
+             from parquet.metadata('{}') where row_group_id = 1 and path_in_schema = 'name';",
+            LOCAL_TEST_FILE_PATH
+        ))
+        .unwrap()
+        .unwrap();
+
+        let mut file_bytes = std::fs::read(LOCAL_TEST_FILE_PATH).unwrap();
+        file_bytes[page_offset as usize..page_offset as usize + 16].fill(0xFF);
+        std::fs::write(LOCAL_TEST_FILE_PATH, file_bytes).unwrap();
+
+        // the footer is intact
+        assert_eq!(
+            validate(LOCAL_TEST_FILE_PATH, false),
+            (Some(true), Some(10), Some(2), None)
+        );
+
+        let (ok, num_rows, num_row_groups, error) = validate(LOCAL_TEST_FILE_PATH, true);
+
+        assert_eq!(
+            (ok, num_rows, num_row_groups),
+            (Some(false), Some(10), Some(2))
+        );
+        assert!(error.unwrap().starts_with("row group 1 column \"name\": "));
+    }
+
+    #[pg_test]
+    fn test_validate_missing_file() {
+        let _ = std::fs::remove_file("/tmp/pg_parquet_missing.parquet");
+
+        let (ok, num_rows, num_row_groups, error) =
+            validate("/tmp/pg_parquet_missing.parquet", false);
+
+        assert_eq!((ok, num_rows, num_row_groups), (Some(false), None, None));
+        assert!(error
+            .unwrap()
+            .starts_with("failed to get object store metadata"));
+    }
+
+    #[pg_test]
+    #[should_panic(expected = "permission denied to COPY from a file")]
+    fn test_validate_without_read_privilege() {
+        Spi::run("CREATE ROLE test_role;").unwrap();
+        Spi::run("SET ROLE test_role;").unwrap();
+
+        validate(LOCAL_TEST_FILE_PATH, false);
+    }
 }
@@ -1,14 +1,21 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, future::Future, time::Duration};
 
 use pgrx::{
+    check_for_interrupts,
     pg_sys::{
         getBaseType, getBaseTypeAndTypmod, get_element_type, get_extension_oid,
-        lookup_rowtype_tupdesc, type_is_array, type_is_rowtype, AsPgCStr, FormData_pg_attribute,
-        InvalidOid, Oid,
+        lookup_rowtype_tupdesc, type_is_array, type_is_enum, type_is_rowtype, AsPgCStr,
+        FormData_pg_attribute, InvalidOid, Oid,
     },
-    PgTupleDesc,
+    PgTupleDesc, Spi,
 };
 
+use crate::PG_BACKEND_TOKIO_RUNTIME;
+
+// INTERRUPT_CHECK_INTERVAL is the interval, in which the backend checks for interrupts while
+// it waits for a future, e.g. an object store request.
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum CollectAttributesFor {
     CopyFrom,
@@ -73,6 +80,10 @@ pub(crate) fn is_array_type(typoid: Oid) -> bool {
     unsafe { type_is_array(typoid) }
 }
 
+pub(crate) fn is_enum_type(typoid: Oid) -> bool {
+    unsafe { type_is_enum(typoid) }
+}
+
 pub(crate) fn is_domain_of_array_type(typoid: Oid) -> bool {
     if is_array_type(typoid) {
         return false;
@@ -102,8 +113,59 @@ pub(crate) fn domain_array_base_elem_type(domain_typoid: Oid) -> (Oid, i32) {
     (array_element_typoid(base_array_typoid), base_array_typmod)
 }
 
+// domain_base_type returns the base type and typmod of the domain, or None if the type is
+// not a domain. Nested domains are resolved to the base type of the innermost domain.
+pub(crate) fn domain_base_type(typoid: Oid) -> Option<(Oid, i32)> {
+    let mut base_typmod = -1;
+    let base_typoid = unsafe { getBaseTypeAndTypmod(typoid, &mut base_typmod) };
+
+    if base_typoid == typoid {
+        None
+    } else {
+        Some((base_typoid, base_typmod))
+    }
+}
+
 pub(crate) fn extension_exists(extension_name: &str) -> bool {
     let extension_name = extension_name.as_pg_cstr();
     let extension_oid = unsafe { get_extension_oid(extension_name, true) };
     extension_oid != InvalidOid
 }
+
+// extension_schema_name returns the quoted name of the schema, in which the objects of
+// pg_parquet are created, i.e. parquet by default or the schema of CREATE EXTENSION ... SCHEMA.
+// It is found by the async_jobs table of the extension, since the schema of the extension is
+// public by default, while its objects are in schema parquet.
+pub(crate) fn extension_schema_name() -> String {
+    Spi::get_one::<String>(
+        "SELECT quote_ident(n.nspname)
+         FROM pg_depend d
+         JOIN pg_extension e ON d.refclassid = 'pg_extension'::regclass AND d.refobjid = e.oid
+         JOIN pg_class c ON d.classid = 'pg_class'::regclass AND d.objid = c.oid
+         JOIN pg_namespace n ON c.relnamespace = n.oid
+         WHERE e.extname = 'pg_parquet' AND d.deptype = 'e' AND c.relname = 'async_jobs'",
+    )
+    .unwrap_or_else(|e| panic!("failed to find the schema of pg_parquet: {}", e))
+    .unwrap_or_else(|| panic!("pg_parquet is not installed in the database"))
+}
+
+// block_on_interruptible runs the future on the backend's tokio runtime, and checks for
+// interrupts, e.g. a cancel request or statement_timeout, while the future is pending.
+// A pending interrupt raises its usual error, e.g. "canceling statement due to user request",
+// and the future is dropped. Interrupts are processed outside of the runtime.
+pub(crate) fn block_on_interruptible<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+
+    loop {
+        let output = PG_BACKEND_TOKIO_RUNTIME.block_on(async {
+            tokio::time::timeout(INTERRUPT_CHECK_INTERVAL, future.as_mut())
+                .await
+                .ok()
+        });
+
+        match output {
+            Some(output) => return output,
+            None => check_for_interrupts!(),
+        }
+    }
+}
@@ -1,4 +1,9 @@
 pub(crate) mod fallback_to_text;
 pub(crate) mod geometry;
+pub(crate) mod geometry_crs;
 pub(crate) mod map;
+pub(crate) mod money;
+pub(crate) mod multidim_array;
+pub(crate) mod network_address;
 pub(crate) mod pg_arrow_type_conversions;
+pub(crate) mod server_encoding;
@@ -1,15 +1,20 @@
-use std::{borrow::BorrowMut, ffi::CStr};
+use std::{
+    borrow::BorrowMut,
+    ffi::{CStr, CString},
+};
 
 use once_cell::sync::OnceCell;
 use pgrx::{
     datum::UnboxDatum,
     pg_sys::{
-        fmgr_info, getTypeInputInfo, getTypeOutputInfo, AsPgCStr, Datum, FmgrInfo,
-        InputFunctionCall, InvalidOid, Oid, OutputFunctionCall,
+        fmgr_info, getTypeInputInfo, getTypeOutputInfo, Datum, FmgrInfo, InputFunctionCall,
+        InvalidOid, Oid, OutputFunctionCall, BPCHAROID,
     },
     FromDatum, IntoDatum, PgBox,
 };
 
+use super::server_encoding::{server_to_utf8, utf8_to_server};
+
 // we need to reset the FallbackToTextContext for each type which fallbacks to text
 static mut FALLBACK_TO_TEXT_CONTEXT: OnceCell<FallbackToTextContext> = OnceCell::new();
 
@@ -83,6 +88,21 @@ impl FallbackToTextContext {
 
         out_func
     }
+
+    // output returns the text representation of the datum, which is converted from the server
+    // encoding into UTF-8. Trailing blanks of char(n) values are trimmed, as the cast to text
+    // does, and the input function pads them back on COPY FROM.
+    fn output(&mut self, datum: Datum) -> String {
+        let att_cstr = unsafe { OutputFunctionCall(self.output_func.borrow_mut(), datum) };
+
+        let att_val = server_to_utf8(unsafe { CStr::from_ptr(att_cstr) }.to_bytes());
+
+        if self.typoid == BPCHAROID {
+            att_val.trim_end_matches(' ').to_owned()
+        } else {
+            att_val
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -98,10 +118,14 @@ impl IntoDatum for FallbackToText {
     fn into_datum(self) -> Option<Datum> {
         let fallback_to_text_context = get_fallback_to_text_context();
 
+        // the text is converted into the server encoding, which the input function expects
+        let text = CString::new(utf8_to_server(&self.0))
+            .expect("fallback-to-text attribute value contains a nul byte");
+
         let datum = unsafe {
             InputFunctionCall(
                 fallback_to_text_context.input_func.borrow_mut(),
-                self.0.as_pg_cstr(),
+                text.as_ptr() as *mut _,
                 fallback_to_text_context.input_ioparam,
                 fallback_to_text_context.typmod,
             )
@@ -123,16 +147,7 @@ impl FromDatum for FallbackToText {
         if is_null {
             None
         } else {
-            let att_cstr = OutputFunctionCall(
-                get_fallback_to_text_context().output_func.borrow_mut(),
-                datum,
-            );
-            let att_val = CStr::from_ptr(att_cstr)
-                .to_str()
-                .expect("fallback-to-text attribute value is not a valid C string")
-                .to_owned();
-
-            Some(Self(att_val))
+            Some(Self(get_fallback_to_text_context().output(datum)))
         }
     }
 }
@@ -144,16 +159,6 @@ unsafe impl UnboxDatum for FallbackToText {
     where
         Self: 'src,
     {
-        let att_cstr = OutputFunctionCall(
-            get_fallback_to_text_context().output_func.borrow_mut(),
-            datum.sans_lifetime(),
-        );
-
-        let att_val = CStr::from_ptr(att_cstr)
-            .to_str()
-            .expect("fallback-to-text attribute value is not a valid C string")
-            .to_owned();
-
-        Self(att_val)
+        Self(get_fallback_to_text_context().output(datum.sans_lifetime()))
     }
 }
@@ -1,46 +1,96 @@
-use std::{collections::HashMap, ffi::CString, ops::Deref};
+use std::{
+    collections::HashMap,
+    ffi::{c_int, CString},
+    ops::Deref,
+};
 
 use once_cell::sync::OnceCell;
 use pgrx::{
     datum::UnboxDatum,
+    pg_guard,
     pg_sys::{
-        get_extension_oid, makeString, Anum_pg_type_oid, AsPgCStr, Datum, GetSysCacheOid,
-        InvalidOid, LookupFuncName, Oid, OidFunctionCall1Coll, SysCacheIdentifier::TYPENAMENSP,
-        BYTEAOID,
+        get_extension_oid, makeString, Anum_pg_type_oid, AsPgCStr, CacheRegisterSyscacheCallback,
+        Datum, FirstNormalObjectId, FormData_pg_attribute, GetSysCacheOid, InvalidOid,
+        LookupFuncName, Oid, OidFunctionCall1Coll, OidFunctionCall2Coll,
+        SysCacheIdentifier::{PROCOID, TYPENAMENSP, TYPEOID},
+        BYTEAOID, INT4OID,
     },
     FromDatum, IntoDatum, PgList, PgTupleDesc, Spi,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::pgrx_utils::{collect_attributes_for, CollectAttributesFor};
+use crate::pgrx_utils::{
+    array_element_typoid, collect_attributes_for, is_array_type, is_composite_type, tuple_desc,
+    CollectAttributesFor,
+};
+
+use super::geometry_crs::srid_crs;
 
-// we need to reset the postgis context at each copy start
+// POSTGIS_CONTEXT is initialized lazily at the first lookup of a geometry column, and cached
+// across COPYs until a pg_type or pg_proc invalidation, e.g. CREATE or DROP EXTENSION postgis.
 static mut POSTGIS_CONTEXT: OnceCell<PostgisContext> = OnceCell::new();
 
+// POSTGIS_CONTEXT_VALID is cleared by the syscache callbacks. We do not drop the context in
+// the callbacks since they might fire while the context is being initialized.
+static mut POSTGIS_CONTEXT_VALID: bool = false;
+
+// POSTGIS_CONTEXT_INIT_COUNT is the number of times the postgis context is initialized
+static mut POSTGIS_CONTEXT_INIT_COUNT: i64 = 0;
+
 fn get_postgis_context() -> &'static PostgisContext {
     #[allow(static_mut_refs)]
     unsafe {
+        if !POSTGIS_CONTEXT_VALID || POSTGIS_CONTEXT.get().is_none() {
+            // set before initialization to not miss an invalidation during the catalog lookups
+            POSTGIS_CONTEXT_VALID = true;
+
+            let postgis_context = PostgisContext::new();
+
+            POSTGIS_CONTEXT_INIT_COUNT += 1;
+
+            POSTGIS_CONTEXT.take();
+
+            POSTGIS_CONTEXT
+                .set(postgis_context)
+                .expect("failed to initialize postgis context");
+        }
+
         POSTGIS_CONTEXT
             .get()
             .expect("postgis context is not initialized")
     }
 }
 
-pub(crate) fn reset_postgis_context() {
-    #[allow(static_mut_refs)]
+// register_postgis_context_invalidation registers the syscache callbacks, which invalidate the
+// cached postgis context when a type or function is created, altered or dropped.
+// pg_extension has no syscache, but CREATE, ALTER and DROP EXTENSION postgis change its types.
+pub(crate) fn register_postgis_context_invalidation() {
     unsafe {
-        POSTGIS_CONTEXT.take()
-    };
+        CacheRegisterSyscacheCallback(
+            TYPEOID as _,
+            Some(invalidate_postgis_context),
+            Datum::from(0),
+        );
+
+        CacheRegisterSyscacheCallback(
+            PROCOID as _,
+            Some(invalidate_postgis_context),
+            Datum::from(0),
+        );
+    }
+}
 
-    #[allow(static_mut_refs)]
-    unsafe {
-        POSTGIS_CONTEXT
-            .set(PostgisContext::new())
-            .expect("failed to reset postgis context")
-    };
+#[pg_guard]
+unsafe extern "C" fn invalidate_postgis_context(_arg: Datum, _cacheid: c_int, _hashvalue: u32) {
+    POSTGIS_CONTEXT_VALID = false;
 }
 
 pub(crate) fn is_postgis_geometry_type(typoid: Oid) -> bool {
+    // builtin types cannot be geometry, no need to initialize the postgis context
+    if typoid.as_u32() < FirstNormalObjectId {
+        return false;
+    }
+
     if let Some(geometry_typoid) = get_postgis_context().geometry_typoid {
         return typoid == geometry_typoid;
     }
@@ -88,6 +138,146 @@ pub(crate) enum GeometryEncoding {
 pub(crate) struct GeometryColumn {
     pub(crate) encoding: GeometryEncoding,
     pub(crate) geometry_types: Vec<GeometryType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) covering: Option<GeometryCovering>,
+    // crs is omitted when the SRID is unknown, which readers interpret as OGC:CRS84.
+    // It is null when the column has mixed SRIDs, which means the crs is unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) crs: Option<serde_json::Value>,
+}
+
+// GeometryCovering refers to the bbox column of a geometry column by the paths of its fields,
+// e.g. {"bbox":{"xmin":["a_bbox","xmin"],"ymin":["a_bbox","ymin"],...}}
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GeometryCovering {
+    pub(crate) bbox: BboxCovering,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct BboxCovering {
+    pub(crate) xmin: Vec<String>,
+    pub(crate) ymin: Vec<String>,
+    pub(crate) xmax: Vec<String>,
+    pub(crate) ymax: Vec<String>,
+}
+
+impl GeometryCovering {
+    fn new(geometry_column_name: &str) -> Self {
+        let bbox_column_name = geoparquet_bbox_column_name(geometry_column_name);
+
+        let path = |field_name: &str| vec![bbox_column_name.clone(), field_name.to_string()];
+
+        GeometryCovering {
+            bbox: BboxCovering {
+                xmin: path("xmin"),
+                ymin: path("ymin"),
+                xmax: path("xmax"),
+                ymax: path("ymax"),
+            },
+        }
+    }
+}
+
+// geoparquet_bbox_column_name returns the name of the bbox column, which is written for
+// the geometry column with geoparquet_covering option.
+pub(crate) fn geoparquet_bbox_column_name(geometry_column_name: &str) -> String {
+    format!("{}_bbox", geometry_column_name)
+}
+
+// GeometryPath is the path from a column to its geometries, which are the column itself, the
+// elements of its array, or a field of its composite type.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GeometryPath {
+    Geometry,
+    Elements(Box<GeometryPath>),
+    Field(String, Box<GeometryPath>),
+}
+
+// GeometryColumnPath is a geometry column, or a geometry nested in a column, which is written
+// as WKB and listed in the geoparquet metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GeometryColumnPath {
+    pub(crate) attnum: i16,
+    // the column name, followed by the names of the composite fields, separated by dots,
+    // e.g. "location.geom" for the geom field of the location column. The elements of arrays
+    // do not have names.
+    pub(crate) name: String,
+    pub(crate) path: GeometryPath,
+    pub(crate) typmod: i32,
+}
+
+// geometry_column_paths returns the geometries of the attributes, including the geometry
+// fields of composite types and the elements of geometry arrays, in the attribute order.
+pub(crate) fn geometry_column_paths(
+    attributes: &[FormData_pg_attribute],
+) -> Vec<GeometryColumnPath> {
+    let mut geometry_column_paths = vec![];
+
+    for attribute in attributes {
+        for (field_names, path, typmod) in geometry_paths(attribute.atttypid, attribute.atttypmod) {
+            let name = std::iter::once(attribute.name().to_string())
+                .chain(field_names)
+                .collect::<Vec<_>>()
+                .join(".");
+
+            geometry_column_paths.push(GeometryColumnPath {
+                attnum: attribute.attnum,
+                name,
+                path,
+                typmod,
+            });
+        }
+    }
+
+    geometry_column_paths
+}
+
+// geometry_paths returns the field names, the path and the typmod of the geometries of a type.
+fn geometry_paths(typoid: Oid, typmod: i32) -> Vec<(Vec<String>, GeometryPath, i32)> {
+    // builtin types cannot be or contain geometry, no need to initialize the postgis context
+    if typoid.as_u32() < FirstNormalObjectId {
+        return vec![];
+    }
+
+    if is_postgis_geometry_type(typoid) {
+        return vec![(vec![], GeometryPath::Geometry, typmod)];
+    }
+
+    if is_array_type(typoid) {
+        // the typmod of an array applies to its elements
+        return geometry_paths(array_element_typoid(typoid), typmod)
+            .into_iter()
+            .map(|(field_names, path, typmod)| {
+                (field_names, GeometryPath::Elements(Box::new(path)), typmod)
+            })
+            .collect();
+    }
+
+    if !is_composite_type(typoid) {
+        return vec![];
+    }
+
+    let tupledesc = tuple_desc(typoid, typmod);
+
+    let mut paths = vec![];
+
+    for attribute in collect_attributes_for(CollectAttributesFor::Other, &tupledesc) {
+        let field_name = attribute.name().to_string();
+
+        for (mut field_names, path, typmod) in
+            geometry_paths(attribute.atttypid, attribute.atttypmod)
+        {
+            field_names.insert(0, field_name.clone());
+
+            paths.push((
+                field_names,
+                GeometryPath::Field(field_name.clone(), Box::new(path)),
+                typmod,
+            ));
+        }
+    }
+
+    paths
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,20 +288,24 @@ pub(crate) struct GeometryColumnsMetadata {
 }
 
 impl GeometryColumnsMetadata {
-    fn from_tupledesc(tupledesc: &PgTupleDesc) -> Option<GeometryColumnsMetadata> {
+    // from_tupledesc returns the metadata of the geometry columns, and of the geometries nested
+    // in composite types and arrays by their dotted names. The crs of a column is set from the
+    // SRID of its type, e.g. geometry(point, 3857). Otherwise, it is set by the writer from the
+    // SRIDs of the column's values.
+    pub(crate) fn from_tupledesc(
+        tupledesc: &PgTupleDesc,
+        geoparquet_covering: bool,
+    ) -> Option<GeometryColumnsMetadata> {
         let mut columns = HashMap::new();
         let mut primary_column = String::new();
+        let mut first_nested_column = None;
 
         let attributes = collect_attributes_for(CollectAttributesFor::CopyTo, tupledesc);
 
-        for attribute in attributes {
-            let attribute_typoid = attribute.type_oid().value();
+        for geometry_column_path in geometry_column_paths(&attributes) {
+            let typmod = geometry_column_path.typmod;
 
-            if !is_postgis_geometry_type(attribute_typoid) {
-                continue;
-            }
-
-            let typmod = attribute.type_mod();
+            let is_top_level = geometry_column_path.path == GeometryPath::Geometry;
 
             let geometry_types = if let Some(geom_type) = GeometryType::from_typmod(typmod) {
                 vec![geom_type]
@@ -121,15 +315,28 @@ impl GeometryColumnsMetadata {
 
             let encoding = GeometryEncoding::WKB;
 
+            let column_name = geometry_column_path.name;
+
+            // bbox columns are only written for the top level geometry columns
+            let covering = if geoparquet_covering && is_top_level {
+                Some(GeometryCovering::new(&column_name))
+            } else {
+                None
+            };
+
+            let crs = srid_from_typmod(typmod).map(srid_crs);
+
             let geometry_column = GeometryColumn {
                 encoding,
                 geometry_types,
+                covering,
+                crs,
             };
 
-            let column_name = attribute.name().to_string();
-
-            // we use the first geometry column as the primary column
-            if primary_column.is_empty() {
+            // we use the first top level geometry column as the primary column
+            if !is_top_level {
+                first_nested_column.get_or_insert_with(|| column_name.clone());
+            } else if primary_column.is_empty() {
                 primary_column = column_name.clone();
             }
 
@@ -140,31 +347,64 @@ impl GeometryColumnsMetadata {
             return None;
         }
 
+        if primary_column.is_empty() {
+            primary_column = first_nested_column.unwrap_or_default();
+        }
+
         Some(GeometryColumnsMetadata {
             version: "1.1.0".into(),
             primary_column,
             columns,
         })
     }
+
+    // to_json returns metadata for geometry columns in json format.
+    // in a format specified by https://geoparquet.org/releases/v1.1.0
+    // e.g. "{\"version\":\"1.1.0\",
+    //        \"primary_column\":\"a\",
+    //        \"columns\":{\"a\":{\"encoding\":\"WKB\", \"geometry_types\":[\"Point\"]},
+    //                     \"b\":{\"encoding\":\"WKB\", \"geometry_types\":[\"LineString\"]}}}"
+    // With geoparquet_covering, each column also refers to its bbox column by "covering".
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self)
+            .unwrap_or_else(|_| panic!("failed to serialize geometry columns metadata {:?}", self))
+    }
 }
 
-// geoparquet_metadata_json_from_tupledesc returns metadata for geometry columns in json format.
-// in a format specified by https://geoparquet.org/releases/v1.1.0
-// e.g. "{\"version\":\"1.1.0\",
-//        \"primary_column\":\"a\",
-//        \"columns\":{\"a\":{\"encoding\":\"WKB\", \"geometry_types\":[\"Point\"]},
-//                     \"b\":{\"encoding\":\"WKB\", \"geometry_types\":[\"LineString\"]}}}"
-pub(crate) fn geoparquet_metadata_json_from_tupledesc(tupledesc: &PgTupleDesc) -> Option<String> {
-    let geometry_columns_metadata = GeometryColumnsMetadata::from_tupledesc(tupledesc);
+// geometry columns are written as Binary fields marked with the "geoarrow.wkb" extension type,
+// in addition to the geoparquet file metadata, since arrow native readers look for the
+// extension type on the field. See https://geoarrow.org/extension-types
+pub(crate) const GEOARROW_WKB_EXTENSION_NAME: &str = "geoarrow.wkb";
 
-    geometry_columns_metadata.map(|metadata| {
-        serde_json::to_string(&metadata).unwrap_or_else(|_| {
-            panic!(
-                "failed to serialize geometry columns metadata {:?}",
-                metadata
-            )
-        })
-    })
+// srid_from_typmod returns the SRID of a geometry typmod, e.g. 4326 for geometry(point, 4326).
+// see postgis: TYPMOD_GET_SRID in liblwgeom.h
+pub(crate) fn srid_from_typmod(typmod: i32) -> Option<i32> {
+    if typmod < 0 {
+        return None;
+    }
+
+    let srid = ((typmod & 0x0FFFFF00) - (typmod & 0x08000000)) >> 8;
+
+    // 0 is the unknown SRID
+    if srid <= 0 {
+        return None;
+    }
+
+    Some(srid)
+}
+
+// geoarrow_wkb_extension_metadata returns the "ARROW:extension:metadata" of a geometry field,
+// e.g. {"crs":"EPSG:4326","crs_type":"authority_code"}, or {} if the SRID is unknown.
+pub(crate) fn geoarrow_wkb_extension_metadata(typmod: i32) -> String {
+    let metadata = match srid_from_typmod(typmod) {
+        Some(srid) => serde_json::json!({
+            "crs": format!("EPSG:{}", srid),
+            "crs_type": "authority_code",
+        }),
+        None => serde_json::json!({}),
+    };
+
+    metadata.to_string()
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -172,6 +412,9 @@ struct PostgisContext {
     geometry_typoid: Option<Oid>,
     st_asbinary_funcoid: Option<Oid>,
     st_geomfromwkb_funcoid: Option<Oid>,
+    st_srid_funcoid: Option<Oid>,
+    st_setsrid_funcoid: Option<Oid>,
+    st_transform_funcoid: Option<Oid>,
 }
 
 impl PostgisContext {
@@ -194,6 +437,13 @@ impl PostgisContext {
 
         let st_geomfromwkb_funcoid = postgis_ext_oid.map(|_| Self::st_geomfromwkb_funcoid());
 
+        let st_srid_funcoid = postgis_ext_oid.map(|postgis_ext_oid| {
+            Self::st_srid_funcoid(
+                postgis_ext_oid,
+                postgis_ext_schema_oid.expect("expected postgis is created"),
+            )
+        });
+
         let geometry_typoid = postgis_ext_oid.map(|_| {
             Self::geometry_typoid(
                 postgis_ext_oid.expect("expected postgis is created"),
@@ -201,10 +451,19 @@ impl PostgisContext {
             )
         });
 
+        let st_setsrid_funcoid = geometry_typoid
+            .map(|geometry_typoid| Self::geometry_int4_funcoid("st_setsrid", geometry_typoid));
+
+        let st_transform_funcoid = geometry_typoid
+            .map(|geometry_typoid| Self::geometry_int4_funcoid("st_transform", geometry_typoid));
+
         Self {
             geometry_typoid,
             st_asbinary_funcoid,
             st_geomfromwkb_funcoid,
+            st_srid_funcoid,
+            st_setsrid_funcoid,
+            st_transform_funcoid,
         }
     }
 
@@ -234,6 +493,45 @@ impl PostgisContext {
         }
     }
 
+    fn st_srid_funcoid(postgis_ext_oid: Oid, postgis_ext_schema_oid: Oid) -> Oid {
+        unsafe {
+            let postgis_geometry_typoid =
+                Self::geometry_typoid(postgis_ext_oid, postgis_ext_schema_oid);
+
+            let function_name = makeString("st_srid".as_pg_cstr());
+            let mut function_name_list = PgList::new();
+            function_name_list.push(function_name);
+
+            let mut arg_types = vec![postgis_geometry_typoid];
+
+            LookupFuncName(
+                function_name_list.as_ptr(),
+                1,
+                arg_types.as_mut_ptr(),
+                false,
+            )
+        }
+    }
+
+    // geometry_int4_funcoid looks up a function with (geometry, integer) arguments,
+    // e.g. st_setsrid(geometry, integer)
+    fn geometry_int4_funcoid(function_name: &str, geometry_typoid: Oid) -> Oid {
+        unsafe {
+            let function_name = makeString(function_name.as_pg_cstr());
+            let mut function_name_list = PgList::new();
+            function_name_list.push(function_name);
+
+            let mut arg_types = vec![geometry_typoid, INT4OID];
+
+            LookupFuncName(
+                function_name_list.as_ptr(),
+                2,
+                arg_types.as_mut_ptr(),
+                false,
+            )
+        }
+    }
+
     fn st_geomfromwkb_funcoid() -> Oid {
         unsafe {
             let function_name = makeString("st_geomfromwkb".as_pg_cstr());
@@ -358,3 +656,91 @@ unsafe impl UnboxDatum for Geometry {
         Geometry(wkb)
     }
 }
+
+// set_geometry_srid sets the SRID of the geometry via ST_SetSRID, without changing its coordinates
+pub(crate) fn set_geometry_srid(geometry_datum: Datum, srid: i32) -> Datum {
+    let st_setsrid_funcoid = get_postgis_context()
+        .st_setsrid_funcoid
+        .expect("st_setsrid_funcoid");
+
+    let srid_datum = srid.into_datum().expect("cannot convert srid to datum");
+
+    unsafe { OidFunctionCall2Coll(st_setsrid_funcoid, InvalidOid, geometry_datum, srid_datum) }
+}
+
+// transform_geometry reprojects the geometry into the SRID via ST_Transform
+pub(crate) fn transform_geometry(geometry_datum: Datum, srid: i32) -> Datum {
+    let st_transform_funcoid = get_postgis_context()
+        .st_transform_funcoid
+        .expect("st_transform_funcoid");
+
+    let srid_datum = srid.into_datum().expect("cannot convert srid to datum");
+
+    unsafe { OidFunctionCall2Coll(st_transform_funcoid, InvalidOid, geometry_datum, srid_datum) }
+}
+
+// GeometrySrid is the SRID of a geometry value, which is read without converting the geometry
+// into WKB.
+#[derive(Debug, PartialEq)]
+pub(crate) struct GeometrySrid(pub(crate) i32);
+
+impl IntoDatum for GeometrySrid {
+    fn into_datum(self) -> Option<Datum> {
+        unreachable!("GeometrySrid is only read from geometry datums");
+    }
+
+    fn type_oid() -> Oid {
+        get_postgis_context()
+            .geometry_typoid
+            .expect("postgis context not initialized")
+    }
+}
+
+// GeometrySrid is unboxed from the elements of geometry arrays
+unsafe impl UnboxDatum for GeometrySrid {
+    type As<'src> = GeometrySrid;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        let is_null = false;
+        GeometrySrid::from_datum(datum.sans_lifetime(), is_null)
+            .expect("cannot convert datum to srid")
+    }
+}
+
+impl FromDatum for GeometrySrid {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            let st_srid_funcoid = get_postgis_context()
+                .st_srid_funcoid
+                .expect("st_srid_funcoid");
+
+            let srid_datum = unsafe { OidFunctionCall1Coll(st_srid_funcoid, InvalidOid, datum) };
+
+            let is_null = false;
+            let srid = i32::from_datum(srid_datum, is_null).expect("cannot convert datum to srid");
+            Some(Self(srid))
+        }
+    }
+}
+
+// The following udfs are only used for testing purposes.
+#[cfg(feature = "pg_test")]
+#[pgrx::pg_schema]
+mod parquet_test {
+    use pgrx::pg_extern;
+
+    use super::POSTGIS_CONTEXT_INIT_COUNT;
+
+    #[pg_extern]
+    fn postgis_context_init_count() -> i64 {
+        unsafe { POSTGIS_CONTEXT_INIT_COUNT }
+    }
+}
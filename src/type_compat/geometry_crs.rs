@@ -0,0 +1,539 @@
+use std::{collections::HashMap, num::NonZeroUsize, str::FromStr};
+
+use once_cell::sync::Lazy;
+use pgrx::{
+    heap_tuple::PgHeapTuple,
+    pg_sys::{Datum, Oid},
+    AllocatedByRust, FromDatum, IntoDatum, Spi,
+};
+use serde_json::{json, Value};
+
+use super::geometry::{
+    set_geometry_srid, srid_from_typmod, transform_geometry, Geometry, GeometryColumnPath,
+    GeometryPath, GeometrySrid,
+};
+
+// PROJJSON_CACHE caches the crs of each SRID for the duration of a COPY TO, since
+// spatial_ref_sys might be changed between COPYs.
+static mut PROJJSON_CACHE: Lazy<HashMap<i32, Value>> = Lazy::new(HashMap::new);
+
+pub(crate) fn reset_projjson_cache() {
+    #[allow(static_mut_refs)]
+    unsafe {
+        PROJJSON_CACHE.clear()
+    };
+}
+
+// srid_crs returns the geoparquet crs of the SRID as PROJJSON, or null if the SRID is not
+// found in spatial_ref_sys, which means the crs is unknown.
+pub(crate) fn srid_crs(srid: i32) -> Value {
+    #[allow(static_mut_refs)]
+    unsafe {
+        PROJJSON_CACHE
+            .entry(srid)
+            .or_insert_with(|| srid_projjson(srid).unwrap_or(Value::Null))
+            .clone()
+    }
+}
+
+const PROJJSON_SCHEMA: &str = "https://proj.org/schemas/v0.7/projjson.schema.json";
+
+// srid_projjson returns the PROJJSON of the SRID. PostGIS does not convert spatial_ref_sys
+// entries into PROJJSON, so the full PROJJSON is bundled for the most common EPSG codes.
+// Others are identified by the authority code, and the name and the type in their srtext.
+fn srid_projjson(srid: i32) -> Option<Value> {
+    let (auth_name, auth_srid, srtext) = spatial_ref_sys_entry(srid)?;
+
+    if auth_name.eq_ignore_ascii_case("EPSG") {
+        if let Some(projjson) = bundled_epsg_projjson(auth_srid) {
+            return Some(projjson);
+        }
+    }
+
+    let crs_type = match srtext.split('[').next().unwrap_or_default().trim() {
+        "GEOGCS" | "GEOGCRS" => "GeographicCRS",
+        "PROJCS" | "PROJCRS" => "ProjectedCRS",
+        "GEOCCS" | "GEODCRS" => "GeodeticCRS",
+        "COMPD_CS" | "COMPOUNDCRS" => "CompoundCRS",
+        "VERT_CS" | "VERTCRS" => "VerticalCRS",
+        _ => return None,
+    };
+
+    // e.g. PROJCS["ETRS89 / UTM zone 32N",...]
+    let name = srtext.split('"').nth(1).unwrap_or_default();
+
+    Some(json!({
+        "$schema": PROJJSON_SCHEMA,
+        "type": crs_type,
+        "name": name,
+        "id": {
+            "authority": auth_name,
+            "code": auth_srid,
+        },
+    }))
+}
+
+// spatial_ref_sys_table returns the qualified name of the spatial_ref_sys table of postgis
+fn spatial_ref_sys_table() -> Option<String> {
+    Spi::get_one::<String>(
+        "SELECT (SELECT quote_ident(n.nspname) || '.spatial_ref_sys'
+                 FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace
+                 WHERE e.extname = 'postgis')",
+    )
+    .expect("failed to get postgis extension schema")
+}
+
+fn spatial_ref_sys_entry(srid: i32) -> Option<(String, i32, String)> {
+    let spatial_ref_sys = spatial_ref_sys_table()?;
+
+    // returns a row of nulls if the SRID is not found
+    let query = format!(
+        "SELECT s.auth_name::text, s.auth_srid, s.srtext::text
+         FROM (SELECT 1) AS d LEFT JOIN {} AS s ON s.srid = {}",
+        spatial_ref_sys, srid
+    );
+
+    let (auth_name, auth_srid, srtext) = Spi::get_three::<String, i32, String>(&query)
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to get spatial_ref_sys entry of SRID {}: {}",
+                srid, e
+            )
+        });
+
+    Some((auth_name?, auth_srid?, srtext.unwrap_or_default()))
+}
+
+// crs_srid returns the SRID of a geoparquet crs by the authority code of its PROJJSON, e.g.
+// {"id": {"authority": "EPSG", "code": 3857}}, which is looked up in spatial_ref_sys. It returns
+// None if the crs is unknown (null), or its authority code is not found in spatial_ref_sys.
+pub(crate) fn crs_srid(crs: &Value) -> Option<i32> {
+    let id = crs.get("id")?;
+
+    let authority = id.get("authority")?.as_str()?;
+
+    let code = match id.get("code")? {
+        Value::Number(code) => code.to_string(),
+        Value::String(code) => code.clone(),
+        _ => return None,
+    };
+
+    // OGC:CRS84 is the longitude/latitude order of WGS 84, which postgis uses for EPSG:4326
+    if authority.eq_ignore_ascii_case("OGC") && code.eq_ignore_ascii_case("CRS84") {
+        return Some(4326);
+    }
+
+    let code = code.parse::<i32>().ok()?;
+
+    // the authority is read from the file, so only plain names are looked up
+    if !authority.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let spatial_ref_sys = spatial_ref_sys_table()?;
+
+    let query = format!(
+        "SELECT min(srid) FROM {} WHERE upper(auth_name) = upper('{}') AND auth_srid = {}",
+        spatial_ref_sys, authority, code
+    );
+
+    Spi::get_one::<i32>(&query).unwrap_or_else(|e| {
+        panic!(
+            "failed to get SRID of crs {}:{} from spatial_ref_sys: {}",
+            authority, code, e
+        )
+    })
+}
+
+// geoparquet_column_srids returns the SRIDs of the crs of the geometry columns in the "geo"
+// metadata of a parquet file. Columns with an omitted crs are not returned, since their
+// SRID is unknown (0) when written by pg_parquet.
+pub(crate) fn geoparquet_column_srids(geo_metadata: &str) -> HashMap<String, Option<i32>> {
+    let mut column_srids = HashMap::new();
+
+    let Ok(geo_metadata) = serde_json::from_str::<Value>(geo_metadata) else {
+        pgrx::debug2!("ignoring invalid geoparquet metadata");
+        return column_srids;
+    };
+
+    let Some(columns) = geo_metadata.get("columns").and_then(Value::as_object) else {
+        return column_srids;
+    };
+
+    for (column_name, column) in columns {
+        if let Some(crs) = column.get("crs") {
+            column_srids.insert(column_name.clone(), crs_srid(crs));
+        }
+    }
+
+    column_srids
+}
+
+fn wgs84_geographic_crs(id: Option<Value>) -> Value {
+    let mut crs = json!({
+        "$schema": PROJJSON_SCHEMA,
+        "type": "GeographicCRS",
+        "name": "WGS 84",
+        "datum": {
+            "type": "GeodeticReferenceFrame",
+            "name": "World Geodetic System 1984",
+            "ellipsoid": {
+                "name": "WGS 84",
+                "semi_major_axis": 6378137,
+                "inverse_flattening": 298.257223563
+            }
+        },
+        "coordinate_system": {
+            "subtype": "ellipsoidal",
+            "axis": [
+                {
+                    "name": "Geodetic latitude",
+                    "abbreviation": "Lat",
+                    "direction": "north",
+                    "unit": "degree"
+                },
+                {
+                    "name": "Geodetic longitude",
+                    "abbreviation": "Lon",
+                    "direction": "east",
+                    "unit": "degree"
+                }
+            ]
+        },
+    });
+
+    if let Some(id) = id {
+        crs["id"] = id;
+    }
+
+    crs
+}
+
+fn bundled_epsg_projjson(code: i32) -> Option<Value> {
+    let epsg_id = |code: i32| json!({"authority": "EPSG", "code": code});
+
+    match code {
+        4326 => Some(wgs84_geographic_crs(Some(epsg_id(4326)))),
+        3857 => {
+            let mut base_crs = wgs84_geographic_crs(Some(epsg_id(4326)));
+            base_crs
+                .as_object_mut()
+                .expect("expected json object")
+                .remove("$schema");
+
+            let parameter = |name: &str, unit: &str, code: i32| json!({"name": name, "value": 0, "unit": unit, "id": epsg_id(code)});
+
+            Some(json!({
+                "$schema": PROJJSON_SCHEMA,
+                "type": "ProjectedCRS",
+                "name": "WGS 84 / Pseudo-Mercator",
+                "base_crs": base_crs,
+                "conversion": {
+                    "name": "Popular Visualisation Pseudo-Mercator",
+                    "method": {
+                        "name": "Popular Visualisation Pseudo Mercator",
+                        "id": epsg_id(1024)
+                    },
+                    "parameters": [
+                        parameter("Latitude of natural origin", "degree", 8801),
+                        parameter("Longitude of natural origin", "degree", 8802),
+                        parameter("False easting", "metre", 8806),
+                        parameter("False northing", "metre", 8807)
+                    ]
+                },
+                "coordinate_system": {
+                    "subtype": "Cartesian",
+                    "axis": [
+                        {
+                            "name": "Easting",
+                            "abbreviation": "X",
+                            "direction": "east",
+                            "unit": "metre"
+                        },
+                        {
+                            "name": "Northing",
+                            "abbreviation": "Y",
+                            "direction": "north",
+                            "unit": "metre"
+                        }
+                    ]
+                },
+                "id": epsg_id(3857)
+            }))
+        }
+        _ => None,
+    }
+}
+
+// GeometryColumnSrid tracks the SRID of the values of a geometry column, or of the geometries
+// nested in a column, whose type does not declare the SRID, e.g. "geometry" instead of
+// "geometry(point, 3857)".
+pub(crate) struct GeometryColumnSrid {
+    attnum: i16,
+    name: String,
+    path: GeometryPath,
+    srid: Option<i32>,
+    crs: Option<Value>,
+}
+
+impl GeometryColumnSrid {
+    pub(crate) fn new(geometry_column_path: GeometryColumnPath) -> Self {
+        Self {
+            attnum: geometry_column_path.attnum,
+            name: geometry_column_path.name,
+            path: geometry_column_path.path,
+            srid: None,
+            crs: None,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    // track reads the SRIDs of the column's values. It fails when the column has mixed SRIDs,
+    // unless allow_mixed_srids is set.
+    pub(crate) fn track(
+        &mut self,
+        tuples: &[Option<PgHeapTuple<AllocatedByRust>>],
+        allow_mixed_srids: bool,
+    ) {
+        // crs is already unknown
+        if self.crs == Some(Value::Null) {
+            return;
+        }
+
+        let attnum = NonZeroUsize::new(self.attnum as usize).expect("invalid attnum");
+
+        let mut srids = vec![];
+
+        for tuple in tuples.iter().flatten() {
+            srids.clear();
+
+            collect_geometry_srids(
+                tuple,
+                GeometryAttribute::Index(attnum),
+                &self.path,
+                &mut srids,
+            );
+
+            for srid in srids.iter().copied() {
+                self.track_srid(srid, allow_mixed_srids);
+
+                if self.crs == Some(Value::Null) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn track_srid(&mut self, srid: i32, allow_mixed_srids: bool) {
+        match self.srid {
+            None => {
+                self.srid = Some(srid);
+
+                // 0 is the unknown SRID, for which the crs is omitted as before
+                if srid > 0 {
+                    self.crs = Some(srid_crs(srid));
+                }
+            }
+            Some(column_srid) if column_srid == srid => {}
+            Some(column_srid) if allow_mixed_srids => {
+                pgrx::debug2!(
+                    "geometry column \"{}\" has mixed SRIDs {} and {}",
+                    self.name,
+                    column_srid,
+                    srid
+                );
+
+                self.crs = Some(Value::Null);
+            }
+            Some(column_srid) => panic!(
+                "geometry column \"{}\" has mixed SRIDs {} and {}. \
+                 Use allow_mixed_srids option to write it with unknown crs.",
+                self.name, column_srid, srid
+            ),
+        }
+    }
+
+    // crs returns the geoparquet crs of the column, which is None (omitted) when the SRID
+    // is unknown (0), and null when the column has mixed SRIDs.
+    pub(crate) fn crs(&self) -> Option<Value> {
+        self.crs.clone()
+    }
+}
+
+// GeometryAttribute is a top level attribute of a tuple, or a field of a composite value.
+enum GeometryAttribute<'a> {
+    Index(NonZeroUsize),
+    Name(&'a str),
+}
+
+fn geometry_attribute_value<T: FromDatum + IntoDatum>(
+    tuple: &PgHeapTuple<AllocatedByRust>,
+    attribute: GeometryAttribute,
+) -> Option<T> {
+    match attribute {
+        GeometryAttribute::Index(attnum) => tuple.get_by_index(attnum),
+        GeometryAttribute::Name(name) => tuple.get_by_name(name),
+    }
+    .unwrap_or_else(|e| panic!("failed to get attribute: {}", e))
+}
+
+// collect_geometry_srids appends the SRIDs of the geometries at the path of the attribute,
+// e.g. the SRIDs of the elements of a geometry array.
+fn collect_geometry_srids(
+    tuple: &PgHeapTuple<AllocatedByRust>,
+    attribute: GeometryAttribute,
+    path: &GeometryPath,
+    srids: &mut Vec<i32>,
+) {
+    match path {
+        GeometryPath::Geometry => {
+            if let Some(GeometrySrid(srid)) = geometry_attribute_value(tuple, attribute) {
+                srids.push(srid);
+            }
+        }
+        GeometryPath::Field(field_name, field_path) => {
+            let composite: Option<PgHeapTuple<AllocatedByRust>> =
+                geometry_attribute_value(tuple, attribute);
+
+            if let Some(composite) = composite {
+                collect_geometry_srids(
+                    &composite,
+                    GeometryAttribute::Name(field_name),
+                    field_path,
+                    srids,
+                );
+            }
+        }
+        GeometryPath::Elements(element_path) => match element_path.as_ref() {
+            GeometryPath::Geometry => {
+                let geometries: Option<pgrx::Array<GeometrySrid>> =
+                    geometry_attribute_value(tuple, attribute);
+
+                srids.extend(
+                    geometries
+                        .iter()
+                        .flat_map(|geometries| geometries.iter().flatten())
+                        .map(|GeometrySrid(srid)| srid),
+                );
+            }
+            GeometryPath::Field(field_name, field_path) => {
+                let composites: Option<pgrx::Array<PgHeapTuple<AllocatedByRust>>> =
+                    geometry_attribute_value(tuple, attribute);
+
+                for composite in composites.iter().flat_map(|composites| composites.iter()) {
+                    let Some(composite) = composite else {
+                        continue;
+                    };
+
+                    collect_geometry_srids(
+                        &composite,
+                        GeometryAttribute::Name(field_name),
+                        field_path,
+                        srids,
+                    );
+                }
+            }
+            // arrays of arrays are multidimensional arrays of their elements
+            GeometryPath::Elements(_) => unreachable!("nested geometry arrays"),
+        },
+    }
+}
+
+// GeometrySridMismatch determines how COPY FROM handles a geometry column, whose crs in the
+// parquet file differs from the SRID of the table column's type.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum GeometrySridMismatch {
+    #[default]
+    Error,
+    // reprojects the geometries into the SRID of the table column via ST_Transform
+    Reproject,
+}
+
+impl FromStr for GeometrySridMismatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(GeometrySridMismatch::Error),
+            "reproject" => Ok(GeometrySridMismatch::Reproject),
+            _ => Err(format!("unrecognized geometry_srid_mismatch option: {}", s)),
+        }
+    }
+}
+
+// GeometrySridConversion sets the SRID of the geometries, which are read from a parquet file,
+// from the crs of their column, and reprojects them into the SRID of the table column if needed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct GeometrySridConversion {
+    srid: Option<i32>,
+    transform_srid: Option<i32>,
+}
+
+impl GeometrySridConversion {
+    // new returns the conversion of a geometry column with the SRID of its crs in the file.
+    // The geometries keep the unknown SRID (0) if the crs is unknown, which postgis replaces
+    // with the SRID of the table column's type.
+    pub(crate) fn new(
+        column_name: &str,
+        file_srid: Option<i32>,
+        typmod: i32,
+        srid_mismatch: GeometrySridMismatch,
+    ) -> Self {
+        let Some(file_srid) = file_srid else {
+            return Self::default();
+        };
+
+        match srid_from_typmod(typmod) {
+            Some(column_srid) if column_srid != file_srid => match srid_mismatch {
+                GeometrySridMismatch::Error => panic!(
+                    "geometry column \"{}\" has SRID {} in the parquet file, but SRID {} in the table. \
+                     Use geometry_srid_mismatch 'reproject' option to reproject it.",
+                    column_name, file_srid, column_srid
+                ),
+                GeometrySridMismatch::Reproject => Self {
+                    srid: Some(file_srid),
+                    transform_srid: Some(column_srid),
+                },
+            },
+            _ => Self {
+                srid: Some(file_srid),
+                transform_srid: None,
+            },
+        }
+    }
+
+    pub(crate) fn apply(&self, geometry_datum: Datum) -> Datum {
+        let mut geometry_datum = geometry_datum;
+
+        if let Some(srid) = self.srid {
+            geometry_datum = set_geometry_srid(geometry_datum, srid);
+        }
+
+        if let Some(transform_srid) = self.transform_srid {
+            geometry_datum = transform_geometry(geometry_datum, transform_srid);
+        }
+
+        geometry_datum
+    }
+}
+
+// SridConvertedGeometry is a geometry, whose SRID is converted when it is converted into
+// a datum, e.g. the elements of geometry arrays.
+pub(crate) struct SridConvertedGeometry(pub(crate) Geometry, pub(crate) GeometrySridConversion);
+
+impl IntoDatum for SridConvertedGeometry {
+    fn into_datum(self) -> Option<Datum> {
+        let SridConvertedGeometry(geometry, conversion) = self;
+
+        geometry
+            .into_datum()
+            .map(|geometry_datum| conversion.apply(geometry_datum))
+    }
+
+    fn type_oid() -> Oid {
+        Geometry::type_oid()
+    }
+}
@@ -0,0 +1,79 @@
+use pgrx::{
+    datum::UnboxDatum,
+    direct_function_call, direct_function_call_as_datum,
+    pg_sys::{self, Datum, Oid, CASHOID},
+    AnyNumeric, FromDatum, IntoDatum,
+};
+
+use super::pg_arrow_type_conversions::i128_to_numeric;
+
+// money is written as Decimal128(19, 2), which covers its whole range,
+// -92233720368547758.08 to +92233720368547758.07
+pub(crate) const MONEY_PRECISION: u8 = 19;
+pub(crate) const MONEY_SCALE: i8 = 2;
+
+// Money is a money value in hundredths of the currency unit. money is converted via numeric,
+// since the number of fractional digits of its internal integer depends on lc_monetary, and
+// its text form contains the currency symbol of the locale.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Money(pub(crate) i128);
+
+impl IntoDatum for Money {
+    fn into_datum(self) -> Option<Datum> {
+        let numeric = i128_to_numeric(self.0, MONEY_PRECISION as _, MONEY_SCALE as _, -1);
+
+        // rounds to the fractional digits of lc_monetary
+        unsafe { direct_function_call_as_datum(pg_sys::numeric_cash, &[numeric.into_datum()]) }
+    }
+
+    fn type_oid() -> Oid {
+        CASHOID
+    }
+}
+
+impl FromDatum for Money {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(money_datum_to_hundredths(datum))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for Money {
+    type As<'src> = Money;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        money_datum_to_hundredths(datum.sans_lifetime())
+    }
+}
+
+fn money_datum_to_hundredths(datum: Datum) -> Money {
+    let numeric: AnyNumeric = unsafe {
+        direct_function_call(pg_sys::cash_numeric, &[Some(datum)])
+            .expect("cannot convert money to numeric")
+    };
+
+    // e.g. 12 in a locale without fractional digits becomes 12.00
+    let numeric: AnyNumeric = unsafe {
+        direct_function_call(
+            pg_sys::numeric_round,
+            &[numeric.into_datum(), (MONEY_SCALE as i32).into_datum()],
+        )
+        .expect("cannot round money")
+    };
+
+    let hundredths = format!("{}", numeric)
+        .replace('.', "")
+        .parse::<i128>()
+        .expect("invalid money");
+
+    Money(hundredths)
+}
@@ -0,0 +1,113 @@
+use pgrx::{
+    pg_sys::{
+        construct_md_array, deconstruct_array, get_typlenbyvalalign, pg_detoast_datum,
+        pg_detoast_datum_slice, ArrayType, Datum, Oid, ANYARRAYOID, MAXDIM,
+    },
+    FromDatum, IntoDatum,
+};
+
+use crate::pgrx_utils::is_array_type;
+
+// size of the header of an array with MAXDIM dimensions, i.e. ArrayType followed by the
+// dimensions and the lower bounds
+const MAX_ARRAY_HEADER_SIZE: usize =
+    std::mem::size_of::<ArrayType>() + 2 * MAXDIM as usize * std::mem::size_of::<i32>();
+
+// ArrayDims is the dimensions of an array, e.g. [2, 3] for '{{1,2,3},{4,5,6}}' and [] for an
+// empty array. pgrx arrays iterate over the elements of multidimensional arrays as if they
+// were flat, hence the dimensions are read from the header of the array.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ArrayDims(pub(crate) Vec<usize>);
+
+impl ArrayDims {
+    pub(crate) fn ndims(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl IntoDatum for ArrayDims {
+    fn into_datum(self) -> Option<Datum> {
+        unreachable!("array dimensions are not converted into a datum")
+    }
+
+    fn type_oid() -> Oid {
+        ANYARRAYOID
+    }
+
+    fn is_compatible_with(other: Oid) -> bool {
+        is_array_type(other)
+    }
+}
+
+impl FromDatum for ArrayDims {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            return None;
+        }
+
+        // only the header is detoasted, which is enough for the dimensions of large arrays
+        let array = pg_detoast_datum_slice(datum.cast_mut_ptr(), 0, MAX_ARRAY_HEADER_SIZE as _)
+            as *mut ArrayType;
+
+        let ndims = (*array).ndim as usize;
+
+        // the dimensions follow the header, like ARR_DIMS
+        let dims = (array as *const u8).add(std::mem::size_of::<ArrayType>()) as *const i32;
+
+        let dims = std::slice::from_raw_parts(dims, ndims)
+            .iter()
+            .map(|dim| *dim as usize)
+            .collect();
+
+        Some(Self(dims))
+    }
+}
+
+// reshape_array_datum returns the array with the same elements as the given one-dimensional
+// array, which has the given dimensions, e.g. the array read from nested lists.
+pub(crate) fn reshape_array_datum(array_datum: Datum, dims: &ArrayDims) -> Datum {
+    unsafe {
+        let array = pg_detoast_datum(array_datum.cast_mut_ptr()) as *mut ArrayType;
+
+        let element_typoid = (*array).elemtype;
+
+        let mut typlen = 0;
+        let mut typbyval = false;
+        let mut typalign = 0;
+        get_typlenbyvalalign(element_typoid, &mut typlen, &mut typbyval, &mut typalign);
+
+        let mut elements = std::ptr::null_mut();
+        let mut nulls = std::ptr::null_mut();
+        let mut element_count = 0;
+        deconstruct_array(
+            array,
+            element_typoid,
+            typlen as _,
+            typbyval,
+            typalign,
+            &mut elements,
+            &mut nulls,
+            &mut element_count,
+        );
+
+        let mut dims = dims.0.iter().map(|dim| *dim as i32).collect::<Vec<_>>();
+        let mut lower_bounds = vec![1; dims.len()];
+
+        let array = construct_md_array(
+            elements,
+            nulls,
+            dims.len() as _,
+            dims.as_mut_ptr(),
+            lower_bounds.as_mut_ptr(),
+            element_typoid,
+            typlen as _,
+            typbyval,
+            typalign,
+        );
+
+        Datum::from(array)
+    }
+}
@@ -0,0 +1,274 @@
+use pgrx::{
+    datum::UnboxDatum,
+    pg_sys::{self, Datum, Oid, BYTEAOID, CIDROID, INETOID, MACADDR8OID, MACADDROID},
+    FromDatum, IntoDatum,
+};
+
+// Postgres' address families of inet and cidr, i.e. PGSQL_AF_INET and PGSQL_AF_INET6
+const PGSQL_AF_INET: u8 = 2;
+const PGSQL_AF_INET6: u8 = 3;
+
+pub(crate) const INET_FAMILY_FIELD_NAME: &str = "family";
+pub(crate) const INET_BITS_FIELD_NAME: &str = "bits";
+pub(crate) const INET_IS_CIDR_FIELD_NAME: &str = "is_cidr";
+pub(crate) const INET_ADDR_FIELD_NAME: &str = "addr";
+
+pub(crate) const MACADDR_LENGTH: usize = 6;
+pub(crate) const MACADDR8_LENGTH: usize = 8;
+
+pub(crate) fn is_network_address_type(typoid: Oid) -> bool {
+    matches!(typoid, INETOID | CIDROID | MACADDROID | MACADDR8OID)
+}
+
+// NetworkAddress is the value of an inet or cidr, which is written as the struct
+// {family: 4 or 6, bits: netmask length, is_cidr, addr: address bytes in network order}
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct NetworkAddress {
+    pub(crate) family: u8,
+    pub(crate) bits: u8,
+    pub(crate) addr: Vec<u8>,
+}
+
+impl NetworkAddress {
+    // new validates the address as inet_in and cidr_in do for the text form
+    pub(crate) fn new(family: u8, bits: u8, addr: Vec<u8>, is_cidr: bool) -> Result<Self, String> {
+        let addr_len = match family {
+            4 => 4,
+            6 => 16,
+            _ => return Err(format!("invalid address family {}", family)),
+        };
+
+        if addr.len() != addr_len {
+            return Err(format!(
+                "IPv{} address has {} bytes instead of {}",
+                family,
+                addr.len(),
+                addr_len
+            ));
+        }
+
+        if bits as usize > addr_len * 8 {
+            return Err(format!(
+                "invalid netmask length {} for IPv{} address",
+                bits, family
+            ));
+        }
+
+        if is_cidr && has_bits_right_of_mask(&addr, bits) {
+            return Err("cidr value has bits set to right of mask".into());
+        }
+
+        Ok(Self { family, bits, addr })
+    }
+
+    // from_datum reads the address from inet's internal layout {family, bits, ipaddr}
+    // instead of going through its text form
+    unsafe fn from_datum(datum: Datum) -> Self {
+        let is_null = false;
+        let inet = <&[u8]>::from_polymorphic_datum(datum, is_null, BYTEAOID)
+            .expect("cannot read inet datum");
+
+        let family = match inet[0] {
+            PGSQL_AF_INET => 4,
+            PGSQL_AF_INET6 => 6,
+            family => panic!("invalid inet address family {}", family),
+        };
+
+        Self {
+            family,
+            bits: inet[1],
+            addr: inet[2..].to_vec(),
+        }
+    }
+
+    fn into_datum(self) -> Datum {
+        let family = if self.family == 4 {
+            PGSQL_AF_INET
+        } else {
+            PGSQL_AF_INET6
+        };
+
+        let mut inet = Vec::with_capacity(2 + self.addr.len());
+        inet.push(family);
+        inet.push(self.bits);
+        inet.extend(self.addr);
+
+        // inet has the same varlena layout as bytea, with {family, bits, ipaddr} as its data
+        inet.into_datum().expect("cannot create inet datum")
+    }
+}
+
+fn has_bits_right_of_mask(addr: &[u8], bits: u8) -> bool {
+    addr.iter().enumerate().any(|(byte_idx, byte)| {
+        let mask_bits = (bits as usize).saturating_sub(byte_idx * 8).min(8);
+        let host_mask = 0xFFu8.checked_shr(mask_bits as u32).unwrap_or(0);
+
+        byte & host_mask != 0
+    })
+}
+
+// Inet
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Inet(pub(crate) NetworkAddress);
+
+impl IntoDatum for Inet {
+    fn into_datum(self) -> Option<Datum> {
+        Some(self.0.into_datum())
+    }
+
+    fn type_oid() -> Oid {
+        INETOID
+    }
+}
+
+impl FromDatum for Inet {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(Self(NetworkAddress::from_datum(datum)))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for Inet {
+    type As<'src> = Inet;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        Self(NetworkAddress::from_datum(datum.sans_lifetime()))
+    }
+}
+
+// Cidr
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Cidr(pub(crate) NetworkAddress);
+
+impl IntoDatum for Cidr {
+    fn into_datum(self) -> Option<Datum> {
+        Some(self.0.into_datum())
+    }
+
+    fn type_oid() -> Oid {
+        CIDROID
+    }
+}
+
+impl FromDatum for Cidr {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(Self(NetworkAddress::from_datum(datum)))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for Cidr {
+    type As<'src> = Cidr;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        Self(NetworkAddress::from_datum(datum.sans_lifetime()))
+    }
+}
+
+// MacAddr is the 6 bytes of macaddr, which is a fixed length pass by reference type
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct MacAddr(pub(crate) [u8; MACADDR_LENGTH]);
+
+impl IntoDatum for MacAddr {
+    fn into_datum(self) -> Option<Datum> {
+        Some(unsafe { fixed_length_bytes_into_datum(&self.0) })
+    }
+
+    fn type_oid() -> Oid {
+        MACADDROID
+    }
+}
+
+impl FromDatum for MacAddr {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(Self(fixed_length_bytes_from_datum(datum)))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for MacAddr {
+    type As<'src> = MacAddr;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        Self(fixed_length_bytes_from_datum(datum.sans_lifetime()))
+    }
+}
+
+// MacAddr8 is the 8 bytes of macaddr8, which is a fixed length pass by reference type
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct MacAddr8(pub(crate) [u8; MACADDR8_LENGTH]);
+
+impl IntoDatum for MacAddr8 {
+    fn into_datum(self) -> Option<Datum> {
+        Some(unsafe { fixed_length_bytes_into_datum(&self.0) })
+    }
+
+    fn type_oid() -> Oid {
+        MACADDR8OID
+    }
+}
+
+impl FromDatum for MacAddr8 {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(Self(fixed_length_bytes_from_datum(datum)))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for MacAddr8 {
+    type As<'src> = MacAddr8;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        Self(fixed_length_bytes_from_datum(datum.sans_lifetime()))
+    }
+}
+
+unsafe fn fixed_length_bytes_from_datum<const N: usize>(datum: Datum) -> [u8; N] {
+    let bytes = std::slice::from_raw_parts(datum.cast_mut_ptr::<u8>(), N);
+
+    bytes.try_into().expect("invalid fixed length datum")
+}
+
+unsafe fn fixed_length_bytes_into_datum(bytes: &[u8]) -> Datum {
+    let ptr = pg_sys::palloc(bytes.len()) as *mut u8;
+
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+
+    Datum::from(ptr)
+}
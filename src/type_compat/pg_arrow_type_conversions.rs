@@ -1,12 +1,17 @@
 use core::panic;
 use std::ffi::CStr;
 
-use arrow::datatypes::{Decimal128Type, DecimalType};
+use arrow::datatypes::{i256, Decimal128Type, Decimal256Type, DecimalType};
 use pgrx::{
     datum::{Date, Interval, Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone},
     direct_function_call, ereport,
     pg_sys::{self, AsPgCStr},
-    AnyNumeric, IntoDatum, Numeric,
+    AnyNumeric, IntoDatum,
+};
+
+use crate::arrow_parquet::{
+    infinite_timestamps::sentinel_infinity,
+    numeric_overflow::{unbounded_numeric_state, PgParquetNumericOverflow, UnboundedNumericState},
 };
 
 pub(crate) fn date_to_i32(date: Date) -> i32 {
@@ -32,89 +37,109 @@ pub(crate) fn i32_to_date(i32_date: i32) -> Date {
     unsafe { Date::from_pg_epoch_days(i32_date - 10957) }
 }
 
-pub(crate) fn timestamp_to_i64(timestamp: Timestamp) -> i64 {
-    // PG epoch is (2000-01-01). Convert it to Unix epoch (1970-01-01). +10957 days
-    let adjustment_interval = Interval::from_days(10957);
-    let adjusted_timestamp: Timestamp = unsafe {
-        direct_function_call(
-            pg_sys::timestamp_pl_interval,
-            &[timestamp.into_datum(), adjustment_interval.into_datum()],
-        )
-        .expect("cannot adjust PG timestamp to Unix timestamp")
-    };
+// Parquet uses Unix epoch (1970-01-01), and PG uses 2000-01-01, which is 10957 days later
+const UNIX_EPOCH_OFFSET_MICROS: i64 = 10957 * 86_400_000_000;
 
-    let adjusted_timestamp_as_bytes: Vec<u8> = unsafe {
-        direct_function_call(pg_sys::time_send, &[adjusted_timestamp.into_datum()])
-            .expect("cannot convert timestamp to bytes")
-    };
-    i64::from_be_bytes(
-        adjusted_timestamp_as_bytes[0..8]
-            .try_into()
-            .unwrap_or_else(|e| panic!("{}", e)),
-    )
+// timestamp_to_i64 converts the finite timestamp to microseconds since Unix epoch. Infinite
+// timestamps are handled by infinite_timestamps before.
+pub(crate) fn timestamp_to_i64(timestamp: Timestamp) -> i64 {
+    // the latest Postgres timestamps do not fit into i64 since Unix epoch
+    i64::from(timestamp)
+        .checked_add(UNIX_EPOCH_OFFSET_MICROS)
+        .unwrap_or_else(|| {
+            panic!(
+                "timestamp {} is out of range of parquet timestamps",
+                timestamp
+            )
+        })
 }
 
 pub(crate) fn i64_to_timestamp(i64_timestamp: i64) -> Timestamp {
-    let timestamp: Timestamp = i64_timestamp.try_into().unwrap_or_else(|e| panic!("{}", e));
+    match sentinel_infinity(i64_timestamp) {
+        Some(true) => return Timestamp::positive_infinity(),
+        Some(false) => return Timestamp::negative_infinity(),
+        None => {}
+    }
 
-    // Parquet uses Unix epoch (1970-01-01). Convert it to PG epoch (2000-01-01). -10957 days
-    let adjustment_interval = Interval::from_days(10957);
-    let adjusted_timestamp: Timestamp = unsafe {
-        direct_function_call(
-            pg_sys::timestamp_mi_interval,
-            &[timestamp.into_datum(), adjustment_interval.into_datum()],
-        )
-        .expect("cannot adjust Unix timestamp to PG timestamp")
+    let out_of_range = || -> ! {
+        if i64_timestamp == i64::MAX || i64_timestamp == i64::MIN {
+            panic!(
+                "timestamp {} is out of range, use infinite_timestamps 'sentinel' to read it as infinity",
+                i64_timestamp
+            );
+        }
+
+        panic!("timestamp {} is out of range", i64_timestamp);
     };
 
-    adjusted_timestamp
+    let pg_timestamp = i64_timestamp
+        .checked_sub(UNIX_EPOCH_OFFSET_MICROS)
+        .unwrap_or_else(|| out_of_range());
+
+    pg_timestamp.try_into().unwrap_or_else(|_| out_of_range())
 }
 
+// timestamptz_to_i64 converts the finite timestamptz to microseconds since Unix epoch, like
+// timestamp_to_i64.
 pub(crate) fn timestamptz_to_i64(timestamptz: TimestampWithTimeZone) -> i64 {
-    // PG epoch is (2000-01-01). Convert it to Unix epoch (1970-01-01). +10957 days
-    let adjustment_interval = Interval::from_days(10957);
-    let adjusted_timestamptz: TimestampWithTimeZone = unsafe {
-        direct_function_call(
-            pg_sys::timestamptz_pl_interval,
-            &[timestamptz.into_datum(), adjustment_interval.into_datum()],
-        )
-        .expect("cannot adjust PG timestamptz to Unix timestamptz")
-    };
-
-    let adjusted_timestamptz_as_bytes: Vec<u8> = unsafe {
-        direct_function_call(
-            pg_sys::timestamptz_send,
-            &[adjusted_timestamptz.into_datum()],
-        )
-        .expect("cannot convert timestamptz to bytes")
-    };
-    i64::from_be_bytes(
-        adjusted_timestamptz_as_bytes[0..8]
-            .try_into()
-            .unwrap_or_else(|e| panic!("{}", e)),
-    )
+    i64::from(timestamptz)
+        .checked_add(UNIX_EPOCH_OFFSET_MICROS)
+        .unwrap_or_else(|| {
+            panic!(
+                "timestamptz {} is out of range of parquet timestamps",
+                timestamptz
+            )
+        })
 }
 
 pub(crate) fn i64_to_timestamptz(i64_timestamptz: i64, timezone: &str) -> TimestampWithTimeZone {
-    let timestamp: Timestamp = i64_timestamptz
-        .try_into()
-        .unwrap_or_else(|e| panic!("{}", e));
+    match sentinel_infinity(i64_timestamptz) {
+        Some(true) => return TimestampWithTimeZone::positive_infinity(),
+        Some(false) => return TimestampWithTimeZone::negative_infinity(),
+        None => {}
+    }
 
-    let timestamptz: TimestampWithTimeZone = (timestamp, timezone)
+    let timestamp = i64_to_timestamp(i64_timestamptz);
+
+    (timestamp, timezone)
         .try_into()
-        .unwrap_or_else(|e| panic!("{}", e));
+        .unwrap_or_else(|e| panic!("{}", e))
+}
 
-    // Parquet uses Unix epoch (1970-01-01). Convert it to PG epoch (2000-01-01). -10957 days
-    let adjustment_interval = Interval::from_days(10957);
-    let adjusted_timestamptz: TimestampWithTimeZone = unsafe {
-        direct_function_call(
-            pg_sys::timestamptz_mi_interval,
-            &[timestamptz.into_datum(), adjustment_interval.into_datum()],
-        )
-        .expect("cannot adjust Unix timestamptz to PG timestamptz")
-    };
+// The conversions below are used while coercing timestamps between timestamp, timestamptz
+// and date columns in COPY FROM. They call Postgres' own casts, which use the session timezone.
+// Local times, which do not exist due to a DST transition, are interpreted by the UTC offset
+// before the transition, and ambiguous local times by the UTC offset after the transition,
+// same as AT TIME ZONE does.
+
+// utc_i64_to_local_timestamp converts the UTC instant to the local time of the session timezone.
+pub(crate) fn utc_i64_to_local_timestamp(i64_timestamptz: i64) -> Timestamp {
+    let timestamptz = i64_to_timestamptz(i64_timestamptz, "UTC");
+
+    unsafe {
+        direct_function_call(pg_sys::timestamptz_timestamp, &[timestamptz.into_datum()])
+            .expect("cannot convert timestamptz to timestamp")
+    }
+}
+
+// local_i64_to_timestamptz interprets the timestamp as a local time of the session timezone.
+pub(crate) fn local_i64_to_timestamptz(i64_timestamp: i64) -> TimestampWithTimeZone {
+    let timestamp = i64_to_timestamp(i64_timestamp);
 
-    adjusted_timestamptz
+    unsafe {
+        direct_function_call(pg_sys::timestamp_timestamptz, &[timestamp.into_datum()])
+            .expect("cannot convert timestamp to timestamptz")
+    }
+}
+
+// utc_i64_to_local_date converts the UTC instant to the date of the session timezone.
+pub(crate) fn utc_i64_to_local_date(i64_timestamptz: i64) -> Date {
+    let timestamptz = i64_to_timestamptz(i64_timestamptz, "UTC");
+
+    unsafe {
+        direct_function_call(pg_sys::timestamptz_date, &[timestamptz.into_datum()])
+            .expect("cannot convert timestamptz to date")
+    }
 }
 
 pub(crate) fn time_to_i64(time: Time) -> i64 {
@@ -133,39 +158,58 @@ pub(crate) fn i64_to_time(i64_time: i64) -> Time {
     i64_time.try_into().unwrap_or_else(|e| panic!("{}", e))
 }
 
-pub(crate) fn timetz_to_i64(timetz: TimeWithTimeZone) -> i64 {
-    let timezone_as_secs: AnyNumeric = unsafe {
-        direct_function_call(
-            pg_sys::extract_timetz,
-            &["timezone".into_datum(), timetz.into_datum()],
-        )
-    }
-    .expect("cannot extract timezone from timetz");
+// time with time zone is written as a struct of its local time and the offset of its zone
+pub(crate) const TIMETZ_TIME_FIELD_NAME: &str = "time";
+pub(crate) const TIMETZ_OFFSET_FIELD_NAME: &str = "offset_seconds";
 
-    let timezone_as_secs: f64 = timezone_as_secs
-        .try_into()
-        .unwrap_or_else(|e| panic!("{}", e));
+// timetz_to_time_and_offset returns the local time of the timetz and the offset of its zone
+// from UTC in seconds, e.g. (10:00:00, 19800) for '10:00:00+05:30'.
+pub(crate) fn timetz_to_time_and_offset(timetz: TimeWithTimeZone) -> (i64, i32) {
+    let timetz_as_bytes: Vec<u8> = unsafe {
+        direct_function_call(pg_sys::timetz_send, &[timetz.into_datum()])
+            .expect("cannot convert timetz to bytes")
+    };
+
+    let time = i64::from_be_bytes(
+        timetz_as_bytes[0..8]
+            .try_into()
+            .unwrap_or_else(|e| panic!("{}", e)),
+    );
+
+    // Postgres keeps the zone in seconds west of UTC
+    let zone = i32::from_be_bytes(
+        timetz_as_bytes[8..12]
+            .try_into()
+            .unwrap_or_else(|e| panic!("{}", e)),
+    );
+
+    (time, -zone)
+}
 
-    let timezone_as_interval = Interval::from_seconds(timezone_as_secs);
-    let adjusted_timetz: TimeWithTimeZone = unsafe {
+// time_and_offset_to_timetz returns the timetz with the local time in the zone, which is
+// "offset_seconds" east of UTC.
+pub(crate) fn time_and_offset_to_timetz(time: i64, offset_seconds: i32) -> TimeWithTimeZone {
+    let offset = Interval::from_seconds(offset_seconds as f64);
+
+    // the local time at UTC is the same instant as the time in the zone
+    let utc_timetz: TimeWithTimeZone = unsafe {
         direct_function_call(
             pg_sys::timetz_mi_interval,
-            &[timetz.into_datum(), timezone_as_interval.into_datum()],
+            &[i64_to_timetz(time).into_datum(), offset.into_datum()],
         )
-        .expect("cannot adjust PG timetz to Unix timetz")
+        .expect("cannot adjust timetz to UTC")
     };
 
-    let adjusted_timetz_as_bytes: Vec<u8> = unsafe {
-        direct_function_call(pg_sys::timetz_send, &[adjusted_timetz.into_datum()])
-            .expect("cannot convert timetz to bytes")
-    };
-    i64::from_be_bytes(
-        adjusted_timetz_as_bytes[0..8]
-            .try_into()
-            .unwrap_or_else(|e| panic!("{}", e)),
-    )
+    unsafe {
+        direct_function_call(
+            pg_sys::timetz_izone,
+            &[offset.into_datum(), utc_timetz.into_datum()],
+        )
+        .expect("cannot convert timetz to its zone")
+    }
 }
 
+// i64_to_timetz returns the timetz at UTC for the time in UTC
 pub(crate) fn i64_to_timetz(i64_timetz: i64) -> TimeWithTimeZone {
     let utc_tz = 0;
     (i64_timetz, utc_tz)
@@ -188,22 +232,89 @@ fn error_if_special_numeric(numeric: AnyNumeric) {
 }
 
 pub(crate) fn numeric_to_i128(numeric: AnyNumeric, typmod: i32, col_name: &str) -> i128 {
+    let numeric_digits = numeric_decimal_digits(numeric, typmod, col_name);
+
+    numeric_digits
+        .parse::<i128>()
+        .unwrap_or_else(|e| panic!("invalid decimal {}: {}", numeric_digits, e))
+}
+
+// numeric_to_i256 converts numerics with precision > 38, which are written as Decimal256.
+pub(crate) fn numeric_to_i256(numeric: AnyNumeric, typmod: i32, col_name: &str) -> i256 {
+    let numeric_digits = numeric_decimal_digits(numeric, typmod, col_name);
+
+    i256::from_string(&numeric_digits)
+        .unwrap_or_else(|| panic!("invalid decimal: {}", numeric_digits))
+}
+
+// numeric_decimal_digits returns the digits of the numeric value, which is scaled to the scale
+// of its decimal, without the decimal point.
+fn numeric_decimal_digits(numeric: AnyNumeric, typmod: i32, col_name: &str) -> String {
     error_if_special_numeric(numeric.clone());
 
-    let numeric_str = if is_unbounded_numeric_typmod(typmod) {
-        let rescaled_unbounded_numeric = rescale_unbounded_numeric_or_error(numeric, col_name);
+    if is_unbounded_numeric_typmod(typmod) {
+        return rescale_unbounded_numeric_or_error(numeric, col_name);
+    }
 
-        format!("{}", rescaled_unbounded_numeric)
-    } else {
-        // format returns a string representation of the numeric value based on numeric_out
-        format!("{}", numeric)
+    let (precision, scale) = extract_precision_and_scale_from_numeric_typmod(typmod);
+
+    // the value is rounded to the scale of the typmod like a cast to it, e.g. a value of a
+    // composite field, which is not coerced to the typmod. A negative scale rounds the integral
+    // part, e.g. to hundreds for numeric(10,-2), which is written with scale 0.
+    let rounded_numeric = round_numeric(numeric, extract_scale_from_numeric_typmod(typmod));
+
+    let numeric_digits = scaled_numeric_digits(&format!("{}", rounded_numeric), scale);
+
+    if numeric_digits.trim_start_matches('-').len() > precision as usize {
+        ereport!(
+            pgrx::PgLogLevel::ERROR,
+            pgrx::PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+            format!(
+                "numeric value {} of column \"{}\" does not fit into decimal({}, {}) during copy to parquet",
+                rounded_numeric, col_name, precision, scale
+            ),
+        );
+    }
+
+    numeric_digits
+}
+
+// round_numeric rounds the numeric to the scale, half away from zero, like PG casts do.
+fn round_numeric(numeric: AnyNumeric, scale: i32) -> AnyNumeric {
+    unsafe {
+        direct_function_call(
+            pg_sys::numeric_round,
+            &[numeric.into_datum(), scale.into_datum()],
+        )
+        .expect("cannot round numeric")
+    }
+}
+
+// scaled_numeric_digits returns the digits of the numeric text, which has at most "scale" decimal
+// digits, as the integer of the decimal with the scale, e.g. "-12.5" becomes "-1250" for scale 2.
+fn scaled_numeric_digits(numeric_str: &str, scale: u32) -> String {
+    let (sign, numeric_str) = match numeric_str.strip_prefix('-') {
+        Some(numeric_str) => ("-", numeric_str),
+        None => ("", numeric_str),
     };
 
-    let normalized_numeric_str = numeric_str.replace('.', "");
+    let (integral_digits, decimal_digits) =
+        numeric_str.split_once('.').unwrap_or((numeric_str, ""));
 
-    normalized_numeric_str
-        .parse::<i128>()
-        .expect("invalid decimal")
+    debug_assert!(decimal_digits.len() <= scale as usize);
+
+    // pads the decimal digits up to the scale
+    let digits = format!(
+        "{}{:0<width$}",
+        integral_digits,
+        decimal_digits,
+        width = scale as usize
+    );
+
+    match digits.trim_start_matches('0') {
+        "" => "0".into(),
+        digits => format!("{}{}", sign, digits),
+    }
 }
 
 pub(crate) fn i128_to_numeric(
@@ -215,6 +326,23 @@ pub(crate) fn i128_to_numeric(
     // format decimal via arrow since it is consistent with PG's numeric formatting
     let numeric_str = Decimal128Type::format_decimal(decimal, precision as _, scale as _);
 
+    numeric_from_str(&numeric_str, typmod)
+}
+
+// i256_to_numeric converts Decimal256 values, e.g. with precision > 38, via their text form.
+// numeric's input function errors if the value does not fit into the typmod.
+pub(crate) fn i256_to_numeric(
+    decimal: i256,
+    precision: u32,
+    scale: u32,
+    typmod: i32,
+) -> AnyNumeric {
+    let numeric_str = Decimal256Type::format_decimal(decimal, precision as _, scale as _);
+
+    numeric_from_str(&numeric_str, typmod)
+}
+
+fn numeric_from_str(numeric_str: &str, typmod: i32) -> AnyNumeric {
     // compute numeric from string representation
     let numeric: AnyNumeric = unsafe {
         let numeric_cstring = CStr::from_ptr(numeric_str.as_pg_cstr());
@@ -239,84 +367,114 @@ fn unbounded_numeric_value_digits(numeric_str: &str) -> (usize, usize) {
 
     let has_decimal_point = numeric_str.contains('.');
 
+    // the integral zero of e.g. "0.5" is not a digit of the decimal
     if has_decimal_point {
         let parts = numeric_str.split('.').collect::<Vec<_>>();
-        (parts[0].len(), parts[1].len())
+        (parts[0].trim_start_matches('0').len(), parts[1].len())
     } else {
-        (numeric_str.as_str().len(), 0)
+        (numeric_str.trim_start_matches('0').len(), 0)
     }
 }
 
-fn rescale_unbounded_numeric_or_error(
-    unbounded_numeric: AnyNumeric,
-    col_name: &str,
-) -> Numeric<DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE> {
-    let unbounded_numeric_str = format!("{}", unbounded_numeric);
+// rescale_unbounded_numeric_or_error returns the digits of the unbounded numeric value, which
+// is scaled to the decimal of the unconstrained numeric columns. Values with more decimal digits
+// are rounded with numeric_overflow 'round'.
+fn rescale_unbounded_numeric_or_error(unbounded_numeric: AnyNumeric, col_name: &str) -> String {
+    let UnboundedNumericState {
+        precision,
+        scale,
+        overflow,
+    } = unbounded_numeric_state();
+
+    let max_integral_digits = precision - scale;
+
+    let mut unbounded_numeric_str = format!("{}", unbounded_numeric);
+
+    let (_, n_scale_digits) = unbounded_numeric_value_digits(&unbounded_numeric_str);
+
+    // rounding might add an integral digit, e.g. 9.99 becomes 10.0, hence the integral
+    // digits are checked after rounding
+    if n_scale_digits > scale as _ && overflow == PgParquetNumericOverflow::Round {
+        let rounded_numeric: AnyNumeric = unsafe {
+            direct_function_call(
+                pg_sys::numeric_round,
+                &[unbounded_numeric.into_datum(), (scale as i32).into_datum()],
+            )
+            .expect("cannot round numeric")
+        };
+
+        unbounded_numeric_str = format!("{}", rounded_numeric);
+    }
 
     let (n_integral_digits, n_scale_digits) =
         unbounded_numeric_value_digits(&unbounded_numeric_str);
 
     // we need to do error checks before rescaling since rescaling to a lower scale
     // silently truncates the value
-    if n_integral_digits > DEFAULT_UNBOUNDED_NUMERIC_MAX_INTEGRAL_DIGITS as _ {
+    if n_integral_digits > max_integral_digits as _ {
         ereport!(
             pgrx::PgLogLevel::ERROR,
             pgrx::PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
             format!(
                 "numeric value contains {} digits before decimal point, which exceeds max allowed integral digits {} during copy to parquet",
-                n_integral_digits, DEFAULT_UNBOUNDED_NUMERIC_MAX_INTEGRAL_DIGITS
+                n_integral_digits, max_integral_digits
             ),
             format!(
-                "Consider specifying precision and scale for column \"{}\". Replace type \"numeric\" to \"numeric(P,S)\".",
+                "Consider specifying precision and scale for column \"{}\". Replace type \"numeric\" to \"numeric(P,S)\", or set numeric_precision and numeric_scale options.",
                 col_name
             ),
         );
-    } else if n_scale_digits > DEFAULT_UNBOUNDED_NUMERIC_SCALE as _ {
+    } else if n_scale_digits > scale as _ {
         ereport!(
             pgrx::PgLogLevel::ERROR,
             pgrx::PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
             format!(
                 "numeric value contains {} digits after decimal point, which exceeds max allowed decimal digits {} during copy to parquet",
-                n_scale_digits, DEFAULT_UNBOUNDED_NUMERIC_SCALE
+                n_scale_digits, scale
             ),
             format!(
-                "Consider specifying precision and scale for column \"{}\". Replace type \"numeric\" to \"numeric(P,S)\".",
+                "Consider specifying precision and scale for column \"{}\". Replace type \"numeric\" to \"numeric(P,S)\", or set numeric_overflow option to 'round'.",
                 col_name
             ),
         );
     }
 
-    unbounded_numeric
-        .rescale::<DEFAULT_UNBOUNDED_NUMERIC_PRECISION, DEFAULT_UNBOUNDED_NUMERIC_SCALE>()
-        .unwrap_or_else(|e| panic!("{}", e))
+    scaled_numeric_digits(&unbounded_numeric_str, scale)
 }
 
-const MAX_NUMERIC_PRECISION: u32 = 38;
-pub(crate) const DEFAULT_UNBOUNDED_NUMERIC_PRECISION: u32 = MAX_NUMERIC_PRECISION;
+const MAX_DECIMAL128_PRECISION: u32 = 38;
+pub(crate) const MAX_NUMERIC_PRECISION: u32 = 76;
+pub(crate) const DEFAULT_UNBOUNDED_NUMERIC_PRECISION: u32 = MAX_DECIMAL128_PRECISION;
 pub(crate) const DEFAULT_UNBOUNDED_NUMERIC_SCALE: u32 = 9;
-pub(crate) const DEFAULT_UNBOUNDED_NUMERIC_MAX_INTEGRAL_DIGITS: u32 =
-    DEFAULT_UNBOUNDED_NUMERIC_PRECISION - DEFAULT_UNBOUNDED_NUMERIC_SCALE;
 
 // should_write_numeric_as_text determines whether a numeric datum should be written as text.
-// It is written as text when precision is greater than MAX_NUMERIC_PRECISION e.g. "numeric(50, 10)"
+// It is written as text when precision is greater than MAX_NUMERIC_PRECISION e.g. "numeric(100, 10)"
 pub(crate) fn should_write_numeric_as_text(precision: u32) -> bool {
     precision > MAX_NUMERIC_PRECISION
 }
 
+// should_write_numeric_as_decimal256 determines whether a numeric datum should be written as
+// Decimal256 instead of Decimal128, e.g. "numeric(50, 10)".
+pub(crate) fn should_write_numeric_as_decimal256(precision: u32) -> bool {
+    precision > MAX_DECIMAL128_PRECISION
+}
+
 // extract_precision_and_scale_from_numeric_typmod extracts precision and scale from numeric typmod
 // with the following rules:
-// - If typmod is -1, it means unbounded numeric, so we use default precision and scale.
+// - If typmod is -1, it means unbounded numeric, so we use the precision and scale of the
+//   numeric_precision and numeric_scale options, which are 38 and 9 by default.
 // - Even if PG allows negative scale, arrow does not. We adjust precision by adding abs(scale) to it,
 //   and set scale to 0.
 //
 // It always returns non-negative precision and scale due to the above rule.
 pub(crate) fn extract_precision_and_scale_from_numeric_typmod(typmod: i32) -> (u32, u32) {
-    // if typmod is -1, it means unbounded numeric, so we use default precision and scale
+    // if typmod is -1, it means unbounded numeric, so we use the configured precision and scale
     if is_unbounded_numeric_typmod(typmod) {
-        return (
-            DEFAULT_UNBOUNDED_NUMERIC_PRECISION,
-            DEFAULT_UNBOUNDED_NUMERIC_SCALE,
-        );
+        let UnboundedNumericState {
+            precision, scale, ..
+        } = unbounded_numeric_state();
+
+        return (precision, scale);
     }
 
     let mut precision = extract_precision_from_numeric_typmod(typmod);
@@ -0,0 +1,155 @@
+use std::{
+    ffi::{c_char, CStr},
+    panic::AssertUnwindSafe,
+};
+
+use pgrx::{
+    datum::UnboxDatum,
+    pg_sys::{
+        self, cstring_to_text_with_len, panic::CaughtError, pg_detoast_datum_packed,
+        pg_do_encoding_conversion, pg_enc, Datum, GetDatabaseEncoding, Oid, TEXTOID,
+    },
+    varlena::varlena_to_byte_slice,
+    FromDatum, IntoDatum, PgTryBuilder,
+};
+
+use crate::arrow_parquet::pg_to_arrow::conversion_progress::current_conversion_progress;
+
+const PG_UTF8: i32 = pg_enc::PG_UTF8 as i32;
+
+// ServerEncodedText is a text value, which is converted from the server encoding into UTF-8,
+// which parquet requires for its strings, when it is read from a datum, and back into the
+// server encoding when it is written into a datum, e.g. for a LATIN1 database.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ServerEncodedText(pub(crate) String);
+
+impl From<ServerEncodedText> for String {
+    fn from(text: ServerEncodedText) -> String {
+        text.0
+    }
+}
+
+impl IntoDatum for ServerEncodedText {
+    fn into_datum(self) -> Option<Datum> {
+        let text = utf8_to_server(&self.0);
+
+        let text =
+            unsafe { cstring_to_text_with_len(text.as_ptr() as *const c_char, text.len() as i32) };
+
+        Some(Datum::from(text))
+    }
+
+    fn type_oid() -> Oid {
+        TEXTOID
+    }
+}
+
+impl FromDatum for ServerEncodedText {
+    unsafe fn from_polymorphic_datum(datum: Datum, is_null: bool, _typoid: Oid) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        if is_null {
+            None
+        } else {
+            Some(text_datum_to_utf8(datum))
+        }
+    }
+}
+
+unsafe impl UnboxDatum for ServerEncodedText {
+    type As<'src> = ServerEncodedText;
+
+    unsafe fn unbox<'src>(datum: pgrx::datum::Datum<'src>) -> Self::As<'src>
+    where
+        Self: 'src,
+    {
+        text_datum_to_utf8(datum.sans_lifetime())
+    }
+}
+
+fn text_datum_to_utf8(datum: Datum) -> ServerEncodedText {
+    let text = unsafe {
+        let text = pg_detoast_datum_packed(datum.cast_mut_ptr());
+        varlena_to_byte_slice(text)
+    };
+
+    ServerEncodedText(server_to_utf8(text))
+}
+
+// server_to_utf8 converts the text in the server encoding, e.g. the output of a type, into UTF-8.
+pub(crate) fn server_to_utf8(text: &[u8]) -> String {
+    encoding_to_utf8(text, unsafe { GetDatabaseEncoding() })
+}
+
+// utf8_to_server converts the UTF-8 text of the parquet file into the server encoding.
+pub(crate) fn utf8_to_server(text: &str) -> Vec<u8> {
+    utf8_to_encoding(text, unsafe { GetDatabaseEncoding() })
+}
+
+// encoding_to_utf8 converts the text in the given encoding into UTF-8. Text, which cannot be
+// converted, fails with the column and the row, which are being converted.
+pub(crate) fn encoding_to_utf8(text: &[u8], encoding: i32) -> String {
+    let utf8_text = if encoding == PG_UTF8 {
+        text.to_vec()
+    } else {
+        PgTryBuilder::new(AssertUnwindSafe(|| {
+            convert_encoding(text, encoding, PG_UTF8)
+        }))
+        .catch_others(|cause| match &cause {
+            CaughtError::PostgresError(report)
+            | CaughtError::ErrorReport(report)
+            | CaughtError::RustPanic {
+                ereport: report, ..
+            } => raise_utf8_conversion_error(report.message()),
+        })
+        .execute()
+    };
+
+    // SQL_ASCII text is not converted, hence it may not be valid UTF-8
+    String::from_utf8(utf8_text).unwrap_or_else(|e| raise_utf8_conversion_error(&e.to_string()))
+}
+
+// utf8_to_encoding converts the UTF-8 text into the given encoding. COPY FROM reports the
+// location of the text in the parquet file, when it cannot be converted.
+pub(crate) fn utf8_to_encoding(text: &str, encoding: i32) -> Vec<u8> {
+    if encoding == PG_UTF8 {
+        text.as_bytes().to_vec()
+    } else {
+        convert_encoding(text.as_bytes(), PG_UTF8, encoding)
+    }
+}
+
+fn convert_encoding(text: &[u8], src_encoding: i32, dest_encoding: i32) -> Vec<u8> {
+    let converted_text = unsafe {
+        pg_do_encoding_conversion(
+            text.as_ptr() as *mut u8,
+            text.len() as i32,
+            src_encoding,
+            dest_encoding,
+        )
+    };
+
+    // the text is returned as is, when no conversion is needed, e.g. from or into SQL_ASCII
+    if converted_text as *const u8 == text.as_ptr() {
+        return text.to_vec();
+    }
+
+    let converted_bytes = unsafe { CStr::from_ptr(converted_text as *const c_char) }
+        .to_bytes()
+        .to_vec();
+
+    unsafe { pg_sys::pfree(converted_text as _) };
+
+    converted_bytes
+}
+
+fn raise_utf8_conversion_error(message: &str) -> ! {
+    match current_conversion_progress() {
+        Some(progress) => panic!(
+            "cannot convert the value of column \"{}\" at row {} of the row group to UTF-8: {}",
+            progress.attname, progress.row_idx, message
+        ),
+        None => panic!("cannot convert the value to UTF-8: {}", message),
+    }
+}